@@ -0,0 +1,44 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use jinko::context::Context;
+
+/// Repeatedly read variables containing large instances. Each read used to
+/// deep-copy the instance
+const LOOP_SCRIPT: &str = r#"
+func large_string() -> string {
+    mut s = "jinko";
+    mut i = 0;
+    while i < 20 {
+        s = s.concat(s);
+        i = i + 1;
+    };
+
+    s
+}
+
+func read_in_loop(s: string) -> int {
+    mut total = 0;
+    mut i = 0;
+    while i < 100 {
+        copy = s;
+        total = total + 1;
+        i = i + 1;
+    };
+
+    total
+}
+"#;
+
+fn large_instances(c: &mut Criterion) {
+    let mut ctx = Context::new();
+    ctx.init_stdlib().unwrap();
+    ctx.eval(LOOP_SCRIPT).unwrap();
+    ctx.eval("s = large_string()").unwrap();
+
+    c.bench_function("read large string in loop", |b| {
+        b.iter(|| ctx.eval("read_in_loop(s)").unwrap())
+    });
+}
+
+criterion_group!(benches, large_instances);
+criterion_main!(benches);
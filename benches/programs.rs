@@ -0,0 +1,42 @@
+//! Benchmarks of representative programs, located in the `programs` directory. Each
+//! program is measured when parsed, and when typechecked and executed. The execution
+//! benchmarks parse each program once per sample, and only measure the runs timed by
+//! [`Context::run_repeatedly`].
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use jinko::context::Context;
+
+/// Programs to benchmark, along with their name
+const PROGRAMS: [(&str, &str); 5] = [
+    ("fib", include_str!("programs/fib.jk")),
+    ("strings", include_str!("programs/strings.jk")),
+    ("calls", include_str!("programs/calls.jk")),
+    ("arithmetic", include_str!("programs/arithmetic.jk")),
+    ("concat", include_str!("programs/concat.jk")),
+];
+
+fn parse(c: &mut Criterion) {
+    for (name, program) in PROGRAMS {
+        c.bench_function(&format!("parse {}", name), |b| {
+            b.iter(|| jinko::parser::parse(&mut Context::new(), program, None).unwrap())
+        });
+    }
+}
+
+fn execute(c: &mut Criterion) {
+    let mut ctx = Context::new();
+    ctx.init_stdlib().unwrap();
+
+    // Include the standard library once, outside of the measurements
+    ctx.eval("").unwrap();
+
+    for (name, program) in PROGRAMS {
+        c.bench_function(&format!("execute {}", name), |b| {
+            b.iter_custom(|iters| ctx.run_repeatedly(program, iters as usize).unwrap().total())
+        });
+    }
+}
+
+criterion_group!(benches, parse, execute);
+criterion_main!(benches);
@@ -0,0 +1,23 @@
+use jinko::context::Context;
+use jinko::error::Error;
+use jinko::instance::FromObjectInstance;
+use jinko::value::{JkInt, JkString};
+
+fn main() -> Result<(), Error> {
+    let mut ctx = Context::new();
+    ctx.init_stdlib()?;
+
+    // Arguments and return values of primitive types are converted automatically
+    ctx.register_builtin("host_name", || String::from("embedding example"))?;
+
+    ctx.eval("greeting = \"Hello from \".concat(host_name())")?;
+
+    let greeting = ctx.get_value::<JkString>("greeting").unwrap();
+    println!("{}", greeting.rust_value());
+
+    if let Some(answer) = ctx.eval("40 + 2")? {
+        println!("{}", JkInt::from_instance(&answer).rust_value());
+    }
+
+    Ok(())
+}
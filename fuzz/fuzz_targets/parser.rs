@@ -0,0 +1,12 @@
+//! Parse arbitrary input, which should always either succeed or report syntax errors.
+//! Run with `cargo fuzz run parser` from the root of the repository.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(input) = std::str::from_utf8(data) {
+        let _ = jinko::parser::parse_instructions(input);
+    }
+});
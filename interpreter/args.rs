@@ -1,22 +1,66 @@
 //! The `Args` module helps giving command line option to jinko
 
-use structopt::StructOpt;
+use jinko::error::ErrorFormat;
+use structopt::{clap::AppSettings, StructOpt};
 
-use std::path::PathBuf;
+use std::{path::PathBuf, str::FromStr};
+
+/// Command used to format a source file instead of executing it
+const FMT_COMMAND: &str = "fmt";
+
+/// Command used to run the tests declared in a source file, like `--test`
+const TEST_COMMAND: &str = "test";
+
+/// Command used to execute a source file in the interactive debugger
+const DEBUG_COMMAND: &str = "debug";
+
+/// Command used to save a parsed source file to a `.jko` file
+const BUILD_COMMAND: &str = "build";
+
+/// Command used to execute a program saved by the build command, or the project
+/// containing the current directory when no program is given
+const RUN_COMMAND: &str = "run";
+
+/// Representations of the source code which can be emitted instead of executing it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Emit {
+    /// Instruction tree, serialized to JSON
+    AstJson,
+}
+
+impl FromStr for Emit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Emit, String> {
+        match s {
+            "ast-json" => Ok(Emit::AstJson),
+            _ => Err(format!("unknown representation to emit: `{}`", s)),
+        }
+    }
+}
 
 #[derive(StructOpt)]
-#[structopt(name = "jinko", about = "The jinko ctx")]
+#[structopt(
+    name = "jinko",
+    about = "The jinko interpreter",
+    usage = "jinko [FLAGS] [OPTIONS] [input [arguments]...]\n    jinko fmt <input>\n    jinko test <input> [tests]...\n    jinko debug <input> [arguments]...\n    jinko build <input> [-o <output>]\n    jinko run <program> [arguments]...\n    jinko run",
+    setting = AppSettings::TrailingVarArg
+)]
 pub struct Args {
-    #[structopt(short, long)]
+    #[structopt(short, long, help = "Print the version of the interpreter and exit")]
     version: bool,
 
-    #[structopt(short, long)]
+    #[structopt(
+        short,
+        long,
+        help = "Launch the REPL, after executing the input file if one is given"
+    )]
     interactive: bool,
 
-    #[structopt(long = "no-std")]
+    #[structopt(long = "no-std", help = "Do not include the standard library")]
     nostdlib: bool,
 
-    #[structopt(long = "test")]
+    #[structopt(long = "test", help = "Run the tests declared in the input file")]
     test: bool,
 
     #[structopt(
@@ -25,14 +69,67 @@ pub struct Args {
     )]
     check: bool,
 
-    #[structopt(short, long)]
+    #[structopt(
+        long = "lsp",
+        help = "Launch a language server communicating over the standard input and output"
+    )]
+    lsp: bool,
+
+    #[structopt(long = "deny-warnings", help = "Treat warnings as errors")]
+    deny_warnings: bool,
+
+    #[structopt(
+        long = "deterministic",
+        help = "Seed the random builtins with a fixed value, freeze the clock builtins and sort scope dumps, so that the output of the program is reproducible"
+    )]
+    deterministic: bool,
+
+    #[structopt(
+        long = "error-format",
+        possible_values = &["human", "json"],
+        default_value = "human",
+        help = "Format in which errors and warnings are emitted"
+    )]
+    error_format: ErrorFormat,
+
+    #[structopt(
+        long = "emit",
+        possible_values = &["ast-json"],
+        help = "Print a representation of the parsed source code instead of executing it"
+    )]
+    emit: Option<Emit>,
+
+    #[structopt(
+        short,
+        long,
+        help = "Dump the parsed instructions before executing them"
+    )]
     debug: bool,
 
-    #[structopt(parse(from_os_str))]
-    input: Option<PathBuf>,
+    #[structopt(
+        long = "trace",
+        help = "Trace the instructions executed and the variables read or written to"
+    )]
+    trace: bool,
+
+    #[structopt(
+        long = "profile",
+        help = "Print the amount of calls and the time spent in each function once the program ends"
+    )]
+    profile: bool,
+
+    #[structopt(
+        short = "e",
+        long = "eval",
+        help = "Execute the given jinko code instead of an input file"
+    )]
+    eval: Option<String>,
 
-    #[structopt()]
-    arguments: Vec<String>,
+    #[structopt(
+        name = "input",
+        help = "Source file to execute, followed by the arguments given to the jinko program"
+    )]
+    input: Vec<String>,
 }
 
 impl Args {
@@ -64,14 +161,62 @@ impl Args {
         self.debug
     }
 
+    /// Is the execution of the program traced. This is always the case in debug mode
+    pub fn trace(&self) -> bool {
+        self.trace || self.debug
+    }
+
+    /// Are the function calls of the program profiled
+    pub fn profile(&self) -> bool {
+        self.profile
+    }
+
     /// Is the context launched without stdlib
     pub fn nostdlib(&self) -> bool {
         self.nostdlib
     }
 
+    /// Is the interpreter launched as `jinko test <input> [tests]...`
+    fn test_command(&self) -> bool {
+        self.eval.is_none() && self.input.len() > 1 && self.input[0] == TEST_COMMAND
+    }
+
+    /// Is the interpreter launched as `jinko debug <input> [arguments]...`
+    fn debug_command(&self) -> bool {
+        self.eval.is_none() && self.input.len() > 1 && self.input[0] == DEBUG_COMMAND
+    }
+
+    /// Is the interpreter launched as `jinko run <program> [arguments]...`
+    fn run_command(&self) -> bool {
+        self.eval.is_none() && self.input.len() > 1 && self.input[0] == RUN_COMMAND
+    }
+
+    /// Is the interpreter launched as `jinko run`, without a program to execute
+    pub fn project(&self) -> bool {
+        self.eval.is_none() && self.input.len() == 1 && self.input[0] == RUN_COMMAND
+    }
+
+    /// Positional arguments, without the name of the command if one was given
+    fn positional(&self) -> &[String] {
+        match self.test_command() || self.debug_command() || self.run_command() {
+            true => &self.input[1..],
+            false => &self.input,
+        }
+    }
+
     /// Is the interpreter launched in test mode
     pub fn test(&self) -> bool {
-        self.test
+        self.test || self.test_command()
+    }
+
+    /// Is the program executed in the interactive debugger
+    pub fn debugger(&self) -> bool {
+        self.debug_command()
+    }
+
+    /// Is the input a program saved by `jinko build`, rather than a source file
+    pub fn saved_program(&self) -> bool {
+        self.run_command()
     }
 
     /// Is the interpreter launched in checking mode
@@ -79,13 +224,80 @@ impl Args {
         self.check
     }
 
-    /// Arguments given to the program
+    /// Is the interpreter launched as a language server
+    pub fn lsp(&self) -> bool {
+        self.lsp
+    }
+
+    /// Are warnings treated as errors
+    pub fn deny_warnings(&self) -> bool {
+        self.deny_warnings
+    }
+
+    /// Is the program executed in deterministic mode
+    pub fn deterministic(&self) -> bool {
+        self.deterministic
+    }
+
+    /// Format in which errors and warnings are emitted
+    pub fn error_format(&self) -> ErrorFormat {
+        self.error_format
+    }
+
+    /// Representation of the source code to emit, if any
+    pub fn emit(&self) -> Option<Emit> {
+        self.emit
+    }
+
+    /// Code given on the command line, if any
+    pub fn eval(&self) -> Option<&str> {
+        self.eval.as_deref()
+    }
+
+    /// Arguments given to the program. When executing code given on the command
+    /// line, there is no input file: All positional arguments are then forwarded to
+    /// the program.
     pub fn project_args(&self) -> Vec<String> {
-        self.arguments.clone()
+        match self.eval {
+            Some(_) => self.input.clone(),
+            None => self.positional().iter().skip(1).cloned().collect(),
+        }
     }
 
-    /// File input given to the context
-    pub fn input(&self) -> Option<&PathBuf> {
-        self.input.as_ref()
+    /// Source file to format, when the interpreter is launched as `jinko fmt <input>`
+    pub fn format(&self) -> Option<PathBuf> {
+        match (&self.eval, self.input.as_slice()) {
+            (None, [command, input]) if command == FMT_COMMAND => Some(PathBuf::from(input)),
+            _ => None,
+        }
+    }
+
+    /// Source file to build and the file to save it to, when the interpreter is
+    /// launched as `jinko build <input> [-o <output>]`. The program is saved next to
+    /// its source by default
+    pub fn build(&self) -> Option<(PathBuf, PathBuf)> {
+        match (&self.eval, self.input.as_slice()) {
+            (None, [command, input]) if command == BUILD_COMMAND => {
+                let input = PathBuf::from(input);
+                let output = input.with_extension(jinko::program::EXTENSION);
+
+                Some((input, output))
+            }
+            (None, [command, input, flag, output])
+                if command == BUILD_COMMAND && (flag == "-o" || flag == "--output") =>
+            {
+                Some((PathBuf::from(input), PathBuf::from(output)))
+            }
+            _ => None,
+        }
+    }
+
+    /// File input given to the context. There is none when executing code given on
+    /// the command line
+    pub fn input(&self) -> Option<PathBuf> {
+        match self.eval {
+            Some(_) => None,
+            None => self.positional().first().map(PathBuf::from),
+        }
     }
 }
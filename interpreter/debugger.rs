@@ -0,0 +1,181 @@
+//! Interactive frontend to the debugger, used when running `jinko debug <input>`. The
+//! execution of the program pauses on its first statement, and is then controlled by
+//! commands read from the standard input, such as `break 12` or `continue`.
+
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+use jinko::context::Context;
+use jinko::debugger::{DebugHook, Debugger};
+use jinko::error::{ErrKind, Error};
+use jinko::instruction::Instruction;
+
+const PROMPT: &str = "(jdb) ";
+
+const HELP: &str = "available commands:
+    break <line>    pause the execution on a line (b)
+    delete <line>   remove the breakpoint on a line (d)
+    step            execute the next statement, stepping into function calls (s)
+    next            execute the next statement, stepping over function calls (n)
+    continue        resume the execution until the next breakpoint (c)
+    print <var>     print the value of a variable (p)
+    vars            print the variables of the current function (v)
+    quit            stop the program (q)";
+
+/// All the commands available when the execution is paused
+#[derive(Debug, PartialEq, Eq)]
+enum Command {
+    Break(usize),
+    Delete(usize),
+    Step,
+    Next,
+    Continue,
+    Print(String),
+    Vars,
+    Help,
+    Quit,
+}
+
+impl Command {
+    fn parse(line: &str) -> Result<Command, Error> {
+        let line = line.trim();
+        let (name, arg) = match line.split_once(char::is_whitespace) {
+            Some((name, arg)) => (name, arg.trim()),
+            None => (line, ""),
+        };
+
+        let line_arg = || {
+            arg.parse::<usize>().map_err(|_| {
+                Error::new(ErrKind::Debug)
+                    .with_msg(format!("command `{}` expects a line number", name))
+            })
+        };
+
+        match name {
+            "break" | "b" => Ok(Command::Break(line_arg()?)),
+            "delete" | "d" => Ok(Command::Delete(line_arg()?)),
+            "step" | "s" => Ok(Command::Step),
+            "next" | "n" => Ok(Command::Next),
+            "continue" | "c" => Ok(Command::Continue),
+            "print" | "p" if arg.is_empty() => Err(Error::new(ErrKind::Debug)
+                .with_msg(format!("command `{}` expects a variable name", name))),
+            "print" | "p" => Ok(Command::Print(arg.to_string())),
+            "vars" | "v" => Ok(Command::Vars),
+            "help" | "h" => Ok(Command::Help),
+            "quit" | "q" => Ok(Command::Quit),
+            _ => Err(Error::new(ErrKind::Debug)
+                .with_msg(format!("unknown command `{}`", name))
+                .with_hint(Error::hint().with_msg(String::from("use `help` to list commands")))),
+        }
+    }
+}
+
+pub struct InteractiveDebugger {
+    debugger: Debugger,
+    /// Lines of the debugged source, displayed when pausing on them
+    source: Vec<String>,
+    /// Once the standard input is closed, the program runs until its end
+    detached: bool,
+}
+
+impl InteractiveDebugger {
+    pub fn new(input: &str, file: &Path) -> InteractiveDebugger {
+        InteractiveDebugger {
+            debugger: Debugger::new(Some(file.to_owned())),
+            source: input.lines().map(String::from).collect(),
+            detached: false,
+        }
+    }
+
+    /// Print the line the execution paused on
+    fn show(&self, instruction: &dyn Instruction) {
+        if let Some(location) = instruction.location() {
+            let line = location.start().line();
+            let text = self.source.get(line - 1).map(String::as_str);
+
+            println!("{}: {}", line, text.unwrap_or_default().trim());
+        }
+    }
+
+    /// Execute a command. Returns true if the execution of the program resumes
+    fn handle(&mut self, ctx: &Context, command: Command) -> bool {
+        match command {
+            Command::Break(line) => {
+                self.debugger.add_breakpoint(line);
+                println!("breakpoint set on line {}", line);
+            }
+            Command::Delete(line) => match self.debugger.remove_breakpoint(line) {
+                true => println!("breakpoint removed from line {}", line),
+                false => Error::new(ErrKind::Debug)
+                    .with_msg(format!("no breakpoint on line {}", line))
+                    .emit(),
+            },
+            Command::Step => {
+                self.debugger.step();
+                return true;
+            }
+            Command::Next => {
+                self.debugger.next(ctx);
+                return true;
+            }
+            Command::Continue => {
+                self.debugger.resume();
+                return true;
+            }
+            Command::Print(name) => match ctx.get_variable(&name) {
+                Some(var) => println!("{} = {}", name, var.instance().display()),
+                None => Error::new(ErrKind::Debug)
+                    .with_msg(format!("no variable named `{}`", name))
+                    .emit(),
+            },
+            Command::Vars => {
+                let mut vars: Vec<_> = ctx.scope_map().frame_variables().into_iter().collect();
+                vars.sort_by_key(|(name, _)| *name);
+
+                vars.iter()
+                    .for_each(|(name, var)| println!("{} = {}", name, var.instance().display()));
+            }
+            Command::Help => println!("{}", HELP),
+            Command::Quit => std::process::exit(0),
+        }
+
+        false
+    }
+}
+
+impl DebugHook for InteractiveDebugger {
+    fn before_instruction(&mut self, ctx: &mut Context, instruction: &dyn Instruction) {
+        if self.detached || !self.debugger.should_pause(ctx, instruction) {
+            return;
+        }
+
+        self.show(instruction);
+
+        loop {
+            print!("{}", PROMPT);
+            let _ = io::stdout().flush();
+
+            let mut line = String::new();
+            match io::stdin().lock().read_line(&mut line) {
+                Ok(0) | Err(_) => {
+                    self.detached = true;
+                    return;
+                }
+                Ok(_) => {}
+            }
+
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            match Command::parse(&line) {
+                Ok(command) => {
+                    if self.handle(ctx, command) {
+                        return;
+                    }
+                }
+                Err(e) => e.emit(),
+            }
+        }
+    }
+}
@@ -1,60 +1,94 @@
 // FIXME: Add #![warn(missing_docs)]
 
 mod args;
+mod debugger;
+#[cfg(feature = "lsp")]
+mod lsp;
 #[cfg(feature = "repl")]
 mod repl;
 
 use colored::Colorize;
 
 use jinko::context::Context;
-use jinko::error::{ErrKind, Error};
-use jinko::instance::{FromObjectInstance, ObjectInstance};
+use jinko::error::{ErrKind, Error, ErrorFormat, ErrorHandler};
+use jinko::instance::{FromObjectInstance, ObjectInstance, ToObjectInstance};
+use jinko::program;
+use jinko::project::{self, Project};
 use jinko::typechecker::CheckedType;
 use jinko::value::{JkBool, JkFloat, JkInt};
 
-use args::Args;
+use args::{Args, Emit};
+use debugger::InteractiveDebugger;
+#[cfg(feature = "lsp")]
+use lsp::LanguageServer;
 #[cfg(feature = "repl")]
 use repl::Repl;
-use std::{fs, path::Path};
+use std::{fs, path::Path, thread};
+
+/// Size of the stack used to run jinko programs. It needs to be big enough to
+/// reach the recursion limit of the context without overflowing
+const STACK_SIZE: usize = 64 * 1024 * 1024;
 
 // FIXME: Add documentation
 pub type InteractResult = Result<(Option<ObjectInstance>, Context), Error>;
 
-fn handle_exit_code(result: Option<ObjectInstance>) -> ! {
-    use std::process::exit;
+/// Exit code of the interpreter when the program could not be parsed, typechecked
+/// or executed
+const ERROR_EXIT_CODE: i32 = 1;
 
-    match result {
+/// Get the exit code corresponding to the result of a program. Integers and floats
+/// are used as the exit code, while booleans give 0 if they are true and 1 otherwise.
+/// Statements and other values give an exit code of 0
+fn exit_code(result: Option<ObjectInstance>) -> i32 {
+    let instance = match result {
+        Some(instance) => instance,
         // A statement that completes succesfully returns 0
-        None => exit(0),
-
-        // FIXME: Maybe return different stuff based on more types?
-
-        // If it's an expression, return if you can (if it's an int)
-        Some(i) => match i.ty() {
-            CheckedType::Resolved(ty) => match ty.id() {
-                "int" => exit(JkInt::from_instance(&i).rust_value() as i32),
-                "float" => exit(JkFloat::from_instance(&i).rust_value() as i32),
-                "bool" => {
-                    let b_value = JkBool::from_instance(&i).rust_value();
-                    match b_value {
-                        true => exit(0),
-                        false => exit(1),
-                    }
-                }
-                _ => exit(0),
+        None => return 0,
+    };
+
+    match instance.ty() {
+        CheckedType::Resolved(ty) => match ty.id() {
+            // FIXME: Is this cast valid?
+            "int" => JkInt::from_instance(&instance).rust_value() as i32,
+            "float" => JkFloat::from_instance(&instance).rust_value() as i32,
+            "bool" => match JkBool::from_instance(&instance).rust_value() {
+                true => 0,
+                false => 1,
             },
-            CheckedType::Void => exit(0),
-            CheckedType::Error | CheckedType::Later => unreachable!("this shouldn't happen"),
+            _ => 0,
         },
+        CheckedType::Void => 0,
+        CheckedType::Error | CheckedType::Later => unreachable!("this shouldn't happen"),
     }
 }
 
+/// Emit the errors kept by the context after running an operation, whether it
+/// succeeded or not. The library never emits errors by itself, so this needs to be
+/// done before the errors are cleared or the context is dropped
+fn emitted<T>(ctx: &mut Context, res: Result<T, Error>) -> Result<T, Error> {
+    ctx.emit_errors();
+
+    res
+}
+
+/// Run the tests declared in the source, or only the ones whose names were given on
+/// the command line. Each test is reported once it completes, and a summary is
+/// printed at the end. The result is a boolean indicating if all the tests passed,
+/// which is used as the exit code of the interpreter.
 fn run_tests(ctx: &mut Context) -> Result<Option<ObjectInstance>, Error> {
-    let res = ctx.execute()?;
-    let tests: Vec<String> = ctx
+    let res = ctx.execute();
+    emitted(ctx, res)?;
+    ctx.clear_errors();
+
+    // The program exited before its tests could run
+    if ctx.exit_code().is_some() {
+        return Ok(None);
+    }
+
+    let mut tests: Vec<String> = ctx
         .tests()
-        .iter()
-        .map(|(k, _)| k.into())
+        .keys()
+        .map(|k| k.into())
         .filter(|test_name| {
             if !ctx.args().is_empty() {
                 ctx.args().contains(test_name)
@@ -63,16 +97,32 @@ fn run_tests(ctx: &mut Context) -> Result<Option<ObjectInstance>, Error> {
             }
         })
         .collect();
+    tests.sort();
 
-    for test_name in tests {
+    let mut failed = 0;
+    for test_name in &tests {
         eprint!(
             "[{}] running test `{}`... ",
             "WTNG".yellow().blink(),
             test_name
         );
-        let fdec = ctx.tests().get(&test_name).unwrap().clone();
-        let _test_result = fdec.run(ctx);
-        eprintln!("\r[ {} ] running test `{}`... ", "OK".green(), test_name);
+
+        // The test names come from the context, so the tests always exist
+        match ctx.run_test(test_name).unwrap() {
+            true => eprintln!("\r[ {} ] running test `{}`... ", "OK".green(), test_name),
+            false => {
+                failed += 1;
+                eprintln!("\r[{}] running test `{}`... ", "FAIL".red(), test_name);
+            }
+        }
+
+        ctx.emit_errors();
+        ctx.clear_errors();
+
+        // A test exiting the program stops the remaining ones from running
+        if ctx.exit_code().is_some() {
+            return Ok(None);
+        }
 
         // FIXME: We should think about handling error values in tests
         // match test_result.unwrap().ty() {
@@ -85,31 +135,137 @@ fn run_tests(ctx: &mut Context) -> Result<Option<ObjectInstance>, Error> {
         //     }
     }
 
-    ctx.emit_errors();
+    eprintln!(
+        "\ntest result: {} passed; {} failed",
+        tests.len() - failed,
+        failed
+    );
+
+    Ok(Some(JkBool::from(failed == 0).to_instance()))
+}
 
-    Ok(res)
+/// Print the instructions parsed from the source, before their execution
+fn dump_instructions(ctx: &Context) {
+    // The entry point always has a block
+    ctx.entry_point
+        .block()
+        .unwrap()
+        .instructions()
+        .iter()
+        .for_each(|inst| ctx.debug("INSTRUCTION", &inst.print()));
 }
 
 fn handle_input(args: &Args, file: &Path) -> InteractResult {
     let input = fs::read_to_string(file)?;
 
+    match args.saved_program() {
+        true => handle_program(args, &input),
+        false => handle_source(args, create_context(args)?, &input, Some(file)),
+    }
+}
+
+/// Execute the entry point of the project containing the current directory
+fn handle_project(args: &Args) -> InteractResult {
+    let manifest = Project::find(&std::env::current_dir()?).ok_or_else(|| {
+        Error::new(ErrKind::Context).with_msg(format!(
+            "could not find `{}` in the current directory or any of its parents",
+            project::MANIFEST
+        ))
+    })?;
+    let project = Project::load(&manifest)?;
+
+    let entry = project.entry().to_owned();
+    let input = fs::read_to_string(&entry).map_err(|e| {
+        Error::new(ErrKind::IO).with_msg(format!(
+            "cannot read the entry point of the project {:?}: {}",
+            entry, e
+        ))
+    })?;
+
+    let mut ctx = create_context(args)?;
+    ctx.set_project(Some(project));
+
+    handle_source(args, ctx, &input, Some(&entry))
+}
+
+/// Create the context in which the input is loaded
+fn create_context(args: &Args) -> Result<Context, Error> {
     let mut ctx = Context::new();
+    ctx.set_debug(args.trace());
+    ctx.set_error_format(args.error_format());
+    ctx.set_deterministic(args.deterministic());
 
-    if !args.nostdlib() {
-        ctx.init_stdlib()?;
+    // Emitted representations only contain the parsed source, not the stdlib
+    if !args.nostdlib() && args.emit().is_none() {
+        if let Err(e) = ctx.init_stdlib() {
+            ctx.error(e);
+            ctx.emit_errors();
+            return Err(Error::new(ErrKind::Context));
+        }
+    }
+
+    Ok(ctx)
+}
+
+fn handle_source(
+    args: &Args,
+    mut ctx: Context,
+    input: &str,
+    file: Option<&Path>,
+) -> InteractResult {
+    if let Err(e) = jinko::parser::parse(&mut ctx, input, file) {
+        ctx.emit_errors();
+        return Err(e);
     }
 
-    jinko::parser::parse(&mut ctx, &input, Some(file))?;
+    if let Some(Emit::AstJson) = args.emit() {
+        println!("{}", ctx.ast_json());
+
+        return Ok((None, ctx));
+    }
+
+    ctx.set_path(file.map(Path::to_owned));
+
+    if let (true, Some(file)) = (args.debugger(), file) {
+        ctx.set_debug_hook(Box::new(InteractiveDebugger::new(input, file)));
+    }
+
+    handle_context(args, ctx)
+}
+
+/// Execute a program saved by `jinko build`, without parsing its source again
+fn handle_program(args: &Args, input: &str) -> InteractResult {
+    let mut ctx = create_context(args)?;
+
+    if let Err(e) = program::load(&mut ctx, input) {
+        ctx.error(e);
+        ctx.emit_errors();
+        return Err(Error::new(ErrKind::Parsing));
+    }
 
-    ctx.set_path(Some(file.to_owned()));
+    if let Some(Emit::AstJson) = args.emit() {
+        println!("{}", ctx.ast_json());
+
+        return Ok((None, ctx));
+    }
+
+    handle_context(args, ctx)
+}
+
+fn handle_context(args: &Args, mut ctx: Context) -> InteractResult {
     ctx.set_args(args.project_args());
+    ctx.set_deny_warnings(args.deny_warnings());
+
+    if args.debug() {
+        dump_instructions(&ctx);
+    }
 
     ctx.emit_errors();
     ctx.clear_errors();
 
     if args.check() {
-        ctx.check()?;
-        ctx.emit_errors();
+        let res = ctx.typecheck();
+        emitted(&mut ctx, res)?;
 
         return Ok((None, ctx));
     }
@@ -121,10 +277,20 @@ fn handle_input(args: &Args, file: &Path) -> InteractResult {
             #[cfg(not(feature = "repl"))]
             true => panic!("Jinko is not compiled with repl support"),
             false => {
-                let res = ctx.execute()?;
+                if args.profile() {
+                    ctx.enable_profiler();
+                }
+
+                let res = ctx.execute();
                 ctx.emit_errors();
 
-                Ok((res, ctx))
+                // The report is printed even if the program failed, as the calls
+                // leading to the failure were still measured
+                if let Some(profiler) = ctx.profiler() {
+                    eprint!("{}", profiler);
+                }
+
+                Ok((res?, ctx))
             }
         },
         true => {
@@ -140,23 +306,132 @@ fn handle_input(args: &Args, file: &Path) -> InteractResult {
     }
 }
 
-fn main() -> anyhow::Result<()> {
+fn main() {
+    let interpreter = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(run)
+        .expect("cannot spawn the interpreter thread");
+
+    let code = match interpreter.join() {
+        Ok(Ok(code)) => code,
+        Ok(Err(e)) => {
+            eprintln!("Error: {:?}", e);
+            ERROR_EXIT_CODE
+        }
+        Err(panic) => std::panic::resume_unwind(panic),
+    };
+
+    std::process::exit(code)
+}
+
+/// Parse a source file and save it to a `.jko` file, which can then be executed
+/// with `jinko run` without being parsed again. The program is typechecked before
+/// being saved, in order to report errors as early as possible
+fn build_program(args: &Args, input: &Path, output: &Path) -> Result<(), Error> {
+    let source = fs::read_to_string(input)?;
+
+    let mut ctx = Context::new();
+    ctx.set_error_format(args.error_format());
+    if let Err(e) = jinko::parser::parse(&mut ctx, &source, Some(input)) {
+        ctx.emit_errors();
+        return Err(e);
+    }
+    ctx.set_path(Some(input.to_owned()));
+
+    let saved = program::save(&ctx);
+
+    // The saved program does not contain the standard library, which is needed to
+    // typecheck it
+    let mut checked = create_context(args)?;
+    program::load(&mut checked, &saved)?;
+    let res = checked.typecheck();
+    emitted(&mut checked, res)?;
+
+    fs::write(output, saved)?;
+
+    Ok(())
+}
+
+/// Print the formatted version of a source file
+fn format_file(args: &Args, file: &Path) -> Result<(), Error> {
+    let input = fs::read_to_string(file)?;
+
+    match Context::format_source(&input) {
+        Ok(formatted) => {
+            print!("{}", formatted);
+            Ok(())
+        }
+        Err(errors) => {
+            let mut handler = ErrorHandler::default();
+            handler.set_format(args.error_format());
+            errors.into_iter().for_each(|e| handler.add(e));
+            handler.emit();
+
+            Err(Error::new(ErrKind::Parsing))
+        }
+    }
+}
+
+#[cfg(feature = "lsp")]
+fn launch_language_server() -> anyhow::Result<()> {
+    LanguageServer::launch()
+}
+
+#[cfg(not(feature = "lsp"))]
+fn launch_language_server() -> anyhow::Result<()> {
+    Err(Error::new(ErrKind::Context)
+        .with_msg(String::from(
+            "jinko is not compiled with language server support",
+        ))
+        .into())
+}
+
+/// Run the interpreter, returning the exit code of the program
+fn run() -> anyhow::Result<i32> {
     let args = Args::handle();
     if args.debug() {
         jinko::debug::enable();
     }
 
-    #[cfg(feature = "repl")]
-    let result = args.input().map_or_else(
-        || Repl::new()?.launch(),
-        |filename| handle_input(&args, filename),
-    )?;
+    if let Some(file) = args.format() {
+        format_file(&args, &file)?;
+        return Ok(0);
+    }
+
+    if let Some((input, output)) = args.build() {
+        build_program(&args, &input, &output)?;
+        return Ok(0);
+    }
+
+    if args.lsp() {
+        launch_language_server()?;
+        return Ok(0);
+    }
 
-    #[cfg(not(feature = "repl"))]
-    let result = args
-        .input()
-        .map(|filename| handle_input(&args, filename))
-        .unwrap()?;
+    let result = match (args.eval(), args.input()) {
+        (Some(code), _) => handle_source(&args, create_context(&args)?, code, None),
+        (None, _) if args.project() => handle_project(&args),
+        (None, Some(filename)) => handle_input(&args, &filename),
+        #[cfg(feature = "repl")]
+        (None, None) => Repl::new()?.launch(),
+        #[cfg(not(feature = "repl"))]
+        (None, None) => Err(Error::new(ErrKind::Context)
+            .with_msg(String::from("no input file or code to execute given"))),
+    };
+
+    // Keep the error output parsable: Errors without a message only summarize the
+    // diagnostics which have already been emitted
+    let result = match (result, args.error_format()) {
+        (Err(e), ErrorFormat::Json) => {
+            if e.msg().is_some() {
+                eprintln!("{}", e.to_json(false));
+            }
+            return Ok(ERROR_EXIT_CODE);
+        }
+        (result, _) => result?,
+    };
 
-    handle_exit_code(result.0)
+    // Programs which called `exit()` decide of the exit code themselves
+    let (value, ctx) = result;
+    Ok(ctx.exit_code().unwrap_or_else(|| exit_code(value)))
 }
@@ -0,0 +1,346 @@
+//! The LSP module implements a language server for jinko, communicating with editors
+//! over the standard input and output. Sources are analyzed each time they change in
+//! order to publish their diagnostics. The server also provides the declaration and
+//! the description of the symbols used in a source.
+//!
+//! Jinko locations count columns in bytes, while LSP positions count them in UTF-16
+//! code units: Columns are converted using the text of the source they refer to.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use jinko::analysis::{Analysis, Definition};
+use jinko::error::{ErrKind, Error};
+use jinko::location::{Location, SpanTuple};
+
+use lsp_server::{Connection, Message, Notification, Request, RequestId, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+    Notification as NotificationTrait, PublishDiagnostics,
+};
+use lsp_types::request::{GotoDefinition, HoverRequest, Request as RequestTrait};
+use lsp_types::{
+    Diagnostic, DiagnosticSeverity, DidChangeTextDocumentParams, DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams, GotoDefinitionParams, GotoDefinitionResponse, Hover, HoverContents,
+    HoverParams, HoverProviderCapability, MarkupContent, MarkupKind, NumberOrString, OneOf,
+    Position, PublishDiagnosticsParams, Range, ServerCapabilities, TextDocumentPositionParams,
+    TextDocumentSyncCapability, TextDocumentSyncKind, Url,
+};
+
+/// Name given to the source of the diagnostics
+const SOURCE_NAME: &str = "jinko";
+
+/// Source opened in the editor, which might not be saved
+struct Document {
+    text: String,
+    analysis: Analysis,
+}
+
+pub struct LanguageServer {
+    connection: Connection,
+    /// Sources currently opened in the editor
+    documents: HashMap<Url, Document>,
+}
+
+impl LanguageServer {
+    /// Launch the language server, and serve requests until the editor shuts it down
+    pub fn launch() -> anyhow::Result<()> {
+        let (connection, io_threads) = Connection::stdio();
+
+        let capabilities = ServerCapabilities {
+            text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+            definition_provider: Some(OneOf::Left(true)),
+            hover_provider: Some(HoverProviderCapability::Simple(true)),
+            ..ServerCapabilities::default()
+        };
+        connection.initialize(serde_json::to_value(capabilities)?)?;
+
+        let mut server = LanguageServer {
+            connection,
+            documents: HashMap::new(),
+        };
+        server.serve()?;
+
+        // The connection needs to be dropped for the I/O threads to stop
+        drop(server);
+        io_threads.join()?;
+
+        Ok(())
+    }
+
+    fn serve(&mut self) -> anyhow::Result<()> {
+        while let Ok(message) = self.connection.receiver.recv() {
+            match message {
+                Message::Request(req) => {
+                    if self.connection.handle_shutdown(&req)? {
+                        return Ok(());
+                    }
+                    self.handle_request(req)?;
+                }
+                Message::Notification(not) => self.handle_notification(not)?,
+                Message::Response(_) => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_request(&mut self, req: Request) -> anyhow::Result<()> {
+        let response = match req.method.as_str() {
+            GotoDefinition::METHOD => match serde_json::from_value(req.params) {
+                Ok(params) => Response::new_ok(req.id, self.goto_definition(params)),
+                Err(e) => invalid_params(req.id, e),
+            },
+            HoverRequest::METHOD => match serde_json::from_value(req.params) {
+                Ok(params) => Response::new_ok(req.id, self.hover(params)),
+                Err(e) => invalid_params(req.id, e),
+            },
+            _ => unsupported(req.id, &req.method),
+        };
+
+        self.connection.sender.send(Message::Response(response))?;
+
+        Ok(())
+    }
+
+    fn goto_definition(&self, params: GotoDefinitionParams) -> Option<GotoDefinitionResponse> {
+        let uri = &params.text_document_position_params.text_document.uri;
+
+        self.documents
+            .get(uri)
+            .and_then(|document| {
+                let def = self.definition(&params.text_document_position_params)?;
+                definition_location(uri, document, def)
+            })
+            .map(GotoDefinitionResponse::Scalar)
+    }
+
+    fn hover(&self, params: HoverParams) -> Option<Hover> {
+        self.definition(&params.text_document_position_params)
+            .map(|def| Hover {
+                contents: HoverContents::Markup(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value: format!("```jinko\n{}\n```", def.detail()),
+                }),
+                range: None,
+            })
+    }
+
+    fn handle_notification(&mut self, not: Notification) -> anyhow::Result<()> {
+        match not.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: DidOpenTextDocumentParams = serde_json::from_value(not.params)?;
+                self.analyze(params.text_document.uri, &params.text_document.text)
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: DidChangeTextDocumentParams = serde_json::from_value(not.params)?;
+
+                // Documents are always synchronized entirely, so the last change
+                // contains the whole source
+                match params.content_changes.last() {
+                    Some(change) => self.analyze(params.text_document.uri, &change.text),
+                    None => Ok(()),
+                }
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: DidCloseTextDocumentParams = serde_json::from_value(not.params)?;
+                self.documents.remove(&params.text_document.uri);
+
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Analyze a source and publish its diagnostics
+    fn analyze(&mut self, uri: Url, text: &str) -> anyhow::Result<()> {
+        let path = uri.to_file_path().ok();
+        let analysis = Analysis::new(text, path.as_deref());
+
+        let params = PublishDiagnosticsParams {
+            uri: uri.clone(),
+            diagnostics: analysis
+                .diagnostics()
+                .map(|e| diagnostic(text, e))
+                .collect(),
+            version: None,
+        };
+        let notification = Notification::new(String::from(PublishDiagnostics::METHOD), params);
+        self.connection
+            .sender
+            .send(Message::Notification(notification))?;
+
+        let document = Document {
+            text: text.to_owned(),
+            analysis,
+        };
+        self.documents.insert(uri, document);
+
+        Ok(())
+    }
+
+    fn definition(&self, position: &TextDocumentPositionParams) -> Option<&Definition> {
+        let document = self.documents.get(&position.text_document.uri)?;
+
+        document
+            .analysis
+            .definition(&location(&document.text, &position.position))
+    }
+}
+
+fn unsupported(id: RequestId, method: &str) -> Response {
+    Response::new_err(
+        id,
+        lsp_server::ErrorCode::MethodNotFound as i32,
+        format!("unsupported request: `{}`", method),
+    )
+}
+
+/// Answer a request whose parameters could not be deserialized
+fn invalid_params(id: RequestId, error: serde_json::Error) -> Response {
+    Response::new_err(
+        id,
+        lsp_server::ErrorCode::InvalidParams as i32,
+        format!("invalid parameters: {}", error),
+    )
+}
+
+/// Get a line of a source, or an empty line if the source does not contain it
+fn line(text: &str, index: usize) -> &str {
+    text.lines().nth(index).unwrap_or_default()
+}
+
+/// Jinko locations start at one, while LSP positions start at zero. The UTF-16 column
+/// of the position is converted to a byte column
+fn location(text: &str, position: &Position) -> Location {
+    let line = line(text, position.line as usize);
+
+    let mut units = 0;
+    let column = line
+        .char_indices()
+        .find(|(_, c)| {
+            units += c.len_utf16();
+            units > position.character as usize
+        })
+        .map_or(line.len(), |(idx, _)| idx);
+
+    Location::new(position.line as usize + 1, column + 1)
+}
+
+fn position(text: &str, location: &Location) -> Position {
+    let line = line(text, location.line() - 1);
+    let bytes = location.column() - 1;
+
+    let mut end = bytes.min(line.len());
+    while !line.is_char_boundary(end) {
+        end -= 1;
+    }
+    // Columns past the end of the line, such as the end of a span including the line
+    // break, are kept as they are
+    let units = line[..end].encode_utf16().count() + bytes.saturating_sub(line.len());
+
+    Position::new(location.line() as u32 - 1, units as u32)
+}
+
+fn range(text: &str, span: Option<&SpanTuple>) -> Range {
+    match span {
+        Some(span) => Range::new(position(text, span.start()), position(text, span.end())),
+        // Errors which do not concern a specific part of the source are reported
+        // at its beginning
+        None => Range::default(),
+    }
+}
+
+/// Get the location of a definition. Definitions of the document itself use its URI,
+/// as it might not be saved yet
+fn definition_location(
+    uri: &Url,
+    document: &Document,
+    def: &Definition,
+) -> Option<lsp_types::Location> {
+    if document.analysis.is_local(def.location()) {
+        let range = range(&document.text, Some(def.location()));
+        return Some(lsp_types::Location::new(uri.clone(), range));
+    }
+
+    let path: &PathBuf = def.location().path().as_ref()?;
+    let text = std::fs::read_to_string(path).ok()?;
+    let uri = Url::from_file_path(path.canonicalize().ok()?).ok()?;
+
+    Some(lsp_types::Location::new(
+        uri,
+        range(&text, Some(def.location())),
+    ))
+}
+
+fn diagnostic(text: &str, e: &Error) -> Diagnostic {
+    let severity = match e.kind() {
+        ErrKind::Warning => DiagnosticSeverity::WARNING,
+        _ => DiagnosticSeverity::ERROR,
+    };
+
+    // Hints are displayed alongside the message
+    let message = e.hints().iter().filter_map(Error::msg).fold(
+        e.msg().unwrap_or(e.kind().as_str()).to_string(),
+        |msg, hint| format!("{}\nhint: {}", msg, hint),
+    );
+
+    Diagnostic {
+        range: range(text, e.loc()),
+        severity: Some(severity),
+        code: Some(NumberOrString::String(e.kind().code().to_string())),
+        source: Some(String::from(SOURCE_NAME)),
+        message,
+        ..Diagnostic::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_params_keep_serving() {
+        let (connection, client) = Connection::memory();
+        let mut server = LanguageServer {
+            connection,
+            documents: HashMap::new(),
+        };
+
+        let request = Request::new(
+            RequestId::from(1),
+            HoverRequest::METHOD.to_owned(),
+            serde_json::json!({ "textDocument": 12 }),
+        );
+        assert!(server.handle_request(request).is_ok());
+
+        match client.receiver.recv().unwrap() {
+            Message::Response(response) => {
+                assert_eq!(response.id, RequestId::from(1));
+                assert_eq!(
+                    response.error.map(|e| e.code),
+                    Some(lsp_server::ErrorCode::InvalidParams as i32)
+                );
+            }
+            _ => panic!("expected a response"),
+        }
+    }
+
+    #[test]
+    fn columns_are_utf16() {
+        let text = "s = \"é😀\"; s.len()";
+
+        // `s.len()` starts at byte 14, but at the UTF-16 unit 11
+        let loc = Location::new(1, 15);
+        assert_eq!(position(text, &loc), Position::new(0, 11));
+        assert_eq!(location(text, &Position::new(0, 11)), loc);
+    }
+
+    #[test]
+    fn columns_past_the_end_of_line() {
+        let text = "é\nx";
+
+        assert_eq!(position(text, &Location::new(1, 4)), Position::new(0, 2));
+        assert_eq!(location(text, &Position::new(0, 5)), Location::new(1, 3));
+    }
+}
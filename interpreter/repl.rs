@@ -1,54 +1,52 @@
 //! The REPL module implements an interactive mode for the jinko ctx. You can
 //! use it as is, or run a file and then enter the interactive mode.
 
+mod buffer;
+mod command;
+mod input;
 mod prompt;
+use buffer::InputBuffer;
+use command::Command;
+use input::ReplInput;
 use prompt::Prompt;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 
-use jinko::{
-    context::Context,
-    instance::{FromObjectInstance, ObjectInstance},
-    instruction::Instruction,
-    typechecker::CheckedType,
-    value::JkConstant,
-};
+use jinko::{context::Context, error::Error, instance::ObjectInstance, typechecker::CheckedType};
 
 use crate::InteractResult;
 
 use linefeed::{DefaultTerminal, Interface, ReadResult};
 
+/// Echo of a value evaluated in the REPL, displayed along with its type as
+/// `res: int = 42`
 struct ReplInstance(ObjectInstance);
 
+impl ReplInstance {
+    /// Print the value, unless it has no type to display
+    fn echo(self) {
+        if let CheckedType::Resolved(_) = self.0.ty() {
+            println!("{}", self);
+        }
+    }
+}
+
 impl std::fmt::Display for ReplInstance {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{}",
-            match self.0.ty() {
-                CheckedType::Resolved(ty) => match ty.id() {
-                    "int" => JkConstant::<i64>::from_instance(&self.0).print(),
-                    "float" => JkConstant::<f64>::from_instance(&self.0).print(),
-                    "char" => JkConstant::<char>::from_instance(&self.0).print(),
-                    "string" => JkConstant::<String>::from_instance(&self.0).print(),
-                    "bool" => JkConstant::<bool>::from_instance(&self.0).print(),
-                    _ => self.0.as_string(),
-                },
-                _ => String::new(),
-            }
-        )
+        write!(f, "res: {} = {}", self.0.type_name(), self.0.repr())
     }
 }
 
 pub struct Repl {
     ctx: Option<Context>,
-    reader: Interface<DefaultTerminal>,
+    reader: Rc<Interface<DefaultTerminal>>,
 }
 
 impl Repl {
     pub fn new() -> std::io::Result<Repl> {
         Ok(Repl {
             ctx: None,
-            reader: Interface::new("jinko")?,
+            reader: Rc::new(Interface::new("jinko")?),
         })
     }
 
@@ -59,13 +57,59 @@ impl Repl {
         }
     }
 
-    fn setup_context(ctx: &mut Context) {
+    fn setup_context(ctx: &mut Context) -> Result<(), Error> {
         ctx.set_path(Some(PathBuf::from("repl")));
 
-        ctx.init_stdlib().unwrap();
-        ctx.execute().unwrap();
+        ctx.init_stdlib()?;
+
+        // Execute the instructions already present in the context, such as the
+        // inclusion of the standard library, before reading any input. The errors are
+        // emitted before being propagated, so that the user knows why the REPL exited
+        let res = ctx.eval("");
 
         ctx.emit_errors();
+
+        res.map(|_| ())
+    }
+
+    /// Interpret a file in the context of the REPL
+    fn load(ctx: &mut Context, path: &Path) -> Result<Option<ObjectInstance>, Error> {
+        let input = std::fs::read_to_string(path)?;
+
+        Repl::eval(ctx, &input)
+    }
+
+    /// Evaluate some input. If it fails, the declarations it made before failing are
+    /// rolled back, so that the context stays usable
+    fn eval(ctx: &mut Context, input: &str) -> Result<Option<ObjectInstance>, Error> {
+        let snapshot = ctx.snapshot();
+
+        let res = ctx.eval(input);
+        if res.is_err() {
+            ctx.restore(snapshot);
+        }
+
+        res
+    }
+
+    /// Execute a command. Returns `false` if the REPL should stop
+    fn run_command(ctx: &mut Context, command: Command) -> bool {
+        match command {
+            Command::Type(expr) => {
+                if let Ok(ty) = ctx.type_of_input(&expr) {
+                    println!("{}", ty);
+                }
+            }
+            Command::Dump => println!("{}", ctx.dump_scopes()),
+            Command::Load(path) => match Repl::load(ctx, &path) {
+                Ok(Some(res)) => ReplInstance(res).echo(),
+                Ok(None) => {}
+                Err(e) => ctx.error(e),
+            },
+            Command::Quit => return false,
+        }
+
+        true
     }
 
     /// Launch the REPL
@@ -75,17 +119,66 @@ impl Repl {
             None => Context::new(),
         };
 
-        Repl::setup_context(&mut ctx);
+        Repl::setup_context(&mut ctx)?;
+        ctx.set_input(Box::new(ReplInput::new(self.reader.clone())));
 
         self.reader.set_prompt(&Prompt::get(&ctx))?;
 
-        while let ReadResult::Input(input) = self.reader.read_line()? {
-            if let Ok(Some(res)) = ctx.eval(&input) {
-                println!("{}", ReplInstance(res));
+        let mut buffer = InputBuffer::new();
+
+        while let ReadResult::Input(line) = self.reader.read_line()? {
+            if buffer.is_empty() && line.trim().is_empty() {
+                continue;
+            }
+
+            if buffer.is_empty() && Command::is_command(&line) {
+                self.reader.add_history_unique(line.clone());
+
+                let keep_going = match Command::parse(&line) {
+                    Ok(command) => Repl::run_command(&mut ctx, command),
+                    Err(e) => {
+                        ctx.error(e);
+                        true
+                    }
+                };
+
+                ctx.emit_errors();
+                ctx.clear_errors();
+
+                if !keep_going || ctx.exit_code().is_some() {
+                    break;
+                }
+
+                // Loaded files may have read some input, changing the prompt
+                self.reader.set_prompt(&Prompt::get(&ctx))?;
+
+                continue;
             }
 
+            buffer.push_line(&line);
+
+            // Wait for the rest of the input if a block, string or comment is
+            // still open
+            if !buffer.is_complete() {
+                self.reader.set_prompt(&Prompt::continuation())?;
+                continue;
+            }
+
+            let input = buffer.take();
+            self.reader.add_history_unique(input.clone());
+
+            let res = Repl::eval(&mut ctx, &input);
+            ctx.emit_errors();
             ctx.clear_errors();
 
+            if let Ok(Some(res)) = res {
+                ReplInstance(res).echo();
+            }
+
+            if ctx.exit_code().is_some() {
+                break;
+            }
+
             self.reader.set_prompt(&Prompt::get(&ctx))?;
         }
 
@@ -0,0 +1,40 @@
+//! The input buffer accumulates lines entered in the REPL until they form a complete
+//! input, which can then be given to the context. This allows users to write blocks,
+//! function calls or strings over multiple lines.
+
+#[derive(Default)]
+pub struct InputBuffer {
+    input: String,
+}
+
+impl InputBuffer {
+    /// Create a new, empty input buffer
+    pub fn new() -> InputBuffer {
+        InputBuffer::default()
+    }
+
+    /// Add a line to the buffer
+    pub fn push_line(&mut self, line: &str) {
+        if !self.input.is_empty() {
+            self.input.push('\n');
+        }
+
+        self.input.push_str(line);
+    }
+
+    /// Is the buffer currently waiting for continuation lines
+    pub fn is_empty(&self) -> bool {
+        self.input.is_empty()
+    }
+
+    /// Is the buffer's content ready to be interpreted, or should we wait for more
+    /// lines
+    pub fn is_complete(&self) -> bool {
+        !jinko::parser::is_incomplete(&self.input)
+    }
+
+    /// Take the buffer's content, leaving it empty
+    pub fn take(&mut self) -> String {
+        std::mem::take(&mut self.input)
+    }
+}
@@ -0,0 +1,54 @@
+//! Commands are special inputs in the REPL which do not get interpreted as jinko code.
+//! They start with a colon, such as `:quit`, and allow users to inspect or interact
+//! with the REPL's context.
+
+use std::path::PathBuf;
+
+use jinko::error::{ErrKind, Error};
+
+/// All the commands available in the REPL
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+    /// Print the type of an expression: `:type <expr>`
+    Type(String),
+    /// Print the variables, functions and types currently declared: `:dump`
+    Dump,
+    /// Interpret a file in the current context: `:load <file>`
+    Load(PathBuf),
+    /// Exit the REPL: `:quit`
+    Quit,
+}
+
+impl Command {
+    /// Check if a line should be interpreted as a command rather than as jinko code
+    pub fn is_command(line: &str) -> bool {
+        line.trim_start().starts_with(':')
+    }
+
+    /// Parse a command from a line starting with a colon
+    pub fn parse(line: &str) -> Result<Command, Error> {
+        let line = line.trim().trim_start_matches(':');
+        let (name, arg) = match line.split_once(char::is_whitespace) {
+            Some((name, arg)) => (name, arg.trim()),
+            None => (line, ""),
+        };
+
+        let missing_arg = || {
+            Error::new(ErrKind::Context)
+                .with_msg(format!("missing argument for command `:{}`", name))
+        };
+
+        match name {
+            "type" | "t" if arg.is_empty() => Err(missing_arg()),
+            "type" | "t" => Ok(Command::Type(arg.to_string())),
+            "dump" | "d" => Ok(Command::Dump),
+            "load" | "l" if arg.is_empty() => Err(missing_arg()),
+            "load" | "l" => Ok(Command::Load(PathBuf::from(arg))),
+            "quit" | "q" => Ok(Command::Quit),
+            _ => Err(Error::new(ErrKind::Context).with_msg(format!(
+                "unknown command `:{}`. available commands are :type, :dump, :load and :quit",
+                name
+            ))),
+        }
+    }
+}
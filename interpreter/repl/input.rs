@@ -0,0 +1,58 @@
+//! Input given to the programs run by the REPL. The REPL reads its commands from the
+//! terminal through its line editor, so programs read their lines through the same
+//! editor instead of directly from the interpreter's standard input.
+
+use std::io::{self, BufRead, Read};
+use std::rc::Rc;
+
+use linefeed::{DefaultTerminal, Interface, ReadResult};
+
+pub struct ReplInput {
+    reader: Rc<Interface<DefaultTerminal>>,
+    line: Vec<u8>,
+    pos: usize,
+}
+
+impl ReplInput {
+    pub fn new(reader: Rc<Interface<DefaultTerminal>>) -> ReplInput {
+        ReplInput {
+            reader,
+            line: vec![],
+            pos: 0,
+        }
+    }
+}
+
+impl Read for ReplInput {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let amount = available.len().min(buf.len());
+
+        buf[..amount].copy_from_slice(&available[..amount]);
+        self.consume(amount);
+
+        Ok(amount)
+    }
+}
+
+impl BufRead for ReplInput {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.pos == self.line.len() {
+            self.reader.set_prompt("")?;
+
+            self.line = match self.reader.read_line()? {
+                ReadResult::Input(line) => format!("{}\n", line).into_bytes(),
+                // Reaching the end of the terminal's input, or receiving a signal,
+                // exhausts the program's input
+                _ => vec![],
+            };
+            self.pos = 0;
+        }
+
+        Ok(&self.line[self.pos..])
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.pos = (self.pos + amount).min(self.line.len());
+    }
+}
@@ -10,4 +10,9 @@ impl Prompt {
     pub fn get(_: &Context) -> String {
         format!("jinko {} ", ">".purple())
     }
+
+    /// Create the prompt used when waiting for the rest of an incomplete input
+    pub fn continuation() -> String {
+        format!("{} ", "  ... ".purple())
+    }
 }
@@ -0,0 +1,367 @@
+//! The analysis module gathers information about a jinko source for tooling such as
+//! editor integrations: The diagnostics found when typechecking it, as well as the
+//! declarations of the symbols it uses.
+//!
+//! Declarations are recorded by the typechecker as it goes through the source, in
+//! the form of [`Definition`]s. Uses of symbols are found by going through the syntax
+//! tree of the source, and are then resolved to their declarations by name.
+
+use std::path::{Path, PathBuf};
+
+use crate::ast::Json;
+use crate::context::Context;
+use crate::error::{Error, ErrorFormat};
+use crate::location::{Location, SpanTuple};
+use crate::parser;
+
+/// Kinds of symbols which can be declared in jinko
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SymbolKind {
+    Variable,
+    Function,
+    Type,
+}
+
+/// Declaration of a symbol, along with a description of it such as the type of a
+/// variable or the signature of a function
+#[derive(Clone, Debug, PartialEq)]
+pub struct Definition {
+    name: String,
+    kind: SymbolKind,
+    location: SpanTuple,
+    detail: String,
+}
+
+impl Definition {
+    pub fn new(name: &str, kind: SymbolKind, location: SpanTuple, detail: String) -> Definition {
+        Definition {
+            name: name.to_string(),
+            kind,
+            location,
+            detail,
+        }
+    }
+
+    /// Name under which the symbol was declared
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn kind(&self) -> SymbolKind {
+        self.kind
+    }
+
+    /// Location of the declaration
+    pub fn location(&self) -> &SpanTuple {
+        &self.location
+    }
+
+    /// Description of the symbol, such as `x: int` or `func f(a: int) -> int`
+    pub fn detail(&self) -> &str {
+        &self.detail
+    }
+
+    /// Does the definition declare a symbol with the given name. Methods can be
+    /// declared under a name prefixed with the type of their receiver
+    fn declares(&self, name: &str) -> bool {
+        self.name == name
+            || (self.kind == SymbolKind::Function
+                && self
+                    .name
+                    .strip_suffix(name)
+                    .is_some_and(|p| p.ends_with('.')))
+    }
+}
+
+/// Use of a symbol in a source
+#[derive(Debug)]
+struct Usage {
+    name: String,
+    /// Kinds of symbols the usage can refer to
+    kinds: &'static [SymbolKind],
+    /// Is the usage the declaration of the symbol itself
+    declaration: bool,
+    start: Location,
+    end: Location,
+}
+
+impl Usage {
+    /// Find the usages of symbols in a node of the syntax tree and in its children
+    fn collect(node: &Json, usages: &mut Vec<Usage>) {
+        if let Some(usage) = Usage::from_node(node) {
+            usages.push(usage);
+        }
+
+        if let Some(Json::Object(children)) = node.get("children") {
+            children.iter().for_each(|(_, child)| match child {
+                Json::Array(nodes) => nodes.iter().for_each(|node| Usage::collect(node, usages)),
+                child => Usage::collect(child, usages),
+            });
+        }
+    }
+
+    fn from_node(node: &Json) -> Option<Usage> {
+        use SymbolKind::*;
+
+        let (kinds, declaration, name_attribute): (&'static [SymbolKind], bool, &str) =
+            match node.get("kind") {
+                Some(Json::Str(kind)) => match kind.as_str() {
+                    "Var" | "VarAssign" => (&[Variable], false, "name"),
+                    "DecArg" => (&[Variable], true, "name"),
+                    "FunctionCall" | "MethodCall" => (&[Function], false, "name"),
+                    "FunctionDec" => (&[Function], true, "name"),
+                    "TypeInstantiation" => (&[Type], false, "type"),
+                    "TypeDec" => (&[Type], true, "name"),
+                    // Function references and empty types are parsed like variables
+                    "VarOrEmptyType" => (&[Variable, Function, Type], false, "name"),
+                    _ => return None,
+                },
+                _ => return None,
+            };
+
+        let name = match node.get("attributes")?.get(name_attribute)? {
+            Json::Str(name) => name.clone(),
+            _ => return None,
+        };
+
+        let span = node.get("span")?;
+        let (start, end) = (location(span.get("start")?)?, location(span.get("end")?)?);
+
+        // Declarations of functions and types span their whole body: They are
+        // only considered when pointing at their first line
+        let end = match declaration && start.line() != end.line() {
+            true => Location::new(start.line(), usize::MAX),
+            false => end,
+        };
+
+        Some(Usage {
+            name,
+            kinds,
+            declaration,
+            start,
+            end,
+        })
+    }
+
+    fn contains(&self, position: &Location) -> bool {
+        let position = (position.line(), position.column());
+
+        (self.start.line(), self.start.column()) <= position
+            && position <= (self.end.line(), self.end.column())
+    }
+
+    /// Is the usage the same span of source as a declaration
+    fn is_at(&self, location: &SpanTuple) -> bool {
+        self.start == *location.start()
+    }
+}
+
+fn location(json: &Json) -> Option<Location> {
+    match (json.get("line")?, json.get("column")?) {
+        (Json::Int(line), Json::Int(column)) => {
+            Some(Location::new(*line as usize, *column as usize))
+        }
+        _ => None,
+    }
+}
+
+/// Result of the analysis of a source
+pub struct Analysis {
+    path: Option<PathBuf>,
+    usages: Vec<Usage>,
+    definitions: Vec<Definition>,
+    diagnostics: Vec<Error>,
+}
+
+impl Analysis {
+    /// Parse and typecheck a source, along with the standard library. The source does
+    /// not need to be saved: Its path is only used to resolve inclusions
+    pub fn new(input: &str, path: Option<&Path>) -> Analysis {
+        let mut ctx = Context::new();
+        // The source might not match the content of the file at this point, which
+        // human readable errors would display
+        ctx.set_error_format(ErrorFormat::Json);
+
+        let parsed = ctx
+            .init_stdlib()
+            .and_then(|_| parser::parse(&mut ctx, input, path));
+
+        ctx.set_path(path.map(Path::to_owned));
+
+        // Typechecking partially parsed sources only gives misleading errors
+        if parsed.is_ok() {
+            let _ = ctx.typecheck();
+        }
+
+        let mut usages = vec![];
+        // The entry point always has a block
+        ctx.entry_point
+            .block()
+            .unwrap()
+            .instructions()
+            .iter()
+            .for_each(|inst| Usage::collect(&inst.ast().to_json(), &mut usages));
+
+        Analysis {
+            path: path.map(Path::to_owned),
+            usages,
+            definitions: ctx.typechecker.definitions().to_vec(),
+            diagnostics: ctx.error_handler.errors().to_vec(),
+        }
+    }
+
+    /// Errors and warnings concerning the analyzed source
+    pub fn diagnostics(&self) -> impl Iterator<Item = &Error> {
+        self.diagnostics
+            .iter()
+            .filter(|e| e.loc().is_none_or(|loc| self.is_local(loc)))
+    }
+
+    /// Get the declaration of the symbol used at a given position in the source
+    pub fn definition(&self, position: &Location) -> Option<&Definition> {
+        // The innermost usage is the one starting last, as usages contain each other
+        let usage = self
+            .usages
+            .iter()
+            .filter(|usage| usage.contains(position))
+            .max_by_key(|usage| (usage.start.line(), usage.start.column()))?;
+
+        let mut candidates = self
+            .definitions
+            .iter()
+            .filter(|def| usage.kinds.contains(&def.kind()) && def.declares(&usage.name));
+
+        if let Some(def) = candidates.clone().find(|def| usage.is_at(def.location())) {
+            return Some(def);
+        }
+
+        if usage.declaration {
+            return None;
+        }
+
+        // Variables are resolved to the closest declaration preceding them, while
+        // functions and types can be declared anywhere
+        let preceding = |def: &&Definition| {
+            let start = def.location().start();
+
+            self.is_local(def.location())
+                && (start.line(), start.column()) <= (usage.start.line(), usage.start.column())
+        };
+
+        candidates
+            .clone()
+            .filter(|def| def.kind() == SymbolKind::Variable)
+            .filter(preceding)
+            .max_by_key(|def| {
+                (
+                    def.location().start().line(),
+                    def.location().start().column(),
+                )
+            })
+            .or_else(|| candidates.find(|def| def.kind() != SymbolKind::Variable))
+    }
+
+    /// Get a description of the symbol used at a given position in the source
+    pub fn hover(&self, position: &Location) -> Option<&str> {
+        self.definition(position).map(Definition::detail)
+    }
+
+    /// Is a location part of the analyzed source
+    pub fn is_local(&self, location: &SpanTuple) -> bool {
+        location.path().as_deref() == self.path.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrKind;
+
+    fn definition_at(input: &str, line: usize, column: usize) -> Option<Definition> {
+        Analysis::new(input, None)
+            .definition(&Location::new(line, column))
+            .cloned()
+    }
+
+    #[test]
+    fn diagnostics_contain_errors_and_warnings() {
+        let analysis = Analysis::new("func f() { unused = 1; }\nf();\na = undeclared;", None);
+        let kinds: Vec<ErrKind> = analysis.diagnostics().map(Error::kind).collect();
+
+        assert!(kinds.contains(&ErrKind::TypeChecker));
+        assert!(kinds.contains(&ErrKind::Warning));
+    }
+
+    #[test]
+    fn diagnostics_of_invalid_syntax() {
+        let analysis = Analysis::new("a = ;", None);
+
+        assert!(analysis.diagnostics().any(|e| e.kind() == ErrKind::Parsing));
+    }
+
+    #[test]
+    fn function_definition() {
+        let input = "func add(a: int, b: int) -> int { a + b }\nres = add(1, 2);";
+        let def = definition_at(input, 2, 8).unwrap();
+
+        assert_eq!(def.kind(), SymbolKind::Function);
+        assert_eq!(def.location().start(), &Location::new(1, 1));
+        assert_eq!(def.detail(), "func add(a: int, b: int) -> int");
+    }
+
+    #[test]
+    fn function_declaration_hover() {
+        let analysis = Analysis::new("func one() -> int {\n    1\n}\none();", None);
+
+        assert_eq!(
+            analysis.hover(&Location::new(1, 7)),
+            Some("func one() -> int")
+        );
+        assert_eq!(analysis.hover(&Location::new(2, 5)), None);
+    }
+
+    #[test]
+    fn variable_definition() {
+        let input = "a = 1;\nb = 2;\nres = a + b;";
+        let def = definition_at(input, 3, 11).unwrap();
+
+        assert_eq!(def.name(), "b");
+        assert_eq!(def.location().start(), &Location::new(2, 1));
+        assert_eq!(def.detail(), "b: int");
+    }
+
+    #[test]
+    fn shadowed_variable_definition() {
+        let input = "a = 1;\nx = a;\n{\n    a = \"shadow\";\n    y = a;\n}";
+        let def = definition_at(input, 5, 9).unwrap();
+
+        assert_eq!(def.location().start(), &Location::new(4, 5));
+        assert_eq!(def.detail(), "a: string");
+    }
+
+    #[test]
+    fn argument_definition() {
+        let input = "func double(x: int) -> int { x * 2 }\ndouble(1);";
+        let def = definition_at(input, 1, 30).unwrap();
+
+        assert_eq!(def.location().start(), &Location::new(1, 13));
+        assert_eq!(def.detail(), "x: int");
+    }
+
+    #[test]
+    fn type_definition() {
+        let input = "type Point(x: int, y: int);\np = Point(x: 1, y: 2);";
+        let def = definition_at(input, 2, 6).unwrap();
+
+        assert_eq!(def.kind(), SymbolKind::Type);
+        assert_eq!(def.detail(), "type Point(x: int, y: int)");
+    }
+
+    #[test]
+    fn hover_outside_of_symbols() {
+        let analysis = Analysis::new("a = 1;\n\nb = 2;", None);
+
+        assert_eq!(analysis.hover(&Location::new(2, 1)), None);
+        assert_eq!(analysis.hover(&Location::new(3, 1)), Some("b: int"));
+    }
+}
@@ -0,0 +1,584 @@
+//! The ast module exposes the instruction tree built by the parser to external
+//! tooling, such as editor plugins or linters, so that they do not need to
+//! reimplement the parser. Each [`Instruction`] converts itself into an [`AstNode`],
+//! which is serialized to JSON in the following shape:
+//!
+//! ```json
+//! {
+//!     "kind": "BinaryOp",
+//!     "span": {"path": null, "start": {"line": 1, "column": 1}, "end": {...}},
+//!     "attributes": {"operator": "+"},
+//!     "children": {"lhs": {...}, "rhs": {...}}
+//! }
+//! ```
+//!
+//! A child is either a node, an array of nodes or `null` when it is optional and
+//! absent, such as the else body of an if-else.
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use crate::error::{ErrKind, Error};
+use crate::instruction::Instruction;
+use crate::location::{Location, SpanTuple};
+use crate::typechecker::TypeId;
+
+/// A JSON value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub(crate) fn object<K: Into<String>>(members: Vec<(K, Json)>) -> Json {
+        Json::Object(members.into_iter().map(|(k, v)| (k.into(), v)).collect())
+    }
+
+    /// Get the value of a member, if the value is an object containing it
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members
+                .iter()
+                .find(|(member, _)| member == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// Parse a JSON document, such as the ones produced by displaying a [`Json`] value
+    pub fn parse(input: &str) -> Result<Json, Error> {
+        let mut reader = JsonReader { input, pos: 0 };
+
+        let value = reader.value()?;
+        reader.skip_whitespace();
+
+        match reader.pos == input.len() {
+            true => Ok(value),
+            false => Err(reader.error("trailing characters")),
+        }
+    }
+
+    fn write_str(f: &mut Formatter, s: &str) -> FmtResult {
+        write!(f, "\"")?;
+        for c in s.chars() {
+            match c {
+                '"' => write!(f, "\\\"")?,
+                '\\' => write!(f, "\\\\")?,
+                '\n' => write!(f, "\\n")?,
+                '\r' => write!(f, "\\r")?,
+                '\t' => write!(f, "\\t")?,
+                c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+                c => write!(f, "{}", c)?,
+            }
+        }
+        write!(f, "\"")
+    }
+}
+
+/// Recursive descent parser for JSON documents
+struct JsonReader<'i> {
+    input: &'i str,
+    pos: usize,
+}
+
+impl<'i> JsonReader<'i> {
+    fn error(&self, msg: &str) -> Error {
+        Error::new(ErrKind::Parsing)
+            .with_msg(format!("invalid JSON at offset {}: {}", self.pos, msg))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(' ' | '\n' | '\r' | '\t') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), Error> {
+        self.skip_whitespace();
+
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            _ => Err(self.error(&format!("expected `{}`", expected))),
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str, value: Json) -> Result<Json, Error> {
+        match self.input[self.pos..].starts_with(keyword) {
+            true => {
+                self.pos += keyword.len();
+                Ok(value)
+            }
+            false => Err(self.error("unknown value")),
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, Error> {
+        self.skip_whitespace();
+
+        match self.peek() {
+            Some('n') => self.keyword("null", Json::Null),
+            Some('t') => self.keyword("true", Json::Bool(true)),
+            Some('f') => self.keyword("false", Json::Bool(false)),
+            Some('"') => self.string().map(Json::Str),
+            Some('[') => self.array(),
+            Some('{') => self.object(),
+            Some('-' | '0'..='9') => self.number(),
+            Some(_) => Err(self.error("unknown value")),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn number(&mut self) -> Result<Json, Error> {
+        let start = self.pos;
+        while let Some('-' | '+' | '.' | 'e' | 'E' | '0'..='9') = self.peek() {
+            self.pos += 1;
+        }
+
+        let number = &self.input[start..self.pos];
+        let value = match number.contains(&['.', 'e', 'E'][..]) {
+            true => number.parse().map(Json::Float).ok(),
+            false => number.parse().map(Json::Int).ok(),
+        };
+
+        value.ok_or_else(|| self.error(&format!("invalid number `{}`", number)))
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        self.expect('"')?;
+
+        let mut s = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(s),
+                Some('\\') => {
+                    let c = match self.next() {
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('u') => self.unicode_escape()?,
+                        Some(c @ ('"' | '\\' | '/')) => c,
+                        _ => return Err(self.error("invalid escape sequence")),
+                    };
+                    s.push(c);
+                }
+                Some(c) => s.push(c),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    fn hex_code(&mut self) -> Result<u32, Error> {
+        let code = self
+            .input
+            .get(self.pos..self.pos + 4)
+            .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+            .ok_or_else(|| self.error("invalid unicode escape"))?;
+        self.pos += 4;
+
+        Ok(code)
+    }
+
+    fn unicode_escape(&mut self) -> Result<char, Error> {
+        let mut code = self.hex_code()?;
+
+        // Characters outside of the basic plane are escaped as surrogate pairs
+        if (0xd800..0xdc00).contains(&code) && self.input[self.pos..].starts_with("\\u") {
+            self.pos += 2;
+            let low = self.hex_code()?;
+            if !(0xdc00..0xe000).contains(&low) {
+                return Err(self.error("invalid unicode escape"));
+            }
+
+            code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+        }
+
+        char::from_u32(code).ok_or_else(|| self.error("invalid unicode escape"))
+    }
+
+    fn array(&mut self) -> Result<Json, Error> {
+        self.expect('[')?;
+        self.skip_whitespace();
+
+        let mut values = vec![];
+        if self.peek() == Some(']') {
+            self.pos += 1;
+            return Ok(Json::Array(values));
+        }
+
+        loop {
+            values.push(self.value()?);
+            self.skip_whitespace();
+
+            match self.next() {
+                Some(',') => continue,
+                Some(']') => return Ok(Json::Array(values)),
+                _ => return Err(self.error("expected `,` or `]`")),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, Error> {
+        self.expect('{')?;
+        self.skip_whitespace();
+
+        let mut members = vec![];
+        if self.peek() == Some('}') {
+            self.pos += 1;
+            return Ok(Json::Object(members));
+        }
+
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.expect(':')?;
+            members.push((key, self.value()?));
+            self.skip_whitespace();
+
+            match self.next() {
+                Some(',') => continue,
+                Some('}') => return Ok(Json::Object(members)),
+                _ => return Err(self.error("expected `,` or `}`")),
+            }
+        }
+    }
+}
+
+impl Display for Json {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(b) => write!(f, "{}", b),
+            Json::Int(i) => write!(f, "{}", i),
+            // JSON has no representation for infinite or NaN values
+            Json::Float(v) if !v.is_finite() => write!(f, "null"),
+            Json::Float(v) => write!(f, "{:?}", v),
+            Json::Str(s) => Json::write_str(f, s),
+            Json::Array(values) => {
+                write!(f, "[")?;
+                for (idx, value) in values.iter().enumerate() {
+                    if idx != 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(members) => {
+                write!(f, "{{")?;
+                for (idx, (key, value)) in members.iter().enumerate() {
+                    if idx != 0 {
+                        write!(f, ",")?;
+                    }
+                    Json::write_str(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+impl From<bool> for Json {
+    fn from(b: bool) -> Json {
+        Json::Bool(b)
+    }
+}
+
+impl From<i64> for Json {
+    fn from(i: i64) -> Json {
+        Json::Int(i)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(u: usize) -> Json {
+        Json::Int(u as i64)
+    }
+}
+
+impl From<f64> for Json {
+    fn from(v: f64) -> Json {
+        Json::Float(v)
+    }
+}
+
+impl From<char> for Json {
+    fn from(c: char) -> Json {
+        Json::Str(c.to_string())
+    }
+}
+
+impl From<&str> for Json {
+    fn from(s: &str) -> Json {
+        Json::Str(s.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(s: String) -> Json {
+        Json::Str(s)
+    }
+}
+
+/// Types are given as they would be written in jinko source code
+impl From<&TypeId> for Json {
+    fn from(ty: &TypeId) -> Json {
+        Json::Str(ty.print())
+    }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(value: Option<T>) -> Json {
+        value.map_or(Json::Null, Into::into)
+    }
+}
+
+impl<T: Into<Json>> From<Vec<T>> for Json {
+    fn from(values: Vec<T>) -> Json {
+        Json::Array(values.into_iter().map(Into::into).collect())
+    }
+}
+
+impl From<&Location> for Json {
+    fn from(loc: &Location) -> Json {
+        Json::object(vec![
+            ("line", loc.line().into()),
+            ("column", loc.column().into()),
+        ])
+    }
+}
+
+impl From<&SpanTuple> for Json {
+    fn from(span: &SpanTuple) -> Json {
+        let path = span.path().as_ref().map(|p| p.display().to_string());
+
+        Json::object(vec![
+            ("path", path.into()),
+            ("start", span.start().into()),
+            ("end", span.end().into()),
+        ])
+    }
+}
+
+/// Node of the syntax tree, created from an [`Instruction`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct AstNode {
+    kind: &'static str,
+    span: Option<SpanTuple>,
+    attributes: Vec<(&'static str, Json)>,
+    children: Vec<(&'static str, Json)>,
+}
+
+impl AstNode {
+    /// Create a new node without attributes or children
+    pub fn new(kind: &'static str, span: Option<&SpanTuple>) -> AstNode {
+        AstNode {
+            kind,
+            span: span.cloned(),
+            attributes: vec![],
+            children: vec![],
+        }
+    }
+
+    /// Add an attribute to the node, such as the name of a variable or an operator
+    pub fn with_attr(mut self, name: &'static str, value: impl Into<Json>) -> AstNode {
+        self.attributes.push((name, value.into()));
+        self
+    }
+
+    /// Add a child to the node. It can be a single node, an optional node or a
+    /// list of nodes
+    pub fn with_child(mut self, name: &'static str, child: impl Into<Json>) -> AstNode {
+        self.children.push((name, child.into()));
+        self
+    }
+
+    /// Add a list of instructions as a child of the node
+    pub fn with_children(self, name: &'static str, children: &[Box<dyn Instruction>]) -> AstNode {
+        let nodes: Vec<AstNode> = children.iter().map(|child| child.ast()).collect();
+
+        self.with_child(name, nodes)
+    }
+
+    /// Kind of the instruction represented by the node
+    pub fn kind(&self) -> &str {
+        self.kind
+    }
+
+    /// Attribute of the node with the given name
+    pub fn attribute(&self, name: &str) -> Option<&Json> {
+        self.attributes
+            .iter()
+            .find(|(attr, _)| *attr == name)
+            .map(|(_, value)| value)
+    }
+
+    /// Child of the node with the given name
+    pub fn child(&self, name: &str) -> Option<&Json> {
+        self.children
+            .iter()
+            .find(|(child, _)| *child == name)
+            .map(|(_, value)| value)
+    }
+
+    /// Convert the node and all of its children to JSON
+    pub fn to_json(&self) -> Json {
+        Json::object(vec![
+            ("kind", self.kind.into()),
+            ("span", self.span.as_ref().into()),
+            ("attributes", Json::object(self.attributes.clone())),
+            ("children", Json::object(self.children.clone())),
+        ])
+    }
+}
+
+impl From<AstNode> for Json {
+    fn from(node: AstNode) -> Json {
+        node.to_json()
+    }
+}
+
+impl Display for AstNode {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{}", self.to_json())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::Context;
+
+    fn ast_json(input: &str) -> String {
+        let mut ctx = Context::new();
+        crate::parser::parse(&mut ctx, input, None).unwrap();
+
+        ctx.ast_json()
+    }
+
+    #[test]
+    fn json_escape_strings() {
+        let json = Json::from("a \"quoted\"\n\\ \u{1}");
+
+        assert_eq!(json.to_string(), r#""a \"quoted\"\n\\ \u0001""#);
+    }
+
+    #[test]
+    fn json_values() {
+        let json: Json = vec![
+            Json::Null,
+            true.into(),
+            15i64.into(),
+            1.0.into(),
+            f64::NAN.into(),
+            Json::object(vec![("a", Json::Array(vec![]))]),
+        ]
+        .into();
+
+        assert_eq!(json.to_string(), r#"[null,true,15,1.0,null,{"a":[]}]"#);
+    }
+
+    #[test]
+    fn json_parse_values() {
+        let json =
+            Json::parse(r#" [null, true, -15, 1.5e3, "a\"\n\u00e9\ud83d\ude00", {"a": []}] "#);
+
+        assert_eq!(
+            json.unwrap(),
+            Json::Array(vec![
+                Json::Null,
+                true.into(),
+                (-15i64).into(),
+                1500.0.into(),
+                "a\"\né😀".into(),
+                Json::object(vec![("a", Json::Array(vec![]))]),
+            ])
+        );
+    }
+
+    #[test]
+    fn json_parse_round_trip() {
+        let json = ast_json("func f(a: int) -> int { a * 2 }; f(\"x{1}\".len())");
+
+        assert_eq!(Json::parse(&json).unwrap().to_string(), json);
+    }
+
+    #[test]
+    fn json_parse_invalid() {
+        assert!(Json::parse("[1, 2").is_err());
+        assert!(Json::parse("{\"a\" 1}").is_err());
+        assert!(Json::parse("\"unterminated").is_err());
+        assert!(Json::parse("nul").is_err());
+        assert!(Json::parse("1 2").is_err());
+    }
+
+    #[test]
+    fn node_without_span() {
+        let node = AstNode::new("Continue", None);
+
+        assert_eq!(
+            node.to_string(),
+            r#"{"kind":"Continue","span":null,"attributes":{},"children":{}}"#
+        );
+    }
+
+    #[test]
+    fn function_dec_node() {
+        let mut ctx = Context::new();
+        crate::parser::parse(&mut ctx, "func f[T](a: T, b: int) -> T { a }", None).unwrap();
+        let node = ctx.entry_point.block().unwrap().instructions()[0].ast();
+
+        assert_eq!(node.kind(), "FunctionDec");
+        assert_eq!(node.attribute("name"), Some(&Json::from("f")));
+        assert_eq!(node.attribute("generics"), Some(&vec!["T"].into()));
+        assert_eq!(node.attribute("return_type"), Some(&Json::from("T")));
+        assert!(matches!(node.child("args"), Some(Json::Array(args)) if args.len() == 2));
+        assert!(matches!(node.child("block"), Some(Json::Object(_))));
+    }
+
+    #[test]
+    fn string_literal_node() {
+        let json = ast_json("\"a{b}\"");
+
+        assert!(json.contains(r#""kind":"MethodCall""#));
+        assert!(json.contains(r#""string_literal":true"#));
+    }
+
+    #[test]
+    fn binary_op_node() {
+        let json = ast_json("1 + a");
+
+        assert_eq!(
+            json,
+            concat!(
+                r#"[{"kind":"BinaryOp","#,
+                r#""span":{"path":null,"start":{"line":1,"column":1},"end":{"line":1,"column":6}},"#,
+                r#""attributes":{"operator":"+"},"children":{"#,
+                r#""lhs":{"kind":"Constant","#,
+                r#""span":{"path":null,"start":{"line":1,"column":1},"end":{"line":1,"column":2}},"#,
+                r#""attributes":{"type":"int","value":1},"children":{}},"#,
+                r#""rhs":{"kind":"VarOrEmptyType","#,
+                r#""span":{"path":null,"start":{"line":1,"column":5},"end":{"line":1,"column":6}},"#,
+                r#""attributes":{"name":"a"},"children":{}}}}]"#,
+            )
+        );
+    }
+}
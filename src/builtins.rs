@@ -1,44 +1,157 @@
 //! This module contains all builtin functions declared in the jinko interpreter
 
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::rc::Rc;
 
-use crate::context::Context;
+use crate::context::{Capability, Context, Interrupt};
+use crate::error::{ErrKind, Error};
 #[cfg(feature = "ffi")]
 use crate::ffi;
 use crate::generics;
 use crate::instance::{FromObjectInstance, ObjectInstance, ToObjectInstance};
-use crate::instruction::Instruction;
-use crate::value::{JkBool, JkChar, JkFloat, JkInt, JkString};
+use crate::instruction::{Instruction, Operator};
+use crate::typechecker::{CheckedType, TypeId};
+use crate::value::{
+    JkBool, JkChar, JkCommandResult, JkFloat, JkInt, JkMap, JkOption, JkString, JkVec, Value,
+    MAP_TYPE, OPTION_TYPE, VEC_TYPE,
+};
 
-type Args = Vec<Box<dyn Instruction>>;
-type BuiltinFn = fn(&mut Context, Args) -> Option<ObjectInstance>;
+/// Arguments given to a builtin. They are evaluated by the interpreter before the
+/// builtin gets called, in the order in which they were given
+pub type Args = [ObjectInstance];
+/// Function called when executing a builtin
+pub type BuiltinFn = Rc<dyn Fn(&mut Context, &Args) -> Option<ObjectInstance>>;
+
+/// Type of an argument expected by a builtin
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArgType {
+    /// Instances of the type with the given name
+    Type(&'static str),
+    /// Instances of any type, for builtins declared with generic arguments
+    Any,
+}
+
+impl ArgType {
+    fn accepts(&self, instance: &ObjectInstance) -> bool {
+        match (self, instance.ty()) {
+            (ArgType::Any, _) => true,
+            (ArgType::Type(name), CheckedType::Resolved(ty)) => ty.id() == *name,
+            (ArgType::Type(_), _) => false,
+        }
+    }
+}
+
+impl Display for ArgType {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            ArgType::Type(name) => write!(f, "{}", name),
+            ArgType::Any => write!(f, "any"),
+        }
+    }
+}
+
+/// Arguments expected by a builtin. They are checked before calling the builtin, so
+/// that it can access its arguments without checking their amount or their types
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Signature(Vec<ArgType>);
+
+impl Signature {
+    pub fn new(args: &[ArgType]) -> Signature {
+        Signature(args.to_vec())
+    }
+
+    /// Types of the arguments, in order
+    pub fn args(&self) -> &[ArgType] {
+        &self.0
+    }
+
+    /// Check that arguments match the signature of a builtin
+    pub fn check(&self, builtin: &str, args: &Args) -> Result<(), Error> {
+        let matches =
+            self.0.len() == args.len() && self.0.iter().zip(args).all(|(ty, arg)| ty.accepts(arg));
+
+        match matches {
+            true => Ok(()),
+            false => {
+                let given: Vec<String> = args.iter().map(ObjectInstance::type_name).collect();
+
+                Err(Error::new(ErrKind::Context)
+                    .with_msg(format!(
+                        "invalid arguments given to builtin `{}`: expected `{}`, got `({})`",
+                        generics::original_name(builtin),
+                        self,
+                        given.join(", ")
+                    ))
+                    .with_hint(Error::hint().with_msg(String::from(
+                        "the declaration of the builtin does not match its implementation",
+                    ))))
+            }
+        }
+    }
+}
+
+impl Display for Signature {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let args: Vec<String> = self.0.iter().map(ArgType::to_string).collect();
+
+        write!(f, "({})", args.join(", "))
+    }
+}
+
+const BOOL: ArgType = ArgType::Type("bool");
+const INT: ArgType = ArgType::Type("int");
+const FLOAT: ArgType = ArgType::Type("float");
+const CHAR: ArgType = ArgType::Type("char");
+const STRING: ArgType = ArgType::Type("string");
+const VEC: ArgType = ArgType::Type(VEC_TYPE);
+const MAP: ArgType = ArgType::Type(MAP_TYPE);
+const MAYBE_INT: ArgType = ArgType::Type(OPTION_TYPE);
+const ANY: ArgType = ArgType::Any;
 
 /// Contains the various components declared during the interpreter's initialization
 pub struct Builtins {
     functions: HashMap<String, BuiltinFn>,
+    /// Arguments expected by each builtin
+    signatures: HashMap<String, Signature>,
+    /// Capabilities the context needs to grant for some builtins to be called
+    capabilities: HashMap<String, Capability>,
 }
 
 /// Get the length of a string. Defined in stdlib/string.jk
-/// The first argument is the string to get the length of
-fn string_len(ctx: &mut Context, args: Args) -> Option<ObjectInstance> {
-    let arg0 = args[0].execute(ctx).unwrap();
-    let jk_string = JkString::from_instance(&arg0);
+/// The first argument is the string to get the length of. As for the other string
+/// builtins, the length is expressed in characters and not in bytes
+fn string_len(_ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let s = JkString::as_str(&args[0]);
 
-    Some(JkInt::from(jk_string.0.len() as i64).to_instance())
+    Some(JkInt::from(s.chars().count() as i64).to_instance())
 }
 
 /// Concatenate two strings together. Defined in stdlib/string.jk
-fn string_concat(ctx: &mut Context, args: Args) -> Option<ObjectInstance> {
-    let lhs = JkString::from_instance(&args[0].execute(ctx).unwrap()).0;
-    let rhs = JkString::from_instance(&args[1].execute(ctx).unwrap()).0;
+fn string_concat(_ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let (lhs, rhs) = (&args[0], &args[1]);
 
-    Some(JkString::from(format!("{}{}", lhs, rhs)).to_instance())
+    // Concatenating an empty string does not create a new one
+    match (lhs.data(), rhs.data()) {
+        (_, []) => Some(lhs.clone()),
+        ([], _) => Some(rhs.clone()),
+        (lhs, rhs) => {
+            let mut s = String::with_capacity(lhs.len() + rhs.len());
+            s.push_str(JkString::as_str(&args[0]));
+            s.push_str(JkString::as_str(&args[1]));
+
+            Some(JkString::shared_instance(s.into()))
+        }
+    }
 }
 
-fn string_display(ctx: &mut Context, args: Args) -> Option<ObjectInstance> {
-    let s = JkString::from_instance(&args[0].execute(ctx).unwrap()).0;
-    let add_newline = JkBool::from_instance(&args[1].execute(ctx).unwrap()).0;
+fn string_display(_ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let s = JkString::as_str(&args[0]);
+    let add_newline = JkBool::from_instance(&args[1]).0;
 
     print!("{}", s);
 
@@ -49,9 +162,9 @@ fn string_display(ctx: &mut Context, args: Args) -> Option<ObjectInstance> {
     None
 }
 
-fn string_display_err(ctx: &mut Context, args: Args) -> Option<ObjectInstance> {
-    let s = JkString::from_instance(&args[0].execute(ctx).unwrap()).0;
-    let add_newline = JkBool::from_instance(&args[1].execute(ctx).unwrap()).0;
+fn string_display_err(_ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let s = JkString::as_str(&args[0]);
+    let add_newline = JkBool::from_instance(&args[1]).0;
 
     eprint!("{}", s);
 
@@ -62,27 +175,269 @@ fn string_display_err(ctx: &mut Context, args: Args) -> Option<ObjectInstance> {
     None
 }
 
-fn string_is_empty(ctx: &mut Context, args: Args) -> Option<ObjectInstance> {
-    let s = JkString::from_instance(&args[0].execute(ctx).unwrap()).0;
+fn string_is_empty(_ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let s = JkString::as_str(&args[0]);
 
     Some(JkBool::from(s.is_empty()).to_instance())
 }
 
-fn string_equals(ctx: &mut Context, args: Args) -> Option<ObjectInstance> {
-    let lhs = JkString::from_instance(&args[0].execute(ctx).unwrap()).0;
-    let rhs = JkString::from_instance(&args[1].execute(ctx).unwrap()).0;
+fn string_equals(_ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let lhs = JkString::as_str(&args[0]);
+    let rhs = JkString::as_str(&args[1]);
 
     Some(JkBool::from(lhs == rhs).to_instance())
 }
 
+/// Get the character at a given index in a string. Indices are expressed in characters
+/// and not in bytes. Defined in stdlib/string.jk
+fn string_char_at(ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let s = JkString::as_str(&args[0]);
+    let idx = JkInt::from_instance(&args[1]).0;
+
+    let c = usize::try_from(idx).ok().and_then(|idx| s.chars().nth(idx));
+
+    match c {
+        Some(c) => Some(JkChar::from(c).to_instance()),
+        None => {
+            ctx.error(Error::new(ErrKind::Context).with_msg(format!(
+                "index out of bounds: cannot access character {} of string \"{}\"",
+                idx, s
+            )));
+            None
+        }
+    }
+}
+
+/// Get the part of a string between two indices, the first one being inclusive and the
+/// second one exclusive. Indices are expressed in characters and not in bytes. Defined
+/// in stdlib/string.jk
+fn string_substring(ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let s = JkString::as_str(&args[0]);
+    let from = JkInt::from_instance(&args[1]).0;
+    let to = JkInt::from_instance(&args[2]).0;
+
+    let char_count = s.chars().count() as i64;
+    if from < 0 || to < from || to > char_count {
+        ctx.error(Error::new(ErrKind::Context).with_msg(format!(
+            "invalid range: cannot get substring {}..{} of string \"{}\"",
+            from, to, s
+        )));
+        return None;
+    }
+
+    if from == 0 && to == char_count {
+        return Some(args[0].clone());
+    }
+
+    let substring: String = s
+        .chars()
+        .skip(from as usize)
+        .take((to - from) as usize)
+        .collect();
+
+    Some(JkString::shared_instance(substring.into()))
+}
+
+/// Find the index of the first occurence of a pattern in a string, or -1 if the
+/// pattern is not present. The index is expressed in characters and not in bytes.
+/// Defined in stdlib/string.jk
+fn string_find(_ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let s = JkString::as_str(&args[0]);
+    let pattern = JkString::as_str(&args[1]);
+
+    let idx = s
+        .find(pattern)
+        .map_or(-1, |byte_idx| s[..byte_idx].chars().count() as i64);
+
+    Some(JkInt::from(idx).to_instance())
+}
+
+/// Split a string around each occurence of a separator, returning a vector of
+/// strings. Defined in stdlib/string.jk
+fn string_split(ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let s = JkString::as_str(&args[0]);
+    let sep = JkString::as_str(&args[1]);
+
+    if sep.is_empty() {
+        let loc = ctx.call_location().cloned();
+        ctx.error(
+            Error::new(ErrKind::Context)
+                .with_msg(format!(
+                    "cannot split string \"{}\" around an empty separator",
+                    s
+                ))
+                .with_loc(loc),
+        );
+        return None;
+    }
+
+    let parts = s
+        .split(sep)
+        .map(|part| JkString::shared_instance(part.into()))
+        .collect::<Vec<ObjectInstance>>();
+
+    Some(JkVec::from(parts).to_instance())
+}
+
+/// Return a string transformed by a builtin, or the original instance if the
+/// transformation left it unchanged, so that its content does not get copied
+fn string_or_unchanged(original: &ObjectInstance, transformed: &str) -> ObjectInstance {
+    match transformed == JkString::as_str(original) {
+        true => original.clone(),
+        false => JkString::shared_instance(transformed.into()),
+    }
+}
+
+/// Remove the whitespace at the beginning and end of a string. Defined in
+/// stdlib/string.jk
+fn string_trim(_ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let s = JkString::as_str(&args[0]);
+
+    Some(string_or_unchanged(&args[0], s.trim()))
+}
+
+/// Convert a string to uppercase. Defined in stdlib/string.jk
+fn string_to_upper(_ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let s = JkString::as_str(&args[0]);
+
+    Some(string_or_unchanged(&args[0], &s.to_uppercase()))
+}
+
+/// Convert a string to lowercase. Defined in stdlib/string.jk
+fn string_to_lower(_ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let s = JkString::as_str(&args[0]);
+
+    Some(string_or_unchanged(&args[0], &s.to_lowercase()))
+}
+
+/// Replace all the occurences of a pattern in a string. Defined in stdlib/string.jk
+fn string_replace(_ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let s = JkString::as_str(&args[0]);
+    let from = JkString::as_str(&args[1]);
+    let to = JkString::as_str(&args[2]);
+
+    match s.contains(from) {
+        true => Some(JkString::shared_instance(s.replace(from, to).into())),
+        false => Some(args[0].clone()),
+    }
+}
+
+/// Create a new, empty vector. Defined in stdlib/vec.jk
+fn vec_new(_ctx: &mut Context, _args: &Args) -> Option<ObjectInstance> {
+    Some(JkVec::new().to_instance())
+}
+
+/// Return a new vector with an element added at its end. Defined in stdlib/vec.jk
+fn vec_push(_ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let mut vec = JkVec::from_instance(&args[0]);
+    let value = args[1].clone();
+
+    vec.0.push(value);
+
+    Some(vec.to_instance())
+}
+
+/// Return a new vector without the last element of the original one. Defined in
+/// stdlib/vec.jk
+fn vec_pop(ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let mut vec = JkVec::from_instance(&args[0]);
+
+    if vec.0.pop().is_none() {
+        ctx.error(
+            Error::new(ErrKind::Context).with_msg(String::from("cannot pop from an empty vector")),
+        );
+        return None;
+    }
+
+    Some(vec.to_instance())
+}
+
+/// Get the element at a given index in a vector. Defined in stdlib/vec.jk
+fn vec_get(ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let vec = JkVec::from_instance(&args[0]);
+    let idx = JkInt::from_instance(&args[1]).0;
+
+    match usize::try_from(idx).ok().and_then(|idx| vec.0.get(idx)) {
+        Some(value) => Some(value.clone()),
+        None => {
+            ctx.error(Error::new(ErrKind::Context).with_msg(format!(
+                "index out of bounds: cannot access element {} of vector of length {}",
+                idx,
+                vec.0.len()
+            )));
+            None
+        }
+    }
+}
+
+/// Get the amount of elements in a vector. Defined in stdlib/vec.jk
+fn vec_len(_ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let vec = JkVec::from_instance(&args[0]);
+
+    Some(JkInt::from(vec.0.len() as i64).to_instance())
+}
+
+/// Create a new, empty map. Defined in stdlib/map.jk
+fn map_new(_ctx: &mut Context, _args: &Args) -> Option<ObjectInstance> {
+    Some(JkMap::new().to_instance())
+}
+
+/// Return a new map with an entry added to it, replacing the previous value associated
+/// with the key if there was one. Defined in stdlib/map.jk
+fn map_insert(_ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let mut map = JkMap::from_instance(&args[0]);
+    let key = JkString::from_instance(&args[1]).0;
+    let value = args[2].clone();
+
+    map.0.insert(key, value);
+
+    Some(map.to_instance())
+}
+
+/// Get the value associated with a key in a map. Defined in stdlib/map.jk
+fn map_get(ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let map = JkMap::from_instance(&args[0]);
+    let key = JkString::from_instance(&args[1]).0;
+
+    match map.0.get(&key) {
+        Some(value) => Some(value.clone()),
+        None => {
+            ctx.error(
+                Error::new(ErrKind::Context)
+                    .with_msg(format!("key \"{}\" is not present in map", key)),
+            );
+            None
+        }
+    }
+}
+
+/// Check if a key is present in a map. Defined in stdlib/map.jk
+fn map_contains(_ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let map = JkMap::from_instance(&args[0]);
+    let key = JkString::from_instance(&args[1]).0;
+
+    Some(JkBool::from(map.0.contains_key(&key)).to_instance())
+}
+
+/// Return a new map without the entry associated with a key. Removing a key which is
+/// not present does nothing. Defined in stdlib/map.jk
+fn map_remove(_ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let mut map = JkMap::from_instance(&args[0]);
+    let key = JkString::from_instance(&args[1]).0;
+
+    map.0.remove(&key);
+
+    Some(map.to_instance())
+}
+
 /// Link with a given library at runtime
-fn ffi_link_with(ctx: &mut Context, args: Args) -> Option<ObjectInstance> {
+fn ffi_link_with(ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
     #[cfg(feature = "ffi")]
     {
-        let lib_path = JkString::from_instance(&args[0].execute(ctx).unwrap()).0;
+        let lib_path = JkString::from_instance(&args[0]).0;
 
         if let Err(e) = ffi::link_with(ctx, PathBuf::from(&lib_path)) {
-            ctx.error(e.with_msg(format!("couldn't link with library `{}`", &lib_path)));
+            ctx.error(e);
         }
 
         None
@@ -90,8 +445,6 @@ fn ffi_link_with(ctx: &mut Context, args: Args) -> Option<ObjectInstance> {
 
     #[cfg(not(feature = "ffi"))]
     {
-        use crate::{ErrKind, Error};
-
         ctx.error(Error::new(ErrKind::Context).with_msg(format!(
             "jinko is not compiled with FFI support. `link_with()` is disabled"
         )));
@@ -101,15 +454,17 @@ fn ffi_link_with(ctx: &mut Context, args: Args) -> Option<ObjectInstance> {
 }
 
 // Get an argument from the argument vector at a certain index
-fn arg_get(ctx: &mut Context, args: Args) -> Option<ObjectInstance> {
-    let idx = JkInt::from_instance(&args[0].execute(ctx).unwrap()).0;
+fn arg_get(ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let idx = JkInt::from_instance(&args[0]).0;
 
     let args = ctx.args();
 
     let result_string = match idx {
-        0 => ctx.path().map_or(std::env::args().next().unwrap(), |s| {
-            s.to_str().unwrap().to_string()
-        }),
+        // Code given directly to the interpreter has no path
+        0 => ctx
+            .path()
+            .map(|path| path.to_string_lossy().into_owned())
+            .unwrap_or_default(),
         _ => args
             // FIXME: Is this cast valid?
             .get((idx - 1) as usize)
@@ -120,160 +475,1639 @@ fn arg_get(ctx: &mut Context, args: Args) -> Option<ObjectInstance> {
     Some(JkString::from(result_string).to_instance())
 }
 
-fn arg_amount(ctx: &mut Context, _args: Args) -> Option<ObjectInstance> {
+fn arg_amount(ctx: &mut Context, _args: &Args) -> Option<ObjectInstance> {
     Some(JkInt::from(ctx.args().len() as i64).to_instance())
 }
 
-/// Exit the interpreter with a given exit code
-fn exit(ctx: &mut Context, args: Args) -> Option<ObjectInstance> {
-    let exit_code = JkInt::from_instance(&args[0].execute(ctx).unwrap()).0;
+/// Get the arguments given to the program, without the path of the source file
+fn arg_vec(ctx: &mut Context, _args: &Args) -> Option<ObjectInstance> {
+    let args = ctx
+        .args()
+        .iter()
+        .map(|arg| JkString::from(arg.as_str()).to_instance())
+        .collect::<Vec<ObjectInstance>>();
+
+    Some(JkVec::from(args).to_instance())
+}
+
+/// Stop the program with a given exit code. The process itself is not exited: The exit
+/// code is kept in the context, for the interpreter to use
+fn exit(ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let exit_code = JkInt::from_instance(&args[0]).0;
 
     // FIXME: Is this cast valid?
-    std::process::exit(exit_code as i32);
+    ctx.interrupt(Interrupt::Exit(exit_code as i32));
+
+    None
 }
 
-fn fmt_char(ctx: &mut Context, args: Args) -> Option<ObjectInstance> {
-    let value = JkChar::from_instance(&args[0].execute(ctx).unwrap()).0;
+fn fmt_char(_ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let value = JkChar::from_instance(&args[0]).0;
 
     Some(JkString::from(value.to_string()).to_instance())
 }
 
-fn fmt_float(ctx: &mut Context, args: Args) -> Option<ObjectInstance> {
-    let value = JkFloat::from_instance(&args[0].execute(ctx).unwrap()).0;
+fn fmt_float(_ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let value = JkFloat::from_instance(&args[0]).0;
 
     Some(JkString::from(value.to_string()).to_instance())
 }
 
-fn size_of(ctx: &mut Context, args: Args) -> Option<ObjectInstance> {
-    let instance = args[0].execute(ctx).unwrap();
+/// Get the optional value given to a builtin, reporting an error if its type is not
+/// shaped like the `Maybe_int` of the standard library
+fn option_arg(ctx: &mut Context, arg: &ObjectInstance) -> Option<JkOption> {
+    match JkOption::try_from_instance(arg) {
+        Ok(maybe) => Some(maybe),
+        Err(e) => {
+            let loc = ctx.call_location().cloned();
+            ctx.error(e.with_loc(loc));
+            None
+        }
+    }
+}
+
+/// Check if an optional value contains something. Defined in stdlib/maybe.jk
+fn maybe_is_some(ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let maybe = option_arg(ctx, &args[0])?;
 
-    Some(JkInt::from(instance.size() as i64).to_instance())
+    Some(JkBool::from(maybe.value().is_some()).to_instance())
 }
 
-fn type_of(ctx: &mut Context, args: Args) -> Option<ObjectInstance> {
-    let instance = args[0].execute(ctx).unwrap();
-    let instance_ty = instance.ty().to_string();
+/// Get the value contained in an optional value, or a default value if it is empty.
+/// Defined in stdlib/maybe.jk
+fn maybe_unwrap_or(ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let maybe = option_arg(ctx, &args[0])?;
+    let default = JkInt::from_instance(&args[1]).0;
 
-    Some(JkString::from(instance_ty).to_instance())
+    Some(JkInt::from(maybe.value().unwrap_or(default)).to_instance())
 }
 
-impl Builtins {
-    fn add(&mut self, name: &'static str, builtin_fn: BuiltinFn) {
-        self.functions.insert(String::from(name), builtin_fn);
+/// Convert an integer to its decimal representation. Defined in stdlib/fmt.jk
+fn int_to_string(_ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let value = JkInt::from_instance(&args[0]).0;
+
+    Some(JkString::from(value.to_string()).to_instance())
+}
+
+/// Parse an integer from a string. This returns an empty `Maybe_int` if the string
+/// does not represent a valid integer. Defined in stdlib/fmt.jk
+fn string_to_int(_ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let s = JkString::from_instance(&args[0]).0;
+
+    Some(JkOption::from(s.trim().parse().ok()).to_instance())
+}
+
+/// Convert a floating point number to its decimal representation. Defined in
+/// stdlib/fmt.jk
+fn float_to_string(_ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let value = JkFloat::from_instance(&args[0]).0;
+
+    Some(JkString::from(value.to_string()).to_instance())
+}
+
+/// Replace each `{}` placeholder of a format string with the display form of the
+/// next argument. Literal braces are written `{{` and `}}`
+fn format_string(fmt: &str, args: &[ObjectInstance]) -> Result<String, Error> {
+    let invalid = |msg: &str| {
+        Error::new(ErrKind::Context).with_msg(format!("invalid format string \"{}\": {}", fmt, msg))
+    };
+
+    let mut result = String::with_capacity(fmt.len());
+    let mut args = args.iter();
+    let mut chars = fmt.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+                result.push(c);
+            }
+            ('{', Some('}')) => {
+                chars.next();
+                match args.next() {
+                    Some(arg) => result.push_str(&arg.display()),
+                    None => return Err(invalid("not enough arguments")),
+                }
+            }
+            ('{', _) => return Err(invalid("unmatched `{`")),
+            ('}', _) => return Err(invalid("unmatched `}`")),
+            (c, _) => result.push(c),
+        }
     }
 
-    /// Create a new instance of builtins, with pre-defined functions
-    pub fn new() -> Builtins {
-        let mut builtins = Builtins {
-            functions: HashMap::new(),
-        };
+    match args.next() {
+        Some(_) => Err(invalid("too many arguments")),
+        None => Ok(result),
+    }
+}
 
-        builtins.add("__builtin_string_len", string_len);
-        builtins.add("__builtin_string_concat", string_concat);
-        builtins.add("__builtin_string_display", string_display);
-        builtins.add("__builtin_string_display_err", string_display_err);
-        builtins.add("__builtin_string_is_empty", string_is_empty);
-        builtins.add("__builtin_string_equals", string_equals);
-        builtins.add("__builtin_ffi_link_with", ffi_link_with);
-        builtins.add("__builtin_fmt_char", fmt_char);
-        builtins.add("__builtin_fmt_float", fmt_float);
-        builtins.add("__builtin_arg_get", arg_get);
-        builtins.add("__builtin_arg_amount", arg_amount);
-        builtins.add("__builtin_exit", exit);
-        builtins.add("size_of", size_of);
-        builtins.add("type_of", type_of);
+/// Format a string using a vector of arguments. Defined in stdlib/fmt.jk
+fn string_fmt(ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let fmt = JkString::from_instance(&args[0]).0;
+    let values = JkVec::from_instance(&args[1]);
 
-        builtins
+    match format_string(&fmt, values.elements()) {
+        Ok(result) => Some(JkString::from(result).to_instance()),
+        Err(e) => {
+            ctx.error(e);
+            None
+        }
     }
+}
 
-    pub fn contains(&self, name: &str) -> bool {
-        // We can demangle builtins to dispatch to our single, non generic
-        // implementation.
-        let name = generics::original_name(name);
+/// Parse a floating point number from a string. This returns an empty `Maybe_float`
+/// if the string does not represent a valid number. Defined in stdlib/fmt.jk
+// FIXME: Return a `Maybe[float]` once generic types are usable from builtins
+fn string_to_float(_ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let s = JkString::from_instance(&args[0]).0;
+    let value = s.trim().parse::<f64>().ok();
 
-        self.functions.contains_key(name)
+    Some(ObjectInstance::from_fields(
+        CheckedType::Resolved(TypeId::from("Maybe_float")),
+        vec![
+            (
+                String::from("inner"),
+                JkFloat::from(value.unwrap_or_default()).to_instance(),
+            ),
+            (
+                String::from("is_some"),
+                JkBool::from(value.is_some()).to_instance(),
+            ),
+        ],
+    ))
+}
+
+/// Report an error which happened while accessing a file. The file builtins are called
+/// by the functions of stdlib/file.jk, so the error points to the call to these functions
+fn file_error(ctx: &mut Context, action: &str, path: &str, e: io::Error) {
+    let loc = ctx.call_location().cloned();
+
+    ctx.error(
+        Error::new(ErrKind::IO)
+            .with_msg(format!("cannot {} file `{}`: {}", action, path, e))
+            .with_loc(loc),
+    );
+}
+
+/// Read the entire content of a file. Defined in stdlib/file.jk
+fn file_read_to_string(ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let path = JkString::from_instance(&args[0]).0;
+
+    match fs::read_to_string(&path) {
+        Ok(content) => Some(JkString::from(content).to_instance()),
+        Err(e) => {
+            file_error(ctx, "read", &path, e);
+            None
+        }
     }
+}
 
-    pub fn get(&self, builtin: &str) -> Option<&BuiltinFn> {
-        self.functions.get(generics::original_name(builtin))
+/// Write a string to a file, replacing its content. The file is created if it
+/// does not exist. Defined in stdlib/file.jk
+fn file_write(ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let path = JkString::from_instance(&args[0]).0;
+    let content = JkString::from_instance(&args[1]).0;
+
+    if let Err(e) = fs::write(&path, content) {
+        file_error(ctx, "write to", &path, e);
     }
+
+    None
 }
 
-impl Default for Builtins {
-    fn default() -> Self {
-        Self::new()
+/// Write a string at the end of a file. The file is created if it does not exist.
+/// Defined in stdlib/file.jk
+fn file_append(ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let path = JkString::from_instance(&args[0]).0;
+    let content = JkString::from_instance(&args[1]).0;
+
+    let result = OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(content.as_bytes()));
+
+    if let Err(e) = result {
+        file_error(ctx, "append to", &path, e);
     }
+
+    None
 }
 
-#[cfg(test)]
-mod tests {
-    use crate::jinko;
+/// Check if a file exists. Defined in stdlib/file.jk
+fn file_exists(_ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let path = JkString::from_instance(&args[0]).0;
 
-    #[test]
-    fn t_string_builtins_are_valid() {
-        jinko! {
-            __builtin_string_len("jk");
-            __builtin_string_concat("file", ".jk");
-            __builtin_string_display("to display", true);
-            __builtin_string_display_err("to display on err", true);
-            __builtin_string_equals("jin", "ko");
-            __builtin_string_is_empty("jinko");
-        };
+    Some(JkBool::from(Path::new(&path).exists()).to_instance())
+}
+
+/// Report an error which happened while reading the input of the program
+fn input_error(ctx: &mut Context, e: io::Error) {
+    let loc = ctx.call_location().cloned();
+
+    ctx.error(
+        Error::new(ErrKind::IO)
+            .with_msg(format!("cannot read input: {}", e))
+            .with_loc(loc),
+    );
+}
+
+/// Read a line from the input of the program, including its trailing newline. An
+/// empty string is returned once the input is exhausted. Defined in stdlib/stdin.jk
+fn stdin_read_line(ctx: &mut Context, _args: &Args) -> Option<ObjectInstance> {
+    let mut line = String::new();
+
+    match ctx.input().read_line(&mut line) {
+        Ok(_) => Some(JkString::from(line).to_instance()),
+        Err(e) => {
+            input_error(ctx, e);
+            None
+        }
+    }
+}
+
+/// Read the input of the program until it is exhausted. Defined in stdlib/stdin.jk
+fn stdin_read_all(ctx: &mut Context, _args: &Args) -> Option<ObjectInstance> {
+    let mut content = String::new();
+
+    match ctx.input().read_to_string(&mut content) {
+        Ok(_) => Some(JkString::from(content).to_instance()),
+        Err(e) => {
+            input_error(ctx, e);
+            None
+        }
     }
+}
 
-    #[test]
-    #[cfg(feature = "ffi")]
-    fn t_ffi_builtins_are_valid() {
-        jinko! {
-            __builtin_ffi_link_with("tests/fixtures/clib/lib.so");
-        };
+/// Run a command through the system's shell and wait for it to complete. Defined in
+/// stdlib/shell.jk
+fn shell_exec(ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let cmd = JkString::from_instance(&args[0]).0;
+
+    match Command::new("sh").arg("-c").arg(&cmd).output() {
+        Ok(output) => Some(
+            JkCommandResult::new(
+                // Commands killed by a signal do not have an exit code
+                output.status.code().map_or(-1, i64::from),
+                String::from_utf8_lossy(&output.stdout).into_owned(),
+                String::from_utf8_lossy(&output.stderr).into_owned(),
+            )
+            .to_instance(),
+        ),
+        Err(e) => {
+            let loc = ctx.call_location().cloned();
+            ctx.error(
+                Error::new(ErrKind::IO)
+                    .with_msg(format!("cannot execute command `{}`: {}", cmd, e))
+                    .with_loc(loc),
+            );
+            None
+        }
     }
+}
 
-    #[test]
-    #[cfg(not(feature = "ffi"))]
-    fn t_ffi_builtins_are_valid_no_ffi() {
-        use crate::jinko_fail;
+/// Numbers the math builtins operate on. As in binary operations, integers are
+/// promoted to floats when mixed with them
+#[derive(Clone, Copy)]
+enum Number {
+    Int(i64),
+    Float(f64),
+}
 
-        jinko_fail! {
-            __builtin_ffi_link_with("tests/fixtures/clib/lib.so");
-        };
+impl Number {
+    fn as_float(self) -> f64 {
+        match self {
+            Number::Int(value) => value as f64,
+            Number::Float(value) => value,
+        }
     }
+}
 
-    #[test]
-    fn t_args_builtins_are_valid() {
-        jinko! {
-            __builtin_arg_get(158);
-            amount = __builtin_arg_amount();
-        };
+/// Get the value of an argument of a math builtin. The math builtins are generic, and only
+/// called with integers or floats by the functions of stdlib/math.jk
+fn math_operand(ctx: &mut Context, instance: &ObjectInstance) -> Option<Number> {
+    match instance.ty() {
+        CheckedType::Resolved(ty) if ty.id() == "int" => {
+            Some(Number::Int(JkInt::from_instance(instance).0))
+        }
+        CheckedType::Resolved(ty) if ty.id() == "float" => {
+            Some(Number::Float(JkFloat::from_instance(instance).0))
+        }
+        ty => {
+            let loc = ctx.call_location().cloned();
+            ctx.error(
+                Error::new(ErrKind::Context)
+                    .with_msg(format!(
+                        "math functions can only be applied to numbers, not `{}`",
+                        ty
+                    ))
+                    .with_loc(loc),
+            );
+            None
+        }
     }
+}
 
-    #[test]
-    fn t_exit_builtin_is_valid() {
-        use libc::{c_int, fork, waitpid, WEXITSTATUS};
+/// Create a math builtin applying a floating point function to its argument, which
+/// gets promoted to a float if needed. Defined in stdlib/math.jk
+fn math_float(f: fn(f64) -> f64) -> impl Fn(&mut Context, &Args) -> Option<ObjectInstance> {
+    move |ctx, args| {
+        let value = math_operand(ctx, &args[0])?.as_float();
+
+        Some(JkFloat::from(f(value)).to_instance())
+    }
+}
+
+/// Get the absolute value of a number. Defined in stdlib/math.jk
+fn math_abs(ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    match math_operand(ctx, &args[0])? {
+        Number::Int(value) => match value.checked_abs() {
+            Some(abs) => Some(JkInt::from(abs).to_instance()),
+            None => {
+                let loc = ctx.call_location().cloned();
+                ctx.error(
+                    Error::new(ErrKind::Context)
+                        .with_msg(format!("integer overflow: abs({})", value))
+                        .with_loc(loc),
+                );
+                None
+            }
+        },
+        Number::Float(value) => Some(JkFloat::from(value.abs()).to_instance()),
+    }
+}
 
-        let pid = unsafe { fork() };
-        if pid == 0 {
-            jinko! {
-                __builtin_exit(42);
-            };
+/// Raise a number to a power, with the same rules as the `**` operator. Defined in
+/// stdlib/math.jk
+fn math_pow(ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let base = math_operand(ctx, &args[0])?;
+    let exponent = math_operand(ctx, &args[1])?;
+
+    let result = match (base, exponent) {
+        (Number::Int(base), Number::Int(exponent)) => {
+            JkInt::from(base).do_op(&JkInt::from(exponent), Operator::Pow)
+        }
+        (base, exponent) => {
+            JkFloat::from(base.as_float()).do_op(&JkFloat::from(exponent.as_float()), Operator::Pow)
+        }
+    };
 
-            panic!("We should have exited by now");
+    match result {
+        Ok(value) => Some(value),
+        Err(e) => {
+            let loc = ctx.call_location().cloned();
+            ctx.error(e.with_loc(loc));
+            None
         }
+    }
+}
 
-        let mut status: c_int = 0;
-        let status_ptr: *mut c_int = &mut status;
-        unsafe {
-            waitpid(pid, status_ptr, 0);
+/// Create a math builtin keeping one of its two arguments. Integers are compared
+/// as such, and promoted to floats if mixed with them. Defined in stdlib/math.jk
+fn math_pick(
+    pick_int: fn(i64, i64) -> i64,
+    pick_float: fn(f64, f64) -> f64,
+) -> impl Fn(&mut Context, &Args) -> Option<ObjectInstance> {
+    move |ctx, args| {
+        let lhs = math_operand(ctx, &args[0])?;
+        let rhs = math_operand(ctx, &args[1])?;
+
+        match (lhs, rhs) {
+            (Number::Int(lhs), Number::Int(rhs)) => {
+                Some(JkInt::from(pick_int(lhs, rhs)).to_instance())
+            }
+            (lhs, rhs) => {
+                Some(JkFloat::from(pick_float(lhs.as_float(), rhs.as_float())).to_instance())
+            }
         }
+    }
+}
+
+/// Draw a random float between 0 (included) and 1 (excluded). Defined in
+/// stdlib/random.jk
+fn random_float(ctx: &mut Context, _args: &Args) -> Option<ObjectInstance> {
+    Some(JkFloat::from(ctx.rng().next_f64()).to_instance())
+}
+
+/// Draw a random integer between two bounds, the first one being inclusive and the
+/// second one exclusive. Defined in stdlib/random.jk
+fn random_int(ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let from = JkInt::from_instance(&args[0]).0;
+    let to = JkInt::from_instance(&args[1]).0;
 
-        assert_eq!(WEXITSTATUS(status), 42);
+    if to <= from {
+        let loc = ctx.call_location().cloned();
+        ctx.error(
+            Error::new(ErrKind::Context)
+                .with_msg(format!(
+                    "invalid range: cannot draw a random integer in {}..{}",
+                    from, to
+                ))
+                .with_loc(loc),
+        );
+        return None;
     }
 
-    #[test]
-    fn t_fmt_builtins_are_valid() {
-        jinko! {
-            __builtin_fmt_char('a');
-            __builtin_fmt_float(7.4);
-        };
+    // The width of the range is computed without overflowing, and the slight bias of
+    // the modulo is acceptable for a non cryptographic generator
+    let width = to.abs_diff(from);
+    let offset = ctx.rng().next_u64() % width;
+
+    Some(JkInt::from(from.wrapping_add(offset as i64)).to_instance())
+}
+
+/// Get the amount of milliseconds elapsed since the UNIX epoch. Defined in
+/// stdlib/time.jk
+fn clock_ms(ctx: &mut Context, _args: &Args) -> Option<ObjectInstance> {
+    Some(JkInt::from(ctx.clock().as_millis() as i64).to_instance())
+}
+
+fn size_of(_ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let instance = &args[0];
+
+    Some(JkInt::from(instance.size() as i64).to_instance())
+}
+
+/// Get the name of the type of an instance. Defined in stdlib/intrinsics.jk
+fn type_of(_ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let instance = &args[0];
+
+    Some(JkString::from(instance.type_name()).to_instance())
+}
+
+/// Check if an instance is of a given type, whose name is written either as in jinko
+/// source code or mangled. Defined in stdlib/intrinsics.jk
+fn is_a(_ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let instance = &args[0];
+    let type_name = JkString::from_instance(&args[1]).0;
+
+    let is_a = match instance.ty() {
+        CheckedType::Resolved(ty) => ty.id() == type_name || instance.type_name() == type_name,
+        _ => false,
+    };
+
+    Some(JkBool::from(is_a).to_instance())
+}
+
+/// Compare two instances structurally. Defined in stdlib/cmp.jk
+fn eq_any(_ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let lhs = &args[0];
+    let rhs = &args[1];
+
+    Some(JkBool::from(lhs.deep_eq(rhs)).to_instance())
+}
+
+/// Get the display form of any instance. Defined in stdlib/fmt.jk
+fn display_any(_ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let instance = &args[0];
+
+    Some(JkString::from(instance.display()).to_instance())
+}
+
+/// Representation of an instance shown when an assertion fails. Primitive values are
+/// printed as jinko literals
+fn print_instance(instance: &ObjectInstance) -> String {
+    let ty = match instance.ty() {
+        CheckedType::Resolved(ty) => ty.id(),
+        _ => return String::from("<unknown>"),
+    };
+
+    match ty {
+        "int" => JkInt::from_instance(instance).print(),
+        "float" => JkFloat::from_instance(instance).print(),
+        "bool" => JkBool::from_instance(instance).print(),
+        "char" => JkChar::from_instance(instance).print(),
+        "string" => JkString::from_instance(instance).print(),
+        _ => instance.display(),
+    }
+}
+
+/// Raise an error for a failed assertion. The assertion builtins are called by the
+/// functions of stdlib/assert.jk, so the location given to the error is the one of
+/// the call to these functions
+fn assertion_failure(ctx: &mut Context, msg: String) {
+    let loc = ctx.call_location().cloned();
+
+    ctx.error(
+        Error::new(ErrKind::Context)
+            .with_msg(format!("assertion failed: {}", msg))
+            .with_loc(loc),
+    );
+}
+
+/// Check that a condition holds. Defined in stdlib/assert.jk
+fn assert(ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let condition = JkBool::from_instance(&args[0]).0;
+
+    if !condition {
+        assertion_failure(ctx, String::from("condition is false"));
+    }
+
+    None
+}
+
+/// Check that two instances are equal. Defined in stdlib/assert.jk
+fn assert_eq(ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let lhs = &args[0];
+    let rhs = &args[1];
+
+    if lhs != rhs {
+        let msg = format!(
+            "values are not equal\n  left: {}\n right: {}",
+            print_instance(lhs),
+            print_instance(rhs)
+        );
+        assertion_failure(ctx, msg);
+    }
+
+    None
+}
+
+/// Check that two instances are different. Defined in stdlib/assert.jk
+fn assert_ne(ctx: &mut Context, args: &Args) -> Option<ObjectInstance> {
+    let lhs = &args[0];
+    let rhs = &args[1];
+
+    if lhs == rhs {
+        let msg = format!(
+            "values are equal\n  left: {}\n right: {}",
+            print_instance(lhs),
+            print_instance(rhs)
+        );
+        assertion_failure(ctx, msg);
+    }
+
+    None
+}
+
+impl Builtins {
+    /// Add a new builtin expecting arguments of the given types, or replace an
+    /// existing one
+    pub fn add(
+        &mut self,
+        name: &str,
+        args: &[ArgType],
+        builtin_fn: impl Fn(&mut Context, &Args) -> Option<ObjectInstance> + 'static,
+    ) {
+        self.functions
+            .insert(String::from(name), Rc::new(builtin_fn));
+        self.signatures
+            .insert(String::from(name), Signature::new(args));
+    }
+
+    /// Add a new builtin which can only be called if the context grants it a
+    /// capability
+    pub fn add_restricted(
+        &mut self,
+        name: &str,
+        capability: Capability,
+        args: &[ArgType],
+        builtin_fn: impl Fn(&mut Context, &Args) -> Option<ObjectInstance> + 'static,
+    ) {
+        self.add(name, args, builtin_fn);
+        self.capabilities.insert(String::from(name), capability);
+    }
+
+    /// Create a new instance of builtins, with pre-defined functions
+    pub fn new() -> Builtins {
+        let mut builtins = Builtins {
+            functions: HashMap::new(),
+            signatures: HashMap::new(),
+            capabilities: HashMap::new(),
+        };
+
+        builtins.add("__builtin_string_len", &[STRING], string_len);
+        builtins.add("__builtin_string_concat", &[STRING, STRING], string_concat);
+        builtins.add("__builtin_string_display", &[STRING, BOOL], string_display);
+        builtins.add(
+            "__builtin_string_display_err",
+            &[STRING, BOOL],
+            string_display_err,
+        );
+        builtins.add("__builtin_string_is_empty", &[STRING], string_is_empty);
+        builtins.add("__builtin_string_equals", &[STRING, STRING], string_equals);
+        builtins.add("__builtin_string_char_at", &[STRING, INT], string_char_at);
+        builtins.add(
+            "__builtin_string_substring",
+            &[STRING, INT, INT],
+            string_substring,
+        );
+        builtins.add("__builtin_string_find", &[STRING, STRING], string_find);
+        builtins.add("__builtin_string_split", &[STRING, STRING], string_split);
+        builtins.add("__builtin_string_trim", &[STRING], string_trim);
+        builtins.add("__builtin_string_to_upper", &[STRING], string_to_upper);
+        builtins.add("__builtin_string_to_lower", &[STRING], string_to_lower);
+        builtins.add(
+            "__builtin_string_replace",
+            &[STRING, STRING, STRING],
+            string_replace,
+        );
+        builtins.add("__builtin_vec_new", &[], vec_new);
+        builtins.add("__builtin_vec_push", &[VEC, ANY], vec_push);
+        builtins.add("__builtin_vec_pop", &[VEC], vec_pop);
+        builtins.add("__builtin_vec_get", &[VEC, INT], vec_get);
+        builtins.add("__builtin_vec_len", &[VEC], vec_len);
+        builtins.add("__builtin_map_new", &[], map_new);
+        builtins.add("__builtin_map_insert", &[MAP, STRING, ANY], map_insert);
+        builtins.add("__builtin_map_get", &[MAP, STRING], map_get);
+        builtins.add("__builtin_map_contains", &[MAP, STRING], map_contains);
+        builtins.add("__builtin_map_remove", &[MAP, STRING], map_remove);
+        builtins.add("__builtin_maybe_is_some", &[MAYBE_INT], maybe_is_some);
+        builtins.add(
+            "__builtin_maybe_unwrap_or",
+            &[MAYBE_INT, INT],
+            maybe_unwrap_or,
+        );
+        builtins.add_restricted(
+            "__builtin_ffi_link_with",
+            Capability::Ffi,
+            &[STRING],
+            ffi_link_with,
+        );
+        builtins.add("__builtin_fmt_char", &[CHAR], fmt_char);
+        builtins.add("__builtin_fmt_float", &[FLOAT], fmt_float);
+        builtins.add("__builtin_int_to_string", &[INT], int_to_string);
+        builtins.add("__builtin_string_to_int", &[STRING], string_to_int);
+        builtins.add("__builtin_float_to_string", &[FLOAT], float_to_string);
+        builtins.add("__builtin_string_to_float", &[STRING], string_to_float);
+        builtins.add("__builtin_string_fmt", &[STRING, VEC], string_fmt);
+        builtins.add("__builtin_display_any", &[ANY], display_any);
+        builtins.add_restricted(
+            "__builtin_file_read_to_string",
+            Capability::Io,
+            &[STRING],
+            file_read_to_string,
+        );
+        builtins.add_restricted(
+            "__builtin_file_write",
+            Capability::Io,
+            &[STRING, STRING],
+            file_write,
+        );
+        builtins.add_restricted(
+            "__builtin_file_append",
+            Capability::Io,
+            &[STRING, STRING],
+            file_append,
+        );
+        builtins.add_restricted(
+            "__builtin_file_exists",
+            Capability::Io,
+            &[STRING],
+            file_exists,
+        );
+        builtins.add("__builtin_stdin_read_line", &[], stdin_read_line);
+        builtins.add("__builtin_stdin_read_all", &[], stdin_read_all);
+        builtins.add_restricted(
+            "__builtin_shell_exec",
+            Capability::Shell,
+            &[STRING],
+            shell_exec,
+        );
+        builtins.add("__builtin_math_sqrt", &[ANY], math_float(f64::sqrt));
+        builtins.add("__builtin_math_floor", &[ANY], math_float(f64::floor));
+        builtins.add("__builtin_math_ceil", &[ANY], math_float(f64::ceil));
+        builtins.add("__builtin_math_round", &[ANY], math_float(f64::round));
+        builtins.add("__builtin_math_sin", &[ANY], math_float(f64::sin));
+        builtins.add("__builtin_math_cos", &[ANY], math_float(f64::cos));
+        builtins.add("__builtin_math_tan", &[ANY], math_float(f64::tan));
+        builtins.add("__builtin_math_asin", &[ANY], math_float(f64::asin));
+        builtins.add("__builtin_math_acos", &[ANY], math_float(f64::acos));
+        builtins.add("__builtin_math_atan", &[ANY], math_float(f64::atan));
+        builtins.add("__builtin_math_abs", &[ANY], math_abs);
+        builtins.add("__builtin_math_pow", &[ANY, ANY], math_pow);
+        builtins.add(
+            "__builtin_math_min",
+            &[ANY, ANY],
+            math_pick(i64::min, f64::min),
+        );
+        builtins.add(
+            "__builtin_math_max",
+            &[ANY, ANY],
+            math_pick(i64::max, f64::max),
+        );
+        builtins.add("__builtin_random_float", &[], random_float);
+        builtins.add("__builtin_random_int", &[INT, INT], random_int);
+        builtins.add("__builtin_clock_ms", &[], clock_ms);
+        builtins.add_restricted("__builtin_arg_get", Capability::Env, &[INT], arg_get);
+        builtins.add_restricted("__builtin_arg_amount", Capability::Env, &[], arg_amount);
+        builtins.add_restricted("__builtin_arg_vec", Capability::Env, &[], arg_vec);
+        builtins.add_restricted("__builtin_exit", Capability::Env, &[INT], exit);
+        builtins.add("__builtin_assert", &[BOOL], assert);
+        builtins.add("__builtin_assert_eq", &[ANY, ANY], assert_eq);
+        builtins.add("__builtin_assert_ne", &[ANY, ANY], assert_ne);
+        builtins.add("size_of", &[ANY], size_of);
+        builtins.add("type_of", &[ANY], type_of);
+        builtins.add("__builtin_type_of", &[ANY], type_of);
+        builtins.add("__builtin_is_a", &[ANY, STRING], is_a);
+        builtins.add("__builtin_eq_any", &[ANY, ANY], eq_any);
+
+        builtins
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        // We can demangle builtins to dispatch to our single, non generic
+        // implementation.
+        let name = generics::original_name(name);
+
+        self.functions.contains_key(name)
+    }
+
+    pub fn get(&self, builtin: &str) -> Option<&BuiltinFn> {
+        self.functions.get(generics::original_name(builtin))
+    }
+
+    /// Arguments expected by a builtin
+    pub fn signature(&self, builtin: &str) -> Option<&Signature> {
+        self.signatures.get(generics::original_name(builtin))
+    }
+
+    /// Capability required to call a builtin, if any
+    pub fn capability(&self, builtin: &str) -> Option<Capability> {
+        self.capabilities
+            .get(generics::original_name(builtin))
+            .copied()
+    }
+}
+
+impl Default for Builtins {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ArgType, Signature};
+    use crate::instance::{ObjectInstance, ToObjectInstance};
+    use crate::jinko;
+    use crate::value::{JkInt, JkString};
+
+    #[test]
+    fn t_signature_check() {
+        let signature = Signature::new(&[ArgType::Type("string"), ArgType::Any]);
+        let string = JkString::from("jinko").to_instance();
+        let int = JkInt::from(15).to_instance();
+
+        assert_eq!(signature.to_string(), "(string, any)");
+        assert!(signature
+            .check("builtin", &[string.clone(), int.clone()])
+            .is_ok());
+        assert!(signature
+            .check("builtin", &[string.clone(), string.clone()])
+            .is_ok());
+        assert!(signature
+            .check("builtin", &[int.clone(), int.clone()])
+            .is_err());
+        assert!(signature.check("builtin", &[string]).is_err());
+    }
+
+    #[test]
+    fn t_string_builtins_are_valid() {
+        jinko! {
+            __builtin_string_len("jk");
+            __builtin_string_concat("file", ".jk");
+            __builtin_string_display("to display", true);
+            __builtin_string_display_err("to display on err", true);
+            __builtin_string_equals("jin", "ko");
+            __builtin_string_is_empty("jinko");
+            __builtin_string_char_at("jinko", 2);
+            __builtin_string_substring("jinko", 1, 3);
+            __builtin_string_find("jinko", "nk");
+            __builtin_string_split("jin,ko", ",");
+            __builtin_string_trim(" jinko ");
+            __builtin_string_to_upper("jinko");
+            __builtin_string_to_lower("JINKO");
+            __builtin_string_replace("jinko", "k", "x");
+        };
+    }
+
+    #[test]
+    fn t_string_manipulation_builtins() {
+        use crate::instance::ToObjectInstance;
+        use crate::jk_execute;
+        use crate::value::{JkChar, JkInt, JkString};
+
+        assert_eq!(
+            jk_execute!("jinko".char_at(1)),
+            Some(JkChar::from('i').to_instance())
+        );
+        assert_eq!(
+            jk_execute!("jinko".substring(1, 4)),
+            Some(JkString::from("ink").to_instance())
+        );
+        assert_eq!(
+            jk_execute!("jinko".find("ko")),
+            Some(JkInt::from(3).to_instance())
+        );
+        assert_eq!(
+            jk_execute!("jinko".find("jk")),
+            Some(JkInt::from(-1).to_instance())
+        );
+    }
+
+    #[test]
+    fn t_string_non_ascii_indices() {
+        use crate::instance::ToObjectInstance;
+        use crate::jk_execute;
+        use crate::value::{JkChar, JkInt, JkString};
+
+        assert_eq!(
+            jk_execute!("héllo".len()),
+            Some(JkInt::from(5).to_instance())
+        );
+        assert_eq!(
+            jk_execute!(s = "héllo"; s.substring(0, s.len())),
+            Some(JkString::from("héllo").to_instance())
+        );
+        assert_eq!(
+            jk_execute!(s = "héllo"; s.char_at(s.find("l") - 1)),
+            Some(JkChar::from('é').to_instance())
+        );
+    }
+
+    #[test]
+    fn t_string_text_processing() {
+        use crate::instance::ToObjectInstance;
+        use crate::jk_execute;
+        use crate::value::{JkInt, JkString};
+
+        assert_eq!(
+            jk_execute!(" \tjinko\n ".trim()),
+            Some(JkString::from("jinko").to_instance())
+        );
+        assert_eq!(
+            jk_execute!("jinko".to_upper()),
+            Some(JkString::from("JINKO").to_instance())
+        );
+        assert_eq!(
+            jk_execute!("JiNkO".to_lower()),
+            Some(JkString::from("jinko").to_instance())
+        );
+        assert_eq!(
+            jk_execute!("a-b-c".replace("-", "")),
+            Some(JkString::from("abc").to_instance())
+        );
+        assert_eq!(
+            jk_execute!("a,,b".split(",").length[string]()),
+            Some(JkInt::from(3).to_instance())
+        );
+        assert_eq!(
+            jk_execute!("a,,b".split(",").get[string](2)),
+            Some(JkString::from("b").to_instance())
+        );
+    }
+
+    #[test]
+    fn t_string_builtins_share_unchanged_strings() {
+        use super::{string_concat, string_replace, string_to_upper, string_trim};
+        use crate::context::Context;
+        use std::rc::Rc;
+
+        let ctx = &mut Context::new();
+        let jinko = JkString::from("jinko").to_instance();
+        let empty = JkString::from("").to_instance();
+        let shares_data = |res: Option<ObjectInstance>| {
+            Rc::ptr_eq(&res.unwrap().shared_data(), &jinko.shared_data())
+        };
+
+        assert!(shares_data(string_concat(
+            ctx,
+            &[jinko.clone(), empty.clone()]
+        )));
+        assert!(shares_data(string_concat(
+            ctx,
+            &[empty.clone(), jinko.clone()]
+        )));
+        assert!(shares_data(string_trim(ctx, std::slice::from_ref(&jinko))));
+        assert!(shares_data(string_replace(
+            ctx,
+            &[jinko.clone(), JkString::from("x").to_instance(), empty]
+        )));
+        assert!(!shares_data(string_to_upper(
+            ctx,
+            std::slice::from_ref(&jinko)
+        )));
+        assert!(!shares_data(string_concat(
+            ctx,
+            &[jinko.clone(), jinko.clone()]
+        )));
+    }
+
+    #[test]
+    fn t_string_split_empty_separator() {
+        let mut ctx = jinko! {};
+
+        assert!(ctx.eval("\"jinko\".split(\"\")").is_err());
+    }
+
+    #[test]
+    fn t_string_manipulation_out_of_bounds() {
+        let mut ctx = jinko! {};
+
+        assert!(ctx.eval("\"jinko\".char_at(5)").is_err());
+        assert!(ctx.eval("\"jinko\".substring(3, 2)").is_err());
+        assert!(ctx.eval("\"jinko\".substring(0, 6)").is_err());
+    }
+
+    #[test]
+    fn t_vec_builtins_are_valid() {
+        jinko! {
+            empty = __builtin_vec_new();
+            v = empty.__builtin_vec_push[int](15);
+            v.__builtin_vec_get[int](0);
+            v.__builtin_vec_len();
+            v.__builtin_vec_pop();
+        };
+    }
+
+    #[test]
+    fn t_vec_builtins() {
+        use crate::instance::ToObjectInstance;
+        use crate::jk_execute;
+        use crate::value::JkInt;
+
+        assert_eq!(
+            jk_execute! {
+                v = vec[int]().push[int](1).push[int](2).push[int](3);
+                v.get[int](1) + v.length[int]()
+            },
+            Some(JkInt::from(5).to_instance())
+        );
+        assert_eq!(
+            jk_execute!(vec[int]().push[int](1).pop[int]().rest.length[int]()),
+            Some(JkInt::from(0).to_instance())
+        );
+        assert_eq!(
+            jk_execute!(vec[int]().push[int](1).push[int](2).pop[int]().value),
+            Some(JkInt::from(2).to_instance())
+        );
+    }
+
+    #[test]
+    fn t_vec_builtins_out_of_bounds() {
+        let mut ctx = jinko! {
+            v = vec[int]().push[int](1);
+        };
+
+        assert!(ctx.eval("v.get[int](1)").is_err());
+        assert!(ctx.eval("vec[int]().pop[int]()").is_err());
+    }
+
+    #[test]
+    fn t_assert_builtins_are_valid() {
+        jinko! {
+            __builtin_assert(true);
+            15.__builtin_assert_eq[int](15);
+            "jin".__builtin_assert_ne[string]("ko");
+        };
+    }
+
+    #[test]
+    fn t_assertions() {
+        let mut ctx = jinko! {
+            x = 3;
+        };
+
+        assert!(ctx.eval("assert(x == 3)").is_ok());
+        assert!(ctx.eval("assert_eq[int](x * 2, 6)").is_ok());
+        assert!(ctx.eval("assert_ne[string](\"jin\", \"ko\")").is_ok());
+
+        assert!(ctx.eval("assert(x == 4)").is_err());
+        assert!(ctx.eval("assert_eq[int](x, 4)").is_err());
+        assert!(ctx.eval("assert_ne[char]('j', 'j')").is_err());
+    }
+
+    #[test]
+    fn t_assertion_failure_message() {
+        let mut ctx = jinko! {};
+
+        assert!(ctx.eval("assert_eq[string](\"jin\", \"ko\")").is_err());
+
+        let msg = ctx.error_handler.errors()[0].msg().unwrap();
+        assert_eq!(
+            msg,
+            "assertion failed: values are not equal\n  left: \"jin\"\n right: \"ko\""
+        );
+    }
+
+    #[test]
+    fn t_map_builtins_are_valid() {
+        jinko! {
+            empty = __builtin_map_new();
+            m = empty.__builtin_map_insert[int]("jinko", 15);
+            m.__builtin_map_get[int]("jinko");
+            m.__builtin_map_contains("jinko");
+            m.__builtin_map_remove("jinko");
+        };
+    }
+
+    #[test]
+    fn t_map_builtins() {
+        use crate::instance::ToObjectInstance;
+        use crate::jk_execute;
+        use crate::value::{JkBool, JkInt};
+
+        assert_eq!(
+            jk_execute! {
+                m = map[int]().insert[int]("a", 1).insert[int]("b", 2).insert[int]("a", 3);
+                m.lookup[int]("a") + m.lookup[int]("b")
+            },
+            Some(JkInt::from(5).to_instance())
+        );
+        assert_eq!(
+            jk_execute! {
+                m = map[int]().insert[int]("a", 1).remove[int]("a");
+                m.contains[int]("a")
+            },
+            Some(JkBool::from(false).to_instance())
+        );
+    }
+
+    #[test]
+    fn t_map_builtins_missing_key() {
+        let mut ctx = jinko! {
+            m = map[int]().insert[int]("a", 1);
+        };
+
+        assert!(ctx.eval("m.lookup[int](\"b\")").is_err());
+    }
+
+    #[test]
+    fn t_maybe_builtins_are_valid() {
+        jinko! {
+            __builtin_maybe_is_some(some(15));
+            __builtin_maybe_unwrap_or(nothing(), 15);
+        };
+    }
+
+    #[test]
+    fn t_maybe_builtins_on_user_type() {
+        use crate::context::Context;
+
+        let mut ctx = Context::new();
+
+        let code = "type Maybe_int(x: int); \
+            ext func __builtin_maybe_is_some(m: Maybe_int) -> bool; \
+            Maybe_int(x: 1).__builtin_maybe_is_some()";
+        assert!(ctx.eval(code).is_err());
+        assert_eq!(
+            ctx.error_handler.errors()[0].msg(),
+            Some("field `is_some` does not exist on instance")
+        );
+    }
+
+    #[test]
+    fn t_maybe_builtins() {
+        use crate::instance::ToObjectInstance;
+        use crate::jk_execute;
+        use crate::value::{JkBool, JkInt};
+
+        assert_eq!(
+            jk_execute!(some(0).is_some()),
+            Some(JkBool::from(true).to_instance())
+        );
+        assert_eq!(
+            jk_execute!(nothing().is_none()),
+            Some(JkBool::from(true).to_instance())
+        );
+        assert_eq!(
+            jk_execute!(parse_int("jinko").unwrap_or(15)),
+            Some(JkInt::from(15).to_instance())
+        );
+        assert_eq!(
+            jk_execute!(parse_int("27").unwrap_or(15)),
+            Some(JkInt::from(27).to_instance())
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "ffi")]
+    fn t_ffi_builtins_are_valid() {
+        jinko! {
+            __builtin_ffi_link_with("tests/fixtures/clib/lib.so");
+        };
+    }
+
+    #[test]
+    #[cfg(not(feature = "ffi"))]
+    fn t_ffi_builtins_are_valid_no_ffi() {
+        use crate::jinko_fail;
+
+        jinko_fail! {
+            __builtin_ffi_link_with("tests/fixtures/clib/lib.so");
+        };
+    }
+
+    #[test]
+    fn t_args_builtins_are_valid() {
+        jinko! {
+            __builtin_arg_get(158);
+            amount = __builtin_arg_amount();
+            args = __builtin_arg_vec();
+        };
+    }
+
+    #[test]
+    fn t_exit_builtin_is_valid() {
+        let ctx = jinko! {
+            __builtin_exit(42);
+            unreachable = 15;
+        };
+
+        assert_eq!(ctx.exit_code(), Some(42));
+        assert!(ctx.get_variable("unreachable").is_none());
+    }
+
+    #[test]
+    fn t_fmt_builtins_are_valid() {
+        jinko! {
+            __builtin_fmt_char('a');
+            __builtin_fmt_float(7.4);
+        };
+    }
+
+    #[test]
+    fn t_conversion_builtins_are_valid() {
+        jinko! {
+            __builtin_int_to_string(-15);
+            __builtin_string_to_int("15");
+            __builtin_float_to_string(7.4);
+            __builtin_string_to_float("7.4");
+        };
+    }
+
+    #[test]
+    fn t_string_fmt_builtin_is_valid() {
+        jinko! {
+            __builtin_string_fmt("no placeholders", vec[int]().inner);
+        };
+    }
+
+    #[test]
+    fn t_file_builtins_are_valid() {
+        use crate::context::Context;
+        use crate::instance::ToObjectInstance;
+        use crate::value::JkString;
+
+        let path = std::env::temp_dir().join("jinko_t_file_builtins_are_valid");
+        let path = path.to_str().unwrap();
+
+        let mut ctx = Context::new();
+        ctx.init_stdlib().unwrap();
+        crate::parser::parse(
+            &mut ctx,
+            &format!(
+                r#"
+                __builtin_file_write("{0}", "hello");
+                __builtin_file_append("{0}", " jinko");
+                __builtin_file_exists("{0}");
+                __builtin_file_read_to_string("{0}")
+                "#,
+                path
+            ),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(
+            ctx.execute().unwrap(),
+            Some(JkString::from("hello jinko").to_instance())
+        );
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn t_file_exists() {
+        use crate::instance::ToObjectInstance;
+        use crate::jk_execute;
+        use crate::value::JkBool;
+
+        assert_eq!(
+            jk_execute!(file("Cargo.toml").exists()),
+            Some(JkBool::from(true).to_instance())
+        );
+        assert_eq!(
+            jk_execute!(file("does/not/exist").exists()),
+            Some(JkBool::from(false).to_instance())
+        );
+    }
+
+    #[test]
+    fn t_file_read_missing() {
+        use crate::jinko_fail;
+
+        jinko_fail! {
+            file("does/not/exist").read();
+        };
+    }
+
+    fn execute_with_input(input: &'static str, source: &str) -> Option<ObjectInstance> {
+        use crate::context::Context;
+
+        let mut ctx = Context::new();
+        ctx.init_stdlib().unwrap();
+        ctx.set_input(Box::new(input.as_bytes()));
+        crate::parser::parse(&mut ctx, source, None).unwrap();
+
+        ctx.execute().unwrap()
+    }
+
+    #[test]
+    fn t_stdin_builtins_are_valid() {
+        use crate::context::Context;
+
+        let mut ctx = Context::new();
+        ctx.init_stdlib().unwrap();
+        ctx.set_input(Box::new("jinko\n".as_bytes()));
+        crate::jk_parse!(&mut ctx, {
+            __builtin_stdin_read_line();
+            __builtin_stdin_read_all();
+        });
+
+        assert!(ctx.execute().is_ok());
+        assert!(!ctx.error_handler.has_errors());
+    }
+
+    #[test]
+    fn t_stdin_read_line() {
+        use crate::instance::ToObjectInstance;
+        use crate::value::JkString;
+
+        assert_eq!(
+            execute_with_input("first\nsecond\n", "read_line(); read_line()"),
+            Some(JkString::from("second\n").to_instance())
+        );
+    }
+
+    #[test]
+    fn t_stdin_read_line_exhausted() {
+        use crate::instance::ToObjectInstance;
+        use crate::value::JkString;
+
+        assert_eq!(
+            execute_with_input("last", "read_line(); read_line()"),
+            Some(JkString::from("").to_instance())
+        );
+    }
+
+    #[test]
+    fn t_stdin_read_all() {
+        use crate::instance::ToObjectInstance;
+        use crate::value::JkString;
+
+        assert_eq!(
+            execute_with_input("first\nsecond\nthird", "read_line(); read_all()"),
+            Some(JkString::from("second\nthird").to_instance())
+        );
+    }
+
+    #[test]
+    fn t_shell_builtins_are_valid() {
+        jinko! {
+            __builtin_shell_exec("true");
+        };
+    }
+
+    #[test]
+    fn t_shell_exec() {
+        use crate::instance::{FromObjectInstance, ToObjectInstance};
+        use crate::jk_execute;
+        use crate::value::{JkBool, JkCommandResult};
+
+        let result = jk_execute!(shell("echo jinko; exit 4")).unwrap();
+
+        assert_eq!(
+            JkCommandResult::from_instance(&result),
+            JkCommandResult::new(4, String::from("jinko\n"), String::new())
+        );
+        assert_eq!(
+            jk_execute!(shell("false").is_success()),
+            Some(JkBool::from(false).to_instance())
+        );
+    }
+
+    #[test]
+    fn t_math_builtins_are_valid() {
+        jinko! {
+            __builtin_math_sqrt[int](4);
+            __builtin_math_floor[float](4.5);
+            __builtin_math_ceil[float](4.5);
+            __builtin_math_round[float](4.5);
+            __builtin_math_sin[float](1.0);
+            __builtin_math_cos[float](1.0);
+            __builtin_math_tan[float](1.0);
+            __builtin_math_asin[float](1.0);
+            __builtin_math_acos[float](1.0);
+            __builtin_math_atan[float](1.0);
+            __builtin_math_abs[int](-4);
+            __builtin_math_pow[float](2.0, 0.5);
+            __builtin_math_min[int](1, 2);
+            __builtin_math_max[float](1.0, 2.0);
+        };
+    }
+
+    #[test]
+    fn t_math_keeps_integers() {
+        use crate::instance::ToObjectInstance;
+        use crate::jk_execute;
+        use crate::value::JkInt;
+
+        assert_eq!(jk_execute!(abs(-15)), Some(JkInt::from(15).to_instance()));
+        assert_eq!(jk_execute!(pow(3, 3)), Some(JkInt::from(27).to_instance()));
+        assert_eq!(jk_execute!(min(4, -4)), Some(JkInt::from(-4).to_instance()));
+    }
+
+    #[test]
+    fn t_math_promotes_integers() {
+        use crate::instance::ToObjectInstance;
+        use crate::jk_execute;
+        use crate::value::JkFloat;
+
+        assert_eq!(jk_execute!(sqrt(9)), Some(JkFloat::from(3.0).to_instance()));
+        assert_eq!(jk_execute!(cos(0)), Some(JkFloat::from(1.0).to_instance()));
+    }
+
+    #[test]
+    fn t_math_promotes_mixed_arguments() {
+        use crate::instance::ToObjectInstance;
+        use crate::jk_execute;
+        use crate::value::{JkFloat, JkInt};
+
+        assert_eq!(
+            jk_execute!(min(1, 2.5)),
+            Some(JkFloat::from(1.0).to_instance())
+        );
+        assert_eq!(
+            jk_execute!(min(2.5, 1)),
+            Some(JkFloat::from(1.0).to_instance())
+        );
+        assert_eq!(
+            jk_execute!(max(1, 2.5)),
+            Some(JkFloat::from(2.5).to_instance())
+        );
+        assert_eq!(
+            jk_execute!(pow(4, 0.5)),
+            Some(JkFloat::from(2.0).to_instance())
+        );
+        assert_eq!(jk_execute!(max(1, 2)), Some(JkInt::from(2).to_instance()));
+    }
+
+    #[test]
+    fn t_math_invalid() {
+        use crate::jinko_fail;
+
+        jinko_fail! {
+            abs(-9223372036854775807 - 1);
+        };
+        jinko_fail! {
+            pow(2, -1);
+        };
+        jinko_fail! {
+            __builtin_math_sqrt[string]("four");
+        };
+    }
+
+    #[test]
+    fn t_random_builtins() {
+        use crate::jinko_fail;
+
+        jinko! {
+            f = random();
+            __builtin_assert(f >= 0.0);
+            __builtin_assert(f < 1.0);
+            i = random_int(-3, 3);
+            __builtin_assert(i >= -3);
+            __builtin_assert(i < 3);
+            __builtin_assert(random_int(15, 16) == 15);
+            random_int(-9223372036854775807 - 1, 9223372036854775807);
+        };
+        jinko_fail! {
+            random_int(2, 2);
+        };
+    }
+
+    #[test]
+    fn t_deterministic_builtins() {
+        use crate::context::Context;
+
+        let draw = || {
+            let mut ctx = Context::new();
+            ctx.init_stdlib().unwrap();
+            ctx.set_deterministic(true);
+
+            ctx.eval("random_int(0, 1000) * 1000 + random_int(0, 1000) + clock_ms()")
+                .unwrap()
+        };
+
+        assert_eq!(draw(), draw());
+    }
+
+    #[test]
+    fn t_reflection_builtins_are_valid() {
+        jinko! {
+            __builtin_type_of[int](15);
+            __builtin_is_a[string]("jinko", "string");
+        };
+    }
+
+    #[test]
+    fn t_type_of() {
+        use crate::instance::ToObjectInstance;
+        use crate::jk_execute;
+        use crate::value::JkString;
+
+        assert_eq!(
+            jk_execute! {
+                type Point(x: int, y: int);
+                __builtin_type_of[Point](Point(x: 1, y: 2))
+            },
+            Some(JkString::from("Point").to_instance())
+        );
+        assert_eq!(
+            jk_execute!(type_of[Vec[int]](vec[int]())),
+            Some(JkString::from("Vec[int]").to_instance())
+        );
+    }
+
+    #[test]
+    fn t_is_a() {
+        use crate::instance::ToObjectInstance;
+        use crate::jk_execute;
+        use crate::value::JkBool;
+
+        assert_eq!(
+            jk_execute!(is_a[float](1.5, "float")),
+            Some(JkBool::from(true).to_instance())
+        );
+        assert_eq!(
+            jk_execute!(is_a[int](15, "float")),
+            Some(JkBool::from(false).to_instance())
+        );
+        assert_eq!(
+            jk_execute!(is_a[Vec[int]](vec[int](), "Vec[int]")),
+            Some(JkBool::from(true).to_instance())
+        );
+    }
+
+    #[test]
+    fn t_eq_any_builtin_is_valid() {
+        jinko! {
+            type Point(x: int, y: int);
+
+            __builtin_eq_any[int](15, 15);
+            __builtin_eq_any[Point](Point(x: 1, y: 2), Point(x: 1, y: 2));
+        };
+    }
+
+    #[test]
+    fn t_eq_stdlib() {
+        use crate::instance::ToObjectInstance;
+        use crate::jk_execute;
+        use crate::value::JkBool;
+
+        assert_eq!(
+            jk_execute! {
+                type Point(x: int, y: int);
+
+                eq[Point](Point(x: 1, y: 2), Point(x: 1, y: 2))
+            },
+            Some(JkBool::from(true).to_instance())
+        );
+        assert_eq!(
+            jk_execute! {
+                type Point(x: int, y: int);
+
+                ne[Point](Point(x: 1, y: 2), Point(x: 1, y: 2))
+            },
+            Some(JkBool::from(false).to_instance())
+        );
+    }
+
+    #[test]
+    fn t_display_any_builtin_is_valid() {
+        jinko! {
+            type Point(x: int, y: int);
+
+            __builtin_display_any[int](15);
+            __builtin_display_any[Point](Point(x: 1, y: 2));
+        };
+    }
+
+    #[test]
+    fn t_display_stdlib() {
+        use crate::instance::ToObjectInstance;
+        use crate::jk_execute;
+        use crate::value::JkString;
+
+        assert_eq!(
+            jk_execute! {
+                type Point(x: int, y: int);
+
+                display[Point](Point(x: 1, y: 2))
+            },
+            Some(JkString::from("Point(x: 1, y: 2)").to_instance())
+        );
+    }
+
+    #[test]
+    fn t_format_string() {
+        use super::format_string;
+        use crate::instance::ToObjectInstance;
+        use crate::value::{JkBool, JkChar, JkFloat, JkInt, JkString};
+
+        let args = vec![
+            JkInt::from(-15).to_instance(),
+            JkFloat::from(1.5).to_instance(),
+            JkBool::from(true).to_instance(),
+            JkChar::from('c').to_instance(),
+            JkString::from("jinko").to_instance(),
+        ];
+
+        assert_eq!(
+            format_string("{} {} {} {} {}", &args).unwrap(),
+            "-15 1.5 true c jinko"
+        );
+        assert_eq!(format_string("{{}} {}", &args[..1]).unwrap(), "{} -15");
+        assert_eq!(
+            format_string("no placeholders", &[]).unwrap(),
+            "no placeholders"
+        );
+    }
+
+    #[test]
+    fn t_format_string_invalid() {
+        use super::format_string;
+        use crate::instance::ToObjectInstance;
+        use crate::value::JkInt;
+
+        let args = vec![JkInt::from(1).to_instance()];
+
+        assert!(format_string("{} {}", &args).is_err());
+        assert!(format_string("", &args).is_err());
+        assert!(format_string("{", &[]).is_err());
+        assert!(format_string("}", &[]).is_err());
+        assert!(format_string("{a}", &args).is_err());
+    }
+
+    #[test]
+    fn t_format_stdlib() {
+        use crate::instance::ToObjectInstance;
+        use crate::jk_execute;
+        use crate::value::JkString;
+
+        assert_eq!(
+            jk_execute!(format[int]("{} + {}", vec[int]().push[int](1).push[int](2))),
+            Some(JkString::from("1 + 2").to_instance())
+        );
+        assert_eq!(
+            jk_execute!(format[int]("{{ok}} {}", vec[int]().push[int](1))),
+            Some(JkString::from("{ok} 1").to_instance())
+        );
+    }
+
+    #[test]
+    fn t_number_conversions() {
+        use crate::instance::ToObjectInstance;
+        use crate::jk_execute;
+        use crate::value::{JkBool, JkFloat, JkInt, JkString};
+
+        assert_eq!(
+            jk_execute!(fmt_int(-154)),
+            Some(JkString::from("-154").to_instance())
+        );
+        assert_eq!(
+            jk_execute!(parse_int("-154").unpack()),
+            Some(JkInt::from(-154).to_instance())
+        );
+        assert_eq!(
+            jk_execute!(parse_int("15a").is_some()),
+            Some(JkBool::from(false).to_instance())
+        );
+        assert_eq!(
+            jk_execute!(fmt_float(2.5)),
+            Some(JkString::from("2.5").to_instance())
+        );
+        assert_eq!(
+            jk_execute!(parse_float("2.5").unpack()),
+            Some(JkFloat::from(2.5).to_instance())
+        );
+    }
+
+    #[test]
+    fn t_parse_invalid_float() {
+        use crate::instance::ToObjectInstance;
+        use crate::jk_execute;
+        use crate::value::{JkBool, JkFloat};
+
+        assert_eq!(
+            jk_execute!(parse_float("jinko").is_some()),
+            Some(JkBool::from(false).to_instance())
+        );
+        assert_eq!(
+            jk_execute!(parse_float("jinko").unwrap_or(1.5)),
+            Some(JkFloat::from(1.5).to_instance())
+        );
     }
 }
@@ -4,24 +4,44 @@
 //! source file returns a "Context", which is really just a complex structure
 //! aggregating the necessary information to run a jinko program.
 
+use std::io::{self, BufRead, BufReader};
 use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use colored::Colorize;
 
+mod capabilities;
+mod reachability;
+mod rng;
+mod run_stats;
 mod scope_map;
+mod symbol_map;
+pub use capabilities::{Capabilities, Capability};
+use rng::{Rng, DETERMINISTIC_SEED};
+pub use run_stats::RunStats;
 pub use scope_map::{Scope, ScopeMap};
+pub use symbol_map::{IncludedSymbol, SymbolMap};
 
 use std::collections::{HashMap, HashSet};
 use std::fmt::{Display, Formatter, Result as FmtResult};
 use std::rc::Rc;
 
-use crate::builtins::Builtins;
-use crate::error::{ErrKind, Error, ErrorHandler};
-use crate::instance::ObjectInstance;
-use crate::instruction::{Block, FunctionDec, FunctionKind, Instruction, TypeDec, Var};
+use crate::ast::{AstNode, Json};
+use crate::builtins::{ArgType, Args, Builtins};
+use crate::debugger::DebugHook;
+use crate::error::{ErrKind, Error, ErrorFormat, ErrorHandler, Frame};
+use crate::instance::{FromObjectInstance, ObjectInstance};
+use crate::instruction::{
+    Block, FunctionCall, FunctionDec, FunctionKind, Incl, Instruction, InterfaceDec, InterfaceImpl,
+    TypeDec, Var, VarAssign, VarOrEmptyType,
+};
+use crate::location::SpanTuple;
+use crate::native::NativeFunction;
 use crate::parser;
+use crate::profiler::Profiler;
+use crate::project::Project;
 use crate::typechecker::CheckedType;
-use crate::typechecker::{SpecializedNode, TypeCheck, TypeCtx, TypeId};
+use crate::typechecker::{SpecializedNode, TypeCtx, TypeId};
 
 /// Type the context uses for keys
 type CtxKey = String;
@@ -29,6 +49,50 @@ type CtxKey = String;
 /// Name of the entry point in jinko
 const ENTRY_NAME: &str = "__entry";
 
+/// Name of the function used as the entry point of a program, if it is declared.
+/// Its signature is either `func main() -> int` or `func main(args: Vec[string]) -> int`
+const MAIN_NAME: &str = "main";
+
+/// Declarations reported as warnings when they are never used, after typechecking a
+/// program
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum UnusedDeclarations {
+    /// Nothing is reported, since code evaluated later on could use the declarations
+    Allowed,
+    /// Functions which are never called nor referenced
+    Functions,
+    /// Functions and types which cannot be reached from the top-level code of the
+    /// program or from its `main` function
+    Unreachable,
+}
+
+/// Environment variable containing the path to the directory of the standard library
+pub const STDLIB_VAR: &str = "JINKO_STDLIB";
+
+/// Installation prefix of the interpreter, given when compiling it. The standard
+/// library is then installed in `<prefix>/share/jinko/stdlib`
+const INSTALL_PREFIX: Option<&str> = option_env!("JINKO_PREFIX");
+
+/// Maximum depth of nested function calls, unless changed with
+/// [`Context::set_recursion_limit`]
+pub const DEFAULT_RECURSION_LIMIT: usize = 1000;
+
+/// Interruptions in the regular flow of execution, caused by instructions such as
+/// `break`, `continue` or the `?` operator. An interrupt stops the execution of all the blocks it is
+/// raised in, until it is caught by the instruction it concerns
+#[derive(Clone, Debug, PartialEq)]
+pub enum Interrupt {
+    /// Exit the innermost loop, with an optional value
+    Break(Option<ObjectInstance>),
+    /// Go to the next iteration of the innermost loop
+    Continue,
+    /// Exit the current function, with an optional value
+    Return(Option<ObjectInstance>),
+    /// Stop the whole program with an exit code. This is never caught by loops or
+    /// functions, only by the entry point
+    Exit(i32),
+}
+
 // FIXME: Rework visibility here
 /// A context represents the state of a jinko program. It contains functions,
 /// variables, tests... and can be optimized, typechecked, executed or
@@ -39,10 +103,17 @@ pub struct Context {
     path: Option<PathBuf>,
     /// Arguments given to the jinko program
     args: Vec<String>,
+    /// Input read by the jinko program, which is the standard input of the
+    /// interpreter unless the context is used interactively
+    input: Box<dyn BufRead>,
     /// Contains the functions shipping with the interpreter
     builtins: Builtins,
     /// Tests registered in the context
     tests: HashMap<CtxKey, FunctionDec>,
+    /// Mocks registered in the context, replacing functions during each test
+    mocks: Vec<FunctionDec>,
+    /// Functions replaced by a mock during the test currently running, if any
+    mocked: Option<Vec<Rc<FunctionDec>>>,
     /// Sources included by the context
     included: HashSet<PathBuf>,
     /// External libraries to use via FFI
@@ -60,6 +131,52 @@ pub struct Context {
     pub entry_point: FunctionDec,
     /// Errors being kept by the context
     pub error_handler: ErrorHandler,
+    /// Interrupt currently being propagated, if any
+    interrupt: Option<Interrupt>,
+    /// Exit code requested by the program during its last execution, if it exited
+    exit_code: Option<i32>,
+    /// Maximum depth of nested function calls
+    recursion_limit: usize,
+    /// Function calls currently being executed, the innermost one being last
+    calls: Vec<Frame>,
+    /// Amount of scopes entered when starting to execute the entry point, from which
+    /// traces are indented
+    trace_depth: usize,
+    /// Hook called before executing each statement, when debugging the program
+    debug_hook: Option<Box<dyn DebugHook>>,
+    /// Profiler measuring the function calls, when profiling the program
+    profiler: Option<Profiler>,
+    /// Maximum amount of statements and loop iterations executed by a single call to
+    /// [`Context::execute`] or [`Context::eval`]
+    instruction_limit: Option<usize>,
+    /// Maximum duration of a single call to [`Context::execute`] or [`Context::eval`]
+    timeout: Option<Duration>,
+    /// Amount of statements and loop iterations executed since the execution started
+    executed: usize,
+    /// Time at which the current execution goes over its timeout
+    deadline: Option<Instant>,
+    /// Has the current execution gone over one of its limits
+    limit_exceeded: bool,
+    /// Capabilities granted to the programs executed by the context. They are not part
+    /// of snapshots, so that restoring one cannot grant capabilities again
+    capabilities: Capabilities,
+    /// Does the context produce the same output on each run of a program
+    deterministic: bool,
+    /// Generator used by the random builtins
+    rng: Rng,
+}
+
+/// State of a [`Context`] at a given point, which it can be restored to. This includes
+/// the variables, functions, types and tests declared in the context, as well as the
+/// state of its typechecker. Side effects of the execution, such as output which was
+/// printed or libraries which were loaded, cannot be rolled back.
+#[derive(Clone)]
+pub struct Snapshot {
+    scope_map: ScopeMap<Var, Rc<FunctionDec>, Rc<TypeDec>>,
+    typechecker: TypeCtx,
+    tests: HashMap<CtxKey, FunctionDec>,
+    mocks: Vec<FunctionDec>,
+    included: HashSet<PathBuf>,
 }
 
 impl Default for Context {
@@ -83,8 +200,11 @@ impl Context {
         let mut ctx = Context {
             path: None,
             args: Vec::new(),
+            input: Box::new(BufReader::new(io::stdin())),
             builtins: Builtins::new(),
             tests: HashMap::new(),
+            mocks: Vec::new(),
+            mocked: None,
             included: HashSet::new(),
             #[cfg(feature = "ffi")]
             external_libs: Vec::new(),
@@ -94,6 +214,21 @@ impl Context {
             code: None,
             entry_point: Self::new_entry(),
             error_handler: ErrorHandler::default(),
+            interrupt: None,
+            exit_code: None,
+            recursion_limit: DEFAULT_RECURSION_LIMIT,
+            calls: vec![],
+            trace_depth: 0,
+            debug_hook: None,
+            profiler: None,
+            instruction_limit: None,
+            timeout: None,
+            executed: 0,
+            deadline: None,
+            limit_exceeded: false,
+            capabilities: Capabilities::default(),
+            deterministic: false,
+            rng: Rng::from_entropy(),
         };
 
         ctx.scope_enter();
@@ -106,6 +241,24 @@ impl Context {
         ctx
     }
 
+    /// Create a new context without the standard library. This is equivalent to
+    /// [`Context::new`], as contexts never include the standard library until
+    /// [`Context::init_stdlib`] is called: Embedders which only expose their own
+    /// builtins can use this to make it explicit
+    ///
+    /// ```
+    /// use jinko::context::Context;
+    ///
+    /// let mut ctx = Context::new_without_stdlib();
+    /// assert!(ctx.eval("max(1, 2)").is_err());
+    ///
+    /// ctx.init_stdlib().unwrap();
+    /// assert!(ctx.eval("max(1, 2)").is_ok());
+    /// ```
+    pub fn new_without_stdlib() -> Context {
+        Context::new()
+    }
+
     /// Get a reference to a context's source path
     pub fn path(&self) -> Option<&PathBuf> {
         self.path.as_ref()
@@ -121,6 +274,92 @@ impl Context {
         self.args = args;
     }
 
+    /// Get the input read by the program
+    pub fn input(&mut self) -> &mut dyn BufRead {
+        &mut *self.input
+    }
+
+    /// Set the input read by the program. This allows the REPL to keep reading its
+    /// commands from the terminal while a program is reading its own input
+    pub fn set_input(&mut self, input: Box<dyn BufRead>) {
+        self.input = input;
+    }
+
+    /// Set the maximum depth of nested function calls. Calls going over the limit
+    /// fail instead of overflowing the stack of the interpreter
+    pub fn set_recursion_limit(&mut self, limit: usize) {
+        self.recursion_limit = limit;
+    }
+
+    /// Get the maximum depth of nested function calls
+    pub fn recursion_limit(&self) -> usize {
+        self.recursion_limit
+    }
+
+    /// Set the maximum amount of statements and loop iterations executed each time
+    /// the context is executed, evaluates code or runs a test. Going over the limit stops the
+    /// execution with an [`ErrKind::Limit`] error. This allows executing untrusted
+    /// code, which could otherwise never terminate
+    ///
+    /// ```
+    /// # use jinko::context::Context;
+    /// # use jinko::error::ErrKind;
+    /// let mut ctx = Context::new();
+    /// ctx.set_instruction_limit(1000);
+    ///
+    /// let err = ctx.eval("mut i = 0; while true { i = i + 1 }").unwrap_err();
+    /// assert_eq!(err.kind(), ErrKind::Limit);
+    /// ```
+    pub fn set_instruction_limit(&mut self, limit: usize) {
+        self.instruction_limit = Some(limit);
+    }
+
+    /// Set the maximum duration of each execution of the context or evaluation of
+    /// code. Going over the timeout stops the execution with an [`ErrKind::Limit`]
+    /// error. Calls to external functions cannot be interrupted, and are only
+    /// accounted for once they return
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = Some(timeout);
+    }
+
+    /// Start measuring the execution against the limits of the context
+    fn reset_limits(&mut self) {
+        self.executed = 0;
+        self.deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        self.limit_exceeded = false;
+    }
+
+    /// Account for the execution of a statement or of a loop iteration. Returns false
+    /// if the execution went over one of the limits of the context and must stop, in
+    /// which case an error is reported the first time
+    pub(crate) fn check_limits(&mut self, location: Option<&SpanTuple>) -> bool {
+        if self.limit_exceeded {
+            return false;
+        }
+
+        self.executed += 1;
+
+        let msg = match (self.instruction_limit, self.deadline) {
+            (Some(limit), _) if self.executed > limit => {
+                format!("execution went over its limit of {} instructions", limit)
+            }
+            (_, Some(deadline)) if Instant::now() >= deadline => format!(
+                "execution went over its timeout of {}ms",
+                self.timeout.unwrap_or_default().as_millis()
+            ),
+            _ => return true,
+        };
+
+        self.limit_exceeded = true;
+        self.error(
+            Error::new(ErrKind::Limit)
+                .with_msg(msg)
+                .with_loc(location.cloned()),
+        );
+
+        false
+    }
+
     /// Set the source code that the context should refer to
     pub fn set_code(&mut self, code: String) {
         self.code = Some(code)
@@ -136,20 +375,31 @@ impl Context {
         self.error_handler
             .set_path(self.path.clone().unwrap_or_default());
 
-        match &self.path {
-            Some(p) => {
-                self.included.insert(p.clone());
-            }
-            None => {}
-        };
+        if let Some(p) = &self.path {
+            self.included.insert(p.clone());
+        }
+    }
+
+    /// Set the project that the executed sources are part of. The entry point of the
+    /// project still needs to be parsed in the context
+    pub fn set_project(&mut self, project: Option<Project>) {
+        self.typechecker.set_project(project);
     }
 
     /// Add an error to the context
     pub fn error(&mut self, err: Error) {
+        // Errors happening inside of function calls carry the calls leading to them
+        let err = match err.kind() {
+            ErrKind::Warning | ErrKind::Hint => err,
+            _ if self.calls.is_empty() || !err.trace().is_empty() => err,
+            _ => err.with_trace(self.calls.clone()),
+        };
+
         self.error_handler.add(err)
     }
 
-    /// Emit all the errors currently kept in the context and remove them
+    /// Emit all the errors currently kept in the context which have not been emitted
+    /// yet
     pub fn emit_errors(&mut self) {
         self.error_handler.emit();
     }
@@ -159,6 +409,63 @@ impl Context {
         self.error_handler.clear();
     }
 
+    /// Treat warnings as errors, which prevents the program from being executed if
+    /// any warning is emitted
+    pub fn set_deny_warnings(&mut self, deny_warnings: bool) {
+        self.error_handler.set_deny_warnings(deny_warnings);
+    }
+
+    /// Set the format in which the errors of the context are emitted
+    pub fn set_error_format(&mut self, format: ErrorFormat) {
+        self.error_handler.set_format(format);
+    }
+
+    /// Make the output of the programs executed by the context reproducible, so that
+    /// it can be compared to an expected output. In deterministic mode, the random
+    /// builtins draw the same sequence of numbers on each run, the clock builtins
+    /// always return the UNIX epoch, and scopes are dumped in alphabetical order
+    ///
+    /// ```
+    /// # use jinko::context::Context;
+    /// let mut ctx = Context::new();
+    /// ctx.init_stdlib().unwrap();
+    ///
+    /// ctx.set_deterministic(true);
+    /// let first = ctx.eval("random()").unwrap();
+    ///
+    /// ctx.set_deterministic(true);
+    /// assert_eq!(ctx.eval("random()").unwrap(), first);
+    /// assert_eq!(ctx.eval("clock_ms()").unwrap(), ctx.eval("0").unwrap());
+    /// ```
+    pub fn set_deterministic(&mut self, deterministic: bool) {
+        self.deterministic = deterministic;
+        self.rng = match deterministic {
+            true => Rng::from_seed(DETERMINISTIC_SEED),
+            false => Rng::from_entropy(),
+        };
+    }
+
+    /// Is the context in deterministic mode
+    pub fn is_deterministic(&self) -> bool {
+        self.deterministic
+    }
+
+    /// Get the generator used by the random builtins
+    pub(crate) fn rng(&mut self) -> &mut Rng {
+        &mut self.rng
+    }
+
+    /// Time elapsed since the UNIX epoch, as seen by the clock builtins. The clock is
+    /// frozen at the epoch in deterministic mode
+    pub(crate) fn clock(&self) -> Duration {
+        match self.deterministic {
+            true => Duration::ZERO,
+            false => SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default(),
+        }
+    }
+
     /// Add a function to the context. Returns `Ok` if the function was added, `Err`
     /// if it existed already and was not.
     pub fn add_function(&mut self, function: FunctionDec) -> Result<(), Error> {
@@ -179,9 +486,19 @@ impl Context {
             .add_type(custom_type.name().to_owned(), Rc::new(custom_type))
     }
 
+    /// Get the name of the variable a name refers to. Variables of included sources
+    /// are aliased by their prefixed name, such as `lib::counter` for the `counter`
+    /// variable of `lib.jk`: Both names refer to the same variable
+    fn variable_name<'ctx>(&'ctx self, name: &'ctx str) -> &'ctx str {
+        self.symbols()
+            .get(name)
+            .map_or(name, |symbol| symbol.name())
+    }
+
     /// Replace a variable or create it if it does not exist
     pub fn replace_variable(&mut self, var: Var) -> Result<(), Error> {
-        match self.scope_map.get_variable_mut(var.name()) {
+        let name = self.variable_name(var.name()).to_owned();
+        match self.scope_map.get_variable_mut(&name) {
             None => self.add_variable(var)?,
             Some(var_ref) => var_ref.set_instance(var.instance()),
         }
@@ -196,7 +513,31 @@ impl Context {
 
     /// Get a reference on an existing variable
     pub fn get_variable(&self, name: &str) -> Option<&Var> {
-        self.scope_map.get_variable(name)
+        self.scope_map.get_variable(self.variable_name(name))
+    }
+
+    /// Build the environment captured by a closure when it is created: Each of the
+    /// given variables is copied, and cannot be mutated from within the closure
+    pub fn capture(&self, names: &[String]) -> Result<Vec<Var>, Error> {
+        names
+            .iter()
+            .map(|name| match self.get_variable(name) {
+                Some(var) => {
+                    let mut captured = var.clone();
+                    captured.set_mutable(false);
+                    Ok(captured)
+                }
+                None => Err(Error::new(ErrKind::Context)
+                    .with_msg(format!("cannot capture undeclared variable `{}`", name))),
+            })
+            .collect()
+    }
+
+    /// Is a variable declared in the current scope, as opposed to an enclosing one
+    pub fn is_variable_in_current_scope(&self, name: &str) -> bool {
+        self.scope_map
+            .get_variable_in_current_scope(self.variable_name(name))
+            .is_some()
     }
 
     /// Get a reference on an existing type
@@ -214,9 +555,126 @@ impl Context {
         self.scope_map.scope_exit()
     }
 
+    /// Create a new frame, in which the variables of the current one are not visible.
+    /// This is used when calling functions
+    pub fn frame_enter(&mut self) {
+        self.scope_map.frame_enter()
+    }
+
+    /// Exit the latest created frame
+    pub fn frame_exit(&mut self) {
+        self.scope_map.frame_exit()
+    }
+
+    /// Register the start of a call to the function `name` made at the given location.
+    /// This fails if the call would go over the recursion limit, in which case the
+    /// call must not be executed
+    pub(crate) fn call_enter(
+        &mut self,
+        name: &str,
+        location: Option<&SpanTuple>,
+    ) -> Result<(), Error> {
+        if self.calls.len() >= self.recursion_limit {
+            return Err(Error::new(ErrKind::Context)
+                .with_msg(String::from("maximum recursion depth exceeded"))
+                .with_hint(Error::hint().with_msg(format!(
+                    "the limit is {} nested calls",
+                    self.recursion_limit
+                ))));
+        }
+
+        // Calls are displayed the way the function is declared, even when it is
+        // called through a prefix, as a method or as a specialized generic function
+        let frame = match self.symbols().get(name) {
+            Some(symbol) => Frame::new(
+                FunctionDec::display_name(symbol.name()),
+                Some(symbol.module().to_owned()),
+                location.cloned(),
+            ),
+            None => Frame::new(FunctionDec::display_name(name), None, location.cloned()),
+        };
+        self.calls.push(frame);
+
+        Ok(())
+    }
+
+    /// Register the end of a function call started with [`Context::call_enter`]
+    pub(crate) fn call_exit(&mut self) {
+        self.calls.pop();
+    }
+
+    /// Location of the innermost function call currently being executed, if any.
+    /// Calls to external functions are not taken into account
+    pub fn call_location(&self) -> Option<&SpanTuple> {
+        self.calls.last().and_then(Frame::loc)
+    }
+
+    /// Start recording the function calls made by the program and the time spent
+    /// executing them
+    pub fn enable_profiler(&mut self) {
+        self.profiler = Some(Profiler::new());
+    }
+
+    /// Get the measures taken by the profiler, if it was enabled
+    pub fn profiler(&self) -> Option<&Profiler> {
+        self.profiler.as_ref()
+    }
+
+    /// Take the profiler out of the context, stopping the profiling
+    pub fn take_profiler(&mut self) -> Option<Profiler> {
+        self.profiler.take()
+    }
+
+    /// Register the start of a call to a function with the profiler, if enabled
+    pub(crate) fn profile_enter(&mut self, name: &str) {
+        if let Some(profiler) = &mut self.profiler {
+            profiler.enter(name);
+        }
+    }
+
+    /// Register the end of the innermost call registered with
+    /// [`Context::profile_enter`]
+    pub(crate) fn profile_exit(&mut self) {
+        if let Some(profiler) = &mut self.profiler {
+            profiler.exit();
+        }
+    }
+
+    /// Amount of function calls currently being executed
+    pub fn call_depth(&self) -> usize {
+        self.calls.len()
+    }
+
+    /// Get the original names of the symbols declared under a prefix by inclusions,
+    /// which diagnostics display instead of their prefixed names
+    pub fn symbols(&self) -> &SymbolMap {
+        self.typechecker.symbols()
+    }
+
+    /// Get a reference on the scopes of the context, containing all the variables,
+    /// functions and types currently declared
+    pub fn scope_map(&self) -> &ScopeMap<Var, Rc<FunctionDec>, Rc<TypeDec>> {
+        &self.scope_map
+    }
+
+    /// Print the types, variables and functions declared in the scopes of the
+    /// context. They are sorted by name in deterministic mode, and printed in an
+    /// unspecified order otherwise
+    pub fn dump_scopes(&self) -> String {
+        match self.deterministic {
+            true => self
+                .scope_map
+                .scopes()
+                .iter()
+                .map(|scope| format!("{}\n", scope.print_sorted()))
+                .collect(),
+            false => self.scope_map.to_string(),
+        }
+    }
+
     /// Pretty-prints valid jinko code from a given ctx
     pub fn print(&self) -> String {
-        let mut s = format!("{}\n", self.scope_map);
+        let mut s = format!("{}\n", self.dump_scopes());
         s = format!("{}{}", s, self.entry_point.print());
 
         s
@@ -232,6 +690,69 @@ impl Context {
         }
     }
 
+    /// Enable or disable the debug mode of the context. In debug mode, the execution
+    /// of the program is traced on the standard error: Each instruction is displayed
+    /// before getting executed, as well as the values of the variables read or
+    /// written to
+    pub fn set_debug(&mut self, debug: bool) {
+        self.debug_mode = debug;
+    }
+
+    /// Trace a step of the execution if the context is in debug mode. Traces are
+    /// indented according to the depth of the scope they happen in. The message is
+    /// only built when tracing, as printing instructions can be costly
+    pub fn trace<F: FnOnce() -> String>(&self, msg: F) {
+        if self.debug_mode {
+            let depth = self
+                .scope_map
+                .scopes()
+                .len()
+                .saturating_sub(self.trace_depth);
+
+            eprintln!("{} {}{}", "trace:".purple(), "    ".repeat(depth), msg());
+        }
+    }
+
+    /// Trace the execution of an instruction. Only the first line of instructions
+    /// containing blocks is displayed, as the instructions of the blocks are traced
+    /// when they get executed
+    pub fn trace_instruction(&self, instruction: &dyn Instruction) {
+        self.trace(|| {
+            let print = instruction.print();
+
+            match print.lines().nth(1) {
+                Some(_) => format!("{} ...", print.lines().next().unwrap_or_default()),
+                None => print,
+            }
+        })
+    }
+
+    /// Set the hook called before executing each statement of the program, which
+    /// debuggers use to pause the execution
+    pub fn set_debug_hook(&mut self, hook: Box<dyn DebugHook>) {
+        self.debug_hook = Some(hook);
+    }
+
+    /// Steps taken before executing a statement: Checking the limits of the context,
+    /// tracing it, and calling the debug hook if there is one. Returns false if the
+    /// statement must not be executed, as the execution went over its limits
+    pub(crate) fn before_instruction(&mut self, instruction: &dyn Instruction) -> bool {
+        if !self.check_limits(instruction.location()) {
+            return false;
+        }
+
+        self.trace_instruction(instruction);
+
+        // The hook is taken out of the context while it runs, as it needs to access
+        // the context itself
+        if let Some(mut hook) = self.debug_hook.take() {
+            hook.before_instruction(self, instruction);
+            self.debug_hook = Some(hook);
+        }
+
+        true
+    }
+
     /// Print a debugging step if the context is in debug mode, according to the
     /// following format:
     ///
@@ -259,6 +780,47 @@ impl Context {
         }
     }
 
+    /// Register a mock in the context. A mock declared in a test replaces the
+    /// function until the end of that test, while mocks declared outside of tests
+    /// replace their function during each test ran by the context
+    pub fn add_mock(&mut self, mock: FunctionDec) -> Result<(), Error> {
+        if self.mocked.is_some() {
+            return self.install_mock(mock);
+        }
+
+        match self.mocks.iter().find(|m| m.name() == mock.name()) {
+            Some(previous) => Err(Error::new(ErrKind::Context)
+                .with_msg(format!("mock already declared: {}", mock.name()))
+                .with_loc(mock.loc())
+                .with_hint(
+                    Error::hint()
+                        .with_msg(String::from("previous declaration here"))
+                        .with_loc(previous.loc()),
+                )),
+            None => {
+                self.mocks.push(mock);
+                Ok(())
+            }
+        }
+    }
+
+    /// Replace the mocked function with the mock, and keep the original declaration
+    /// so that it can be restored at the end of the current test
+    fn install_mock(&mut self, mock: FunctionDec) -> Result<(), Error> {
+        let name = mock.name().to_owned();
+        let loc = mock.loc();
+
+        match self.scope_map.replace_function(&name, Rc::new(mock)) {
+            Some(original) => {
+                self.mocked.get_or_insert_with(Vec::new).push(original);
+                Ok(())
+            }
+            None => Err(Error::new(ErrKind::Context)
+                .with_msg(format!("cannot mock undeclared function `{}`", name))
+                .with_loc(loc)),
+        }
+    }
+
     /// Check if a source is included or not
     pub fn is_included(&self, source: &Path) -> bool {
         self.included.contains(source)
@@ -280,14 +842,154 @@ impl Context {
         }
     }
 
-    fn inner_check(&mut self, ep: &mut Block) -> Result<(), Error> {
+    /// Typecheck a single expression without executing it, and return its type. The
+    /// declarations made by the expression are discarded.
+    pub fn type_of_input(&mut self, input: &str) -> Result<CheckedType, Error> {
+        let (remaining, mut instruction) =
+            parser::constructs::expr(parser::ParseInput::new_extra(input, None))?;
+
+        if !remaining.trim().is_empty() {
+            return Err(Error::new(ErrKind::Parsing).with_msg(format!(
+                "unexpected input after expression: `{}`",
+                remaining
+            )));
+        }
+
+        self.typechecker.scope_enter();
+        let ty = self.type_check(&mut *instruction);
+        self.typechecker.scope_exit();
+
+        ty
+    }
+
+    /// Is an instruction allowed at the top level of a program declaring a `main`
+    /// function
+    fn is_declaration(instruction: &dyn Instruction) -> bool {
+        instruction.is::<FunctionDec>()
+            || instruction.is::<TypeDec>()
+            || instruction.is::<InterfaceDec>()
+            || instruction.is::<InterfaceImpl>()
+            || instruction.is::<Incl>()
+            || instruction.is::<VarAssign>()
+    }
+
+    /// If the entry point declares a `main` function, check that its signature is
+    /// valid and that the rest of the top-level code only contains declarations. A
+    /// call to `main` is then added at the end of the entry point, so that its return
+    /// value becomes the result of the program
+    fn add_main_call(&mut self, ep: &mut Block) {
+        let main = ep
+            .instructions()
+            .iter()
+            .filter_map(|instr| instr.downcast_ref::<FunctionDec>())
+            .find(|dec| dec.name() == MAIN_NAME && dec.fn_kind() == FunctionKind::Func);
+        let main = match main {
+            Some(main) => main,
+            None => return,
+        };
+
+        let args_ty = TypeId::from("Vec").with_generic(TypeId::from("string"));
+        let takes_args = match main.args().as_slice() {
+            [] => Some(false),
+            [arg] if arg.get_type() == &args_ty => Some(true),
+            _ => None,
+        };
+
+        if takes_args.is_none() || main.ty() != Some(&TypeId::from("int")) {
+            self.error(
+                Error::new(ErrKind::Context)
+                    .with_msg(format!("invalid signature for `{}` function", MAIN_NAME))
+                    .with_loc(main.loc())
+                    .with_hint(Error::hint().with_msg(format!(
+                        "expected `func {0}() -> int` or `func {0}(args: {1}) -> int`",
+                        MAIN_NAME,
+                        args_ty.print()
+                    ))),
+            );
+            return;
+        }
+
+        let main_loc = main.loc();
+        let top_level_code: Vec<Option<SpanTuple>> = ep
+            .instructions()
+            .iter()
+            .filter(|instr| !Context::is_declaration(&***instr))
+            .map(|instr| instr.location().cloned())
+            .collect();
+        for loc in top_level_code {
+            self.error(
+                Error::new(ErrKind::Context)
+                    .with_msg(format!(
+                        "top-level code is not allowed in a program declaring a `{}` function",
+                        MAIN_NAME
+                    ))
+                    .with_loc(loc)
+                    .with_hint(
+                        Error::hint()
+                            .with_msg(format!("move this code to the `{}` function", MAIN_NAME))
+                            .with_loc(main_loc.clone()),
+                    ),
+            );
+        }
+
+        let args: Vec<Box<dyn Instruction>> = match takes_args {
+            Some(true) => vec![Box::new(FunctionCall::new(
+                String::from("to_vec"),
+                vec![],
+                vec![Box::new(VarOrEmptyType::new(String::from("Args")))],
+            ))],
+            _ => vec![],
+        };
+
+        ep.add_instruction(Box::new(FunctionCall::new(
+            String::from(MAIN_NAME),
+            vec![],
+            args,
+        )));
+    }
+
+    /// Emit a warning for each function declared in the entry point which is never
+    /// called or referenced. The `main` function and functions starting with an
+    /// underscore are ignored
+    fn warn_unused_functions(&mut self, ep: &Block) {
+        let unused: Vec<Error> = ep
+            .instructions()
+            .iter()
+            .filter_map(|instr| instr.downcast_ref::<FunctionDec>())
+            .filter(|dec| dec.fn_kind() == FunctionKind::Func)
+            .filter(|dec| dec.name() != MAIN_NAME && !dec.name().starts_with('_'))
+            .filter(|dec| !self.typechecker.is_function_used(dec.name()))
+            .map(|dec| {
+                Error::new(ErrKind::Warning)
+                    .with_msg(format!("function `{}` is never used", dec.name()))
+                    .with_loc(dec.loc())
+            })
+            .collect();
+
+        unused.into_iter().for_each(|warning| self.error(warning));
+    }
+
+    fn inner_check(&mut self, ep: &mut Block, unused: UnusedDeclarations) -> Result<(), Error> {
+        self.add_main_call(ep);
         self.scope_enter();
 
-        ep.type_of(&mut self.typechecker);
+        // The instructions of the entry point are typechecked in the global scope, so
+        // that the following calls to `eval` can use their declarations
+        self.typechecker.hoist_declarations(ep.instructions_mut());
+        ep.instructions_mut().iter_mut().for_each(|inst| {
+            inst.type_of(&mut self.typechecker);
+        });
+
+        match unused {
+            UnusedDeclarations::Allowed => {}
+            UnusedDeclarations::Functions => self.warn_unused_functions(ep),
+            UnusedDeclarations::Unreachable => reachability::unreachable_declarations(ep)
+                .into_iter()
+                .for_each(|warning| self.error(warning)),
+        }
 
         self.error_handler
             .append(&mut self.typechecker.error_handler);
-        self.emit_errors();
 
         let new_nodes = self.typechecker.take_specialized_nodes();
         new_nodes.into_iter().for_each(|node| {
@@ -305,59 +1007,399 @@ impl Context {
         }
     }
 
-    pub fn check(&mut self) -> Result<(), Error> {
+    /// Is an instruction a declaration executed before the other instructions of a
+    /// source, so that it can be used before being declared
+    pub(crate) fn is_hoisted(instruction: &dyn Instruction) -> bool {
+        match instruction.downcast_ref::<FunctionDec>() {
+            Some(dec) => matches!(dec.fn_kind(), FunctionKind::Func | FunctionKind::Ext),
+            None => instruction.is::<Incl>() || instruction.is::<TypeDec>(),
+        }
+    }
+
+    /// Execute the inclusions, type and function declarations of a source, before its
+    /// other instructions
+    pub(crate) fn execute_declarations(&mut self, instructions: &[Box<dyn Instruction>]) {
+        instructions
+            .iter()
+            .filter(|instr| Context::is_hoisted(&***instr))
+            .for_each(|instr| {
+                instr.execute(self);
+            });
+    }
+
+    /// Typecheck the whole program without executing it. Function calls, type
+    /// instantiations and variable uses are all resolved, and every error found is
+    /// reported at once rather than stopping at the first one.
+    pub fn typecheck(&mut self) -> Result<(), Error> {
         // The entry point always has a block
         let mut ep = self.entry_point.block().unwrap().clone();
-        self.inner_check(&mut ep)
+        self.inner_check(&mut ep, UnusedDeclarations::Unreachable)
     }
 
+    /// Typecheck and execute the program parsed in the context, and return its value.
+    /// This is the value of its last top-level expression, or the value returned by
+    /// its `main` function or by a top-level `return`. Programs ending with a
+    /// statement, or which called `exit()`, have no value: See [`Context::exit_code`]
+    /// for the latter.
+    ///
+    /// ```
+    /// use jinko::context::Context;
+    /// use jinko::instance::FromObjectInstance;
+    /// use jinko::value::JkInt;
+    ///
+    /// let mut ctx = Context::new_without_stdlib();
+    /// jinko::parse(&mut ctx, "func twice(x: int) -> int { x * 2 } twice(21)", None).unwrap();
+    ///
+    /// let value = ctx.execute().unwrap().unwrap();
+    /// assert_eq!(JkInt::from_instance(&value).rust_value(), 42);
+    /// ```
     pub fn execute(&mut self) -> Result<Option<ObjectInstance>, Error> {
+        let res = self.execute_entry_point(UnusedDeclarations::Functions);
+
+        // The program has been executed: Following calls to `eval` must not check and
+        // execute its instructions again
+        self.entry_point = Context::new_entry();
+
+        res
+    }
+
+    fn execute_entry_point(
+        &mut self,
+        unused: UnusedDeclarations,
+    ) -> Result<Option<ObjectInstance>, Error> {
         // The entry point always has a block
         let mut ep = self.entry_point.block().unwrap().clone();
-        self.inner_check(&mut ep)?;
+        self.inner_check(&mut ep, unused)?;
 
-        let res = ep
+        self.execute_declarations(ep.instructions());
+
+        self.trace_depth = self.scope_map.scopes().len();
+        self.reset_limits();
+        self.exit_code = None;
+
+        // Declarations were executed already, and do not change the value of the
+        // program
+        let instructions = ep
             .instructions()
             .iter()
-            .map(|inst| inst.execute(self))
-            .last()
-            .flatten();
+            .filter(|inst| !Context::is_hoisted(&***inst));
 
-        self.emit_errors();
+        let mut res = None;
+        for inst in instructions {
+            if !self.before_instruction(&**inst) {
+                break;
+            }
 
-        match self.error_handler.has_errors() {
-            true => Err(Error::new(ErrKind::Context)),
-            false => Ok(res),
+            res = inst.execute(self);
+
+            match self.take_interrupt() {
+                // Returning from the entry point stops the program
+                Some(Interrupt::Return(value)) => {
+                    res = value;
+                    break;
+                }
+                Some(Interrupt::Exit(code)) => {
+                    self.exit_code = Some(code);
+                    res = None;
+                    break;
+                }
+                _ => {}
+            }
+        }
+
+        match (self.error_handler.has_errors(), self.limit_exceeded) {
+            (true, true) => Err(Error::new(ErrKind::Limit)),
+            (true, false) => Err(Error::new(ErrKind::Context)),
+            (false, _) => Ok(res),
         }
     }
 
+    /// Format some jinko source code in a canonical style, indenting blocks and
+    /// normalizing spacing and line breaks. Comments are kept. This fails if the
+    /// source code cannot be parsed, in which case all the syntax errors are returned.
+    ///
+    /// ```
+    /// use jinko::context::Context;
+    ///
+    /// let formatted = Context::format_source("func f() -> int{1+2}").unwrap();
+    /// assert_eq!(formatted, "func f() -> int {\n    1 + 2\n}\n");
+    /// ```
+    pub fn format_source(input: &str) -> Result<String, Vec<Error>> {
+        let instructions = parser::parse_instructions(input)?;
+
+        Ok(crate::format::format(input, &instructions))
+    }
+
+    /// Dump the instructions parsed in the context as JSON, for external tooling. The
+    /// result is an array containing a node for each top-level instruction. The shape
+    /// of the nodes is described in the [`crate::ast`] module.
+    ///
+    /// ```
+    /// use jinko::context::Context;
+    ///
+    /// let mut ctx = Context::new();
+    /// jinko::parse(&mut ctx, "x = 15", None).unwrap();
+    ///
+    /// assert!(ctx.ast_json().starts_with(r#"[{"kind":"VarAssign""#));
+    /// ```
+    pub fn ast_json(&self) -> String {
+        // The entry point always has a block
+        let nodes: Vec<AstNode> = self
+            .entry_point
+            .block()
+            .unwrap()
+            .instructions()
+            .iter()
+            .map(|inst| inst.ast())
+            .collect();
+
+        Json::from(nodes).to_string()
+    }
+
+    /// Parse, typecheck and execute a snippet of jinko code in the context, and
+    /// return the value of its last expression. Declarations made by the snippet are
+    /// kept in the context and can be used by the following calls to `eval`.
+    ///
+    /// Instructions which were added to the context before the call, such as the
+    /// inclusion of the standard library, are executed first.
     pub fn eval(&mut self, input: &str) -> Result<Option<ObjectInstance>, Error> {
+        self.clear_errors();
+
+        // Functions declared by the snippet may be used by the following ones, so they
+        // are not reported as unused
+        let res = parser::parse(self, input, None)
+            .and_then(|_| self.execute_entry_point(UnusedDeclarations::Allowed));
+
+        self.entry_point = Context::new_entry();
+
+        res
+    }
+
+    /// Take a snapshot of the declarations of the context, in order to roll them back
+    /// later on. This is useful to discard the partial effects of code which failed
+    /// halfway, or to try evaluating code without altering the context.
+    ///
+    /// ```
+    /// # use jinko::context::Context;
+    /// let mut ctx = Context::new();
+    /// let snapshot = ctx.snapshot();
+    ///
+    /// assert!(ctx.eval("a = 1; b = a + undeclared;").is_err());
+    /// ctx.restore(snapshot);
+    ///
+    /// // `a` was never declared: It can now be declared with a different type
+    /// assert!(ctx.eval("a = \"jinko\";").is_ok());
+    /// ```
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            scope_map: self.scope_map.clone(),
+            typechecker: self.typechecker.clone(),
+            tests: self.tests.clone(),
+            mocks: self.mocks.clone(),
+            included: self.included.clone(),
+        }
+    }
+
+    /// Restore the declarations of the context to the state they were in when a
+    /// snapshot was taken. Errors kept in the context are left untouched
+    pub fn restore(&mut self, snapshot: Snapshot) {
+        self.scope_map = snapshot.scope_map;
+        self.typechecker = snapshot.typechecker;
+        // The typechecker of the snapshot might have been granted other capabilities
+        self.typechecker.set_capabilities(self.capabilities);
+        self.tests = snapshot.tests;
+        self.mocks = snapshot.mocks;
+        self.included = snapshot.included;
+    }
+
+    /// Execute a snippet of jinko code multiple times and measure the duration of each
+    /// run, in order to track the performance of the interpreter. The snippet is only
+    /// parsed once, while it is typechecked and executed on each run. Every run starts
+    /// from the current state of the context: The declarations made by the snippet are
+    /// rolled back after each of them, as with [`Context::restore`]. Pending
+    /// instructions, such as the inclusion of the standard library, need to be
+    /// executed beforehand if they should not be measured.
+    ///
+    /// ```
+    /// use jinko::context::Context;
+    ///
+    /// let mut ctx = Context::new();
+    /// let stats = ctx.run_repeatedly("func f() -> int { 15 } f()", 3).unwrap();
+    ///
+    /// assert_eq!(stats.runs(), 3);
+    /// assert!(ctx.get_function("f").is_none());
+    /// ```
+    pub fn run_repeatedly(&mut self, input: &str, runs: usize) -> Result<RunStats, Error> {
+        self.clear_errors();
+
+        let parsed = parser::parse(self, input, None);
+        let snapshot = self.snapshot();
+
+        let mut stats = RunStats::default();
+        let res = parsed.and_then(|_| {
+            (0..runs).try_for_each(|_| {
+                let start = Instant::now();
+                let res = self.execute_entry_point(UnusedDeclarations::Allowed);
+                stats.add(start.elapsed());
+
+                self.restore(snapshot.clone());
+
+                res.map(|_| ())
+            })
+        });
+
         self.entry_point = Context::new_entry();
 
-        parser::parse(self, input, None)?;
+        res.map(|_| stats)
+    }
 
-        self.execute()
+    /// Get the value of a variable declared in the context, converted to a Rust type.
+    /// The caller is responsible for requesting the type the variable was declared
+    /// with, for example `JkInt` for a variable of type `int`.
+    pub fn get_value<T: FromObjectInstance>(&self, name: &str) -> Option<T> {
+        self.get_variable(name)
+            .map(|var| T::from_instance(&var.instance()))
     }
 
     pub fn has_errors(&self) -> bool {
         self.error_handler.has_errors()
     }
 
+    /// Raise an interrupt, stopping the execution of the current blocks
+    pub fn interrupt(&mut self, interrupt: Interrupt) {
+        self.interrupt = Some(interrupt)
+    }
+
+    /// Is an interrupt currently being propagated
+    pub fn is_interrupted(&self) -> bool {
+        self.interrupt.is_some()
+    }
+
+    /// Catch the interrupt currently being propagated, if any
+    pub fn take_interrupt(&mut self) -> Option<Interrupt> {
+        self.interrupt.take()
+    }
+
+    /// Exit code requested by the last execution of the context, if the program
+    /// called `exit()`. The context never exits the process by itself: It is up to the
+    /// caller to decide what to do with the exit code.
+    ///
+    /// ```
+    /// use jinko::context::Context;
+    ///
+    /// let mut ctx = Context::new_without_stdlib();
+    /// ctx.eval("ext func __builtin_exit(code: int); __builtin_exit(3); 15").unwrap();
+    ///
+    /// assert_eq!(ctx.exit_code(), Some(3));
+    /// ```
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+
+    /// Register a Rust function as a builtin, which can then be called from jinko code
+    /// once declared as an external function: `ext func name(arg: int) -> int;`. The
+    /// builtin is only called with arguments of the given types. Returns `Err` if a
+    /// builtin with the same name already exists.
+    pub fn add_builtin(
+        &mut self,
+        name: &str,
+        args: &[ArgType],
+        builtin: impl Fn(&mut Context, &Args) -> Option<ObjectInstance> + 'static,
+    ) -> Result<(), Error> {
+        match self.builtins.contains(name) {
+            true => Err(Error::new(ErrKind::Context)
+                .with_msg(format!("builtin already declared: {}", name))),
+            false => {
+                self.builtins.add(name, args, builtin);
+                Ok(())
+            }
+        }
+    }
+
+    /// Register a Rust function taking and returning primitive types as a builtin.
+    /// Its arguments and return value are converted automatically, and the function
+    /// is declared in the context: It can be called directly from jinko code.
+    pub fn register_builtin<Marker, F: NativeFunction<Marker>>(
+        &mut self,
+        name: &str,
+        builtin: F,
+    ) -> Result<(), Error> {
+        let dec = F::declaration(name);
+        let builtin = builtin.into_builtin();
+
+        self.add_builtin(name, &F::arg_types(), move |ctx, args: &Args| {
+            builtin(ctx, args)
+        })?;
+        self.entry_point.add_instruction(Box::new(dec))
+    }
+
     pub fn is_builtin(&self, name: &str) -> bool {
         self.builtins.contains(name)
     }
 
+    /// Call a builtin with arguments which have already been evaluated
     pub fn call_builtin(
         &mut self,
         builtin: &str,
-        args: Vec<Box<dyn Instruction>>,
+        args: &[ObjectInstance],
     ) -> Result<Option<ObjectInstance>, Error> {
-        match self.builtins.get(builtin) {
+        if let Some(capability) = self.builtins.capability(builtin) {
+            self.check_capability(capability, builtin)?;
+        }
+
+        if let Some(signature) = self.builtins.signature(builtin) {
+            signature
+                .check(builtin, args)
+                .map_err(|e| e.with_loc(self.call_location().cloned()))?;
+        }
+
+        match self.builtins.get(builtin).cloned() {
             Some(f) => Ok(f(self, args)),
             None => Err(Error::new(ErrKind::Context)),
         }
     }
 
+    /// Set the capabilities granted to the programs executed by the context. This
+    /// allows hosts to execute untrusted code without giving it access to the
+    /// filesystem or to shell commands, for example. Without the `io` capability,
+    /// only the standard library can be included
+    ///
+    /// ```
+    /// # use jinko::context::{Capabilities, Context};
+    /// let mut ctx = Context::new();
+    /// ctx.init_stdlib().unwrap();
+    /// ctx.set_capabilities(Capabilities { shell: false, ..Capabilities::all() });
+    ///
+    /// assert!(ctx.eval("shell(\"ls\")").is_err());
+    /// ```
+    pub fn set_capabilities(&mut self, capabilities: Capabilities) {
+        self.capabilities = capabilities;
+        self.typechecker.set_capabilities(capabilities);
+    }
+
+    /// Get the capabilities granted to the programs executed by the context
+    pub fn capabilities(&self) -> Capabilities {
+        self.capabilities
+    }
+
+    /// Check that a capability required by a function is granted by the context
+    pub(crate) fn check_capability(
+        &self,
+        capability: Capability,
+        function: &str,
+    ) -> Result<(), Error> {
+        match self.capabilities().allows(capability) {
+            true => Ok(()),
+            false => Err(Error::new(ErrKind::Context)
+                .with_msg(format!("{} is disabled", capability.description()))
+                .with_loc(self.call_location().cloned())
+                .with_hint(Error::hint().with_msg(format!(
+                    "`{}` requires the `{}` capability",
+                    function, capability
+                )))),
+        }
+    }
+
     /// Add a library to the interpreter
     #[cfg(feature = "ffi")]
     pub fn add_lib(&mut self, lib: libloading::Library) {
@@ -370,11 +1412,85 @@ impl Context {
         &self.external_libs
     }
 
-    /// Includes the standard library in the context
+    /// Get the directories in which the standard library is looked for, in order:
+    ///
+    /// 1. The directory given by the `JINKO_STDLIB` environment variable
+    /// 2. The installation prefix the interpreter was compiled with, if any
+    /// 3. The `stdlib` directory next to the interpreter's executable
+    /// 4. The `stdlib` directory of the current directory
+    /// 5. The user's libraries, where `install.sh` copies the standard library
+    pub fn stdlib_candidates() -> Vec<PathBuf> {
+        let mut candidates = vec![];
+
+        if let Some(path) = std::env::var_os(STDLIB_VAR) {
+            candidates.push(PathBuf::from(path));
+        }
+
+        if let Some(prefix) = INSTALL_PREFIX {
+            candidates.push(PathBuf::from(prefix).join("share/jinko/stdlib"));
+        }
+
+        if let Some(dir) = std::env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(Path::to_owned))
+        {
+            candidates.push(dir.join("stdlib"));
+        }
+
+        candidates.push(PathBuf::from("stdlib"));
+
+        if let Some(home) = std::env::var_os("HOME") {
+            candidates.push(PathBuf::from(home).join(".jinko/libs/stdlib"));
+        }
+
+        candidates
+    }
+
+    /// Find the directory of the standard library among the given candidates. The
+    /// error lists all the directories which were tried
+    pub fn find_stdlib(candidates: &[PathBuf]) -> Result<PathBuf, Error> {
+        candidates
+            .iter()
+            .find(|candidate| candidate.join("lib.jk").is_file())
+            .cloned()
+            .ok_or_else(|| {
+                let tried = candidates
+                    .iter()
+                    .map(|candidate| format!("{:?}", candidate))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+
+                Error::new(ErrKind::Context)
+                    .with_msg(String::from("cannot find the standard library"))
+                    .with_hint(Error::hint().with_msg(format!(
+                        "no `lib.jk` file in any of these directories: {}",
+                        tried
+                    )))
+                    .with_hint(Error::hint().with_msg(format!(
+                        "set `{}` to the directory of the standard library, or use `--no-std`",
+                        STDLIB_VAR
+                    )))
+            })
+    }
+
+    /// Includes the standard library in the context. See
+    /// [`Context::stdlib_candidates`] for the directories it is looked for in
     pub fn init_stdlib(&mut self) -> Result<(), Error> {
-        let mut stdlib_incl =
-            crate::instruction::Incl::new(String::from("stdlib"), Some(String::from("")));
-        stdlib_incl.set_base(PathBuf::new());
+        let mut stdlib = Context::find_stdlib(&Context::stdlib_candidates())?;
+
+        // The standard library is included by the name of its directory, from its
+        // parent. Directories such as `.` need to be resolved to get a name
+        if stdlib.file_name().is_none() {
+            stdlib = stdlib.canonicalize()?;
+        }
+
+        let name = stdlib
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let mut stdlib_incl = crate::instruction::Incl::new(name, Some(String::from("")));
+        stdlib_incl.set_base(stdlib.parent().map(Path::to_owned).unwrap_or_default());
+        self.typechecker.set_stdlib(stdlib);
 
         self.entry_point.add_instruction(Box::new(stdlib_incl))?;
 
@@ -385,6 +1501,47 @@ impl Context {
     pub fn tests(&self) -> &HashMap<CtxKey, FunctionDec> {
         &self.tests
     }
+
+    /// Run the test declared in the context with the given name, and return whether
+    /// it passed. A test fails if an error is raised during its execution, such as a
+    /// failed assertion, in which case the rest of the test is not executed. Mocked
+    /// functions are restored once the test is done. The errors are kept in the
+    /// context so that they can be emitted afterwards. This returns `None` if there
+    /// is no test with the given name.
+    pub fn run_test(&mut self, name: &str) -> Option<bool> {
+        let test = self.tests.get(name)?.clone();
+        let errors = self.error_handler.errors().len();
+
+        self.mocked = Some(vec![]);
+        for mock in self.mocks.clone() {
+            if let Err(e) = self.install_mock(mock) {
+                self.error(e);
+            }
+        }
+
+        // Each test is limited separately
+        self.reset_limits();
+        self.exit_code = None;
+        test.run(self);
+
+        if let Some(Interrupt::Exit(code)) = self.take_interrupt() {
+            self.exit_code = Some(code);
+        }
+
+        // Restore the originals in reverse order, in case a function was mocked
+        // multiple times during the test
+        let originals = self.mocked.take().unwrap_or_default();
+        for original in originals.into_iter().rev() {
+            let name = original.name().to_owned();
+            self.scope_map.replace_function(&name, original);
+        }
+
+        let passed = self.error_handler.errors()[errors..]
+            .iter()
+            .all(|e| e.kind() == ErrKind::Warning);
+
+        Some(passed)
+    }
 }
 
 /// Printer for the context's usage of the ScopeMap
@@ -398,6 +1555,29 @@ impl<V: Instruction, F: Instruction, T: Instruction> Display for ScopeMap<V, Rc<
     }
 }
 
+impl<V: Instruction, F: Instruction, T: Instruction> Scope<V, Rc<F>, Rc<T>> {
+    /// Print the scope like its [`Display`] implementation, with its types, variables
+    /// and functions sorted by name
+    fn print_sorted(&self) -> String {
+        fn sorted<U>(map: &HashMap<String, U>) -> Vec<&U> {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by_key(|(name, _)| *name);
+
+            entries.into_iter().map(|(_, entry)| entry).collect()
+        }
+
+        let types = sorted(&self.types).into_iter().map(|ty| ty.print());
+        let vars = sorted(&self.variables).into_iter().map(|var| var.print());
+        let funcs = sorted(&self.functions).into_iter().map(|func| func.print());
+
+        types
+            .chain(vars)
+            .chain(funcs)
+            .map(|s| format!("{}\n", s))
+            .collect()
+    }
+}
+
 impl<V: Instruction, F: Instruction, T: Instruction> Display for Scope<V, Rc<F>, Rc<T>> {
     fn fmt(&self, f: &mut Formatter) -> FmtResult {
         for ty in self.types.values() {
@@ -419,7 +1599,9 @@ impl<V: Instruction, F: Instruction, T: Instruction> Display for Scope<V, Rc<F>,
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::instance::ToObjectInstance;
     use crate::jinko;
+    use crate::value::{JkBool, JkInt, JkString};
 
     #[test]
     fn t_redefinition_of_function() {
@@ -432,6 +1614,25 @@ mod tests {
         assert!(i.add_function(f0_copy).is_err());
     }
 
+    #[test]
+    fn t_dump_scopes_sorted() {
+        let mut ctx = jinko! {
+            zeta = 1;
+            alpha = 2;
+            func gamma() {}
+            func beta() {}
+        };
+        ctx.set_deterministic(true);
+
+        let dump = ctx.dump_scopes();
+        let position = |s| dump.find(s).unwrap();
+
+        assert!(position("alpha") < position("zeta"));
+        assert!(position("zeta") < position("func beta"));
+        assert!(position("func beta") < position("func gamma"));
+        assert_eq!(dump, ctx.dump_scopes());
+    }
+
     #[test]
     fn t_redefinition_of_variable() {
         let v0 = Var::new("v0".to_owned());
@@ -478,6 +1679,36 @@ mod tests {
         };
     }
 
+    #[test]
+    fn t_builtin_args_evaluated_once() {
+        use std::cell::Cell;
+
+        let evaluations = Rc::new(Cell::new(0));
+        let counter = evaluations.clone();
+
+        let mut ctx = Context::new();
+        ctx.register_builtin("next", move || {
+            counter.set(counter.get() + 1);
+            counter.get()
+        })
+        .unwrap();
+        ctx.add_builtin("sum_twice", &[ArgType::Type("int")], |_, args| {
+            let lhs = JkInt::from_instance(&args[0]).rust_value();
+            let rhs = JkInt::from_instance(&args[0]).rust_value();
+
+            Some(JkInt::from(lhs + rhs).to_instance())
+        })
+        .unwrap();
+
+        let res = ctx
+            .eval("ext func sum_twice(value: int) -> int; sum_twice(next())")
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(JkInt::from_instance(&res).rust_value(), 2);
+        assert_eq!(evaluations.get(), 1);
+    }
+
     #[test]
     fn t_eval() {
         let mut ctx = Context::new();
@@ -504,4 +1735,628 @@ mod tests {
         assert!(output.contains("my_var"));
         assert!(output.contains("my_new_var"));
     }
+
+    #[test]
+    fn t_type_of_input() {
+        let mut ctx = jinko! {
+            func id(x: int) -> int { x }
+            s = "jinko";
+        };
+
+        assert_eq!(
+            ctx.type_of_input("id(15)"),
+            Ok(CheckedType::Resolved(TypeId::from("int")))
+        );
+        assert_eq!(
+            ctx.type_of_input("s"),
+            Ok(CheckedType::Resolved(TypeId::from("string")))
+        );
+        assert!(ctx.type_of_input("id(s)").is_err());
+    }
+
+    #[test]
+    fn t_type_of_input_does_not_declare() {
+        let mut ctx = Context::new();
+
+        ctx.type_of_input("x = 15").unwrap();
+
+        assert!(ctx.eval("x").is_err());
+    }
+
+    #[test]
+    fn t_typecheck_does_not_execute() {
+        let mut ctx = Context::new();
+        crate::jk_parse! {
+            &mut ctx,
+            x = 15;
+        };
+
+        assert!(ctx.typecheck().is_ok());
+        assert!(ctx.get_variable("x").is_none());
+    }
+
+    #[test]
+    fn t_typecheck_reports_all_errors() {
+        let mut ctx = Context::new();
+        crate::jk_parse! {
+            &mut ctx,
+            func id(x: int) -> int { x }
+            a = id("jinko");
+            b = undeclared_function();
+            c = undeclared_variable;
+        };
+
+        assert!(ctx.typecheck().is_err());
+        assert_eq!(ctx.error_handler.errors().len(), 3);
+    }
+
+    #[test]
+    fn t_eval_with_stdlib() {
+        let mut ctx = Context::new();
+        ctx.init_stdlib().unwrap();
+
+        let res = ctx.eval("\"jinko\".len()").unwrap().unwrap();
+
+        assert_eq!(JkInt::from_instance(&res).rust_value(), 5);
+    }
+
+    #[test]
+    fn t_eval_after_error() {
+        let mut ctx = Context::new();
+
+        assert!(ctx.eval("x = undeclared_variable").is_err());
+        assert!(ctx.eval("y = 15").is_ok());
+    }
+
+    #[test]
+    fn t_restore_after_runtime_error() {
+        let mut ctx = Context::new();
+        let snapshot = ctx.snapshot();
+
+        assert!(ctx.eval("a = 1; b = 1 / 0;").is_err());
+        assert!(ctx.get_variable("a").is_some());
+
+        ctx.restore(snapshot);
+
+        assert!(ctx.get_variable("a").is_none());
+        assert!(ctx.eval("a").is_err());
+    }
+
+    #[test]
+    fn t_restore_after_typechecking_error() {
+        let mut ctx = Context::new();
+        let snapshot = ctx.snapshot();
+
+        assert!(ctx.eval("func f() -> int { 1 } a = undeclared;").is_err());
+        ctx.restore(snapshot);
+
+        assert!(ctx.eval("func f() -> string { \"jinko\" } f()").is_ok());
+    }
+
+    #[test]
+    fn t_snapshot_keeps_previous_declarations() {
+        let mut ctx = Context::new();
+        ctx.eval("a = 1;").unwrap();
+
+        let snapshot = ctx.snapshot();
+        ctx.eval("b = 2;").unwrap();
+        ctx.restore(snapshot);
+
+        assert_eq!(ctx.get_value::<JkInt>("a").unwrap().rust_value(), 1);
+        assert!(ctx.get_variable("b").is_none());
+        assert!(ctx.eval("b = \"two\";").is_ok());
+    }
+
+    #[test]
+    fn t_execution_in_debug_mode() {
+        let mut ctx = Context::new();
+        ctx.set_debug(true);
+
+        let res = ctx
+            .eval("func f(x: int) -> int { x + 1 } mut a = 1; { a = f(a); } a")
+            .unwrap()
+            .unwrap();
+
+        assert!(ctx.debug_mode);
+        assert_eq!(JkInt::from_instance(&res).rust_value(), 2);
+    }
+
+    #[test]
+    fn t_instruction_limit() {
+        let mut ctx = Context::new();
+        ctx.set_instruction_limit(100);
+
+        let err = ctx.eval("loop {}").unwrap_err();
+        assert_eq!(err.kind(), ErrKind::Limit);
+        assert_eq!(ctx.error_handler.errors().len(), 1);
+
+        let err = ctx
+            .eval("func f(n: int) -> int { f(n + 1) } f(0)")
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrKind::Limit);
+    }
+
+    #[test]
+    fn t_instruction_limit_is_per_execution() {
+        let mut ctx = Context::new();
+        ctx.set_instruction_limit(20);
+
+        assert!(ctx.eval("mut i = 0; while i < 5 { i = i + 1 }").is_ok());
+        assert!(ctx.eval("i = 0; while i < 5 { i = i + 1 }").is_ok());
+
+        let err = ctx
+            .eval("mut j = 0; while j < 50 { j = j + 1 }")
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrKind::Limit);
+    }
+
+    #[test]
+    fn t_execution_stops_once_limit_is_exceeded() {
+        let mut ctx = Context::new();
+        ctx.set_instruction_limit(10);
+
+        assert!(ctx.eval("mut a = 0; loop { } a = 1;").is_err());
+        assert_eq!(ctx.get_value::<JkInt>("a").unwrap().rust_value(), 0);
+    }
+
+    #[test]
+    fn t_timeout() {
+        let mut ctx = Context::new();
+        ctx.set_timeout(Duration::from_millis(50));
+
+        let err = ctx.eval("while true {}").unwrap_err();
+        assert_eq!(err.kind(), ErrKind::Limit);
+    }
+
+    #[test]
+    fn t_disabled_capability() {
+        let path = std::env::temp_dir().join("jinko_t_disabled_capability.txt");
+        let _ = std::fs::remove_file(&path);
+
+        let mut ctx = Context::new();
+        ctx.init_stdlib().unwrap();
+        ctx.set_capabilities(Capabilities {
+            io: false,
+            ..Capabilities::all()
+        });
+
+        let res = ctx.eval(&format!(
+            "f = file(\"{}\"); f.write(\"content\");",
+            path.display()
+        ));
+
+        assert!(res.is_err());
+        assert!(!path.exists());
+
+        let err = &ctx.error_handler.errors()[0];
+        assert_eq!(err.msg(), Some("access to the filesystem is disabled"));
+        assert_eq!(
+            err.hints()[0].msg(),
+            Some("`__builtin_file_write` requires the `io` capability")
+        );
+    }
+
+    #[test]
+    fn t_disabled_inclusion() {
+        let path = std::env::temp_dir().join("jinko_t_disabled_inclusion.jk");
+        std::fs::write(&path, "secret = 42").unwrap();
+
+        let mut ctx = Context::new();
+        ctx.init_stdlib().unwrap();
+        ctx.set_capabilities(Capabilities::none());
+
+        // The standard library can still be included
+        assert!(ctx.eval("\"jinko\".len()").is_ok());
+        assert!(ctx.eval(&format!("incl \"{}\"", path.display())).is_err());
+
+        let err = ctx.error_handler.errors().last().unwrap();
+        assert_eq!(err.msg(), Some("access to the filesystem is disabled"));
+
+        ctx.set_capabilities(Capabilities::all());
+        assert!(ctx.eval(&format!("incl \"{}\"", path.display())).is_ok());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn t_disabled_inclusion_through_stdlib_parent() {
+        let dir =
+            std::env::temp_dir().join(format!("jinko_t_stdlib_parent_{}", std::process::id()));
+        let stdlib = dir.join("stdlib");
+        std::fs::create_dir_all(&stdlib).unwrap();
+        std::fs::write(stdlib.join("lib.jk"), "").unwrap();
+        std::fs::write(dir.join("secret.jk"), "secret = 42").unwrap();
+
+        let mut ctx = Context::new();
+        ctx.typechecker.set_stdlib(stdlib.clone());
+        ctx.set_capabilities(Capabilities::none());
+
+        assert!(ctx
+            .typechecker
+            .check_inclusion(&stdlib.join("lib.jk"))
+            .is_ok());
+        assert!(ctx
+            .typechecker
+            .check_inclusion(&stdlib.join("../secret.jk"))
+            .is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn t_restore_keeps_capabilities() {
+        let mut ctx = Context::new();
+        ctx.init_stdlib().unwrap();
+        ctx.execute().unwrap();
+
+        let snapshot = ctx.snapshot();
+        ctx.set_capabilities(Capabilities::none());
+        ctx.restore(snapshot);
+
+        assert_eq!(ctx.capabilities(), Capabilities::none());
+        assert_eq!(ctx.typechecker.capabilities(), Capabilities::none());
+        assert!(ctx.eval("shell(\"true\")").is_err());
+    }
+
+    #[test]
+    fn t_capabilities_only_restrict_some_builtins() {
+        let mut ctx = Context::new();
+        ctx.init_stdlib().unwrap();
+        ctx.set_capabilities(Capabilities::none());
+
+        assert!(ctx.eval("\"jinko\".len()").is_ok());
+        assert!(ctx.eval("Args.amount()").is_err());
+
+        ctx.set_capabilities(Capabilities::all());
+        assert!(ctx.eval("Args.amount()").is_ok());
+    }
+
+    #[test]
+    fn t_get_value() {
+        let mut ctx = Context::new();
+        ctx.eval("x = 15; s = \"jinko\"").unwrap();
+
+        assert_eq!(ctx.get_value::<JkInt>("x").unwrap().rust_value(), 15);
+        assert_eq!(
+            ctx.get_value::<JkString>("s").unwrap().rust_value(),
+            "jinko"
+        );
+        assert!(ctx.get_value::<JkInt>("undeclared").is_none());
+    }
+
+    #[test]
+    fn t_add_builtin() {
+        use std::cell::Cell;
+
+        let calls = Rc::new(Cell::new(0));
+        let counter = calls.clone();
+
+        let mut ctx = Context::new();
+        ctx.add_builtin("host_double", &[ArgType::Type("int")], move |_, args| {
+            counter.set(counter.get() + 1);
+
+            let value = JkInt::from_instance(&args[0]).rust_value();
+            Some(JkInt::from(value * 2).to_instance())
+        })
+        .unwrap();
+
+        let res = ctx
+            .eval("ext func host_double(value: int) -> int; host_double(21)")
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(JkInt::from_instance(&res).rust_value(), 42);
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    fn t_run_test() {
+        let mut ctx = Context::new();
+        ctx.init_stdlib().unwrap();
+        parser::parse(
+            &mut ctx,
+            "test passing { a = 1 }; test failing { \"jinko\".char_at(5) }",
+            None,
+        )
+        .unwrap();
+        ctx.execute().unwrap();
+
+        assert_eq!(ctx.run_test("passing"), Some(true));
+        assert!(!ctx.has_errors());
+
+        assert_eq!(ctx.run_test("failing"), Some(false));
+        assert!(ctx.has_errors());
+
+        assert_eq!(ctx.run_test("undeclared"), None);
+    }
+
+    #[test]
+    fn t_run_test_with_mocks() {
+        let mut ctx = Context::new();
+        ctx.init_stdlib().unwrap();
+        parser::parse(
+            &mut ctx,
+            "func one() -> int { 1 }; func two() -> int { 2 };
+            mock one() -> int { 11 };
+            test global_mock { assert_eq[int](one(), 11) };
+            test local_mock { mock two() -> int { 22 }; assert_eq[int](two(), 22) };
+            test restored { assert_eq[int](two(), 2) }",
+            None,
+        )
+        .unwrap();
+        ctx.execute().unwrap();
+
+        let original = ctx.get_function("one").unwrap().clone();
+
+        assert_eq!(ctx.run_test("global_mock"), Some(true));
+        assert_eq!(ctx.run_test("local_mock"), Some(true));
+        assert_eq!(ctx.run_test("restored"), Some(true));
+        assert!(!ctx.has_errors());
+
+        assert!(Rc::ptr_eq(ctx.get_function("one").unwrap(), &original));
+    }
+
+    #[test]
+    fn t_add_builtin_twice() {
+        let mut ctx = Context::new();
+
+        assert!(ctx
+            .add_builtin("__builtin_string_len", &[], |_, _| None)
+            .is_err());
+    }
+
+    #[test]
+    fn t_builtin_signature_mismatch() {
+        let mut ctx = Context::new();
+
+        assert!(ctx
+            .eval("ext func __builtin_string_len(s: int) -> int; __builtin_string_len(1)")
+            .is_err());
+        assert!(ctx
+            .eval("ext func __builtin_string_len() -> int; __builtin_string_len()")
+            .is_err());
+    }
+
+    #[test]
+    fn t_call_before_declaration() {
+        let ctx = jinko! {
+            res = double(21);
+            func double(x: int) -> int { x * 2 }
+        };
+
+        assert_eq!(
+            ctx.get_variable("res").unwrap().instance(),
+            JkInt::from(42).to_instance()
+        );
+    }
+
+    #[test]
+    fn t_mutually_recursive_functions() {
+        let ctx = jinko! {
+            func is_even(n: int) -> bool { if n == 0 { true } else { is_odd(n - 1) } }
+            func is_odd(n: int) -> bool { if n == 0 { false } else { is_even(n - 1) } }
+            res = is_odd(7);
+        };
+
+        assert_eq!(
+            ctx.get_variable("res").unwrap().instance(),
+            JkBool::from(true).to_instance()
+        );
+    }
+
+    #[test]
+    fn t_type_used_before_declaration() {
+        let ctx = jinko! {
+            func origin() -> Point { Point(x: 0, y: 0) }
+            res = origin().x;
+            type Point(x: int, y: int);
+        };
+
+        assert_eq!(
+            ctx.get_variable("res").unwrap().instance(),
+            JkInt::from(0).to_instance()
+        );
+    }
+
+    #[test]
+    fn t_generic_call_before_declaration() {
+        let ctx = jinko! {
+            res = id[int](3);
+            func id[T](x: T) -> T { x }
+        };
+
+        assert_eq!(
+            ctx.get_variable("res").unwrap().instance(),
+            JkInt::from(3).to_instance()
+        );
+    }
+
+    #[test]
+    fn t_variable_used_before_declaration() {
+        crate::jinko_fail! {
+            res = a + 1;
+            a = 1;
+        };
+    }
+
+    #[test]
+    fn t_main_return_value() {
+        let res = crate::jk_execute! {
+            value = 40;
+            func main() -> int { value + two() }
+            func two() -> int { 2 }
+        };
+
+        assert_eq!(res, Some(JkInt::from(42).to_instance()));
+    }
+
+    #[test]
+    fn t_main_args() {
+        let mut ctx = Context::new();
+        ctx.init_stdlib().unwrap();
+        ctx.set_args(vec![String::from("jin"), String::from("ko")]);
+
+        let res = ctx
+            .eval("func main(args: Vec[string]) -> int { args.get[string](1).len() }")
+            .unwrap();
+
+        assert_eq!(res, Some(JkInt::from(2).to_instance()));
+    }
+
+    #[test]
+    fn t_main_with_top_level_code() {
+        crate::jinko_fail! {
+            func main() -> int { 0 }
+            main();
+        };
+    }
+
+    #[test]
+    fn t_main_invalid_signature() {
+        crate::jinko_fail! {
+            func main(a: int) -> int { a }
+        };
+    }
+
+    #[test]
+    fn t_unused_function_warns() {
+        let mut ctx = Context::new();
+        ctx.eval("func unused() {}").unwrap();
+
+        // Unused functions are not reported when evaluating code, since they could be
+        // called by code evaluated later on
+        assert!(ctx.error_handler.errors().is_empty());
+
+        let mut ctx = Context::new();
+        crate::parser::parse(&mut ctx, "func unused() {}", None).unwrap();
+        ctx.execute().unwrap();
+
+        assert!(ctx
+            .error_handler
+            .errors()
+            .iter()
+            .any(|e| e.kind() == ErrKind::Warning));
+    }
+
+    #[test]
+    fn t_used_functions_and_main_do_not_warn() {
+        let mut ctx = Context::new();
+        crate::parser::parse(
+            &mut ctx,
+            "func used() -> int { 0 } func main() -> int { used() }",
+            None,
+        )
+        .unwrap();
+        ctx.execute().unwrap();
+
+        assert!(ctx.error_handler.errors().is_empty());
+    }
+
+    #[test]
+    fn t_deny_warnings() {
+        let mut ctx = Context::new();
+        ctx.set_deny_warnings(true);
+        crate::parser::parse(&mut ctx, "func unused() {}", None).unwrap();
+
+        assert!(ctx.execute().is_err());
+    }
+
+    #[test]
+    fn t_run_repeatedly() {
+        let mut ctx = Context::new();
+        ctx.eval("mut counter = 0").unwrap();
+
+        let stats = ctx
+            .run_repeatedly("counter = counter + 1; x = counter", 4)
+            .unwrap();
+
+        assert_eq!(stats.runs(), 4);
+        // Every run starts from the same state
+        assert_eq!(ctx.get_value::<JkInt>("counter").unwrap().rust_value(), 0);
+        assert!(ctx.get_variable("x").is_none());
+    }
+
+    #[test]
+    fn t_run_repeatedly_invalid() {
+        let mut ctx = Context::new();
+
+        assert!(ctx.run_repeatedly("a = undeclared", 4).is_err());
+        assert!(ctx.run_repeatedly("a = ", 4).is_err());
+        assert!(ctx.eval("a = 1").is_ok());
+    }
+
+    fn execute_value(input: &str) -> Option<i64> {
+        let mut ctx = Context::new();
+        parser::parse(&mut ctx, input, None).unwrap();
+
+        ctx.execute()
+            .unwrap()
+            .map(|value| JkInt::from_instance(&value).rust_value())
+    }
+
+    #[test]
+    fn t_execute_value() {
+        assert_eq!(execute_value("x = 40; x + 2"), Some(42));
+        assert_eq!(execute_value("if true { 1 } else { 2 }"), Some(1));
+        assert_eq!(execute_value("return 3; 4"), Some(3));
+        assert_eq!(execute_value("func main() -> int { 7 }"), Some(7));
+        assert_eq!(execute_value("x = 15;"), None);
+    }
+
+    #[test]
+    fn t_execute_value_ignores_declarations() {
+        assert_eq!(execute_value("15 func f() -> int { 1 }"), Some(15));
+        assert_eq!(execute_value("16 type Point(x: int, y: int);"), Some(16));
+    }
+
+    #[test]
+    fn t_exit_from_function() {
+        let mut ctx = Context::new();
+        ctx.init_stdlib().unwrap();
+        ctx.eval("mut reached = false").unwrap();
+
+        let res = ctx
+            .eval(
+                "func stop() { mut i = 0; loop { i = i + 1; if i == 3 { exit(i); } } } \
+                 stop(); reached = true; 15",
+            )
+            .unwrap();
+
+        assert!(res.is_none());
+        assert_eq!(ctx.exit_code(), Some(3));
+        assert!(!ctx.get_value::<JkBool>("reached").unwrap().rust_value());
+
+        // The exit code only concerns the last execution
+        ctx.eval("reached = true").unwrap();
+        assert_eq!(ctx.exit_code(), None);
+    }
+
+    #[test]
+    fn t_errors_are_kept() {
+        let mut ctx = Context::new_without_stdlib();
+
+        // Errors are not emitted by the context, but kept for its user to handle
+        assert!(ctx.eval("undeclared + 15").is_err());
+        assert!(ctx.error_handler.has_errors());
+    }
+
+    #[test]
+    fn t_find_stdlib() {
+        let candidates = [PathBuf::from("does/not/exist"), PathBuf::from("stdlib")];
+
+        assert_eq!(
+            Context::find_stdlib(&candidates).unwrap(),
+            PathBuf::from("stdlib")
+        );
+    }
+
+    #[test]
+    fn t_find_stdlib_lists_candidates() {
+        let candidates = [PathBuf::from("does/not/exist"), PathBuf::from("tests")];
+        let err = Context::find_stdlib(&candidates).unwrap_err();
+
+        let hint = err.hints()[0].msg().unwrap();
+        assert!(hint.contains(r#""does/not/exist""#));
+        assert!(hint.contains(r#""tests""#));
+    }
 }
@@ -0,0 +1,95 @@
+//! Capabilities restrict what the programs executed by a context are allowed to do
+//! outside of the interpreter, such as accessing the filesystem or running shell
+//! commands. Hosts evaluating untrusted scripts can disable them: Builtins requiring
+//! a disabled capability then fail with an error instead of being executed, and
+//! sources other than the standard library cannot be included without the `io`
+//! capability.
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// A single capability, required by some builtins
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Capability {
+    /// Reading and writing files
+    Io,
+    /// Loading libraries and calling external functions
+    Ffi,
+    /// Accessing the arguments of the program and exiting the process
+    Env,
+    /// Running shell commands
+    Shell,
+}
+
+impl Capability {
+    /// Description of what the capability allows
+    pub fn description(&self) -> &'static str {
+        match self {
+            Capability::Io => "access to the filesystem",
+            Capability::Ffi => "access to external functions",
+            Capability::Env => "access to the environment of the process",
+            Capability::Shell => "execution of shell commands",
+        }
+    }
+}
+
+impl Display for Capability {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let name = match self {
+            Capability::Io => "io",
+            Capability::Ffi => "ffi",
+            Capability::Env => "env",
+            Capability::Shell => "shell",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// Capabilities granted to the programs executed by a context. All of them are
+/// granted by default
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Capabilities {
+    pub io: bool,
+    pub ffi: bool,
+    pub env: bool,
+    pub shell: bool,
+}
+
+impl Capabilities {
+    /// Grant every capability
+    pub fn all() -> Capabilities {
+        Capabilities {
+            io: true,
+            ffi: true,
+            env: true,
+            shell: true,
+        }
+    }
+
+    /// Deny every capability, only allowing programs to compute values and to use
+    /// their standard input and output
+    pub fn none() -> Capabilities {
+        Capabilities {
+            io: false,
+            ffi: false,
+            env: false,
+            shell: false,
+        }
+    }
+
+    /// Is a capability granted
+    pub fn allows(&self, capability: Capability) -> bool {
+        match capability {
+            Capability::Io => self.io,
+            Capability::Ffi => self.ffi,
+            Capability::Env => self.env,
+            Capability::Shell => self.shell,
+        }
+    }
+}
+
+impl Default for Capabilities {
+    fn default() -> Capabilities {
+        Capabilities::all()
+    }
+}
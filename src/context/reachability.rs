@@ -0,0 +1,236 @@
+//! Reachability analysis of a program, performed when checking it. Starting from the
+//! top-level code of the entry point, the analysis follows the functions and types
+//! referenced by each declaration it reaches. Declarations which are never reached
+//! are dead code, even if they are used by other dead code.
+//!
+//! References are found by walking the syntax tree of the program: Every word written
+//! in the attributes of a node, such as the name of a called function or the type of
+//! an argument, counts as a reference. This overestimates the references of the
+//! program, so that a reachable declaration is never reported.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::Json;
+use crate::error::{ErrKind, Error};
+use crate::instruction::{Block, FunctionDec, FunctionKind, Instruction, TypeDec};
+
+use super::MAIN_NAME;
+
+/// Function or type which can be reported as dead code
+struct Declaration<'ep> {
+    instruction: &'ep dyn Instruction,
+    /// Kind of the declaration, as written in warnings
+    kind: &'static str,
+    name: &'ep str,
+    /// Names under which the declaration is referenced. The variants of an enum are
+    /// used without naming the enum itself
+    names: Vec<String>,
+    references: HashSet<String>,
+}
+
+impl<'ep> Declaration<'ep> {
+    fn new(instruction: &'ep dyn Instruction, ast: &Json) -> Option<Declaration<'ep>> {
+        let (kind, name) = match (
+            instruction.downcast_ref::<FunctionDec>(),
+            instruction.downcast_ref::<TypeDec>(),
+        ) {
+            (Some(dec), _) if dec.fn_kind() == FunctionKind::Func && dec.name() != MAIN_NAME => {
+                ("function", dec.name())
+            }
+            (_, Some(dec)) => ("type", dec.name()),
+            _ => return None,
+        };
+
+        // As for variables, names starting with an underscore are never reported
+        if name.starts_with('_') {
+            return None;
+        }
+
+        let mut names = vec![name.to_string()];
+        if let Some(Json::Array(variants)) = ast.get("children").and_then(|c| c.get("variants")) {
+            names.extend(variants.iter().filter_map(|variant| {
+                match variant.get("attributes")?.get("name")? {
+                    Json::Str(name) => Some(name.clone()),
+                    _ => None,
+                }
+            }));
+        }
+
+        let mut references = HashSet::new();
+        collect_references(ast, &mut references);
+
+        Some(Declaration {
+            instruction,
+            kind,
+            name,
+            names,
+            references,
+        })
+    }
+}
+
+/// Add the words written in the attributes of a node and of its children to a set of
+/// references
+fn collect_references(node: &Json, references: &mut HashSet<String>) {
+    fn words(value: &Json, references: &mut HashSet<String>) {
+        match value {
+            Json::Str(s) => s
+                .split(|c: char| !c.is_alphanumeric() && c != '_')
+                .filter(|word| !word.is_empty())
+                .for_each(|word| {
+                    references.insert(word.to_string());
+                }),
+            Json::Array(values) => values.iter().for_each(|value| words(value, references)),
+            _ => {}
+        }
+    }
+
+    if let Some(Json::Object(attributes)) = node.get("attributes") {
+        attributes
+            .iter()
+            .for_each(|(_, value)| words(value, references));
+    }
+
+    if let Some(Json::Object(children)) = node.get("children") {
+        children.iter().for_each(|(_, child)| match child {
+            Json::Array(nodes) => nodes
+                .iter()
+                .for_each(|node| collect_references(node, references)),
+            child => collect_references(child, references),
+        });
+    }
+}
+
+/// Create a warning for each function and type of the entry point which cannot be
+/// reached from its top-level code, nor from its `main` function
+pub(super) fn unreachable_declarations(ep: &Block) -> Vec<Error> {
+    let mut declarations = vec![];
+    let mut roots = HashSet::new();
+
+    for instruction in ep.instructions() {
+        let ast = instruction.ast().to_json();
+        match Declaration::new(&**instruction, &ast) {
+            Some(declaration) => declarations.push(declaration),
+            None => collect_references(&ast, &mut roots),
+        }
+    }
+
+    let mut by_name: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (i, declaration) in declarations.iter().enumerate() {
+        for name in declaration.names.iter() {
+            by_name.entry(name.as_str()).or_default().push(i);
+        }
+    }
+
+    let mut reached = vec![false; declarations.len()];
+    let mut queue: Vec<&str> = roots.iter().map(String::as_str).collect();
+    while let Some(word) = queue.pop() {
+        for &i in by_name.get(word).into_iter().flatten() {
+            if !reached[i] {
+                reached[i] = true;
+                queue.extend(declarations[i].references.iter().map(String::as_str));
+            }
+        }
+    }
+
+    let dead: Vec<&Declaration> = declarations
+        .iter()
+        .zip(reached)
+        .filter_map(|(declaration, reached)| (!reached).then_some(declaration))
+        .collect();
+
+    dead.iter()
+        .map(|declaration| {
+            let warning = Error::new(ErrKind::Warning)
+                .with_msg(format!(
+                    "{} `{}` is never used",
+                    declaration.kind, declaration.name
+                ))
+                .with_loc(declaration.instruction.location().cloned());
+
+            let used_by_dead_code = dead.iter().any(|other| {
+                other.name != declaration.name
+                    && declaration
+                        .names
+                        .iter()
+                        .any(|name| other.references.contains(name))
+            });
+
+            match used_by_dead_code {
+                true => warning.with_hint(Error::hint().with_msg(format!(
+                    "`{}` is only used by code which is never used itself",
+                    declaration.name
+                ))),
+                false => warning,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::Context;
+    use crate::parser;
+
+    fn warnings(input: &str) -> Vec<String> {
+        let mut ctx = Context::new_without_stdlib();
+        parser::parse(&mut ctx, input, None).unwrap();
+
+        let ep = ctx.entry_point.block().unwrap();
+        unreachable_declarations(ep)
+            .iter()
+            .map(|e| e.msg().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn reachable_from_top_level_code() {
+        assert!(warnings("func f() -> int { g() } func g() -> int { 1 } f()").is_empty());
+    }
+
+    #[test]
+    fn reachable_from_main() {
+        assert!(warnings("type T(a: int); func main() { t = T(a: 1); }").is_empty());
+    }
+
+    #[test]
+    fn only_used_by_dead_code() {
+        assert_eq!(
+            warnings("func dead() -> int { helper() } func helper() -> int { 1 }"),
+            [
+                "function `dead` is never used",
+                "function `helper` is never used"
+            ]
+        );
+    }
+
+    #[test]
+    fn unused_types() {
+        assert_eq!(
+            warnings("type Unused(a: int); type Field(a: int); type Used(f: Field); u = Used;"),
+            ["type `Unused` is never used"]
+        );
+    }
+
+    #[test]
+    fn enum_reached_through_variants() {
+        assert!(warnings(
+            "type Shape = Circle(r: float) | Square(side: float); s = Circle(r: 1.0);"
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn recursion_is_not_a_use() {
+        assert_eq!(
+            warnings("func fact(n: int) -> int { if n < 2 { 1 } else { n * fact(n - 1) } }"),
+            ["function `fact` is never used"]
+        );
+    }
+
+    #[test]
+    fn ignored_declarations() {
+        assert!(warnings("func main() {} func _f() {} type _T; test t() {}").is_empty());
+    }
+}
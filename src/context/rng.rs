@@ -0,0 +1,69 @@
+//! Pseudo-random number generator used by the random builtins. Its seed differs on
+//! each run of the interpreter, unless the context is deterministic: All the programs
+//! then draw the same sequence of numbers.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+/// Seed used by deterministic contexts
+pub const DETERMINISTIC_SEED: u64 = 0x006a_696e_6b6f;
+
+/// SplitMix64 generator. It is not suitable for cryptographic purposes
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// Create a generator producing the sequence of numbers associated with a seed
+    pub fn from_seed(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    /// Create a generator seeded from the randomness of the process
+    pub fn from_entropy() -> Rng {
+        Rng::from_seed(RandomState::new().build_hasher().finish())
+    }
+
+    /// Draw the next integer of the sequence
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+
+        z ^ (z >> 31)
+    }
+
+    /// Draw a float between 0 (included) and 1 (excluded)
+    pub fn next_f64(&mut self) -> f64 {
+        // Keep 53 bits, the precision of a double
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_sequence() {
+        let mut lhs = Rng::from_seed(DETERMINISTIC_SEED);
+        let mut rhs = Rng::from_seed(DETERMINISTIC_SEED);
+
+        for _ in 0..16 {
+            assert_eq!(lhs.next_u64(), rhs.next_u64());
+        }
+    }
+
+    #[test]
+    fn floats_in_range() {
+        let mut rng = Rng::from_seed(15);
+
+        for _ in 0..1000 {
+            let value = rng.next_f64();
+            assert!((0.0..1.0).contains(&value));
+        }
+    }
+}
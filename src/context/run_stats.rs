@@ -0,0 +1,90 @@
+//! Statistics gathered when running a program multiple times with
+//! [`crate::context::Context::run_repeatedly`], in order to measure its performance.
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::time::Duration;
+
+/// Time spent in each run of a program
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RunStats {
+    durations: Vec<Duration>,
+}
+
+impl RunStats {
+    /// Record the duration of a run
+    pub(crate) fn add(&mut self, duration: Duration) {
+        self.durations.push(duration)
+    }
+
+    /// Amount of runs of the program
+    pub fn runs(&self) -> usize {
+        self.durations.len()
+    }
+
+    /// Duration of each run, in the order they were made
+    pub fn durations(&self) -> &[Duration] {
+        &self.durations
+    }
+
+    /// Time spent in all the runs
+    pub fn total(&self) -> Duration {
+        self.durations.iter().sum()
+    }
+
+    /// Duration of the fastest run, if there was one
+    pub fn min(&self) -> Option<Duration> {
+        self.durations.iter().min().copied()
+    }
+
+    /// Duration of the slowest run, if there was one
+    pub fn max(&self) -> Option<Duration> {
+        self.durations.iter().max().copied()
+    }
+
+    /// Average duration of a run, if there was one
+    pub fn mean(&self) -> Option<Duration> {
+        match self.runs() {
+            0 => None,
+            runs => Some(self.total() / runs as u32),
+        }
+    }
+}
+
+impl Display for RunStats {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match (self.min(), self.mean(), self.max()) {
+            (Some(min), Some(mean), Some(max)) => write!(
+                f,
+                "{} runs: min {:?}, mean {:?}, max {:?}",
+                self.runs(),
+                min,
+                mean,
+                max
+            ),
+            _ => write!(f, "0 runs"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats() {
+        let mut stats = RunStats::default();
+        assert_eq!(stats.mean(), None);
+        assert_eq!(stats.to_string(), "0 runs");
+
+        stats.add(Duration::from_millis(3));
+        stats.add(Duration::from_millis(1));
+        stats.add(Duration::from_millis(5));
+
+        assert_eq!(stats.runs(), 3);
+        assert_eq!(stats.total(), Duration::from_millis(9));
+        assert_eq!(stats.min(), Some(Duration::from_millis(1)));
+        assert_eq!(stats.max(), Some(Duration::from_millis(5)));
+        assert_eq!(stats.mean(), Some(Duration::from_millis(3)));
+        assert_eq!(stats.to_string(), "3 runs: min 1ms, mean 3ms, max 5ms");
+    }
+}
@@ -3,6 +3,9 @@
 //! In order to access variables and functions, the scope map first looks in the current
 //! scope. If the specified name cannot be found, it searches the other scopes, defined
 //! before the current one, until it finds the correct component.
+//! Calling a function enters a new frame: Variables declared by the caller are then
+//! hidden until the frame is exited, and only the global variables, declared before
+//! entering the first frame, stay visible.
 
 use std::{
     borrow::Borrow,
@@ -56,6 +59,8 @@ pub type ScopeStack<T> = LinkedList<T>;
 #[derive(Clone, Default)]
 pub struct ScopeMap<V, F, T> {
     scopes: ScopeStack<Scope<V, F, T>>,
+    /// Depth at which each frame currently entered starts
+    frames: Vec<usize>,
 }
 
 impl<V, F, T> ScopeMap<V, F, T> {
@@ -63,6 +68,7 @@ impl<V, F, T> ScopeMap<V, F, T> {
     pub fn new() -> ScopeMap<V, F, T> {
         ScopeMap {
             scopes: ScopeStack::new(),
+            frames: Vec::new(),
         }
     }
 
@@ -83,11 +89,96 @@ impl<V, F, T> ScopeMap<V, F, T> {
         self.scopes.pop_front().unwrap();
     }
 
+    /// Enter into a new frame, containing a new scope. The variables declared in the
+    /// current frame are not visible from the new one
+    pub fn frame_enter(&mut self) {
+        self.frames.push(self.scopes.len());
+        self.scope_enter();
+    }
+
+    /// Exit the last added frame and its scope
+    pub fn frame_exit(&mut self) {
+        self.scope_exit();
+
+        // We unwrap for the same reasons as in `scope_exit()`
+        let frame_start = self.frames.pop().unwrap();
+        assert_eq!(frame_start, self.scopes.len());
+    }
+
+    /// Is the scope at the given position, starting from the current scope, visible
+    /// when looking for variables
+    fn is_variable_scope_visible(&self, position: usize) -> bool {
+        let depth = self.scopes.len();
+
+        match (self.frames.first(), self.frames.last()) {
+            (Some(globals), Some(frame_start)) => {
+                position < depth - frame_start || position >= depth - globals
+            }
+            _ => true,
+        }
+    }
+
+    /// Get the scopes in which variables are visible: The scopes of the current frame,
+    /// followed by the global scopes
+    fn variable_scopes(&self) -> impl Iterator<Item = &Scope<V, F, T>> {
+        self.scopes
+            .iter()
+            .enumerate()
+            .filter(|(position, _)| self.is_variable_scope_visible(*position))
+            .map(|(_, scope)| scope)
+    }
+
+    /// Get the number of frames currently entered
+    pub fn frame_depth(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Get the scopes of the current frame, from the innermost to the outermost one.
+    /// When no frame has been entered, all scopes are part of the current frame
+    fn frame_scopes(&self) -> impl Iterator<Item = &Scope<V, F, T>> {
+        let frame_start = self.frames.last().copied().unwrap_or(0);
+
+        self.scopes.iter().take(self.scopes.len() - frame_start)
+    }
+
+    /// Get all the variables visible from the current scope. Variables shadowed by
+    /// another one with the same name are not returned
+    pub fn variables(&self) -> HashMap<&String, &V> {
+        let mut variables = HashMap::new();
+        self.variable_scopes()
+            .flat_map(|scope| scope.variables.iter())
+            .for_each(|(name, var)| {
+                variables.entry(name).or_insert(var);
+            });
+
+        variables
+    }
+
+    /// Get the variables declared in the current frame. Variables shadowed by
+    /// another one with the same name are not returned
+    pub fn frame_variables(&self) -> HashMap<&String, &V> {
+        let mut variables = HashMap::new();
+        self.frame_scopes()
+            .flat_map(|scope| scope.variables.iter())
+            .for_each(|(name, var)| {
+                variables.entry(name).or_insert(var);
+            });
+
+        variables
+    }
+
+    /// Maybe get a variable declared in the current frame
+    pub fn get_frame_variable(&self, name: &str) -> Option<&V> {
+        self.frame_scopes()
+            .map(|scope| scope.variables.get(name))
+            .find(|var| var.is_some())?
+    }
+
     fn get<'map, K, Q, U>(
         &'map self,
         key: &Q,
         map_extractor: impl Fn(&Scope<V, F, T>) -> &HashMap<K, U>,
-    ) -> Option<&U>
+    ) -> Option<&'map U>
     where
         K: Borrow<Q> + Hash + Eq + 'map,
         Q: Hash + Eq + ?Sized,
@@ -121,16 +212,52 @@ impl<V, F, T> ScopeMap<V, F, T> {
         }
     }
 
+    /// Insert a value in the outermost scope, if it hasn't been added before
+    fn insert_global<K, U>(
+        &mut self,
+        key: K,
+        value: U,
+        map_extractor: impl Fn(&mut Scope<V, F, T>) -> &mut HashMap<K, U>,
+    ) -> Result<(), Error>
+    where
+        K: Hash + Eq,
+    {
+        // If there is no scope, this is an error in the interpreter's logic
+        let global = self.scopes.back_mut().unwrap();
+        let map = map_extractor(global);
+
+        match map.get(&key) {
+            Some(_) => Err(Error::new(ErrKind::Context)),
+            None => {
+                map.insert(key, value);
+                Ok(())
+            }
+        }
+    }
+
     /// Maybe get a variable in any available scopes
     pub fn get_variable(&self, name: &str) -> Option<&V> {
-        self.get(name, |scope| &scope.variables)
+        self.variable_scopes()
+            .map(|scope| scope.variables.get(name))
+            .find(|var| var.is_some())?
+    }
+
+    /// Maybe get a variable declared in the current scope
+    pub fn get_variable_in_current_scope(&self, name: &str) -> Option<&V> {
+        self.scopes.front()?.variables.get(name)
     }
 
     /// Maybe get a mutable reference to a variable in any available scopes
     pub fn get_variable_mut(&mut self, name: &str) -> Option<&mut V> {
+        let visible: Vec<bool> = (0..self.scopes.len())
+            .map(|position| self.is_variable_scope_visible(position))
+            .collect();
+
         self.scopes
             .iter_mut()
-            .map(|scope| scope.variables.get_mut(name))
+            .zip(visible)
+            .filter(|(_, visible)| *visible)
+            .map(|(scope, _)| scope.variables.get_mut(name))
             .find(|var| var.is_some())?
     }
 
@@ -174,6 +301,27 @@ impl<V, F, T> ScopeMap<V, F, T> {
         self.insert_unique(name, custom_type, |scope| &mut scope.types)
     }
 
+    /// Add a function to the outermost scope if it hasn't been added before, so that
+    /// it stays available once the current scope is exited
+    pub fn add_global_function(&mut self, name: String, func: F) -> Result<(), Error> {
+        self.insert_global(name, func, |scope| &mut scope.functions)
+    }
+
+    /// Add a type to the outermost scope if it hasn't been added before, so that it
+    /// stays available once the current scope is exited
+    pub fn add_global_type(&mut self, name: String, custom_type: T) -> Result<(), Error> {
+        self.insert_global(name, custom_type, |scope| &mut scope.types)
+    }
+
+    /// Replace a function in the scope it was declared in, and return the previous
+    /// declaration. Nothing is replaced if the function has not been declared
+    pub fn replace_function(&mut self, name: &str, func: F) -> Option<F> {
+        self.scopes
+            .iter_mut()
+            .find_map(|scope| scope.functions.get_mut(name))
+            .map(|previous| std::mem::replace(previous, func))
+    }
+
     /// Add a generic type to the current scope if it hasn't been added before
     pub fn add_generic_type(&mut self, name: String, custom_type: T) -> Result<(), Error> {
         self.insert_unique(name, custom_type, |scope| &mut scope.generic_types)
@@ -265,4 +413,72 @@ mod tests {
         let _ = Scope::<i32, i32, String>::default();
         let _ = Scope::<(), (), ()>::default();
     }
+
+    #[test]
+    fn t_frame_hides_caller_variables() {
+        let mut s = new_scopemap();
+
+        s.scope_enter();
+        s.add_variable(s!("global"), Var::new("global".to_owned()))
+            .unwrap();
+
+        s.frame_enter();
+        s.add_variable(s!("caller"), Var::new("caller".to_owned()))
+            .unwrap();
+        s.scope_enter();
+
+        s.frame_enter();
+        assert!(s.get_variable("caller").is_none());
+        assert!(s.get_variable_mut("caller").is_none());
+        assert!(s.get_variable("global").is_some());
+
+        s.frame_exit();
+        assert!(s.get_variable("caller").is_some());
+    }
+
+    #[test]
+    fn t_variable_in_current_scope() {
+        let mut s = new_scopemap();
+
+        s.scope_enter();
+        s.add_variable(s!("a"), Var::new("a".to_owned())).unwrap();
+        assert!(s.get_variable_in_current_scope("a").is_some());
+
+        s.scope_enter();
+        assert!(s.get_variable_in_current_scope("a").is_none());
+        assert!(s.get_variable("a").is_some());
+    }
+
+    #[test]
+    #[should_panic]
+    fn t_exit_frame_from_inner_scope() {
+        let mut s = new_scopemap();
+
+        s.frame_enter();
+        s.scope_enter();
+        s.frame_exit();
+    }
+
+    #[test]
+    fn t_frame_variables() {
+        let mut s = new_scopemap();
+
+        s.scope_enter();
+        s.add_variable(s!("global"), Var::new("global".to_owned()))
+            .unwrap();
+
+        s.frame_enter();
+        s.add_variable(s!("a"), Var::new("a".to_owned())).unwrap();
+        s.scope_enter();
+        s.add_variable(s!("b"), Var::new("b".to_owned())).unwrap();
+
+        let mut frame: Vec<&String> = s.frame_variables().into_keys().collect();
+        frame.sort();
+        assert_eq!(frame, vec!["a", "b"]);
+        assert!(s.get_frame_variable("global").is_none());
+
+        let mut visible: Vec<&String> = s.variables().into_keys().collect();
+        visible.sort();
+        assert_eq!(visible, vec!["a", "b", "global"]);
+    }
 }
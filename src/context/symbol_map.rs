@@ -0,0 +1,97 @@
+//! Symbols of an included source are made available under a prefixed name, such as
+//! `lib::f` for the function `f` of `lib.jk`. The name a symbol is declared as at
+//! runtime then differs from the one written in its source. The symbol map remembers
+//! the original name of each prefixed symbol, along with the source it was included
+//! from, so that diagnostics refer to symbols the way they are written.
+
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// Symbol declared in an included source
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IncludedSymbol {
+    name: String,
+    module: String,
+}
+
+impl IncludedSymbol {
+    /// Name of the symbol in the source declaring it
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Source the symbol was included from, as written in the inclusion
+    pub fn module(&self) -> &str {
+        &self.module
+    }
+}
+
+impl Display for IncludedSymbol {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "`{}` from `{}`", self.name, self.module)
+    }
+}
+
+/// Table of the prefixed symbols declared in a context
+#[derive(Clone, Debug, Default)]
+pub struct SymbolMap {
+    symbols: HashMap<String, IncludedSymbol>,
+}
+
+impl SymbolMap {
+    /// Create an empty symbol map
+    pub fn new() -> SymbolMap {
+        SymbolMap::default()
+    }
+
+    /// Record that `prefixed` refers to the symbol `name` of the source `module`. If
+    /// `name` is itself prefixed, such as when a source exports the symbols of the
+    /// sources it includes, it refers to the same original symbol
+    pub fn insert(&mut self, prefixed: String, name: &str, module: &str) {
+        let symbol = self.get(name).cloned().unwrap_or_else(|| IncludedSymbol {
+            name: name.to_owned(),
+            module: module.to_owned(),
+        });
+
+        self.symbols.insert(prefixed, symbol);
+    }
+
+    /// Get the original symbol a prefixed name refers to, if it is one
+    pub fn get(&self, prefixed: &str) -> Option<&IncludedSymbol> {
+        self.symbols.get(prefixed)
+    }
+
+    /// Display a name the way it is written in its source, as well as the source it
+    /// comes from if it was included
+    pub fn display(&self, name: &str) -> String {
+        match self.get(name) {
+            Some(symbol) => symbol.to_string(),
+            None => format!("`{}`", name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_prefixed() {
+        let mut symbols = SymbolMap::new();
+        symbols.insert(String::from("l::f"), "f", "lib");
+
+        assert_eq!(symbols.display("l::f"), "`f` from `lib`");
+        assert_eq!(symbols.display("f"), "`f`");
+    }
+
+    #[test]
+    fn nested_prefixes_refer_to_original() {
+        let mut symbols = SymbolMap::new();
+        symbols.insert(String::from("inner::f"), "f", "inner");
+        symbols.insert(String::from("outer::inner::f"), "inner::f", "outer");
+
+        let symbol = symbols.get("outer::inner::f").unwrap();
+        assert_eq!(symbol.name(), "f");
+        assert_eq!(symbol.module(), "inner");
+    }
+}
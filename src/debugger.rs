@@ -0,0 +1,204 @@
+//! The debugger module allows pausing the execution of a program in order to inspect
+//! it. Before executing each statement, the context calls its [`DebugHook`], if one
+//! was set with [`Context::set_debug_hook`]. Frontends, such as the interactive one
+//! used by `jinko debug <input>`, rely on a [`Debugger`] to decide when to pause:
+//! It keeps track of breakpoints and of the way the user is stepping through the
+//! program.
+
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+use crate::context::Context;
+use crate::instruction::Instruction;
+
+/// Hook called by the context before executing each statement
+pub trait DebugHook {
+    fn before_instruction(&mut self, ctx: &mut Context, instruction: &dyn Instruction);
+}
+
+/// Ways of resuming the execution of a paused program
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Mode {
+    /// Pause on the next statement, including the ones of called functions
+    Step,
+    /// Pause on the next statement which is not nested in a function call deeper
+    /// than the given depth
+    Next(usize),
+    /// Only pause on breakpoints
+    Continue,
+}
+
+/// State of a debugging session
+pub struct Debugger {
+    /// Path of the source being debugged. Statements from other sources, such as the
+    /// standard library, are stepped over
+    path: Option<PathBuf>,
+    /// Lines on which the execution pauses
+    breakpoints: BTreeSet<usize>,
+    mode: Mode,
+}
+
+impl Debugger {
+    /// Create a new debugger for a source. The execution of the program first pauses
+    /// on its first statement
+    pub fn new(path: Option<PathBuf>) -> Debugger {
+        Debugger {
+            path,
+            breakpoints: BTreeSet::new(),
+            mode: Mode::Step,
+        }
+    }
+
+    /// Pause the execution on a line. Returns false if there already was a
+    /// breakpoint on that line
+    pub fn add_breakpoint(&mut self, line: usize) -> bool {
+        self.breakpoints.insert(line)
+    }
+
+    /// Remove the breakpoint on a line. Returns false if there was none
+    pub fn remove_breakpoint(&mut self, line: usize) -> bool {
+        self.breakpoints.remove(&line)
+    }
+
+    /// Lines on which a breakpoint was set, in ascending order
+    pub fn breakpoints(&self) -> impl Iterator<Item = &usize> {
+        self.breakpoints.iter()
+    }
+
+    /// Resume the execution until the next statement
+    pub fn step(&mut self) {
+        self.mode = Mode::Step;
+    }
+
+    /// Resume the execution until the next statement, without pausing in the functions
+    /// called by the current one
+    pub fn next(&mut self, ctx: &Context) {
+        self.mode = Mode::Next(ctx.call_depth());
+    }
+
+    /// Resume the execution until the next breakpoint
+    pub fn resume(&mut self) {
+        self.mode = Mode::Continue;
+    }
+
+    /// Should the execution pause before executing a statement. Only statements of
+    /// the debugged source are considered
+    pub fn should_pause(&self, ctx: &Context, instruction: &dyn Instruction) -> bool {
+        let location = match instruction.location() {
+            Some(location) if *location.path() == self.path => location,
+            _ => return false,
+        };
+
+        self.breakpoints.contains(&location.start().line())
+            || match self.mode {
+                Mode::Step => true,
+                Mode::Next(depth) => ctx.call_depth() <= depth,
+                Mode::Continue => false,
+            }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    const SOURCE: &str = "func double(x: int) -> int {
+    y = x * 2;
+    y
+}
+
+mut a = 1;
+a = double(a);
+a = double(a);
+a";
+
+    /// Actions taken each time the execution pauses
+    #[derive(Clone, Copy)]
+    enum Action {
+        Step,
+        Next,
+        Continue,
+    }
+
+    /// Hook recording the lines on which the execution paused
+    struct Recorder {
+        debugger: Debugger,
+        action: Action,
+        paused: Rc<RefCell<Vec<usize>>>,
+    }
+
+    impl DebugHook for Recorder {
+        fn before_instruction(&mut self, ctx: &mut Context, instruction: &dyn Instruction) {
+            if !self.debugger.should_pause(ctx, instruction) {
+                return;
+            }
+
+            let line = instruction.location().unwrap().start().line();
+            self.paused.borrow_mut().push(line);
+
+            match self.action {
+                Action::Step => self.debugger.step(),
+                Action::Next => self.debugger.next(ctx),
+                Action::Continue => self.debugger.resume(),
+            }
+        }
+    }
+
+    fn paused_lines(action: Action, breakpoints: &[usize]) -> Vec<usize> {
+        let paused = Rc::new(RefCell::new(vec![]));
+
+        let mut debugger = Debugger::new(None);
+        breakpoints.iter().for_each(|line| {
+            debugger.add_breakpoint(*line);
+        });
+
+        let mut ctx = Context::new();
+        ctx.set_debug_hook(Box::new(Recorder {
+            debugger,
+            action,
+            paused: paused.clone(),
+        }));
+
+        parser::parse(&mut ctx, SOURCE, None).unwrap();
+        ctx.execute().unwrap();
+
+        paused.take()
+    }
+
+    #[test]
+    fn step_pauses_on_every_statement() {
+        assert_eq!(
+            paused_lines(Action::Step, &[]),
+            vec![6, 7, 2, 3, 8, 2, 3, 9]
+        );
+    }
+
+    #[test]
+    fn next_steps_over_function_calls() {
+        assert_eq!(paused_lines(Action::Next, &[]), vec![6, 7, 8, 9]);
+    }
+
+    #[test]
+    fn continue_pauses_on_breakpoints() {
+        assert_eq!(paused_lines(Action::Continue, &[]), vec![6]);
+        assert_eq!(paused_lines(Action::Continue, &[3, 9]), vec![6, 3, 3, 9]);
+    }
+
+    #[test]
+    fn breakpoints() {
+        let mut debugger = Debugger::new(None);
+
+        assert!(debugger.add_breakpoint(4));
+        assert!(debugger.add_breakpoint(2));
+        assert!(!debugger.add_breakpoint(4));
+        assert_eq!(debugger.breakpoints().collect::<Vec<_>>(), vec![&2, &4]);
+
+        assert!(debugger.remove_breakpoint(4));
+        assert!(!debugger.remove_breakpoint(4));
+        assert_eq!(debugger.breakpoints().collect::<Vec<_>>(), vec![&2]);
+    }
+}
@@ -1,48 +1,99 @@
 //! The Error module contains helpful wrapper around possible errors in jinko. They
 //! are used by the context as well as the parser.
+//!
+//! The library never emits errors nor exits the process by itself: Errors are kept in
+//! the [`ErrorHandler`] of the context until its user, such as the `jinko` binary,
+//! decides to emit them with [`crate::context::Context::emit_errors`] and to exit
+//! based on their presence.
+//!
+//! Each kind of error has a stable code, such as `E0003` for typechecking errors,
+//! which is displayed alongside the error. Errors can either be emitted in a human
+//! readable format, or as JSON for editor integrations. In that case, each error is
+//! emitted on its own line in the following shape:
+//!
+//! ```json
+//! {
+//!     "code": "E0003",
+//!     "severity": "error",
+//!     "message": "...",
+//!     "span": {"path": "file.jk", "start": {"line": 1, "column": 1}, "end": {...}},
+//!     "hints": [{"message": "...", "span": null}],
+//!     "trace": [{"function": "f", "module": null, "span": {...}}]
+//! }
+//! ```
+//!
+//! Errors happening during the execution of a function carry the calls which led to
+//! them, starting from the outermost one. They are emitted after the error as a
+//! traceback, with the most recent call last.
 
 use std::fmt::{Display, Formatter};
 use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
 
-use colored::Colorize;
+use colored::{ColoredString, Colorize};
 
+use crate::ast::Json;
 use crate::location::SpanTuple;
-use crate::parser::ParseInput;
 
 /// The role of the error handler is to keep track of errors and emit them properly
-/// once done
-#[derive(Default)]
+/// once asked to
+#[derive(Clone, Default)]
 pub struct ErrorHandler {
     errors: Vec<Error>,
+    /// Amount of errors which have been emitted already
+    emitted: usize,
+    /// Are warnings treated as errors
+    deny_warnings: bool,
+    format: ErrorFormat,
     file: PathBuf,
 }
 
 impl ErrorHandler {
-    /// Emit all the errors contained in a handler
-    pub fn emit(&self) {
-        if let Some(first_err) = self.errors.first() {
-            first_err.emit();
+    /// Emit all the errors contained in a handler which have not been emitted yet
+    pub fn emit(&mut self) {
+        for (position, e) in self.errors.iter().enumerate().skip(self.emitted) {
+            let denied = e.kind == ErrKind::Warning && self.deny_warnings;
+
+            match self.format {
+                ErrorFormat::Json => eprintln!("{}", e.to_json(denied)),
+                ErrorFormat::Human => {
+                    if position != 0 {
+                        eprintln!();
+                    }
+
+                    match denied {
+                        true => e.emit_denied(),
+                        false => e.emit(),
+                    }
+                }
+            }
         }
-        self.errors.iter().skip(1).for_each(|e| {
-            eprintln!();
-            e.emit()
-        });
+
+        self.emitted = self.errors.len();
     }
 
-    /// Add a new error to the handler
+    /// Add a new error to the handler. Warnings which were already added are ignored,
+    /// as the same code can get typechecked multiple times
     pub fn add(&mut self, err: Error) {
+        if err.kind == ErrKind::Warning && self.errors.contains(&err) {
+            return;
+        }
+
         self.errors.push(err)
     }
 
     /// Drains all the errors contained in another handler in order to accumulate them
     /// in one place
     pub fn append(&mut self, other: &mut ErrorHandler) {
-        self.errors.append(&mut other.errors)
+        other.errors.drain(..).for_each(|err| self.add(err));
+        other.emitted = 0;
     }
 
     /// Remove all the errors contained in the handler
     pub fn clear(&mut self) {
-        self.errors.clear()
+        self.errors.clear();
+        self.emitted = 0;
     }
 
     /// Set the file that should be used by the error handler. This function should be
@@ -51,9 +102,49 @@ impl ErrorHandler {
         self.file = file;
     }
 
-    /// Has the error handler seen errors or not
+    /// Treat warnings as errors or not
+    pub fn set_deny_warnings(&mut self, deny_warnings: bool) {
+        self.deny_warnings = deny_warnings;
+    }
+
+    /// Set the format in which errors are emitted
+    pub fn set_format(&mut self, format: ErrorFormat) {
+        self.format = format;
+    }
+
+    /// Get a reference on the errors currently kept in the handler
+    pub fn errors(&self) -> &[Error] {
+        &self.errors
+    }
+
+    /// Has the error handler seen errors or not. Warnings only count as errors if
+    /// they are denied
     pub fn has_errors(&self) -> bool {
-        !self.errors.is_empty()
+        self.errors
+            .iter()
+            .any(|e| e.kind != ErrKind::Warning || self.deny_warnings)
+    }
+}
+
+/// Format in which errors are emitted
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum ErrorFormat {
+    /// Colored errors, displayed alongside the source code they refer to
+    #[default]
+    Human,
+    /// One JSON object per error, meant to be consumed by other tools
+    Json,
+}
+
+impl FromStr for ErrorFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ErrorFormat, String> {
+        match s {
+            "human" => Ok(ErrorFormat::Human),
+            "json" => Ok(ErrorFormat::Json),
+            _ => Err(format!("unknown error format: `{}`", s)),
+        }
     }
 }
 
@@ -68,12 +159,17 @@ pub enum ErrKind {
     ExternFunc,
     IO,
     Debug,
+    /// The execution went over a limit set by the embedder of the context, such as
+    /// an instruction budget or a timeout
+    Limit,
+    Warning,
 }
 
 impl ErrKind {
     pub fn as_str(&self) -> &'static str {
         match self {
             ErrKind::Hint => "hint",
+            ErrKind::Warning => "warning",
             ErrKind::Parsing => "parsing",
             ErrKind::Context => "runtime",
             ErrKind::TypeChecker => "typechecker",
@@ -81,34 +177,119 @@ impl ErrKind {
             ErrKind::IO => "i/o",
             ErrKind::ExternFunc => "external function",
             ErrKind::Debug => "debug",
+            ErrKind::Limit => "limit",
+        }
+    }
+
+    /// Stable code identifying the kind of error. Codes must never be reused or
+    /// reassigned, as external tools rely on them
+    pub fn code(&self) -> &'static str {
+        match self {
+            ErrKind::Parsing => "E0001",
+            ErrKind::Context => "E0002",
+            ErrKind::TypeChecker => "E0003",
+            ErrKind::Generics => "E0004",
+            ErrKind::ExternFunc => "E0005",
+            ErrKind::IO => "E0006",
+            ErrKind::Debug => "E0007",
+            ErrKind::Limit => "E0008",
+            ErrKind::Warning => "W0001",
+            ErrKind::Hint => "H0001",
         }
     }
 }
 
+/// Function call being executed when an error occurred
+#[derive(Debug, PartialEq, Clone)]
+pub struct Frame {
+    /// Name of the called function, as written in its source
+    function: String,
+    /// Source the function was included from, if it was called through a prefix
+    module: Option<String>,
+    /// Location of the call
+    loc: Option<SpanTuple>,
+}
+
+impl Frame {
+    pub fn new(function: String, module: Option<String>, loc: Option<SpanTuple>) -> Frame {
+        Frame {
+            function,
+            module,
+            loc,
+        }
+    }
+
+    /// Get the name of the called function
+    pub fn function(&self) -> &str {
+        &self.function
+    }
+
+    /// Get the source the called function was included from, if any
+    pub fn module(&self) -> Option<&str> {
+        self.module.as_deref()
+    }
+
+    /// Get the location of the call, if it has one
+    pub fn loc(&self) -> Option<&SpanTuple> {
+        self.loc.as_ref()
+    }
+
+    fn to_json(&self) -> Json {
+        Json::object(vec![
+            ("function", self.function.as_str().into()),
+            ("module", self.module.clone().into()),
+            ("span", self.loc.as_ref().into()),
+        ])
+    }
+}
+
+impl Display for Frame {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        if let Some(loc) = &self.loc {
+            if let Some(path) = loc.path() {
+                write!(f, "{}:", path.display())?;
+            }
+            write!(f, "{}:{}: ", loc.start().line(), loc.start().column())?;
+        }
+
+        write!(f, "call to `{}`", self.function)?;
+        if let Some(module) = &self.module {
+            write!(f, " from `{}`", module)?;
+        }
+
+        Ok(())
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct Error {
     kind: ErrKind,
     msg: Option<String>,
     loc: Option<SpanTuple>,
     hints: Vec<Error>,
+    /// Calls being executed when the error occurred, the innermost one being last.
+    /// The trace is shared in order to keep errors small
+    trace: Arc<Vec<Frame>>,
 }
 
 impl Error {
-    fn emit_full_loc(&self, loc: &SpanTuple) {
+    fn emit_full_loc(&self, loc: &SpanTuple, label: ColoredString) {
         let (before_ctx, after_ctx) = loc.generate_context();
 
         if let Some(msg) = &self.msg {
-            if let Some(path) = loc.path() {
-                eprintln!(
+            match loc.path() {
+                Some(path) => eprintln!(
                     "{}: {}:{}:{}: {}",
-                    "error".black().on_yellow(),
+                    label,
                     path.display().to_string().yellow(),
                     loc.start().line(),
                     loc.start().column(),
                     msg
-                );
-                eprintln!();
+                ),
+                // Inputs which do not come from a file, such as the REPL's
+                None => eprintln!("{}: {}", label, msg),
             }
+            eprintln!();
         }
 
         if let Some(ctx) = before_ctx {
@@ -141,9 +322,9 @@ impl Error {
         }
     }
 
-    pub fn emit(&self) {
+    fn emit_with_label(&self, label: ColoredString) {
         if let Some(loc) = &self.loc {
-            self.emit_full_loc(loc);
+            self.emit_full_loc(loc, label);
         } else if let Some(msg) = &self.msg {
             eprintln!("{}", msg)
         }
@@ -153,6 +334,55 @@ impl Error {
         }
 
         self.hints.iter().skip(1).for_each(|hint| hint.emit_hint());
+
+        self.emit_trace();
+    }
+
+    /// Emit the calls which led to the error, with the most recent call last. Frames
+    /// repeated by a recursive function are only emitted once
+    fn emit_trace(&self) {
+        if self.trace.is_empty() {
+            return;
+        }
+
+        eprintln!();
+        eprintln!("{}", "traceback (most recent call last):".bold());
+
+        let mut frames = self.trace.iter().peekable();
+        while let Some(frame) = frames.next() {
+            eprintln!("    {}", frame);
+
+            let mut repeated = 0;
+            while frames.next_if_eq(&frame).is_some() {
+                repeated += 1;
+            }
+            if repeated != 0 {
+                eprintln!("    ... repeated {} more times", repeated);
+            }
+        }
+    }
+
+    pub fn emit(&self) {
+        let label = match self.kind {
+            ErrKind::Warning => self.label("warning").black().on_cyan(),
+            _ => self.label("error").black().on_yellow(),
+        };
+
+        self.emit_with_label(label)
+    }
+
+    /// Label of the error, containing its code, such as `error[E0003]`
+    fn label(&self, severity: &str) -> String {
+        format!("{}[{}]", severity, self.kind.code())
+    }
+
+    /// Emit a warning as an error, when warnings are denied
+    fn emit_denied(&self) {
+        self.emit_with_label(self.label("error").black().on_yellow());
+
+        Error::hint()
+            .with_msg(String::from("warnings are treated as errors"))
+            .emit_hint();
     }
 
     /// Emit a debug interpreter error - this is only useful for debugging the
@@ -195,12 +425,42 @@ impl Error {
         eprintln!();
     }
 
+    /// Convert the error to its JSON diagnostic. Denied warnings have an error
+    /// severity
+    pub fn to_json(&self, denied: bool) -> Json {
+        let severity = match self.kind {
+            ErrKind::Warning if !denied => "warning",
+            _ => "error",
+        };
+        let hints = self
+            .hints
+            .iter()
+            .map(|hint| {
+                Json::object(vec![
+                    ("message", hint.msg.clone().into()),
+                    ("span", hint.loc.as_ref().into()),
+                ])
+            })
+            .collect();
+        let trace = self.trace.iter().map(Frame::to_json).collect();
+
+        Json::object(vec![
+            ("code", self.kind.code().into()),
+            ("severity", severity.into()),
+            ("message", self.msg.clone().into()),
+            ("span", self.loc.as_ref().into()),
+            ("hints", Json::Array(hints)),
+            ("trace", Json::Array(trace)),
+        ])
+    }
+
     pub fn new(kind: ErrKind) -> Error {
         Error {
             kind,
             msg: None,
             loc: None,
             hints: vec![],
+            trace: Arc::new(vec![]),
         }
     }
 
@@ -208,6 +468,31 @@ impl Error {
         Error::new(ErrKind::Hint)
     }
 
+    /// Get the kind of the error
+    pub fn kind(&self) -> ErrKind {
+        self.kind
+    }
+
+    /// Get the message of the error, if it has one
+    pub fn msg(&self) -> Option<&str> {
+        self.msg.as_deref()
+    }
+
+    /// Get the location of the error, if it has one
+    pub fn loc(&self) -> Option<&SpanTuple> {
+        self.loc.as_ref()
+    }
+
+    /// Get the hints emitted alongside the error
+    pub fn hints(&self) -> &[Error] {
+        &self.hints
+    }
+
+    /// Get the calls which led to the error, the innermost one being last
+    pub fn trace(&self) -> &[Frame] {
+        &self.trace
+    }
+
     pub fn with_msg(self, msg: String) -> Error {
         Error {
             msg: Some(msg),
@@ -220,6 +505,14 @@ impl Error {
         Error { loc, ..self }
     }
 
+    /// Record the calls which led to the error
+    pub fn with_trace(self, trace: Vec<Frame>) -> Error {
+        Error {
+            trace: Arc::new(trace),
+            ..self
+        }
+    }
+
     // Add a hint to emit alongside the error
     pub fn with_hint(self, hint: Error) -> Error {
         let mut new_hints = self.hints;
@@ -230,11 +523,6 @@ impl Error {
             ..self
         }
     }
-
-    pub fn exit(&self) {
-        // The exit code depends on the kind of error
-        std::process::exit(self.kind as i32 + 1);
-    }
 }
 
 use std::convert::From;
@@ -247,60 +535,11 @@ impl From<io::Error> for Error {
     }
 }
 
-/// Nom errors are automatically parsing errors
-impl<'i> From<nom::Err<(ParseInput<'i>, nom::error::ErrorKind)>> for Error {
-    fn from(e: nom::Err<(ParseInput<'i>, nom::error::ErrorKind)>) -> Error {
-        // FIXME: Is this correct?
-        Error::new(ErrKind::Parsing).with_msg(e.to_string())
-    }
-}
-
-/// Likewise, if we need to convert from a nom::Err<jinko::Error> to a jinko::Error.
-/// While this pattern may seem weird, nom sometimes requires you to wrap errors in an
-/// Error or Failure state in order to specify to parse combinators how to proceed. You
-/// can see this being used with the `NomError` alias throughout the project. Thus, we
-/// might need to lower the wrapped errors back into our regular errors
-impl From<nom::Err<Error>> for Error {
-    fn from(e: nom::Err<Error>) -> Error {
-        match e {
-            nom::Err::Incomplete(_) => Error::new(ErrKind::Parsing),
-            nom::Err::Error(inner) | nom::Err::Failure(inner) => inner,
-        }
-    }
-}
-
-impl<'i> nom::error::ParseError<ParseInput<'i>> for Error {
-    fn from_error_kind(span: ParseInput<'i>, _: nom::error::ErrorKind) -> Error {
-        // FIXME: Add better location here in order to print whole line and
-        // display specific hint about parse error
-        Error::new(ErrKind::Parsing).with_loc(Some(SpanTuple::new(
-            span.extra,
-            span.into(),
-            span.into(),
-        )))
-    }
-
-    fn append(span: ParseInput<'i>, _: nom::error::ErrorKind, _other: Error) -> Error {
-        // FIXME: Should we accumulate errors this way?
-        // let other_msg = match other.msg {
-        //     Some(msg) => format!("{}\n", msg),
-        //     None => String::new(),
-        // };
-
-        Error::new(ErrKind::Parsing).with_loc(Some(SpanTuple::new(
-            span.extra,
-            span.into(),
-            span.into(),
-        )))
-        // /* FIXME  */ with_msg(format!("{}{}", other_msg, input))
-    }
-}
-
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
         write!(f, "{}", self.kind.as_str())?;
-        if self.msg.is_some() {
-            write!(f, ": {}", self.msg.as_ref().unwrap())?;
+        if let Some(msg) = &self.msg {
+            write!(f, ": {}", msg)?;
         }
 
         if let Some(loc) = &self.loc {
@@ -330,3 +569,76 @@ impl std::convert::From<std::env::VarError> for Error {
 }
 
 impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::location::Location;
+
+    #[test]
+    fn error_codes_are_unique() {
+        let kinds = [
+            ErrKind::Hint,
+            ErrKind::Parsing,
+            ErrKind::Context,
+            ErrKind::TypeChecker,
+            ErrKind::Generics,
+            ErrKind::ExternFunc,
+            ErrKind::IO,
+            ErrKind::Debug,
+            ErrKind::Limit,
+            ErrKind::Warning,
+        ];
+
+        for (idx, kind) in kinds.iter().enumerate() {
+            assert!(kinds[idx + 1..].iter().all(|k| k.code() != kind.code()));
+        }
+    }
+
+    #[test]
+    fn error_format_from_str() {
+        assert_eq!("human".parse(), Ok(ErrorFormat::Human));
+        assert_eq!("json".parse(), Ok(ErrorFormat::Json));
+        assert!("xml".parse::<ErrorFormat>().is_err());
+    }
+
+    #[test]
+    fn error_to_json() {
+        let loc = SpanTuple::new(Some("file.jk"), Location::new(1, 1), Location::new(1, 4));
+        let err = Error::new(ErrKind::TypeChecker)
+            .with_msg(String::from("mismatched types"))
+            .with_loc(Some(loc))
+            .with_hint(Error::hint().with_msg(String::from("use an int")));
+
+        assert_eq!(
+            err.to_json(false).to_string(),
+            r#"{"code":"E0003","severity":"error","message":"mismatched types","span":{"path":"file.jk","start":{"line":1,"column":1},"end":{"line":1,"column":4}},"hints":[{"message":"use an int","span":null}],"trace":[]}"#
+        );
+    }
+
+    #[test]
+    fn frame_display() {
+        let loc = SpanTuple::new(Some("file.jk"), Location::new(3, 5), Location::new(3, 9));
+        let frame = Frame::new(String::from("f"), Some(String::from("lib")), Some(loc));
+
+        assert_eq!(frame.to_string(), "file.jk:3:5: call to `f` from `lib`");
+        assert_eq!(
+            Frame::new(String::from("g"), None, None).to_string(),
+            "call to `g`"
+        );
+    }
+
+    #[test]
+    fn denied_warning_to_json() {
+        let warning = Error::new(ErrKind::Warning);
+
+        assert!(warning
+            .to_json(false)
+            .to_string()
+            .contains(r#""severity":"warning""#));
+        assert!(warning
+            .to_json(true)
+            .to_string()
+            .contains(r#""severity":"error""#));
+    }
+}
@@ -1,97 +1,262 @@
 //! The FFI module allows the jinko context to call into native code.
-//! Primitive types are converted to their C counterparts.
-//! FIXME
+//! Primitive types are converted to their C counterparts: `int` to `int64_t`,
+//! `float` to `double`, `bool` to `bool`, `char` to `char` and `string` to
+//! `const char *`. The `Pointer` type declared in the standard library is converted
+//! to and from `void *`.
 
 use crate::context::Context;
 use crate::error::{ErrKind, Error};
 use crate::instance::{FromObjectInstance, ObjectInstance, ToObjectInstance};
-use crate::instruction::{FunctionCall, FunctionDec};
-use crate::value::{JkBool, JkFloat, JkInt, JkString};
+use crate::instruction::{FunctionCall, FunctionDec, Instruction};
+use crate::typechecker::{CheckedType, TypeId};
+use crate::value::{JkBool, JkChar, JkFloat, JkInt, JkString};
 
 use libffi::high::{arg, call as ffi_call, Arg as FfiArg, CodePtr};
 use libloading::{Library, Symbol};
 
+use std::collections::HashSet;
+use std::env::consts::{DLL_PREFIX, DLL_SUFFIX};
 use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
 use std::path::{Path, PathBuf};
 
-fn find_lib(paths: &[PathBuf], lib_path: &Path) -> Result<Library, Error> {
-    for dir_base in paths.iter() {
-        let lib_path = PathBuf::from(dir_base).join(lib_path);
-        if lib_path.exists() {
-            if let Ok(lib) = unsafe { Library::new(lib_path) } {
-                return Ok(lib);
+/// Environment variable containing a list of directories in which to look for
+/// libraries before the system ones
+const JINKO_LIB_PATH: &str = "JINKO_LIB_PATH";
+
+/// Environment variable used by the platform's dynamic loader to find libraries
+#[cfg(target_os = "windows")]
+const SYSTEM_LIB_PATH: &str = "PATH";
+#[cfg(target_os = "macos")]
+const SYSTEM_LIB_PATH: &str = "DYLD_LIBRARY_PATH";
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+const SYSTEM_LIB_PATH: &str = "LD_LIBRARY_PATH";
+
+/// Directories containing the system's libraries. They are searched through, as well
+/// as the directories they contain
+#[cfg(target_os = "windows")]
+const SYSTEM_LIB_DIRS: &[&str] = &["C:\\Windows\\System32"];
+#[cfg(target_os = "macos")]
+const SYSTEM_LIB_DIRS: &[&str] = &["/usr/local/lib", "/usr/lib"];
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+const SYSTEM_LIB_DIRS: &[&str] = &["/lib", "/usr/lib"];
+
+/// Get the file names a library might have. If the library is given without an
+/// extension, such as `m`, then the platform specific names are tried as well:
+/// `libm.so` and `m.so` on Linux, `libm.dylib` and `m.dylib` on macOS or `m.dll` on
+/// Windows.
+fn lib_names(lib_path: &Path) -> Vec<PathBuf> {
+    let mut names = vec![lib_path.to_owned()];
+
+    if lib_path.extension().is_none() {
+        if let Some(name) = lib_path.file_name() {
+            let name = name.to_string_lossy();
+            let with_suffix = format!("{}{}", name, DLL_SUFFIX);
+            let with_prefix = format!("{}{}", DLL_PREFIX, with_suffix);
+
+            if !DLL_PREFIX.is_empty() {
+                names.push(lib_path.with_file_name(with_prefix));
             }
+            names.push(lib_path.with_file_name(with_suffix));
         }
     }
 
-    Err(Error::new(ErrKind::ExternFunc))
+    names
 }
 
-fn lib_from_root(base: &Path, lib_path: &Path) -> Result<Library, Error> {
-    // We search through all entries in the `base` directory as well as all the entries
-    // in the directories contained in `base`.
-    let mut dirs = vec![PathBuf::from(base)];
-    for entry in base.read_dir()? {
-        let entry = entry?;
-        if entry.file_type()?.is_dir() {
-            dirs.push(entry.path())
+/// Get the directories to search through, in order: The current directory, the
+/// directory of the script being executed, the directories contained in
+/// `JINKO_LIB_PATH` and in the platform's library path, and finally the system's
+/// library directories and their subdirectories.
+fn lib_dirs(ctx: &Context) -> Vec<PathBuf> {
+    let mut dirs = vec![PathBuf::new()];
+
+    if let Some(script_dir) = ctx.path().and_then(|path| path.parent()) {
+        dirs.push(script_dir.to_owned());
+    }
+
+    for var in [JINKO_LIB_PATH, SYSTEM_LIB_PATH] {
+        if let Some(paths) = std::env::var_os(var) {
+            dirs.extend(std::env::split_paths(&paths).filter(|p| !p.as_os_str().is_empty()));
         }
     }
 
-    find_lib(&dirs, lib_path)
+    for root in SYSTEM_LIB_DIRS.iter().map(PathBuf::from) {
+        let subdirs = root
+            .read_dir()
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir());
+
+        dirs.push(root.clone());
+        dirs.extend(subdirs);
+    }
+
+    let mut unique = HashSet::new();
+    dirs.retain(|dir| unique.insert(dir.clone()));
+
+    dirs
 }
 
-/// Fetch libraries from the path according to dlopen's (simplified) rules.
-/// If the LD_LIBRARY_PATH variable is set and contains a colon, then assume it
-/// contains a list of colon-separated directories and search through them.
-/// Then, search through /lib/, and then finally through /usr/lib/
-fn lib_from_path(lib_path: PathBuf) -> Result<Library, Error> {
-    if let Ok(lib) = lib_from_ld_library_path(&lib_path) {
-        return Ok(lib);
-    }
+/// Load a shared library and make its functions available to the context. If the
+/// library cannot be found, the error lists every location which was searched
+pub fn link_with(ctx: &mut Context, lib_path: PathBuf) -> Result<(), Error> {
+    let names = lib_names(&lib_path);
+    let dirs = lib_dirs(ctx);
 
-    if let Ok(lib) = lib_from_root(&PathBuf::from("/lib"), &lib_path) {
-        return Ok(lib);
+    let mut load_errors = Vec::new();
+
+    for dir in dirs.iter() {
+        for name in names.iter() {
+            let path = dir.join(name);
+            if !path.is_file() {
+                continue;
+            }
+
+            match unsafe { Library::new(&path) } {
+                Ok(lib) => {
+                    ctx.add_lib(lib);
+                    return Ok(());
+                }
+                Err(e) => load_errors.push(format!("\n    {}: {}", path.display(), e)),
+            }
+        }
     }
 
-    if let Ok(lib) = lib_from_root(&PathBuf::from("/usr/lib"), &lib_path) {
-        return Ok(lib);
+    let names = names
+        .iter()
+        .map(|name| format!("`{}`", name.display()))
+        .collect::<Vec<String>>()
+        .join(", ");
+    let dirs = dirs
+        .iter()
+        .map(|dir| match dir.as_os_str().is_empty() {
+            true => String::from("\n    ."),
+            false => format!("\n    {}", dir.display()),
+        })
+        .collect::<String>();
+
+    let mut msg = format!(
+        "couldn't link with library `{}`: tried {} in the following directories:{}",
+        lib_path.display(),
+        names,
+        dirs
+    );
+    if !load_errors.is_empty() {
+        msg.push_str("\nthe following libraries were found but could not be loaded:");
+        load_errors.iter().for_each(|e| msg.push_str(e));
     }
 
-    Err(Error::new(ErrKind::ExternFunc))
+    Err(Error::new(ErrKind::IO).with_msg(msg))
 }
 
-/// Look through the paths specified in the LD_LIBRARY_PATH environment variable (if any)
-fn lib_from_ld_library_path(lib_path: &Path) -> Result<Library, Error> {
-    let paths = std::env::var("LD_LIBRARY_PATH")?;
+/// Name of the jinko type used to represent pointers, declared in stdlib/ffi.jk
+const POINTER_TYPE: &str = "Pointer";
+
+enum FfiJkArg {
+    Int(i64),
+    Float(f64),
+    Bool(u8),
+    Char(u8),
+    Pointer(*const ()),
+}
 
-    for dir in paths.split(':') {
-        let path = PathBuf::from(dir).join(&lib_path);
-        if path.exists() {
-            return unsafe { Ok(Library::new(path)?) };
+fn unsupported_type(ty: &TypeId, position: &str) -> Error {
+    Error::new(ErrKind::ExternFunc).with_msg(format!(
+        "ffi module does not support `{}` as {}",
+        ty, position
+    ))
+}
+
+/// Convert a jinko instance to its C counterpart. Strings are converted to C strings,
+/// which are kept in `strings` so that they live for the whole duration of the call
+fn to_ffi_arg(
+    instance: &ObjectInstance,
+    ty: &TypeId,
+    strings: &mut Vec<CString>,
+) -> Result<FfiJkArg, Error> {
+    match ty.id() {
+        "int" => Ok(FfiJkArg::Int(JkInt::from_instance(instance).0)),
+        "float" => Ok(FfiJkArg::Float(JkFloat::from_instance(instance).0)),
+        "bool" => Ok(FfiJkArg::Bool(JkBool::from_instance(instance).0 as u8)),
+        "char" => {
+            let c = JkChar::from_instance(instance).0;
+            match c.is_ascii() {
+                true => Ok(FfiJkArg::Char(c as u8)),
+                false => Err(Error::new(ErrKind::ExternFunc).with_msg(format!(
+                    "character `{}` cannot be represented as a C `char`",
+                    c
+                ))),
+            }
+        }
+        "string" => {
+            let s = JkString::from_instance(instance).0;
+            let s = CString::new(s).map_err(|_| {
+                Error::new(ErrKind::ExternFunc).with_msg(String::from(
+                    "strings given to external functions cannot contain null bytes",
+                ))
+            })?;
+            let ptr = FfiJkArg::Pointer(s.as_ptr() as *const ());
+            strings.push(s);
+
+            Ok(ptr)
         }
+        POINTER_TYPE => {
+            let address = instance.get_field_as::<i64>("address")?;
+
+            Ok(FfiJkArg::Pointer(address as *const ()))
+        }
+        _ => Err(unsupported_type(ty, "an argument type")),
     }
+}
 
-    Err(Error::new(ErrKind::ExternFunc))
+/// Create an instance of jinko's pointer type from a C pointer
+fn pointer_instance(ptr: *const ()) -> ObjectInstance {
+    let address = JkInt::from(ptr as i64).to_instance();
+
+    ObjectInstance::from_fields(
+        CheckedType::Resolved(TypeId::from(POINTER_TYPE)),
+        vec![(String::from("address"), address)],
+    )
 }
 
-pub fn link_with(ctx: &mut Context, lib_path: PathBuf) -> Result<(), Error> {
-    let lib = if std::path::Path::new(&lib_path).exists() {
-        unsafe { Library::new(lib_path)? }
-    } else {
-        lib_from_path(lib_path)?
+/// Call a C function and convert its return value to a jinko instance
+unsafe fn call_with_return(
+    func: CodePtr,
+    args: &[FfiArg],
+    return_ty: Option<&TypeId>,
+) -> Result<Option<ObjectInstance>, Error> {
+    let ty = match return_ty {
+        None => {
+            ffi_call::<()>(func, args);
+            return Ok(None);
+        }
+        Some(ty) => ty,
     };
 
-    ctx.add_lib(lib);
+    let instance = match ty.id() {
+        "int" => JkInt::from(ffi_call::<i64>(func, args)).to_instance(),
+        "float" => JkFloat::from(ffi_call::<f64>(func, args)).to_instance(),
+        "bool" => JkBool::from(ffi_call::<u8>(func, args) != 0).to_instance(),
+        "char" => JkChar::from(char::from(ffi_call::<u8>(func, args))).to_instance(),
+        "string" => {
+            let raw_ptr = ffi_call::<*const c_char>(func, args);
+            // FIXME: Do we really want to return an empty string if the ffi
+            // function returns NULL?
+            let s = match raw_ptr.is_null() {
+                true => String::new(),
+                false => CStr::from_ptr(raw_ptr).to_string_lossy().into_owned(),
+            };
+
+            JkString::from(s).to_instance()
+        }
+        POINTER_TYPE => pointer_instance(ffi_call::<*const ()>(func, args)),
+        _ => return Err(unsupported_type(ty, "a return type")),
+    };
 
-    Ok(())
-}
-
-enum FfiJkArg {
-    Int(i64),
-    Float(f64),
-    Pointer(*const ()),
-    Error,
+    Ok(Some(instance))
 }
 
 pub fn execute(
@@ -99,120 +264,73 @@ pub fn execute(
     call: &FunctionCall,
     ctx: &mut Context,
 ) -> Result<Option<ObjectInstance>, Error> {
-    let sym = call.name().as_bytes();
-
-    let mut errors = vec![];
+    // Functions included under a prefix are exported by libraries under their own name
+    let sym = match ctx.symbols().get(call.name()) {
+        Some(symbol) => symbol.name().to_owned(),
+        None => call.name().to_owned(),
+    };
+    let display = ctx.symbols().display(call.name());
+
+    if let Some(ty) = dec.ty() {
+        if !matches!(
+            ty.id(),
+            "int" | "float" | "bool" | "char" | "string" | POINTER_TYPE
+        ) {
+            return Err(unsupported_type(ty, "a return type").with_loc(call.location().cloned()));
+        }
+    }
 
-    let jk_args: Vec<ObjectInstance> = call
-        .args()
-        .iter()
-        .map(|arg| arg.execute(ctx).unwrap())
-        .collect();
+    let mut jk_args = Vec::new();
+    for arg in call.args() {
+        match arg.execute(ctx) {
+            Some(instance) => jk_args.push(instance),
+            None => {
+                return Err(Error::new(ErrKind::ExternFunc).with_msg(format!(
+                    "could not evaluate argument `{}` of external function {}",
+                    arg.print(),
+                    display
+                )))
+            }
+        }
+    }
 
     // We need pointers for strings to live long enough... So keep them here
     // for the duration of the function call
     let mut strings = Vec::new();
 
-    // FIXME: Don't unwrap
-    let ffi_jk_args: Vec<FfiJkArg> = jk_args
+    let ffi_jk_args = jk_args
         .iter()
-        .zip(
-            dec.args()
-                .iter()
-                .map(|dec_arg| dec_arg.get_type().id().to_owned()),
-        )
-        .map(|(arg_value, arg_ty)| match arg_ty.as_str() {
-            "int" => FfiJkArg::Int(JkInt::from_instance(arg_value).0),
-            "bool" => FfiJkArg::Int(JkBool::from_instance(arg_value).0 as i64),
-            "float" => FfiJkArg::Float(JkFloat::from_instance(arg_value).0),
-            "string" => {
-                let arg = JkString::from_instance(arg_value).0;
-                let arg = CString::new(arg).unwrap();
-                let res = FfiJkArg::Pointer(arg.as_ptr() as *const ());
-                strings.push(arg);
-                res
-            }
-            _ => {
-                errors.push(Error::new(ErrKind::ExternFunc).with_msg(format!(
-                    "ffi module does not support calls with arguments of type {} yet",
-                    arg_ty
-                )));
-                FfiJkArg::Error
-            }
+        .zip(dec.args().iter())
+        .map(|(arg_value, dec_arg)| {
+            to_ffi_arg(arg_value, dec_arg.get_type(), &mut strings)
+                .map_err(|e| e.with_loc(call.location().cloned()))
         })
-        .collect();
-
-    if !errors.is_empty() {
-        errors.into_iter().for_each(|e| ctx.error(e));
-        return Err(Error::new(ErrKind::ExternFunc));
-    }
+        .collect::<Result<Vec<FfiJkArg>, Error>>()?;
 
-    // At this point, we cannot have errors anymore and will have returned from
-    // the function instead of making the call
     let args: Vec<FfiArg> = ffi_jk_args
         .iter()
         .map(|ffi_jk_arg| match ffi_jk_arg {
             FfiJkArg::Int(i) => arg(i),
             FfiJkArg::Float(f) => arg(f),
+            FfiJkArg::Bool(b) => arg(b),
+            FfiJkArg::Char(c) => arg(c),
             FfiJkArg::Pointer(p) => arg(p),
-            _ => unreachable!(),
         })
         .collect();
 
     for lib in ctx.libs().iter() {
         // FIXME: Rework this
-        let func = unsafe { lib.get::<Symbol<fn()>>(sym) };
+        let func = unsafe { lib.get::<Symbol<fn()>>(sym.as_bytes()) };
         let func = match func {
             Ok(func) => CodePtr::from_ptr(unsafe { func.into_raw().into_raw() }),
             Err(_) => continue,
         };
 
-        unsafe {
-            match dec.ty() {
-                None => {
-                    ffi_call::<()>(func, &args);
-                    return Ok(None);
-                }
-                Some(ty) => match ty.id() {
-                    "int" => {
-                        return Ok(Some(
-                            JkInt::from(ffi_call::<i64>(func, &args)).to_instance(),
-                        ))
-                    }
-                    "bool" => {
-                        return Ok(Some(
-                            JkBool::from(ffi_call::<i32>(func, &args) != 0).to_instance(),
-                        ))
-                    }
-                    "string" => {
-                        let raw_ptr = ffi_call::<*mut i8>(func, &args);
-                        // FIXME: Do we really want to return an empty string if the ffi
-                        // function returns NULL?
-                        return if raw_ptr.is_null() {
-                            Ok(Some(JkString::from(String::new()).to_instance()))
-                        } else {
-                            Ok(Some(
-                                JkString::from(CStr::from_ptr(raw_ptr).to_str().unwrap())
-                                    .to_instance(),
-                            ))
-                        };
-                    }
-                    _ => {
-                        ctx.error(Error::new(ErrKind::ExternFunc).with_msg(format!(
-                            "ffi module does not support {} as a return type",
-                            ty
-                        )));
-                        return Err(Error::new(ErrKind::ExternFunc));
-                    }
-                },
-            }
-        }
+        return unsafe { call_with_return(func, &args, dec.ty()) };
     }
 
-    Err(Error::new(ErrKind::ExternFunc).with_msg(format!(
-        "could not find external function `{}`",
-        call.name()
-    )))
+    Err(Error::new(ErrKind::ExternFunc)
+        .with_msg(format!("could not find external function {}", display)))
 }
 
 #[cfg(test)]
@@ -220,8 +338,7 @@ mod tests {
     use super::*;
     use crate::instance::ToObjectInstance;
     use crate::parser::constructs;
-    use crate::value::JkInt;
-    use crate::{jinko, span};
+    use crate::{jinko, jinko_fail, jk_execute, span};
 
     fn init_ctx() -> Context {
         jinko! {
@@ -250,7 +367,7 @@ mod tests {
         let call = call.downcast_ref::<FunctionCall>().unwrap();
 
         assert_eq!(
-            execute(&dec, &call, &mut i),
+            execute(dec, call, &mut i),
             Ok(Some(JkInt::from(15).to_instance()))
         );
     }
@@ -266,12 +383,129 @@ mod tests {
         let call = constructs::expr(span!("print_something()")).unwrap().1;
         let call = call.downcast_ref::<FunctionCall>().unwrap();
 
-        assert_eq!(execute(&dec, &call, &mut i), Ok(None));
+        assert_eq!(execute(dec, call, &mut i), Ok(None));
+    }
+
+    #[test]
+    fn t_float_return() {
+        let res = jk_execute! {
+            link_with("./tests/fixtures/clib/lib.so");
+            ext func half(value: float) -> float;
+            half(5.0)
+        };
+
+        assert_eq!(res, Some(JkFloat::from(2.5).to_instance()));
+    }
+
+    #[test]
+    fn t_char() {
+        let res = jk_execute! {
+            link_with("./tests/fixtures/clib/lib.so");
+            ext func next_char(c: char) -> char;
+            next_char('a')
+        };
+
+        assert_eq!(res, Some(JkChar::from('b').to_instance()));
+    }
+
+    #[test]
+    fn t_bool_return() {
+        let res = jk_execute! {
+            link_with("./tests/fixtures/clib/lib.so");
+            ext func is_positive(value: int) -> bool;
+            is_positive(15)
+        };
+
+        assert_eq!(res, Some(JkBool::from(true).to_instance()));
+    }
+
+    #[test]
+    fn t_pointer_roundtrip() {
+        let res = jk_execute! {
+            link_with("./tests/fixtures/clib/lib.so");
+            ext func stored_value_ptr() -> Pointer;
+            ext func deref(ptr: Pointer) -> int;
+            ptr = stored_value_ptr();
+            deref(ptr)
+        };
+
+        assert_eq!(res, Some(JkInt::from(42).to_instance()));
+    }
+
+    #[test]
+    fn t_null_pointer() {
+        let res = jk_execute! {
+            link_with("./tests/fixtures/clib/lib.so");
+            ext func null_ptr() -> Pointer;
+            null_ptr().is_null()
+        };
+
+        assert_eq!(res, Some(JkBool::from(true).to_instance()));
+    }
+
+    #[test]
+    fn t_unsupported_return_type() {
+        jinko_fail! {
+            link_with("./tests/fixtures/clib/lib.so");
+            ext func no_arg() -> Maybe_int;
+            no_arg()
+        };
+    }
+
+    #[test]
+    fn t_non_ascii_char_argument() {
+        jinko_fail! {
+            link_with("./tests/fixtures/clib/lib.so");
+            ext func next_char(c: char) -> char;
+            next_char('é')
+        };
+    }
+
+    #[test]
+    fn t_lib_names_with_extension() {
+        assert_eq!(
+            lib_names(Path::new("dir/lib.so")),
+            vec![PathBuf::from("dir/lib.so")]
+        );
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn t_lib_names_without_extension() {
+        assert_eq!(
+            lib_names(Path::new("dir/m")),
+            vec![
+                PathBuf::from("dir/m"),
+                PathBuf::from("dir/libm.so"),
+                PathBuf::from("dir/m.so")
+            ]
+        );
+    }
+
+    #[test]
+    fn t_link_with_relative_to_script() {
+        let mut ctx = Context::new();
+        ctx.set_path(Some(PathBuf::from("tests/fixtures/clib/clib.jk")));
+
+        assert!(link_with(&mut ctx, PathBuf::from("lib.so")).is_ok());
+    }
+
+    #[test]
+    fn t_link_with_lists_tried_paths() {
+        let mut ctx = Context::new();
+        ctx.set_path(Some(PathBuf::from("tests/fixtures/clib/clib.jk")));
+
+        let err = link_with(&mut ctx, PathBuf::from("jinko_nonexistent"))
+            .unwrap_err()
+            .to_string();
+
+        assert!(err.contains(&format!("{}jinko_nonexistent{}", DLL_PREFIX, DLL_SUFFIX)));
+        assert!(err.contains("tests/fixtures/clib"));
     }
 
     #[test]
     fn load_libs_stress() {
-        let ld_library_path = std::env::var("LD_LIBRARY_PATH").unwrap_or(String::new());
+        let ld_library_path = std::env::var("LD_LIBRARY_PATH").unwrap_or_default();
         let pwd = std::env::var("PWD").unwrap();
         std::env::set_var(
             "LD_LIBRARY_PATH",
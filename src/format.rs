@@ -0,0 +1,446 @@
+//! The format module re-emits parsed jinko source code in a canonical style. Each
+//! top-level instruction is printed back using [`Instruction::print`], which takes
+//! care of indentation and spacing, and the formatter lays the instructions out:
+//! Top-level statements end with a semicolon, and function declarations are
+//! separated from their surroundings by an empty line. Other empty lines are kept,
+//! but never more than one in a row.
+//!
+//! The instructions do not keep track of comments. Comments placed in between
+//! top-level instructions are taken from the source and kept as they are, while an
+//! instruction containing comments is re-emitted exactly as it was written, so that
+//! no comment is ever lost.
+
+use std::ops::Range;
+
+use crate::instruction::{Block, FunctionDec, FunctionKind, IfElse, Instruction, Loop, Match};
+use crate::location::Location;
+
+/// Find the ranges of all the comments in some jinko source code. Strings and
+/// characters are skipped, as well as the code inside of format strings
+fn comments(src: &str) -> Vec<Range<usize>> {
+    /// What the lexer is currently going through
+    enum Mode {
+        /// Code, with the depth of the blocks opened in it
+        Code(usize),
+        String,
+    }
+
+    let bytes = src.as_bytes();
+    let mut comments = vec![];
+    // The outermost mode is regular code, which is never left
+    let mut modes = vec![Mode::Code(0)];
+    let mut idx = 0;
+
+    while idx < bytes.len() {
+        let in_code = matches!(modes.last(), Some(Mode::Code(_)));
+
+        if in_code && (bytes[idx] == b'#' || src[idx..].starts_with("//")) {
+            let end = src[idx..].find('\n').map_or(src.len(), |len| idx + len);
+            comments.push(idx..end);
+            idx = end;
+            continue;
+        }
+
+        if in_code && src[idx..].starts_with("/*") {
+            // Multi-line comments can be nested
+            let (start, mut depth) = (idx, 0);
+            while idx < bytes.len() {
+                if src[idx..].starts_with("/*") {
+                    depth += 1;
+                    idx += 2;
+                } else if src[idx..].starts_with("*/") {
+                    depth -= 1;
+                    idx += 2;
+                    if depth == 0 {
+                        break;
+                    }
+                } else {
+                    idx += 1;
+                }
+            }
+            comments.push(start..idx);
+            continue;
+        }
+
+        let nested = modes.len() > 1;
+        match (modes.last_mut().unwrap(), bytes[idx]) {
+            (Mode::String, b'\\') => idx += 1,
+            (Mode::String, b'"') => {
+                modes.pop();
+            }
+            (Mode::String, b'{') => modes.push(Mode::Code(0)),
+            (Mode::Code(_), b'"') => modes.push(Mode::String),
+            (Mode::Code(_), b'\'') => {
+                // Skip the character, which might be a quote itself or an escaped one
+                let inner = &src[idx + 1..];
+                idx += match inner.strip_prefix('\\') {
+                    Some(escaped) => 1 + escaped.chars().next().map_or(0, char::len_utf8),
+                    None => inner.chars().next().map_or(0, char::len_utf8),
+                };
+            }
+            (Mode::Code(depth), b'{') => *depth += 1,
+            (Mode::Code(0), b'}') if nested => {
+                // End of a format argument, go back to the string
+                modes.pop();
+            }
+            (Mode::Code(depth), b'}') => *depth = depth.saturating_sub(1),
+            _ => {}
+        }
+
+        idx += 1;
+    }
+
+    comments
+}
+
+/// Top-level instruction, along with the part of the source code it was parsed from
+struct Item<'a> {
+    instruction: &'a dyn Instruction,
+    /// Range of the instruction's code in the source, without the comments and
+    /// whitespace that follow it
+    code: Range<usize>,
+    /// Does the instruction contain comments
+    has_comments: bool,
+}
+
+impl Item<'_> {
+    /// Does the item declare a function with a body, which should be set apart
+    fn is_function(&self) -> bool {
+        self.instruction
+            .downcast_ref::<FunctionDec>()
+            .is_some_and(|dec| dec.fn_kind() != FunctionKind::Ext)
+    }
+
+    /// Does the item need a semicolon at the end. Instructions ending with a block,
+    /// such as functions, conditions and loops, do not
+    fn needs_semicolon(&self) -> bool {
+        !(self.is_function()
+            || self.instruction.is::<IfElse>()
+            || self.instruction.is::<Loop>()
+            || self.instruction.is::<Match>()
+            || self.instruction.is::<Block>())
+    }
+
+    fn print(&self, src: &str) -> String {
+        let mut code = match self.has_comments {
+            true => src[self.code.clone()].to_string(),
+            false => self.instruction.print(),
+        };
+
+        if self.needs_semicolon() && !code.ends_with(';') {
+            code.push(';');
+        }
+
+        code
+    }
+}
+
+/// Output being built by the formatter
+#[derive(Default)]
+struct Output {
+    text: String,
+    /// Should an empty line be emitted before the next line
+    pending_empty_line: bool,
+}
+
+impl Output {
+    /// Add some lines to the output, starting on a new line
+    fn push_lines(&mut self, lines: &str) {
+        if !self.text.is_empty() {
+            self.text.push('\n');
+
+            if self.pending_empty_line {
+                self.text.push('\n');
+            }
+        }
+
+        self.pending_empty_line = false;
+        self.text.push_str(lines);
+    }
+
+    /// Add a comment to the end of the current line
+    fn push_trailing(&mut self, comment: &str) {
+        self.text.push(' ');
+        self.text.push_str(comment);
+    }
+}
+
+/// Does some whitespace contain an empty line
+fn has_empty_line(whitespace: &str) -> bool {
+    whitespace.matches('\n').count() > 1
+}
+
+/// Emit the comments contained in a part of the source code, as well as the empty
+/// lines between them. `trailing` indicates that a comment on the first line
+/// belongs to the end of the current line
+fn push_gap(
+    out: &mut Output,
+    src: &str,
+    gap: Range<usize>,
+    comments: &[Range<usize>],
+    trailing: bool,
+) {
+    let mut last = gap.start;
+
+    for comment in comments
+        .iter()
+        .filter(|c| c.start >= gap.start && c.end <= gap.end)
+    {
+        let whitespace = &src[last..comment.start];
+
+        if trailing && last == gap.start && !whitespace.contains('\n') && !out.text.is_empty() {
+            out.push_trailing(&src[comment.clone()]);
+        } else {
+            out.pending_empty_line |= has_empty_line(whitespace);
+            out.push_lines(&src[comment.clone()]);
+        }
+
+        last = comment.end;
+    }
+
+    out.pending_empty_line |= has_empty_line(&src[last..gap.end]);
+}
+
+/// Convert a location to an offset in the source
+fn offset(line_starts: &[usize], location: &Location) -> usize {
+    line_starts[location.line() - 1] + location.column() - 1
+}
+
+/// Format the source code from which the given top-level instructions were parsed
+pub fn format(src: &str, instructions: &[Box<dyn Instruction>]) -> String {
+    let comments = comments(src);
+    let in_comment = |idx: usize| comments.iter().find(|c| c.contains(&idx));
+    let line_starts: Vec<usize> = std::iter::once(0)
+        .chain(src.match_indices('\n').map(|(idx, _)| idx + 1))
+        .collect();
+
+    // Skip whitespace and comments backwards, returning the end of the code located
+    // before `idx`
+    let code_end = |mut idx: usize, min: usize| {
+        while idx > min {
+            match in_comment(idx - 1) {
+                Some(comment) => idx = comment.start,
+                None if src.as_bytes()[idx - 1].is_ascii_whitespace() => idx -= 1,
+                None => break,
+            }
+        }
+        idx
+    };
+
+    // Instructions without a location are considered to start with the previous one,
+    // so that no part of the source gets emitted twice
+    let mut starts: Vec<usize> = Vec::with_capacity(instructions.len());
+    for inst in instructions {
+        let previous = starts.last().copied().unwrap_or(0);
+        let start = inst
+            .location()
+            .map_or(previous, |loc| offset(&line_starts, loc.start()));
+        starts.push(start.max(previous));
+    }
+
+    // Parenthesized expressions start after their opening parentheses, which need
+    // to be kept with them
+    for idx in 1..starts.len() {
+        let mut start = starts[idx];
+        loop {
+            let end = code_end(start, starts[idx - 1]);
+            match end > starts[idx - 1] && src.as_bytes()[end - 1] == b'(' {
+                true => start = end - 1,
+                false => break,
+            }
+        }
+        starts[idx] = start;
+    }
+
+    let items: Vec<Item> = instructions
+        .iter()
+        .enumerate()
+        .map(|(idx, inst)| {
+            let end = starts.get(idx + 1).copied().unwrap_or(src.len());
+            let code = starts[idx]..code_end(end, starts[idx]);
+            let has_comments = comments
+                .iter()
+                .any(|c| c.start >= code.start && c.end <= code.end);
+
+            Item {
+                instruction: &**inst,
+                code,
+                has_comments,
+            }
+        })
+        .collect();
+
+    let mut out = Output::default();
+    let first_start = starts.first().copied().unwrap_or(src.len());
+    push_gap(&mut out, src, 0..first_start, &comments, false);
+    // Empty lines at the very beginning of the file are not kept
+    out.pending_empty_line &= !out.text.is_empty();
+
+    for (idx, item) in items.iter().enumerate() {
+        if let Some(previous) = idx.checked_sub(1).map(|prev| &items[prev]) {
+            out.pending_empty_line |= previous.is_function() || item.is_function();
+        }
+
+        out.push_lines(&item.print(src));
+
+        let gap_end = starts.get(idx + 1).copied().unwrap_or(src.len());
+        push_gap(&mut out, src, item.code.end..gap_end, &comments, true);
+    }
+
+    out.text.push('\n');
+    out.text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::Context;
+
+    fn fmt(src: &str) -> String {
+        Context::format_source(src).unwrap()
+    }
+
+    #[test]
+    fn find_comments() {
+        let src = "a // one\n\"// no\" /* two /* nested */ */ '/' # three";
+        let found: Vec<&str> = comments(src).into_iter().map(|c| &src[c]).collect();
+
+        assert_eq!(found, vec!["// one", "/* two /* nested */ */", "# three"]);
+    }
+
+    #[test]
+    fn find_comments_after_escaped_chars() {
+        let src = r#"a = '\\'; b = '\''; "/* no */" // one"#;
+        let found: Vec<&str> = comments(src).into_iter().map(|c| &src[c]).collect();
+
+        assert_eq!(found, vec!["// one"]);
+    }
+
+    #[test]
+    fn find_comments_in_format_string() {
+        let src = "s = \"{ f(\"// no\") } {{1}}\" // yes";
+        let found: Vec<&str> = comments(src).into_iter().map(|c| &src[c]).collect();
+
+        assert_eq!(found, vec!["// yes"]);
+    }
+
+    #[test]
+    fn format_statements() {
+        assert_eq!(fmt("x=1+2*3\ny  =  x"), "x = 1 + 2 * 3;\ny = x;\n");
+    }
+
+    #[test]
+    fn format_blocks() {
+        let src = "func f(a:int) -> int{ if a<2 {1} else { b = a; b*f(a-1) } }";
+        let expected = r#"func f(a: int) -> int {
+    if a < 2 {
+        1
+    } else {
+        b = a;
+        b * f(a - 1)
+    }
+}
+"#;
+
+        assert_eq!(fmt(src), expected);
+    }
+
+    #[test]
+    fn format_empty_lines() {
+        let src = "x = 1;\n\n\n\ny = 2;\nz = 3;\nfunc f() {}\nf()";
+
+        assert_eq!(
+            fmt(src),
+            "x = 1;\n\ny = 2;\nz = 3;\n\nfunc f() {}\n\nf();\n"
+        );
+    }
+
+    #[test]
+    fn format_keeps_comments() {
+        let src = "#!/usr/bin/env jinko\n\n// doc\nfunc f() -> int { 1 } // trailing\n/* end */";
+        let expected =
+            "#!/usr/bin/env jinko\n\n// doc\nfunc f() -> int {\n    1\n} // trailing\n/* end */\n";
+
+        assert_eq!(fmt(src), expected);
+    }
+
+    #[test]
+    fn format_keeps_instructions_with_comments() {
+        let src = "func f() {\n  // nothing\n}\nx   =   1";
+
+        assert_eq!(fmt(src), "func f() {\n  // nothing\n}\n\nx = 1;\n");
+    }
+
+    #[test]
+    fn format_literals() {
+        let src = r#"s = "a\n{1 + 2}\"b\"\{"; c = 'c'; f = 2.0; n = -4"#;
+
+        assert_eq!(
+            fmt(src),
+            "s = \"a\\n{1 + 2}\\\"b\\\"\\{\";\nc = 'c';\nf = 2.0;\nn = -4;\n"
+        );
+    }
+
+    #[test]
+    fn format_char_escapes() {
+        let src = r"a = '\n'; b = '\''; c = '\\'; d = '\u{1F600}'; e = '\u{7}'";
+
+        assert_eq!(
+            fmt(src),
+            "a = '\\n';\nb = '\\'';\nc = '\\\\';\nd = '😀';\ne = '\\u{7}';\n"
+        );
+    }
+
+    #[test]
+    fn format_parentheses() {
+        let src = "x = (1 + 2) * (3 - (4 - 5)); y = -(x + 1); z = (x + y).f(); (x)";
+
+        assert_eq!(
+            fmt(src),
+            "x = (1 + 2) * (3 - (4 - 5));\ny = -(x + 1);\nz = (x + y).f();\nx;\n"
+        );
+    }
+
+    #[test]
+    fn format_operator_associativity() {
+        let src = "x = 2**(3**2); y = (2**3)**2; z = a-(b-c)&(d|e)";
+
+        assert_eq!(
+            fmt(src),
+            "x = 2 ** 3 ** 2;\ny = (2 ** 3) ** 2;\nz = a - (b - c) & (d | e);\n"
+        );
+    }
+
+    #[test]
+    fn format_declarations() {
+        let src = r#"incl "../lib" as lib
+ext func ext_f(a: int, b: func(int) -> int) -> Vec[int];
+type Point[T](x: T, y: int = 0)
+p = Point[int](x: 1, y: 2); @dump()
+loop { break }"#;
+        let expected = r#"incl "../lib" as lib;
+ext func ext_f(a: int, b: func(int) -> int) -> Vec[int];
+type Point[T](x: T, y: int = 0);
+p = Point[int](x: 1, y: 2);
+@dump();
+loop {
+    break
+}
+"#;
+
+        assert_eq!(fmt(src), expected);
+    }
+
+    #[test]
+    fn format_is_idempotent() {
+        let src = "// c\nmut i = 0 while i < 3 { i = i + 1; } /* d */ func g() { }";
+        let once = fmt(src);
+
+        assert_eq!(fmt(&once), once);
+    }
+
+    #[test]
+    fn format_invalid_source() {
+        assert!(Context::format_source("func f( {").is_err());
+    }
+}
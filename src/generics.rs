@@ -121,6 +121,22 @@ pub fn original_name(mangled_name: &str) -> &str {
     }
 }
 
+/// Turn a mangled name back into the name it was written as in jinko source code,
+/// such as `Pair[int, string]` for `Pair+int+string`
+///
+/// ```rust
+/// use jinko::generics::display_name;
+///
+/// assert_eq!(display_name("Pair+int+string"), "Pair[int, string]");
+/// assert_eq!(display_name("int"), "int");
+/// ```
+pub fn display_name(mangled_name: &str) -> String {
+    match mangled_name.split_once('+') {
+        Some((name, generics)) => format!("{}[{}]", name, generics.replace('+', ", ")),
+        None => String::from(mangled_name),
+    }
+}
+
 /// Since most of the instructions cannot do generic expansion, we can implement
 /// default methods which do nothing. This avoid more boilerplate code for instructions
 /// such as constants or variables which cannot be generic.
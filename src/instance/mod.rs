@@ -6,27 +6,42 @@
 //! For example, a variable contains an ObjectInstance. Since a variable cannot be uninitialized,
 //! the instance is always there. The type of the ObjectInstance might be resolved later, after
 //! different passes of the typechecker.
+//! Instances are immutable once created: Their data and fields are reference counted
+//! and shared between the clones of an instance, which makes reading a variable cheap
+//! no matter the size of the value it contains.
 
 use std::collections::HashMap;
+use std::rc::Rc;
 
 use crate::error::{ErrKind, Error};
+use crate::generics;
 use crate::indent::Indent;
+use crate::native::NativeType;
 use crate::typechecker::CheckedType;
+use crate::typechecker::TypeId;
+use crate::value::{JkBool, JkChar, JkFloat, JkFunc, JkInt, JkString, MAP_TYPE, VEC_TYPE};
 
 pub type Name = String;
 type Offset = usize;
+type Position = usize;
 
+/// A field of an instance, along with its offset in the instance's data and its
+/// position in the list of fields the instance was created with
 #[derive(Debug, PartialEq, Clone)]
-pub struct FieldInstance(Offset, ObjectInstance);
+pub struct FieldInstance(Offset, Position, ObjectInstance);
 
 impl FieldInstance {
     pub fn offset(&self) -> &Offset {
         &self.0
     }
 
-    pub fn instance(&self) -> &ObjectInstance {
+    pub fn position(&self) -> &Position {
         &self.1
     }
+
+    pub fn instance(&self) -> &ObjectInstance {
+        &self.2
+    }
 }
 
 type FieldsMap = HashMap<Name, FieldInstance>;
@@ -34,12 +49,17 @@ type FieldsMap = HashMap<Name, FieldInstance>;
 /// The type is optional. At first, the type might not be known, and will only be
 /// revealed during the typechecking phase. `size` is the size of the instance in bytes.
 /// It's the same as `data.len()`. `data` is the raw byte value of the instance.
+/// Instances of functions used as values also keep a reference to the function's
+/// declaration in `function`. Instances of enums are tagged with the name of the
+/// variant they were created from in `variant`.
 #[derive(Debug, PartialEq, Clone)]
 pub struct ObjectInstance {
     ty: CheckedType,
     size: usize,
-    data: Vec<u8>,
-    fields: Option<FieldsMap>,
+    data: Rc<[u8]>,
+    fields: Option<Rc<FieldsMap>>,
+    function: Option<JkFunc>,
+    variant: Option<Name>,
 }
 
 impl ObjectInstance {
@@ -55,13 +75,39 @@ impl ObjectInstance {
         data: Vec<u8>,
         fields: Option<Vec<(Name, ObjectInstance)>>,
     ) -> ObjectInstance {
-        let fields = fields.map(ObjectInstance::fields_vec_to_hash_map);
+        let fields = fields.map(|fields| Rc::new(ObjectInstance::fields_vec_to_hash_map(fields)));
 
         ObjectInstance {
             ty,
             size,
-            data,
+            data: data.into(),
             fields,
+            function: None,
+            variant: None,
+        }
+    }
+
+    /// Create a new instance referring to a function. The data of the instance is the
+    /// name of the function
+    pub fn from_function(ty: CheckedType, function: JkFunc) -> ObjectInstance {
+        let name = function.dec().name().as_bytes().to_vec();
+
+        ObjectInstance {
+            function: Some(function),
+            ..ObjectInstance::new(ty, name.len(), name, None)
+        }
+    }
+
+    /// Create a new instance without fields, sharing data which is already reference
+    /// counted instead of copying it
+    pub fn from_shared(ty: CheckedType, data: Rc<[u8]>) -> ObjectInstance {
+        ObjectInstance {
+            ty,
+            size: data.len(),
+            data,
+            fields: None,
+            function: None,
+            variant: None,
         }
     }
 
@@ -90,33 +136,145 @@ impl ObjectInstance {
         &self.data
     }
 
+    /// Get a new reference to the data of the instance, without copying it
+    pub fn shared_data(&self) -> Rc<[u8]> {
+        self.data.clone()
+    }
+
     pub fn size(&self) -> usize {
         self.size
     }
 
-    pub fn get_field(&self, field_name: &str) -> Result<ObjectInstance, Error> {
+    /// Create a new instance from its fields. The data of the instance is laid out
+    /// from the data of each field, in the order they are given in
+    pub fn from_fields(ty: CheckedType, fields: Vec<(Name, ObjectInstance)>) -> ObjectInstance {
+        let data: Vec<u8> = fields
+            .iter()
+            .flat_map(|(_, instance)| instance.data().iter().copied())
+            .collect();
+
+        ObjectInstance::new(ty, data.len(), data, Some(fields))
+    }
+
+    fn field(&self, field_name: &str) -> Result<&FieldInstance, Error> {
         match self.fields.as_ref() {
             None => {
                 Err(Error::new(ErrKind::Context).with_msg(String::from("no fields on instance")))
             }
-            Some(fields) => fields.get(field_name).map_or(
-                Err(Error::new(ErrKind::Context)
-                    .with_msg(format!("field `{}` does not exist on instance", field_name))),
-                |FieldInstance(_, instance)| Ok(instance.clone()),
-            ),
+            Some(fields) => fields.get(field_name).ok_or_else(|| {
+                Error::new(ErrKind::Context)
+                    .with_msg(format!("field `{}` does not exist on instance", field_name))
+            }),
+        }
+    }
+
+    pub fn get_field(&self, field_name: &str) -> Result<ObjectInstance, Error> {
+        self.field(field_name).map(|field| field.instance().clone())
+    }
+
+    /// Get the value of a field as a Rust value, making sure that the field has the
+    /// type corresponding to `T`
+    pub fn get_field_as<T: NativeType>(&self, field_name: &str) -> Result<T, Error> {
+        let instance = self.field(field_name)?.instance();
+        let expected = T::type_name().map_or(CheckedType::Void, |ty| {
+            CheckedType::Resolved(TypeId::from(ty))
+        });
+
+        match instance.ty() == &expected {
+            true => Ok(T::from_native(instance)),
+            false => Err(Error::new(ErrKind::Context).with_msg(format!(
+                "field `{}` is of type `{}`, not `{}`",
+                field_name,
+                instance.type_name(),
+                T::type_name().unwrap_or("void")
+            ))),
+        }
+    }
+
+    /// Get the bytes of a field inside the data of the instance
+    pub fn field_data(&self, field_name: &str) -> Result<&[u8], Error> {
+        let field = self.field(field_name)?;
+        let start = *field.offset();
+
+        Ok(&self.data[start..start + field.instance().size()])
+    }
+
+    /// Create a copy of the instance in which a field is replaced by another value
+    /// of the same type. The data of the instance is laid out again, so that the
+    /// offsets of the following fields stay valid
+    pub fn with_field(
+        &self,
+        field_name: &str,
+        value: ObjectInstance,
+    ) -> Result<ObjectInstance, Error> {
+        let previous = self.field(field_name)?.instance();
+        if previous.ty() != value.ty() {
+            return Err(Error::new(ErrKind::Context).with_msg(format!(
+                "trying to assign value of type `{}` to field `{}` of type `{}`",
+                value.type_name(),
+                field_name,
+                previous.type_name()
+            )));
+        }
+
+        let fields = self
+            .ordered_fields()
+            .into_iter()
+            .map(|(name, instance)| match name == field_name {
+                true => (name.clone(), value.clone()),
+                false => (name.clone(), instance.clone()),
+            })
+            .collect();
+
+        Ok(ObjectInstance {
+            function: self.function.clone(),
+            variant: self.variant.clone(),
+            ..ObjectInstance::from_fields(self.ty.clone(), fields)
+        })
+    }
+
+    pub fn fields(&self) -> Option<&FieldsMap> {
+        self.fields.as_deref()
+    }
+
+    /// Tag the instance with the variant of its enum it was created from
+    pub fn with_variant(self, variant: Name) -> ObjectInstance {
+        ObjectInstance {
+            variant: Some(variant),
+            ..self
         }
     }
 
-    pub fn fields(&self) -> &Option<FieldsMap> {
-        &self.fields
+    /// Get the name of the variant the instance was created from, if it is an
+    /// instance of an enum
+    pub fn variant(&self) -> Option<&str> {
+        self.variant.as_deref()
+    }
+
+    /// Get the function the instance refers to, if it is a function value
+    pub fn function(&self) -> Option<&JkFunc> {
+        self.function.as_ref()
+    }
+
+    /// Get the fields of the instance in the order they were declared in
+    pub fn ordered_fields(&self) -> Vec<(&Name, &ObjectInstance)> {
+        let mut fields: Vec<(&Name, &FieldInstance)> =
+            self.fields().into_iter().flatten().collect();
+
+        fields.sort_by_key(|(_, field)| field.position());
+
+        fields
+            .into_iter()
+            .map(|(name, field)| (name, field.instance()))
+            .collect()
     }
 
     fn fields_vec_to_hash_map(vec: Vec<(Name, ObjectInstance)>) -> FieldsMap {
         let mut current_offset: usize = 0;
         let mut hashmap = FieldsMap::new();
-        for (name, instance) in vec {
+        for (position, (name, instance)) in vec.into_iter().enumerate() {
             let inst_size = instance.size();
-            hashmap.insert(name, FieldInstance(current_offset, instance));
+            hashmap.insert(name, FieldInstance(current_offset, position, instance));
             current_offset += inst_size;
         }
 
@@ -136,7 +294,7 @@ impl ObjectInstance {
         if let Some(fields) = &instance.fields {
             base = format!("{}{}fields:\n", base, indent);
 
-            for (name, FieldInstance(_, instance)) in fields {
+            for (name, FieldInstance(_, _, instance)) in fields.iter() {
                 base = format!(
                     "{}{}{}:\n{}",
                     base,
@@ -153,6 +311,119 @@ impl ObjectInstance {
     pub fn as_string(&self) -> String {
         ObjectInstance::as_string_inner(self, Indent::default())
     }
+
+    /// Compare two instances structurally: They are equal if they have the same type
+    /// and either contain the same data, or fields which are deeply equal to one
+    /// another. Unlike `==`, this does not depend on the layout of the instances, and
+    /// floating point numbers are compared by value
+    // FIXME: Protect against cycles once instances are able to contain references
+    pub fn deep_eq(&self, other: &ObjectInstance) -> bool {
+        if self.ty != other.ty || self.variant != other.variant {
+            return false;
+        }
+
+        if self.function.is_some() || other.function.is_some() {
+            return self.function == other.function;
+        }
+
+        match (&self.ty, self.fields(), other.fields()) {
+            (CheckedType::Resolved(ty), _, _) if ty.id() == "float" => {
+                JkFloat::from_instance(self).0 == JkFloat::from_instance(other).0
+            }
+            (_, Some(lhs), Some(rhs)) => {
+                lhs.len() == rhs.len()
+                    && lhs.iter().all(|(name, field)| {
+                        rhs.get(name)
+                            .is_some_and(|other| field.instance().deep_eq(other.instance()))
+                    })
+            }
+            (_, None, None) => self.data() == other.data(),
+            _ => false,
+        }
+    }
+
+    /// Get the name of the type of the instance, as it would be written in jinko source
+    /// code: Generic types are named `Vec[int]` instead of their mangled name
+    pub fn type_name(&self) -> String {
+        match &self.ty {
+            CheckedType::Resolved(ty @ TypeId::Functor { .. }) => ty.print(),
+            CheckedType::Resolved(ty) => generics::display_name(ty.id()),
+            CheckedType::Void => String::from("void"),
+            _ => String::from("<unknown>"),
+        }
+    }
+
+    fn display_inner(&self, nested: bool) -> String {
+        if let Some(function) = &self.function {
+            return function.signature();
+        }
+
+        let ty = match &self.ty {
+            CheckedType::Resolved(ty) => ty.id(),
+            CheckedType::Void => return String::from("void"),
+            _ => return String::from("<unknown>"),
+        };
+
+        let display_fields = |fields: Vec<(&Name, &ObjectInstance)>, named: bool| {
+            fields
+                .into_iter()
+                .map(|(name, instance)| match named {
+                    true => format!("{}: {}", name, instance.display_inner(true)),
+                    false => instance.display_inner(true),
+                })
+                .collect::<Vec<String>>()
+                .join(", ")
+        };
+
+        match ty {
+            "int" => JkInt::from_instance(self).0.to_string(),
+            "float" => JkFloat::from_instance(self).0.to_string(),
+            "bool" => JkBool::from_instance(self).0.to_string(),
+            // Strings and chars are quoted when they are part of another value, so that
+            // `Point(name: "1", x: 1)` stays readable
+            "char" if nested => format!("'{}'", JkChar::from_instance(self).0),
+            "char" => JkChar::from_instance(self).0.to_string(),
+            "string" if nested => format!("\"{}\"", JkString::from_instance(self).0),
+            "string" => JkString::from_instance(self).0,
+            VEC_TYPE => format!("[{}]", display_fields(self.ordered_fields(), false)),
+            MAP_TYPE => {
+                let entries = self
+                    .ordered_fields()
+                    .into_iter()
+                    .map(|(key, value)| format!("\"{}\": {}", key, value.display_inner(true)))
+                    .collect::<Vec<String>>();
+
+                format!("{{{}}}", entries.join(", "))
+            }
+            _ => {
+                let name = match &self.variant {
+                    Some(variant) => variant.clone(),
+                    None => self.type_name(),
+                };
+
+                match self.fields() {
+                    Some(fields) if !fields.is_empty() => {
+                        format!("{}({})", name, display_fields(self.ordered_fields(), true))
+                    }
+                    _ => name,
+                }
+            }
+        }
+    }
+
+    /// Render the instance as a human readable string. Primitive values are displayed
+    /// as-is, while custom types are displayed as `TypeName(field: value, ...)`, with
+    /// each of their fields displayed recursively
+    pub fn display(&self) -> String {
+        self.display_inner(false)
+    }
+
+    /// Render the instance like [`ObjectInstance::display`], but with strings and
+    /// chars quoted even at the top level, so that they can be told apart from other
+    /// values: `"15"` and `15` are displayed differently
+    pub fn repr(&self) -> String {
+        self.display_inner(true)
+    }
 }
 
 /// Convert a Jinko type to an instance. This is handled by jinko's primitive types
@@ -173,7 +444,8 @@ mod tests {
     use crate::context::Context;
     use crate::parser::constructs;
     use crate::span;
-    use crate::value::JkInt;
+    use crate::typechecker::TypeId;
+    use crate::value::{JkInt, JkVec};
 
     fn setup() -> Context {
         let mut ctx = Context::new();
@@ -227,4 +499,253 @@ mod tests {
         assert_eq!(v_f_x, JkInt::from(1).to_instance());
         assert_eq!(v_f_y, JkInt::from(2).to_instance());
     }
+
+    #[test]
+    fn t_variable_read_shares_data() {
+        let mut ctx = crate::jinko! {
+            s = "a string which should not be copied when read";
+        };
+
+        let inst = constructs::expr(span!("s")).unwrap().1;
+        let first = inst.execute(&mut ctx).unwrap();
+        let second = inst.execute(&mut ctx).unwrap();
+
+        assert_eq!(first.data().as_ptr(), second.data().as_ptr());
+    }
+
+    #[test]
+    fn t_deep_eq_custom_types() {
+        let ctx = crate::jinko! {
+            type Point(x: int, y: int);
+            type Segment(from: Point, to: Point);
+
+            a = Segment(from: Point(x: 1, y: 2), to: Point(x: 3, y: 4));
+            b = Segment(from: Point(x: 1, y: 2), to: Point(x: 3, y: 4));
+            c = Segment(from: Point(x: 1, y: 2), to: Point(x: 3, y: 5));
+        };
+
+        let get = |name| ctx.get_variable(name).unwrap().instance();
+
+        assert!(get("a").deep_eq(&get("b")));
+        assert!(!get("a").deep_eq(&get("c")));
+    }
+
+    #[test]
+    fn t_deep_eq_different_types() {
+        let ctx = crate::jinko! {
+            type Meters(value: int);
+            type Seconds(value: int);
+
+            m = Meters(value: 1);
+            s = Seconds(value: 1);
+        };
+
+        let m = ctx.get_variable("m").unwrap().instance();
+        let s = ctx.get_variable("s").unwrap().instance();
+
+        assert!(!m.deep_eq(&s));
+        assert!(!JkInt::from(1)
+            .to_instance()
+            .deep_eq(&JkFloat::from(1.0).to_instance()));
+    }
+
+    #[test]
+    fn t_deep_eq_ignores_layout() {
+        let lhs = ObjectInstance::new(
+            CheckedType::Resolved(TypeId::from("map")),
+            0,
+            vec![],
+            Some(vec![
+                (String::from("a"), JkInt::from(1).to_instance()),
+                (String::from("b"), JkString::from("").to_instance()),
+            ]),
+        );
+        let rhs = ObjectInstance::new(
+            CheckedType::Resolved(TypeId::from("map")),
+            0,
+            vec![],
+            Some(vec![
+                (String::from("b"), JkString::from("").to_instance()),
+                (String::from("a"), JkInt::from(1).to_instance()),
+            ]),
+        );
+
+        assert_ne!(lhs, rhs);
+        assert!(lhs.deep_eq(&rhs));
+    }
+
+    #[test]
+    fn t_deep_eq_floats() {
+        let zero = JkFloat::from(0.0).to_instance();
+        let negative_zero = JkFloat::from(-0.0).to_instance();
+        let nan = JkFloat::from(f64::NAN).to_instance();
+
+        assert!(zero.deep_eq(&negative_zero));
+        assert!(!nan.deep_eq(&nan));
+    }
+
+    #[test]
+    fn t_get_field_as() {
+        let ctx = crate::jinko! {
+            type Person(name: string, age: int);
+
+            p = Person(name: "jinko", age: 3);
+        };
+
+        let p = ctx.get_variable("p").unwrap().instance();
+
+        assert_eq!(p.get_field_as::<String>("name").unwrap(), "jinko");
+        assert_eq!(p.get_field_as::<i64>("age").unwrap(), 3);
+        assert!(p.get_field_as::<i64>("name").is_err());
+        assert!(p.get_field_as::<i64>("height").is_err());
+        assert!(JkInt::from(3)
+            .to_instance()
+            .get_field_as::<i64>("age")
+            .is_err());
+    }
+
+    #[test]
+    fn t_field_data() {
+        let mut ctx = setup();
+
+        let inst = constructs::expr(span!("p")).unwrap().1;
+        let p = inst.execute(&mut ctx).unwrap();
+
+        assert_eq!(p.size(), p.data().len());
+        assert_eq!(
+            p.field_data("x").unwrap(),
+            JkInt::from(1).to_instance().data()
+        );
+        assert_eq!(
+            p.field_data("y").unwrap(),
+            JkInt::from(2).to_instance().data()
+        );
+    }
+
+    #[test]
+    fn t_with_field() {
+        let instance = ObjectInstance::from_fields(
+            CheckedType::Resolved(TypeId::from("Person")),
+            vec![
+                (String::from("name"), JkString::from("jk").to_instance()),
+                (String::from("age"), JkInt::from(3).to_instance()),
+            ],
+        );
+
+        let renamed = instance
+            .with_field("name", JkString::from("jinko").to_instance())
+            .unwrap();
+
+        assert_eq!(renamed.get_field_as::<String>("name").unwrap(), "jinko");
+        assert_eq!(renamed.get_field_as::<i64>("age").unwrap(), 3);
+        assert_eq!(
+            renamed.field_data("age").unwrap(),
+            JkInt::from(3).to_instance().data()
+        );
+        assert_eq!(renamed.size(), instance.size() + 3);
+        assert_eq!(instance.get_field_as::<String>("name").unwrap(), "jk");
+
+        assert!(instance
+            .with_field("age", JkString::from("3").to_instance())
+            .is_err());
+        assert!(instance
+            .with_field("height", JkInt::from(3).to_instance())
+            .is_err());
+    }
+
+    #[test]
+    fn t_ordered_fields() {
+        let mut ctx = setup();
+
+        let inst = constructs::expr(span!("v")).unwrap().1;
+        let v = inst.execute(&mut ctx).unwrap();
+        let names: Vec<&Name> = v
+            .ordered_fields()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+
+        assert_eq!(names, vec!["f", "s"]);
+    }
+
+    #[test]
+    fn t_type_name() {
+        let instance = ObjectInstance::new(
+            CheckedType::Resolved(TypeId::from("Pair+int+string")),
+            0,
+            vec![],
+            None,
+        );
+
+        assert_eq!(JkInt::from(15).to_instance().type_name(), "int");
+        assert_eq!(instance.type_name(), "Pair[int, string]");
+        assert_eq!(ObjectInstance::empty().type_name(), "<unknown>");
+    }
+
+    #[test]
+    fn t_display_primitives() {
+        assert_eq!(JkInt::from(-15).to_instance().display(), "-15");
+        assert_eq!(JkString::from("jinko").to_instance().display(), "jinko");
+        assert_eq!(JkChar::from('j').to_instance().display(), "j");
+        assert_eq!(JkBool::from(false).to_instance().display(), "false");
+        assert_eq!(JkFloat::from(1.5).to_instance().display(), "1.5");
+    }
+
+    #[test]
+    fn t_display_custom_type() {
+        let mut ctx = setup();
+
+        let inst = constructs::expr(span!("v")).unwrap().1;
+        let v = inst.execute(&mut ctx).unwrap();
+
+        assert_eq!(
+            v.display(),
+            "Vec2(f: Point(x: 1, y: 2), s: Point(x: 1, y: 2))"
+        );
+    }
+
+    #[test]
+    fn t_display_quotes_nested_strings() {
+        let ctx = crate::jinko! {
+            type Person(name: string, initial: char);
+
+            p = Person(name: "", initial: 'j');
+        };
+
+        let p = ctx.get_variable("p").unwrap().instance();
+
+        assert_eq!(p.display(), "Person(name: \"\", initial: 'j')");
+    }
+
+    #[test]
+    fn t_repr_quotes_strings() {
+        assert_eq!(JkString::from("15").to_instance().repr(), "\"15\"");
+        assert_eq!(JkChar::from('j').to_instance().repr(), "'j'");
+        assert_eq!(JkInt::from(15).to_instance().repr(), "15");
+    }
+
+    #[test]
+    fn t_display_vec() {
+        let vec = JkVec::from(vec![
+            JkInt::from(1).to_instance(),
+            JkString::from("two").to_instance(),
+        ]);
+
+        assert_eq!(vec.to_instance().display(), "[1, \"two\"]");
+    }
+
+    #[test]
+    fn t_display_generic_type() {
+        let instance = ObjectInstance::new(
+            CheckedType::Resolved(TypeId::from("Pair+int+string")),
+            0,
+            vec![],
+            Some(vec![
+                (String::from("0"), JkInt::from(1).to_instance()),
+                (String::from("1"), JkString::from("b").to_instance()),
+            ]),
+        );
+
+        assert_eq!(instance.display(), "Pair[int, string](0: 1, 1: \"b\")");
+    }
 }
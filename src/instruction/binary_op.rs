@@ -2,18 +2,31 @@
 //! 1 + 2, a BinaryOp will be created containing "1" as a left hand side operand, "2" as
 //! a right hand side operand and "+" as the operator.
 //!
-//! The available operators are `+`, `-`, `*` and `/`.
-//! That is `Add`, `Substract`, `Multiply` and `Divide`.
+//! The available arithmetic operators are `+`, `-`, `*`, `/`, `%` and `**`.
+//! That is `Add`, `Substract`, `Multiply`, `Divide`, `Remainder` and `Power`.
+//! The bit-wise operators `&`, `|`, `^`, `<<` and `>>` can only be applied to
+//! integers. Operations on integers which overflow raise an error.
+//!
+//! Integers and floats can be mixed in a binary operation, in which case the integer
+//! operand is promoted to a float: `1 + 2.5` evaluates to `3.5`.
+//!
+//! Booleans can only be compared with `==` and `!=`. Other values, such as strings,
+//! characters or instances of custom types, cannot be used as operands.
+//!
+//! Nested operations on integers, floats and booleans are evaluated without boxing
+//! their intermediate results into instances: Only the value of the outermost
+//! operation is converted to an instance.
 
+use crate::ast::AstNode;
 use crate::context::Context;
 use crate::error::{ErrKind, Error};
 use crate::generics::GenericUser;
-use crate::instance::{FromObjectInstance, ObjectInstance};
+use crate::instance::{ObjectInstance, ToObjectInstance};
 use crate::instruction::{InstrKind, Instruction, Operator};
 use crate::location::SpanTuple;
 use crate::typechecker::TypeCheck;
 use crate::typechecker::{CheckedType, TypeCtx, TypeId};
-use crate::value::{JkFloat, JkInt, Value};
+use crate::value::Primitive;
 
 /// The `BinaryOp` struct contains two expressions and an operator, which can be an arithmetic
 /// or a comparison one
@@ -46,28 +59,78 @@ impl BinaryOp {
 
     // Get a reference on the left side member of a BinaryOp
     #[cfg(test)]
-    pub fn lhs(&self) -> &Box<dyn Instruction> {
-        &self.lhs
+    pub fn lhs(&self) -> &dyn Instruction {
+        &*self.lhs
     }
 
     /// Get a reference on the right side member of a BinaryOp
     #[cfg(test)]
-    pub fn rhs(&self) -> &Box<dyn Instruction> {
-        &self.rhs
+    pub fn rhs(&self) -> &dyn Instruction {
+        &*self.rhs
     }
 
-    // FIXME: Use Context::execute_expression
-    /// Execute a node of the binary operation
-    fn execute_node(&self, node: &dyn Instruction, ctx: &mut Context) -> Option<ObjectInstance> {
-        match node.execute(ctx) {
+    /// Evaluate an operand as a primitive value. Constants and nested operations are
+    /// evaluated without creating an instance
+    fn evaluate_operand(operand: &dyn Instruction, ctx: &mut Context) -> Option<Primitive> {
+        if let Some(op) = operand.downcast_ref::<BinaryOp>() {
+            return op.evaluate(ctx);
+        }
+
+        if let Some(constant) = Primitive::from_constant(operand) {
+            return Some(constant);
+        }
+
+        let instance = operand.execute_expression(ctx)?;
+
+        // The typechecker only allows primitive operands, but instances can come from
+        // code which was not typechecked, such as external functions
+        match Primitive::from_instance(&instance) {
+            Some(value) => Some(value),
             None => {
-                ctx.error(Error::new(ErrKind::Context).with_msg(format!(
-                    "invalid use of statement in binary operation: {}",
-                    node.print()
-                )));
+                ctx.error(
+                    Error::new(ErrKind::Context)
+                        .with_msg(format!(
+                            "invalid operand for binary operation: `{}` of type {}",
+                            operand.print(),
+                            instance.ty()
+                        ))
+                        .with_loc(operand.location().cloned()),
+                );
                 None
             }
-            Some(v) => Some(v),
+        }
+    }
+
+    /// Evaluate the operation without converting its result to an instance
+    fn evaluate(&self, ctx: &mut Context) -> Option<Primitive> {
+        let lhs = BinaryOp::evaluate_operand(&*self.lhs, ctx)?;
+        let rhs = BinaryOp::evaluate_operand(&*self.rhs, ctx)?;
+
+        match lhs.apply(rhs, self.op) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                ctx.error(e.with_loc(self.location.clone()));
+                None
+            }
+        }
+    }
+
+    /// Print one of the operands, surrounded by parentheses if needed: `(1 + 2) * 3`
+    /// must not be printed as `1 + 2 * 3`. Operations of the same precedence need
+    /// parentheses as well when they are on the right hand side of a left associative
+    /// operator, or on the left hand side of a right associative one
+    fn print_operand(&self, operand: &dyn Instruction, is_rhs: bool) -> String {
+        let needs_parentheses = match operand.downcast_ref::<BinaryOp>() {
+            Some(op) => {
+                let (inner, outer) = (op.op.precedence(), self.op.precedence());
+                inner < outer || (inner == outer && is_rhs == self.op.is_left_associative())
+            }
+            None => false,
+        };
+
+        match needs_parentheses {
+            true => format!("({})", operand.print()),
+            false => operand.print(),
         }
     }
 
@@ -84,48 +147,21 @@ impl Instruction for BinaryOp {
     fn print(&self) -> String {
         format!(
             "{} {} {}",
-            self.lhs.print(),
+            self.print_operand(&*self.lhs, false),
             self.op.as_str(),
-            self.rhs.print()
+            self.print_operand(&*self.rhs, true)
         )
     }
 
-    fn execute(&self, ctx: &mut Context) -> Option<ObjectInstance> {
-        let l_value = self.execute_node(&*self.lhs, ctx)?;
-        let r_value = self.execute_node(&*self.rhs, ctx)?;
-
-        // FIXME: This produces unhelpful errors for now
-        if l_value.ty() != r_value.ty() {
-            return None;
-        }
-
-        let return_value;
-
-        // At this point, we will already have checked whether or not a binary op
-        // is valid type-wise. So we can unwrap at will. If a type is still unknown
-        // at this point, this is an interpreter error
-        match l_value.ty() {
-            CheckedType::Resolved(ty) => match ty.id() {
-                "int" => {
-                    return_value = JkInt::from_instance(&l_value)
-                        .do_op(&JkInt::from_instance(&r_value), self.op)
-                        .unwrap();
-                }
-                "float" => {
-                    return_value = JkFloat::from_instance(&l_value)
-                        .do_op(&JkFloat::from_instance(&r_value), self.op)
-                        .unwrap();
-                }
-                _ => unreachable!(
-                    "attempting binary operation with void type or unknown type AFTER typechecking"
-                ),
-            },
-            _ => unreachable!(
-                "attempting binary operation with void type or unknown type AFTER typechecking"
-            ),
-        }
+    fn ast(&self) -> AstNode {
+        AstNode::new("BinaryOp", self.location())
+            .with_attr("operator", self.op.as_str())
+            .with_child("lhs", self.lhs.ast())
+            .with_child("rhs", self.rhs.ast())
+    }
 
-        Some(return_value)
+    fn execute(&self, ctx: &mut Context) -> Option<ObjectInstance> {
+        self.evaluate(ctx).map(|value| value.to_instance())
     }
 
     fn location(&self) -> Option<&SpanTuple> {
@@ -138,11 +174,76 @@ impl TypeCheck for BinaryOp {
         let l_type = self.lhs.type_of(ctx);
         let r_type = self.rhs.type_of(ctx);
 
-        if l_type != r_type {
+        let is_numeric = |ty: &TypeId| ty.id() == "int" || ty.id() == "float";
+        let is_primitive = |ty: &TypeId| is_numeric(ty) || ty.id() == "bool";
+
+        let operand_ty = match (&l_type, &r_type) {
+            // Binary operations are only defined on primitive values
+            (CheckedType::Resolved(l_ty), CheckedType::Resolved(r_ty))
+                if l_ty == r_ty && is_primitive(l_ty) =>
+            {
+                l_type.clone()
+            }
+            // Mixing integers and floats promotes the integer to a float
+            (CheckedType::Resolved(l_ty), CheckedType::Resolved(r_ty))
+                if is_numeric(l_ty) && is_numeric(r_ty) =>
+            {
+                CheckedType::Resolved(TypeId::from("float"))
+            }
+            _ => {
+                let err = Error::new(ErrKind::TypeChecker)
+                    .with_msg(format!(
+                        "trying to do binary operation on invalid types: {} {} {}",
+                        l_type,
+                        self.op.as_str(),
+                        r_type,
+                    ))
+                    .with_loc(self.location.clone())
+                    .with_hint(Error::hint().with_msg(String::from(
+                        "binary operations can only be applied to `int`, `float` and `bool` values",
+                    )));
+
+                // Characters and strings cannot be compared with `==` either
+                let string = CheckedType::Resolved(TypeId::from("string"));
+                let err = match self.op {
+                    Operator::Equals | Operator::NotEquals
+                        if l_type == string && r_type == string =>
+                    {
+                        err.with_hint(Error::hint().with_msg(String::from(
+                            "compare strings with the `equals` function: `lhs.equals(rhs)`",
+                        )))
+                    }
+                    _ => err,
+                };
+
+                ctx.error(err);
+                return CheckedType::Error;
+            }
+        };
+
+        // Booleans can only be compared for equality
+        if operand_ty == CheckedType::Resolved(TypeId::from("bool"))
+            && !matches!(self.op, Operator::Equals | Operator::NotEquals)
+        {
             ctx.error(
                 Error::new(ErrKind::TypeChecker)
                     .with_msg(format!(
-                        "trying to do binary operation on invalid types: {} {} {}",
+                        "invalid operation on booleans: {} {} {}",
+                        l_type,
+                        self.op.as_str(),
+                        r_type,
+                    ))
+                    .with_loc(self.location.clone()),
+            );
+            return CheckedType::Error;
+        }
+
+        // Bit-wise operations do not make sense on floating point numbers
+        if self.op.is_bitwise() && operand_ty != CheckedType::Resolved(TypeId::from("int")) {
+            ctx.error(
+                Error::new(ErrKind::TypeChecker)
+                    .with_msg(format!(
+                        "bit-wise operations can only be applied to integers: {} {} {}",
                         l_type,
                         self.op.as_str(),
                         r_type,
@@ -159,7 +260,7 @@ impl TypeCheck for BinaryOp {
             | Operator::GtEq
             | Operator::Equals
             | Operator::NotEquals => CheckedType::Resolved(TypeId::from("bool")),
-            _ => l_type,
+            _ => operand_ty,
         }
     }
 
@@ -181,13 +282,13 @@ impl GenericUser for BinaryOp {
 
 // TODO: Add typechecking tests
 #[cfg(test)]
+#[allow(clippy::identity_op)]
 mod tests {
     use nom_locate::LocatedSpan;
 
     use super::*;
     use crate::context::Context;
-    use crate::instance::ToObjectInstance;
-    use crate::value::JkInt;
+    use crate::value::{JkFloat, JkInt};
     use crate::{jinko, jinko_fail};
 
     #[test]
@@ -271,7 +372,6 @@ mod tests {
     }
 
     #[test]
-    #[ignore]
     fn comparison_precedence() {
         assert_bool(
             "1 + 4 * 2 - 1 + 2 * (14 + (2 - 17) * 1) - 12 + 3 / 2 < 45",
@@ -279,6 +379,13 @@ mod tests {
         );
     }
 
+    #[test]
+    fn left_associativity() {
+        assert_bool("1 - 2 - 3 == 0 - 4", true);
+        assert_bool("100 / 10 / 5 == 2", true);
+        assert_bool("2 * 3 - 8 / 4 * 2 == 2", true);
+    }
+
     #[test]
     fn tc_binop_valid() {
         jinko! {
@@ -303,6 +410,208 @@ mod tests {
         };
     }
 
+    #[test]
+    fn tc_binop_mixed_numbers() {
+        jinko! {
+            f = 1 + 2.5;
+            b = 4.0 < 5;
+        };
+    }
+
+    #[test]
+    fn t_binop_mixed_promotion() {
+        use crate::jk_execute;
+        use crate::value::JkBool;
+
+        assert_eq!(jk_execute!(1 + 2.5), Some(JkFloat::from(3.5).to_instance()));
+        assert_eq!(jk_execute!(5.0 / 2), Some(JkFloat::from(2.5).to_instance()));
+        assert_eq!(
+            jk_execute!(2 == 2.0),
+            Some(JkBool::from(true).to_instance())
+        );
+    }
+
+    #[test]
+    fn t_binop_float_equality() {
+        assert_bool("1.5 == 1.5", true);
+        assert_bool("1.5 != 1.5", false);
+    }
+
+    #[test]
+    fn t_binop_bool_equality() {
+        assert_bool("true == true", true);
+        assert_bool("true != false", true);
+        assert_bool("false == true", false);
+        assert_bool("(1 < 2) == true", true);
+    }
+
+    #[test]
+    fn tc_binop_bool_arithmetic() {
+        jinko_fail! {
+            b = true + false;
+        };
+        jinko_fail! {
+            b = true < false;
+        };
+    }
+
+    #[test]
+    fn tc_binop_string_operands() {
+        jinko_fail! {
+            s = "x" + "y";
+        };
+    }
+
+    #[test]
+    fn tc_binop_char_operands() {
+        jinko_fail! {
+            c = 'a' < 'b';
+        };
+    }
+
+    #[test]
+    fn tc_binop_char_string_equality() {
+        jinko_fail! {
+            b = 'a' == 'b';
+        };
+        jinko_fail! {
+            b = 'a' != 'b';
+        };
+        jinko_fail! {
+            b = "a" != "b";
+        };
+
+        let ctx = jinko_fail! {
+            b = "a" == "b";
+        };
+        let hints = ctx.error_handler.errors()[0].hints();
+        assert!(hints[1].msg().unwrap().contains("equals"));
+    }
+
+    #[test]
+    fn tc_binop_custom_operands() {
+        jinko_fail! {
+            type Point(x: int, y: int);
+            a = Point(x: 1, y: 2);
+            b = a + a;
+        };
+        jinko_fail! {
+            type Shape = Circle(r: int) | Square(side: int);
+            x = Circle(r: 1);
+            y = Square(side: 1);
+            b = x == y;
+        };
+    }
+
+    #[test]
+    fn tc_binop_function_operands() {
+        jinko_fail! {
+            func f() -> int { 1 }
+            g = f;
+            b = g == g;
+        };
+    }
+
+    #[test]
+    fn t_binop_int_division_by_zero() {
+        let mut ctx = jinko! {
+            zero = 0;
+        };
+
+        assert!(ctx.eval("14 / zero").is_err());
+    }
+
+    #[test]
+    fn t_binop_int_overflow() {
+        let mut ctx = jinko! {
+            max = 9223372036854775807;
+        };
+
+        assert!(ctx.eval("max + 1").is_err());
+    }
+
+    #[test]
+    fn t_binop_nested_overflow_location() {
+        let mut ctx = jinko! {
+            max = 9223372036854775807;
+        };
+
+        // The error is reported once, at the location of the operation which failed
+        assert!(ctx.eval("2 * (1 + (max + 1))").is_err());
+        assert_eq!(ctx.error_handler.errors().len(), 1);
+
+        let loc = ctx.error_handler.errors()[0].loc().unwrap();
+        assert_eq!(loc.start().column(), 11);
+    }
+
+    #[test]
+    fn t_binop_nested_mixed() {
+        use crate::jk_execute;
+
+        assert_eq!(
+            jk_execute!((1 + 2) * 2.5 - 10 / 4),
+            Some(JkFloat::from(5.5).to_instance())
+        );
+    }
+
+    #[test]
+    fn t_binop_int_rem_by_zero() {
+        let mut ctx = jinko! {
+            zero = 0;
+        };
+
+        assert!(ctx.eval("14 % zero").is_err());
+    }
+
+    #[test]
+    fn t_binop_int_pow() {
+        let ctx = jinko! {
+            a = 2 ** 10;
+            b = 2 ** 3 ** 2;
+            c = 2 ** 0;
+        };
+
+        assert_eq!(ctx.get_value::<JkInt>("a").unwrap().rust_value(), 1024);
+        assert_eq!(ctx.get_value::<JkInt>("b").unwrap().rust_value(), 512);
+        assert_eq!(ctx.get_value::<JkInt>("c").unwrap().rust_value(), 1);
+    }
+
+    #[test]
+    fn t_binop_int_pow_errors() {
+        let mut ctx = Context::new();
+
+        assert!(ctx.eval("2 ** 63").is_err());
+        assert!(ctx.eval("2 ** -1").is_err());
+    }
+
+    #[test]
+    fn t_binop_int_shift_errors() {
+        let mut ctx = Context::new();
+
+        assert!(ctx.eval("1 << 64").is_err());
+        assert!(ctx.eval("1 >> -1").is_err());
+    }
+
+    #[test]
+    fn t_binop_float_rem_pow() {
+        assert_bool("7.5 % 2.0 == 1.5", true);
+        assert_bool("4.0 ** 0.5 == 2.0", true);
+        assert_bool("2 ** 0.5 > 1.41", true);
+    }
+
+    #[test]
+    fn tc_binop_bitwise_float() {
+        jinko_fail! {
+            f = 1.5 & 2;
+        };
+        jinko_fail! {
+            f = 1.0 << 2.0;
+        };
+        jinko_fail! {
+            b = true | false;
+        };
+    }
+
     macro_rules! binop_assert {
         ($expr:expr) => {{
             let mut ctx = Context::new();
@@ -335,6 +644,27 @@ mod tests {
         binop_assert!(2 + 99);
     }
 
+    #[test]
+    fn t_binop_rem() {
+        binop_assert!(17 % 5 * 2);
+    }
+
+    #[test]
+    fn t_binop_bitwise() {
+        binop_assert!(6 & 3 | 8 ^ 1);
+    }
+
+    #[test]
+    #[allow(clippy::precedence)]
+    fn t_binop_shift() {
+        binop_assert!(1 + 1 << 2);
+    }
+
+    #[test]
+    fn t_binop_shift_negative() {
+        binop_assert!(-17 >> 2);
+    }
+
     #[test]
     fn t_binop_mul_same() {
         binop_assert!(12 * 12);
@@ -17,10 +17,13 @@
 //! The return value of the function is the last instruction if it is an expression.
 //! Otherwise, it's `void`
 
+use crate::ast::AstNode;
 use crate::context::Context;
+use crate::error::{ErrKind, Error};
 use crate::generics::{GenericMap, GenericUser};
+use crate::indent::Indent;
 use crate::instance::ObjectInstance;
-use crate::instruction::{InstrKind, Instruction};
+use crate::instruction::{Break, Continue, IfElse, InstrKind, Instruction, Return};
 use crate::location::SpanTuple;
 use crate::typechecker::{CheckedType, TypeCheck, TypeCtx};
 
@@ -49,6 +52,11 @@ impl Block {
         &self.instructions
     }
 
+    /// Get a mutable reference to the instructions contained in the block
+    pub fn instructions_mut(&mut self) -> &mut Vec<Box<dyn Instruction>> {
+        &mut self.instructions
+    }
+
     /// Gives a set of instructions to the block
     pub fn set_instructions(&mut self, instructions: Vec<Box<dyn Instruction>>) {
         self.instructions = instructions;
@@ -74,6 +82,12 @@ impl Block {
         self.instructions.pop()
     }
 
+    /// Is the block a statement, meaning that it does not return the value of its
+    /// last instruction
+    pub fn is_statement(&self) -> bool {
+        self.is_statement
+    }
+
     /// Set block is_statement to given value
     pub fn set_statement(&mut self, is_statement: bool) {
         self.is_statement = is_statement;
@@ -94,32 +108,62 @@ impl Instruction for Block {
     }
 
     fn print(&self) -> String {
+        if self.instructions.is_empty() {
+            return String::from("{}");
+        }
+
+        let indent = Indent::default().increment();
         let mut base = String::from("{\n");
 
-        if let Some((last, instructions)) = self.instructions.split_last() {
-            instructions.iter().for_each(|instr| {
-                base = format!("{}    {};\n", base, &instr.print());
-            });
-            base = format!("{}    {}\n", base, last.print());
+        for (idx, instr) in self.instructions.iter().enumerate() {
+            // Nested instructions span multiple lines, which all need to be indented
+            instr
+                .print()
+                .lines()
+                .for_each(|line| base.push_str(&format!("{}{}\n", indent, line)));
+
+            // Only the last instruction of an expression block has no semicolon
+            if idx + 1 < self.instructions.len() || self.is_statement {
+                base.pop();
+                base.push_str(";\n");
+            }
         }
 
         base.push('}');
         base
     }
 
+    fn ast(&self) -> AstNode {
+        AstNode::new("Block", self.location())
+            .with_attr("is_statement", self.is_statement)
+            .with_children("instructions", &self.instructions)
+    }
+
     fn execute(&self, ctx: &mut Context) -> Option<ObjectInstance> {
         ctx.scope_enter();
 
-        let ret_val = self
-            .instructions
-            .iter()
-            .map(|inst| inst.execute(ctx))
-            .last();
+        let mut ret_val = None;
+        for inst in self.instructions.iter() {
+            if !ctx.before_instruction(&**inst) {
+                ret_val = None;
+                break;
+            }
+
+            ret_val = inst.execute(ctx);
+
+            // Instructions such as `break` or `continue` stop the execution of the
+            // block early. The interrupt is then handled by the enclosing loop.
+            // Errors stop the execution of the block as well
+            if ctx.is_interrupted() || ctx.has_errors() {
+                ret_val = None;
+                break;
+            }
+        }
 
         ctx.scope_exit();
 
         match self.is_statement {
-            false => ret_val.flatten(),
+            false => ret_val,
             true => None,
         }
     }
@@ -129,8 +173,75 @@ impl Instruction for Block {
     }
 }
 
+impl Block {
+    /// Does an instruction always return, break or continue, either by itself or
+    /// because all of its branches do
+    fn diverges(instr: &dyn Instruction) -> bool {
+        if instr.is::<Return>() || instr.is::<Break>() || instr.is::<Continue>() {
+            return true;
+        }
+
+        if let Some(block) = instr.downcast_ref::<Block>() {
+            return block
+                .instructions
+                .iter()
+                .any(|instr| Block::diverges(&**instr));
+        }
+
+        match instr.downcast_ref::<IfElse>() {
+            Some(if_else) => match if_else.else_body() {
+                Some(else_body) => Block::diverges(if_else.if_body()) && Block::diverges(else_body),
+                None => false,
+            },
+            None => false,
+        }
+    }
+
+    /// Emit a warning if some instructions of the block follow a `return`, `break` or
+    /// `continue` instruction, or a condition whose branches all end with one, since
+    /// they will never get executed
+    fn warn_unreachable_code(&self, ctx: &mut TypeCtx) {
+        let diverging = self
+            .instructions
+            .iter()
+            .position(|instr| Block::diverges(&**instr));
+
+        let (diverging, unreachable) = match diverging {
+            Some(position) if position + 1 < self.instructions.len() => (
+                &self.instructions[position],
+                &self.instructions[position + 1],
+            ),
+            _ => return,
+        };
+
+        ctx.error(
+            Error::new(ErrKind::Warning)
+                .with_msg(String::from("unreachable code"))
+                .with_loc(unreachable.location().cloned())
+                .with_hint(
+                    Error::hint()
+                        .with_msg(match (diverging.is::<IfElse>(), diverging.is::<Block>()) {
+                            (true, _) => String::from(
+                                "all the branches of this condition return, break or continue",
+                            ),
+                            (_, true) => {
+                                String::from("this block always returns, breaks or continues")
+                            }
+                            _ => {
+                                format!("any code following `{}` is unreachable", diverging.print())
+                            }
+                        })
+                        .with_loc(diverging.location().cloned()),
+                ),
+        );
+    }
+}
+
 impl TypeCheck for Block {
     fn resolve_type(&mut self, ctx: &mut TypeCtx) -> CheckedType {
+        self.warn_unreachable_code(ctx);
+        ctx.scope_enter();
+
         let last_type = self
             .instructions
             .iter_mut()
@@ -138,6 +249,8 @@ impl TypeCheck for Block {
             .last()
             .unwrap_or(CheckedType::Void);
 
+        ctx.scope_exit();
+
         match &self.is_statement {
             true => CheckedType::Void,
             false => last_type,
@@ -174,7 +287,7 @@ mod tests {
         let b = Block::new();
 
         assert_eq!(b.kind(), InstrKind::Statement);
-        assert_eq!(b.print(), "{\n}");
+        assert_eq!(b.print(), "{}");
     }
 
     #[test]
@@ -291,4 +404,65 @@ mod tests {
             takes_int({ { { true } } });
         };
     }
+
+    #[test]
+    fn unreachable_code_after_return_warns() {
+        let ctx = jinko! {
+            func f() -> int {
+                return 1;
+                2
+            }
+            f();
+        };
+
+        assert!(ctx
+            .error_handler
+            .errors()
+            .iter()
+            .any(|e| e.kind() == ErrKind::Warning));
+    }
+
+    #[test]
+    fn trailing_return_does_not_warn() {
+        let ctx = jinko! {
+            func f() -> int {
+                a = 1;
+                return a
+            }
+            f();
+        };
+
+        assert!(ctx.error_handler.errors().is_empty());
+    }
+
+    #[test]
+    fn unreachable_code_after_diverging_condition_warns() {
+        let ctx = jinko! {
+            func f(x: int) -> int {
+                if x > 0 { return 1 } else { return 2 };
+                3
+            }
+            f(1);
+        };
+
+        assert!(ctx
+            .error_handler
+            .errors()
+            .iter()
+            .any(|e| e.msg() == Some("unreachable code")));
+    }
+
+    #[test]
+    fn condition_without_else_does_not_warn() {
+        let ctx = jinko! {
+            func f(x: int) -> int {
+                if x > 0 { return 1; };
+                { if x < 0 { return 2; } else { println("positive"); }; };
+                4
+            }
+            f(1);
+        };
+
+        assert!(ctx.error_handler.errors().is_empty());
+    }
 }
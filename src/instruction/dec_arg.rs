@@ -1,13 +1,18 @@
 use std::fmt::{Display, Formatter, Result};
 
+use crate::ast::AstNode;
 use crate::generics::{GenericMap, GenericUser};
+use crate::instruction::Instruction;
 use crate::location::SpanTuple;
 use crate::typechecker::{TypeCtx, TypeId};
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug)]
 pub struct DecArg {
     name: String,
     ty: TypeId,
+    /// Value used when the argument is omitted. This is only available for the
+    /// fields of a type declaration
+    default: Option<Box<dyn Instruction>>,
     location: Option<SpanTuple>,
 }
 
@@ -17,6 +22,7 @@ impl DecArg {
         DecArg {
             name,
             ty,
+            default: None,
             location: None,
         }
     }
@@ -31,6 +37,21 @@ impl DecArg {
         &self.ty
     }
 
+    /// Get a reference to the argument's default value, if it has one
+    pub fn default_value(&self) -> Option<&dyn Instruction> {
+        self.default.as_deref()
+    }
+
+    /// Get a mutable reference to the argument's default value, if it has one
+    pub fn default_value_mut(&mut self) -> Option<&mut (dyn Instruction + 'static)> {
+        self.default.as_deref_mut()
+    }
+
+    /// Set the value to use when the argument is omitted
+    pub fn set_default(&mut self, default: Box<dyn Instruction>) {
+        self.default = Some(default)
+    }
+
     /// Get a reference to the argument's location
     pub fn location(&self) -> Option<&SpanTuple> {
         self.location.as_ref()
@@ -45,6 +66,14 @@ impl DecArg {
     pub fn set_location(&mut self, location: SpanTuple) {
         self.location = Some(location)
     }
+
+    /// Convert the argument to a node of the syntax tree
+    pub fn ast(&self) -> AstNode {
+        AstNode::new("DecArg", self.location())
+            .with_attr("name", self.name.as_str())
+            .with_attr("type", &self.ty)
+            .with_child("default", self.default.as_ref().map(|value| value.ast()))
+    }
 }
 
 impl GenericUser for DecArg {
@@ -53,8 +82,25 @@ impl GenericUser for DecArg {
     }
 }
 
+// Instructions cannot be compared, so default values are compared using their
+// representation
+impl PartialEq for DecArg {
+    fn eq(&self, other: &DecArg) -> bool {
+        self.name == other.name
+            && self.ty == other.ty
+            && self.location == other.location
+            && self.default.as_ref().map(|value| value.print())
+                == other.default.as_ref().map(|value| value.print())
+    }
+}
+
 impl Display for DecArg {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
-        write!(f, "{}: {}", self.name, self.ty.id())
+        write!(f, "{}: {}", self.name, self.ty.print())?;
+
+        match &self.default {
+            Some(value) => write!(f, " = {}", value.print()),
+            None => Ok(()),
+        }
     }
 }
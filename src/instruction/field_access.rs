@@ -1,11 +1,18 @@
-//! FieldAccesses represent an access onto a type instance's members.
-//! FIXME: Add doc
-
+//! FieldAccesses represent an access onto a type instance's members, such as `p.x`.
+//! The accessed instance can be any expression, including another field access:
+//! `line.start.x` accesses the `x` field of the `start` field of `line`.
+//!
+//! When typechecking, the type of the field is fetched from the declaration of the
+//! instance's type. When executing, the field's value is fetched from the instance's
+//! fields, which keep track of their offset inside the instance's data as well as
+//! their own type.
+
+use crate::ast::AstNode;
 use crate::context::Context;
 use crate::error::{ErrKind, Error};
 use crate::generics::{GenericMap, GenericUser};
 use crate::instance::ObjectInstance;
-use crate::instruction::{InstrKind, Instruction};
+use crate::instruction::{print_receiver, InstrKind, Instruction};
 use crate::location::SpanTuple;
 use crate::typechecker::{CheckedType, TypeCheck, TypeCtx};
 
@@ -31,6 +38,7 @@ impl FieldAccess {
     /// Get a reference to the accessed field's instance
     fn get_field_instance(&self, ctx: &mut Context) -> Option<ObjectInstance> {
         let calling_instance = match self.instance.execute(ctx) {
+            None if ctx.is_interrupted() => return None,
             None => {
                 ctx.error(
                     Error::new(ErrKind::Context)
@@ -68,7 +76,13 @@ impl Instruction for FieldAccess {
     }
 
     fn print(&self) -> String {
-        format!("{}.{}", self.instance.print(), self.field_name)
+        format!("{}.{}", print_receiver(&*self.instance), self.field_name)
+    }
+
+    fn ast(&self) -> AstNode {
+        AstNode::new("FieldAccess", self.location())
+            .with_attr("field", self.field_name.as_str())
+            .with_child("instance", self.instance.ast())
     }
 
     fn execute(&self, ctx: &mut Context) -> Option<ObjectInstance> {
@@ -143,17 +157,16 @@ mod tests {
     use super::*;
     use crate::instance::ToObjectInstance;
     use crate::parser::constructs;
+    use crate::typechecker::TypeId;
     use crate::value::JkInt;
     use crate::{jinko, jinko_fail, span};
 
     fn setup() -> Context {
-        let ctx = jinko! {
+        jinko! {
             type Point(x: int, y:int);
             func basic() -> Point { Point ( x : 15, y : 14 )}
             b = basic();
-        };
-
-        ctx
+        }
     }
 
     #[test]
@@ -163,7 +176,7 @@ mod tests {
         let inst = constructs::expr(span!("b.x")).unwrap().1;
         let res = match inst.execute(&mut ctx) {
             Some(i) => i,
-            None => return assert!(false, "Error when accessing valid field"),
+            None => panic!("Error when accessing valid field"),
         };
 
         let exp = JkInt::from(15).to_instance();
@@ -189,26 +202,19 @@ mod tests {
     }
 
     #[test]
-    #[ignore] // FIXME: Do not ignore once we can type instance fields
     fn t_valid_multi_field_access() {
-        let mut ctx = Context::new();
-
-        let inst = constructs::expr(span!("type Pair1(x: int, y: int)"))
-            .unwrap()
-            .1;
-        inst.execute(&mut ctx).unwrap();
-
-        let inst = constructs::expr(span!("type Pair2(x: Pair1, y: int)"))
-            .unwrap()
-            .1;
-        inst.execute(&mut ctx).unwrap();
+        let mut ctx = jinko! {
+            type Pair1(x: int, y: int);
+            type Pair2(x: Pair1, y: int);
+            p = Pair2(x: Pair1(x: 1, y: 2), y: 3);
+        };
 
-        let inst = constructs::expr(span!("p = Pair2 ( x : Pair1 ( x : 1, y : 2), y : 3)"))
-            .unwrap()
-            .1;
-        inst.execute(&mut ctx).unwrap();
+        let mut inst = constructs::expr(span!("p.x.y")).unwrap().1;
+        assert_eq!(
+            ctx.type_check(&mut *inst).unwrap(),
+            CheckedType::Resolved(TypeId::from("int"))
+        );
 
-        let inst = constructs::expr(span!("p.x.y")).unwrap().1;
         let res = match inst.execute(&mut ctx) {
             Some(i) => i,
             None => unreachable!("Error when accessing valid multi field"),
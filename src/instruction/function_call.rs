@@ -1,22 +1,41 @@
 //! FunctionCalls are used when calling a function. The argument list is given to the
 //! function on execution.
-
-use std::rc::Rc;
-
+//!
+//! Since a type can also be instantiated by giving its fields in order, such as
+//! `Point(1, 2)`, a function call might refer to a type instead. This is decided
+//! when typechecking: If no function exists with the given name but a type does, the
+//! call is turned into a [`TypeInstantiation`] which is executed instead.
+//!
+//! A call can also refer to a variable containing a function value, such as `f` in
+//! `func apply(f: func(int) -> int, x: int) -> int { f(x) }`. These indirect calls
+//! are dispatched through the function the variable refers to, and take precedence
+//! over functions declared with the same name.
+//!
+//! Calls are dispatched on the type of their first argument. When the arguments mix
+//! integers and floats, the version of the function declared for floats is called
+//! instead if there is one, and the integers are promoted to floats: `min(1, 2.5)`
+//! calls `min(lhs: float, rhs: float)`, as `1 + 2.5` adds two floats.
+
+use crate::ast::{AstNode, Json};
 use crate::context::Context;
 use crate::error::{ErrKind, Error};
 use crate::generics::{self, GenericExpander, GenericMap, GenericUser};
-use crate::instance::ObjectInstance;
-use crate::instruction::{FunctionDec, FunctionKind, Var};
+use crate::instance::{FromObjectInstance, ObjectInstance, ToObjectInstance};
+use crate::instruction::{FunctionDec, FunctionKind, TypeDec, TypeInstantiation, Var, VarAssign};
 use crate::instruction::{InstrKind, Instruction};
 use crate::location::SpanTuple;
 use crate::typechecker::{CheckedType, SpecializedNode, TypeCheck, TypeCtx, TypeId};
+use crate::value::{JkFloat, JkFunc, JkInt};
 
 #[derive(Clone)]
 pub struct FunctionCall {
     fn_name: String,
     generics: Vec<TypeId>,
     args: Vec<Box<dyn Instruction>>,
+    /// Positional type instantiation the call resolved to, if any
+    instantiation: Option<Box<TypeInstantiation>>,
+    /// Are the integers given as arguments promoted to floats
+    promoted: bool,
     cached_type: Option<CheckedType>,
     location: Option<SpanTuple>,
 }
@@ -32,6 +51,8 @@ impl FunctionCall {
             fn_name,
             generics,
             args,
+            instantiation: None,
+            promoted: false,
             cached_type: None,
             location: None,
         }
@@ -62,11 +83,19 @@ impl FunctionCall {
         self.location = Some(loc)
     }
 
-    /// Get the corresponding declaration from a context
-    fn get_declaration(&self, ctx: &mut Context) -> Result<Rc<FunctionDec>, Error> {
+    /// Get the function to call from a context. Variables containing a function value
+    /// are looked up first
+    fn get_declaration(&self, ctx: &mut Context) -> Result<JkFunc, Error> {
+        let indirect = ctx
+            .get_variable(self.name())
+            .and_then(|var| var.instance().function().cloned());
+        if let Some(function) = indirect {
+            return Ok(function);
+        }
+
         match ctx.get_function(self.name()) {
             // get_function() return a Rc, so this clones the Rc, not the FunctionDec
-            Some(f) => Ok(f.clone()),
+            Some(f) => Ok(JkFunc::new(f.clone())),
             // FIXME: Fix Location and input
             None => Err(Error::new(ErrKind::Context)
                 .with_msg(format!("cannot find function {}", self.name()))
@@ -74,50 +103,107 @@ impl FunctionCall {
         }
     }
 
-    /// Map each argument to its corresponding instruction
-    fn map_args(&self, function: &FunctionDec, ctx: &mut Context) {
-        for (call_arg, func_arg) in self.args.iter().zip(function.args()) {
+    /// Map each argument to its corresponding instruction. The arguments are
+    /// evaluated in the scope of the caller, and returned as variables to bind in the
+    /// frame of the function. This fails if an argument cannot be mapped, or if its
+    /// type differs from the declared one
+    fn map_args(&self, function: &FunctionDec, ctx: &mut Context) -> Result<Vec<Var>, Error> {
+        let mut vars = Vec::with_capacity(self.args.len());
+
+        for (position, (call_arg, func_arg)) in self.args.iter().zip(function.args()).enumerate() {
             // Create a new variable, and execute the content of the function argument
             // passed to the call
             let mut new_var = Var::new(func_arg.name().to_owned());
+            if let Some(location) = func_arg.location() {
+                new_var.set_location(location.clone());
+            }
             let mut instance = match call_arg.execute_expression(ctx) {
                 Some(i) => i,
                 None => {
-                    ctx.error(
-                        Error::new(ErrKind::Context)
-                            .with_msg(format!(
-                                "trying to map statement to function argument: {} -> {}",
-                                call_arg.print(),
-                                func_arg
-                            ))
-                            .with_loc(func_arg.location().cloned()),
-                    );
-                    return;
+                    return Err(Error::new(ErrKind::Context)
+                        .with_msg(format!(
+                            "trying to map statement to function argument: {} -> {}",
+                            call_arg.print(),
+                            func_arg
+                        ))
+                        .with_loc(func_arg.location().cloned()));
                 }
             };
 
-            let ty = match ctx.get_type(func_arg.get_type()) {
+            let ty = match (func_arg.get_type(), ctx.get_type(func_arg.get_type())) {
+                // Functor types are not declared anywhere, and are used as-is
+                (ty @ TypeId::Functor { .. }, _) => ty.clone(),
                 // Double dereferencing: Some(t) gives us a &Rc<TypeDec>. We dereference
                 // it to access the Rc, and dereference it again to access the TypeDec.
-                Some(t) => (**t).clone(),
-                None => {
-                    ctx.error(
-                        Error::new(ErrKind::Context)
-                            .with_msg(format!("type not found: {}", func_arg.get_type().id()))
-                            .with_loc(func_arg.location().cloned()),
-                    );
-                    return;
+                (_, Some(t)) => (**t).clone().into(),
+                (_, None) => {
+                    return Err(Error::new(ErrKind::Context)
+                        .with_msg(format!("type not found: {}", func_arg.get_type().id()))
+                        .with_loc(func_arg.location().cloned()));
                 }
             };
 
-            instance.set_ty(CheckedType::Resolved(ty.into()));
+            // The typechecker should have caught any mismatch already, but instances
+            // built at runtime need to be checked before being bound to the argument
+            let expected = CheckedType::Resolved(ty);
+            if self.promoted
+                && is_primitive(&expected, "float")
+                && is_primitive(instance.ty(), "int")
+            {
+                instance = JkFloat::from(JkInt::from_instance(&instance).0 as f64).to_instance();
+            }
+            if let CheckedType::Resolved(_) = instance.ty() {
+                if instance.ty() != &expected {
+                    return Err(Error::new(ErrKind::Context)
+                            .with_msg(format!(
+                                "invalid type for argument {} (`{}`) of function `{}`: expected {}, got {}",
+                                position + 1,
+                                func_arg.name(),
+                                FunctionDec::display_name(function.name()),
+                                expected,
+                                instance.ty()
+                            ))
+                            .with_loc(self.location.clone())
+                            .with_hint(
+                                Error::new(ErrKind::Hint)
+                                    .with_msg(String::from("argument declared here"))
+                                    .with_loc(func_arg.location().cloned()),
+                            ));
+                }
+            }
+
+            instance.set_ty(expected);
 
             new_var.set_instance(instance);
 
-            if let Err(e) = ctx.add_variable(new_var) {
-                ctx.error(e);
-            }
+            vars.push(new_var);
         }
+
+        Ok(vars)
+    }
+
+    /// Get the version of the function declared for floats, if the call should be
+    /// promoted to it: Its arguments mix integers and floats
+    fn promoted_overload(&mut self, name: &str, ctx: &mut TypeCtx) -> Option<String> {
+        let (mut ints, mut floats) = (false, false);
+        for arg in self.args.iter_mut() {
+            let ty = arg.type_of(ctx);
+            ints |= is_primitive(&ty, "int");
+            floats |= is_primitive(&ty, "float");
+        }
+        if !ints || !floats {
+            return None;
+        }
+
+        let method = FunctionDec::method_name(&TypeId::from("float"), name);
+        if ctx.get_function(&method).is_some() {
+            return Some(method);
+        }
+
+        ctx.get_function(name)
+            .and_then(|dec| dec.args().first())
+            .filter(|arg| arg.get_type().id() == "float")
+            .map(|_| name.to_owned())
     }
 
     fn type_args(&self, args: Vec<(String, CheckedType)>, ctx: &mut TypeCtx) {
@@ -136,9 +222,29 @@ impl FunctionCall {
         &self,
         ctx: &mut Context,
         dec: &FunctionDec,
+    ) -> Option<ObjectInstance> {
+        ctx.profile_enter(dec.name());
+        let ret_val = self.call_external_function(ctx, dec);
+        ctx.profile_exit();
+
+        ret_val
+    }
+
+    fn call_external_function(
+        &self,
+        ctx: &mut Context,
+        dec: &FunctionDec,
     ) -> Option<ObjectInstance> {
         if ctx.is_builtin(dec.name()) {
-            match ctx.call_builtin(dec.name(), self.args.clone()) {
+            // Arguments are evaluated once, before calling the builtin. An argument
+            // without a value has already reported an error
+            let args = self
+                .args
+                .iter()
+                .map(|arg| arg.execute(ctx))
+                .collect::<Option<Vec<_>>>()?;
+
+            match ctx.call_builtin(dec.name(), &args) {
                 Ok(value) => value,
                 Err(e) => {
                     ctx.error(e);
@@ -147,7 +253,11 @@ impl FunctionCall {
             }
         } else {
             #[cfg(feature = "ffi")]
-            match crate::ffi::execute(dec, self, ctx) {
+            match ctx
+                .check_capability(crate::context::Capability::Ffi, dec.name())
+                .map_err(|e| e.with_loc(self.location.clone()))
+                .and_then(|_| crate::ffi::execute(dec, self, ctx))
+            {
                 Ok(value) => value,
                 Err(e) => {
                     ctx.error(e);
@@ -160,8 +270,8 @@ impl FunctionCall {
                 ctx.error(
                     Error::new(ErrKind::Context)
                         .with_msg(format!(
-                    "jinko is not compiled with FFI support. Cannot call `{}` external function",
-                    dec.name()
+                    "jinko is not compiled with FFI support. Cannot call {} external function",
+                    ctx.symbols().display(dec.name())
                 ))
                         .with_loc(self.location.clone()),
                 );
@@ -170,6 +280,58 @@ impl FunctionCall {
         }
     }
 
+    /// Execute a call to a function declared in jinko, with the variables it captured
+    /// if it is a closure
+    fn execute_function(
+        &self,
+        ctx: &mut Context,
+        function: &FunctionDec,
+        captures: Vec<Var>,
+    ) -> Option<ObjectInstance> {
+        let vars = match self.map_args(function, ctx) {
+            Ok(vars) => vars,
+            // An argument exited the current function early, or failed and reported
+            // why already
+            Err(_) if ctx.is_interrupted() || ctx.has_errors() => return None,
+            Err(e) => {
+                ctx.error(e);
+                return None;
+            }
+        };
+
+        if let Err(e) = ctx.call_enter(self.name(), self.location.as_ref()) {
+            ctx.error(e.with_loc(self.location.clone()));
+            return None;
+        }
+
+        // The function cannot access the variables of its caller
+        ctx.frame_enter();
+
+        // Closures also have access to the variables they captured
+        let vars = captures.into_iter().chain(vars);
+
+        let ret_val = match vars.into_iter().try_for_each(|var| ctx.add_variable(var)) {
+            Ok(_) => {
+                // Arguments were evaluated already, and are not part of the time
+                // spent in the function
+                ctx.profile_enter(function.name());
+                let ret_val = function.run(ctx);
+                ctx.profile_exit();
+
+                ret_val
+            }
+            Err(e) => {
+                ctx.error(e);
+                None
+            }
+        };
+
+        ctx.frame_exit();
+        ctx.call_exit();
+
+        ret_val
+    }
+
     pub fn generics(&self) -> &Vec<TypeId> {
         &self.generics
     }
@@ -178,7 +340,160 @@ impl FunctionCall {
         self.fn_name = fn_name
     }
 
+    /// Build an instantiation of the given type from the call, by associating each
+    /// argument to the field declared at the same position
+    fn to_type_instantiation(&self, dec: &TypeDec) -> Result<TypeInstantiation, Error> {
+        if self.args.len() > dec.fields().len() {
+            return Err(Error::new(ErrKind::TypeChecker)
+                .with_msg(format!(
+                    "too many fields given to instantiation of type `{}`: expected {}, got {}",
+                    dec.name(),
+                    dec.fields().len(),
+                    self.args.len()
+                ))
+                .with_loc(self.location.clone())
+                .with_hint(
+                    Error::hint()
+                        .with_msg(format!("type `{}` declared here", dec.name()))
+                        .with_loc(dec.location().cloned()),
+                ));
+        }
+
+        let mut instantiation = TypeInstantiation::new(TypeId::from(self.name()));
+        instantiation.set_generics(self.generics.clone());
+        if let Some(loc) = &self.location {
+            instantiation.set_location(loc.clone());
+        }
+
+        for (field_dec, arg) in dec.fields().iter().zip(self.args.iter()) {
+            let mut field = VarAssign::new(false, field_dec.name().to_owned(), arg.clone());
+            if let Some(loc) = arg.location() {
+                field.set_location(loc.clone());
+            }
+
+            instantiation.add_field(field);
+        }
+
+        Ok(instantiation)
+    }
+
+    /// Turn the call into an instantiation of the given type and typecheck it
+    fn resolve_type_instantiation(&mut self, dec: &TypeDec, ctx: &mut TypeCtx) -> CheckedType {
+        match self.to_type_instantiation(dec) {
+            Ok(instantiation) => {
+                let instantiation = self.instantiation.insert(Box::new(instantiation));
+                instantiation.type_of(ctx)
+            }
+            Err(e) => {
+                ctx.error(e);
+                CheckedType::Error
+            }
+        }
+    }
+
+    /// Typecheck a call made through a variable containing a function value, whose
+    /// type is a functor type
+    fn resolve_indirect_call(
+        &mut self,
+        arg_types: Vec<TypeId>,
+        return_type: Option<TypeId>,
+        ctx: &mut TypeCtx,
+    ) -> CheckedType {
+        if self.args.len() != arg_types.len() {
+            ctx.error(
+                Error::new(ErrKind::TypeChecker)
+                    .with_msg(format!(
+                        "wrong number of arguments for call to function value {}: expected {}, got {}",
+                        ctx.symbols().display(self.name()),
+                        arg_types.len(),
+                        self.args.len()
+                    ))
+                    .with_loc(self.location.clone()),
+            );
+        }
+
+        for (expected_ty, given_arg) in arg_types.into_iter().zip(self.args.iter_mut()) {
+            let given_ty = given_arg.type_of(ctx);
+            let expected_ty = CheckedType::Resolved(expected_ty);
+            if expected_ty != given_ty {
+                let err = Error::new(ErrKind::TypeChecker)
+                    .with_msg(format!(
+                        "invalid type used for function argument: expected `{}`, got `{}`",
+                        expected_ty, given_ty
+                    ))
+                    .with_loc(given_arg.location().cloned());
+                ctx.error(err);
+            }
+        }
+
+        return_type.map_or(CheckedType::Void, CheckedType::Resolved)
+    }
+
+    /// Infer the types given for the interfaces taken by a function, from the
+    /// arguments declared with these interfaces. Generic types which are not
+    /// interfaces must be given explicitly, and come first
+    fn infer_interfaces(&mut self, function: &FunctionDec, ctx: &mut TypeCtx) -> Result<(), Error> {
+        for generic in function.generics().iter().skip(self.generics.len()) {
+            let interface = generic.id();
+            if ctx.get_interface(interface).is_none() {
+                return Ok(());
+            }
+
+            let position = function
+                .args()
+                .iter()
+                .position(|arg| arg.get_type() == generic);
+            let given = match position.and_then(|position| self.args.get_mut(position)) {
+                Some(arg) => (arg.type_of(ctx), arg.location().cloned()),
+                None => continue,
+            };
+
+            match given {
+                (CheckedType::Resolved(ty), _) if ctx.implements(interface, &ty) => {
+                    self.generics.push(ty)
+                }
+                (CheckedType::Resolved(ty), loc) => {
+                    return Err(Error::new(ErrKind::TypeChecker)
+                        .with_msg(format!(
+                            "type `{}` does not implement interface `{}`",
+                            ty, interface
+                        ))
+                        .with_loc(loc))
+                }
+                (ty, loc) => {
+                    return Err(Error::new(ErrKind::TypeChecker)
+                        .with_msg(format!(
+                            "invalid type used for argument of interface `{}`: `{}`",
+                            interface, ty
+                        ))
+                        .with_loc(loc))
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     fn resolve_generic_call(&mut self, function: FunctionDec, ctx: &mut TypeCtx) -> CheckedType {
+        if let Err(e) = self.infer_interfaces(&function, ctx) {
+            ctx.error(e);
+            return CheckedType::Error;
+        }
+
+        // Generic types given explicitly, such as `Vec[int]`, need to be specialized
+        let generics = self
+            .generics
+            .iter()
+            .map(|generic| ctx.resolve_concrete_type(generic))
+            .collect::<Result<Vec<TypeId>, Error>>();
+        self.generics = match generics {
+            Ok(generics) => generics,
+            Err(e) => {
+                ctx.error(e.with_loc(self.location.clone()));
+                return CheckedType::Error;
+            }
+        };
+
         let type_map = match GenericMap::create(function.generics(), self.generics(), ctx) {
             Ok(map) => map,
             Err(e) => {
@@ -203,6 +518,11 @@ impl FunctionCall {
     }
 }
 
+/// Is a type the primitive type with the given name
+fn is_primitive(ty: &CheckedType, name: &str) -> bool {
+    matches!(ty, CheckedType::Resolved(ty) if ty.id() == name)
+}
+
 impl Instruction for FunctionCall {
     fn kind(&self) -> InstrKind {
         // FIXME: Add logic
@@ -213,12 +533,12 @@ impl Instruction for FunctionCall {
         let mut base = String::from(&self.fn_name);
 
         if !self.generics.is_empty() {
-            base = format!("{}[{}", base, self.generics[0]);
+            base = format!("{}[{}", base, self.generics[0].print());
 
             self.generics
                 .iter()
                 .skip(1)
-                .for_each(|generic| base.push_str(&format!(", {}", generic)));
+                .for_each(|generic| base.push_str(&format!(", {}", generic.print())));
             base.push(']');
         }
 
@@ -238,28 +558,33 @@ impl Instruction for FunctionCall {
         base
     }
 
+    fn ast(&self) -> AstNode {
+        AstNode::new("FunctionCall", self.location())
+            .with_attr("name", self.fn_name.as_str())
+            .with_attr(
+                "generics",
+                self.generics.iter().map(Json::from).collect::<Vec<_>>(),
+            )
+            .with_children("args", &self.args)
+    }
+
     fn execute(&self, ctx: &mut Context) -> Option<ObjectInstance> {
-        let function = match self.get_declaration(ctx) {
-            Ok(f) => f,
+        if let Some(instantiation) = &self.instantiation {
+            return instantiation.execute(ctx);
+        }
+
+        let (function, captures) = match self.get_declaration(ctx) {
+            Ok(f) => (f.dec().clone(), f.captures().to_vec()),
             Err(e) => {
                 ctx.error(e);
                 return None;
             }
         };
 
-        if function.fn_kind() == FunctionKind::Ext {
-            return self.execute_external_function(ctx, &function);
+        match function.fn_kind() {
+            FunctionKind::Ext => self.execute_external_function(ctx, &function),
+            _ => self.execute_function(ctx, &function, captures),
         }
-
-        ctx.scope_enter();
-
-        self.map_args(&function, ctx);
-
-        let ret_val = function.run(ctx);
-
-        ctx.scope_exit();
-
-        ret_val
     }
 
     fn location(&self) -> Option<&SpanTuple> {
@@ -274,11 +599,55 @@ impl TypeCheck for FunctionCall {
         //     return CheckedType::Later;
         // }
 
+        // The call has already been turned into a type instantiation when resolving
+        // generic usages
+        if let Some(instantiation) = &mut self.instantiation {
+            return instantiation.type_of(ctx);
+        }
+
+        // The call is made through a variable containing a function value
+        if self.generics.is_empty() {
+            if let Some(CheckedType::Resolved(TypeId::Functor {
+                arg_types,
+                return_type,
+                ..
+            })) = ctx.get_var(self.name()).cloned()
+            {
+                return self.resolve_indirect_call(arg_types, return_type.map(|ty| *ty), ctx);
+            }
+        }
+
+        // Resolve the method associated with the type of the first argument, if there
+        // is one
+        if self.generics.is_empty() {
+            if let Some(receiver) = self.args.first_mut() {
+                match receiver.type_of(ctx) {
+                    CheckedType::Resolved(ty) => {
+                        let name = self.name().to_owned();
+                        let method = FunctionDec::method_name(&ty, &name);
+                        if ctx.get_function(&method).is_some() {
+                            self.fn_name = method;
+                        }
+
+                        if let Some(promoted) = self.promoted_overload(&name, ctx) {
+                            self.fn_name = promoted;
+                            self.promoted = true;
+                        }
+                    }
+                    CheckedType::Error => return CheckedType::Error,
+                    _ => {}
+                }
+            }
+        }
+
         // FIXME: This function is very large and should be refactored
         let function = match ctx.get_function(self.name()) {
             Some(f) => f.clone(), // FIXME: Remove this clone...
-            // FIXME: This does not account for functions declared later in the code
             None => {
+                if let Some(dec) = ctx.get_custom_type(self.name()).cloned() {
+                    return self.resolve_type_instantiation(&dec, ctx);
+                }
+
                 ctx.error(
                     Error::new(ErrKind::TypeChecker)
                         .with_msg(format!(
@@ -291,6 +660,8 @@ impl TypeCheck for FunctionCall {
             }
         };
 
+        ctx.use_function(self.name());
+
         // FIXME: Add check for calling non-generic function usign generics
 
         let (args_type, return_type) = (function.args(), function.ty());
@@ -308,8 +679,8 @@ impl TypeCheck for FunctionCall {
                 Error::new(ErrKind::TypeChecker)
                     .with_msg(format!(
                         "wrong number of arguments \
-                    for call to function `{}`: expected {}, got {}",
-                        self.name(),
+                    for call to function {}: expected {}, got {}",
+                        ctx.symbols().display(self.name()),
                         args_type.len(),
                         self.args().len()
                     ))
@@ -317,11 +688,15 @@ impl TypeCheck for FunctionCall {
             );
         }
 
-        for (dec_arg, given_arg) in args_type.iter().zip(self.args.iter()) {
-            // FIXME: Remove clone
-            let given_ty = given_arg.clone().type_of(ctx);
+        // The arguments need to be typechecked in place: Generic calls given as
+        // arguments get specialized while they are typechecked
+        for (dec_arg, given_arg) in args_type.iter().zip(self.args.iter_mut()) {
+            let given_ty = given_arg.type_of(ctx);
             let expected_ty = CheckedType::Resolved(dec_arg.get_type().clone());
-            if expected_ty != given_ty {
+            let promoted = self.promoted
+                && is_primitive(&expected_ty, "float")
+                && is_primitive(&given_ty, "int");
+            if expected_ty != given_ty && !promoted {
                 errors.push(
                     Error::new(ErrKind::TypeChecker)
                         .with_msg(format!(
@@ -361,12 +736,33 @@ impl GenericUser for FunctionCall {
         // using the generic map. And obviously just visit all of our arguments
 
         // FIXME: Can we unwrap here?
+        if let Some(instantiation) = &mut self.instantiation {
+            instantiation.resolve_usages(type_map, ctx);
+            return;
+        }
+
+        // The call is a positional type instantiation
+        if ctx.get_function(&self.fn_name).is_none() {
+            if let Some(dec) = ctx.get_custom_type(&self.fn_name).cloned() {
+                match self.to_type_instantiation(&dec) {
+                    Ok(mut instantiation) => {
+                        instantiation.resolve_usages(type_map, ctx);
+                        self.instantiation = Some(Box::new(instantiation));
+                    }
+                    Err(e) => ctx.error(e),
+                }
+                return;
+            }
+        }
+
         let dec = match ctx.get_function(&self.fn_name) {
             Some(f) => f,
+            // The call might be made through a variable containing a function value,
+            // which can only be known once the specialized function is typechecked
             None => {
-                ctx.error(Error::new(ErrKind::Generics)
-                    .with_msg(format!("trying to access undeclared function in new specialized function: `{}`", self.fn_name))
-                    .with_loc(self.location.clone()));
+                self.args
+                    .iter_mut()
+                    .for_each(|arg| arg.resolve_usages(type_map, ctx));
                 return;
             }
         };
@@ -403,7 +799,9 @@ impl GenericUser for FunctionCall {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::instance::ToObjectInstance;
     use crate::parser::constructs;
+    use crate::value::JkInt;
     use crate::{jinko, jinko_fail, span};
 
     #[test]
@@ -415,6 +813,100 @@ mod tests {
 
     // Don't ignore once variable execution is implemented
 
+    #[test]
+    fn t_nested_generic_calls() {
+        use crate::instance::ToObjectInstance;
+        use crate::jk_execute;
+        use crate::value::JkInt;
+
+        assert_eq!(
+            jk_execute! {
+                func snd[T](a: T, b: T) -> T { b }
+                snd[int](snd[int](1, 2), 3)
+            },
+            Some(JkInt::from(3).to_instance())
+        );
+    }
+
+    #[test]
+    fn t_generic_call_in_multiple_functions() {
+        use crate::instance::ToObjectInstance;
+        use crate::jk_execute;
+        use crate::value::JkInt;
+
+        assert_eq!(
+            jk_execute! {
+                func id[T](value: T) -> T { value }
+                func twice[T](value: T) -> T { id[T](value) }
+                func f() -> int { twice[int](1) }
+                func g() -> int { twice[int](2) }
+                f() + g()
+            },
+            Some(JkInt::from(3).to_instance())
+        );
+    }
+
+    #[test]
+    fn t_generic_call_with_generic_type() {
+        use crate::instance::ToObjectInstance;
+        use crate::jk_execute;
+        use crate::value::JkInt;
+
+        assert_eq!(
+            jk_execute! {
+                func id[T](value: T) -> T { value }
+                id[Vec[int]](vec[int]().push[int](15)).get[int](0)
+            },
+            Some(JkInt::from(15).to_instance())
+        );
+    }
+
+    #[test]
+    fn t_positional_type_instantiation() {
+        use crate::instance::ToObjectInstance;
+        use crate::jk_execute;
+        use crate::value::JkInt;
+
+        let instance = jk_execute! {
+            type Point(x: int, y: int = 15);
+            Point(1)
+        }
+        .unwrap();
+
+        assert_eq!(
+            instance.get_field("x").unwrap(),
+            JkInt::from(1).to_instance()
+        );
+        assert_eq!(
+            instance.get_field("y").unwrap(),
+            JkInt::from(15).to_instance()
+        );
+    }
+
+    #[test]
+    fn t_positional_generic_type_instantiation() {
+        use crate::instance::ToObjectInstance;
+        use crate::jk_execute;
+        use crate::value::JkInt;
+
+        assert_eq!(
+            jk_execute! {
+                type Wrapper[T](inner: T);
+                func wrap[T](value: T) -> Wrapper[T] { Wrapper[T](value) }
+                wrap[int](14).inner
+            },
+            Some(JkInt::from(14).to_instance())
+        );
+    }
+
+    #[test]
+    fn tc_positional_type_instantiation_too_many_fields() {
+        jinko_fail! {
+            type Point(x: int, y: int);
+            p = Point(1, 2, 3);
+        };
+    }
+
     #[test]
     fn t_invalid_args_number() {
         use crate::value::JkInt;
@@ -462,6 +954,21 @@ mod tests {
         );
     }
 
+    #[test]
+    fn t_func_call_invalid_arg_type_at_runtime() {
+        let mut i = Context::new();
+        let func_dec = constructs::expr(span!("func __second(f: int, s: int) -> int { s }"))
+            .unwrap()
+            .1;
+        // Execute the call directly, without typechecking it first
+        let func_call = constructs::expr(span!("__second(1, \"two\")")).unwrap().1;
+
+        func_dec.execute(&mut i);
+
+        assert!(func_call.execute(&mut i).is_none());
+        assert!(i.error_handler.has_errors());
+    }
+
     #[test]
     fn t_func_call_arg_return_binop() {
         use crate::instance::ToObjectInstance;
@@ -500,6 +1007,22 @@ mod tests {
         );
     }
 
+    #[test]
+    fn t_func_call_expression_args() {
+        use crate::instance::ToObjectInstance;
+        use crate::jk_execute;
+        use crate::value::JkInt;
+
+        let res = jk_execute! {
+            func g(a: int) -> int { a * 2 }
+            func f(a: int, b: int) -> int { a - b }
+            x = 1;
+            f(g(1) + 2, x) + f({ y = 3; y }, f(x, x))
+        };
+
+        assert_eq!(res, Some(JkInt::from(6).to_instance()));
+    }
+
     #[test]
     fn tc_invalid_type_for_arg() {
         jinko_fail! {
@@ -542,4 +1065,216 @@ mod tests {
             not_a_builtin();
         };
     }
+
+    #[test]
+    fn t_function_cannot_see_caller_variables() {
+        jinko_fail! {
+            func f() -> int { y }
+            func g() -> int {
+                y = 3;
+                f()
+            }
+            g();
+        };
+    }
+
+    #[test]
+    fn t_recursive_function() {
+        let ctx = jinko! {
+            func fact(n: int) -> int {
+                if n < 2 {
+                    1
+                } else {
+                    n * fact(n - 1)
+                }
+            }
+            res = fact(5);
+        };
+
+        assert_eq!(
+            ctx.get_variable("res").unwrap().instance(),
+            JkInt::from(120).to_instance()
+        );
+    }
+
+    #[test]
+    fn t_recursive_function_keeps_own_arguments() {
+        let ctx = jinko! {
+            func sum(n: int) -> int {
+                if n == 0 {
+                    0
+                } else {
+                    rest = sum(n - 1);
+                    n + rest
+                }
+            }
+            res = sum(4);
+        };
+
+        assert_eq!(
+            ctx.get_variable("res").unwrap().instance(),
+            JkInt::from(10).to_instance()
+        );
+    }
+
+    #[test]
+    fn t_arguments_evaluated_in_caller_scope() {
+        let ctx = jinko! {
+            func sub(x: int, y: int) -> int { x - y }
+            func call(x: int, y: int) -> int { sub(y, x) }
+            res = call(1, 3);
+        };
+
+        assert_eq!(
+            ctx.get_variable("res").unwrap().instance(),
+            JkInt::from(2).to_instance()
+        );
+    }
+
+    #[test]
+    fn t_recursion_limit() {
+        let mut ctx = Context::new();
+        ctx.set_recursion_limit(50);
+
+        let code = "func f(n: int) -> int { if n == 0 { 0 } else { 1 + f(n - 1) } }";
+        ctx.eval(code).unwrap();
+
+        let res = ctx.eval("f(49)").unwrap().unwrap();
+        assert_eq!(res, JkInt::from(49).to_instance());
+
+        assert!(ctx.eval("f(50)").is_err());
+        assert_eq!(ctx.error_handler.errors().len(), 1);
+
+        // The depth is reset once the calls have failed
+        assert!(ctx.eval("f(10)").is_ok());
+    }
+
+    #[test]
+    fn t_recursion_limit_stops_execution() {
+        let mut ctx = Context::new();
+        ctx.set_recursion_limit(20);
+
+        let code = "func f(n: int) -> int { rest = f(n + 1); n + rest } f(0)";
+
+        assert!(ctx.eval(code).is_err());
+        assert_eq!(ctx.error_handler.errors().len(), 1);
+        assert_eq!(ctx.error_handler.errors()[0].trace().len(), 20);
+    }
+
+    #[test]
+    fn t_runtime_error_trace() {
+        let mut ctx = Context::new();
+
+        let code = "func inner(x: int) -> int { x / 0 } func outer(x: int) -> int { inner(x) }";
+        ctx.eval(code).unwrap();
+        assert!(ctx.eval("outer(1)").is_err());
+
+        let trace = ctx.error_handler.errors()[0].trace();
+        let functions: Vec<&str> = trace.iter().map(|frame| frame.function()).collect();
+        assert_eq!(functions, ["outer", "inner"]);
+        assert_eq!(trace[1].loc().unwrap().start().column(), 65);
+    }
+
+    #[test]
+    fn t_error_trace_displays_declared_names() {
+        let mut ctx = Context::new();
+
+        let code = "func div(x: int, y: int) -> int { x / y } \
+            func div(x: float, y: int) -> int { 1 / y } \
+            func g[T](value: T) -> int { 1.0.div(0) }";
+        ctx.eval(code).unwrap();
+        assert!(ctx.eval("g[bool](true)").is_err());
+
+        let trace = ctx.error_handler.errors()[0].trace();
+        let functions: Vec<&str> = trace.iter().map(|frame| frame.function()).collect();
+        assert_eq!(functions, ["g[bool]", "div"]);
+    }
+
+    #[test]
+    fn t_top_level_error_has_no_trace() {
+        let mut ctx = Context::new();
+
+        assert!(ctx.eval("x = 1 / 0").is_err());
+        assert!(ctx.error_handler.errors()[0].trace().is_empty());
+    }
+
+    #[test]
+    fn t_function_as_argument() {
+        let ctx = jinko! {
+            func double(x: int) -> int { x * 2 }
+            func apply(f: func(int) -> int, x: int) -> int { f(x) }
+            res = apply(double, 21);
+        };
+
+        assert_eq!(
+            ctx.get_variable("res").unwrap().instance(),
+            JkInt::from(42).to_instance()
+        );
+    }
+
+    #[test]
+    fn t_function_stored_in_variable() {
+        let ctx = jinko! {
+            func double(x: int) -> int { x * 2 }
+            f = double;
+            res = f(f(3));
+        };
+
+        assert_eq!(
+            ctx.get_variable("res").unwrap().instance(),
+            JkInt::from(12).to_instance()
+        );
+    }
+
+    #[test]
+    fn t_function_value_shadows_function() {
+        let ctx = jinko! {
+            func f(x: int) -> int { x }
+            func double(x: int) -> int { x * 2 }
+            func apply(f: func(int) -> int, x: int) -> int { f(x) }
+            res = apply(double, 2);
+        };
+
+        assert_eq!(
+            ctx.get_variable("res").unwrap().instance(),
+            JkInt::from(4).to_instance()
+        );
+    }
+
+    #[test]
+    fn t_function_value_in_generic_function() {
+        let ctx = jinko! {
+            func double(x: int) -> int { x * 2 }
+            func twice[T](f: func(T) -> T, x: T) -> T { f(f(x)) }
+            res = twice[int](double, 5);
+        };
+
+        assert_eq!(
+            ctx.get_variable("res").unwrap().instance(),
+            JkInt::from(20).to_instance()
+        );
+    }
+
+    #[test]
+    fn t_function_value_invalid_signature() {
+        jinko_fail! {
+            func id(s: string) -> string { s }
+            func apply(f: func(int) -> int, x: int) -> int { f(x) }
+            apply(id, 2);
+        };
+    }
+
+    #[test]
+    fn t_function_value_invalid_arguments() {
+        jinko_fail! {
+            func double(x: int) -> int { x * 2 }
+            f = double;
+            f("hello");
+        };
+        jinko_fail! {
+            func double(x: int) -> int { x * 2 }
+            f = double;
+            f(1, 2);
+        };
+    }
 }
@@ -1,9 +1,18 @@
 //! Function Declarations are used when adding a new function to the source. They contain
 //! a name, a list of required arguments as well as an associated code block
-
-use crate::context::Context;
+//!
+//! Functions act as methods of the type of their first argument: Multiple functions can
+//! share the same name, as long as their first arguments are of different types. The
+//! first function declared keeps its name, while the following ones are registered
+//! under a name specific to the type of their first argument (see
+//! [`FunctionDec::method_name`]). Calls are then resolved using the type of their first
+//! argument, so that both `s.len()` and `len(s)` call the right function.
+
+use crate::analysis::SymbolKind;
+use crate::ast::{AstNode, Json};
+use crate::context::{Context, Interrupt};
 use crate::error::{ErrKind, Error};
-use crate::generics::{GenericExpander, GenericMap, GenericUser};
+use crate::generics::{self, GenericExpander, GenericMap, GenericUser};
 use crate::instance::ObjectInstance;
 use crate::instruction::{Block, DecArg, InstrKind, Instruction};
 use crate::location::{Location, SpanTuple};
@@ -20,6 +29,19 @@ pub enum FunctionKind {
     Mock,
 }
 
+impl FunctionKind {
+    /// Name of the kind of function
+    pub fn as_str(&self) -> &str {
+        match self {
+            FunctionKind::Unknown => "unknown",
+            FunctionKind::Func => "func",
+            FunctionKind::Ext => "ext",
+            FunctionKind::Test => "test",
+            FunctionKind::Mock => "mock",
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct FunctionDec {
     name: String,
@@ -28,6 +50,9 @@ pub struct FunctionDec {
     generics: Vec<TypeId>,
     args: Vec<DecArg>,
     block: Option<Block>,
+    /// Result of declaring the signature of the function in the typechecker, once it
+    /// has been declared
+    declaration: Option<CheckedType>,
     typechecked: bool,
     location: Option<SpanTuple>,
 }
@@ -47,6 +72,7 @@ impl FunctionDec {
             generics,
             args,
             block: None,
+            declaration: None,
             typechecked: false,
             location: None,
         }
@@ -96,6 +122,73 @@ impl FunctionDec {
         &self.name
     }
 
+    /// Name under which a function is registered when it is a method of the given
+    /// type, and another function with the same name already exists. This cannot
+    /// clash with user-defined functions since identifiers cannot contain dots
+    pub fn method_name(receiver: &TypeId, name: &str) -> String {
+        format!("{}.{}", receiver.id(), name)
+    }
+
+    /// Name of a function the way it is written in its source: Methods are displayed
+    /// without the type of their receiver, and specialized generic functions with
+    /// their type arguments, such as `g[int]` instead of `g+int`
+    pub fn display_name(name: &str) -> String {
+        let name = name.rsplit_once('.').map_or(name, |(_, name)| name);
+
+        generics::display_name(name)
+    }
+
+    /// Render the signature of the function, without its body, such as
+    /// `func add(a: int, b: int) -> int`
+    pub fn signature(&self) -> String {
+        let mut base = String::from(match self.kind {
+            FunctionKind::Func => "func",
+            FunctionKind::Ext => "ext func",
+            FunctionKind::Test => "test",
+            FunctionKind::Mock => "mock",
+            FunctionKind::Unknown => "UNKNOWN",
+        });
+
+        base = format!("{} {}", base, self.name);
+
+        if !self.generics.is_empty() {
+            base.push('[');
+            base.push_str(&self.generics.first().unwrap().print());
+            let generic_str = self
+                .generics
+                .iter()
+                .skip(1)
+                .fold(String::new(), |acc, ty_id| {
+                    format!("{}, {}", acc, ty_id.print())
+                });
+            base.push_str(&generic_str);
+            base.push(']');
+        }
+
+        base.push('(');
+        if !self.args.is_empty() {
+            base.push_str(&format!("{}", self.args().iter().next().unwrap()));
+            let arg_str = self
+                .args
+                .iter()
+                .skip(1)
+                .fold(String::new(), |acc, field| format!("{}, {}", acc, field));
+            base.push_str(&arg_str);
+        }
+        base.push(')');
+
+        match &self.ty {
+            Some(ty) => format!("{} -> {}", base, ty.print()),
+            None => base,
+        }
+    }
+
+    /// Rename the function, for example when prefixing it with the name of the
+    /// source it was included from
+    pub fn set_name(&mut self, name: String) {
+        self.name = name
+    }
+
     pub fn loc(&self) -> Option<SpanTuple> {
         self.location.clone()
     }
@@ -148,7 +241,16 @@ impl FunctionDec {
     /// Run through the function as if it was called. This is useful for setting
     /// an entry point into the interpreter and executing it
     pub fn run(&self, ctx: &mut Context) -> Option<ObjectInstance> {
-        self.block().unwrap().execute(ctx)
+        let value = self.block().unwrap().execute(ctx);
+
+        match ctx.take_interrupt() {
+            Some(Interrupt::Return(value)) => value,
+            Some(interrupt) => {
+                ctx.interrupt(interrupt);
+                value
+            }
+            None => value,
+        }
     }
 }
 
@@ -171,7 +273,12 @@ impl Instruction for FunctionDec {
                     ctx.error(e);
                 }
             }
-            FunctionKind::Mock | FunctionKind::Unknown => ctx.error(
+            FunctionKind::Mock => {
+                if let Err(e) = ctx.add_mock(self.clone()) {
+                    ctx.error(e);
+                }
+            }
+            FunctionKind::Unknown => ctx.error(
                 Error::new(ErrKind::Context)
                     .with_msg(format!("unknown type for function {}", self.name()))
                     .with_loc(self.loc()),
@@ -182,51 +289,28 @@ impl Instruction for FunctionDec {
     }
 
     fn print(&self) -> String {
-        let mut base = String::from(match self.kind {
-            FunctionKind::Func => "func",
-            FunctionKind::Ext => "ext func",
-            FunctionKind::Test => "test",
-            FunctionKind::Mock => "mock",
-            FunctionKind::Unknown => "UNKNOWN",
-        });
-
-        base = format!("{} {}", base, self.name);
-
-        if !self.generics.is_empty() {
-            base.push('[');
-            base.push_str(self.generics.first().unwrap().id());
-            let generic_str = self
-                .generics
-                .iter()
-                .skip(1)
-                .fold(String::new(), |acc, ty_id| {
-                    format!("{}, {}", acc, ty_id.id())
-                });
-            base.push_str(&generic_str);
-            base.push(']');
-        }
+        let base = self.signature();
 
-        base.push('(');
-        if !self.args.is_empty() {
-            base.push_str(&format!("{}", self.args().iter().next().unwrap()));
-            let arg_str = self
-                .args
-                .iter()
-                .skip(1)
-                .fold(String::new(), |acc, field| format!("{}, {}", acc, field));
-            base.push_str(&arg_str);
+        match (&self.block, self.kind) {
+            (_, FunctionKind::Ext) => format!("{};", base),
+            (Some(block), _) => format!("{} {}", base, block.print()),
+            (None, _) => format!("{} {{}}", base),
         }
-        base.push(')');
+    }
 
-        base = match &self.ty {
-            Some(ty) => format!("{} -> {}", base, ty.id()),
-            None => base,
-        };
+    fn ast(&self) -> AstNode {
+        let args: Vec<AstNode> = self.args.iter().map(DecArg::ast).collect();
 
-        match &self.block {
-            Some(block) => format!("{} {}", base, block.print()),
-            None => format!("{} {{}}", base),
-        }
+        AstNode::new("FunctionDec", self.location())
+            .with_attr("name", self.name.as_str())
+            .with_attr("kind", self.kind.as_str())
+            .with_attr(
+                "generics",
+                self.generics.iter().map(Json::from).collect::<Vec<_>>(),
+            )
+            .with_attr("return_type", self.ty.as_ref())
+            .with_child("args", args)
+            .with_child("block", self.block.as_ref().map(Block::ast))
     }
 
     fn location(&self) -> Option<&SpanTuple> {
@@ -234,37 +318,27 @@ impl Instruction for FunctionDec {
     }
 }
 
-impl TypeCheck for FunctionDec {
-    fn resolve_type(&mut self, ctx: &mut TypeCtx) -> CheckedType {
-        // FIXME Do not declare test functions in the typechecker? But typecheck
-        // them still? Is this the correct behavior?
-        if self.fn_kind() == FunctionKind::Test || self.fn_kind() == FunctionKind::Mock {
-            return self
-                .block
-                .as_mut()
-                .map_or(CheckedType::Void, |b| b.type_of(ctx));
+impl FunctionDec {
+    /// Resolve the argument and return types written with concrete generic types,
+    /// such as `Vec[int]`, to their specialized types
+    fn resolve_concrete_types(&mut self, ctx: &mut TypeCtx) -> Result<(), Error> {
+        for arg in self.args.iter_mut() {
+            let ty = ctx.resolve_concrete_type(arg.get_type())?;
+            arg.set_type(ty);
         }
 
-        // If a declaration contains generic types, there is no point in type-checking
-        // it: All the methods or field accesses will, by definition, not exist, since
-        // the generic types do not exist yet
-        if !self.generics.is_empty() {
-            // Just declare the function so we have it in the context and can
-            // duplicate it
-            if let Err(e) = ctx.declare_generic_function(self.name().into(), self.clone()) {
-                ctx.error(e);
-                return CheckedType::Error;
-            }
-
-            return CheckedType::Later;
+        if let Some(ty) = &self.ty {
+            self.ty = Some(ctx.resolve_concrete_type(ty)?);
         }
 
-        // FIXME: Remove clone?
-        if let Err(e) = ctx.declare_function(self.name().into(), self.clone()) {
-            ctx.error(e);
-        }
+        Ok(())
+    }
 
-        ctx.scope_enter();
+    /// Typecheck the body of the function against its return type, in a frame
+    /// containing only its arguments
+    fn resolve_body_type(&mut self, ctx: &mut TypeCtx) -> CheckedType {
+        // The body of the function cannot access the variables of its caller
+        ctx.frame_enter();
 
         // FIXME: Both return_ty and args_ty can be factored from the `ty_declare`
         // function
@@ -285,39 +359,193 @@ impl TypeCheck for FunctionDec {
             })
             .collect();
 
-        args_ty.iter().for_each(|(name, ty)| {
-            if let Err(e) = ctx.declare_var(name.clone(), ty.clone()) {
-                ctx.error(e);
-            }
-        });
+        args_ty
+            .iter()
+            .zip(&self.args)
+            .for_each(|((name, ty), dec_arg)| {
+                let detail = format!("{}: {}", name, ty.print());
+                ctx.define(name, SymbolKind::Variable, dec_arg.location(), detail);
+
+                if let Err(e) = ctx.declare_var(name.clone(), ty.clone()) {
+                    ctx.error(e);
+                }
+            });
 
         // If the function has no block, trust the declaration
         if let Some(b) = &mut self.block {
+            ctx.function_enter(return_ty.clone());
             let block_ty = b.type_of(ctx);
+            ctx.function_exit();
 
             if block_ty != return_ty {
                 ctx.error(
                     Error::new(ErrKind::TypeChecker)
                         .with_msg(format!(
                     "invalid type returned in function `{}`: expected type {}, found type {}",
-                    self.name(),
+                    FunctionDec::display_name(self.name()),
                     return_ty,
                     block_ty
                 ))
                         .with_loc(self.loc()),
                 );
 
-                ctx.scope_exit();
+                ctx.frame_exit();
 
                 return CheckedType::Error;
             }
         }
 
-        ctx.scope_exit();
+        ctx.frame_exit();
 
         CheckedType::Void
     }
 
+    /// Typecheck an anonymous function: Unlike regular functions, it is not declared
+    /// in the context and can only be called through the value it evaluates to
+    pub(crate) fn resolve_anonymous_type(&mut self, ctx: &mut TypeCtx) -> CheckedType {
+        if let Err(e) = self.resolve_concrete_types(ctx) {
+            ctx.error(e.with_loc(self.loc()));
+            return CheckedType::Error;
+        }
+
+        self.resolve_body_type(ctx)
+    }
+
+    /// Declare the signature of the function in the typechecker, without typechecking
+    /// its body. This is done before typechecking the other instructions of a source,
+    /// so that functions can be called before being declared. Returns `Void` once the
+    /// function is declared, or `Later` if it is generic
+    pub(crate) fn declare(&mut self, ctx: &mut TypeCtx) -> CheckedType {
+        let declaration = self.declare_signature(ctx);
+        self.declaration = Some(declaration.clone());
+
+        declaration
+    }
+
+    fn declare_signature(&mut self, ctx: &mut TypeCtx) -> CheckedType {
+        // Functions taking interfaces are specialized for each type they are called
+        // with, in the same way as generic functions
+        if self.fn_kind() == FunctionKind::Func {
+            for arg in self.args.iter() {
+                let ty = arg.get_type();
+                if ctx.get_interface(ty.id()).is_some() && !self.generics.contains(ty) {
+                    self.generics.push(ty.clone());
+                }
+            }
+        }
+
+        // If a declaration contains generic types, there is no point in type-checking
+        // it: All the methods or field accesses will, by definition, not exist, since
+        // the generic types do not exist yet
+        if !self.generics.is_empty() {
+            // Just declare the function so we have it in the context and can
+            // duplicate it
+            if let Err(e) = ctx.declare_generic_function(self.name().into(), self.clone()) {
+                ctx.error(e);
+                return CheckedType::Error;
+            }
+
+            return CheckedType::Later;
+        }
+
+        if let Err(e) = self.resolve_concrete_types(ctx) {
+            ctx.error(e.with_loc(self.loc()));
+            return CheckedType::Error;
+        }
+
+        // If a function with the same name exists for a different receiver type, then
+        // this function is a method of its first argument's type
+        let is_method = match (ctx.get_function(self.name()), self.args.first()) {
+            (Some(existing), Some(receiver)) if self.fn_kind() == FunctionKind::Func => {
+                existing.args().first().map(DecArg::get_type) != Some(receiver.get_type())
+            }
+            _ => false,
+        };
+        if is_method {
+            self.name = FunctionDec::method_name(self.args[0].get_type(), &self.name);
+        }
+
+        // FIXME: Remove clone?
+        if let Err(e) = ctx.declare_function(self.name().into(), self.clone()) {
+            ctx.error(e);
+        }
+
+        CheckedType::Void
+    }
+
+    /// A mock replaces a function during tests, so it must have the exact same
+    /// signature for the calls to that function to stay valid
+    fn resolve_mock_type(&mut self, ctx: &mut TypeCtx) -> CheckedType {
+        let (same_signature, original_loc) = match ctx.get_function(self.name()) {
+            Some(original) => (
+                original.ty() == self.ty()
+                    && original
+                        .args()
+                        .iter()
+                        .map(DecArg::get_type)
+                        .eq(self.args.iter().map(DecArg::get_type)),
+                original.loc(),
+            ),
+            None => {
+                ctx.error(
+                    Error::new(ErrKind::TypeChecker)
+                        .with_msg(format!("cannot mock undeclared function `{}`", self.name()))
+                        .with_loc(self.loc()),
+                );
+                return CheckedType::Error;
+            }
+        };
+
+        if !same_signature {
+            ctx.error(
+                Error::new(ErrKind::TypeChecker)
+                    .with_msg(format!(
+                        "mock of function `{}` does not have the same signature as the function",
+                        self.name()
+                    ))
+                    .with_loc(self.loc())
+                    .with_hint(
+                        Error::new(ErrKind::Hint)
+                            .with_msg(String::from("function declared here"))
+                            .with_loc(original_loc),
+                    ),
+            );
+            return CheckedType::Error;
+        }
+
+        self.resolve_body_type(ctx)
+    }
+}
+
+impl TypeCheck for FunctionDec {
+    fn resolve_type(&mut self, ctx: &mut TypeCtx) -> CheckedType {
+        // FIXME Do not declare test functions in the typechecker? But typecheck
+        // them still? Is this the correct behavior?
+        if self.fn_kind() == FunctionKind::Test {
+            return self
+                .block
+                .as_mut()
+                .map_or(CheckedType::Void, |b| b.type_of(ctx));
+        }
+
+        if self.fn_kind() == FunctionKind::Mock {
+            return self.resolve_mock_type(ctx);
+        }
+
+        // The signature of the function might have been declared already, before
+        // typechecking the other instructions of the source
+        let declaration = match self.declaration.clone() {
+            Some(declaration) => declaration,
+            None => self.declare(ctx),
+        };
+
+        match declaration {
+            CheckedType::Void => self.resolve_body_type(ctx),
+            // Generic functions only get typechecked once specialized
+            declaration => declaration,
+        }
+    }
+
     fn set_cached_type(&mut self, _ty: CheckedType) {
         self.typechecked = true
     }
@@ -349,6 +577,7 @@ impl GenericExpander for FunctionDec {
         let mut new_fn = self.clone();
         new_fn.name = mangled_name;
         new_fn.generics = vec![];
+        new_fn.declaration = None;
         new_fn.typechecked = false;
 
         new_fn
@@ -396,7 +625,7 @@ impl From<&str> for FunctionKind {
 mod tests {
     use super::*;
     use crate::span;
-    use crate::{jinko, parser::constructs, typechecker::TypeId};
+    use crate::{jinko, jinko_fail, parser::constructs, typechecker::TypeId};
 
     #[test]
     fn simple_no_arg() {
@@ -473,4 +702,21 @@ mod tests {
             func return_int_i(i: int) -> int { i }
         };
     }
+
+    #[test]
+    fn tc_concrete_generic_types_in_signature() {
+        jinko! {
+            func first(v: Vec[int]) -> int { v.get[int](0) }
+            func single(value: int) -> Vec[int] { vec[int]().push[int](value) }
+
+            first(single(15));
+        };
+    }
+
+    #[test]
+    fn tc_undeclared_generic_type_in_signature() {
+        jinko_fail! {
+            func first(v: Undeclared[int]) -> int { 15 }
+        };
+    }
 }
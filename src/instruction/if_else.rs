@@ -14,7 +14,11 @@
 //! ```ignore
 //! x = if condition { 12 } else { 13 };
 //! ```
+//!
+//! `else if` chains are represented as an else body containing the next `IfElse`, and
+//! are printed back as a flat chain.
 
+use crate::ast::AstNode;
 use crate::context::Context;
 use crate::error::{ErrKind, Error};
 use crate::generics::{GenericMap, GenericUser};
@@ -52,6 +56,27 @@ impl IfElse {
     pub fn set_location(&mut self, location: SpanTuple) {
         self.location = Some(location)
     }
+
+    /// Get the block executed when the condition is true
+    pub fn if_body(&self) -> &Block {
+        &self.if_body
+    }
+
+    /// Get the block executed when the condition is false, if there is one
+    pub fn else_body(&self) -> Option<&Block> {
+        self.else_body.as_ref()
+    }
+
+    /// Get the next condition of an `else if` chain. The `else` block of the
+    /// condition needs to only contain another condition, whose value it returns
+    pub fn else_if(&self) -> Option<&IfElse> {
+        let else_body = self.else_body.as_ref()?;
+
+        match else_body.instructions().as_slice() {
+            [inst] if !else_body.is_statement() => inst.downcast_ref::<IfElse>(),
+            _ => None,
+        }
+    }
 }
 
 impl Instruction for IfElse {
@@ -64,12 +89,20 @@ impl Instruction for IfElse {
     fn print(&self) -> String {
         let base = format!("if {} {}", self.condition.print(), self.if_body.print());
 
-        match &self.else_body {
-            Some(body) => format!("{} else {}", base, body.print()),
-            None => base,
+        match (self.else_if(), &self.else_body) {
+            (Some(else_if), _) => format!("{} else {}", base, else_if.print()),
+            (None, Some(body)) => format!("{} else {}", base, body.print()),
+            (None, None) => base,
         }
     }
 
+    fn ast(&self) -> AstNode {
+        AstNode::new("IfElse", self.location())
+            .with_child("condition", self.condition.ast())
+            .with_child("if_body", self.if_body.ast())
+            .with_child("else_body", self.else_body.as_ref().map(Block::ast))
+    }
+
     fn execute(&self, ctx: &mut Context) -> Option<ObjectInstance> {
         let cond = self.condition.execute(ctx)?;
 
@@ -114,6 +147,9 @@ impl TypeCheck for IfElse {
 
         match (if_ty, else_ty) {
             (CheckedType::Void, None) => CheckedType::Void,
+            // The error was reported already, for example by the condition of an
+            // `else if`
+            (CheckedType::Error, _) | (_, Some(CheckedType::Error)) => CheckedType::Error,
             (if_ty, Some(else_ty)) => {
                 if if_ty != else_ty {
                     ctx.error(
@@ -165,6 +201,7 @@ impl GenericUser for IfElse {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::value::JkInt;
     use crate::{jinko, jinko_fail};
 
     #[test]
@@ -173,7 +210,7 @@ mod tests {
 
         let if_block = IfElse::new(Box::new(JkBool::from(true)), Block::new(), None);
 
-        assert_eq!(if_block.print(), "if true {\n}".to_string());
+        assert_eq!(if_block.print(), "if true {}".to_string());
     }
 
     #[test]
@@ -186,7 +223,7 @@ mod tests {
             Some(Block::new()),
         );
 
-        assert_eq!(if_block.print(), "if true {\n} else {\n}".to_string());
+        assert_eq!(if_block.print(), "if true {} else {}".to_string());
     }
 
     #[test]
@@ -287,4 +324,60 @@ mod tests {
             }
         };
     }
+
+    #[test]
+    fn t_else_if_print() {
+        let (_, if_else) =
+            crate::parser::constructs::expr(crate::span!("if a { 1 } else if b { 2 } else { 3 }"))
+                .unwrap();
+
+        assert_eq!(
+            if_else.print(),
+            "if a {\n    1\n} else if b {\n    2\n} else {\n    3\n}"
+        );
+    }
+
+    #[test]
+    fn t_else_statement_block_print() {
+        // The value of the condition is discarded: It is not part of an `else if` chain
+        let (_, if_else) = crate::parser::constructs::expr(crate::span!(
+            "if a { 1; } else { if b { 2 } else { 3 }; }"
+        ))
+        .unwrap();
+
+        assert!(if_else.print().contains("} else {\n    if b"));
+    }
+
+    #[test]
+    fn t_else_if_execute() {
+        let ctx = jinko! {
+            func sign(x: int) -> int {
+                if x < 0 { 0 - 1 } else if x == 0 { 0 } else { 1 }
+            }
+
+            a = sign(0 - 4);
+            b = sign(0);
+            c = sign(12);
+        };
+
+        let value = |name| ctx.get_value::<JkInt>(name).unwrap().rust_value();
+        assert_eq!((value("a"), value("b"), value("c")), (-1, 0, 1));
+    }
+
+    #[test]
+    fn tc_else_if_without_else() {
+        let ctx = jinko_fail! {
+            if true { 1 } else if false { 2 }
+        };
+
+        // The error is only reported for the last condition of the chain
+        assert_eq!(ctx.error_handler.errors().len(), 1);
+    }
+
+    #[test]
+    fn tc_else_if_mismatched_types() {
+        jinko_fail! {
+            if true { 1 } else if false { 2 } else { 4.5 }
+        };
+    }
 }
@@ -0,0 +1,506 @@
+//! Interfaces declare a set of functions which a type must provide. The implementing
+//! type is referred to as `Self` in the signatures of the functions:
+//!
+//! ```ignore
+//! interface Printable {
+//!     func to_string(value: Self) -> string;
+//! }
+//!
+//! impl Printable for Point {
+//!     func to_string(p: Point) -> string { ... }
+//! }
+//! ```
+//!
+//! An `impl` block declares its functions like regular ones, and the typechecker
+//! ensures that all the functions of the interface are implemented with the right
+//! signature. Functions required by an interface can also be declared outside of the
+//! `impl` block.
+//!
+//! Interfaces can then be used as the type of function arguments. Functions taking
+//! interfaces are specialized for each type they get called with, just like generic
+//! functions, once the typechecker has verified that the type implements the interface.
+
+use crate::ast::AstNode;
+use crate::context::Context;
+use crate::error::{ErrKind, Error};
+use crate::generics::GenericUser;
+use crate::instance::ObjectInstance;
+use crate::instruction::{FunctionDec, InstrKind, Instruction};
+use crate::location::SpanTuple;
+use crate::typechecker::{CheckedType, TypeCheck, TypeCtx, TypeId};
+
+/// Name used in the signatures of an interface to refer to the implementing type
+pub const SELF_TYPE: &str = "Self";
+
+/// Replace the usages of `Self` in a type with the type implementing an interface
+fn replace_self(ty: &TypeId, implementor: &TypeId) -> TypeId {
+    let replace_all = |types: &[TypeId]| {
+        types
+            .iter()
+            .map(|ty| replace_self(ty, implementor))
+            .collect()
+    };
+
+    match ty {
+        TypeId::Type { id, .. } if id.access() == SELF_TYPE => implementor.clone(),
+        TypeId::Type { id, generics } => TypeId::Type {
+            id: id.clone(),
+            generics: replace_all(generics),
+        },
+        TypeId::Functor {
+            generics,
+            arg_types,
+            return_type,
+        } => TypeId::Functor {
+            generics: replace_all(generics),
+            arg_types: replace_all(arg_types),
+            return_type: return_type
+                .as_ref()
+                .map(|ret| Box::new(replace_self(ret, implementor))),
+        },
+    }
+}
+
+/// Print a list of function declarations, indented by one level
+fn print_functions(functions: &[FunctionDec], print: impl Fn(&FunctionDec) -> String) -> String {
+    functions
+        .iter()
+        .map(|function| {
+            print(function)
+                .lines()
+                .map(|line| format!("    {}\n", line))
+                .collect::<String>()
+        })
+        .collect()
+}
+
+#[derive(Clone)]
+pub struct InterfaceDec {
+    name: String,
+    functions: Vec<FunctionDec>,
+    typechecked: bool,
+    location: Option<SpanTuple>,
+}
+
+impl InterfaceDec {
+    /// Create a new interface requiring the given functions
+    pub fn new(name: String, functions: Vec<FunctionDec>) -> InterfaceDec {
+        InterfaceDec {
+            name,
+            functions,
+            typechecked: false,
+            location: None,
+        }
+    }
+
+    /// Get a reference to the name of the interface
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the signatures of the functions required by the interface
+    pub fn functions(&self) -> &[FunctionDec] {
+        &self.functions
+    }
+
+    pub fn set_location(&mut self, location: SpanTuple) {
+        self.location = Some(location)
+    }
+
+    /// Print the signature of a function, without a block
+    fn print_signature(function: &FunctionDec) -> String {
+        format!("{};", function.print().trim_end_matches(" {}"))
+    }
+}
+
+impl Instruction for InterfaceDec {
+    fn kind(&self) -> InstrKind {
+        InstrKind::Statement
+    }
+
+    fn print(&self) -> String {
+        format!(
+            "interface {} {{\n{}}}",
+            self.name,
+            print_functions(&self.functions, InterfaceDec::print_signature)
+        )
+    }
+
+    fn ast(&self) -> AstNode {
+        let functions: Vec<AstNode> = self.functions.iter().map(FunctionDec::ast).collect();
+
+        AstNode::new("InterfaceDec", self.location())
+            .with_attr("name", self.name.as_str())
+            .with_child("functions", functions)
+    }
+
+    fn execute(&self, _ctx: &mut Context) -> Option<ObjectInstance> {
+        // Interfaces only exist in the typechecker
+        None
+    }
+
+    fn location(&self) -> Option<&SpanTuple> {
+        self.location.as_ref()
+    }
+}
+
+impl TypeCheck for InterfaceDec {
+    fn resolve_type(&mut self, ctx: &mut TypeCtx) -> CheckedType {
+        if ctx.get_custom_type(&self.name).is_some() {
+            ctx.error(
+                Error::new(ErrKind::TypeChecker)
+                    .with_msg(format!(
+                        "interface `{}` has the same name as an existing type",
+                        self.name
+                    ))
+                    .with_loc(self.location.clone()),
+            );
+            return CheckedType::Error;
+        }
+
+        if let Err(e) = ctx.declare_interface(self.clone()) {
+            ctx.error(e.with_loc(self.location.clone()));
+            return CheckedType::Error;
+        }
+
+        CheckedType::Void
+    }
+
+    fn set_cached_type(&mut self, _ty: CheckedType) {
+        self.typechecked = true
+    }
+
+    fn cached_type(&self) -> Option<&CheckedType> {
+        match self.typechecked {
+            true => Some(&CheckedType::Void),
+            false => None,
+        }
+    }
+}
+
+impl GenericUser for InterfaceDec {}
+
+#[derive(Clone)]
+pub struct InterfaceImpl {
+    interface: String,
+    ty: TypeId,
+    functions: Vec<FunctionDec>,
+    cached_type: Option<CheckedType>,
+    location: Option<SpanTuple>,
+}
+
+impl InterfaceImpl {
+    /// Create a new implementation of an interface for a type, containing the given
+    /// functions
+    pub fn new(interface: String, ty: TypeId, functions: Vec<FunctionDec>) -> InterfaceImpl {
+        InterfaceImpl {
+            interface,
+            ty,
+            functions,
+            cached_type: None,
+            location: None,
+        }
+    }
+
+    /// Get a reference to the name of the implemented interface
+    pub fn interface(&self) -> &str {
+        &self.interface
+    }
+
+    /// Get a reference to the type implementing the interface
+    pub fn ty(&self) -> &TypeId {
+        &self.ty
+    }
+
+    pub fn set_location(&mut self, location: SpanTuple) {
+        self.location = Some(location)
+    }
+
+    /// Check that a function required by the interface is declared for the
+    /// implementing type, with the same signature
+    fn check_function(&self, signature: &FunctionDec, ctx: &mut TypeCtx) -> Result<(), Error> {
+        let arg_types: Vec<TypeId> = signature
+            .args()
+            .iter()
+            .map(|arg| replace_self(arg.get_type(), &self.ty))
+            .collect();
+        let return_type = signature.ty().map(|ty| replace_self(ty, &self.ty));
+
+        let mut candidates = vec![String::from(signature.name())];
+        if let Some(receiver) = arg_types.first() {
+            candidates.insert(0, FunctionDec::method_name(receiver, signature.name()));
+        }
+
+        let implementation = candidates.iter().find(|name| {
+            ctx.get_function(name).is_some_and(|function| {
+                function.ty() == return_type.as_ref()
+                    && function
+                        .args()
+                        .iter()
+                        .map(|arg| arg.get_type())
+                        .eq(arg_types.iter())
+            })
+        });
+
+        match implementation {
+            Some(name) => {
+                // Implementations are used through the interface
+                ctx.use_function(name);
+                Ok(())
+            }
+            None => {
+                let mut expected = signature.clone();
+                expected.set_args(
+                    signature
+                        .args()
+                        .iter()
+                        .map(|arg| {
+                            let mut arg = arg.clone();
+                            arg.set_type(replace_self(arg.get_type(), &self.ty));
+                            arg
+                        })
+                        .collect(),
+                );
+                expected.set_ty(return_type);
+
+                Err(Error::new(ErrKind::TypeChecker)
+                    .with_msg(format!(
+                        "type `{}` does not implement function `{}` of interface `{}`",
+                        self.ty,
+                        signature.name(),
+                        self.interface
+                    ))
+                    .with_loc(self.location.clone())
+                    .with_hint(
+                        Error::hint()
+                            .with_msg(format!(
+                                "expected signature: {}",
+                                InterfaceDec::print_signature(&expected)
+                            ))
+                            .with_loc(signature.loc()),
+                    ))
+            }
+        }
+    }
+}
+
+impl Instruction for InterfaceImpl {
+    fn kind(&self) -> InstrKind {
+        InstrKind::Statement
+    }
+
+    fn print(&self) -> String {
+        format!(
+            "impl {} for {} {{\n{}}}",
+            self.interface,
+            self.ty.print(),
+            print_functions(&self.functions, FunctionDec::print)
+        )
+    }
+
+    fn ast(&self) -> AstNode {
+        let functions: Vec<AstNode> = self.functions.iter().map(FunctionDec::ast).collect();
+
+        AstNode::new("InterfaceImpl", self.location())
+            .with_attr("interface", self.interface.as_str())
+            .with_attr("type", &self.ty)
+            .with_child("functions", functions)
+    }
+
+    fn execute(&self, ctx: &mut Context) -> Option<ObjectInstance> {
+        self.functions.iter().for_each(|function| {
+            function.execute(ctx);
+        });
+
+        None
+    }
+
+    fn location(&self) -> Option<&SpanTuple> {
+        self.location.as_ref()
+    }
+}
+
+impl TypeCheck for InterfaceImpl {
+    fn resolve_type(&mut self, ctx: &mut TypeCtx) -> CheckedType {
+        let interface = match ctx.get_interface(&self.interface) {
+            Some(interface) => interface.clone(),
+            None => {
+                ctx.error(
+                    Error::new(ErrKind::TypeChecker)
+                        .with_msg(format!("use of undeclared interface `{}`", self.interface))
+                        .with_loc(self.location.clone()),
+                );
+                return CheckedType::Error;
+            }
+        };
+
+        self.ty = match ctx.resolve_concrete_type(&self.ty) {
+            Ok(ty) if ctx.get_custom_type(ty.id()).is_some() => ty,
+            Ok(ty) => {
+                ctx.error(
+                    Error::new(ErrKind::TypeChecker)
+                        .with_msg(format!("use of undeclared type `{}`", ty))
+                        .with_loc(self.location.clone()),
+                );
+                return CheckedType::Error;
+            }
+            Err(e) => {
+                ctx.error(e.with_loc(self.location.clone()));
+                return CheckedType::Error;
+            }
+        };
+
+        let mut errors = vec![];
+        for function in self.functions.iter_mut() {
+            let name = function.name().to_owned();
+            if !interface.functions().iter().any(|f| f.name() == name) {
+                errors.push(
+                    Error::new(ErrKind::TypeChecker)
+                        .with_msg(format!(
+                            "function `{}` is not a member of interface `{}`",
+                            name, self.interface
+                        ))
+                        .with_loc(function.loc()),
+                );
+            }
+
+            function.type_of(ctx);
+        }
+
+        interface
+            .functions()
+            .iter()
+            .filter_map(|signature| self.check_function(signature, ctx).err())
+            .for_each(|e| errors.push(e));
+
+        if !errors.is_empty() {
+            errors.into_iter().for_each(|e| ctx.error(e));
+            return CheckedType::Error;
+        }
+
+        if let Err(e) = ctx.declare_implementation(&self.interface, &self.ty) {
+            ctx.error(e.with_loc(self.location.clone()));
+            return CheckedType::Error;
+        }
+
+        CheckedType::Void
+    }
+
+    fn set_cached_type(&mut self, ty: CheckedType) {
+        self.cached_type = Some(ty)
+    }
+
+    fn cached_type(&self) -> Option<&CheckedType> {
+        self.cached_type.as_ref()
+    }
+}
+
+impl GenericUser for InterfaceImpl {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instance::ToObjectInstance;
+    use crate::value::{JkInt, JkString};
+    use crate::{jinko, jinko_fail};
+
+    #[test]
+    fn t_replace_self() {
+        let ty = TypeId::functor()
+            .with_arg(TypeId::from("Self"))
+            .with_return_type(TypeId::from("int"));
+
+        assert_eq!(
+            replace_self(&ty, &TypeId::from("Point")).print(),
+            "func(Point) -> int"
+        );
+    }
+
+    #[test]
+    fn t_interface_argument() {
+        let ctx = jinko! {
+            interface Named { func name(value: Self) -> string; }
+            type Dog;
+            impl Named for Dog { func name(d: Dog) -> string { "dog" } }
+            func greet(value: Named) -> string { value.name() }
+            res = greet(Dog);
+        };
+
+        assert_eq!(
+            ctx.get_variable("res").unwrap().instance(),
+            JkString::from("dog").to_instance()
+        );
+    }
+
+    #[test]
+    fn t_interface_implemented_outside_of_impl() {
+        let ctx = jinko! {
+            interface Sized { func size(value: Self) -> int; }
+            type Box(inner: int);
+            func size(b: Box) -> int { b.inner }
+            impl Sized for Box {}
+            func double_size(value: Sized) -> int { value.size() * 2 }
+            res = double_size(Box(inner: 21));
+        };
+
+        assert_eq!(
+            ctx.get_variable("res").unwrap().instance(),
+            JkInt::from(42).to_instance()
+        );
+    }
+
+    #[test]
+    fn t_interface_not_implemented() {
+        jinko_fail! {
+            interface Named { func name(value: Self) -> string; }
+            func greet(value: Named) -> string { value.name() }
+            greet(15);
+        };
+    }
+
+    #[test]
+    fn t_impl_missing_function() {
+        jinko_fail! {
+            interface Named { func name(value: Self) -> string; }
+            type Dog;
+            impl Named for Dog {}
+        };
+    }
+
+    #[test]
+    fn t_impl_invalid_signature() {
+        jinko_fail! {
+            interface Named { func name(value: Self) -> string; }
+            type Dog;
+            impl Named for Dog { func name(d: Dog) -> int { 0 } }
+        };
+    }
+
+    #[test]
+    fn t_impl_extra_function() {
+        jinko_fail! {
+            interface Named { func name(value: Self) -> string; }
+            type Dog;
+            impl Named for Dog {
+                func name(d: Dog) -> string { "dog" }
+                func bark(d: Dog) -> string { "woof" }
+            }
+        };
+    }
+
+    #[test]
+    fn t_impl_undeclared_interface() {
+        jinko_fail! {
+            type Dog;
+            impl Named for Dog {}
+        };
+    }
+
+    #[test]
+    fn t_interface_declared_twice() {
+        jinko_fail! {
+            interface Named { func name(value: Self) -> string; }
+            interface Named {}
+        };
+    }
+}
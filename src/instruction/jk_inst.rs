@@ -3,7 +3,8 @@
 //! really an `Instruction`, and therefore their implementation lives in the parser
 //! module. They are executed at "compile" time, when running through the code first.
 
-use crate::context::Context;
+use crate::ast::AstNode;
+use crate::context::{Context, Interrupt};
 use crate::error::{ErrKind, Error};
 use crate::generics::GenericUser;
 use crate::instance::ObjectInstance;
@@ -19,6 +20,17 @@ pub enum JkInstKind {
     Ir,
 }
 
+impl JkInstKind {
+    /// Name of the directive, as given after the `@`
+    pub fn as_str(&self) -> &str {
+        match self {
+            JkInstKind::Dump => "dump",
+            JkInstKind::Quit => "quit",
+            JkInstKind::Ir => "ir",
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct JkInst {
     kind: JkInstKind,
@@ -56,18 +68,17 @@ impl Instruction for JkInst {
     }
 
     fn print(&self) -> String {
-        match self.kind {
-            JkInstKind::Dump => "@dump",
-            JkInstKind::Quit => "@quit",
-            JkInstKind::Ir => "@ir",
-        }
-        .to_string()
+        format!("@{}()", self.kind.as_str())
+    }
+
+    fn ast(&self) -> AstNode {
+        AstNode::new("JkInst", self.location()).with_attr("name", self.kind.as_str())
     }
 
     fn execute(&self, ctx: &mut Context) -> Option<ObjectInstance> {
         match self.kind {
             JkInstKind::Dump => println!("{}", ctx.print()),
-            JkInstKind::Quit => std::process::exit(0),
+            JkInstKind::Quit => ctx.interrupt(Interrupt::Exit(0)),
             JkInstKind::Ir => eprintln!("usage: {:?} <statement|expr>", JkInstKind::Ir),
         };
 
@@ -9,7 +9,8 @@
 //! return 42
 //! ```
 
-use crate::context::Context;
+use crate::ast::AstNode;
+use crate::context::{Context, Interrupt};
 use crate::generics::GenericUser;
 use crate::instance::ObjectInstance;
 use crate::instruction::{InstrKind, Instruction};
@@ -55,11 +56,22 @@ impl Instruction for Return {
         }
     }
 
+    fn ast(&self) -> AstNode {
+        AstNode::new("Return", self.location())
+            .with_child("value", self.value.as_ref().map(|value| value.ast()))
+    }
+
     fn execute(&self, ctx: &mut Context) -> Option<ObjectInstance> {
-        match &self.value {
-            Some(val) => val.execute(ctx),
+        let value = match &self.value {
+            Some(val) => Some(val.execute_expression(ctx)?),
             None => None,
-        }
+        };
+
+        // The interrupt is handled by the function being executed, which stops
+        // executing its block and returns the value
+        ctx.interrupt(Interrupt::Return(value));
+
+        None
     }
 
     fn location(&self) -> Option<&SpanTuple> {
@@ -142,4 +154,20 @@ mod tests {
 
         assert_eq!(res, Some(JkInt::from(42).to_instance()));
     }
+
+    #[test]
+    fn t_return_stops_function_execution() {
+        use crate::instance::ToObjectInstance;
+        use crate::value::JkInt;
+
+        let res = jk_execute! {
+            func early() -> int {
+                return 1;
+                2
+            }
+            early()
+        };
+
+        assert_eq!(res, Some(JkInt::from(1).to_instance()));
+    }
 }
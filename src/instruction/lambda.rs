@@ -0,0 +1,289 @@
+//! Lambdas are anonymous functions, declared inline as expressions. They are written
+//! like regular function declarations, without a name:
+//!
+//! ```ignore
+//! add_one = func(x: int) -> int { x + 1 };
+//! apply(func(x: int) -> int { x * 2 }, 4);
+//! ```
+//!
+//! A lambda evaluates to a function value of the corresponding functor type, here
+//! `func(int) -> int`.
+//!
+//! Lambdas are closures: They capture the variables of the enclosing scope which they
+//! reference, by value, when they are created. Modifying a variable after creating a
+//! closure does not change the value seen by the closure. Captured variables cannot be
+//! assigned to from within the closure, even if they are mutable in the enclosing
+//! scope.
+
+use std::rc::Rc;
+
+use crate::ast::AstNode;
+use crate::context::Context;
+use crate::generics::{GenericExpander, GenericMap, GenericUser};
+use crate::instance::{ObjectInstance, ToObjectInstance};
+use crate::instruction::{FunctionDec, InstrKind, Instruction};
+use crate::location::SpanTuple;
+use crate::typechecker::{CheckedType, TypeCheck, TypeCtx};
+use crate::value::JkFunc;
+
+/// Name given to the declarations of anonymous functions
+pub const LAMBDA_NAME: &str = "lambda";
+
+#[derive(Clone)]
+pub struct Lambda {
+    function: Rc<FunctionDec>,
+    /// Variables of the enclosing scopes used by the lambda, resolved when typechecking
+    captures: Vec<String>,
+    cached_type: Option<CheckedType>,
+    location: Option<SpanTuple>,
+}
+
+impl Lambda {
+    /// Create a new lambda from the declaration of an anonymous function
+    pub fn new(mut function: FunctionDec) -> Lambda {
+        function.set_name(String::from(LAMBDA_NAME));
+
+        Lambda {
+            function: Rc::new(function),
+            captures: vec![],
+            cached_type: None,
+            location: None,
+        }
+    }
+
+    /// Get a reference to the declaration of the anonymous function
+    pub fn function(&self) -> &FunctionDec {
+        &self.function
+    }
+
+    /// Get the names of the variables captured by the lambda
+    pub fn captures(&self) -> &[String] {
+        &self.captures
+    }
+
+    pub fn set_location(&mut self, location: SpanTuple) {
+        self.location = Some(location)
+    }
+}
+
+impl Instruction for Lambda {
+    fn kind(&self) -> InstrKind {
+        InstrKind::Expression(None)
+    }
+
+    fn print(&self) -> String {
+        // Remove the name from the printed declaration
+        let dec = self.function.print();
+
+        dec.replacen(&format!("func {}(", LAMBDA_NAME), "func(", 1)
+    }
+
+    fn ast(&self) -> AstNode {
+        AstNode::new("Lambda", self.location()).with_child("function", self.function.ast())
+    }
+
+    fn execute(&self, ctx: &mut Context) -> Option<ObjectInstance> {
+        match ctx.capture(&self.captures) {
+            Ok(captures) => {
+                Some(JkFunc::with_captures(self.function.clone(), captures).to_instance())
+            }
+            Err(e) => {
+                ctx.error(e.with_loc(self.location.clone()));
+                None
+            }
+        }
+    }
+
+    fn location(&self) -> Option<&SpanTuple> {
+        self.location.as_ref()
+    }
+}
+
+impl TypeCheck for Lambda {
+    fn resolve_type(&mut self, ctx: &mut TypeCtx) -> CheckedType {
+        ctx.capture_enter();
+        let function_ty = Rc::make_mut(&mut self.function).resolve_anonymous_type(ctx);
+        self.captures = ctx.capture_exit();
+
+        match function_ty {
+            CheckedType::Error => CheckedType::Error,
+            _ => CheckedType::Resolved(JkFunc::type_of(&self.function)),
+        }
+    }
+
+    fn set_cached_type(&mut self, ty: CheckedType) {
+        self.cached_type = Some(ty)
+    }
+
+    fn cached_type(&self) -> Option<&CheckedType> {
+        self.cached_type.as_ref()
+    }
+}
+
+impl GenericUser for Lambda {
+    fn resolve_usages(&mut self, type_map: &GenericMap, ctx: &mut TypeCtx) {
+        self.function = Rc::new(
+            self.function
+                .generate(String::from(LAMBDA_NAME), type_map, ctx),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::JkInt;
+    use crate::{jinko, jinko_fail};
+
+    #[test]
+    fn t_lambda_print() {
+        let mut ctx = Context::new();
+        let lambda = crate::parser::constructs::expr(crate::span!("func(x: int) -> int { x + 1 }"))
+            .unwrap()
+            .1;
+
+        assert_eq!(lambda.print(), "func(x: int) -> int {\n    x + 1\n}");
+
+        let mut lambda = lambda;
+        assert!(ctx.type_check(&mut *lambda).is_ok());
+    }
+
+    #[test]
+    fn t_lambda_call() {
+        let ctx = jinko! {
+            add_one = func(x: int) -> int { x + 1 };
+            res = add_one(41);
+        };
+
+        assert_eq!(
+            ctx.get_variable("res").unwrap().instance(),
+            JkInt::from(42).to_instance()
+        );
+    }
+
+    #[test]
+    fn t_lambda_as_argument() {
+        let ctx = jinko! {
+            func apply(f: func(int) -> int, x: int) -> int { f(x) }
+            res = apply(func(x: int) -> int { x * 2 }, 4);
+        };
+
+        assert_eq!(
+            ctx.get_variable("res").unwrap().instance(),
+            JkInt::from(8).to_instance()
+        );
+    }
+
+    #[test]
+    fn t_lambda_in_generic_function() {
+        let ctx = jinko! {
+            func twice[T](x: T) -> T {
+                f = func(y: T) -> T { y };
+                f(f(x))
+            }
+            res = twice[int](3);
+        };
+
+        assert_eq!(
+            ctx.get_variable("res").unwrap().instance(),
+            JkInt::from(3).to_instance()
+        );
+    }
+
+    #[test]
+    fn t_lambda_invalid_return_type() {
+        jinko_fail! {
+            f = func(x: int) -> string { x };
+        };
+    }
+
+    #[test]
+    fn t_closure_captures_local_variables() {
+        let ctx = jinko! {
+            func adder(n: int) -> func(int) -> int {
+                func(x: int) -> int { x + n }
+            }
+            add_two = adder(2);
+            res = add_two(40);
+        };
+
+        assert_eq!(
+            ctx.get_variable("res").unwrap().instance(),
+            JkInt::from(42).to_instance()
+        );
+    }
+
+    #[test]
+    fn t_closure_captures_by_value() {
+        let ctx = jinko! {
+            mut a = 1;
+            f = func() -> int { a };
+            a = 2;
+            res = f();
+        };
+
+        assert_eq!(
+            ctx.get_variable("res").unwrap().instance(),
+            JkInt::from(1).to_instance()
+        );
+    }
+
+    #[test]
+    fn t_nested_closures() {
+        let ctx = jinko! {
+            func outer(a: int) -> func(int) -> func(int) -> int {
+                func(b: int) -> func(int) -> int {
+                    func(c: int) -> int { a + b + c }
+                }
+            }
+            add_one = outer(1);
+            add_three = add_one(2);
+            res = add_three(3);
+        };
+
+        assert_eq!(
+            ctx.get_variable("res").unwrap().instance(),
+            JkInt::from(6).to_instance()
+        );
+    }
+
+    #[test]
+    fn t_closure_only_captures_used_variables() {
+        let mut ctx = Context::new();
+        let mut lambda =
+            crate::parser::constructs::expr(crate::span!("func(x: int) -> int { y = x + b; y }"))
+                .unwrap()
+                .1;
+
+        ctx.eval("a = 1; b = 2;").unwrap();
+        assert!(ctx.type_check(&mut *lambda).is_ok());
+
+        let lambda = lambda.downcast_ref::<Lambda>().unwrap();
+        assert_eq!(lambda.captures(), &[String::from("b")]);
+    }
+
+    #[test]
+    fn t_closure_cannot_assign_captured_mutable_variable() {
+        jinko_fail! {
+            mut a = 1;
+            f = func() { a = 2; };
+        };
+    }
+
+    #[test]
+    fn t_closure_can_shadow_captured_variable() {
+        let ctx = jinko! {
+            func shadow() -> int {
+                a = 1;
+                f = func() -> int { a = 2; a };
+                f() + a
+            }
+            res = shadow();
+        };
+
+        assert_eq!(
+            ctx.get_variable("res").unwrap().instance(),
+            JkInt::from(3).to_instance()
+        );
+    }
+}
@@ -1,12 +1,17 @@
 //! The Loop instruction is used for repeating instructions. They can be of three
 //! different kinds, `for`, `while` or `loop`.
 
-use crate::context::Context;
+use std::ops::ControlFlow;
+
+use crate::analysis::SymbolKind;
+use crate::ast::AstNode;
+use crate::context::{Context, Interrupt};
+use crate::error::{ErrKind, Error};
 use crate::generics::GenericUser;
 use crate::instance::{FromObjectInstance, ObjectInstance};
-use crate::instruction::{Block, FunctionCall, InstrKind, Instruction, Var};
+use crate::instruction::{Block, FunctionCall, FunctionDec, InstrKind, Instruction, Var};
 use crate::location::SpanTuple;
-use crate::typechecker::{CheckedType, TypeCheck, TypeCtx};
+use crate::typechecker::{CheckedType, TypeCheck, TypeCtx, TypeId};
 use crate::value::JkBool;
 
 /// What kind of loop the loop block represents: Either a for Loop, with a variable and
@@ -42,173 +47,381 @@ impl Loop {
     pub fn set_location(&mut self, location: SpanTuple) {
         self.location = Some(location)
     }
+
+    /// Execute the loop's block once. Returns `Break` if the loop should stop
+    /// iterating, for example if an error occured during the execution of the block
+    /// or if a `break` instruction was executed. The loop then evaluates to the value
+    /// contained in `Break`, if any.
+    fn run_block(&self, ctx: &mut Context) -> ControlFlow<Option<ObjectInstance>> {
+        // Iterations count against the limits of the context, as loops with an empty
+        // block would otherwise never be stopped
+        if !ctx.check_limits(self.location.as_ref()) {
+            return ControlFlow::Break(None);
+        }
+
+        self.block.execute(ctx);
+
+        if ctx.has_errors() {
+            return ControlFlow::Break(None);
+        }
+
+        match ctx.take_interrupt() {
+            Some(Interrupt::Break(value)) => ControlFlow::Break(value),
+            // Returning from the enclosing function or exiting the program stops the
+            // loop, but needs to keep being propagated
+            Some(interrupt @ (Interrupt::Return(_) | Interrupt::Exit(_))) => {
+                ctx.interrupt(interrupt);
+                ControlFlow::Break(None)
+            }
+            Some(Interrupt::Continue) | None => ControlFlow::Continue(()),
+        }
+    }
+
+    /// Evaluate the condition of a `while` loop
+    fn check_condition(cond: &dyn Instruction, ctx: &mut Context) -> Option<bool> {
+        let cond = cond.execute_expression(ctx)?;
+
+        Some(JkBool::from_instance(&cond).rust_value())
+    }
+
+    /// Execute a `for` loop, iterating on the value of `range_expression` using the
+    /// iteration protocol: `iter()`, `value()` and `next()`
+    fn iterate(
+        &self,
+        var: &Var,
+        range_expression: &dyn Instruction,
+        ctx: &mut Context,
+    ) -> Option<()> {
+        // Let's break down the implementation for the following loop
+        // ```
+        // for i in range(0, 10) { /* exec() */ }
+        // ```
+
+        // We create new variables in the scope, with the required iterator's
+        // name from the user. We can create these variables with special names
+        // since we are only using them from the interpreter. Here, we prefix
+        // them with a plus sign to make sure they do not interact with the user.
+        // Each call to a method of the iteration protocol is dispatched on the
+        // type of its receiver, so that user types can be iterated on.
+        // ```
+        // +inner = range(0, 10);
+        // +iterator = iter(+inner); // or `+inner` if it has no `iter` method
+        //
+        // +maybe_value = value(+iterator);
+        // if !+maybe_value.is_some() { break }
+        //
+        // i = +maybe_value.unpack(); // This is our <iter_value>
+        //
+        // // We can now do our first execution, and then repeat part of the
+        // // above process
+        // /* exec() */
+        //
+        // // FIXME: This needs to change. Ideally, we'd want to mutate
+        // the `+iterator` variable
+        // +iterator = next(+iterator);
+        // +maybe_value = value(+iterator);
+        // if !+maybe_value.is_some() { break }
+        //
+        // i = +maybe_value.unpack();
+        // /* exec() */
+        // ```
+
+        // Now, let's create our variables
+        let mut inner = Var::new(String::from("+inner"));
+        let mut iterator = Var::new(String::from("+iterator"));
+        let mut maybe = Var::new(String::from("+maybe_value"));
+        let mut iter_value = Var::new(var.name().to_owned());
+        if let Some(location) = var.location() {
+            iter_value.set_location(location.clone());
+        }
+
+        // We execute the iterable expression
+        // `+inner = range(0, 10)`
+        inner.set_instance(range_expression.execute_expression(ctx)?);
+        ctx.add_variable(inner.clone()).unwrap();
+
+        // We construct the iterator from the iterable expression
+        // `+iterator = iter(+inner)`. A type without an `iter` method is its
+        // own iterator
+        let has_iter = match inner.instance().ty() {
+            CheckedType::Resolved(ty) => {
+                Loop::protocol_function(ty, "iter", |f| ctx.get_function(f).map(|f| &**f)).is_some()
+            }
+            _ => false,
+        };
+        let instance = match has_iter {
+            true => Loop::protocol_call("iter", &inner, ctx)?,
+            false => inner.instance(),
+        };
+        iterator.set_instance(instance);
+
+        // Now we can declare our variables in the context
+        ctx.add_variable(iterator.clone()).unwrap();
+        ctx.add_variable(maybe.clone()).unwrap();
+        ctx.add_variable(iter_value.clone()).unwrap();
+
+        loop {
+            // We fetch the current value from the iterator: `value(+iterator)`
+            maybe.set_instance(Loop::protocol_call("value", &iterator, ctx)?);
+            ctx.replace_variable(maybe.clone()).unwrap();
+
+            // We check if `+maybe_value` contains `Some` or `Nothing`
+            let is_some = Loop::protocol_call("is_some", &maybe, ctx)?;
+            if !JkBool::from_instance(&is_some).rust_value() {
+                break;
+            }
+
+            iter_value.set_instance(Loop::protocol_call("unpack", &maybe, ctx)?);
+            ctx.replace_variable(iter_value.clone()).unwrap();
+
+            if self.run_block(ctx).is_break() {
+                break;
+            }
+
+            // We advance the iterator: `+iterator = next(+iterator)`
+            iterator.set_instance(Loop::protocol_call("next", &iterator, ctx)?);
+            ctx.replace_variable(iterator.clone()).unwrap();
+        }
+
+        Some(())
+    }
+
+    /// Get the name of the function implementing a method of the iteration protocol
+    /// for a given type. The function is either a method declared on that type, or a
+    /// regular function whose first argument is of that type
+    fn protocol_function<'f>(
+        ty: &TypeId,
+        name: &str,
+        mut get_function: impl FnMut(&str) -> Option<&'f FunctionDec>,
+    ) -> Option<String> {
+        let method = FunctionDec::method_name(ty, name);
+        if get_function(&method).is_some() {
+            return Some(method);
+        }
+
+        get_function(name)
+            .and_then(|f| f.args().first())
+            .filter(|arg| arg.get_type() == ty)
+            .map(|_| name.to_owned())
+    }
+
+    /// Call a method of the iteration protocol on a receiver. The function to call is
+    /// dispatched on the type of the receiver's instance
+    fn protocol_call(name: &str, receiver: &Var, ctx: &mut Context) -> Option<ObjectInstance> {
+        let fn_name = match receiver.instance().ty() {
+            CheckedType::Resolved(ty) => {
+                Loop::protocol_function(ty, name, |f| ctx.get_function(f).map(|f| &**f))
+            }
+            _ => None,
+        };
+
+        let call = FunctionCall::new(
+            fn_name.unwrap_or_else(|| name.to_owned()),
+            vec![],
+            vec![Box::new(receiver.clone())],
+        );
+
+        call.execute(ctx)
+    }
+
+    /// Build the expression used to fetch the value of each iteration of a `for`
+    /// loop: `unpack(value(iter(<range_expression>)))`, or
+    /// `unpack(value(<range_expression>))` if the expression is already an iterator.
+    /// This is only used to figure out the type of the iteration variable
+    fn iteration_value(range_expression: &dyn Instruction, is_iterator: bool) -> FunctionCall {
+        let iterator = Loop::iterator(range_expression, is_iterator);
+        let maybe = FunctionCall::new(String::from("value"), vec![], vec![iterator]);
+
+        FunctionCall::new(String::from("unpack"), vec![], vec![Box::new(maybe)])
+    }
+
+    /// Build the expression advancing the iterator created from an iterable
+    /// expression: `next(iter(range_expression))`
+    fn iteration_next(range_expression: &dyn Instruction, is_iterator: bool) -> FunctionCall {
+        let iterator = Loop::iterator(range_expression, is_iterator);
+
+        FunctionCall::new(String::from("next"), vec![], vec![iterator])
+    }
+
+    /// Build the expression creating an iterator from an iterable expression
+    fn iterator(range_expression: &dyn Instruction, is_iterator: bool) -> Box<dyn Instruction> {
+        match is_iterator {
+            true => range_expression.box_clone(),
+            false => Box::new(FunctionCall::new(
+                String::from("iter"),
+                vec![],
+                vec![range_expression.box_clone()],
+            )),
+        }
+    }
 }
 
 impl Instruction for Loop {
     fn kind(&self) -> InstrKind {
-        self.block.kind()
+        InstrKind::Statement
     }
 
     fn print(&self) -> String {
         match &self.kind {
             LoopKind::For(var, range) => format!(
-                "for {} in {} {}\n",
+                "for {} in {} {}",
                 var.name(),
                 range.print(),
                 self.block.print()
             ),
             LoopKind::While(condition) => {
-                format!("while {} {}\n", condition.print(), self.block.print())
+                format!("while {} {}", condition.print(), self.block.print())
             }
-            LoopKind::Loop => format!("loop {}\n", self.block.print()),
+            LoopKind::Loop => format!("loop {}", self.block.print()),
         }
     }
 
+    fn ast(&self) -> AstNode {
+        let node = AstNode::new("Loop", self.location());
+        let node = match &self.kind {
+            LoopKind::For(var, iterator) => node
+                .with_attr("kind", "for")
+                .with_child("variable", var.ast())
+                .with_child("iterator", iterator.ast()),
+            LoopKind::While(condition) => node
+                .with_attr("kind", "while")
+                .with_child("condition", condition.ast()),
+            LoopKind::Loop => node.with_attr("kind", "loop"),
+        };
+
+        node.with_child("block", self.block.ast())
+    }
+
     fn execute(&self, ctx: &mut Context) -> Option<ObjectInstance> {
         match &self.kind {
             LoopKind::Loop => loop {
-                self.block.execute(ctx)?;
+                if let ControlFlow::Break(value) = self.run_block(ctx) {
+                    return value;
+                }
             },
             LoopKind::While(cond) => {
-                let cond = cond.execute(ctx)?;
-                while JkBool::from_instance(&cond).rust_value() {
-                    self.block.execute(ctx)?;
+                while Loop::check_condition(&**cond, ctx)? {
+                    if self.run_block(ctx).is_break() {
+                        break;
+                    }
                 }
             }
             LoopKind::For(var, range_expression) => {
-                // Let's break down the implementation for the following loop
-                // ```
-                // for i in range(0, 10) { /* exec() */ }
-                // ```
-
                 // We enter a scope to declare our internal variables
                 ctx.scope_enter();
+                self.iterate(var, &**range_expression, ctx);
+                ctx.scope_exit();
+            }
+        }
 
-                // We create new variables in the scope, with the required iterator's
-                // name from the user. We can create these variables with special names
-                // since we are only using them from the interpreter. Here, we prefix
-                // them with a plus sign to make sure they do not interact with the user.
-                // ```
-                // +inner = range(0, 10);
-                // +iterator = iter(+inner);
-                //
-                // +maybe_value = value(+iterator);
-                // if !+maybe_value.is_some() { break }
-                //
-                // i = +maybe_iter_value.unpack(); // This is our <iter_value>
-                //
-                // // We can now do our first execution, and then repeat part of the
-                // // above process
-                // /* exec() */
-                //
-                // // FIXME: This needs to change. Ideally, we'd want to mutate
-                // the `+iterator` variable
-                // +iterator = next(+iterator);
-                // +maybe_value = current(+iterator);
-                // if !+maybe_value.is_some() { break }
-                //
-                // i = +maybe_iter_value.unpack();
-                // /* exec() */
-                // ```
-
-                // Let's create our names
-                let inner_name = String::from("+inner");
-                let iterator_name = String::from("+iterator");
-                let maybe_name = String::from("+maybe_value");
-
-                // Now, let's create our variables
-                let mut inner = Var::new(inner_name);
-                let mut iterator = Var::new(iterator_name);
-                let mut maybe = Var::new(maybe_name);
-                let mut iter_value = Var::new(var.name().to_owned());
-
-                // We execute the iterable expression
-                // `+inner = range(0, 10)`
-                inner.set_instance(range_expression.execute(ctx).unwrap());
-                ctx.add_variable(inner.clone()).unwrap();
-
-                // We construct the iterator from the iterable expression
-                // `+iterator = iter(+inner)`
-                let mut iter_constructor = FunctionCall::new(String::from("iter"), vec![], vec![]);
-                iter_constructor.add_arg(Box::new(inner.clone()));
-                iterator.set_instance(iter_constructor.execute(ctx).unwrap());
-
-                // We fetch the first value from the iterator: `value(+iterator)`
-                // This call will be reused multiple times!
-                let mut iterator_value = FunctionCall::new(String::from("value"), vec![], vec![]);
-                iterator_value.add_arg(Box::new(iterator.clone()));
-
-                // We check if `+maybe_value` contains `Some` or `Nothing`: `is_some(+maybe_value)`
-                // This call will be reused multiple times!
-                let mut maybe_is_some = FunctionCall::new(String::from("is_some"), vec![], vec![]);
-                maybe_is_some.add_arg(Box::new(maybe.clone()));
-
-                // We advance the iterator: `next(+iterator)`
-                // This call will be reused multiple times!
-                let mut iterator_next = FunctionCall::new(String::from("next"), vec![], vec![]);
-                iterator_next.add_arg(Box::new(iterator.clone()));
-
-                let mut maybe_unpack = FunctionCall::new(String::from("unpack"), vec![], vec![]);
-                maybe_unpack.add_arg(Box::new(maybe.clone()));
-
-                // Now we can declare our variables in the context
-                ctx.add_variable(iterator.clone()).unwrap();
-                ctx.add_variable(maybe.clone()).unwrap();
-                ctx.add_variable(iter_value.clone()).unwrap();
-
-                maybe.set_instance(iterator_value.execute(ctx).unwrap());
-                ctx.replace_variable(maybe.clone()).unwrap();
-
-                let maybe_is_nothing = {
-                    let instance = maybe_is_some.execute(ctx).unwrap();
-                    !JkBool::from_instance(&instance).0
-                };
+        // Only `loop` blocks can produce a value, via `break`
+        None
+    }
 
-                if maybe_is_nothing {
-                    ctx.scope_exit();
-                    return None;
+    fn location(&self) -> Option<&SpanTuple> {
+        self.location.as_ref()
+    }
+}
+
+impl TypeCheck for Loop {
+    fn resolve_type(&mut self, ctx: &mut TypeCtx) -> CheckedType {
+        ctx.loop_enter();
+
+        match &mut self.kind {
+            LoopKind::Loop => {
+                self.block.type_of(ctx);
+            }
+            LoopKind::While(cond) => {
+                let bool_checkedtype = CheckedType::Resolved(TypeId::from("bool"));
+                let cond_ty = cond.type_of(ctx);
+
+                if cond_ty != bool_checkedtype {
+                    ctx.error(
+                        Error::new(ErrKind::TypeChecker)
+                            .with_msg(format!(
+                                "while condition should be a boolean, not a `{}`",
+                                cond_ty
+                            ))
+                            .with_loc(cond.location().cloned()),
+                    );
                 }
 
-                iter_value.set_instance(maybe_unpack.execute(ctx).unwrap());
-                ctx.replace_variable(iter_value.clone()).unwrap();
+                self.block.type_of(ctx);
+            }
+            LoopKind::For(var, range_expression) => {
+                let range_ty = match range_expression.type_of(ctx) {
+                    CheckedType::Resolved(ty) => ty,
+                    _ => {
+                        ctx.loop_exit();
+                        return CheckedType::Error;
+                    }
+                };
 
-                loop {
-                    self.block.execute(ctx);
+                // A type without an `iter` method is its own iterator, and needs to
+                // implement the rest of the iteration protocol
+                let get_function = |f: &str| ctx.get_function(f);
+                let is_iterator =
+                    Loop::protocol_function(&range_ty, "iter", get_function).is_none();
+                if is_iterator
+                    && Loop::protocol_function(&range_ty, "value", get_function).is_none()
+                {
+                    ctx.error(
+                        Error::new(ErrKind::TypeChecker)
+                            .with_msg(format!(
+                                "cannot iterate on a value of type `{}`: it implements neither `iter` nor `value`",
+                                range_ty
+                            ))
+                            .with_loc(range_expression.location().cloned()),
+                    );
+                    ctx.loop_exit();
+                    return CheckedType::Error;
+                }
 
-                    iterator.set_instance(iterator_next.execute(ctx).unwrap());
-                    ctx.replace_variable(iterator.clone()).unwrap();
-                    maybe.set_instance(iterator_value.execute(ctx).unwrap());
-                    ctx.replace_variable(maybe.clone()).unwrap();
+                let value_ty = Loop::iteration_value(&**range_expression, is_iterator).type_of(ctx);
+                Loop::iteration_next(&**range_expression, is_iterator).type_of(ctx);
 
-                    let maybe_is_nothing = {
-                        let instance = maybe_is_some.execute(ctx).unwrap();
-                        !JkBool::from_instance(&instance).0
-                    };
+                ctx.scope_enter();
 
-                    if maybe_is_nothing {
-                        break;
-                    }
+                let detail = format!("{}: {}", var.name(), value_ty.print());
+                ctx.define(var.name(), SymbolKind::Variable, var.location(), detail);
 
-                    iter_value.set_instance(maybe_unpack.execute(ctx).unwrap());
-                    ctx.replace_variable(iter_value.clone()).unwrap();
+                if let Err(e) = ctx.declare_var(var.name().to_owned(), value_ty) {
+                    ctx.error(e);
                 }
+                self.block.type_of(ctx);
 
                 ctx.scope_exit();
             }
         }
 
-        // FIXME: Add logic. Right now they only return on error, not the actual value
-        None
-    }
-
-    fn location(&self) -> Option<&SpanTuple> {
-        self.location.as_ref()
-    }
-}
+        let break_types = ctx.loop_exit();
+        let break_ty = break_types.first().cloned().unwrap_or(CheckedType::Void);
+
+        if break_types.iter().any(|ty| ty != &break_ty) {
+            ctx.error(
+                Error::new(ErrKind::TypeChecker)
+                    .with_msg(String::from(
+                        "loop is broken out of with values of different types",
+                    ))
+                    .with_loc(self.location.clone()),
+            );
+            return CheckedType::Error;
+        }
 
-impl TypeCheck for Loop {
-    fn resolve_type(&mut self, ctx: &mut TypeCtx) -> CheckedType {
-        self.block.type_of(ctx)
+        match (&self.kind, &break_ty) {
+            (LoopKind::Loop, _) | (_, CheckedType::Void) => break_ty,
+            _ => {
+                ctx.error(
+                    Error::new(ErrKind::TypeChecker)
+                        .with_msg(format!(
+                            "only `loop` blocks can be broken out of with a value, not `{}`",
+                            break_ty
+                        ))
+                        .with_loc(self.location.clone()),
+                );
+                CheckedType::Error
+            }
+        }
     }
 
     fn set_cached_type(&mut self, ty: CheckedType) {
@@ -227,15 +440,15 @@ mod tests {
     use super::*;
     use crate::instance::ToObjectInstance;
     use crate::instruction::FunctionCall;
-    use crate::jinko;
     use crate::value::JkInt;
+    use crate::{jinko, jinko_fail};
 
     #[test]
     fn pretty_print_loop() {
         let b = Block::new();
         let l = Loop::new(LoopKind::Loop, b);
 
-        assert_eq!(l.print().as_str(), "loop {\n}\n")
+        assert_eq!(l.print().as_str(), "loop {}")
     }
 
     #[test]
@@ -244,7 +457,7 @@ mod tests {
         let b = Block::new();
         let l = Loop::new(LoopKind::For(Box::new(Var::new("i".to_owned())), r), b);
 
-        assert_eq!(l.print().as_str(), "for i in iter() {\n}\n")
+        assert_eq!(l.print().as_str(), "for i in iter() {}")
     }
 
     #[test]
@@ -253,23 +466,61 @@ mod tests {
         let b = Block::new();
         let l = Loop::new(LoopKind::While(r), b);
 
-        assert_eq!(l.print().as_str(), "while {\n} {\n}\n")
+        assert_eq!(l.print().as_str(), "while {} {}")
     }
 
     #[test]
-    #[ignore]
     fn tc_valid_loop_blocks() {
         jinko! {
-            // FIXME: Don't ignore once for loop behavior is implemented
-            // l0 = for value in range { value }
+            for value in range(0, 4) { value }
 
             mut i = 0;
             while i < 15 { i = i + 1 }
+        };
+    }
 
-            l2 = loop { i = i + 1; i }
+    #[test]
+    fn tc_invalid_while_condition() {
+        jinko_fail! {
+            while 15 { }
         };
     }
 
+    #[test]
+    fn valid_while_block() {
+        let ctx = jinko! {
+            mut i = 0;
+            while i < 15 { i = i + 1; }
+        };
+
+        let i = ctx.get_variable("i").unwrap();
+        assert_eq!(i.instance(), JkInt::from(15).to_instance());
+    }
+
+    #[test]
+    fn valid_while_block_without_execution() {
+        let ctx = jinko! {
+            mut i = 0;
+            while i > 0 { i = i + 1; }
+        };
+
+        let i = ctx.get_variable("i").unwrap();
+        assert_eq!(i.instance(), JkInt::from(0).to_instance());
+    }
+
+    #[test]
+    fn valid_for_block_uses_value() {
+        let ctx = jinko! {
+            mut sum = 0;
+            for i in range(0, 5) {
+                sum = sum + i;
+            }
+        };
+
+        let sum = ctx.get_variable("sum").unwrap();
+        assert_eq!(sum.instance(), JkInt::from(10).to_instance());
+    }
+
     #[test]
     fn valid_for_block() {
         let ctx = jinko! {
@@ -290,4 +541,59 @@ mod tests {
             }
         };
     }
+
+    #[test]
+    fn valid_for_block_custom_iterator() {
+        let ctx = jinko! {
+            type Countdown(from: int);
+
+            func value(c: Countdown) -> Maybe_int {
+                if c.from > 0 { some(c.from) } else { nothing() }
+            }
+            func next(c: Countdown) -> Countdown { Countdown(from: c.from - 1) }
+
+            mut sum = 0;
+            for i in Countdown(from: 4) {
+                sum = sum + i;
+            }
+        };
+
+        let sum = ctx.get_variable("sum").unwrap();
+        assert_eq!(sum.instance(), JkInt::from(10).to_instance());
+    }
+
+    #[test]
+    fn valid_for_block_custom_iterable() {
+        let ctx = jinko! {
+            type Couple(a: int, b: int);
+            type CoupleIter(pair: Couple, index: int);
+
+            func iter(p: Couple) -> CoupleIter { CoupleIter(pair: p, index: 0) }
+            func value(it: CoupleIter) -> Maybe_int {
+                if it.index == 0 {
+                    some(it.pair.a)
+                } else {
+                    if it.index == 1 { some(it.pair.b) } else { nothing() }
+                }
+            }
+            func next(it: CoupleIter) -> CoupleIter { CoupleIter(pair: it.pair, index: it.index + 1) }
+
+            mut product = 1;
+            for i in Couple(a: 3, b: 5) {
+                product = product * i;
+            }
+        };
+
+        let product = ctx.get_variable("product").unwrap();
+        assert_eq!(product.instance(), JkInt::from(15).to_instance());
+    }
+
+    #[test]
+    fn tc_invalid_for_not_iterable() {
+        jinko_fail! {
+            type Foo(a: int);
+
+            for i in Foo(a: 1) { }
+        };
+    }
 }
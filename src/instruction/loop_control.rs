@@ -0,0 +1,323 @@
+//! Loop control instructions are used to alter the regular flow of a loop. `break`
+//! exits the innermost loop, while `continue` skips to its next iteration.
+//!
+//! ```ignore
+//! for i in range(0, 10) {
+//!     if i == 2 { continue };
+//!     if i == 5 { break };
+//! }
+//! ```
+//!
+//! `break` can also be given a value, which then becomes the value of a `loop` block
+//!
+//! ```ignore
+//! mut i = 0;
+//! twelve = loop {
+//!     i = i + 1;
+//!     if i == 12 { break i };
+//! };
+//! ```
+
+use crate::ast::AstNode;
+use crate::context::{Context, Interrupt};
+use crate::error::{ErrKind, Error};
+use crate::generics::{GenericMap, GenericUser};
+use crate::instance::ObjectInstance;
+use crate::instruction::{InstrKind, Instruction};
+use crate::location::SpanTuple;
+use crate::typechecker::{CheckedType, TypeCheck, TypeCtx};
+
+/// Emit an error if a loop control instruction is used outside of a loop
+fn check_in_loop(ctx: &mut TypeCtx, keyword: &str, location: Option<SpanTuple>) -> bool {
+    if !ctx.in_loop() {
+        ctx.error(
+            Error::new(ErrKind::TypeChecker)
+                .with_msg(format!("`{}` used outside of a loop", keyword))
+                .with_loc(location),
+        );
+        return false;
+    }
+
+    true
+}
+
+#[derive(Clone)]
+pub struct Break {
+    value: Option<Box<dyn Instruction>>,
+    cached_type: Option<CheckedType>,
+    location: Option<SpanTuple>,
+}
+
+impl Break {
+    /// Create a new Break instruction, with an optional value to exit the loop with
+    pub fn new(value: Option<Box<dyn Instruction>>) -> Break {
+        Break {
+            value,
+            cached_type: None,
+            location: None,
+        }
+    }
+
+    /// Get a reference to the value given to the Break instruction, if any
+    pub fn value(&self) -> Option<&dyn Instruction> {
+        self.value.as_deref()
+    }
+
+    pub fn set_location(&mut self, location: SpanTuple) {
+        self.location = Some(location)
+    }
+}
+
+impl Instruction for Break {
+    fn kind(&self) -> InstrKind {
+        InstrKind::Statement
+    }
+
+    fn print(&self) -> String {
+        match &self.value {
+            Some(val) => format!("break {}", val.print()),
+            None => String::from("break"),
+        }
+    }
+
+    fn ast(&self) -> AstNode {
+        AstNode::new("Break", self.location())
+            .with_child("value", self.value.as_ref().map(|value| value.ast()))
+    }
+
+    fn execute(&self, ctx: &mut Context) -> Option<ObjectInstance> {
+        let value = match &self.value {
+            Some(val) => Some(val.execute_expression(ctx)?),
+            None => None,
+        };
+
+        ctx.interrupt(Interrupt::Break(value));
+
+        None
+    }
+
+    fn location(&self) -> Option<&SpanTuple> {
+        self.location.as_ref()
+    }
+}
+
+impl TypeCheck for Break {
+    fn resolve_type(&mut self, ctx: &mut TypeCtx) -> CheckedType {
+        let value_ty = match &mut self.value {
+            Some(val) => val.type_of(ctx),
+            None => CheckedType::Void,
+        };
+
+        if check_in_loop(ctx, "break", self.location.clone()) {
+            ctx.declare_break(value_ty);
+        }
+
+        CheckedType::Void
+    }
+
+    fn set_cached_type(&mut self, ty: CheckedType) {
+        self.cached_type = Some(ty)
+    }
+
+    fn cached_type(&self) -> Option<&CheckedType> {
+        self.cached_type.as_ref()
+    }
+}
+
+impl GenericUser for Break {
+    fn resolve_usages(&mut self, type_map: &GenericMap, ctx: &mut TypeCtx) {
+        if let Some(val) = &mut self.value {
+            val.resolve_usages(type_map, ctx)
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Continue {
+    cached_type: Option<CheckedType>,
+    location: Option<SpanTuple>,
+}
+
+impl Continue {
+    /// Create a new Continue instruction
+    pub fn new() -> Continue {
+        Continue::default()
+    }
+
+    pub fn set_location(&mut self, location: SpanTuple) {
+        self.location = Some(location)
+    }
+}
+
+impl Instruction for Continue {
+    fn kind(&self) -> InstrKind {
+        InstrKind::Statement
+    }
+
+    fn print(&self) -> String {
+        String::from("continue")
+    }
+
+    fn ast(&self) -> AstNode {
+        AstNode::new("Continue", self.location())
+    }
+
+    fn execute(&self, ctx: &mut Context) -> Option<ObjectInstance> {
+        ctx.interrupt(Interrupt::Continue);
+
+        None
+    }
+
+    fn location(&self) -> Option<&SpanTuple> {
+        self.location.as_ref()
+    }
+}
+
+impl TypeCheck for Continue {
+    fn resolve_type(&mut self, ctx: &mut TypeCtx) -> CheckedType {
+        check_in_loop(ctx, "continue", self.location.clone());
+
+        CheckedType::Void
+    }
+
+    fn set_cached_type(&mut self, ty: CheckedType) {
+        self.cached_type = Some(ty)
+    }
+
+    fn cached_type(&self) -> Option<&CheckedType> {
+        self.cached_type.as_ref()
+    }
+}
+
+impl GenericUser for Continue {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instance::ToObjectInstance;
+    use crate::value::JkInt;
+    use crate::{jinko, jinko_fail, jk_execute};
+
+    #[test]
+    fn t_print() {
+        let b = Break::new(Some(Box::new(JkInt::from(4))));
+
+        assert_eq!(b.print(), "break 4");
+        assert_eq!(Break::new(None).print(), "break");
+        assert_eq!(Continue::new().print(), "continue");
+    }
+
+    #[test]
+    fn t_break_while() {
+        let ctx = jinko! {
+            mut i = 0;
+            while true {
+                i = i + 1;
+                if i == 7 { break };
+            }
+        };
+
+        let i = ctx.get_variable("i").unwrap();
+        assert_eq!(i.instance(), JkInt::from(7).to_instance());
+    }
+
+    #[test]
+    fn t_break_for() {
+        let ctx = jinko! {
+            mut last = 0;
+            for i in range(0, 15) {
+                if i == 4 { break };
+                last = i;
+            }
+        };
+
+        let last = ctx.get_variable("last").unwrap();
+        assert_eq!(last.instance(), JkInt::from(3).to_instance());
+    }
+
+    #[test]
+    fn t_continue_for() {
+        let ctx = jinko! {
+            mut sum = 0;
+            for i in range(0, 6) {
+                if i == 2 { continue };
+                sum = sum + i;
+            }
+        };
+
+        let sum = ctx.get_variable("sum").unwrap();
+        assert_eq!(sum.instance(), JkInt::from(13).to_instance());
+    }
+
+    #[test]
+    fn t_break_inner_loop_only() {
+        let ctx = jinko! {
+            mut count = 0;
+            for i in range(0, 3) {
+                loop {
+                    count = count + 1;
+                    break;
+                }
+            }
+        };
+
+        let count = ctx.get_variable("count").unwrap();
+        assert_eq!(count.instance(), JkInt::from(3).to_instance());
+    }
+
+    #[test]
+    fn t_loop_break_value() {
+        assert_eq!(
+            jk_execute! {
+                mut i = 0;
+                loop {
+                    i = i + 1;
+                    if i == 12 { break i * 2 };
+                }
+            },
+            Some(JkInt::from(24).to_instance())
+        );
+    }
+
+    #[test]
+    fn tc_valid_loop_break_value() {
+        jinko! {
+            mut i = 0;
+            twelve = loop {
+                i = i + 1;
+                if i == 12 { break i };
+            };
+        };
+    }
+
+    #[test]
+    fn tc_break_outside_loop() {
+        jinko_fail! {
+            break;
+        };
+    }
+
+    #[test]
+    fn tc_continue_outside_loop() {
+        jinko_fail! {
+            func f() { continue }
+        };
+    }
+
+    #[test]
+    fn tc_loop_mismatched_break_values() {
+        jinko_fail! {
+            loop {
+                if true { break 1 };
+                break 'c';
+            };
+        };
+    }
+
+    #[test]
+    fn tc_while_break_value() {
+        jinko_fail! {
+            while true { break 1 };
+        };
+    }
+}
@@ -0,0 +1,729 @@
+//! The Match block is used to execute different blocks depending on the shape of a
+//! value. Each arm of the match contains a pattern and a block, which is executed if
+//! the value matches the pattern. Only the first matching arm gets executed.
+//!
+//! ```ignore
+//! match value {
+//!     0 -> { zero() },
+//!     Point(x: px, y) -> { point(px, y) },
+//!     _ -> { anything_else() },
+//! }
+//! ```
+//!
+//! Patterns can be constants, custom types whose fields get bound to new variables,
+//! or the wildcard `_`, which matches any value. Values of an enum are matched against
+//! its variants, which are destructured like custom types:
+//!
+//! ```ignore
+//! type Shape = Circle(r: float) | Square(side: float);
+//!
+//! match shape {
+//!     Circle(r) -> { 3.14 * r * r },
+//!     Square(side) -> { side * side },
+//! }
+//! ```
+
+use crate::ast::{AstNode, Json};
+use crate::context::Context;
+use crate::error::{ErrKind, Error};
+use crate::generics::{GenericMap, GenericUser};
+use crate::instance::ObjectInstance;
+use crate::instruction::{Block, InstrKind, Instruction, Var};
+use crate::location::SpanTuple;
+use crate::typechecker::{CheckedType, TypeCheck, TypeCtx, TypeId};
+use crate::value::JkBool;
+
+/// The different patterns a value can be matched against
+#[derive(Clone)]
+pub enum Pattern {
+    /// Matches values equal to a constant: `12`, `"jinko"` or `true`
+    Constant(Box<dyn Instruction>),
+    /// Matches instances of a custom type or of a variant of an enum, binding some of
+    /// their fields to new variables: `Point(x: px, y)` binds the field `x` to `px` and
+    /// the field `y` to `y`
+    Type(TypeId, Vec<(String, String)>),
+    /// Matches any value: `_`
+    Wildcard,
+}
+
+impl Pattern {
+    fn print(&self) -> String {
+        match self {
+            Pattern::Constant(constant) => constant.print(),
+            Pattern::Type(ty, bindings) if bindings.is_empty() => ty.print(),
+            Pattern::Type(ty, bindings) => {
+                let bindings: Vec<String> = bindings
+                    .iter()
+                    .map(|(field, binding)| match field == binding {
+                        true => field.clone(),
+                        false => format!("{}: {}", field, binding),
+                    })
+                    .collect();
+
+                format!("{}({})", ty.print(), bindings.join(", "))
+            }
+            Pattern::Wildcard => String::from("_"),
+        }
+    }
+
+    fn ast(&self) -> AstNode {
+        match self {
+            Pattern::Constant(constant) => AstNode::new("ConstantPattern", constant.location())
+                .with_child("value", constant.ast()),
+            Pattern::Type(ty, bindings) => AstNode::new("TypePattern", None)
+                .with_attr("type", ty)
+                .with_attr(
+                    "bindings",
+                    bindings
+                        .iter()
+                        .map(|(field, binding)| {
+                            Json::object(vec![
+                                ("field", field.as_str().into()),
+                                ("binding", binding.as_str().into()),
+                            ])
+                        })
+                        .collect::<Vec<_>>(),
+                ),
+            Pattern::Wildcard => AstNode::new("WildcardPattern", None),
+        }
+    }
+
+    /// Does the pattern match any value of the type it was checked against
+    fn is_catch_all(&self, ctx: &mut TypeCtx) -> bool {
+        match self {
+            Pattern::Constant(_) => false,
+            Pattern::Type(..) => self.variant(ctx).is_none(),
+            Pattern::Wildcard => true,
+        }
+    }
+
+    /// Get the enum and the variant matched by the pattern, if it matches a variant
+    fn variant(&self, ctx: &mut TypeCtx) -> Option<(String, String)> {
+        match self {
+            Pattern::Type(ty, _) => ctx.get_custom_type(ty.id()).and_then(|dec| {
+                dec.variant_of()
+                    .map(|enum_name| (enum_name.to_string(), dec.name().to_string()))
+            }),
+            _ => None,
+        }
+    }
+
+    /// Check if a value matches the pattern, and get the variables bound by the
+    /// pattern if it does
+    fn matches(&self, value: &ObjectInstance, ctx: &mut Context) -> Option<Vec<Var>> {
+        match self {
+            Pattern::Wildcard => Some(vec![]),
+            Pattern::Constant(constant) => {
+                let constant = constant.execute_expression(ctx)?;
+
+                (&constant == value).then(Vec::new)
+            }
+            Pattern::Type(ty, bindings) => {
+                if value.ty() != &CheckedType::Resolved(ty.clone())
+                    && value.variant() != Some(ty.id())
+                {
+                    return None;
+                }
+
+                bindings
+                    .iter()
+                    .map(|(field, binding)| {
+                        // The typechecker made sure that the fields exist
+                        let mut var = Var::new(binding.clone());
+                        var.set_instance(value.get_field(field).unwrap());
+                        Some(var)
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Get the type of the values the pattern can match, and declare its bindings
+    /// in the current scope
+    fn resolve_type(&mut self, ctx: &mut TypeCtx, loc: Option<&SpanTuple>) -> CheckedType {
+        match self {
+            Pattern::Wildcard => CheckedType::Later,
+            Pattern::Constant(constant) => constant.type_of(ctx),
+            Pattern::Type(ty, bindings) => {
+                let dec = match ctx.get_custom_type(ty.id()) {
+                    Some(dec) => dec.clone(),
+                    None => {
+                        ctx.error(
+                            Error::new(ErrKind::TypeChecker)
+                                .with_msg(format!("use of undeclared type `{}` in pattern", ty))
+                                .with_loc(loc.cloned()),
+                        );
+                        return CheckedType::Error;
+                    }
+                };
+
+                let mut pattern_ty = CheckedType::Resolved(dec.instance_type());
+                for (field, binding) in bindings.iter() {
+                    // Invalid bindings are still declared to avoid reporting errors
+                    // about undeclared variables in the arm's block
+                    let field_ty = match dec.fields().iter().find(|f| f.name() == field) {
+                        Some(field_dec) => CheckedType::Resolved(field_dec.get_type().clone()),
+                        None => {
+                            ctx.error(
+                                Error::new(ErrKind::TypeChecker)
+                                    .with_msg(format!(
+                                        "type `{}` has no field named `{}`",
+                                        ty, field
+                                    ))
+                                    .with_loc(loc.cloned()),
+                            );
+                            pattern_ty = CheckedType::Error;
+                            CheckedType::Error
+                        }
+                    };
+
+                    if let Err(e) = ctx.declare_var(binding.clone(), field_ty) {
+                        ctx.error(e);
+                    }
+                }
+
+                pattern_ty
+            }
+        }
+    }
+}
+
+/// An arm of a match block, containing the pattern to check and the block to execute
+#[derive(Clone)]
+pub struct MatchArm {
+    pattern: Pattern,
+    block: Block,
+    location: Option<SpanTuple>,
+}
+
+impl MatchArm {
+    /// Create a new arm from a pattern and the block to execute on a match
+    pub fn new(pattern: Pattern, block: Block) -> MatchArm {
+        MatchArm {
+            pattern,
+            block,
+            location: None,
+        }
+    }
+
+    /// Get a reference on the pattern of the arm
+    pub fn pattern(&self) -> &Pattern {
+        &self.pattern
+    }
+
+    pub fn set_location(&mut self, location: SpanTuple) {
+        self.location = Some(location)
+    }
+
+    fn print(&self) -> String {
+        format!("{} -> {}", self.pattern.print(), self.block.print())
+    }
+}
+
+/// The Match block struct. Contains the value to match and the arms to check, in order
+#[derive(Clone)]
+pub struct Match {
+    value: Box<dyn Instruction>,
+    arms: Vec<MatchArm>,
+    cached_type: Option<CheckedType>,
+    location: Option<SpanTuple>,
+}
+
+impl Match {
+    pub fn new(value: Box<dyn Instruction>, arms: Vec<MatchArm>) -> Match {
+        Match {
+            value,
+            arms,
+            cached_type: None,
+            location: None,
+        }
+    }
+
+    /// Get a reference on the arms of the match block
+    pub fn arms(&self) -> &Vec<MatchArm> {
+        &self.arms
+    }
+
+    pub fn set_location(&mut self, location: SpanTuple) {
+        self.location = Some(location)
+    }
+
+    /// Warn about arms which can never be executed, as well as about missing ones
+    fn check_exhaustiveness(&self, value_ty: &CheckedType, ctx: &mut TypeCtx) {
+        let mut exhaustive = false;
+        let mut bools = (false, false);
+        let mut variants: Option<(String, Vec<String>)> = None;
+
+        for arm in self.arms.iter() {
+            if exhaustive {
+                ctx.error(
+                    Error::new(ErrKind::Warning)
+                        .with_msg(format!(
+                            "unreachable pattern: `{}` is never checked",
+                            arm.pattern.print()
+                        ))
+                        .with_loc(arm.location.clone()),
+                );
+                continue;
+            }
+
+            if let Pattern::Constant(constant) = &arm.pattern {
+                match constant.downcast_ref::<JkBool>().map(|b| b.rust_value()) {
+                    Some(true) => bools.0 = true,
+                    Some(false) => bools.1 = true,
+                    None => {}
+                }
+            }
+
+            if let Some((enum_name, variant)) = arm.pattern.variant(ctx) {
+                let (_, covered) = variants.get_or_insert_with(|| (enum_name, vec![]));
+                covered.push(variant);
+            }
+
+            exhaustive = arm.pattern.is_catch_all(ctx)
+                || bools == (true, true)
+                || variants
+                    .as_ref()
+                    .is_some_and(|variants| Match::missing_variants(variants, ctx).is_empty());
+        }
+
+        if !exhaustive {
+            let mut warning = Error::new(ErrKind::Warning)
+                .with_msg(format!(
+                    "non-exhaustive `match` on a value of type {}: add a wildcard arm `_`",
+                    value_ty
+                ))
+                .with_loc(self.location.clone());
+
+            if let Some(variants) = &variants {
+                warning = warning.with_hint(Error::hint().with_msg(format!(
+                    "or add arms for the missing variants: {}",
+                    Match::missing_variants(variants, ctx).join(", ")
+                )));
+            }
+
+            ctx.error(warning);
+        }
+    }
+
+    /// Get the variants of an enum which are not matched by any arm, given the name of
+    /// the enum and the variants which are
+    fn missing_variants(
+        (enum_name, covered): &(String, Vec<String>),
+        ctx: &mut TypeCtx,
+    ) -> Vec<String> {
+        ctx.get_custom_type(enum_name)
+            .into_iter()
+            .flat_map(|dec| dec.variants())
+            .map(|variant| variant.name().to_string())
+            .filter(|variant| !covered.contains(variant))
+            .collect()
+    }
+}
+
+impl Instruction for Match {
+    fn kind(&self) -> InstrKind {
+        match self.arms.first() {
+            Some(arm) => arm.block.kind(),
+            None => InstrKind::Statement,
+        }
+    }
+
+    fn print(&self) -> String {
+        let mut base = format!("match {} {{\n", self.value.print());
+
+        for arm in self.arms.iter() {
+            arm.print()
+                .lines()
+                .for_each(|line| base.push_str(&format!("    {}\n", line)));
+            base.pop();
+            base.push_str(",\n");
+        }
+
+        base.push('}');
+        base
+    }
+
+    fn ast(&self) -> AstNode {
+        let arms: Vec<AstNode> = self
+            .arms
+            .iter()
+            .map(|arm| {
+                AstNode::new("MatchArm", arm.location.as_ref())
+                    .with_child("pattern", arm.pattern.ast())
+                    .with_child("block", arm.block.ast())
+            })
+            .collect();
+
+        AstNode::new("Match", self.location())
+            .with_child("value", self.value.ast())
+            .with_child("arms", arms)
+    }
+
+    fn execute(&self, ctx: &mut Context) -> Option<ObjectInstance> {
+        let value = self.value.execute_expression(ctx)?;
+
+        for arm in self.arms.iter() {
+            let bindings = match arm.pattern.matches(&value, ctx) {
+                Some(bindings) => bindings,
+                None if ctx.has_errors() => return None,
+                None => continue,
+            };
+
+            ctx.scope_enter();
+            let result = match bindings
+                .into_iter()
+                .try_for_each(|var| ctx.add_variable(var))
+            {
+                Ok(_) => arm.block.execute(ctx),
+                Err(e) => {
+                    ctx.error(e);
+                    None
+                }
+            };
+            ctx.scope_exit();
+
+            return result;
+        }
+
+        if self.kind() != InstrKind::Statement {
+            ctx.error(
+                Error::new(ErrKind::Context)
+                    .with_msg(format!(
+                        "no pattern matched the value of `{}`",
+                        self.value.print()
+                    ))
+                    .with_loc(self.location.clone()),
+            );
+        }
+
+        None
+    }
+
+    fn location(&self) -> Option<&SpanTuple> {
+        self.location.as_ref()
+    }
+}
+
+impl TypeCheck for Match {
+    fn resolve_type(&mut self, ctx: &mut TypeCtx) -> CheckedType {
+        let value_ty = self.value.type_of(ctx);
+        let mut arms_ty = None;
+
+        for arm in self.arms.iter_mut() {
+            ctx.scope_enter();
+
+            let pattern_ty = arm.pattern.resolve_type(ctx, arm.location.as_ref());
+            if value_ty != CheckedType::Error
+                && pattern_ty != CheckedType::Error
+                && pattern_ty != CheckedType::Later
+                && pattern_ty != value_ty
+            {
+                ctx.error(
+                    Error::new(ErrKind::TypeChecker)
+                        .with_msg(format!(
+                            "pattern `{}` of type {} cannot match a value of type {}",
+                            arm.pattern.print(),
+                            pattern_ty,
+                            value_ty
+                        ))
+                        .with_loc(arm.location.clone()),
+                );
+            }
+
+            let block_ty = arm.block.type_of(ctx);
+            ctx.scope_exit();
+
+            match &arms_ty {
+                None => arms_ty = Some(block_ty),
+                Some(ty) if ty != &block_ty => {
+                    ctx.error(
+                        Error::new(ErrKind::TypeChecker)
+                            .with_msg(format!(
+                                "incompatible types for `match` arms: {} and {}",
+                                ty, block_ty,
+                            ))
+                            .with_loc(arm.location.clone()),
+                    );
+                    return CheckedType::Error;
+                }
+                Some(_) => {}
+            }
+        }
+
+        self.check_exhaustiveness(&value_ty, ctx);
+
+        arms_ty.unwrap_or(CheckedType::Void)
+    }
+
+    fn set_cached_type(&mut self, ty: CheckedType) {
+        self.cached_type = Some(ty)
+    }
+
+    fn cached_type(&self) -> Option<&CheckedType> {
+        self.cached_type.as_ref()
+    }
+}
+
+impl GenericUser for Match {
+    fn resolve_usages(&mut self, type_map: &GenericMap, ctx: &mut TypeCtx) {
+        self.value.resolve_usages(type_map, ctx);
+        self.arms.iter_mut().for_each(|arm| {
+            if let Pattern::Constant(constant) = &mut arm.pattern {
+                constant.resolve_usages(type_map, ctx);
+            }
+            arm.block.resolve_usages(type_map, ctx);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instance::ToObjectInstance;
+    use crate::value::{JkInt, JkString};
+    use crate::{jinko, jinko_fail, jk_execute};
+
+    #[test]
+    fn t_print() {
+        let arms = vec![
+            MatchArm::new(Pattern::Constant(Box::new(JkInt::from(1))), Block::new()),
+            MatchArm::new(
+                Pattern::Type(
+                    TypeId::from("Point"),
+                    vec![
+                        (String::from("x"), String::from("px")),
+                        (String::from("y"), String::from("y")),
+                    ],
+                ),
+                Block::new(),
+            ),
+            MatchArm::new(Pattern::Wildcard, Block::new()),
+        ];
+        let match_block = Match::new(Box::new(JkInt::from(1)), arms);
+
+        assert_eq!(
+            match_block.print(),
+            "match 1 {\n    1 -> {},\n    Point(x: px, y) -> {},\n    _ -> {},\n}"
+        );
+    }
+
+    #[test]
+    fn t_execute_constant_patterns() {
+        assert_eq!(
+            jk_execute! {
+                match 2 {
+                    1 -> { "one" },
+                    2 -> { "two" },
+                    _ -> { "many" },
+                }
+            },
+            Some(JkString::from("two").to_instance())
+        );
+    }
+
+    #[test]
+    fn t_execute_wildcard() {
+        assert_eq!(
+            jk_execute! {
+                match "jinko" {
+                    "jk" -> { 1 },
+                    _ -> { 2 },
+                }
+            },
+            Some(JkInt::from(2).to_instance())
+        );
+    }
+
+    #[test]
+    fn t_execute_first_matching_arm() {
+        assert_eq!(
+            jk_execute! {
+                match 1 {
+                    1 -> { 1 },
+                    1 -> { 2 },
+                    _ -> { 3 },
+                }
+            },
+            Some(JkInt::from(1).to_instance())
+        );
+    }
+
+    #[test]
+    fn t_execute_type_pattern_bindings() {
+        assert_eq!(
+            jk_execute! {
+                type Point(x: int, y: int);
+
+                match Point(x: 40, y: 2) {
+                    Point(x: a, y) -> { a + y },
+                }
+            },
+            Some(JkInt::from(42).to_instance())
+        );
+    }
+
+    #[test]
+    fn t_execute_variant_patterns() {
+        assert_eq!(
+            jk_execute! {
+                type Shape = Circle(r: int) | Square(side: int) | Empty;
+
+                func area(s: Shape) -> int {
+                    match s {
+                        Circle(r: radius) -> { 3 * radius * radius },
+                        Square(side) -> { side * side },
+                        Empty -> { 0 },
+                    }
+                }
+
+                area(Circle(r: 2)) + area(Square(side: 3)) + area(Empty)
+            },
+            Some(JkInt::from(21).to_instance())
+        );
+    }
+
+    #[test]
+    fn tc_exhaustive_variants() {
+        let ctx = jinko! {
+            type Shape = Circle(r: int) | Empty;
+
+            s = Circle(r: 1);
+            n = match s {
+                Empty -> { 0 },
+                Circle(r) -> { r },
+            };
+        };
+
+        assert!(!ctx
+            .error_handler
+            .errors()
+            .iter()
+            .any(|e| e.kind() == ErrKind::Warning));
+    }
+
+    #[test]
+    fn tc_non_exhaustive_variants_warning() {
+        let ctx = jinko! {
+            type Shape = Circle(r: int) | Square(side: int) | Empty;
+
+            s = Circle(r: 1);
+            n = match s {
+                Circle(r) -> { r },
+                Empty -> { 0 },
+            };
+        };
+
+        assert!(ctx
+            .error_handler
+            .errors()
+            .iter()
+            .any(|e| e.kind() == ErrKind::Warning));
+    }
+
+    #[test]
+    fn tc_invalid_variant_of_other_enum() {
+        jinko_fail! {
+            type Shape = Circle(r: int) | Empty;
+            type Color = Red | Blue;
+
+            match Red {
+                Circle(r) -> { r },
+                _ -> { 0 },
+            }
+        };
+    }
+
+    #[test]
+    fn t_bindings_do_not_leak() {
+        jinko_fail! {
+            type Point(x: int, y: int);
+
+            match Point(x: 40, y: 2) {
+                Point(x) -> { x },
+            };
+            x
+        };
+    }
+
+    #[test]
+    fn tc_exhaustive_bool() {
+        let ctx = jinko! {
+            b = true;
+            n = match b {
+                true -> { 1 },
+                false -> { 0 },
+            };
+        };
+
+        assert!(!ctx
+            .error_handler
+            .errors()
+            .iter()
+            .any(|e| e.kind() == ErrKind::Warning));
+    }
+
+    #[test]
+    fn tc_non_exhaustive_warning() {
+        let ctx = jinko! {
+            n = 1;
+            match n {
+                1 -> { },
+                2 -> { },
+            };
+        };
+
+        assert!(ctx
+            .error_handler
+            .errors()
+            .iter()
+            .any(|e| e.kind() == ErrKind::Warning));
+    }
+
+    #[test]
+    fn tc_unreachable_arm_warning() {
+        let ctx = jinko! {
+            match 1 {
+                _ -> { },
+                1 -> { },
+            };
+        };
+
+        assert!(ctx
+            .error_handler
+            .errors()
+            .iter()
+            .any(|e| e.kind() == ErrKind::Warning));
+    }
+
+    #[test]
+    fn tc_invalid_pattern_type() {
+        jinko_fail! {
+            match 1 {
+                "one" -> { 1 },
+                _ -> { 2 },
+            }
+        };
+    }
+
+    #[test]
+    fn tc_invalid_field_binding() {
+        jinko_fail! {
+            type Point(x: int, y: int);
+
+            match Point(x: 1, y: 2) {
+                Point(z) -> { z },
+            }
+        };
+    }
+
+    #[test]
+    fn tc_incompatible_arm_types() {
+        jinko_fail! {
+            match 1 {
+                1 -> { 1 },
+                _ -> { "many" },
+            }
+        };
+    }
+}
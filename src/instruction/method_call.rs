@@ -1,34 +1,87 @@
-//! A method like call is syntactic sugar over regular function calls. During executions,
-//! they get desugared into a normal function call.
+//! A method like call is syntactic sugar over regular function calls. They get
+//! desugared into a normal function call as soon as they are created: `a.f(b)` is
+//! equivalent to `f(a, b)`. This also means that method calls can be chained, with
+//! `a.f().g()` being equivalent to `g(f(a))`.
 
+use crate::ast::{AstNode, Json};
 use crate::context::Context;
 use crate::generics::GenericUser;
 use crate::instance::ObjectInstance;
 use crate::instruction::FunctionCall;
-use crate::instruction::{InstrKind, Instruction};
+use crate::instruction::{print_receiver, InstrKind, Instruction};
 use crate::location::SpanTuple;
 use crate::typechecker::{CheckedType, TypeCheck, TypeCtx};
+use crate::value::JkString;
 
 #[derive(Clone)]
 pub struct MethodCall {
-    var: Box<dyn Instruction>,
-    method: FunctionCall,
+    /// Desugared function call. Its first argument is the receiver of the method
+    call: FunctionCall,
+    /// Is the method call the result of desugaring a string literal: `"a{b}"` is
+    /// parsed as `"a".concat(b)`
+    string_literal: bool,
     cached_type: Option<CheckedType>,
     location: Option<SpanTuple>,
 }
 
 impl MethodCall {
     /// Create a new MethodCall from a variable and an associated function
-    pub fn new(var: Box<dyn Instruction>, method: FunctionCall) -> MethodCall {
+    pub fn new(var: Box<dyn Instruction>, mut method: FunctionCall) -> MethodCall {
+        method.add_arg_front(var);
+
         MethodCall {
-            var,
-            method,
+            call: method,
+            string_literal: false,
             cached_type: None,
             location: None,
         }
     }
 
+    /// Concatenate two parts of a string literal containing escaped characters or
+    /// format arguments. The method call is printed back as a string literal
+    pub(crate) fn string_literal(
+        lhs: Box<dyn Instruction>,
+        rhs: Box<dyn Instruction>,
+    ) -> MethodCall {
+        let concat = FunctionCall::new(String::from("concat"), vec![], vec![rhs]);
+
+        MethodCall {
+            string_literal: true,
+            ..MethodCall::new(lhs, concat)
+        }
+    }
+
+    /// Print a part of a string literal, without the surrounding quotes
+    fn print_string_part(part: &dyn Instruction) -> String {
+        if let Some(s) = part.downcast_ref::<JkString>() {
+            JkString::escape(&s.0)
+        } else if let Some(call) = part
+            .downcast_ref::<MethodCall>()
+            .filter(|c| c.string_literal)
+        {
+            call.call
+                .args()
+                .iter()
+                .map(|part| MethodCall::print_string_part(&**part))
+                .collect()
+        } else {
+            format!("{{{}}}", part.print())
+        }
+    }
+
+    /// Get a reference on the receiver of the method call, `a` in `a.f()`
+    pub fn receiver(&self) -> &dyn Instruction {
+        // A method call always has a receiver, which is the first argument
+        &*self.call.args()[0]
+    }
+
+    /// Get a reference on the desugared function call
+    pub fn call(&self) -> &FunctionCall {
+        &self.call
+    }
+
     pub fn set_location(&mut self, location: SpanTuple) {
+        self.call.set_location(location.clone());
         self.location = Some(location)
     }
 }
@@ -40,18 +93,37 @@ impl Instruction for MethodCall {
     }
 
     fn print(&self) -> String {
-        format!("{}.{}", self.var.print(), self.method.print())
-    }
+        if self.string_literal {
+            return format!("\"{}\"", MethodCall::print_string_part(self));
+        }
 
-    fn execute(&self, ctx: &mut Context) -> Option<ObjectInstance> {
-        let mut call = self.method.clone();
+        let method = FunctionCall::new(
+            self.call.name().to_owned(),
+            self.call.generics().clone(),
+            self.call.args().iter().skip(1).cloned().collect(),
+        );
 
-        call.add_arg_front(self.var.clone());
-        if let Some(loc) = &self.location {
-            call.set_location(loc.clone())
-        };
+        format!("{}.{}", print_receiver(self.receiver()), method.print())
+    }
 
-        call.execute(ctx)
+    fn ast(&self) -> AstNode {
+        AstNode::new("MethodCall", self.location())
+            .with_attr("name", self.call.name())
+            .with_attr(
+                "generics",
+                self.call
+                    .generics()
+                    .iter()
+                    .map(Json::from)
+                    .collect::<Vec<_>>(),
+            )
+            .with_attr("string_literal", self.string_literal)
+            .with_child("receiver", self.receiver().ast())
+            .with_children("args", &self.call.args()[1..])
+    }
+
+    fn execute(&self, ctx: &mut Context) -> Option<ObjectInstance> {
+        self.call.execute(ctx)
     }
 
     fn location(&self) -> Option<&SpanTuple> {
@@ -61,21 +133,7 @@ impl Instruction for MethodCall {
 
 impl TypeCheck for MethodCall {
     fn resolve_type(&mut self, ctx: &mut TypeCtx) -> CheckedType {
-        let mut call = self.method.clone();
-
-        call.add_arg_front(self.var.clone());
-        if let Some(loc) = &self.location {
-            call.set_location(loc.clone())
-        };
-
-        let res = call.type_of(ctx);
-
-        // FIXME: Figure out a way to do this without the extra clone, which
-        // is useless. We should be able to do desugaring here (or before)
-        // and simply resolve to the new mangled name if necessary
-        self.method.set_name(call.name().to_string());
-
-        res
+        self.call.type_of(ctx)
     }
 
     fn set_cached_type(&mut self, ty: CheckedType) {
@@ -89,8 +147,7 @@ impl TypeCheck for MethodCall {
 
 impl GenericUser for MethodCall {
     fn resolve_usages(&mut self, type_map: &crate::generics::GenericMap, ctx: &mut TypeCtx) {
-        // FIXME: Can we avoid adding the argument here?
-        self.method.resolve_usages(type_map, ctx);
+        self.call.resolve_usages(type_map, ctx);
     }
 }
 
@@ -99,8 +156,9 @@ mod tests {
     use super::*;
     use crate::instance::ToObjectInstance;
     use crate::parser::constructs;
+    use crate::typechecker::TypeId;
     use crate::value::JkInt;
-    use crate::{jinko, span};
+    use crate::{jinko, jinko_fail, span};
 
     #[test]
     fn t_print() {
@@ -111,6 +169,63 @@ mod tests {
         assert_eq!(mc.print(), "15.some_int_func()".to_owned())
     }
 
+    #[test]
+    fn t_print_args_and_generics() {
+        let var = Box::new(JkInt::from(15));
+        let method = FunctionCall::new(
+            "f".to_owned(),
+            vec![TypeId::from("int")],
+            vec![Box::new(JkInt::from(2))],
+        );
+        let mc = MethodCall::new(var, method);
+
+        assert_eq!(mc.print(), "15.f[int](2)".to_owned())
+    }
+
+    #[test]
+    fn t_desugared_receiver() {
+        let var = Box::new(JkInt::from(15));
+        let method = FunctionCall::new("f".to_owned(), vec![], vec![Box::new(JkInt::from(2))]);
+        let mc = MethodCall::new(var, method);
+
+        assert_eq!(mc.call().args().len(), 2);
+        assert_eq!(mc.receiver().print(), "15");
+    }
+
+    #[test]
+    fn t_method_resolved_by_receiver_type() {
+        use crate::jk_execute;
+
+        let res = jk_execute! {
+            type MyStr(inner: string);
+            func len(s: MyStr) -> int { s.inner.len() * 2 }
+
+            s = MyStr("jinko");
+            s.len() + len(s) + "jk".len()
+        };
+
+        assert_eq!(res, Some(JkInt::from(22).to_instance()));
+    }
+
+    #[test]
+    fn tc_method_same_receiver_type() {
+        jinko_fail! {
+            func twice(a: int) -> int { a * 2 }
+            func twice(a: int) -> int { a + a }
+        };
+    }
+
+    #[test]
+    fn t_execute_chained() {
+        use crate::jk_execute;
+        use crate::value::JkString;
+
+        assert_eq!(
+            jk_execute!("jin".concat("k").concat("o")),
+            Some(JkString::from("jinko").to_instance())
+        );
+    }
+
     #[test]
     fn t_execute() {
         let mut ctx = Context::new();
@@ -142,7 +257,6 @@ mod tests {
     }
 
     #[test]
-    #[ignore] // FIXME: #340
     fn tc_valid_call_multi_arg() {
         jinko! {
             func to_int(self: bool, truthy_value: int) -> int {
@@ -158,9 +272,8 @@ mod tests {
     }
 
     #[test]
-    #[ignore] // FIXME: #340
     fn tc_invalid_call_type() {
-        jinko! {
+        jinko_fail! {
             func id(x: int) -> int { x }
             true.id();
         };
@@ -4,6 +4,7 @@
 
 use std::fmt::Debug;
 
+use crate::ast::AstNode;
 use crate::context::Context;
 use crate::error::{ErrKind, Error};
 use crate::generics::GenericUser;
@@ -22,14 +23,21 @@ mod function_call;
 mod function_declaration;
 mod if_else;
 mod incl;
+mod interface;
 mod jk_inst;
 mod jk_return;
+mod lambda;
 mod loop_block;
+mod loop_control;
+mod match_block;
 mod method_call;
 mod operator;
+mod range;
 mod rename;
+mod try_op;
 mod type_declaration;
 mod type_instantiation;
+mod unary_op;
 mod var;
 mod var_assignment;
 mod var_or_empty_type;
@@ -42,13 +50,20 @@ pub use function_call::FunctionCall;
 pub use function_declaration::{FunctionDec, FunctionKind};
 pub use if_else::IfElse;
 pub use incl::Incl;
+pub use interface::{InterfaceDec, InterfaceImpl, SELF_TYPE};
 pub use jk_inst::{JkInst, JkInstKind};
 pub use jk_return::Return;
+pub use lambda::{Lambda, LAMBDA_NAME};
 pub use loop_block::{Loop, LoopKind};
+pub use loop_control::{Break, Continue};
+pub use match_block::{Match, MatchArm, Pattern};
 pub use method_call::MethodCall;
 pub use operator::Operator;
+pub use range::Range;
+pub use try_op::TryOp;
 pub use type_declaration::TypeDec;
 pub use type_instantiation::TypeInstantiation;
+pub use unary_op::{UnaryOp, UnaryOperator};
 pub use var::Var;
 pub use var_assignment::VarAssign;
 pub use var_or_empty_type::VarOrEmptyType;
@@ -83,10 +98,15 @@ pub trait Instruction: InstructionClone + Downcast + TypeCheck + GenericUser {
     /// Execute the instruction, hoping for an instance to be returned. If no instance is
     /// returned, error out.
     fn execute_expression(&self, ctx: &mut Context) -> Option<ObjectInstance> {
+        let errors = ctx.error_handler.errors().len();
         let instance = self.execute(ctx);
 
         match instance {
             Some(obj) => Some(obj),
+            // The instruction failed and reported why already
+            None if ctx.error_handler.errors().len() > errors => None,
+            // The instruction exited the current function early
+            None if ctx.is_interrupted() => None,
             None => {
                 ctx.error(Error::new(ErrKind::Context).with_msg(format!(
                     "statement found when expression was expected: {}",
@@ -125,6 +145,9 @@ pub trait Instruction: InstructionClone + Downcast + TypeCheck + GenericUser {
     /// Pretty-print the instruction to valid jinko code
     fn print(&self) -> String;
 
+    /// Convert the instruction to a node of the syntax tree, used by external tooling
+    fn ast(&self) -> AstNode;
+
     /// Fetch a reference to this instruction's location
     fn location(&self) -> Option<&SpanTuple> {
         // FIXME: Remove default implementation
@@ -134,6 +157,17 @@ pub trait Instruction: InstructionClone + Downcast + TypeCheck + GenericUser {
 
 impl_downcast!(Instruction);
 
+/// Print an instruction used as the operand of a unary operation, or as the receiver
+/// of a method call or field access. Operations are surrounded by parentheses, as
+/// `(a + b).f()` would otherwise be printed as `a + b.f()`
+pub(crate) fn print_receiver(instruction: &dyn Instruction) -> String {
+    if instruction.is::<BinaryOp>() || instruction.is::<UnaryOp>() || instruction.is::<Range>() {
+        format!("({})", instruction.print())
+    } else {
+        instruction.print()
+    }
+}
+
 impl Debug for dyn Instruction {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.print())
@@ -9,6 +9,13 @@ pub enum Operator {
     Sub,
     Mul,
     Div,
+    Rem,
+    Pow,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
     Lt,
     Gt,
     LtEq,
@@ -27,6 +34,13 @@ impl Operator {
             "-" => Operator::Sub,
             "*" => Operator::Mul,
             "/" => Operator::Div,
+            "%" => Operator::Rem,
+            "**" => Operator::Pow,
+            "&" => Operator::BitAnd,
+            "|" => Operator::BitOr,
+            "^" => Operator::BitXor,
+            "<<" => Operator::Shl,
+            ">>" => Operator::Shr,
             "<" => Operator::Lt,
             ">" => Operator::Gt,
             "<=" => Operator::LtEq,
@@ -46,6 +60,13 @@ impl Operator {
             Operator::Sub => "-",
             Operator::Mul => "*",
             Operator::Div => "/",
+            Operator::Rem => "%",
+            Operator::Pow => "**",
+            Operator::BitAnd => "&",
+            Operator::BitOr => "|",
+            Operator::BitXor => "^",
+            Operator::Shl => "<<",
+            Operator::Shr => ">>",
             Operator::Lt => "<",
             Operator::Gt => ">",
             Operator::LtEq => "<=",
@@ -60,9 +81,15 @@ impl Operator {
     /// Return the operator's precedence according to the Shunting Yard algorithm
     pub fn precedence(&self) -> u8 {
         match self {
-            // Classic SY operator precedence
-            Operator::Mul | Operator::Div => 3,
-            Operator::Add | Operator::Sub => 2,
+            // Classic SY operator precedence. Bit-wise operators bind tighter than
+            // comparisons, unlike in C
+            Operator::Pow => 8,
+            Operator::Mul | Operator::Div | Operator::Rem => 7,
+            Operator::Add | Operator::Sub => 6,
+            Operator::Shl | Operator::Shr => 5,
+            Operator::BitAnd => 4,
+            Operator::BitXor => 3,
+            Operator::BitOr => 2,
             Operator::Lt
             | Operator::Gt
             | Operator::LtEq
@@ -76,14 +103,17 @@ impl Operator {
         }
     }
 
-    /// Is the operator a left associative one
+    /// Is the operator a left associative one. Only the power operator is right
+    /// associative: `2 ** 3 ** 2` is `2 ** (3 ** 2)`
     pub fn is_left_associative(&self) -> bool {
-        // FIXME: Not entirely true
-        // - Changes once we add more operators such as the Power one
-        // match self {
-        //     _ => true,
-        // }
+        !matches!(self, Operator::Pow)
+    }
 
-        true
+    /// Is the operator a bit-wise one, which can only be applied to integers
+    pub fn is_bitwise(&self) -> bool {
+        matches!(
+            self,
+            Operator::BitAnd | Operator::BitOr | Operator::BitXor | Operator::Shl | Operator::Shr
+        )
     }
 }
@@ -0,0 +1,137 @@
+//! Ranges represent the integers going from a start value to an end value. `0..10`
+//! contains the integers from zero to nine, while the inclusive range `0..=10` also
+//! contains ten. Ranges can be iterated on using a `for` loop.
+//!
+//! A range evaluates to a `Range_int`, which is declared in the standard library, by
+//! calling the `range` or `range_inclusive` function.
+
+use crate::ast::AstNode;
+use crate::context::Context;
+use crate::error::{ErrKind, Error};
+use crate::generics::{GenericMap, GenericUser};
+use crate::instance::ObjectInstance;
+use crate::instruction::{FunctionCall, InstrKind, Instruction};
+use crate::location::SpanTuple;
+use crate::typechecker::{CheckedType, TypeCheck, TypeCtx, TypeId};
+
+/// The `Range` struct contains the call to the standard library function creating the
+/// range, whose arguments are the bounds of the range
+#[derive(Clone)]
+pub struct Range {
+    call: FunctionCall,
+    inclusive: bool,
+    cached_type: Option<CheckedType>,
+    location: Option<SpanTuple>,
+}
+
+impl Range {
+    /// Create a new range from its bounds. An inclusive range contains its end bound
+    pub fn new(start: Box<dyn Instruction>, end: Box<dyn Instruction>, inclusive: bool) -> Range {
+        let fn_name = match inclusive {
+            true => "range_inclusive",
+            false => "range",
+        };
+
+        Range {
+            call: FunctionCall::new(String::from(fn_name), vec![], vec![start, end]),
+            inclusive,
+            cached_type: None,
+            location: None,
+        }
+    }
+
+    /// Get a reference on the start bound of the range
+    pub fn start(&self) -> &dyn Instruction {
+        &*self.call.args()[0]
+    }
+
+    /// Get a reference on the end bound of the range
+    pub fn end(&self) -> &dyn Instruction {
+        &*self.call.args()[1]
+    }
+
+    /// Is the end bound part of the range
+    pub fn is_inclusive(&self) -> bool {
+        self.inclusive
+    }
+
+    fn operator(&self) -> &str {
+        match self.inclusive {
+            true => "..=",
+            false => "..",
+        }
+    }
+
+    pub fn set_location(&mut self, location: SpanTuple) {
+        self.call.set_location(location.clone());
+        self.location = Some(location)
+    }
+}
+
+impl Instruction for Range {
+    fn kind(&self) -> InstrKind {
+        InstrKind::Expression(None)
+    }
+
+    fn print(&self) -> String {
+        format!(
+            "{}{}{}",
+            self.start().print(),
+            self.operator(),
+            self.end().print()
+        )
+    }
+
+    fn ast(&self) -> AstNode {
+        AstNode::new("Range", self.location())
+            .with_attr("inclusive", self.inclusive)
+            .with_child("start", self.start().ast())
+            .with_child("end", self.end().ast())
+    }
+
+    fn execute(&self, ctx: &mut Context) -> Option<ObjectInstance> {
+        self.call.execute(ctx)
+    }
+
+    fn location(&self) -> Option<&SpanTuple> {
+        self.location.as_ref()
+    }
+}
+
+impl TypeCheck for Range {
+    fn resolve_type(&mut self, ctx: &mut TypeCtx) -> CheckedType {
+        let int = CheckedType::Resolved(TypeId::from("int"));
+        let start_ty = self.call.args()[0].clone().type_of(ctx);
+        let end_ty = self.call.args()[1].clone().type_of(ctx);
+
+        if start_ty != int || end_ty != int {
+            ctx.error(
+                Error::new(ErrKind::TypeChecker)
+                    .with_msg(format!(
+                        "range bounds must be integers: {}{}{}",
+                        start_ty,
+                        self.operator(),
+                        end_ty,
+                    ))
+                    .with_loc(self.location.clone()),
+            );
+            return CheckedType::Error;
+        }
+
+        self.call.type_of(ctx)
+    }
+
+    fn set_cached_type(&mut self, ty: CheckedType) {
+        self.cached_type = Some(ty)
+    }
+
+    fn cached_type(&self) -> Option<&CheckedType> {
+        self.cached_type.as_ref()
+    }
+}
+
+impl GenericUser for Range {
+    fn resolve_usages(&mut self, type_map: &GenericMap, ctx: &mut TypeCtx) {
+        self.call.resolve_usages(type_map, ctx)
+    }
+}
@@ -39,6 +39,11 @@
 //! as well. Much like the Instruction trait, every function should also be called on
 //! fields implementing the Rename trait when being executed, allowing for an AST-like
 //! behavior
+//!
+//! Prefixed names should not leak into diagnostics, since users never wrote them.
+//! Every prefixed symbol is recorded in the context's `SymbolMap` along with its
+//! original name and the source it comes from, so that errors can refer to
+//! `module::a` as `a` from `module`.
 
 // FIXME: This trait needs to be worked on and fixed. For now, do not implement it.
 // pub trait Rename {
@@ -0,0 +1,277 @@
+//! The `?` operator propagates errors out of the current function. When writing
+//! `x?`, a TryOp is created containing `x` as its operand.
+//!
+//! The operand needs to be a `Result_int`, which is declared in the standard library.
+//! If it contains a value, the operation evaluates to that value. Otherwise, the current
+//! function returns the erroneous result, which means `?` can only be used in functions
+//! returning a `Result_int`.
+
+use crate::ast::AstNode;
+use crate::context::{Context, Interrupt};
+use crate::error::{ErrKind, Error};
+use crate::generics::{GenericMap, GenericUser};
+use crate::instance::ObjectInstance;
+use crate::instruction::{print_receiver, InstrKind, Instruction};
+use crate::location::SpanTuple;
+use crate::typechecker::{CheckedType, TypeCheck, TypeCtx, TypeId};
+
+/// Name of the type the `?` operator can be applied to
+// FIXME: Allow other result types once generic types are usable from the interpreter
+pub const RESULT_TYPE: &str = "Result_int";
+
+/// The `TryOp` struct contains the expression whose errors get propagated
+#[derive(Clone)]
+pub struct TryOp {
+    operand: Box<dyn Instruction>,
+    cached_type: Option<CheckedType>,
+    location: Option<SpanTuple>,
+}
+
+impl TryOp {
+    /// Create a new `TryOp` from an instruction
+    pub fn new(operand: Box<dyn Instruction>) -> TryOp {
+        TryOp {
+            operand,
+            cached_type: None,
+            location: None,
+        }
+    }
+
+    /// Get a reference on the operand of the TryOp
+    pub fn operand(&self) -> &dyn Instruction {
+        &*self.operand
+    }
+
+    pub fn set_location(&mut self, location: SpanTuple) {
+        self.location = Some(location)
+    }
+
+    /// Does the declared `Result_int` have the fields the operator relies on. The type
+    /// can be declared by users when the standard library is not included
+    fn has_result_fields(ctx: &mut TypeCtx) -> bool {
+        let dec = match ctx.get_custom_type(RESULT_TYPE) {
+            Some(dec) => dec,
+            None => return false,
+        };
+        let has_field = |name: &str, ty: &str| {
+            dec.fields()
+                .iter()
+                .any(|field| field.name() == name && field.get_type().id() == ty)
+        };
+
+        has_field("is_ok", "bool") && has_field("value", "int")
+    }
+}
+
+impl Instruction for TryOp {
+    fn kind(&self) -> InstrKind {
+        InstrKind::Expression(None)
+    }
+
+    fn print(&self) -> String {
+        format!("{}?", print_receiver(&*self.operand))
+    }
+
+    fn ast(&self) -> AstNode {
+        AstNode::new("TryOp", self.location()).with_child("operand", self.operand.ast())
+    }
+
+    fn execute(&self, ctx: &mut Context) -> Option<ObjectInstance> {
+        let result = self.operand.execute_expression(ctx)?;
+
+        // The typechecker made sure that the operand is a `Result_int` containing the
+        // `is_ok` and `value` fields
+        let value = result
+            .get_field_as::<bool>("is_ok")
+            .and_then(|is_ok| is_ok.then(|| result.get_field("value")).transpose());
+
+        match value {
+            Ok(Some(value)) => Some(value),
+            Ok(None) => {
+                ctx.interrupt(Interrupt::Return(Some(result)));
+                None
+            }
+            Err(e) => {
+                ctx.error(e.with_loc(self.location.clone()));
+                None
+            }
+        }
+    }
+
+    fn location(&self) -> Option<&SpanTuple> {
+        self.location.as_ref()
+    }
+}
+
+impl TypeCheck for TryOp {
+    fn resolve_type(&mut self, ctx: &mut TypeCtx) -> CheckedType {
+        let result_ty = CheckedType::Resolved(TypeId::from(RESULT_TYPE));
+        let operand_ty = self.operand.type_of(ctx);
+
+        if operand_ty == CheckedType::Error {
+            return CheckedType::Error;
+        }
+
+        if operand_ty != result_ty {
+            ctx.error(
+                Error::new(ErrKind::TypeChecker)
+                    .with_msg(format!(
+                        "the `?` operator can only be applied to a `{}`, not a `{}`",
+                        RESULT_TYPE, operand_ty,
+                    ))
+                    .with_loc(self.location.clone()),
+            );
+            return CheckedType::Error;
+        }
+
+        if !TryOp::has_result_fields(ctx) {
+            ctx.error(
+                Error::new(ErrKind::TypeChecker)
+                    .with_msg(format!(
+                        "the `?` operator requires `{}` to have an `is_ok: bool` and a `value: int` field",
+                        RESULT_TYPE,
+                    ))
+                    .with_loc(self.location.clone()),
+            );
+            return CheckedType::Error;
+        }
+
+        if ctx.function_return_type() != Some(&result_ty) {
+            ctx.error(
+                Error::new(ErrKind::TypeChecker)
+                    .with_msg(format!(
+                        "the `?` operator can only be used in a function returning a `{}`",
+                        RESULT_TYPE,
+                    ))
+                    .with_loc(self.location.clone()),
+            );
+            return CheckedType::Error;
+        }
+
+        CheckedType::Resolved(TypeId::from("int"))
+    }
+
+    fn set_cached_type(&mut self, ty: CheckedType) {
+        self.cached_type = Some(ty)
+    }
+
+    fn cached_type(&self) -> Option<&CheckedType> {
+        self.cached_type.as_ref()
+    }
+}
+
+impl GenericUser for TryOp {
+    fn resolve_usages(&mut self, type_map: &GenericMap, ctx: &mut TypeCtx) {
+        self.operand.resolve_usages(type_map, ctx);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instance::ToObjectInstance;
+    use crate::value::{JkBool, JkInt, JkString};
+    use crate::{jinko, jinko_fail, jk_execute};
+
+    #[test]
+    fn t_print() {
+        let try_op = TryOp::new(Box::new(JkInt::from(12)));
+
+        assert_eq!(try_op.print(), "12?");
+    }
+
+    #[test]
+    fn t_execute_ok() {
+        assert_eq!(
+            jk_execute! {
+                func double(r: Result_int) -> Result_int {
+                    ok(r? * 2)
+                }
+
+                double(ok(21)).unwrap_or(0)
+            },
+            Some(JkInt::from(42).to_instance())
+        );
+    }
+
+    #[test]
+    fn t_execute_err_returns_early() {
+        let ctx = jinko! {
+            func f(r: Result_int) -> Result_int {
+                value = r?;
+                ok(value + 1)
+            }
+
+            r = f(err("invalid"));
+        };
+
+        let r = ctx.get_variable("r").unwrap().instance();
+        assert_eq!(
+            r.get_field("error").unwrap(),
+            JkString::from("invalid").to_instance()
+        );
+        assert_eq!(
+            r.get_field("is_ok").unwrap(),
+            JkBool::from(false).to_instance()
+        );
+    }
+
+    #[test]
+    fn t_execute_err_in_nested_expressions() {
+        assert_eq!(
+            jk_execute! {
+                func sum(a: Result_int, b: Result_int) -> Result_int {
+                    mut total = 0;
+                    for i in 0..3 {
+                        total = total + a? + b?;
+                    };
+
+                    ok(total)
+                }
+
+                sum(ok(1), err("no")).is_err()
+            },
+            Some(JkBool::from(true).to_instance())
+        );
+    }
+
+    #[test]
+    fn tc_invalid_operand() {
+        jinko_fail! {
+            func f() -> Result_int {
+                x = 15?;
+                ok(x)
+            }
+        };
+    }
+
+    #[test]
+    fn tc_invalid_outside_result_function() {
+        jinko_fail! {
+            func f(r: Result_int) -> int {
+                r?
+            }
+        };
+    }
+
+    #[test]
+    fn tc_invalid_user_result_type() {
+        let mut ctx = Context::new();
+
+        let code = "type Result_int(x: int); \
+            func f() -> Result_int { y = Result_int(x: 1)?; Result_int(x: y) } \
+            f()";
+        assert!(ctx.eval(code).is_err());
+        assert!(ctx.error_handler.errors()[0]
+            .msg()
+            .unwrap()
+            .contains("`is_ok: bool`"));
+    }
+
+    #[test]
+    fn tc_invalid_at_top_level() {
+        jinko_fail! {
+            x = ok(15)?;
+        };
+    }
+}
@@ -1,16 +1,26 @@
 use super::{DecArg, InstrKind, Instruction};
 
+use std::collections::HashSet;
+
+use crate::ast::{AstNode, Json};
 use crate::context::Context;
-use crate::generics::{GenericExpander, GenericMap, GenericUser};
+use crate::error::{ErrKind, Error};
+use crate::generics::{self, GenericExpander, GenericMap, GenericUser};
 use crate::instance::ObjectInstance;
 use crate::location::SpanTuple;
 use crate::typechecker::{CheckedType, TypeCheck, TypeCtx, TypeId};
 
+/// Declaration of a custom type. A type is either a record of fields, such as
+/// `type Point(x: int, y: int)`, or an enum made of multiple variants, such as
+/// `type Shape = Circle(r: float) | Square(side: float)`. Each variant is itself a
+/// type declaration, whose instances are of the type of the enum
 #[derive(Clone, Debug, PartialEq)]
 pub struct TypeDec {
     name: String,
     generics: Vec<TypeId>,
     fields: Vec<DecArg>,
+    variants: Vec<TypeDec>,
+    variant_of: Option<String>,
     typechecked: bool,
     location: Option<SpanTuple>,
 }
@@ -22,11 +32,29 @@ impl TypeDec {
             name,
             generics,
             fields,
+            variants: vec![],
+            variant_of: None,
             typechecked: false,
             location: None,
         }
     }
 
+    /// Create a new enum from its variants
+    pub fn new_enum(name: String, variants: Vec<TypeDec>) -> TypeDec {
+        let variants = variants
+            .into_iter()
+            .map(|variant| TypeDec {
+                variant_of: Some(name.clone()),
+                ..variant
+            })
+            .collect();
+
+        TypeDec {
+            variants,
+            ..TypeDec::new(name, vec![], vec![])
+        }
+    }
+
     /// Get a reference to the name of the type
     pub fn name(&self) -> &str {
         &self.name
@@ -42,39 +70,46 @@ impl TypeDec {
         &self.generics
     }
 
-    pub fn set_location(&mut self, location: SpanTuple) {
-        self.location = Some(location)
+    /// Get a reference to the variants of the type, if it is an enum
+    pub fn variants(&self) -> &Vec<TypeDec> {
+        &self.variants
     }
-}
 
-impl Instruction for TypeDec {
-    fn kind(&self) -> InstrKind {
-        InstrKind::Statement
+    /// Is the type an enum
+    pub fn is_enum(&self) -> bool {
+        !self.variants.is_empty()
     }
 
-    fn execute(&self, ctx: &mut Context) -> Option<ObjectInstance> {
-        if let Err(e) = ctx.add_type(self.clone()) {
-            ctx.error(e);
-            return None;
-        }
+    /// Get the name of the enum the type is a variant of, if any
+    pub fn variant_of(&self) -> Option<&str> {
+        self.variant_of.as_deref()
+    }
 
-        // Declaring a type is always a statement (for now)
-        None
+    /// Get the type of the instances created from the declaration. Instances of a
+    /// variant are of the type of their enum
+    pub fn instance_type(&self) -> TypeId {
+        TypeId::from(self.variant_of().unwrap_or(&self.name))
     }
 
+    pub fn set_location(&mut self, location: SpanTuple) {
+        self.location = Some(location)
+    }
+
+    /// Print the name of the type along with its generics and fields, as they are
+    /// written in its declaration
     // FIXME: Really unefficient
-    fn print(&self) -> String {
-        let mut base = format!("type {}", self.name);
+    fn signature(&self) -> String {
+        let mut base = self.name.clone();
 
         if !self.generics.is_empty() {
             base.push('[');
-            base.push_str(self.generics.first().unwrap().id());
+            base.push_str(&self.generics.first().unwrap().print());
             let generic_str = self
                 .generics
                 .iter()
                 .skip(1)
                 .fold(String::new(), |acc, ty_id| {
-                    format!("{}, {}", acc, ty_id.id())
+                    format!("{}, {}", acc, ty_id.print())
                 });
             base.push_str(&generic_str);
             base.push(']');
@@ -95,6 +130,104 @@ impl Instruction for TypeDec {
         base
     }
 
+    /// Look for a chain of fields leading from the type back to itself, starting at
+    /// one of its own fields. Instances of such a type would have to contain another
+    /// instance of the type, and would thus have an infinite size
+    fn find_cycle(&self, field: &DecArg, ctx: &mut TypeCtx) -> Option<Vec<String>> {
+        fn visit(
+            target: &str,
+            ty: &str,
+            ctx: &mut TypeCtx,
+            path: &mut Vec<String>,
+            visited: &mut HashSet<String>,
+        ) -> bool {
+            path.push(ty.to_string());
+            if ty == target {
+                return true;
+            }
+
+            if visited.insert(ty.to_string()) {
+                let field_types: Vec<String> = ctx
+                    .get_custom_type(ty)
+                    .into_iter()
+                    .flat_map(|dec| dec.fields())
+                    .map(|field| field.get_type().id().to_string())
+                    .collect();
+
+                for field_ty in field_types {
+                    if visit(target, &field_ty, ctx, path, visited) {
+                        return true;
+                    }
+                }
+            }
+
+            path.pop();
+            false
+        }
+
+        let mut path = vec![self.name.clone()];
+        let mut visited = HashSet::new();
+
+        visit(
+            &self.name,
+            field.get_type().id(),
+            ctx,
+            &mut path,
+            &mut visited,
+        )
+        .then_some(path)
+    }
+}
+
+impl Instruction for TypeDec {
+    fn kind(&self) -> InstrKind {
+        InstrKind::Statement
+    }
+
+    fn execute(&self, ctx: &mut Context) -> Option<ObjectInstance> {
+        for variant in self.variants.iter() {
+            if let Err(e) = ctx.add_type(variant.clone()) {
+                ctx.error(e);
+                return None;
+            }
+        }
+
+        if let Err(e) = ctx.add_type(self.clone()) {
+            ctx.error(e);
+            return None;
+        }
+
+        // Declaring a type is always a statement (for now)
+        None
+    }
+
+    fn print(&self) -> String {
+        match self.is_enum() {
+            true => {
+                let variants: Vec<String> = self.variants.iter().map(TypeDec::signature).collect();
+
+                format!("type {} = {}", self.signature(), variants.join(" | "))
+            }
+            false => format!("type {}", self.signature()),
+        }
+    }
+
+    fn ast(&self) -> AstNode {
+        let fields: Vec<AstNode> = self.fields.iter().map(DecArg::ast).collect();
+
+        AstNode::new("TypeDec", self.location())
+            .with_attr("name", self.name.as_str())
+            .with_attr(
+                "generics",
+                self.generics.iter().map(Json::from).collect::<Vec<_>>(),
+            )
+            .with_child("fields", fields)
+            .with_child(
+                "variants",
+                self.variants.iter().map(TypeDec::ast).collect::<Vec<_>>(),
+            )
+    }
+
     fn location(&self) -> Option<&SpanTuple> {
         self.location.as_ref()
     }
@@ -102,10 +235,77 @@ impl Instruction for TypeDec {
 
 impl TypeCheck for TypeDec {
     fn resolve_type(&mut self, ctx: &mut TypeCtx) -> CheckedType {
+        for variant in self.variants.iter_mut() {
+            variant.type_of(ctx);
+        }
+
+        let generics = self.generics.clone();
+        for field in self.fields.iter_mut() {
+            // Generic types only get resolved once they are specialized
+            if generics.is_empty() {
+                match ctx.resolve_concrete_type(field.get_type()) {
+                    Ok(ty) => field.set_type(ty),
+                    Err(e) => {
+                        ctx.error(e.with_loc(field.location().cloned()));
+                        continue;
+                    }
+                }
+            }
+
+            // FIXME: Default values of generic fields can only be checked once the
+            // type is specialized
+            if generics.contains(field.get_type()) {
+                continue;
+            }
+
+            let expected_ty = CheckedType::Resolved(field.get_type().clone());
+            let default_ty = match field.default_value_mut() {
+                Some(value) => value.type_of(ctx),
+                None => continue,
+            };
+
+            if default_ty != expected_ty {
+                ctx.error(
+                    Error::new(ErrKind::TypeChecker)
+                        .with_msg(format!(
+                            "default value of type `{}` given to field `{}` of type `{}`",
+                            default_ty,
+                            field.name(),
+                            expected_ty
+                        ))
+                        .with_loc(field.location().cloned()),
+                );
+            }
+        }
+
         if let Err(e) = ctx.declare_custom_type(self.name.clone(), self.clone()) {
             ctx.error(e);
         }
 
+        if generics.is_empty() {
+            for field in self.fields.iter() {
+                if let Some(path) = self.find_cycle(field, ctx) {
+                    ctx.error(
+                        Error::new(ErrKind::TypeChecker)
+                            .with_msg(format!(
+                                "recursive type `{}` has an infinite size",
+                                generics::display_name(&self.name)
+                            ))
+                            .with_loc(field.location().cloned())
+                            .with_hint(Error::hint().with_msg(format!(
+                                "field `{}` contains the type itself: {}",
+                                field.name(),
+                                path.iter()
+                                    .map(|ty| generics::display_name(ty))
+                                    .collect::<Vec<String>>()
+                                    .join(" -> ")
+                            ))),
+                    );
+                    break;
+                }
+            }
+        }
+
         CheckedType::Void
     }
 
@@ -164,6 +364,8 @@ impl From<String> for TypeDec {
             name: type_name,
             generics: vec![],
             fields: vec![],
+            variants: vec![],
+            variant_of: None,
             typechecked: false,
             location: None,
         }
@@ -188,6 +390,23 @@ mod tests {
         };
     }
 
+    #[test]
+    fn tc_valid_default_values() {
+        jinko! {
+            type Point(x: int = 0, y: int = 0);
+            origin = Point();
+            p = Point(x: 15);
+            q = Point(y: 14);
+        };
+    }
+
+    #[test]
+    fn tc_invalid_default_value() {
+        jinko_fail! {
+            type Point(x: int = "zero", y: int = 0);
+        };
+    }
+
     #[test]
     fn tc_valid_hard() {
         jinko! {
@@ -216,4 +435,165 @@ mod tests {
             v = Vector2(v0: 15, v1: zero());
         };
     }
+
+    #[test]
+    fn tc_valid_nested_types() {
+        let ctx = jinko! {
+            type Inner(x: int);
+            type Middle(inner: Inner, tag: string);
+            type Outer(middle: Middle, inner: Inner);
+
+            o = Outer(middle: Middle(inner: Inner(x: 1), tag: "jk"), inner: Inner(x: 2));
+            x = o.middle.inner.x;
+        };
+
+        let o = ctx.get_variable("o").unwrap().instance();
+
+        assert_eq!(o.size(), o.data().len());
+        assert_eq!(
+            o.display(),
+            "Outer(middle: Middle(inner: Inner(x: 1), tag: \"jk\"), inner: Inner(x: 2))"
+        );
+    }
+
+    #[test]
+    fn tc_invalid_recursive_type() {
+        jinko_fail! {
+            type Node(value: int, next: Node);
+        };
+    }
+
+    #[test]
+    fn tc_invalid_mutually_recursive_types() {
+        jinko_fail! {
+            type Even(odd: Odd);
+            type Odd(even: Even);
+        };
+    }
+
+    #[test]
+    fn tc_invalid_indirectly_recursive_type() {
+        jinko_fail! {
+            type Inner(outer: Outer);
+            type Middle(inner: Inner);
+            type Outer(middle: Middle);
+        };
+    }
+
+    #[test]
+    fn tc_invalid_recursive_generic_type() {
+        jinko_fail! {
+            type List[T](value: T, next: List[T]);
+            func head(l: List[int]) -> int { l.value }
+        };
+    }
+
+    #[test]
+    fn tc_valid_enum() {
+        let ctx = jinko! {
+            type Shape = Circle(r: float) | Square(side: float) | Empty;
+
+            func describe(s: Shape) -> Shape { s }
+
+            circle = describe(Circle(r: 1.5));
+            square = Square(2.0);
+            empty = Empty;
+        };
+
+        let circle = ctx.get_variable("circle").unwrap().instance();
+        let empty = ctx.get_variable("empty").unwrap().instance();
+
+        assert_eq!(circle.type_name(), "Shape");
+        assert_eq!(circle.variant(), Some("Circle"));
+        assert_eq!(circle.display(), "Circle(r: 1.5)");
+        assert_eq!(empty.variant(), Some("Empty"));
+        assert!(!circle.deep_eq(&empty));
+    }
+
+    #[test]
+    fn tc_valid_recursive_enum() {
+        jinko! {
+            type List = Cons(value: int, next: List) | Nil;
+
+            l = Cons(value: 1, next: Cons(value: 2, next: Nil));
+        };
+    }
+
+    #[test]
+    fn tc_invalid_enum_instantiation() {
+        jinko_fail! {
+            type Shape = Circle(r: float) | Empty;
+            s = Shape;
+        };
+    }
+
+    #[test]
+    fn tc_invalid_variant_field() {
+        jinko_fail! {
+            type Shape = Circle(r: float) | Empty;
+            s = Circle(r: 1);
+        };
+    }
+
+    #[test]
+    fn tc_invalid_variant_name_collision() {
+        let ctx = jinko_fail! {
+            type Circle(r: float);
+            type Shape = Circle(r: float) | Empty;
+        };
+
+        let err = &ctx.error_handler.errors()[0];
+        assert_eq!(
+            err.msg(),
+            Some("variant `Circle` of `Shape` has the same name as an existing type")
+        );
+        assert_eq!(err.hints()[0].msg(), Some("previous declaration here"));
+    }
+
+    #[test]
+    fn tc_invalid_variant_shared_by_enums() {
+        let ctx = jinko_fail! {
+            type S = A | B;
+            type T = A | C;
+        };
+
+        let err = &ctx.error_handler.errors()[0];
+        assert_eq!(
+            err.msg(),
+            Some("variant `A` of `T` has the same name as an existing type")
+        );
+        assert_eq!(
+            err.hints()[0].msg(),
+            Some("`A` previously declared as a variant of `S`")
+        );
+    }
+
+    #[test]
+    fn tc_invalid_type_redeclaration() {
+        let ctx = jinko_fail! {
+            type P(a: int);
+            type P(b: int);
+        };
+
+        assert_eq!(
+            ctx.error_handler.errors()[0].msg(),
+            Some("type `P` is already declared")
+        );
+    }
+
+    #[test]
+    fn tc_valid_concrete_generic_field() {
+        jinko! {
+            type Bag(items: Vec[int]);
+            b = Bag(items: vec[int]().push[int](15));
+        };
+    }
+
+    #[test]
+    fn tc_invalid_concrete_generic_field() {
+        jinko_fail! {
+            type Bag(items: Vec[int]);
+            b = Bag(items: vec[string]());
+        };
+    }
 }
@@ -1,11 +1,17 @@
 //! TypeInstantiations are used when instantiating a type. The argument list is given to the
 //! type on execution.
+//!
+//! Fields are given by name and in any order: `Point(y: 2, x: 1)` is the same as
+//! `Point(x: 1, y: 2)`. Each declared field must be given exactly once, unless it has
+//! a default value, in which case it can be omitted. The resulting instance's data is
+//! laid out in the order of the declaration.
 
+use crate::ast::{AstNode, Json};
 use crate::context::Context;
 use crate::error::{ErrKind, Error};
 use crate::generics::{self, GenericExpander, GenericMap, GenericUser};
 use crate::instance::{Name, ObjectInstance};
-use crate::instruction::{InstrKind, Instruction, TypeDec, VarAssign};
+use crate::instruction::{DecArg, InstrKind, Instruction, TypeDec, VarAssign};
 use crate::location::SpanTuple;
 use crate::symbol::Symbol;
 use crate::typechecker::{CheckedType, SpecializedNode, TypeCheck, TypeCtx, TypeId};
@@ -65,19 +71,90 @@ impl TypeInstantiation {
         }
     }
 
-    /// Check if the fields received and the fields expected match
-    fn check_fields_count(&self, type_dec: &TypeDec) -> Result<(), Error> {
-        match self.fields().len() == type_dec.fields().len() {
-            true => Ok(()),
-            false => Err(Error::new(ErrKind::Context).with_msg(format!(
-                "Wrong number of arguments \
-                    for type instantiation `{}`: Expected {}, got {}",
-                self.name().id(),
-                type_dec.fields().len(),
-                self.fields().len()
-            ))),
-            // FIXME: Add hint with typedec here
+    /// Check that the fields received and the fields expected match: Each given field
+    /// must be declared and given only once, and each declared field must either be
+    /// given or have a default value
+    fn check_fields(&self, type_dec: &TypeDec, kind: ErrKind) -> Vec<Error> {
+        if type_dec.is_enum() {
+            let variants: Vec<&str> = type_dec.variants().iter().map(TypeDec::name).collect();
+
+            return vec![Error::new(kind)
+                .with_msg(format!(
+                    "enum `{}` cannot be instantiated directly",
+                    type_dec.name()
+                ))
+                .with_loc(self.location.clone())
+                .with_hint(Error::hint().with_msg(format!(
+                    "instantiate one of its variants instead: {}",
+                    variants.join(", ")
+                )))];
         }
+
+        let mut errors = vec![];
+        let type_hint = || {
+            Error::hint()
+                .with_msg(format!("type `{}` declared here", type_dec.name()))
+                .with_loc(type_dec.location().cloned())
+        };
+
+        for (idx, field) in self.fields.iter().enumerate() {
+            if !type_dec
+                .fields()
+                .iter()
+                .any(|field_dec| field_dec.name() == field.symbol())
+            {
+                errors.push(
+                    Error::new(kind)
+                        .with_msg(format!(
+                            "unknown field `{}` for type `{}`",
+                            field.symbol(),
+                            type_dec.name()
+                        ))
+                        .with_loc(field.location().cloned())
+                        .with_hint(type_hint()),
+                );
+            } else if self.fields[..idx]
+                .iter()
+                .any(|previous| previous.symbol() == field.symbol())
+            {
+                errors.push(
+                    Error::new(kind)
+                        .with_msg(format!(
+                            "field `{}` is given more than once",
+                            field.symbol()
+                        ))
+                        .with_loc(field.location().cloned()),
+                );
+            }
+        }
+
+        for field_dec in type_dec.fields() {
+            if field_dec.default_value().is_none() && self.get_field(field_dec).is_none() {
+                errors.push(
+                    Error::new(kind)
+                        .with_msg(format!(
+                            "missing field `{}` in instantiation of type `{}`",
+                            field_dec.name(),
+                            type_dec.name()
+                        ))
+                        .with_loc(self.location.clone())
+                        .with_hint(
+                            Error::hint()
+                                .with_msg(format!("field `{}` declared here", field_dec.name()))
+                                .with_loc(field_dec.location().cloned()),
+                        ),
+                );
+            }
+        }
+
+        errors
+    }
+
+    /// Get the field given to the instantiation for a declared field, if any
+    fn get_field(&self, field_dec: &DecArg) -> Option<&VarAssign> {
+        self.fields
+            .iter()
+            .find(|field| field.symbol() == field_dec.name())
     }
 
     pub fn set_generics(&mut self, generics: Vec<TypeId>) {
@@ -124,14 +201,21 @@ impl Instruction for TypeInstantiation {
     }
 
     fn print(&self) -> String {
-        let mut base = format!("{}(", self.type_name.id());
+        let mut base = self.type_name.print();
+
+        if !self.generics.is_empty() {
+            let generics: Vec<String> = self.generics.iter().map(TypeId::print).collect();
+            base = format!("{}[{}]", base, generics.join(", "));
+        }
+
+        base.push('(');
         let mut first_arg = true;
         for arg in &self.fields {
             if !first_arg {
                 base.push_str(", ");
             }
 
-            base.push_str(&arg.print());
+            base.push_str(&format!("{}: {}", arg.symbol(), arg.value().print()));
 
             first_arg = false;
         }
@@ -139,37 +223,73 @@ impl Instruction for TypeInstantiation {
         format!("{})", base)
     }
 
+    fn ast(&self) -> AstNode {
+        let fields: Vec<AstNode> = self.fields.iter().map(|field| field.ast()).collect();
+
+        AstNode::new("TypeInstantiation", self.location())
+            .with_attr("type", &self.type_name)
+            .with_attr(
+                "generics",
+                self.generics.iter().map(Json::from).collect::<Vec<_>>(),
+            )
+            .with_child("fields", fields)
+    }
+
     fn execute(&self, ctx: &mut Context) -> Option<ObjectInstance> {
         let type_dec = self.get_declaration(ctx)?;
 
-        if let Err(e) = self.check_fields_count(&type_dec) {
-            ctx.error(e);
+        let errors = self.check_fields(&type_dec, ErrKind::Context);
+        if !errors.is_empty() {
+            errors.into_iter().for_each(|err| ctx.error(err));
             return None;
         }
 
-        let mut size: usize = 0;
-        let mut data: Vec<u8> = Vec::new();
+        // Evaluate the fields in the order they were given in
+        let mut values = Vec::with_capacity(self.fields.len());
+        for field in self.fields.iter() {
+            values.push((field.symbol(), field.value().execute_expression(ctx)?));
+        }
+
         let mut fields: Vec<(Name, ObjectInstance)> = Vec::new();
-        for (_, named_arg) in self.fields.iter().enumerate() {
-            // FIXME: Need to assign the correct field to the field that corresponds
-            // in the typedec
-            let field_instr = named_arg.value();
-            let field_name = named_arg.symbol();
+        for field_dec in type_dec.fields() {
+            let instance = match values
+                .iter()
+                .position(|(name, _)| *name == field_dec.name())
+            {
+                Some(idx) => values.swap_remove(idx).1,
+                // Fields which are not given have been checked to have a default value
+                None => field_dec.default_value()?.execute_expression(ctx)?,
+            };
+
+            // Instances built at runtime might not have been checked by the typechecker
+            let expected_ty = CheckedType::Resolved(field_dec.get_type().clone());
+            if instance.ty() != &expected_ty {
+                ctx.error(
+                    Error::new(ErrKind::Context)
+                        .with_msg(format!(
+                            "trying to assign value of type `{}` to field of type `{}`",
+                            instance.ty(),
+                            field_dec.get_type()
+                        ))
+                        .with_loc(self.location.clone()),
+                );
+                return None;
+            }
 
-            let instance = field_instr.execute_expression(ctx)?;
-            size += instance.size();
+            fields.push((field_dec.name().to_string(), instance));
+        }
+
+        let instance =
+            ObjectInstance::from_fields(CheckedType::Resolved(type_dec.instance_type()), fields);
 
-            data.append(&mut instance.data().to_vec());
-            fields.push((field_name.to_string(), instance));
+        match type_dec.variant_of() {
+            Some(_) => Some(instance.with_variant(type_dec.name().to_string())),
+            None => Some(instance),
         }
+    }
 
-        Some(ObjectInstance::new(
-            // FIXME: Disgusting, maybe do not use Rc for TypeId?
-            CheckedType::Resolved((*type_dec).clone().into()),
-            size,
-            data,
-            Some(fields),
-        ))
+    fn location(&self) -> Option<&SpanTuple> {
+        self.location.as_ref()
     }
 }
 
@@ -195,8 +315,21 @@ impl TypeCheck for TypeInstantiation {
             return self.resolve_generic_instantiation(dec, ctx);
         }
 
+        let errors = self.check_fields(&dec, ErrKind::TypeChecker);
+        if !errors.is_empty() {
+            errors.into_iter().for_each(|err| ctx.error(err));
+            return CheckedType::Error;
+        }
+
         let mut errors = vec![];
-        for (field_dec, var_assign) in dec.fields().iter().zip(self.fields.iter_mut()) {
+        for var_assign in self.fields.iter_mut() {
+            // Every given field has been checked to be declared
+            let field_dec = dec
+                .fields()
+                .iter()
+                .find(|field_dec| field_dec.name() == var_assign.symbol())
+                .unwrap();
+
             let expected_ty = CheckedType::Resolved(field_dec.get_type().clone());
             let value_ty = var_assign.value_mut().type_of(ctx);
             if expected_ty != value_ty {
@@ -221,7 +354,7 @@ impl TypeCheck for TypeInstantiation {
         // Propagate all errors at once in the context
         errors.into_iter().for_each(|err| ctx.error(err));
 
-        CheckedType::Resolved(self.type_name.clone())
+        CheckedType::Resolved(dec.instance_type())
     }
 
     fn set_cached_type(&mut self, ty: CheckedType) {
@@ -401,6 +534,134 @@ mod test {
         );
     }
 
+    #[test]
+    fn t_layout_follows_declaration() {
+        use crate::instance::ToObjectInstance;
+        use crate::jk_execute;
+        use crate::value::JkInt;
+
+        let instance = jk_execute! {
+            type Point(x: int, y: int);
+            Point(x: 1, y: 2)
+        }
+        .unwrap();
+
+        assert_eq!(
+            instance.get_field("x").unwrap(),
+            JkInt::from(1).to_instance()
+        );
+        assert_eq!(
+            instance.get_field("y").unwrap(),
+            JkInt::from(2).to_instance()
+        );
+        assert_eq!(
+            instance.data(),
+            [
+                JkInt::from(1).to_instance().data(),
+                JkInt::from(2).to_instance().data()
+            ]
+            .concat()
+        );
+    }
+
+    #[test]
+    fn tc_unknown_field_name() {
+        jinko_fail! {
+            type Point(x: int, y: int);
+            p = Point(x: 1, z: 2);
+        };
+    }
+
+    #[test]
+    fn t_default_values() {
+        use crate::instance::ToObjectInstance;
+        use crate::jk_execute;
+        use crate::value::JkInt;
+
+        let instance = jk_execute! {
+            type Point(x: int, y: int = 1 + 1);
+            Point(x: 1)
+        }
+        .unwrap();
+
+        assert_eq!(
+            instance.get_field("x").unwrap(),
+            JkInt::from(1).to_instance()
+        );
+        assert_eq!(
+            instance.get_field("y").unwrap(),
+            JkInt::from(2).to_instance()
+        );
+    }
+
+    #[test]
+    fn t_fields_in_any_order() {
+        use crate::instance::ToObjectInstance;
+        use crate::jk_execute;
+        use crate::value::JkInt;
+
+        let instance = jk_execute! {
+            type Point(x: int = 0, y: int, z: int);
+            Point(z: 3, y: 2)
+        }
+        .unwrap();
+
+        assert_eq!(
+            instance.data(),
+            [
+                JkInt::from(0).to_instance().data(),
+                JkInt::from(2).to_instance().data(),
+                JkInt::from(3).to_instance().data()
+            ]
+            .concat()
+        );
+    }
+
+    #[test]
+    fn tc_duplicate_field() {
+        jinko_fail! {
+            type Point(x: int, y: int);
+            p = Point(x: 1, x: 2, y: 3);
+        };
+    }
+
+    #[test]
+    fn tc_missing_field_without_default() {
+        jinko_fail! {
+            type Point(x: int = 0, y: int);
+            p = Point(x: 1);
+        };
+    }
+
+    #[test]
+    fn tc_wrong_number_of_fields() {
+        jinko_fail! {
+            type Point(x: int, y: int);
+            p = Point(x: 1);
+        };
+    }
+
+    #[test]
+    fn t_unknown_field_name_at_runtime() {
+        use super::super::DecArg;
+        use crate::value::JkInt;
+
+        let mut ctx = Context::new();
+
+        let fields = vec![DecArg::new("a".to_owned(), TypeId::from("int"))];
+        TypeDec::new("Type_Test".to_owned(), vec![], fields).execute(&mut ctx);
+
+        let mut t_inst = TypeInstantiation::new(TypeId::from("Type_Test"));
+        t_inst.add_field(VarAssign::new(
+            false,
+            "b".to_string(),
+            Box::new(JkInt::from(12)),
+        ));
+
+        assert!(t_inst.execute(&mut ctx).is_none());
+        assert!(ctx.error_handler.has_errors());
+    }
+
     #[test]
     fn t_instantiate_primitive() {
         jinko_fail! {
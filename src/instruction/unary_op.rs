@@ -0,0 +1,259 @@
+//! Unary operations apply an operation on a single Instruction. When writing `-x`, a
+//! UnaryOp will be created containing `x` as its operand and `-` as the operator.
+//!
+//! The available operators are `-` and `!`. That is `Negate` and `Not`. Negation is
+//! only available on numbers, while `!` can only be applied to booleans.
+
+use crate::ast::AstNode;
+use crate::context::Context;
+use crate::error::{ErrKind, Error};
+use crate::generics::{GenericMap, GenericUser};
+use crate::instance::{FromObjectInstance, ObjectInstance, ToObjectInstance};
+use crate::instruction::{print_receiver, InstrKind, Instruction};
+use crate::location::SpanTuple;
+use crate::typechecker::{CheckedType, TypeCheck, TypeCtx, TypeId};
+use crate::value::{JkBool, JkFloat, JkInt};
+
+/// All the unary operators available
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnaryOperator {
+    Negate,
+    Not,
+}
+
+impl UnaryOperator {
+    /// Create a new unary operator from a given character
+    pub fn new(op_str: &str) -> UnaryOperator {
+        match op_str {
+            "-" => UnaryOperator::Negate,
+            "!" => UnaryOperator::Not,
+            _ => unreachable!("Invalid unary operator: {}", op_str),
+        }
+    }
+
+    /// Return the operator's representation
+    pub fn as_str(&self) -> &str {
+        match self {
+            UnaryOperator::Negate => "-",
+            UnaryOperator::Not => "!",
+        }
+    }
+}
+
+/// The `UnaryOp` struct contains an expression and the operator to apply to it
+#[derive(Clone)]
+pub struct UnaryOp {
+    operand: Box<dyn Instruction>,
+    op: UnaryOperator,
+    cached_type: Option<CheckedType>,
+    location: Option<SpanTuple>,
+}
+
+impl UnaryOp {
+    /// Create a new `UnaryOp` from an instruction and an operator
+    pub fn new(operand: Box<dyn Instruction>, op: UnaryOperator) -> UnaryOp {
+        UnaryOp {
+            operand,
+            op,
+            cached_type: None,
+            location: None,
+        }
+    }
+
+    /// Return the operator used by the UnaryOp
+    pub fn operator(&self) -> UnaryOperator {
+        self.op
+    }
+
+    /// Get a reference on the operand of the UnaryOp
+    pub fn operand(&self) -> &dyn Instruction {
+        &*self.operand
+    }
+
+    pub fn set_location(&mut self, location: SpanTuple) {
+        self.location = Some(location)
+    }
+}
+
+impl Instruction for UnaryOp {
+    fn kind(&self) -> InstrKind {
+        InstrKind::Expression(None)
+    }
+
+    fn print(&self) -> String {
+        format!("{}{}", self.op.as_str(), print_receiver(&*self.operand))
+    }
+
+    fn ast(&self) -> AstNode {
+        AstNode::new("UnaryOp", self.location())
+            .with_attr("operator", self.op.as_str())
+            .with_child("operand", self.operand.ast())
+    }
+
+    fn execute(&self, ctx: &mut Context) -> Option<ObjectInstance> {
+        let value = self.operand.execute_expression(ctx)?;
+
+        // At this point, the typechecker will have made sure that the operand is of
+        // a type compatible with the operator
+        match (self.op, value.ty()) {
+            (UnaryOperator::Negate, CheckedType::Resolved(ty)) if ty.id() == "int" => {
+                let int = JkInt::from_instance(&value).rust_value();
+
+                // The smallest integer has no positive counterpart
+                match int.checked_neg() {
+                    Some(negated) => Some(JkInt::from(negated).to_instance()),
+                    None => {
+                        ctx.error(
+                            Error::new(ErrKind::Context)
+                                .with_msg(format!("integer overflow: -({})", int))
+                                .with_loc(self.location.clone()),
+                        );
+                        None
+                    }
+                }
+            }
+            (UnaryOperator::Negate, CheckedType::Resolved(ty)) if ty.id() == "float" => {
+                Some(JkFloat::from(-JkFloat::from_instance(&value).rust_value()).to_instance())
+            }
+            (UnaryOperator::Not, CheckedType::Resolved(ty)) if ty.id() == "bool" => {
+                Some(JkBool::from(!JkBool::from_instance(&value).rust_value()).to_instance())
+            }
+            _ => unreachable!("attempting unary operation on invalid type AFTER typechecking"),
+        }
+    }
+
+    fn location(&self) -> Option<&SpanTuple> {
+        self.location.as_ref()
+    }
+}
+
+impl TypeCheck for UnaryOp {
+    fn resolve_type(&mut self, ctx: &mut TypeCtx) -> CheckedType {
+        let operand_ty = self.operand.type_of(ctx);
+
+        let valid = match (&self.op, &operand_ty) {
+            (UnaryOperator::Negate, CheckedType::Resolved(ty)) => {
+                ty.id() == "int" || ty.id() == "float"
+            }
+            (UnaryOperator::Not, CheckedType::Resolved(ty)) => ty.id() == "bool",
+            _ => false,
+        };
+
+        if !valid {
+            ctx.error(
+                Error::new(ErrKind::TypeChecker)
+                    .with_msg(format!(
+                        "trying to do unary operation on invalid type: {}{}",
+                        self.op.as_str(),
+                        operand_ty,
+                    ))
+                    .with_loc(self.location.clone()),
+            );
+            return CheckedType::Error;
+        }
+
+        match self.op {
+            UnaryOperator::Not => CheckedType::Resolved(TypeId::from("bool")),
+            UnaryOperator::Negate => operand_ty,
+        }
+    }
+
+    fn set_cached_type(&mut self, ty: CheckedType) {
+        self.cached_type = Some(ty)
+    }
+
+    fn cached_type(&self) -> Option<&CheckedType> {
+        self.cached_type.as_ref()
+    }
+}
+
+impl GenericUser for UnaryOp {
+    fn resolve_usages(&mut self, type_map: &GenericMap, ctx: &mut TypeCtx) {
+        self.operand.resolve_usages(type_map, ctx);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{jinko, jinko_fail, jk_execute};
+
+    #[test]
+    fn t_print() {
+        let neg = UnaryOp::new(Box::new(JkInt::from(12)), UnaryOperator::Negate);
+        let not = UnaryOp::new(Box::new(JkBool::from(true)), UnaryOperator::Not);
+
+        assert_eq!(neg.print(), "-12");
+        assert_eq!(not.print(), "!true");
+    }
+
+    #[test]
+    fn t_execute_negate() {
+        assert_eq!(
+            jk_execute!(x = 14; -x),
+            Some(JkInt::from(-14).to_instance())
+        );
+        assert_eq!(
+            jk_execute!(x = 1.5; -x),
+            Some(JkFloat::from(-1.5).to_instance())
+        );
+    }
+
+    #[test]
+    fn t_execute_negate_overflow() {
+        let mut ctx = jinko! {
+            x = -9223372036854775807 - 1;
+        };
+
+        assert!(ctx.eval("-x").is_err());
+        assert_eq!(
+            ctx.error_handler.errors()[0].msg(),
+            Some("integer overflow: -(-9223372036854775808)")
+        );
+        assert!(ctx.error_handler.errors()[0].loc().is_some());
+    }
+
+    #[test]
+    fn t_execute_not() {
+        assert_eq!(
+            jk_execute!(flag = false; !flag),
+            Some(JkBool::from(true).to_instance())
+        );
+        assert_eq!(
+            jk_execute!(!(1 < 2)),
+            Some(JkBool::from(false).to_instance())
+        );
+    }
+
+    #[test]
+    fn t_execute_in_binop() {
+        assert_eq!(
+            jk_execute!(x = 4; 10 - -x * 2),
+            Some(JkInt::from(18).to_instance())
+        );
+    }
+
+    #[test]
+    fn tc_valid_unary() {
+        jinko! {
+            i = -(4 + 2);
+            f = -1.4;
+            b = !true;
+        };
+    }
+
+    #[test]
+    fn tc_invalid_negate() {
+        jinko_fail! {
+            s = "jinko";
+            -s;
+        };
+    }
+
+    #[test]
+    fn tc_invalid_not() {
+        jinko_fail! {
+            !15;
+        };
+    }
+}
@@ -3,6 +3,7 @@
 //! need to keep an option of an instance. A variable is either there, fully initialized,
 //! or it's not.
 
+use crate::ast::AstNode;
 use crate::context::Context;
 use crate::error::{ErrKind, Error};
 use crate::generics::GenericUser;
@@ -83,6 +84,12 @@ impl Instruction for Var {
         format!("{} = {}", base, self.instance.as_string())
     }
 
+    fn ast(&self) -> AstNode {
+        AstNode::new("Var", self.location())
+            .with_attr("name", self.name.as_str())
+            .with_attr("mutable", self.mutable)
+    }
+
     fn execute(&self, ctx: &mut Context) -> Option<ObjectInstance> {
         let var = match ctx.get_variable(self.name()) {
             Some(v) => v,
@@ -96,6 +103,8 @@ impl Instruction for Var {
             }
         };
 
+        ctx.trace(|| format!("read {} = {}", self.name, var.instance().display()));
+
         Some(var.instance())
     }
 
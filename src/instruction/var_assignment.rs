@@ -1,5 +1,18 @@
 //! The VarAssign struct is used when assigning values to variables.
-
+//!
+//! An assignment either declares a new variable or modifies an existing one:
+//!
+//! - `x = 1` declares `x` if no variable of that name is visible
+//! - Assigning to a variable of the current scope modifies it, which is only allowed
+//!   if it was declared as mutable: `mut x = 1; x = 2` is valid, `x = 1; x = 2` is not.
+//!   Declaring it again with `mut x = 2` is an error as well
+//! - Assigning to a mutable variable of an enclosing scope modifies it
+//! - Assigning to an immutable variable of an enclosing scope, or declaring a variable
+//!   with `mut` when one with the same name exists in an enclosing scope, declares a
+//!   new variable which shadows the outer one until the end of the current scope. A
+//!   warning is emitted in that case, since it is easy to mistake for a modification
+
+use crate::ast::AstNode;
 use crate::context::Context;
 use crate::error::{ErrKind, Error};
 use crate::generics::{GenericMap, GenericUser};
@@ -62,6 +75,21 @@ impl VarAssign {
     pub fn set_location(&mut self, location: SpanTuple) {
         self.location = Some(location)
     }
+
+    /// Does the assignment target an existing, visible variable, or does it declare a
+    /// new one? Variables of the current scope are always assigned to, while
+    /// variables of enclosing scopes are only assigned to if they are mutable and the
+    /// assignment is not a declaration itself. Otherwise, they get shadowed.
+    fn assigns_existing(&self, existing_mutable: bool, in_current_scope: bool) -> bool {
+        in_current_scope || (existing_mutable && !self.mutable)
+    }
+
+    /// Hint pointing to the declaration of the variable the assignment refers to
+    fn declaration_hint(&self, ctx: &TypeCtx) -> Error {
+        Error::hint()
+            .with_msg(format!("`{}` previously declared here", self.symbol))
+            .with_loc(ctx.var_location(&self.symbol).cloned())
+    }
 }
 
 impl Instruction for VarAssign {
@@ -78,42 +106,73 @@ impl Instruction for VarAssign {
         format!("{}{} = {}", base, self.symbol, self.value.print())
     }
 
-    fn execute(&self, ctx: &mut Context) -> Option<ObjectInstance> {
-        // Are we creating the variable or not
-        let mut var_creation = false;
+    fn ast(&self) -> AstNode {
+        AstNode::new("VarAssign", self.location())
+            .with_attr("name", self.symbol.as_str())
+            .with_attr("mutable", self.mutable)
+            .with_child("value", self.value.ast())
+    }
 
-        let mut var = match ctx.get_variable(&self.symbol) {
-            Some(v) => v.clone(),
-            None => {
+    fn execute(&self, ctx: &mut Context) -> Option<ObjectInstance> {
+        let var = match ctx.get_variable(&self.symbol) {
+            Some(v)
+                if self.assigns_existing(
+                    v.mutable(),
+                    ctx.is_variable_in_current_scope(&self.symbol),
+                ) =>
+            {
+                v.clone()
+            }
+            // The variable does not exist yet, or is shadowed by the new declaration
+            _ => {
                 let mut new_v = Var::new(self.symbol().to_string());
                 new_v.set_mutable(self.mutable());
+                if let Some(location) = &self.location {
+                    new_v.set_location(location.clone());
+                }
+                new_v.set_instance(self.value.execute_expression(ctx)?);
 
-                var_creation = true;
+                ctx.trace(|| format!("write {} = {}", self.symbol, new_v.instance().display()));
 
-                new_v
-            }
-        };
+                if let Err(e) = ctx.add_variable(new_v) {
+                    ctx.error(e.with_loc(self.location.clone()));
+                }
 
-        match (var_creation, var.mutable()) {
-            (false, false) => {
-                // The variable already exists. So we need to error out if it isn't
-                // mutable
-                ctx.error(
-                    Error::new(ErrKind::Context)
-                        .with_msg(format!(
-                            "trying to assign value to non mutable variable `{}`: `{}`",
-                            var.name(),
-                            self.value.print()
-                        ))
-                        .with_loc(self.location.clone()),
-                );
+                // A variable assignment is always a statement
                 return None;
             }
-            (true, _) | (_, true) => var.set_instance(self.value.execute_expression(ctx)?),
+        };
+
+        if !var.mutable() {
+            // The variable already exists. So we need to error out if it isn't
+            // mutable
+            ctx.error(
+                Error::new(ErrKind::Context)
+                    .with_msg(format!(
+                        "trying to assign value to non mutable variable `{}`: `{}`",
+                        var.name(),
+                        self.value.print()
+                    ))
+                    .with_loc(self.location.clone())
+                    .with_hint(
+                        Error::hint()
+                            .with_msg(format!("variable `{}` declared here", var.name()))
+                            .with_loc(var.location().cloned()),
+                    )
+                    .with_hint(Error::hint().with_msg(format!(
+                        "declare the variable as `mut {}` to modify it",
+                        var.name()
+                    ))),
+            );
+            return None;
         }
 
-        // We can unwrap safely since we checked that the variable does not
-        // exist
+        let mut var = var;
+        var.set_instance(self.value.execute_expression(ctx)?);
+
+        ctx.trace(|| format!("write {} = {}", self.symbol, var.instance().display()));
+
+        // We can unwrap safely since we checked that the variable exists
         ctx.replace_variable(var).unwrap();
 
         // A variable assignment is always a statement
@@ -127,30 +186,95 @@ impl Instruction for VarAssign {
 
 impl TypeCheck for VarAssign {
     fn resolve_type(&mut self, ctx: &mut TypeCtx) -> CheckedType {
-        let var_ty = match ctx.get_var(&self.symbol) {
-            // FIXME: Remove clone?
-            Some(checked_ty) => {
+        // Closures capture variables by value: Assigning to a mutable variable they
+        // captured would only modify their own copy of it
+        if !self.mutable && ctx.is_var_captured(&self.symbol) && ctx.is_var_mutable(&self.symbol) {
+            ctx.error(
+                Error::new(ErrKind::TypeChecker)
+                    .with_msg(format!(
+                        "cannot assign to variable `{}` captured by a closure",
+                        self.symbol
+                    ))
+                    .with_loc(self.location.clone())
+                    .with_hint(Error::hint().with_msg(String::from(
+                        "closures capture variables by value when they are created",
+                    ))),
+            );
+            return CheckedType::Error;
+        }
+
+        // Global variables are visible from all functions, but can only be modified
+        // from them if they were declared as mutable
+        if !self.mutable && ctx.is_var_global(&self.symbol) && !ctx.is_var_mutable(&self.symbol) {
+            ctx.error(
+                Error::new(ErrKind::TypeChecker)
+                    .with_msg(format!(
+                        "cannot assign to non mutable global variable `{}`",
+                        self.symbol
+                    ))
+                    .with_loc(self.location.clone())
+                    .with_hint(Error::hint().with_msg(format!(
+                        "declare the global variable as `mut {}` to modify it",
+                        self.symbol
+                    ))),
+            );
+            return CheckedType::Error;
+        }
+
+        let existing = ctx.get_assigned_var(&self.symbol).cloned();
+        let in_current_scope = ctx.is_var_in_current_scope(&self.symbol);
+
+        let var_ty = match existing {
+            Some(checked_ty)
+                if self.assigns_existing(ctx.is_var_mutable(&self.symbol), in_current_scope) =>
+            {
                 // If `self` is mutable, then it means that we are creating the variable
                 // for the first time. However, we entered the match arm because the variable
-                // is already present in the context. Error out appropriately.
+                // is already present in the current scope. Error out appropriately.
                 if self.mutable() {
                     let err_msg = format!(
                         "trying to redefine already defined variable: {}",
                         self.symbol()
                     );
 
-                    // FIXME: Add hint here about previous definition
                     ctx.error(
                         Error::new(ErrKind::TypeChecker)
                             .with_msg(err_msg)
-                            .with_loc(self.location.clone()),
+                            .with_loc(self.location.clone())
+                            .with_hint(self.declaration_hint(ctx)),
                     );
                     return CheckedType::Error;
                 }
 
-                checked_ty.clone()
+                if !ctx.is_var_mutable(&self.symbol) {
+                    let err = Error::new(ErrKind::TypeChecker)
+                        .with_msg(format!(
+                            "cannot assign twice to non mutable variable {}",
+                            ctx.symbols().display(&self.symbol)
+                        ))
+                        .with_loc(self.location.clone());
+
+                    // Prefixed names of included variables refer to a copy of them
+                    let err = match ctx.symbols().get(&self.symbol) {
+                        Some(symbol) => err.with_hint(Error::hint().with_msg(format!(
+                            "variables included from `{}` can only be modified by their source",
+                            symbol.module()
+                        ))),
+                        None => err.with_hint(self.declaration_hint(ctx)).with_hint(
+                            Error::hint().with_msg(format!(
+                                "declare the variable as `mut {}` to modify it",
+                                self.symbol
+                            )),
+                        ),
+                    };
+
+                    ctx.error(err);
+                    return CheckedType::Error;
+                }
+
+                checked_ty
             }
-            None => {
+            shadowed => {
                 let instance_ty = self.value.type_of(ctx);
                 if instance_ty == CheckedType::Void {
                     ctx.error(
@@ -163,7 +287,25 @@ impl TypeCheck for VarAssign {
                     );
                     return CheckedType::Error;
                 }
-                if let Err(e) = ctx.declare_var(self.symbol.clone(), instance_ty) {
+
+                if shadowed.is_some() {
+                    ctx.error(
+                        Error::new(ErrKind::Warning)
+                            .with_msg(format!(
+                                "declaration of `{}` shadows a variable from an enclosing scope",
+                                self.symbol
+                            ))
+                            .with_loc(self.location.clone())
+                            .with_hint(self.declaration_hint(ctx)),
+                    );
+                }
+
+                if let Err(e) = ctx.declare_assigned_var(
+                    self.symbol.clone(),
+                    instance_ty,
+                    self.mutable,
+                    self.location.clone(),
+                ) {
                     ctx.error(e);
                 }
 
@@ -275,6 +417,50 @@ mod tests {
         assert!(i.error_handler.has_errors());
     }
 
+    #[test]
+    fn assign_immutable_locations() {
+        let mut ctx = Context::new();
+        crate::parser::parse(&mut ctx, "a = 13;\nb = 2;\na = 15", None).unwrap();
+        assert!(ctx.execute().is_err());
+
+        let errors = ctx.error_handler.errors();
+        let error = errors.first().unwrap();
+
+        assert_eq!(error.kind(), ErrKind::TypeChecker);
+        assert_eq!(error.loc().unwrap().start().line(), 3);
+        assert_eq!(error.hints()[0].loc().unwrap().start().line(), 1);
+    }
+
+    #[test]
+    fn redefine_mutable_locations() {
+        let mut ctx = Context::new();
+        crate::parser::parse(&mut ctx, "mut a = 13;\nmut a = 15", None).unwrap();
+        assert!(ctx.execute().is_err());
+
+        let errors = ctx.error_handler.errors();
+        let error = errors.first().unwrap();
+
+        assert_eq!(error.kind(), ErrKind::TypeChecker);
+        assert_eq!(error.loc().unwrap().start().line(), 2);
+        assert_eq!(error.hints()[0].loc().unwrap().start().line(), 1);
+    }
+
+    #[test]
+    fn shadowing_warning_locations() {
+        let mut ctx = Context::new();
+        crate::parser::parse(&mut ctx, "a = 13;\nb = {\n    a = 15;\n    a\n}", None).unwrap();
+        assert!(ctx.execute().is_ok());
+
+        let errors = ctx.error_handler.errors();
+        let warning = errors
+            .iter()
+            .find(|e| e.kind() == ErrKind::Warning)
+            .unwrap();
+
+        assert_eq!(warning.loc().unwrap().start().line(), 3);
+        assert_eq!(warning.hints()[0].loc().unwrap().start().line(), 1);
+    }
+
     #[test]
     fn create_mutable_twice() {
         jinko_fail! {
@@ -315,6 +501,43 @@ mod tests {
         );
     }
 
+    #[test]
+    fn assign_expressions() {
+        let ctx = jinko! {
+            func f(a: int) -> int { a + 1 }
+            x = f(2) * 3;
+            y = x;
+            z = { tmp = y - 1; tmp * 2 };
+            mut w = if z > 10 { z } else { 0 };
+            w = f(w) + f(x);
+        };
+
+        assert_eq!(
+            ctx.get_variable("x").unwrap().instance(),
+            JkInt::from(9).to_instance()
+        );
+        assert_eq!(
+            ctx.get_variable("y").unwrap().instance(),
+            JkInt::from(9).to_instance()
+        );
+        assert_eq!(
+            ctx.get_variable("z").unwrap().instance(),
+            JkInt::from(16).to_instance()
+        );
+        assert_eq!(
+            ctx.get_variable("w").unwrap().instance(),
+            JkInt::from(27).to_instance()
+        );
+    }
+
+    #[test]
+    fn assign_statement_expression() {
+        jinko_fail! {
+            func f() {}
+            x = f();
+        };
+    }
+
     #[test]
     fn generic_builtin_for_var_assign() {
         jinko! {
@@ -323,4 +546,186 @@ mod tests {
             int_size = size_of[int](a);
         };
     }
+
+    #[test]
+    fn block_variables_do_not_escape() {
+        jinko_fail! {
+            {
+                x = 1;
+            };
+            y = x;
+        };
+    }
+
+    #[test]
+    fn shadowing_in_inner_block() {
+        let ctx = jinko! {
+            x = 1;
+            y = {
+                x = "shadow";
+                x
+            };
+        };
+
+        assert_eq!(
+            ctx.get_variable("x").unwrap().instance(),
+            JkInt::from(1).to_instance()
+        );
+        assert_eq!(
+            ctx.get_variable("y").unwrap().instance(),
+            JkString::from("shadow").to_instance()
+        );
+        assert!(ctx
+            .error_handler
+            .errors()
+            .iter()
+            .any(|e| e.kind() == ErrKind::Warning));
+    }
+
+    #[test]
+    fn shadowing_mutable_declaration() {
+        let ctx = jinko! {
+            mut x = 1;
+            {
+                mut x = 2;
+                x = 3;
+            };
+        };
+
+        assert_eq!(
+            ctx.get_variable("x").unwrap().instance(),
+            JkInt::from(1).to_instance()
+        );
+    }
+
+    #[test]
+    fn assign_mutable_in_nested_blocks() {
+        let ctx = jinko! {
+            mut i = 0;
+            while i < 5 {
+                {
+                    i = i + 1;
+                };
+            }
+        };
+
+        assert_eq!(
+            ctx.get_variable("i").unwrap().instance(),
+            JkInt::from(5).to_instance()
+        );
+    }
+
+    #[test]
+    fn assign_immutable_in_same_scope() {
+        jinko_fail! {
+            x = 1;
+            x = 2;
+        };
+    }
+
+    #[test]
+    fn global_visible_in_function() {
+        let ctx = jinko! {
+            x = 12;
+            func get_x() -> int { x }
+            res = get_x();
+        };
+
+        assert_eq!(
+            ctx.get_variable("res").unwrap().instance(),
+            JkInt::from(12).to_instance()
+        );
+    }
+
+    #[test]
+    fn assign_immutable_global_in_function() {
+        jinko_fail! {
+            x = 12;
+            func change_global() {
+                x = 0;
+            }
+        };
+    }
+
+    #[test]
+    fn assign_immutable_global_in_function_block() {
+        jinko_fail! {
+            x = 12;
+            func change_global() {
+                { x = 0; }
+            }
+        };
+    }
+
+    #[test]
+    fn shadow_immutable_global_in_function() {
+        let ctx = jinko! {
+            x = 12;
+            func shadow_global() -> int {
+                mut x = 0;
+                x = x + 1;
+                x
+            }
+            res = shadow_global() + x;
+        };
+
+        assert_eq!(
+            ctx.get_variable("res").unwrap().instance(),
+            JkInt::from(13).to_instance()
+        );
+    }
+
+    #[test]
+    fn assign_argument_named_like_global() {
+        jinko! {
+            x = 12;
+            func f(x: int) -> int { y = x; y }
+            f(1);
+        };
+    }
+
+    #[test]
+    fn unused_variable_warns() {
+        let ctx = jinko! {
+            func f() {
+                unused = 1;
+            }
+            f();
+        };
+
+        assert!(ctx
+            .error_handler
+            .errors()
+            .iter()
+            .any(|e| e.kind() == ErrKind::Warning));
+    }
+
+    #[test]
+    fn reassigned_but_never_read_variable_warns() {
+        let ctx = jinko! {
+            func f() {
+                mut x = 1;
+                x = 2;
+            }
+            f();
+        };
+
+        assert!(ctx
+            .error_handler
+            .errors()
+            .iter()
+            .any(|e| e.kind() == ErrKind::Warning));
+    }
+
+    #[test]
+    fn unused_variable_with_underscore_does_not_warn() {
+        let ctx = jinko! {
+            func f() {
+                _unused = 1;
+            }
+            f();
+        };
+
+        assert!(ctx.error_handler.errors().is_empty());
+    }
 }
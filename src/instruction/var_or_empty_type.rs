@@ -1,16 +1,20 @@
+use crate::ast::AstNode;
 use crate::context::Context;
+use crate::error::{ErrKind, Error};
 use crate::generics::GenericUser;
-use crate::instance::ObjectInstance;
+use crate::instance::{ObjectInstance, ToObjectInstance};
 use crate::instruction::{InstrKind, Instruction, TypeInstantiation, Var};
 use crate::location::SpanTuple;
 use crate::symbol::Symbol;
 use crate::typechecker::{CheckedType, TypeCheck, TypeCtx, TypeId};
+use crate::value::JkFunc;
 
 #[derive(Clone, PartialEq)]
 enum Kind {
     Unknown,
     EmptyTypeInst,
     VarAccess,
+    FunctionRef,
 }
 
 #[derive(Clone)]
@@ -44,6 +48,12 @@ impl VarOrEmptyType {
             return Kind::VarAccess;
         }
 
+        let resolved = ctx.get_function(&self.symbol);
+        if resolved.is_some() {
+            ctx.use_function(&self.symbol);
+            return Kind::FunctionRef;
+        }
+
         Kind::Unknown
     }
 
@@ -61,6 +71,10 @@ impl Instruction for VarOrEmptyType {
         self.symbol.clone()
     }
 
+    fn ast(&self) -> AstNode {
+        AstNode::new("VarOrEmptyType", self.location()).with_attr("name", self.symbol.as_str())
+    }
+
     fn execute(&self, ctx: &mut Context) -> Option<ObjectInstance> {
         let symbol_type_id = TypeId::new(Symbol::from(self.symbol.clone()));
         match ctx.get_type(&symbol_type_id) {
@@ -68,10 +82,16 @@ impl Instruction for VarOrEmptyType {
                 let ty_inst = TypeInstantiation::new(symbol_type_id);
                 ty_inst.execute(ctx)
             }
-            None => {
-                let var_inst = Var::new(self.symbol.clone());
-                var_inst.execute(ctx)
-            }
+            None => match (
+                ctx.get_variable(&self.symbol),
+                ctx.get_function(&self.symbol),
+            ) {
+                (None, Some(function)) => Some(JkFunc::new(function.clone()).to_instance()),
+                _ => {
+                    let var_inst = Var::new(self.symbol.clone());
+                    var_inst.execute(ctx)
+                }
+            },
         }
     }
 
@@ -89,11 +109,43 @@ impl TypeCheck for VarOrEmptyType {
         };
 
         match kind {
-            Kind::Unknown => CheckedType::Error,
+            Kind::Unknown => {
+                ctx.error(
+                    Error::new(ErrKind::TypeChecker)
+                        .with_msg(format!(
+                            "use of undeclared variable or type: `{}`",
+                            self.symbol
+                        ))
+                        .with_loc(self.location.clone()),
+                );
+                CheckedType::Error
+            }
             Kind::EmptyTypeInst => {
-                CheckedType::Resolved(TypeId::new(Symbol::from(self.symbol.clone())))
+                let mut instantiation =
+                    TypeInstantiation::new(TypeId::new(Symbol::from(self.symbol.clone())));
+                if let Some(location) = &self.location {
+                    instantiation.set_location(location.clone());
+                }
+
+                instantiation.type_of(ctx)
             }
             Kind::VarAccess => ctx.get_var(&self.symbol).unwrap().to_owned(),
+            Kind::FunctionRef => {
+                let function = ctx.get_function(&self.symbol).unwrap();
+                if !function.generics().is_empty() {
+                    ctx.error(
+                        Error::new(ErrKind::TypeChecker)
+                            .with_msg(format!(
+                                "generic function `{}` cannot be used as a value",
+                                self.symbol
+                            ))
+                            .with_loc(self.location.clone()),
+                    );
+                    return CheckedType::Error;
+                }
+
+                CheckedType::Resolved(JkFunc::type_of(function))
+            }
         }
     }
 
@@ -1,24 +1,32 @@
 // FIXME: Make crate attribute `#![warn(missing_docs)]`
 
+pub mod analysis;
+pub mod ast;
 pub mod builtins;
 pub mod context;
 pub mod debug;
+pub mod debugger;
 pub mod error;
 #[cfg(feature = "ffi")]
 mod ffi;
+mod format;
 pub mod generics;
 mod indent;
 pub mod instance;
 pub mod instruction;
 pub mod location;
+pub mod native;
 pub mod parser;
+pub mod profiler;
+pub mod program;
+pub mod project;
 pub mod symbol;
 pub mod typechecker;
 mod utils;
 pub mod value;
 
 pub use builtins::Builtins;
-pub use context::{Context, Scope, ScopeMap};
+pub use context::{Capabilities, Context, Scope, ScopeMap, Snapshot, SymbolMap};
 pub use error::{ErrKind, Error};
 pub use generics::GenericUser;
 pub use indent::Indent;
@@ -217,8 +217,10 @@ impl SpanTuple {
             if i == 0 {
                 result.push_str(&self.format_line(separator, i, &line[(start_col - 1)..]));
             }
-            // Last line, for which we only push up to end.column characters
+            // Last line, for which we only push up to end.column characters. Spans
+            // ending with their last line point right after it
             else if self.start.line() + i == self.end.line() {
+                let end_col = end_col.min(line.len());
                 result.push_str(&self.format_line(separator, i, &line[..end_col]));
                 break;
             } else if self.start.line() == self.end.line() {
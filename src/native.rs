@@ -0,0 +1,211 @@
+//! The native module allows host applications to register Rust functions as jinko
+//! builtins without dealing with jinko instances directly. Arguments and return
+//! values of primitive types are converted automatically, and the function is
+//! declared in the context so that it can be called like any other function.
+//!
+//! ```
+//! use jinko::context::Context;
+//! use jinko::instance::FromObjectInstance;
+//! use jinko::value::JkInt;
+//!
+//! let mut ctx = Context::new();
+//! ctx.register_builtin("add", |lhs: i64, rhs: i64| lhs + rhs).unwrap();
+//!
+//! let res = ctx.eval("add(40, 2)").unwrap().unwrap();
+//! assert_eq!(JkInt::from_instance(&res).rust_value(), 42);
+//! ```
+
+use std::rc::Rc;
+
+use crate::builtins::{ArgType, Args, BuiltinFn};
+use crate::context::Context;
+use crate::instance::{FromObjectInstance, ObjectInstance, ToObjectInstance};
+use crate::instruction::{DecArg, FunctionDec, FunctionKind};
+use crate::typechecker::TypeId;
+use crate::value::{JkBool, JkChar, JkFloat, JkInt, JkString};
+
+/// Rust types which can be given to or returned from a native function
+pub trait NativeType: Sized {
+    /// Name of the jinko type corresponding to the Rust type, if any. `None`
+    /// corresponds to the absence of value
+    fn type_name() -> Option<&'static str>;
+
+    /// Convert an instance to the Rust type
+    fn from_native(instance: &ObjectInstance) -> Self;
+
+    /// Convert the Rust value to an instance
+    fn to_native(self) -> Option<ObjectInstance>;
+}
+
+macro_rules! native_type {
+    ($t:ty, $jk_t:ty, $name:literal) => {
+        impl NativeType for $t {
+            fn type_name() -> Option<&'static str> {
+                Some($name)
+            }
+
+            fn from_native(instance: &ObjectInstance) -> Self {
+                <$jk_t>::from_instance(instance).rust_value()
+            }
+
+            fn to_native(self) -> Option<ObjectInstance> {
+                Some(<$jk_t>::from(self).to_instance())
+            }
+        }
+    };
+}
+
+native_type!(i64, JkInt, "int");
+native_type!(f64, JkFloat, "float");
+native_type!(bool, JkBool, "bool");
+native_type!(char, JkChar, "char");
+native_type!(String, JkString, "string");
+
+impl NativeType for () {
+    fn type_name() -> Option<&'static str> {
+        None
+    }
+
+    fn from_native(_: &ObjectInstance) -> Self {}
+
+    fn to_native(self) -> Option<ObjectInstance> {
+        None
+    }
+}
+
+/// Rust functions which can be registered as builtins. `Marker` is the signature of
+/// the function, and is only used to differentiate the implementations.
+pub trait NativeFunction<Marker> {
+    /// Create the declaration of the function, so that it can be called from
+    /// jinko code
+    fn declaration(name: &str) -> FunctionDec;
+
+    /// Types of the arguments expected by the builtin
+    fn arg_types() -> Vec<ArgType>;
+
+    /// Wrap the function into a builtin, which converts its arguments and return
+    /// value
+    fn into_builtin(self) -> BuiltinFn;
+}
+
+macro_rules! native_function {
+    ($($arg:ident),*) => {
+        impl<F, R, $($arg,)*> NativeFunction<($($arg,)*)> for F
+        where
+            F: Fn($($arg),*) -> R + 'static,
+            R: NativeType,
+            $($arg: NativeType,)*
+        {
+            fn declaration(name: &str) -> FunctionDec {
+                #[allow(unused_mut)]
+                let mut args = Vec::new();
+                $(
+                    args.push(DecArg::new(
+                        format!("arg{}", args.len()),
+                        TypeId::from($arg::type_name().unwrap_or("void")),
+                    ));
+                )*
+
+                let mut dec = FunctionDec::new(
+                    String::from(name),
+                    R::type_name().map(TypeId::from),
+                    vec![],
+                    args,
+                );
+                dec.set_kind(FunctionKind::Ext);
+
+                dec
+            }
+
+            fn arg_types() -> Vec<ArgType> {
+                vec![$($arg::type_name().map_or(ArgType::Any, ArgType::Type)),*]
+            }
+
+            #[allow(non_snake_case, unused_variables, unused_mut)]
+            fn into_builtin(self) -> BuiltinFn {
+                Rc::new(move |_ctx: &mut Context, args: &Args| {
+                    let mut args = args.iter();
+                    $(
+                        let $arg = $arg::from_native(args.next()?);
+                    )*
+
+                    self($($arg),*).to_native()
+                })
+            }
+        }
+    };
+}
+
+native_function!();
+native_function!(A0);
+native_function!(A0, A1);
+native_function!(A0, A1, A2);
+native_function!(A0, A1, A2, A3);
+native_function!(A0, A1, A2, A3, A4);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn t_register_builtin() {
+        let mut ctx = Context::new();
+        ctx.register_builtin("add", |lhs: i64, rhs: i64| lhs + rhs)
+            .unwrap();
+
+        let res = ctx.eval("add(40, 2)").unwrap().unwrap();
+
+        assert_eq!(JkInt::from_instance(&res).rust_value(), 42);
+    }
+
+    #[test]
+    fn t_register_builtin_types() {
+        let mut ctx = Context::new();
+        ctx.register_builtin("repeat", |s: String, n: i64| s.repeat(n as usize))
+            .unwrap();
+        ctx.register_builtin("half", |f: f64| f / 2.0).unwrap();
+        ctx.register_builtin("is_upper", |c: char| c.is_uppercase())
+            .unwrap();
+
+        let res = ctx.eval("repeat(\"jk\", 3)").unwrap().unwrap();
+        assert_eq!(JkString::from_instance(&res).rust_value(), "jkjkjk");
+
+        let res = ctx.eval("half(5.0)").unwrap().unwrap();
+        assert_eq!(JkFloat::from_instance(&res).rust_value(), 2.5);
+
+        let res = ctx.eval("is_upper('J')").unwrap().unwrap();
+        assert!(JkBool::from_instance(&res).rust_value());
+    }
+
+    #[test]
+    fn t_register_builtin_no_return() {
+        let called = Rc::new(Cell::new(false));
+        let flag = called.clone();
+
+        let mut ctx = Context::new();
+        ctx.register_builtin("notify", move || flag.set(true))
+            .unwrap();
+
+        assert!(ctx.eval("notify()").unwrap().is_none());
+        assert!(called.get());
+    }
+
+    #[test]
+    fn t_register_builtin_invalid_args() {
+        let mut ctx = Context::new();
+        ctx.register_builtin("add", |lhs: i64, rhs: i64| lhs + rhs)
+            .unwrap();
+
+        assert!(ctx.eval("add(40, \"two\")").is_err());
+        assert!(ctx.eval("add(40)").is_err());
+    }
+
+    #[test]
+    fn t_register_builtin_twice() {
+        let mut ctx = Context::new();
+        ctx.register_builtin("id", |x: i64| x).unwrap();
+
+        assert!(ctx.register_builtin("id", |x: i64| x).is_err());
+    }
+}
@@ -5,15 +5,14 @@
 //! that sense.
 
 use super::constructs::expr;
-use crate::error::{ErrKind, Error};
-use crate::instruction::{FunctionCall, Instruction, MethodCall};
+use crate::instruction::{Instruction, MethodCall};
 use crate::location::{Location, SpanTuple};
-use crate::parser::{ParseInput, ParseResult, Token};
+use crate::parser::{ParseError, ParseInput, ParseResult, Token};
 use crate::value::{JkBool, JkChar, JkFloat, JkInt, JkString};
 
 use nom::bytes::complete::take;
 use nom::sequence::terminated;
-use nom::Err::Error as NomError;
+use nom::Err::Failure as NomFailure;
 use nom::Slice;
 use nom_locate::position;
 
@@ -24,9 +23,18 @@ impl ConstantConstruct {
     pub(crate) fn char_constant(
         input: ParseInput,
     ) -> ParseResult<ParseInput, Box<dyn Instruction>> {
+        let (input, start_loc) = position(input)?;
         let (input, char_value) = Token::char_constant(input)?;
+        let (input, end_loc) = position(input)?;
 
-        Ok((input, Box::new(JkChar::from(char_value))))
+        let mut constant = JkChar::from(char_value);
+        constant.set_location(SpanTuple::new(
+            input.extra,
+            start_loc.into(),
+            end_loc.into(),
+        ));
+
+        Ok((input, Box::new(constant)))
     }
 
     pub(crate) fn string_constant(
@@ -36,15 +44,20 @@ impl ConstantConstruct {
         let (input, _) = Token::double_quote(input)?;
         let (input, inner) = ConstantConstruct::inner_string(input, start_loc.into())?;
         let (input, end_loc) = position(input)?;
-        let string = inner.unwrap_or_else(|| {
-            let mut s = JkString::from("");
-            s.set_location(SpanTuple::new(
-                input.extra,
-                start_loc.into(),
-                end_loc.into(),
-            ));
-            Box::new(s)
-        });
+        let location = SpanTuple::new(input.extra, start_loc.into(), end_loc.into());
+        let string = match inner {
+            Some(mut inner) => {
+                if let Some(literal) = inner.downcast_mut::<MethodCall>() {
+                    literal.set_location(location);
+                }
+                inner
+            }
+            None => {
+                let mut s = JkString::from("");
+                s.set_location(location);
+                Box::new(s)
+            }
+        };
 
         Ok((input, string))
     }
@@ -65,6 +78,7 @@ impl ConstantConstruct {
         }
     }
 
+    /// | '{}' | '{{'
     /// | '{' expr '}'
     /// | '\' CHAR
     /// | CHAR (* anything except "{\ *)
@@ -74,7 +88,15 @@ impl ConstantConstruct {
     ) -> ParseResult<ParseInput, Box<dyn Instruction>> {
         let special = &['"', '{', '\\'];
 
-        if let Ok((input, _)) = Token::left_curly_bracket(input) {
+        // Empty and doubled braces are not interpolations, so that format strings can
+        // contain `{}` placeholders and `{{` escapes
+        if input.fragment().starts_with("{}") || input.fragment().starts_with("{{") {
+            let (input, braces) = take(2usize)(input)?;
+            let (input, end_loc) = position(input)?;
+            let mut string = JkString::from(*braces.fragment());
+            string.set_location(SpanTuple::new(input.extra, start_loc, end_loc.into()));
+            Ok((input, Box::new(string)))
+        } else if let Ok((input, _)) = Token::left_curly_bracket(input) {
             terminated(expr, Token::right_curly_bracket)(input)
         } else if let Ok((input, _)) = Token::backslash(input) {
             if input.is_empty() {
@@ -89,9 +111,9 @@ impl ConstantConstruct {
                 "r" => "\r",
                 "t" => "\t",
                 _ => {
-                    return Err(NomError(
-                        Error::new(ErrKind::Parsing)
-                            .with_msg(String::from("Unknown character escape")),
+                    return Err(NomFailure(
+                        ParseError::new(special)
+                            .with_msg(format!("unknown character escape `\\{}`", special)),
                     ))
                 }
             };
@@ -106,8 +128,9 @@ impl ConstantConstruct {
             string.set_location(SpanTuple::new(input.extra, start_loc, end_loc.into()));
             Ok((input.slice(index..), Box::new(string)))
         } else {
-            Err(NomError(
-                Error::new(ErrKind::Parsing).with_msg(String::from("Undelimited string")),
+            Err(NomFailure(
+                ParseError::expected(input.slice(input.len()..), "`\"`")
+                    .with_msg("unterminated string"),
             ))
         }
     }
@@ -118,33 +141,57 @@ impl ConstantConstruct {
     ) -> Box<dyn Instruction> {
         match right {
             None => left,
-            Some(right) => Box::new(MethodCall::new(
-                left,
-                FunctionCall::new(String::from("concat"), vec![], vec![right]),
-            )),
+            Some(right) => Box::new(MethodCall::string_literal(left, right)),
         }
     }
 
     pub(crate) fn float_constant(
         input: ParseInput,
     ) -> ParseResult<ParseInput, Box<dyn Instruction>> {
+        let (input, start_loc) = position(input)?;
         let (input, float_value) = Token::float_constant(input)?;
+        let (input, end_loc) = position(input)?;
+
+        let mut constant = JkFloat::from(float_value);
+        constant.set_location(SpanTuple::new(
+            input.extra,
+            start_loc.into(),
+            end_loc.into(),
+        ));
 
-        Ok((input, Box::new(JkFloat::from(float_value))))
+        Ok((input, Box::new(constant)))
     }
 
     pub(crate) fn int_constant(input: ParseInput) -> ParseResult<ParseInput, Box<dyn Instruction>> {
+        let (input, start_loc) = position(input)?;
         let (input, int_value) = Token::int_constant(input)?;
+        let (input, end_loc) = position(input)?;
 
-        Ok((input, Box::new(JkInt::from(int_value))))
+        let mut constant = JkInt::from(int_value);
+        constant.set_location(SpanTuple::new(
+            input.extra,
+            start_loc.into(),
+            end_loc.into(),
+        ));
+
+        Ok((input, Box::new(constant)))
     }
 
     pub(crate) fn bool_constant(
         input: ParseInput,
     ) -> ParseResult<ParseInput, Box<dyn Instruction>> {
+        let (input, start_loc) = position(input)?;
         let (input, bool_value) = Token::bool_constant(input)?;
+        let (input, end_loc) = position(input)?;
+
+        let mut constant = JkBool::from(bool_value);
+        constant.set_location(SpanTuple::new(
+            input.extra,
+            start_loc.into(),
+            end_loc.into(),
+        ));
 
-        Ok((input, Box::new(JkBool::from(bool_value))))
+        Ok((input, Box::new(constant)))
     }
 }
 
@@ -227,6 +274,25 @@ mod tests {
         assert!(expr.downcast_ref::<MethodCall>().is_some());
     }
 
+    #[test]
+    fn empty_braces_are_literal() {
+        let input = span!("\"hello {}\"");
+
+        let (input, expr) = ConstantConstruct::string_constant(input).unwrap();
+        assert_eq!(*input.fragment(), "");
+        assert!(expr.downcast_ref::<MethodCall>().is_some());
+    }
+
+    #[test]
+    fn doubled_braces_are_literal() {
+        let input = span!("\"{{\"");
+
+        let (input, expr) = ConstantConstruct::string_constant(input).unwrap();
+        assert_eq!(*input.fragment(), "");
+        let string = expr.downcast_ref::<JkString>().unwrap();
+        assert_eq!(string.0, "{{");
+    }
+
     #[test]
     fn formatted_not_delimited() {
         let input = span!("\"hello {world}");
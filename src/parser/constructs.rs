@@ -15,22 +15,69 @@
 
 use nom::Err::Error as NomError;
 use nom::{
-    branch::alt, character::complete::multispace0, combinator::opt, multi::many0,
-    sequence::delimited, sequence::pair, sequence::preceded, sequence::terminated,
+    branch::alt, character::complete::multispace0, combinator::map, combinator::opt, multi::many0,
+    sequence::delimited, sequence::pair, sequence::preceded, sequence::terminated, Slice,
 };
 use nom_locate::position;
+use std::cell::Cell;
+use std::iter::Peekable;
+use std::str::CharIndices;
 
 use crate::error::Error;
 use crate::instruction::{
-    BinaryOp, Block, DecArg, FieldAccess, FunctionCall, FunctionDec, FunctionKind, IfElse, Incl,
-    Instruction, JkInst, Loop, LoopKind, MethodCall, Operator, Return, TypeDec, TypeInstantiation,
-    Var, VarAssign, VarOrEmptyType,
+    BinaryOp, Block, Break, Continue, DecArg, FieldAccess, FunctionCall, FunctionDec, FunctionKind,
+    IfElse, Incl, Instruction, InterfaceDec, InterfaceImpl, JkInst, Lambda, Loop, LoopKind, Match,
+    MatchArm, MethodCall, Operator, Pattern, Range, Return, TryOp, TypeDec, TypeInstantiation,
+    UnaryOp, UnaryOperator, Var, VarAssign, VarOrEmptyType,
 };
 use crate::location::{Location, SpanTuple};
-use crate::parser::{ConstantConstruct, ParseInput, ParseResult, Token};
+use crate::parser::{error, ConstantConstruct, ParseError, ParseInput, ParseResult, Token};
 use crate::symbol::Symbol;
 use crate::typechecker::TypeId;
 
+/// Maximum amount of nested expressions, blocks or types. Parsing is recursive, so deeper
+/// inputs are rejected instead of overflowing the stack of the parser
+pub const MAX_NESTING: usize = 256;
+
+thread_local! {
+    /// Amount of nested constructs currently being parsed
+    static NESTING: Cell<usize> = const { Cell::new(0) };
+}
+
+/// A nesting level of the input, which lasts until the guard is dropped
+struct Nesting;
+
+impl Nesting {
+    /// Enter a nesting level, or fail if the input is nested too deeply
+    fn enter(input: ParseInput) -> Result<Nesting, nom::Err<ParseError<ParseInput>>> {
+        let depth = NESTING.with(|nesting| {
+            nesting.set(nesting.get() + 1);
+            nesting.get()
+        });
+
+        // The level is left when the guard is dropped, even if entering it fails
+        let guard = Nesting;
+
+        match depth > MAX_NESTING {
+            true => Err(nom::Err::Failure(
+                ParseError::new(input)
+                    .with_msg(format!(
+                        "input is nested too deeply: the maximum depth is {}",
+                        MAX_NESTING
+                    ))
+                    .with_end(input),
+            )),
+            false => Ok(guard),
+        }
+    }
+}
+
+impl Drop for Nesting {
+    fn drop(&mut self) {
+        NESTING.with(|nesting| nesting.set(nesting.get() - 1))
+    }
+}
+
 /// Parse as many instructions as possible
 /// many_expr = ( expr_semicolon )*
 pub fn many_expr(mut input: ParseInput) -> ParseResult<ParseInput, Vec<Box<dyn Instruction>>> {
@@ -46,6 +93,113 @@ pub fn many_expr(mut input: ParseInput) -> ParseResult<ParseInput, Vec<Box<dyn I
     }
 }
 
+/// Parse as many instructions as possible, recovering from syntax errors. When an
+/// instruction cannot be parsed, the input is skipped until the next synchronization
+/// point and the parsing resumes from there. This returns the instructions which
+/// were parsed alongside the errors which were encountered.
+///
+/// many_expr_recover = ( expr_semicolon | skip_statement )*
+pub fn many_expr_recover(mut input: ParseInput) -> (Vec<Box<dyn Instruction>>, Vec<Error>) {
+    let mut exprs = vec![];
+    let mut errors = vec![];
+    loop {
+        input = next(input);
+        if input.is_empty() {
+            return (exprs, errors);
+        }
+        match expr_semicolon(input) {
+            Ok((new_input, expr)) => {
+                input = new_input;
+                exprs.push(expr);
+            }
+            Err(e) => {
+                let e = match e {
+                    NomError(e) => NomError(e.label(&input, error::STATEMENT)),
+                    e => e,
+                };
+                errors.push(Error::from(e));
+                input = skip_statement(input);
+            }
+        }
+    }
+}
+
+/// Skip the input until the end of the current statement, which is either a semicolon
+/// or the closing bracket of a block, as long as they are not nested in another
+/// block, string or comment. Parentheses are not taken into account, since they
+/// cannot contain statements and are often left unbalanced by syntax errors. An
+/// unmatched closing bracket is skipped as well, so that the parser always makes
+/// progress.
+///
+/// skip_statement = ( [^;{}] | '{' ... '}' )* ( ';' | '}' )
+fn skip_statement(input: ParseInput) -> ParseInput {
+    let mut depth = 0usize;
+    let mut chars = input.fragment().char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '{' => depth += 1,
+            ';' | '}' if depth == 0 => return input.slice(i + 1..),
+            '}' if depth == 1 => {
+                // An if block might be followed by its else block, which is part of
+                // the same statement
+                let rest = input.slice(i + 1..);
+                if Token::else_tok(next(rest)).is_err() {
+                    return rest;
+                }
+                depth -= 1;
+            }
+            '}' => depth -= 1,
+            '"' | '\'' => {
+                skip_quoted(&mut chars, c);
+            }
+            '/' if skip_comment(&mut chars) => {}
+            _ => {}
+        }
+    }
+
+    input.slice(input.fragment().len()..)
+}
+
+/// Skip the rest of a string or character constant opened by `quote`, and return the
+/// position of its closing quote
+fn skip_quoted(chars: &mut Peekable<CharIndices>, quote: char) -> Option<usize> {
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            _ if c == quote => return Some(i),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Skip the rest of a comment, if the slash which was just consumed opens one
+fn skip_comment(chars: &mut Peekable<CharIndices>) -> bool {
+    if chars.next_if(|(_, c)| *c == '/').is_some() {
+        let _ = chars.any(|(_, c)| c == '\n');
+        return true;
+    }
+    if chars.next_if(|(_, c)| *c == '*').is_none() {
+        return false;
+    }
+
+    let mut comment_depth = 1;
+    while comment_depth > 0 {
+        match chars.next() {
+            Some((_, '*')) if chars.next_if(|(_, c)| *c == '/').is_some() => comment_depth -= 1,
+            Some((_, '/')) if chars.next_if(|(_, c)| *c == '*').is_some() => comment_depth += 1,
+            Some(_) => {}
+            None => break,
+        }
+    }
+
+    true
+}
+
 /// Parse an instruction and maybe the semicolon that follows.
 ///
 /// expr_semicolon = expr [ ';' ]
@@ -56,23 +210,25 @@ pub fn expr_semicolon(input: ParseInput) -> ParseResult<ParseInput, Box<dyn Inst
     Ok((input, expr))
 }
 
-// expr = cmp ( '<' cmp | '>' cmp | '<=' cmp | '>=' cmp | '==' cmp | '!=' cmp)*
-pub fn expr(input: ParseInput) -> ParseResult<ParseInput, Box<dyn Instruction>> {
+/// Parse a chain of left associative binary operations of the same precedence, whose
+/// operands are parsed by `operand`. Operators are associated from left to right, so
+/// that `1 - 2 - 3` is parsed as `(1 - 2) - 3`. The chain stops at the first token
+/// which is not one of the operators, such as `;`, `)`, `,` or `}`
+///
+/// binary_op = operand next ( op operand next )*
+fn binary_op<'i>(
+    input: ParseInput<'i>,
+    operand: fn(ParseInput<'i>) -> ParseResult<ParseInput<'i>, Box<dyn Instruction>>,
+    mut op: impl FnMut(ParseInput<'i>) -> ParseResult<ParseInput<'i>, ParseInput<'i>>,
+) -> ParseResult<ParseInput<'i>, Box<dyn Instruction>> {
     let input = next(input);
     let (input, start_loc) = position(input)?;
-    let (mut input, mut expr) = cmp(input)?;
-    while let Ok((new_input, op)) = alt((
-        Token::lt_eq,
-        Token::gt_eq,
-        Token::equals,
-        Token::not_equals,
-        Token::lt,
-        Token::gt,
-    ))(input)
-    {
-        let (new_input, rhs) = cmp(new_input)?;
+    let (input, mut expr) = operand(input)?;
+    let mut input = next(input);
+    while let Ok((new_input, op)) = op(input) {
+        let (new_input, rhs) = operand(new_input)?;
         let (new_input, end_loc) = position(new_input)?;
-        input = new_input;
+        input = next(new_input);
         let mut b_op = BinaryOp::new(expr, rhs, Operator::new(op.fragment()));
         b_op.set_location(SpanTuple::new(
             input.extra,
@@ -84,70 +240,177 @@ pub fn expr(input: ParseInput) -> ParseResult<ParseInput, Box<dyn Instruction>>
     Ok((input, expr))
 }
 
-pub fn cmp(input: ParseInput) -> ParseResult<ParseInput, Box<dyn Instruction>> {
+/// Ranges have the lowest precedence of all operators, and cannot be chained
+///
+/// expr = comparison [ ( '..=' | '..' ) comparison ]
+pub fn expr(input: ParseInput) -> ParseResult<ParseInput, Box<dyn Instruction>> {
     let input = next(input);
     let (input, start_loc) = position(input)?;
-    let (mut input, mut expr) = term(input)?;
-    while let Ok((new_input, op)) = alt((Token::add, Token::sub))(input) {
-        let (new_input, rhs) = term(new_input)?;
-        let (new_input, end_loc) = position(new_input)?;
-        input = new_input;
-        let mut b_op = BinaryOp::new(expr, rhs, Operator::new(op.fragment()));
-        b_op.set_location(SpanTuple::new(
-            input.extra,
-            start_loc.into(),
-            end_loc.into(),
-        ));
-        expr = Box::new(b_op);
+    let (input, start) = comparison(input)?;
+
+    match alt((Token::inclusive_range, Token::range))(input) {
+        Ok((input, op)) => {
+            let (input, end) = comparison(input)?;
+            let (input, end_loc) = position(input)?;
+            let mut range = Range::new(start, end, *op.fragment() == "..=");
+            range.set_location(SpanTuple::new(
+                input.extra,
+                start_loc.into(),
+                end_loc.into(),
+            ));
+            Ok((input, Box::new(range)))
+        }
+        Err(_) => Ok((input, start)),
     }
-    Ok((input, expr))
 }
 
-/// term = factor next ( '*' factor next | '/' factor next )*
+/// comparison = bit_or ( '<' bit_or | '>' bit_or | '<=' bit_or | '>=' bit_or | '==' bit_or | '!=' bit_or )*
+fn comparison(input: ParseInput) -> ParseResult<ParseInput, Box<dyn Instruction>> {
+    binary_op(
+        input,
+        bit_or,
+        alt((
+            Token::lt_eq,
+            Token::gt_eq,
+            Token::equals,
+            Token::not_equals,
+            Token::lt,
+            Token::gt,
+        )),
+    )
+}
+
+/// bit_or = bit_xor ( '|' bit_xor )*
+fn bit_or(input: ParseInput) -> ParseResult<ParseInput, Box<dyn Instruction>> {
+    binary_op(input, bit_xor, Token::bit_or)
+}
+
+/// bit_xor = bit_and ( '^' bit_and )*
+fn bit_xor(input: ParseInput) -> ParseResult<ParseInput, Box<dyn Instruction>> {
+    binary_op(input, bit_and, Token::bit_xor)
+}
+
+/// bit_and = shift ( '&' shift )*
+fn bit_and(input: ParseInput) -> ParseResult<ParseInput, Box<dyn Instruction>> {
+    binary_op(input, shift, Token::bit_and)
+}
+
+/// shift = cmp ( '<<' cmp | '>>' cmp )*
+fn shift(input: ParseInput) -> ParseResult<ParseInput, Box<dyn Instruction>> {
+    binary_op(input, cmp, alt((Token::left_shift, Token::right_shift)))
+}
+
+/// cmp = term ( '+' term | '-' term )*
+pub fn cmp(input: ParseInput) -> ParseResult<ParseInput, Box<dyn Instruction>> {
+    binary_op(input, term, alt((Token::add, Token::sub)))
+}
+
+/// term = power ( '*' power | '/' power | '%' power )*
 fn term(input: ParseInput) -> ParseResult<ParseInput, Box<dyn Instruction>> {
+    binary_op(input, power, alt((Token::mul, Token::div, Token::rem)))
+}
+
+/// The power operator is right associative, so that `2 ** 3 ** 2` is parsed as
+/// `2 ** (3 ** 2)`. Unary operators bind tighter: `-2 ** 2` is `(-2) ** 2`
+///
+/// power = factor next [ '**' power ]
+fn power(input: ParseInput) -> ParseResult<ParseInput, Box<dyn Instruction>> {
     let input = next(input);
     let (input, start_loc) = position(input)?;
-    let (input, mut term) = factor(input)?;
-    let mut input = next(input);
-    while let Ok((new_input, op)) = alt((Token::mul, Token::div))(input) {
-        let (new_input, rhs) = factor(new_input)?;
-        let (new_input, end_loc) = position(new_input)?;
-        let new_input = next(new_input);
-        input = new_input;
-        let mut b_op = BinaryOp::new(term, rhs, Operator::new(op.fragment()));
-        b_op.set_location(SpanTuple::new(
-            input.extra,
-            start_loc.into(),
-            end_loc.into(),
-        ));
-        term = Box::new(b_op);
+    let (input, base) = factor(input)?;
+    let input = next(input);
+    match Token::pow(input) {
+        Ok((input, op)) => {
+            let (input, exponent) = power(input)?;
+            let (input, end_loc) = position(input)?;
+            let mut b_op = BinaryOp::new(base, exponent, Operator::new(op.fragment()));
+            b_op.set_location(SpanTuple::new(
+                input.extra,
+                start_loc.into(),
+                end_loc.into(),
+            ));
+            Ok((input, Box::new(b_op)))
+        }
+        Err(_) => Ok((input, base)),
     }
-    Ok((input, term))
 }
 
-/// factor = next unit factor_rest
+/// factor = next ( '-' | '!' ) factor       (* unless the input is a constant *)
+///        | next unit factor_rest
 fn factor(input: ParseInput) -> ParseResult<ParseInput, Box<dyn Instruction>> {
     let input = next(input);
+    let _nesting = Nesting::enter(input)?;
+    reserved_assignment(input)?;
     let (input, start_loc) = position(input)?;
+
+    // Negative constants such as `-12` are handled by the constant parser directly
+    if constant(input).is_err() {
+        if let Ok((input, op)) = alt((Token::sub, Token::bang))(input) {
+            let (input, operand) = factor(input)?;
+            let (input, end_loc) = position(input)?;
+            let mut u_op = UnaryOp::new(operand, UnaryOperator::new(op.fragment()));
+            u_op.set_location(SpanTuple::new(
+                input.extra,
+                start_loc.into(),
+                end_loc.into(),
+            ));
+
+            return Ok((input, Box::new(u_op)));
+        }
+    }
+
     let (input, unit) = unit(input)?;
     factor_rest(input, unit, start_loc.into())
 }
 
+/// Keywords introduce their own constructs, so assigning to one, as in `func = 3`,
+/// would otherwise fail in the middle of that construct. Reject it right away instead
+fn reserved_assignment(input: ParseInput) -> ParseResult<ParseInput, ()> {
+    if let Ok((rest, keyword)) = Token::keyword(input) {
+        if Token::equal(next(rest)).is_ok() {
+            return Err(nom::Err::Failure(
+                ParseError::new(keyword)
+                    .with_msg(format!("`{}` is a reserved keyword", keyword))
+                    .with_end(rest),
+            ));
+        }
+    }
+
+    Ok((input, ()))
+}
+
 /// factor_rest = '.' IDENTIFIER next method_or_field factor_rest
+///             | '?' factor_rest
 ///             | ε
 fn factor_rest(
     input: ParseInput,
     expr: Box<dyn Instruction>,
     start_loc: Location,
 ) -> ParseResult<ParseInput, Box<dyn Instruction>> {
-    match Token::dot(input) {
-        Ok((input, _)) => {
-            let (input, id) = Token::identifier(input)?;
-            let input = next(input);
-            let (input, expr) = method_or_field(input, expr, id, start_loc.clone())?;
-            factor_rest(input, expr, start_loc)
+    let (mut input, mut expr) = (input, expr);
+
+    // Each access or `?` operator nests the expression it applies to
+    let mut nesting = vec![];
+
+    loop {
+        if let Ok((new_input, _)) = Token::dot(input) {
+            nesting.push(Nesting::enter(input)?);
+            let (new_input, id) = Token::identifier(new_input)?;
+            let new_input = next(new_input);
+            (input, expr) = method_or_field(new_input, expr, id, start_loc.clone())?;
+        } else if let Ok((new_input, _)) = Token::question_mark(input) {
+            nesting.push(Nesting::enter(input)?);
+            let (new_input, end_loc) = position(new_input)?;
+            let mut try_op = TryOp::new(expr);
+            try_op.set_location(SpanTuple::new(
+                new_input.extra,
+                start_loc.clone(),
+                end_loc.into(),
+            ));
+            (input, expr) = (new_input, Box::new(try_op));
+        } else {
+            return Ok((input, expr));
         }
-        _ => Ok((input, expr)),
     }
 }
 
@@ -187,22 +450,28 @@ fn method_or_field(
 }
 
 /// ```ignore
-/// unit = '_f' expr block next [ 'else' next block ]
+/// unit = '_f' expr block next [ 'else' next ( block | 'if' unit_if ) ]
 ///      | 'while' expr block
 ///      | 'loop' next block
 ///      | 'for' spaced_identifier '_n' expr block
+///      | 'match' expr '{' next unit_match
 ///
 ///      | 'func' function_declaration block
-///      | 'test' function_declaration block
+///      | 'func' lambda_declaration block
+///      | 'test' test_declaration block
 ///      | 'mock' function_declaration block
 ///
-///      | 'type' type_id '(' named_args
-///      | 'incl' spaced_identifier [ 'as' next IDENTIFIER ]
+///      | 'type' type_id [ '(' type_field ( ',' type_field )* ')' ]
+///      | 'interface' spaced_identifier '{' next ( 'func' function_declaration ';' next )* '}'
+///      | 'impl' spaced_identifier 'for' type_id '{' next ( 'func' function_declaration block next )* '}'
+///      | 'incl' incl_path [ 'as' next IDENTIFIER ]
 ///      | 'mut' spaced_identifier '=' expr (* mutable variable assigment *)
 ///      | '@' spaced_identifier '(' args
 ///
 ///      | 'extern' 'func' function_declaration ';'
 ///      | 'return' expr
+///      | 'break' [ expr ]
+///      | 'continue'
 ///      | '{' next inner_block
 ///      | '(' expr ')'
 ///
@@ -225,32 +494,47 @@ fn unit(input: ParseInput) -> ParseResult<ParseInput, Box<dyn Instruction>> {
         unit_loop(input, start_loc.into())
     } else if let Ok((input, _)) = Token::for_tok(input) {
         unit_for(input, start_loc.into())
-    } else if let Ok((input, kind)) =
-        alt((Token::func_tok, Token::test_tok, Token::mock_tok))(input)
-    {
+    } else if let Ok((input, _)) = Token::match_tok(input) {
+        unit_match(input, start_loc.into())
+    } else if let Ok((input, _)) = Token::test_tok(input) {
+        unit_test(input, start_loc.into())
+    } else if let Ok((input, kind)) = alt((Token::func_tok, Token::mock_tok))(input) {
         unit_func(input, kind, start_loc.into())
     } else if let Ok((input, _)) = Token::incl_tok(input) {
         unit_incl(input, start_loc.into())
     } else if let Ok((input, _)) = Token::type_tok(input) {
         unit_type_decl(input, start_loc.into())
+    } else if let Ok((input, _)) = Token::interface_tok(input) {
+        unit_interface(input, start_loc.into())
+    } else if let Ok((input, _)) = Token::impl_tok(input) {
+        unit_impl(input, start_loc.into())
     } else if let Ok((input, _)) = Token::mut_tok(input) {
-        unit_mut_var(input)
+        unit_mut_var(input, start_loc.into())
     } else if let Ok((input, _)) = Token::at_sign(input) {
         unit_jk_inst(input, start_loc.into())
     } else if let Ok((input, _)) = Token::ext_tok(input) {
-        unit_extern(input)
+        unit_extern(input, start_loc.into())
     } else if let Ok((input, _)) = Token::return_tok(input) {
         unit_return(input, start_loc.into())
+    } else if let Ok((input, _)) = Token::break_tok(input) {
+        unit_break(input, start_loc.into())
+    } else if let Ok((input, _)) = Token::continue_tok(input) {
+        unit_continue(input, start_loc.into())
     } else if let Ok((input, _)) = Token::left_curly_bracket(input) {
         unit_block(input, start_loc.into())
     } else if let Ok((input, _)) = Token::left_parenthesis(input) {
         terminated(expr, Token::right_parenthesis)(input)
-    } else if let Ok(res) = constant(input) {
-        Ok(res)
     } else {
-        let (input, id) = Token::identifier(input)?;
-        let input = next(input);
-        func_type_or_var(input, id, start_loc.into())
+        match constant(input) {
+            Ok(res) => Ok(res),
+            // Malformed constants such as `1.2.3` are not identifiers either
+            Err(e @ nom::Err::Failure(_)) => Err(e),
+            Err(_) => {
+                let (input, id) = error::label(&["expression"], Token::identifier)(input)?;
+                let input = next(input);
+                func_type_or_var(input, id, start_loc.into())
+            }
+        }
     }
 }
 
@@ -263,6 +547,11 @@ fn unit_if(
     let input = next(input);
     if let Ok((input, _)) = Token::else_tok(input) {
         let input = next(input);
+        let (input, else_if_loc) = position(input)?;
+        if let Ok((input, _)) = Token::if_tok(input) {
+            return unit_else_if(input, start_loc, else_if_loc.into(), cond, success);
+        }
+
         let (input, else_body) = block(input)?;
         let (input, end_loc) = position(input)?;
         let if_end_loc = if let Some(else_loc) = else_body.location() {
@@ -284,6 +573,39 @@ fn unit_if(
     }
 }
 
+/// Parse the rest of an `else if` chain. The chain is right-nested: The `else` block
+/// of the first condition contains the `if` of the second one, and so on.
+///
+/// `if a { 1 } else if b { 2 } else { 3 }` is parsed as
+/// `if a { 1 } else { if b { 2 } else { 3 } }`
+fn unit_else_if(
+    input: ParseInput,
+    start_loc: Location,
+    else_if_loc: Location,
+    cond: Box<dyn Instruction>,
+    success: Block,
+) -> ParseResult<ParseInput, Box<dyn Instruction>> {
+    let _nesting = Nesting::enter(input)?;
+
+    let (input, else_if) = unit_if(input, else_if_loc)?;
+    // Conditions are always located by the parser
+    let else_if_span = else_if.location().cloned().unwrap();
+
+    let mut else_body = Block::new();
+    else_body.add_instruction(else_if);
+    else_body.set_statement(false);
+    else_body.set_location(else_if_span.clone());
+
+    let mut if_else = IfElse::new(cond, success, Some(else_body));
+    if_else.set_location(SpanTuple::new(
+        input.extra,
+        start_loc,
+        else_if_span.end().clone(),
+    ));
+
+    Ok((input, Box::new(if_else)))
+}
+
 fn unit_while(
     input: ParseInput,
     start_loc: Location,
@@ -325,11 +647,113 @@ fn unit_for(
     Ok((input, Box::new(for_loop)))
 }
 
+/// unit_match = expr '{' next ( match_arm next [ ',' next ] )* '}'
+fn unit_match(
+    input: ParseInput,
+    start_loc: Location,
+) -> ParseResult<ParseInput, Box<dyn Instruction>> {
+    let (input, value) = expr(input)?;
+    let (input, _) = Token::left_curly_bracket(input)?;
+    let mut input = next(input);
+    let mut arms = vec![];
+
+    loop {
+        if let Ok((after_bracket, _)) = Token::right_curly_bracket(input) {
+            input = after_bracket;
+            break;
+        }
+
+        let (after_arm, arm) = match_arm(input)?;
+        arms.push(arm);
+
+        let after_arm = next(after_arm);
+        input = match Token::comma(after_arm) {
+            Ok((after_comma, _)) => next(after_comma),
+            Err(_) => after_arm,
+        };
+    }
+
+    let (input, end_loc) = position(input)?;
+    let mut match_block = Match::new(value, arms);
+    match_block.set_location(SpanTuple::new(input.extra, start_loc, end_loc.into()));
+
+    Ok((input, Box::new(match_block)))
+}
+
+/// match_arm = pattern next '->' next block
+fn match_arm(input: ParseInput) -> ParseResult<ParseInput, MatchArm> {
+    let (input, start_loc) = position(input)?;
+    let (input, pattern) = pattern(input)?;
+    let (input, end_loc) = position(input)?;
+    let input = next(input);
+    let (input, _) = Token::arrow(input)?;
+    let input = next(input);
+    let (input, block) = block(input)?;
+
+    let mut arm = MatchArm::new(pattern, block);
+    arm.set_location(SpanTuple::new(
+        input.extra,
+        start_loc.into(),
+        end_loc.into(),
+    ));
+
+    Ok((input, arm))
+}
+
+/// pattern = '_'
+///         | constant
+///         | IDENTIFIER [ '(' comma_list1(next pattern_binding, ')') ]
+fn pattern(input: ParseInput) -> ParseResult<ParseInput, Pattern> {
+    if let Ok((input, _)) = Token::underscore(input) {
+        return Ok((input, Pattern::Wildcard));
+    }
+
+    match constant(input) {
+        Ok((input, constant)) => return Ok((input, Pattern::Constant(constant))),
+        Err(e @ nom::Err::Failure(_)) => return Err(e),
+        Err(_) => {}
+    }
+
+    let (input, ty) = Token::identifier(input)?;
+    let (input, bindings) = match Token::left_parenthesis(input) {
+        Ok((input, _)) => comma_list1(
+            preceded(nom_next, pattern_binding),
+            Token::right_parenthesis,
+        )(input)?,
+        Err(_) => (input, vec![]),
+    };
+
+    Ok((input, Pattern::Type(TypeId::from(ty.as_str()), bindings)))
+}
+
+/// pattern_binding = IDENTIFIER next [ ':' next IDENTIFIER next ]
+fn pattern_binding(input: ParseInput) -> ParseResult<ParseInput, (String, String)> {
+    let (input, field) = Token::identifier(input)?;
+    let input = next(input);
+
+    match Token::colon(input) {
+        Ok((input, _)) => {
+            let input = next(input);
+            let (input, binding) = Token::identifier(input)?;
+            let input = next(input);
+
+            Ok((input, (field, binding)))
+        }
+        Err(_) => Ok((input, (field.clone(), field))),
+    }
+}
+
 fn unit_func<'i>(
     input: ParseInput<'i>,
     kind: ParseInput<'i>,
     start_loc: Location,
 ) -> ParseResult<ParseInput<'i>, Box<dyn Instruction>> {
+    if *kind.fragment() == "func" {
+        if let Ok((input, _)) = Token::left_parenthesis(next(input)) {
+            return unit_lambda(input, start_loc);
+        }
+    }
+
     let (input, mut function) = func_declaration(input)?;
     let input = next(input);
     let (input, body) = block(input)?;
@@ -340,11 +764,69 @@ fn unit_func<'i>(
     Ok((input, Box::new(function)))
 }
 
+/// lambda_declaration = '(' next typed_arg next return_type
+fn unit_lambda(
+    input: ParseInput,
+    start_loc: Location,
+) -> ParseResult<ParseInput, Box<dyn Instruction>> {
+    let input = next(input);
+    let (input, args) = typed_args(input)?;
+    let input = next(input);
+    let (input, return_type) = return_type(input)?;
+    let input = next(input);
+    let (input, body) = block(input)?;
+    let (input, end_loc) = position(input)?;
+
+    let location = SpanTuple::new(input.extra, start_loc, end_loc.into());
+    let mut function = FunctionDec::new(String::new(), return_type, vec![], args);
+    function.set_location(location.clone());
+    function.set_block(body);
+    function.set_kind(FunctionKind::Func);
+
+    let mut lambda = Lambda::new(function);
+    lambda.set_location(location);
+    Ok((input, Box::new(lambda)))
+}
+
+/// test_declaration = function_declaration
+///                  | next spaced_identifier next
+fn unit_test(
+    input: ParseInput,
+    start_loc: Location,
+) -> ParseResult<ParseInput, Box<dyn Instruction>> {
+    let (input, mut test) = match func_declaration(input) {
+        Ok(res) => res,
+        Err(_) => {
+            let (input, (id, _)) = spaced_identifier(next(input))?;
+            (next(input), FunctionDec::new(id, None, vec![], vec![]))
+        }
+    };
+    let input = next(input);
+    let (input, body) = block(input)?;
+    let (input, end_loc) = position(input)?;
+    test.set_location(SpanTuple::new(input.extra, start_loc, end_loc.into()));
+    test.set_block(body);
+    test.set_kind(FunctionKind::Test);
+    Ok((input, Box::new(test)))
+}
+
+/// incl_path = spaced_identifier
+///           | next '"' [^"]* '"' next
+fn incl_path(input: ParseInput) -> ParseResult<ParseInput, (String, Location)> {
+    let spaced = next(input);
+    if let Ok((input, path)) = Token::string_constant(spaced) {
+        let (_, loc) = position(spaced)?;
+        return Ok((next(input), (path.fragment().to_string(), loc.into())));
+    }
+
+    spaced_identifier(input)
+}
+
 fn unit_incl(
     input: ParseInput,
     start_loc: Location,
 ) -> ParseResult<ParseInput, Box<dyn Instruction>> {
-    let (input, (path, id_loc)) = spaced_identifier(input)?;
+    let (input, (path, id_loc)) = incl_path(input)?;
     if let Ok((input, _)) = Token::az_tok(input) {
         let (input, alias) = preceded(nom_next, Token::identifier)(input)?;
         let (input, end_loc) = position(input)?;
@@ -359,19 +841,9 @@ fn unit_incl(
     }
 }
 
-fn type_id(input: ParseInput) -> ParseResult<ParseInput, TypeId> {
+pub(crate) fn type_id(input: ParseInput) -> ParseResult<ParseInput, TypeId> {
     fn arg_types(input: ParseInput) -> ParseResult<ParseInput, Vec<TypeId>> {
-        if let Ok((input, _)) = Token::right_parenthesis(input) {
-            return Ok((input, vec![]));
-        }
-
-        let (input, first_arg) = type_id(input)?;
-        let (input, mut args) = many0(preceded(Token::comma, type_id))(input)?;
-        let (input, _) = Token::right_parenthesis(input)?;
-
-        args.insert(0, first_arg);
-
-        Ok((input, args))
+        comma_list(type_id, Token::right_parenthesis)(input)
     }
 
     fn return_type(input: ParseInput) -> ParseResult<ParseInput, TypeId> {
@@ -382,6 +854,8 @@ fn type_id(input: ParseInput) -> ParseResult<ParseInput, TypeId> {
     }
 
     let input = next(input);
+    let _nesting = Nesting::enter(input)?;
+
     if let Ok((input, _)) = Token::func_tok(input) {
         let ty_id = TypeId::functor();
 
@@ -402,7 +876,7 @@ fn type_id(input: ParseInput) -> ParseResult<ParseInput, TypeId> {
 
         Ok((input, ty_id))
     } else {
-        let (input, (id, _)) = spaced_identifier(input)?;
+        let (input, (id, _)) = error::label(&["type"], spaced_identifier)(input)?;
         let ty_id = TypeId::new(Symbol::from(id));
 
         let (input, generics) = maybe_generic_list(input)?;
@@ -414,35 +888,131 @@ fn type_id(input: ParseInput) -> ParseResult<ParseInput, TypeId> {
     }
 }
 
-/// type_id '(' type_inst_arg (',' type_inst_arg)* ')'
+/// unit_type_decl = spaced_identifier [ generic_list ] [ type_fields ]
+///                | spaced_identifier '=' type_variant ( '|' type_variant )*
 fn unit_type_decl(
     input: ParseInput,
     start_loc: Location,
 ) -> ParseResult<ParseInput, Box<dyn Instruction>> {
     // FIXME: This needs to use TypeIds
-    let (input, (name, _)) = spaced_identifier(input)?;
-    let (input, generics) = maybe_generic_list(input)?;
-    let (input, mut type_dec) = if let Ok((input, _)) = Token::left_parenthesis(input) {
-        let (input, first_arg) = typed_arg(input)?;
-        let (input, mut args) = many0(preceded(Token::comma, typed_arg))(input)?;
-        let (input, _) = Token::right_parenthesis(input)?;
+    let (input, (name, _)) = error::label(&["type name"], spaced_identifier)(input)?;
 
-        args.insert(0, first_arg);
+    if let Ok((input, _)) = Token::equal(input) {
+        let (input, first) = type_variant(input)?;
+        let (input, mut variants) = many0(preceded(Token::pipe, type_variant))(input)?;
+        let (input, end_loc) = position(input)?;
 
-        (input, TypeDec::new(name, generics, args))
-    } else {
-        (input, TypeDec::new(name, generics, vec![]))
-    };
+        variants.insert(0, first);
+
+        let mut type_dec = TypeDec::new_enum(name, variants);
+        type_dec.set_location(SpanTuple::new(input.extra, start_loc, end_loc.into()));
+
+        return Ok((input, Box::new(type_dec)));
+    }
+
+    let (input, generics) = maybe_generic_list(input)?;
+    let (input, fields) = type_fields(input)?;
     let (input, end_loc) = position(input)?;
 
+    let mut type_dec = TypeDec::new(name, generics, fields);
     type_dec.set_location(SpanTuple::new(input.extra, start_loc, end_loc.into()));
 
     Ok((input, Box::new(type_dec)))
 }
 
+/// type_variant = spaced_identifier [ type_fields ] next
+fn type_variant(input: ParseInput) -> ParseResult<ParseInput, TypeDec> {
+    let (input, (name, start_loc)) = spaced_identifier(input)?;
+    let (input, fields) = type_fields(input)?;
+    let (input, end_loc) = position(input)?;
+
+    let mut variant = TypeDec::new(name, vec![], fields);
+    variant.set_location(SpanTuple::new(input.extra, start_loc, end_loc.into()));
+
+    Ok((next(input), variant))
+}
+
+/// type_fields = '(' comma_list1(type_field, ')')
+///             | ε
+fn type_fields(input: ParseInput) -> ParseResult<ParseInput, Vec<DecArg>> {
+    match Token::left_parenthesis(input) {
+        Ok((input, _)) => comma_list1(type_field, Token::right_parenthesis)(input),
+        Err(_) => Ok((input, vec![])),
+    }
+}
+
+/// spaced_identifier '{' next ( 'func' function_declaration ';' next )* '}'
+fn unit_interface(
+    input: ParseInput,
+    start_loc: Location,
+) -> ParseResult<ParseInput, Box<dyn Instruction>> {
+    fn signature(input: ParseInput) -> ParseResult<ParseInput, FunctionDec> {
+        let (input, start_loc) = position(input)?;
+        let (input, mut dec) =
+            delimited(Token::func_tok, func_declaration, Token::semicolon)(input)?;
+        let (input, end_loc) = position(input)?;
+
+        dec.set_kind(FunctionKind::Func);
+        dec.set_location(SpanTuple::new(
+            input.extra,
+            start_loc.into(),
+            end_loc.into(),
+        ));
+        Ok((next(input), dec))
+    }
+
+    let (input, (name, _)) = spaced_identifier(input)?;
+    let (input, _) = Token::left_curly_bracket(input)?;
+    let (input, functions) = many0(signature)(next(input))?;
+    let (input, _) = Token::right_curly_bracket(input)?;
+    let (input, end_loc) = position(input)?;
+
+    let mut interface = InterfaceDec::new(name, functions);
+    interface.set_location(SpanTuple::new(input.extra, start_loc, end_loc.into()));
+    Ok((input, Box::new(interface)))
+}
+
+/// spaced_identifier 'for' type_id '{' next ( 'func' function_declaration block next )* '}'
+fn unit_impl(
+    input: ParseInput,
+    start_loc: Location,
+) -> ParseResult<ParseInput, Box<dyn Instruction>> {
+    fn function(input: ParseInput) -> ParseResult<ParseInput, FunctionDec> {
+        let (input, start_loc) = position(input)?;
+        let (input, _) = Token::func_tok(input)?;
+        let (input, mut dec) = func_declaration(input)?;
+        let (input, body) = block(next(input))?;
+        let (input, end_loc) = position(input)?;
+
+        dec.set_kind(FunctionKind::Func);
+        dec.set_block(body);
+        dec.set_location(SpanTuple::new(
+            input.extra,
+            start_loc.into(),
+            end_loc.into(),
+        ));
+        Ok((next(input), dec))
+    }
+
+    let (input, (interface, _)) = spaced_identifier(input)?;
+    let (input, _) = Token::for_tok(input)?;
+    let (input, ty) = type_id(input)?;
+    let (input, _) = Token::left_curly_bracket(next(input))?;
+    let (input, functions) = many0(function)(next(input))?;
+    let (input, _) = Token::right_curly_bracket(input)?;
+    let (input, end_loc) = position(input)?;
+
+    let mut implementation = InterfaceImpl::new(interface, ty, functions);
+    implementation.set_location(SpanTuple::new(input.extra, start_loc, end_loc.into()));
+    Ok((input, Box::new(implementation)))
+}
+
 /// spaced_identifier '=' expr
-fn unit_mut_var(input: ParseInput) -> ParseResult<ParseInput, Box<dyn Instruction>> {
-    let (input, (symbol, start_loc)) = spaced_identifier(input)?;
+fn unit_mut_var(
+    input: ParseInput,
+    start_loc: Location,
+) -> ParseResult<ParseInput, Box<dyn Instruction>> {
+    let (input, (symbol, _)) = spaced_identifier(input)?;
     let (input, _) = Token::equal(input)?;
     let (input, value) = expr(input)?;
     let (input, end_loc) = position(input)?;
@@ -458,6 +1028,7 @@ fn unit_jk_inst(
     input: ParseInput,
     start_loc: Location,
 ) -> ParseResult<ParseInput, Box<dyn Instruction>> {
+    let start = input;
     let (input, name) = delimited(nom_next, Token::identifier, nom_next)(input)?;
     let (input, _) = Token::left_parenthesis(input)?;
     let (input, args) = args(next(input))?;
@@ -468,16 +1039,25 @@ fn unit_jk_inst(
     call.set_location(SpanTuple::new(input.extra, start_loc, end_loc.into()));
     match JkInst::from_function_call(&call) {
         Ok(inst) => Ok((input, Box::new(inst))),
-        Err(err) => Err(NomError(err)),
+        Err(err) => Err(NomError(
+            ParseError::new(start)
+                .with_msg(err.msg().unwrap_or_default())
+                .with_end(input),
+        )),
     }
 }
 
 /// 'func' function_declaration ';'
-fn unit_extern(input: ParseInput) -> ParseResult<ParseInput, Box<dyn Instruction>> {
+fn unit_extern(
+    input: ParseInput,
+    start_loc: Location,
+) -> ParseResult<ParseInput, Box<dyn Instruction>> {
     let input = next(input);
     let (input, mut dec) = delimited(Token::func_tok, func_declaration, Token::semicolon)(input)?;
+    let (input, end_loc) = position(input)?;
 
     dec.set_kind(FunctionKind::Ext);
+    dec.set_location(SpanTuple::new(input.extra, start_loc, end_loc.into()));
     Ok((input, Box::new(dec)))
 }
 
@@ -495,6 +1075,32 @@ fn unit_return(
     Ok((input, Box::new(ret)))
 }
 
+///  [ expr ]
+fn unit_break(
+    input: ParseInput,
+    start_loc: Location,
+) -> ParseResult<ParseInput, Box<dyn Instruction>> {
+    let (input, expr) = opt(expr)(input)?;
+    let (input, end_loc) = position(input)?;
+
+    let mut brk = Break::new(expr);
+    brk.set_location(SpanTuple::new(input.extra, start_loc, end_loc.into()));
+
+    Ok((input, Box::new(brk)))
+}
+
+fn unit_continue(
+    input: ParseInput,
+    start_loc: Location,
+) -> ParseResult<ParseInput, Box<dyn Instruction>> {
+    let (input, end_loc) = position(input)?;
+
+    let mut cont = Continue::new();
+    cont.set_location(SpanTuple::new(input.extra, start_loc, end_loc.into()));
+
+    Ok((input, Box::new(cont)))
+}
+
 fn unit_block(
     input: ParseInput,
     start_loc: Location,
@@ -517,12 +1123,7 @@ fn generic_list(input: ParseInput) -> ParseResult<ParseInput, Vec<TypeId>> {
         Ok((input, id))
     }
 
-    let (input, first_type) = whitespace_plus_type_id(input)?;
-    let (input, mut generics) = many0(preceded(Token::comma, whitespace_plus_type_id))(input)?;
-    let (input, _) = Token::right_bracket(input)?;
-
-    generics.insert(0, first_type);
-    Ok((input, generics))
+    comma_list1(whitespace_plus_type_id, Token::right_bracket)(input)
 }
 
 fn maybe_generic_list(input: ParseInput) -> ParseResult<ParseInput, Vec<TypeId>> {
@@ -536,7 +1137,7 @@ fn maybe_generic_list(input: ParseInput) -> ParseResult<ParseInput, Vec<TypeId>>
 /// function_declaration = next spaced_identifier [ next '[' spaced_identifier ( ',' spaced_identifier )* ']' ] next '(' next typed_arg next return_type
 fn func_declaration(input: ParseInput) -> ParseResult<ParseInput, FunctionDec> {
     let input = next(input);
-    let (input, (id, _)) = spaced_identifier(input)?;
+    let (input, (id, _)) = error::label(&["function name"], spaced_identifier)(input)?;
     let input = next(input);
 
     let (input, generics) = maybe_generic_list(input)?;
@@ -582,12 +1183,13 @@ pub fn block(input: ParseInput) -> ParseResult<ParseInput, Block> {
 
 /// inner_block = '}'
 ///             | expr '}'                  (* The only case where block is an expr *)
-///             | expr ';' next inner_block
+///             | expr statement_end next inner_block
 fn inner_block(input: ParseInput) -> ParseResult<ParseInput, Block> {
     if let Ok((input, _)) = Token::right_curly_bracket(input) {
         return Ok((input, Block::new()));
     }
 
+    let start = input;
     let (input, inst) = expr(input)?;
     if let Ok((input, _)) = Token::right_curly_bracket(input) {
         let mut block = Block::new();
@@ -596,11 +1198,66 @@ fn inner_block(input: ParseInput) -> ParseResult<ParseInput, Block> {
         return Ok((input, block));
     }
 
-    let (input, mut block) = preceded(Token::semicolon, preceded(nom_next, inner_block))(input)?;
+    let (input, mut block) =
+        preceded(statement_end(start), preceded(nom_next, inner_block))(input)?;
     block.push_front_instruction(inst);
     Ok((input, block))
 }
 
+/// Parse the semicolon terminating the statement which began at `start`. When another
+/// statement follows without it, the error points at the end of the statement instead
+/// of at the next one, which is usually on the following line
+///
+/// statement_end = ';'
+fn statement_end<'i>(
+    start: ParseInput<'i>,
+) -> impl FnMut(ParseInput<'i>) -> ParseResult<ParseInput<'i>, char> {
+    move |input| {
+        Token::semicolon(input).map_err(|e| match e {
+            NomError(_) if starts_statement(&input) => {
+                let offset = input.location_offset() - start.location_offset();
+                let (last, end) = last_token(&start.fragment()[..offset]);
+
+                nom::Err::Failure(
+                    ParseError::new(start.slice(last..))
+                        .with_msg("expected `;` after statement")
+                        .with_end(start.slice(end..)),
+                )
+            }
+            e => e,
+        })
+    }
+}
+
+/// Get the bounds of the last character of a statement which is not part of a comment
+/// or of the whitespace following it
+fn last_token(statement: &str) -> (usize, usize) {
+    let mut last = (0, 0);
+    let mut chars = statement.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '/' if skip_comment(&mut chars) => {}
+            '"' | '\'' => {
+                let end = skip_quoted(&mut chars, c).map_or(statement.len(), |j| j + 1);
+                last = (end - 1, end);
+            }
+            c if c.is_whitespace() => {}
+            c => last = (i, i + c.len_utf8()),
+        }
+    }
+
+    last
+}
+
+/// Can the input be the beginning of a statement, such as an identifier, a keyword, a
+/// constant or a block
+fn starts_statement(input: &ParseInput) -> bool {
+    input
+        .fragment()
+        .starts_with(|c: char| c.is_alphanumeric() || matches!(c, '_' | '"' | '\'' | '{' | '@'))
+}
+
 /// func_type_or_var = '(' next func_or_type_inst_args
 ///                  | '=' expr                   (* variable assigment *)
 ///                  | ε                          (* variable or empty type instantiation *)
@@ -627,33 +1284,23 @@ fn func_type_or_var(
     }
 }
 
-/// func_or_type_inst_args = IDENTIFIER next ':' expr (',' type_inst_arg )* ')'  (* type_instantiation *)
-///                  | args                                            (* function_call *)
+/// func_or_type_inst_args = comma_list1(type_inst_arg, ')')  (* type_instantiation *)
+///                        | args                              (* function_call *)
+///
+/// Positional type instantiations are parsed as function calls, and resolved when
+/// typechecking
 fn func_or_type_inst_args(
     input: ParseInput,
     id: String,
     generics: Vec<TypeId>,
     start_loc: Location,
 ) -> ParseResult<ParseInput, Box<dyn Instruction>> {
-    let (input, first_attr_start_loc) = position(input)?;
-    if let Ok((input, first_attr)) =
-        terminated(terminated(Token::identifier, nom_next), Token::colon)(input)
-    {
-        let input = next(input);
-        let (input, first_attr_val) = expr(input)?;
-        let (input, first_attr_end_loc) = position(input)?;
-        let (input, attrs) = many0(preceded(Token::comma, type_inst_arg))(input)?;
-        let (input, _) = Token::right_parenthesis(input)?;
+    let is_type_inst = terminated(terminated(Token::identifier, nom_next), Token::colon)(input);
+    if is_type_inst.is_ok() {
+        let (input, attrs) = comma_list1(type_inst_arg, Token::right_parenthesis)(input)?;
         let (input, end_loc) = position(input)?;
 
         let mut type_inst = TypeInstantiation::new(TypeId::new(Symbol::from(id)));
-        type_inst.add_field(VarAssign::new(false, first_attr, first_attr_val).with_loc(
-            SpanTuple::new(
-                input.extra,
-                first_attr_start_loc.into(),
-                first_attr_end_loc.into(),
-            ),
-        ));
         attrs.into_iter().for_each(|attr| type_inst.add_field(attr));
 
         type_inst.set_generics(generics);
@@ -683,36 +1330,24 @@ fn generic_func_or_type_inst_args(
     func_or_type_inst_args(next(input), id, generics, start_loc)
 }
 
-///
-/// ARGS
-///
+//
+// ARGS
+//
 
-/// args = expr ( ',' expr )* ')'
-///      | ')'
+/// args = comma_list(expr, ')')
 fn args(input: ParseInput) -> ParseResult<ParseInput, Vec<Box<dyn Instruction>>> {
-    if let Ok((input, _)) = Token::right_parenthesis(input) {
-        return Ok((input, vec![]));
-    }
-    let (input, first_arg) = expr(input)?;
-    let (input, mut args) = many0(preceded(Token::comma, expr))(input)?;
-    let (input, _) = Token::right_parenthesis(input)?;
-
-    args.insert(0, first_arg);
-    Ok((input, args))
+    comma_list(
+        error::label(&["expression", "`)`"], expr),
+        Token::right_parenthesis,
+    )(input)
 }
 
-/// typed_args = typed_arg ( ',' typed_arg )* ')'
-///           | ')'
+/// typed_args = comma_list(typed_arg, ')')
 fn typed_args(input: ParseInput) -> ParseResult<ParseInput, Vec<DecArg>> {
-    if let Ok((input, _)) = Token::right_parenthesis(input) {
-        return Ok((input, vec![]));
-    }
-    let (input, first_arg) = typed_arg(input)?;
-    let (input, mut args) = many0(preceded(Token::comma, typed_arg))(input)?;
-    let (input, _) = Token::right_parenthesis(input)?;
-
-    args.insert(0, first_arg);
-    Ok((input, args))
+    comma_list(
+        error::label(&["argument", "`)`"], typed_arg),
+        Token::right_parenthesis,
+    )(input)
 }
 
 // FIXME: This should not return a String
@@ -748,6 +1383,20 @@ fn typed_arg(input: ParseInput) -> ParseResult<ParseInput, DecArg> {
     Ok((input, dec_arg))
 }
 
+/// type_field = typed_arg [ '=' expr ]
+fn type_field(input: ParseInput) -> ParseResult<ParseInput, DecArg> {
+    let (input, mut field) = typed_arg(input)?;
+
+    if let Ok((input, _)) = Token::equal(input) {
+        let (input, default) = expr(input)?;
+        field.set_default(default);
+
+        return Ok((input, field));
+    }
+
+    Ok((input, field))
+}
+
 /// type_inst_arg = spaced_identifier ':' expr
 fn type_inst_arg(input: ParseInput) -> ParseResult<ParseInput, VarAssign> {
     let input = next(input);
@@ -783,12 +1432,17 @@ fn spaced_identifier(input: ParseInput) -> ParseResult<ParseInput, (String, Loca
 ///       | '/*' [^'*/'] '*/'
 ///       | '//' [^\n]   '\n'
 ///       | '#'  [^\n]   '\n'
-pub fn next(input: ParseInput) -> ParseInput {
-    // FIXME: Do not unwrap
-    let (input, _) = multispace0::<_, Error>(input).unwrap();
-    match Token::consume_comment(input) {
-        Ok((input, _)) => next(input),
-        _ => input,
+pub fn next(mut input: ParseInput) -> ParseInput {
+    loop {
+        // Skipping whitespace never fails: There might just be none
+        if let Ok((new_input, _)) = multispace0::<_, ParseError<_>>(input) {
+            input = new_input;
+        }
+
+        match Token::consume_comment(input) {
+            Ok((new_input, _)) => input = new_input,
+            _ => return input,
+        }
     }
 }
 
@@ -796,10 +1450,63 @@ fn nom_next(input: ParseInput) -> ParseResult<ParseInput, ()> {
     Ok((next(input), ()))
 }
 
-/// Constants are raw values in the source code. For example, `"string"`, `12` and
-/// `0.5`.
+/// Parse a list of elements separated by commas, up to and including the token closing
+/// it. A comma may follow the last element, as in `f(1, 2,)`. Any other comma must be
+/// followed by an element, whose error is returned if it cannot be parsed
+///
+/// comma_list = [ element ( ',' element )* [ ',' ] ] next closing
+fn comma_list<'i, O, C>(
+    element: impl FnMut(ParseInput<'i>) -> ParseResult<ParseInput<'i>, O>,
+    closing: impl FnMut(ParseInput<'i>) -> ParseResult<ParseInput<'i>, C>,
+) -> impl FnMut(ParseInput<'i>) -> ParseResult<ParseInput<'i>, Vec<O>> {
+    comma_list_inner(true, element, closing)
+}
+
+/// Same as [`comma_list`], for lists which contain at least one element
+///
+/// comma_list1 = element ( ',' element )* [ ',' ] next closing
+fn comma_list1<'i, O, C>(
+    element: impl FnMut(ParseInput<'i>) -> ParseResult<ParseInput<'i>, O>,
+    closing: impl FnMut(ParseInput<'i>) -> ParseResult<ParseInput<'i>, C>,
+) -> impl FnMut(ParseInput<'i>) -> ParseResult<ParseInput<'i>, Vec<O>> {
+    comma_list_inner(false, element, closing)
+}
+
+fn comma_list_inner<'i, O, C>(
+    allow_empty: bool,
+    mut element: impl FnMut(ParseInput<'i>) -> ParseResult<ParseInput<'i>, O>,
+    mut closing: impl FnMut(ParseInput<'i>) -> ParseResult<ParseInput<'i>, C>,
+) -> impl FnMut(ParseInput<'i>) -> ParseResult<ParseInput<'i>, Vec<O>> {
+    move |mut input| {
+        let mut elements = vec![];
+
+        loop {
+            if allow_empty || !elements.is_empty() {
+                if let Ok((input, _)) = closing(next(input)) {
+                    return Ok((input, elements));
+                }
+            }
+
+            let (after_element, elt) = element(input)?;
+            let after_element = next(after_element);
+            elements.push(elt);
+
+            // Either the list goes on, or it is closed right after the element
+            let (after_separator, is_comma) =
+                alt((map(Token::comma, |_| true), map(&mut closing, |_| false)))(after_element)?;
+
+            match is_comma {
+                true => input = after_separator,
+                false => return Ok((after_separator, elements)),
+            }
+        }
+    }
+}
+
+/// Constants are raw values in the source code. For example, `"string"`, `12`, `0.5`
+/// and `2.5e-3`.
 ///
-/// `'<any_char>' | "<any_char>*" | <num>? | <num>?.<num>?`
+/// `'<any_char>' | "<any_char>*" | <num>? | <num>?.<num>?[e[+-]<num>]`
 pub(crate) fn constant(input: ParseInput) -> ParseResult<ParseInput, Box<dyn Instruction>> {
     let constant = alt((
         ConstantConstruct::char_constant,
@@ -922,6 +1629,109 @@ mod tests {
         assert_eq!(fifth.print(), "1.1");
     }
 
+    #[test]
+    fn left_associative_sub() {
+        let (input, expr) = expr(span!("1 - 2 - 3")).unwrap();
+        let outer: &BinaryOp = expr.downcast_ref().unwrap();
+        let inner: &BinaryOp = outer.lhs().downcast_ref().unwrap();
+
+        let first: &JkInt = inner.lhs().downcast_ref().unwrap();
+        let second: &JkInt = inner.rhs().downcast_ref().unwrap();
+        let third: &JkInt = outer.rhs().downcast_ref().unwrap();
+
+        assert_eq!(*input.fragment(), "");
+        assert_eq!(first.print(), "1");
+        assert_eq!(second.print(), "2");
+        assert_eq!(third.print(), "3");
+    }
+
+    #[test]
+    fn left_associative_div() {
+        let (input, expr) = expr(span!("100 / 10 / 5")).unwrap();
+        let outer: &BinaryOp = expr.downcast_ref().unwrap();
+        let inner: &BinaryOp = outer.lhs().downcast_ref().unwrap();
+
+        let first: &JkInt = inner.lhs().downcast_ref().unwrap();
+        let third: &JkInt = outer.rhs().downcast_ref().unwrap();
+
+        assert_eq!(*input.fragment(), "");
+        assert_eq!(first.print(), "100");
+        assert_eq!(third.print(), "5");
+    }
+
+    #[test]
+    fn right_associative_pow() {
+        let (input, expr) = expr(span!("2 ** 3 ** 2")).unwrap();
+        let outer: &BinaryOp = expr.downcast_ref().unwrap();
+        let inner: &BinaryOp = outer.rhs().downcast_ref().unwrap();
+
+        let first: &JkInt = outer.lhs().downcast_ref().unwrap();
+        let second: &JkInt = inner.lhs().downcast_ref().unwrap();
+
+        assert_eq!(*input.fragment(), "");
+        assert_eq!(outer.operator(), Operator::Pow);
+        assert_eq!(inner.operator(), Operator::Pow);
+        assert_eq!(first.print(), "2");
+        assert_eq!(second.print(), "3");
+    }
+
+    #[test]
+    fn pow_precedence() {
+        let (input, expr) = expr(span!("2 * 3 ** 2")).unwrap();
+        let mul: &BinaryOp = expr.downcast_ref().unwrap();
+        let pow: &BinaryOp = mul.rhs().downcast_ref().unwrap();
+
+        assert_eq!(*input.fragment(), "");
+        assert_eq!(mul.operator(), Operator::Mul);
+        assert_eq!(pow.operator(), Operator::Pow);
+    }
+
+    #[test]
+    fn bitwise_precedence() {
+        let (input, expr) = expr(span!("a | b ^ c & d << 1 == 0")).unwrap();
+        let eq: &BinaryOp = expr.downcast_ref().unwrap();
+        let or: &BinaryOp = eq.lhs().downcast_ref().unwrap();
+        let xor: &BinaryOp = or.rhs().downcast_ref().unwrap();
+        let and: &BinaryOp = xor.rhs().downcast_ref().unwrap();
+        let shl: &BinaryOp = and.rhs().downcast_ref().unwrap();
+
+        assert_eq!(*input.fragment(), "");
+        assert_eq!(eq.operator(), Operator::Equals);
+        assert_eq!(or.operator(), Operator::BitOr);
+        assert_eq!(xor.operator(), Operator::BitXor);
+        assert_eq!(and.operator(), Operator::BitAnd);
+        assert_eq!(shl.operator(), Operator::Shl);
+    }
+
+    #[test]
+    fn shift_and_comparison() {
+        let (input, expr) = expr(span!("a << 2 <= b >> 1")).unwrap();
+        let cmp: &BinaryOp = expr.downcast_ref().unwrap();
+        let shl: &BinaryOp = cmp.lhs().downcast_ref().unwrap();
+        let shr: &BinaryOp = cmp.rhs().downcast_ref().unwrap();
+
+        assert_eq!(*input.fragment(), "");
+        assert_eq!(cmp.operator(), Operator::LtEq);
+        assert_eq!(shl.operator(), Operator::Shl);
+        assert_eq!(shr.operator(), Operator::Shr);
+    }
+
+    #[test]
+    fn binary_op_terminators() {
+        assert_eq!(*expr(span!("1 + 2; 3")).unwrap().0.fragment(), "; 3");
+        assert_eq!(*expr(span!("1 + 2) * 3")).unwrap().0.fragment(), ") * 3");
+        assert_eq!(*expr(span!("1 * 2, 3")).unwrap().0.fragment(), ", 3");
+        assert_eq!(*expr(span!("1 - 2 }")).unwrap().0.fragment(), "}");
+    }
+
+    #[test]
+    fn binary_op_in_args() {
+        let (input, expr) = expr(span!("f(1 - 2 - 3, 4 * (5 + 6))")).unwrap();
+
+        assert!(expr.downcast_ref::<FunctionCall>().is_some());
+        assert_eq!(*input.fragment(), "");
+    }
+
     #[test]
     fn method_call_no_arg() {
         let (input, expr) = expr(span!("a.call( )")).unwrap();
@@ -1002,6 +1812,34 @@ mod tests {
         assert_eq!(*input.fragment(), "");
     }
 
+    #[test]
+    fn if_else_if() {
+        let (input, expr) = expr(span!(
+            "if a { 1 } else if b { 2 } else if c { 3 } else { 4 }"
+        ))
+        .unwrap();
+        assert_eq!(*input.fragment(), "");
+
+        // The chain is nested to the right
+        let first = expr.downcast_ref::<IfElse>().unwrap();
+        let second = first.else_if().unwrap();
+        let third = second.else_if().unwrap();
+        assert!(third.else_if().is_none());
+
+        assert_eq!(second.location().unwrap().start().column(), 17);
+        assert_eq!(
+            first.location().unwrap().end(),
+            third.location().unwrap().end()
+        );
+    }
+
+    #[test]
+    fn if_else_if_invalid() {
+        assert!(expr(span!("if a { 1 } else if { 2 }")).is_err());
+        assert!(expr(span!("if a { 1 } else if b { 2 } else")).is_err());
+        assert!(expr(span!("if a { 1 } else iff { 2 }")).is_err());
+    }
+
     #[test]
     fn while_loop() {
         let (input, expr) = expr(span!("while true { var + 10 }")).unwrap();
@@ -1018,6 +1856,152 @@ mod tests {
         assert_eq!(*input.fragment(), "");
     }
 
+    #[test]
+    fn for_loop_range() {
+        let (input, expr) = expr(span!("for i in 0..10 { i.print() }")).unwrap();
+
+        assert!(expr.downcast_ref::<Loop>().is_some());
+        assert_eq!(*input.fragment(), "");
+    }
+
+    #[test]
+    fn range_exclusive() {
+        let (input, expr) = expr(span!("0..10")).unwrap();
+        let range = expr.downcast_ref::<Range>().unwrap();
+
+        assert!(!range.is_inclusive());
+        assert_eq!(range.start().print(), "0");
+        assert_eq!(range.end().print(), "10");
+        assert_eq!(*input.fragment(), "");
+    }
+
+    #[test]
+    fn range_inclusive() {
+        let (input, expr) = expr(span!("a + 1..=b * 2")).unwrap();
+        let range = expr.downcast_ref::<Range>().unwrap();
+
+        assert!(range.is_inclusive());
+        assert_eq!(range.start().print(), "a + 1");
+        assert_eq!(range.end().print(), "b * 2");
+        assert_eq!(*input.fragment(), "");
+    }
+
+    #[test]
+    fn range_method_call() {
+        let (input, expr) = expr(span!("(0..10).len()")).unwrap();
+
+        assert!(expr.downcast_ref::<MethodCall>().is_some());
+        assert_eq!(*input.fragment(), "");
+    }
+
+    #[test]
+    fn field_access_is_not_range() {
+        let (input, expr) = expr(span!("a.b")).unwrap();
+
+        assert!(expr.downcast_ref::<Range>().is_none());
+        assert_eq!(*input.fragment(), "");
+    }
+
+    #[test]
+    fn try_operator() {
+        let (input, expr) = expr(span!("a?")).unwrap();
+        let try_op = expr.downcast_ref::<TryOp>().unwrap();
+
+        assert_eq!(try_op.operand().print(), "a");
+        assert_eq!(*input.fragment(), "");
+    }
+
+    #[test]
+    fn try_operator_chained() {
+        let (input, expr) = expr(span!("a.f()?.g()? + 1")).unwrap();
+        let binop = expr.downcast_ref::<BinaryOp>().unwrap();
+
+        assert!(binop.lhs().downcast_ref::<TryOp>().is_some());
+        assert_eq!(binop.lhs().print(), "a.f()?.g()?");
+        assert_eq!(*input.fragment(), "");
+    }
+
+    #[test]
+    fn match_block() {
+        let (input, expr) = expr(span!(
+            "match x { 1 -> { a }, Point(x: px, y) -> { b }, _ -> { c } }"
+        ))
+        .unwrap();
+        let match_block = expr.downcast_ref::<Match>().unwrap();
+
+        assert_eq!(match_block.arms().len(), 3);
+        assert!(matches!(
+            match_block.arms()[0].pattern(),
+            Pattern::Constant(_)
+        ));
+        assert!(matches!(
+            match_block.arms()[1].pattern(),
+            Pattern::Type(_, bindings) if bindings.len() == 2
+        ));
+        assert!(matches!(match_block.arms()[2].pattern(), Pattern::Wildcard));
+        assert_eq!(*input.fragment(), "");
+    }
+
+    #[test]
+    fn match_block_without_commas() {
+        let (input, expr) = expr(span!("match x {\n-1 -> { a }\nNothing -> { b }\n}")).unwrap();
+        let match_block = expr.downcast_ref::<Match>().unwrap();
+
+        assert_eq!(match_block.arms().len(), 2);
+        assert_eq!(*input.fragment(), "");
+    }
+
+    #[test]
+    fn match_block_empty() {
+        let (input, expr) = expr(span!("match x {}")).unwrap();
+
+        assert!(expr.downcast_ref::<Match>().unwrap().arms().is_empty());
+        assert_eq!(*input.fragment(), "");
+    }
+
+    #[test]
+    fn match_is_keyword() {
+        assert!(expr(span!("match = 1")).is_err());
+    }
+
+    #[test]
+    fn assign_to_keyword() {
+        for input in [
+            "func = 1",
+            "mut = 1",
+            "if = 1",
+            "loop = 1",
+            "true = 1",
+            "mut in = 1",
+        ] {
+            match expr(ParseInput::new_extra(input, None)) {
+                Err(nom::Err::Failure(e) | nom::Err::Error(e)) => {
+                    assert!(Error::from(e)
+                        .msg()
+                        .unwrap()
+                        .ends_with("is a reserved keyword"))
+                }
+                _ => unreachable!("{} should not parse", input),
+            }
+        }
+
+        assert!(expr(span!("true == false")).is_ok());
+        assert!(expr(span!("mut loops = 1")).is_ok());
+    }
+
+    #[test]
+    fn keyword_as_function_name() {
+        for input in ["func loop() {}", "func f(mut: int) {}", "func f[for]() {}"] {
+            assert!(
+                expr(ParseInput::new_extra(input, None)).is_err(),
+                "{} should not parse",
+                input
+            );
+        }
+
+        assert!(expr(span!("func looping(format: int) {}")).is_ok());
+    }
+
     #[test]
     fn loop_basic() {
         let (input, expr) = expr(span!("loop { variable.get() + 10 }")).unwrap();
@@ -1092,6 +2076,23 @@ mod tests {
         assert_eq!(func.fn_kind(), FunctionKind::Test);
     }
 
+    #[test]
+    fn test_dec_without_parentheses() {
+        let (input, expr) = expr(span!("test addition { 1 + 1 }")).unwrap();
+        let test = expr.downcast_ref::<FunctionDec>().unwrap();
+
+        assert_eq!(*input.fragment(), "");
+        assert_eq!(test.name(), "addition");
+        assert_eq!(test.fn_kind(), FunctionKind::Test);
+        assert!(test.args().is_empty());
+    }
+
+    #[test]
+    fn test_dec_without_parentheses_invalid() {
+        assert!(expr(span!("test { 1 + 1 }")).is_err());
+        assert!(expr(span!("test a b { 1 + 1 }")).is_err());
+    }
+
     #[test]
     fn mock_dec_one_arg() {
         let (input, expr) = expr(span!("mock id ( arg: int ) { arg }")).unwrap();
@@ -1179,11 +2180,96 @@ mod tests {
         assert_eq!(dec.fields().len(), 2);
     }
 
+    #[test]
+    fn type_dec_enum() {
+        let (input, expr) = expr(span!(
+            "type Shape = Circle ( r: float ) | Square(side: float)|Empty"
+        ))
+        .unwrap();
+        let dec = expr.downcast_ref::<TypeDec>().unwrap();
+
+        assert_eq!(*input.fragment(), "");
+        assert_eq!(dec.name(), "Shape");
+        assert!(dec.fields().is_empty());
+        assert_eq!(dec.variants().len(), 3);
+        assert_eq!(dec.variants()[0].variant_of(), Some("Shape"));
+        assert_eq!(dec.variants()[2].name(), "Empty");
+        assert_eq!(
+            dec.print(),
+            "type Shape = Circle(r: float) | Square(side: float) | Empty"
+        );
+    }
+
+    #[test]
+    fn type_dec_enum_invalid() {
+        assert!(expr(span!("type Shape =")).is_err());
+        assert!(expr(span!("type Shape = Circle(r: float) |")).is_err());
+    }
+
+    #[test]
+    fn type_dec_default_values() {
+        let (input, expr) = expr(span!("type Point(x: int = 0, y: int = 1 + 2)")).unwrap();
+        let dec = expr.downcast_ref::<TypeDec>().unwrap();
+
+        assert_eq!(*input.fragment(), "");
+        assert_eq!(dec.fields()[0].default_value().unwrap().print(), "0");
+        assert!(dec.fields()[1].default_value().is_some());
+        assert_eq!(dec.print(), "type Point(x: int = 0, y: int = 1 + 2)");
+    }
+
     #[test]
     fn type_dec_incomplete() {
         assert!(expr(span!("type Point( x:int , y: )")).is_err());
     }
 
+    #[test]
+    fn interface_dec() {
+        let (input, expr) = expr(span!(
+            "interface Shape { func area(s: Self) -> int; func name(s: Self) -> string; }"
+        ))
+        .unwrap();
+        let dec = expr.downcast_ref::<InterfaceDec>().unwrap();
+
+        assert_eq!(*input.fragment(), "");
+        assert_eq!(dec.name(), "Shape");
+        assert_eq!(dec.functions().len(), 2);
+        assert_eq!(
+            dec.print(),
+            "interface Shape {\n    func area(s: Self) -> int;\n    func name(s: Self) -> string;\n}"
+        );
+    }
+
+    #[test]
+    fn interface_dec_empty() {
+        let (input, expr) = expr(span!("interface Empty {}")).unwrap();
+
+        assert_eq!(*input.fragment(), "");
+        assert!(expr.downcast_ref::<InterfaceDec>().is_some());
+    }
+
+    #[test]
+    fn interface_dec_with_block() {
+        assert!(expr(span!("interface Shape { func area(s: Self) -> int { 0 } }")).is_err());
+    }
+
+    #[test]
+    fn interface_impl() {
+        let (input, expr) = expr(span!(
+            "impl Shape for Vec[int] { func area(v: Vec[int]) -> int { 0 } }"
+        ))
+        .unwrap();
+        let implementation = expr.downcast_ref::<InterfaceImpl>().unwrap();
+
+        assert_eq!(*input.fragment(), "");
+        assert_eq!(implementation.interface(), "Shape");
+        assert_eq!(implementation.ty().print(), "Vec[int]");
+    }
+
+    #[test]
+    fn interface_impl_without_type() {
+        assert!(expr(span!("impl Shape { func area(s: Square) -> int { 0 } }")).is_err());
+    }
+
     #[test]
     fn include_simple() {
         let (input, expr) = expr(span!("incl pair")).unwrap();
@@ -1200,6 +2286,17 @@ mod tests {
         assert!(expr.downcast_ref::<Incl>().is_some());
     }
 
+    #[test]
+    fn include_string_path() {
+        let (input, expr) = expr(span!("incl \"libs/numpy\" as np")).unwrap();
+
+        assert_eq!(*input.fragment(), "");
+        assert_eq!(
+            expr.downcast_ref::<Incl>().unwrap().print(),
+            "incl \"libs/numpy\" as np"
+        );
+    }
+
     #[test]
     fn include_with_alias_missing_path() {
         assert!(expr(span!("incl as uoh")).is_err());
@@ -1243,10 +2340,10 @@ mod tests {
 
     #[test]
     fn t_binary_op_invalid() {
-        let (input, expr) = expr(span!("a ? 12")).unwrap();
+        let (input, expr) = expr(span!("a @ 12")).unwrap();
 
         assert!(expr.downcast_ref::<BinaryOp>().is_none());
-        assert_eq!(*input.fragment(), "? 12");
+        assert_eq!(*input.fragment(), "@ 12");
     }
 
     #[test]
@@ -1327,6 +2424,28 @@ mod tests {
         assert!(expr.downcast_ref::<Return>().is_some());
     }
 
+    #[test]
+    fn break_and_continue() {
+        let (input, brk) = expr(span!("break")).unwrap();
+        assert_eq!(*input.fragment(), "");
+        assert!(brk.downcast_ref::<Break>().unwrap().value().is_none());
+
+        let (input, brk) = expr(span!("break 10 + 9")).unwrap();
+        assert_eq!(*input.fragment(), "");
+        assert!(brk.downcast_ref::<Break>().unwrap().value().is_some());
+
+        let (input, cont) = expr(span!("continue")).unwrap();
+        assert_eq!(*input.fragment(), "");
+        assert!(cont.downcast_ref::<Continue>().is_some());
+    }
+
+    #[test]
+    fn break_keyword_prefix_identifier() {
+        let (input, expr) = expr(span!("breakfast")).unwrap();
+        assert_eq!(*input.fragment(), "");
+        assert!(expr.downcast_ref::<VarOrEmptyType>().is_some());
+    }
+
     /// Mimic previous parsers behaviour
     #[test]
     #[ignore]
@@ -1367,6 +2486,30 @@ mod tests {
         assert_eq!(func.args().len(), 5);
     }
 
+    #[test]
+    fn function_call_expression_args() {
+        let (input, expr) = expr(span!("f(g(1) + 2, x)")).unwrap();
+        let func = expr.downcast_ref::<FunctionCall>().unwrap();
+
+        assert_eq!(*input.fragment(), "");
+        assert_eq!(func.args().len(), 2);
+        assert!(func.args()[0].downcast_ref::<BinaryOp>().is_some());
+        assert!(func.args()[1].downcast_ref::<VarOrEmptyType>().is_some());
+    }
+
+    #[test]
+    fn function_call_block_and_call_args() {
+        let (input, expr) =
+            expr(span!("f({ y = 3; y }, f(x, x).g(), if a { 1 } else { 2 })")).unwrap();
+        let func = expr.downcast_ref::<FunctionCall>().unwrap();
+
+        assert_eq!(*input.fragment(), "");
+        assert_eq!(func.args().len(), 3);
+        assert!(func.args()[0].downcast_ref::<Block>().is_some());
+        assert!(func.args()[1].downcast_ref::<MethodCall>().is_some());
+        assert!(func.args()[2].downcast_ref::<IfElse>().is_some());
+    }
+
     #[test]
     fn function_call_missing_paren() {
         assert!(expr(span!("concat( 'h','e', 'l' , 'l', 'o'")).is_err());
@@ -1487,6 +2630,45 @@ func void() { }"##
         assert!(expr(span!("lhs + (rhs - lhs)")).is_ok())
     }
 
+    #[test]
+    fn unary_negate() {
+        let (input, expr) = expr(span!("-a")).unwrap();
+        let op = expr.downcast_ref::<UnaryOp>().unwrap();
+
+        assert_eq!(*input.fragment(), "");
+        assert_eq!(op.operator(), UnaryOperator::Negate);
+    }
+
+    #[test]
+    fn unary_not() {
+        let (input, expr) = expr(span!("!a.is_empty()")).unwrap();
+        let op = expr.downcast_ref::<UnaryOp>().unwrap();
+
+        assert_eq!(*input.fragment(), "");
+        assert_eq!(op.operator(), UnaryOperator::Not);
+        assert!(op.operand().downcast_ref::<MethodCall>().is_some());
+    }
+
+    #[test]
+    fn unary_in_binop() {
+        let (input, expr) = expr(span!("a - -b * !c")).unwrap();
+        let sub: &BinaryOp = expr.downcast_ref().unwrap();
+        let mul: &BinaryOp = sub.rhs().downcast_ref().unwrap();
+
+        assert_eq!(*input.fragment(), "");
+        assert_eq!(sub.operator(), Operator::Sub);
+        assert!(mul.lhs().downcast_ref::<UnaryOp>().is_some());
+        assert!(mul.rhs().downcast_ref::<UnaryOp>().is_some());
+    }
+
+    #[test]
+    fn unary_negative_constant() {
+        let (input, expr) = expr(span!("-12")).unwrap();
+
+        assert_eq!(*input.fragment(), "");
+        assert!(expr.downcast_ref::<JkInt>().is_some());
+    }
+
     #[test]
     fn parentheses() {
         let (input, expr) = expr(span!("4 * (3 + 5)")).unwrap();
@@ -1568,6 +2750,47 @@ func void() { }"##
         assert!(expr(span!("TypeInst[T, U, V](a: 0, b: 1, c: 2)")).is_ok());
     }
 
+    #[test]
+    fn trailing_comma_func_call() {
+        let (_, call) = expr(span!("f(1, 2,)")).unwrap();
+        let call = call.downcast_ref::<FunctionCall>().unwrap();
+
+        assert_eq!(call.args().len(), 2);
+        assert!(expr(span!("f(\n    1,\n    2, // last\n)")).is_ok());
+        assert!(expr(span!("fn_call[T, U,](a,)")).is_ok());
+    }
+
+    #[test]
+    fn trailing_comma_declarations() {
+        assert!(expr(span!("func g(a: int, b: int,) {}")).is_ok());
+        assert!(expr(span!("func g[T,](a: T,) {}")).is_ok());
+        assert!(expr(span!("type Point(x: int, y: int,);")).is_ok());
+        assert!(expr(span!("func apply(f: func(int,) -> int) {}")).is_ok());
+    }
+
+    #[test]
+    fn trailing_comma_type_instantiation() {
+        let (_, inst) = expr(span!("Point(x: 1, y: 2,)")).unwrap();
+
+        assert_eq!(
+            inst.downcast_ref::<TypeInstantiation>()
+                .unwrap()
+                .fields()
+                .len(),
+            2
+        );
+        assert!(expr(span!("Point(1, 2,)")).is_ok());
+    }
+
+    #[test]
+    fn trailing_comma_alone() {
+        assert!(expr(span!("f(,)")).is_err());
+        assert!(expr(span!("f(1,,)")).is_err());
+        assert!(expr(span!("func g(,) {}")).is_err());
+        assert!(expr(span!("type Point(,);")).is_err());
+        assert!(expr(span!("Point(x: 1,,)")).is_err());
+    }
+
     #[test]
     fn empty_type_declaration() {
         assert!(expr(span!("type CustomType;")).is_ok())
@@ -1666,4 +2889,89 @@ func void() { }"##
         assert!(expr(span!("int_size = size_of[int](15)")).is_ok());
         assert!(expr(span!("int_size = size_of [int] (15)")).is_ok());
     }
+
+    #[test]
+    fn skip_statement_until_semicolon() {
+        let input = skip_statement(span!("x = (1 + ; y = 2"));
+        assert_eq!(*input.fragment(), " y = 2");
+    }
+
+    #[test]
+    fn skip_statement_until_closing_block() {
+        let input = skip_statement(span!("func f() { a = 1 +; a } y = 2"));
+        assert_eq!(*input.fragment(), " y = 2");
+    }
+
+    #[test]
+    fn skip_statement_with_else_block() {
+        let input = skip_statement(span!("if a { b = ) } else { b = 1 } y = 2"));
+        assert_eq!(*input.fragment(), " y = 2");
+    }
+
+    #[test]
+    fn skip_statement_with_else_if_block() {
+        let input = skip_statement(span!("if a { b = ) } else if c { b = 1 } else { } y = 2"));
+        assert_eq!(*input.fragment(), " y = 2");
+    }
+
+    #[test]
+    fn skip_statement_ignores_strings_and_comments() {
+        let input = skip_statement(span!("s = \"a;}\" /* ; } */ + ; y = 2"));
+        assert_eq!(*input.fragment(), " y = 2");
+    }
+
+    #[test]
+    fn skip_statement_unmatched_bracket() {
+        let input = skip_statement(span!("} y = 2"));
+        assert_eq!(*input.fragment(), " y = 2");
+    }
+
+    #[test]
+    fn missing_semicolon_in_block() {
+        match block(span!("{\n    x = f(1)\n    y = 2;\n}")) {
+            Err(nom::Err::Failure(e)) => {
+                let e = Error::from(e);
+                let loc = e.loc().unwrap();
+
+                assert_eq!(e.msg(), Some("expected `;` after statement"));
+                assert_eq!(loc.start().line(), 2);
+                assert_eq!(loc.start().column(), 12);
+                assert_eq!(loc.end().column(), 13);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn missing_semicolon_unexpected_token() {
+        match block(span!("{ x = 1 ) }")) {
+            Err(NomError(e)) => {
+                assert_eq!(Error::from(e).msg(), Some("expected `;`, found `)`"))
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn last_token_skips_comments() {
+        assert_eq!(last_token("x = 1"), (4, 5));
+        assert_eq!(last_token("x = 1 // one\n  "), (4, 5));
+        assert_eq!(last_token("x = \"/* a */\" /* b */"), (12, 13));
+        assert_eq!(last_token("x = '\\''"), (7, 8));
+    }
+
+    #[test]
+    fn many_expr_recover_multiple_errors() {
+        let (exprs, errors) =
+            many_expr_recover(span!("x = (1 + ; y = 2; func f() { a = +; a } z = 3"));
+        assert_eq!(exprs.len(), 2);
+        assert_eq!(errors.len(), 2);
+    }
+
+    #[test]
+    fn many_expr_recover_no_errors() {
+        let (exprs, errors) = many_expr_recover(span!("x = 1; y = 2"));
+        assert_eq!(exprs.len(), 2);
+        assert!(errors.is_empty());
+    }
 }
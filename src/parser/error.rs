@@ -0,0 +1,284 @@
+//! Errors produced by the parser. Rather than nom's error kinds, which do not mean
+//! anything to users, a [`ParseError`] keeps the constructs which were expected where
+//! the parsing failed, such as "expression" or "`)`". When multiple alternatives fail,
+//! the error of the one which went the furthest in the input is kept, and the
+//! constructs expected at the same position are merged. The error is then converted
+//! into a crate [`Error`] whose location covers the offending slice of the input:
+//!
+//! ```text
+//! expected function declaration, type declaration or expression, found `@@`
+//! ```
+
+use nom::error::ErrorKind;
+
+use crate::error::{ErrKind, Error};
+use crate::location::SpanTuple;
+use crate::parser::constructs::next;
+use crate::parser::ParseInput;
+
+/// Constructs which can start a statement
+pub const STATEMENT: &[&str] = &["function declaration", "type declaration", "expression"];
+
+/// An error encountered while parsing the input `I`
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError<I> {
+    /// Input remaining where the parsing failed
+    input: I,
+    /// Length of the offending slice of the input, if it is known
+    len: Option<usize>,
+    /// Constructs which were expected at the position of the error
+    expected: Vec<String>,
+    /// Message replacing the list of expected constructs
+    msg: Option<String>,
+}
+
+impl<I> ParseError<I> {
+    /// Create an error at a given position, without any information on what was
+    /// expected there
+    pub fn new(input: I) -> ParseError<I> {
+        ParseError {
+            input,
+            len: None,
+            expected: vec![],
+            msg: None,
+        }
+    }
+
+    /// Create an error for a construct which was expected at a given position
+    pub fn expected(input: I, expected: impl Into<String>) -> ParseError<I> {
+        ParseError::new(input).with_expected(&[expected.into()])
+    }
+
+    /// Replace the constructs which were expected at the position of the error
+    pub fn with_expected(self, expected: &[impl Into<String> + Clone]) -> ParseError<I> {
+        ParseError {
+            expected: expected.iter().cloned().map(Into::into).collect(),
+            ..self
+        }
+    }
+
+    /// Describe the error with a message rather than with the constructs which were
+    /// expected
+    pub fn with_msg(self, msg: impl Into<String>) -> ParseError<I> {
+        ParseError {
+            msg: Some(msg.into()),
+            ..self
+        }
+    }
+
+    /// Get the constructs which were expected at the position of the error
+    pub fn expected_constructs(&self) -> &[String] {
+        &self.expected
+    }
+
+    /// Get the message of the error, if it has one
+    pub fn msg(&self) -> Option<&str> {
+        self.msg.as_deref()
+    }
+}
+
+impl<'i> ParseError<ParseInput<'i>> {
+    /// Specify where the offending slice of the input ends
+    pub fn with_end(self, end: ParseInput<'i>) -> ParseError<ParseInput<'i>> {
+        let len = end
+            .location_offset()
+            .saturating_sub(self.input.location_offset());
+
+        ParseError {
+            len: Some(len),
+            ..self
+        }
+    }
+
+    /// Label an error with the construct which was expected at `start`, if the error
+    /// happened right there. Errors which happened further in the input are more
+    /// precise, and are kept as they are
+    pub fn label(
+        self,
+        start: &ParseInput<'i>,
+        expected: &[impl Into<String> + Clone],
+    ) -> ParseError<ParseInput<'i>> {
+        match start.location_offset() == self.input.location_offset() && self.msg.is_none() {
+            true => self.with_expected(expected),
+            false => self,
+        }
+    }
+}
+
+impl<'i> nom::error::ParseError<ParseInput<'i>> for ParseError<ParseInput<'i>> {
+    fn from_error_kind(input: ParseInput<'i>, _: ErrorKind) -> Self {
+        ParseError::new(input)
+    }
+
+    fn append(_: ParseInput<'i>, _: ErrorKind, other: Self) -> Self {
+        // The innermost error is the most precise one
+        other
+    }
+
+    fn from_char(input: ParseInput<'i>, c: char) -> Self {
+        ParseError::expected(input, format!("`{}`", c))
+    }
+
+    fn or(self, other: Self) -> Self {
+        match self
+            .input
+            .location_offset()
+            .cmp(&other.input.location_offset())
+        {
+            // The error which went the furthest in the input is the most precise one
+            std::cmp::Ordering::Less => return other,
+            std::cmp::Ordering::Greater => return self,
+            std::cmp::Ordering::Equal => {}
+        }
+
+        let mut merged = self;
+        other.expected.into_iter().for_each(|expected| {
+            if !merged.expected.contains(&expected) {
+                merged.expected.push(expected)
+            }
+        });
+        merged.msg = merged.msg.or(other.msg);
+
+        merged
+    }
+}
+
+/// Label the errors of a parser with the construct it was expected to parse, if they
+/// happened at the start of its input
+pub fn label<'i, O>(
+    expected: &'static [&'static str],
+    mut parser: impl FnMut(
+        ParseInput<'i>,
+    ) -> nom::IResult<ParseInput<'i>, O, ParseError<ParseInput<'i>>>,
+) -> impl FnMut(ParseInput<'i>) -> nom::IResult<ParseInput<'i>, O, ParseError<ParseInput<'i>>> {
+    move |input| {
+        parser(input).map_err(|e| match e {
+            // Parsers skip the whitespace and comments preceding the construct
+            nom::Err::Error(e) => nom::Err::Error(e.label(&next(input), expected)),
+            e => e,
+        })
+    }
+}
+
+/// Get the slice of the input which caused an error: The word, number or symbol at its
+/// position
+fn offending_len(input: &str) -> usize {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+
+    match input.chars().next() {
+        None => 0,
+        Some(c) if is_word(c) => input.find(|c| !is_word(c)).unwrap_or(input.len()),
+        Some(c) => c.len_utf8(),
+    }
+}
+
+/// Join a list of constructs, such as `a, b or c`
+fn join(expected: &[String]) -> String {
+    match expected {
+        [] => String::new(),
+        [single] => single.clone(),
+        [init @ .., last] => format!("{} or {}", init.join(", "), last),
+    }
+}
+
+impl<'i> From<ParseError<ParseInput<'i>>> for Error {
+    fn from(e: ParseError<ParseInput<'i>>) -> Error {
+        // Point at the offending token rather than at the whitespace preceding it
+        let (input, len) = match e.len {
+            Some(len) => (e.input, len.min(e.input.fragment().len())),
+            None => {
+                let blank = e.input.fragment().len() - e.input.fragment().trim_start().len();
+                let input = nom::Slice::slice(&e.input, blank..);
+                (input, offending_len(input.fragment()))
+            }
+        };
+        let end = nom::Slice::slice(&input, len..);
+        let offending = &input.fragment()[..len];
+
+        let found = match offending.is_empty() {
+            true => String::from("end of input"),
+            false => format!("`{}`", offending),
+        };
+
+        let msg = match (e.msg, e.expected.as_slice()) {
+            (Some(msg), _) => msg,
+            (None, []) => format!("unexpected {}", found),
+            (None, expected) => format!("expected {}, found {}", join(expected), found),
+        };
+
+        Error::new(ErrKind::Parsing)
+            .with_msg(msg)
+            .with_loc(Some(SpanTuple::new(input.extra, input.into(), end.into())))
+    }
+}
+
+/// Errors which stop the parser and the ones it can recover from are lowered into the
+/// same crate error
+impl<'i> From<nom::Err<ParseError<ParseInput<'i>>>> for Error {
+    fn from(e: nom::Err<ParseError<ParseInput<'i>>>) -> Error {
+        match e {
+            nom::Err::Incomplete(_) => {
+                Error::new(ErrKind::Parsing).with_msg(String::from("unexpected end of input"))
+            }
+            nom::Err::Error(inner) | nom::Err::Failure(inner) => inner.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::span;
+    use nom::error::ParseError as _;
+    use nom::Slice;
+
+    #[test]
+    fn or_keeps_furthest() {
+        let input = span!("func f(");
+        let near = ParseError::expected(input, "a");
+        let far = ParseError::expected(input.slice(4..), "b");
+
+        assert_eq!(near.clone().or(far.clone()), far);
+        assert_eq!(far.clone().or(near), far);
+    }
+
+    #[test]
+    fn or_merges_expected() {
+        let input = span!("@");
+        let merged = ParseError::expected(input, "a")
+            .or(ParseError::expected(input, "b"))
+            .or(ParseError::expected(input, "a"));
+
+        assert_eq!(merged.expected_constructs(), ["a", "b"]);
+    }
+
+    #[test]
+    fn into_error() {
+        let input = span!("x = foo;");
+        let e = Error::from(ParseError::expected(input.slice(4..), "`(`"));
+
+        assert_eq!(e.msg(), Some("expected `(`, found `foo`"));
+        assert_eq!(e.loc().unwrap().start().column(), 5);
+        assert_eq!(e.loc().unwrap().end().column(), 8);
+    }
+
+    #[test]
+    fn into_error_end_of_input() {
+        let input = span!("x = ");
+        let e = Error::from(ParseError::new(input.slice(4..)).with_expected(STATEMENT));
+
+        assert_eq!(
+            e.msg(),
+            Some(
+                "expected function declaration, type declaration or expression, found end of input"
+            )
+        );
+    }
+
+    #[test]
+    fn into_error_unexpected() {
+        let e = Error::from(ParseError::new(span!("#")));
+
+        assert_eq!(e.msg(), Some("unexpected `#`"));
+    }
+}
@@ -5,32 +5,130 @@
 use std::path::Path;
 
 use crate::context::Context;
-use crate::error::Error;
+use crate::error::{ErrKind, Error};
+use crate::instruction::Instruction;
+use crate::typechecker::TypeId;
 
 mod constant_construct;
 pub mod constructs;
+mod error;
 mod tokens;
 
 pub use constant_construct::ConstantConstruct;
+pub use error::ParseError;
 use nom_locate::LocatedSpan;
 pub use tokens::Token;
 
 pub type ParseInput<'i> = LocatedSpan<&'i str, Option<&'i Path>>;
-pub type ParseResult<T, I> = nom::IResult<T, I, Error>;
+pub type ParseResult<T, I> = nom::IResult<T, I, ParseError<T>>;
 
 /// Parses the entire user input and returns a hashmap corresponding to the user
-/// program
+/// program. The parser does not stop at the first syntax error: All of them are
+/// kept in the context's error handler, and a parsing error is returned if there
+/// were any.
 pub fn parse(ctx: &mut Context, input: &str, file_path: Option<&Path>) -> Result<(), Error> {
     // FIXME: Keep input in context here
     ctx.set_code(input.to_string());
-    let entry_block = ctx.entry_point.block_mut().unwrap();
     let input = LocatedSpan::new_extra(input, file_path);
 
-    let (_, instructions) = constructs::many_expr(input)?;
+    let (instructions, errors) = constructs::many_expr_recover(input);
 
+    // The entry point always has a block
+    let entry_block = ctx.entry_point.block_mut().unwrap();
     entry_block.add_instructions(instructions);
 
-    Ok(())
+    match errors.is_empty() {
+        true => Ok(()),
+        false => {
+            errors.into_iter().for_each(|e| ctx.error(e));
+            Err(Error::new(ErrKind::Parsing))
+        }
+    }
+}
+
+/// Parse some input on its own, without adding it to a context. This returns the parsed
+/// instructions, or all the syntax errors found in the input. Whatever the input is,
+/// this never panics nor exits the process, which makes it suitable for fuzzing.
+///
+/// ```
+/// let instructions = jinko::parser::parse_instructions("x = 15; x + 1").unwrap();
+/// assert_eq!(instructions.len(), 2);
+///
+/// let errors = jinko::parser::parse_instructions("x = ; y = (").unwrap_err();
+/// assert_eq!(errors.len(), 2);
+/// ```
+pub fn parse_instructions(input: &str) -> Result<Vec<Box<dyn Instruction>>, Vec<Error>> {
+    let (instructions, errors) = constructs::many_expr_recover(LocatedSpan::new_extra(input, None));
+
+    match errors.is_empty() {
+        true => Ok(instructions),
+        false => Err(errors),
+    }
+}
+
+/// Parse a type, as it would be written in a declaration: `int`, `Pair[T, U]` or
+/// `func(int) -> string`
+pub(crate) fn parse_type(ty: &str) -> Result<TypeId, Error> {
+    let input = LocatedSpan::new_extra(ty, None);
+
+    match constructs::type_id(input) {
+        Ok((remaining, ty)) if constructs::next(remaining).is_empty() => Ok(ty),
+        _ => Err(Error::new(ErrKind::Parsing).with_msg(format!("invalid type `{}`", ty))),
+    }
+}
+
+/// Check if an input is incomplete, meaning that more input is required before it can
+/// be parsed. This is the case if a block, parenthesis, string or comment was opened
+/// but never closed. This is used to allow multi-line input in interactive mode.
+pub fn is_incomplete(input: &str) -> bool {
+    let mut depth: i64 = 0;
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' | '(' | '[' => depth += 1,
+            '}' | ')' | ']' => depth -= 1,
+            '"' | '\'' => {
+                // Look for the end of the string or character, which might contain
+                // delimiters or escaped quotes
+                let mut closed = false;
+                while let Some(inner) = chars.next() {
+                    match inner {
+                        '\\' => {
+                            chars.next();
+                        }
+                        _ if inner == c => {
+                            closed = true;
+                            break;
+                        }
+                        _ => {}
+                    }
+                }
+                if !closed {
+                    return true;
+                }
+            }
+            '/' if chars.next_if_eq(&'/').is_some() => {
+                // Skip to the end of the line
+                let _ = chars.any(|c| c == '\n');
+            }
+            '/' if chars.next_if_eq(&'*').is_some() => {
+                // Look for the end of the comment, which might contain nested comments
+                let mut comment_depth = 1;
+                while comment_depth > 0 {
+                    match chars.next() {
+                        Some('*') if chars.next_if_eq(&'/').is_some() => comment_depth -= 1,
+                        Some('/') if chars.next_if_eq(&'*').is_some() => comment_depth += 1,
+                        Some(_) => {}
+                        None => return true,
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    depth > 0
 }
 
 #[cfg(test)]
@@ -40,3 +138,146 @@ macro_rules! span {
         nom_locate::LocatedSpan::new_extra($s, None)
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Check if some input can be parsed. Parsing is done on a stack as large as the one
+    /// of the interpreter, since nested constructs take a lot of stack space in
+    /// debug builds
+    fn parses(input: String) -> bool {
+        std::thread::Builder::new()
+            .stack_size(64 * 1024 * 1024)
+            .spawn(move || parse_instructions(&input).is_ok())
+            .unwrap()
+            .join()
+            .unwrap()
+    }
+
+    #[test]
+    fn nesting_limit() {
+        let nested = |depth| format!("{}1{}", "(".repeat(depth), ")".repeat(depth));
+
+        assert!(parses(nested(constructs::MAX_NESTING - 1)));
+        assert!(!parses(nested(constructs::MAX_NESTING * 4)));
+        assert!(!parses(format!("{}1", "-".repeat(10000))));
+        assert!(!parses(format!("a{}", ".b".repeat(10000))));
+        assert!(!parses(format!(
+            "x: {}int{} = 1",
+            "A[".repeat(1000),
+            "]".repeat(1000)
+        )));
+    }
+
+    #[test]
+    fn nesting_limit_error() {
+        let nested = format!("{}1{}", "-".repeat(1000), "");
+        let errors = std::thread::Builder::new()
+            .stack_size(64 * 1024 * 1024)
+            .spawn(move || {
+                parse_instructions(&nested)
+                    .unwrap_err()
+                    .iter()
+                    .map(|e| e.msg().unwrap_or_default().to_owned())
+                    .collect::<Vec<String>>()
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+
+        assert!(errors[0].contains("nested too deeply"));
+    }
+
+    #[test]
+    fn error_names_expected_construct() {
+        let msg = |input: &str| {
+            parse_instructions(input).unwrap_err()[0]
+                .msg()
+                .unwrap()
+                .to_owned()
+        };
+
+        assert_eq!(msg("@@;"), "expected identifier, found `@`");
+        assert_eq!(msg("x = ;"), "expected expression, found `;`");
+        assert_eq!(msg("x = (1 + 2;"), "expected `)`, found `;`");
+        assert_eq!(msg("type 1 = 3;"), "expected type name, found `1`");
+        assert_eq!(msg("func f(a int) {}"), "expected `:`, found `int`");
+        assert_eq!(msg("func f(, ) {}"), "expected argument or `)`, found `,`");
+        assert_eq!(
+            msg("if true { 1 } else"),
+            "expected `{`, found end of input"
+        );
+        assert_eq!(
+            msg("x = 1 $ 2;"),
+            "expected function declaration, type declaration or expression, found `$`"
+        );
+    }
+
+    #[test]
+    fn error_spans_offending_slice() {
+        let errors = parse_instructions("x = 1;\nfunc f(a: int) { a } }").unwrap_err();
+        let loc = errors[0].loc().unwrap();
+
+        assert_eq!(loc.start().line(), 2);
+        assert_eq!(loc.start().column(), 22);
+        assert_eq!(loc.end().column(), 23);
+    }
+
+    #[test]
+    fn many_comments() {
+        let input = format!("{}1", "/* */ // comment\n".repeat(10000));
+
+        assert_eq!(parse_instructions(&input).unwrap().len(), 1);
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn parse_never_panics(input in "\\PC{0,64}") {
+            parses(input);
+        }
+
+        #[test]
+        fn parse_tokens_never_panics(
+            input in "([(){}\\[\\];,:.=+*/<>!?|&'\"@#_a-z0-9 \n-]|func |type |if |else |match |incl |mut |for |in |while |return |ext |test |mock |interface |impl |\\\\u\\{|1e|0\\.5|=>|->|::|\\.\\.){0,48}"
+        ) {
+            parses(input);
+        }
+    }
+
+    #[test]
+    fn complete_inputs() {
+        assert!(!is_incomplete("x = 15"));
+        assert!(!is_incomplete("func f() { 15 }"));
+        assert!(!is_incomplete("s = \"{ (\""));
+        assert!(!is_incomplete("c = '{'"));
+        assert!(!is_incomplete("c = '\\''"));
+        assert!(!is_incomplete("x = 4 // {"));
+        assert!(!is_incomplete("/* { */ x"));
+        assert!(!is_incomplete("/* /* { */ */ x"));
+    }
+
+    #[test]
+    fn incomplete_inputs() {
+        assert!(is_incomplete("func f() {"));
+        assert!(is_incomplete("f(1,"));
+        assert!(is_incomplete("if true { if false {}"));
+        assert!(is_incomplete("s = \"jinko"));
+        assert!(is_incomplete("/* comment"));
+        assert!(is_incomplete("/* /* nested */ comment"));
+    }
+
+    #[test]
+    fn parse_reports_all_errors() {
+        let mut ctx = Context::new();
+
+        assert!(parse(&mut ctx, "x = (1 + ; y = 2; z = +", None).is_err());
+        assert_eq!(ctx.error_handler.errors().len(), 2);
+    }
+
+    #[test]
+    fn unbalanced_closing_is_complete() {
+        // Let the parser report the error
+        assert!(!is_incomplete("}"));
+    }
+}
@@ -5,18 +5,38 @@
 use nom::Err::Error as NomError;
 use nom::{
     branch::alt, bytes::complete::tag, bytes::complete::take_until, bytes::complete::take_while,
-    bytes::complete::take_while1, character::complete::anychar, character::complete::char,
-    character::is_alphanumeric, character::is_digit, combinator::not, combinator::opt,
-    combinator::peek, multi::many0, sequence::delimited, sequence::pair,
+    bytes::complete::take_while1, bytes::complete::take_while_m_n, character::complete::anychar,
+    character::complete::char, character::complete::one_of, character::is_alphanumeric,
+    character::is_digit, combinator::not, combinator::opt, combinator::peek, combinator::recognize,
+    multi::many0, multi::many1, sequence::delimited, sequence::pair, sequence::tuple, Slice,
 };
 
-use crate::error::{ErrKind, Error};
-use crate::parser::{ParseInput, ParseResult};
-
-/// Reserved Keywords by jinko
-const RESERVED_KEYWORDS: [&str; 14] = [
-    "func", "test", "mock", "type", "ext", "for", "while", "loop", "mut", "true", "false", "incl",
-    "as", "return",
+use crate::parser::{ParseError, ParseInput, ParseResult};
+
+/// Reserved Keywords by jinko. They cannot be used as identifiers
+pub const RESERVED_KEYWORDS: [&str; 22] = [
+    "func",
+    "test",
+    "mock",
+    "type",
+    "ext",
+    "for",
+    "while",
+    "loop",
+    "mut",
+    "true",
+    "false",
+    "incl",
+    "as",
+    "return",
+    "break",
+    "continue",
+    "if",
+    "else",
+    "in",
+    "match",
+    "interface",
+    "impl",
 ];
 
 pub struct Token;
@@ -25,7 +45,7 @@ impl Token {
     /// Function used to recognize a specific character such as '[' or '>'. A function
     /// calling this is specifically trying to recognize the given character
     fn specific_char(input: ParseInput, character: char) -> ParseResult<ParseInput, char> {
-        let c = char::<ParseInput, Error>(character)(input)?;
+        let c = char::<ParseInput, ParseError<ParseInput>>(character)(input)?;
 
         Ok(c)
     }
@@ -36,9 +56,9 @@ impl Token {
         input: ParseInput<'input>,
         token: &'input str,
     ) -> ParseResult<ParseInput<'input>, ParseInput<'input>> {
-        let tok = tag(token)(input)?;
-
-        Ok(tok)
+        tag(token)(input).map_err(|_: nom::Err<ParseError<ParseInput>>| {
+            NomError(ParseError::expected(input, format!("`{}`", token)))
+        })
     }
 
     /// Function used to recognize a specific string token such as "func" or "ext"
@@ -48,12 +68,15 @@ impl Token {
         input: ParseInput<'tok>,
         token: &'tok str,
     ) -> ParseResult<ParseInput<'tok>, ParseInput<'tok>> {
-        let (input, tag) = tag(token)(input)?;
+        let start = input;
+        let (input, tag) = Token::token(input, token)?;
 
+        // Keywords are not recognized at the start of a longer identifier
         if let Some(next_char) = input.chars().next() {
             if next_char.is_alphanumeric() || next_char == '_' {
-                return Err(NomError(Error::new(ErrKind::Parsing).with_msg(
-                    String::from("Unexpected alphanum character after symbol"),
+                return Err(NomError(ParseError::expected(
+                    start,
+                    format!("`{}`", token),
                 )));
             }
         }
@@ -109,6 +132,10 @@ impl Token {
         Token::specific_char(input, ';')
     }
 
+    pub fn question_mark(input: ParseInput) -> ParseResult<ParseInput, char> {
+        Token::specific_char(input, '?')
+    }
+
     pub fn at_sign(input: ParseInput) -> ParseResult<ParseInput, char> {
         Token::specific_char(input, '@')
     }
@@ -133,6 +160,14 @@ impl Token {
         Token::specific_token(input, "loop")
     }
 
+    pub fn match_tok(input: ParseInput) -> ParseResult<ParseInput, ParseInput> {
+        Token::specific_token(input, "match")
+    }
+
+    pub fn underscore(input: ParseInput) -> ParseResult<ParseInput, ParseInput> {
+        Token::specific_token(input, "_")
+    }
+
     pub fn while_tok(input: ParseInput) -> ParseResult<ParseInput, ParseInput> {
         Token::specific_token(input, "while")
     }
@@ -161,10 +196,26 @@ impl Token {
         Token::specific_token(input, "return")
     }
 
+    pub fn break_tok(input: ParseInput) -> ParseResult<ParseInput, ParseInput> {
+        Token::specific_token(input, "break")
+    }
+
+    pub fn continue_tok(input: ParseInput) -> ParseResult<ParseInput, ParseInput> {
+        Token::specific_token(input, "continue")
+    }
+
     pub fn type_tok(input: ParseInput) -> ParseResult<ParseInput, ParseInput> {
         Token::specific_token(input, "type")
     }
 
+    pub fn interface_tok(input: ParseInput) -> ParseResult<ParseInput, ParseInput> {
+        Token::specific_token(input, "interface")
+    }
+
+    pub fn impl_tok(input: ParseInput) -> ParseResult<ParseInput, ParseInput> {
+        Token::specific_token(input, "impl")
+    }
+
     pub fn incl_tok(input: ParseInput) -> ParseResult<ParseInput, ParseInput> {
         Token::specific_token(input, "incl")
     }
@@ -187,13 +238,38 @@ impl Token {
     }
 
     pub fn mul(input: ParseInput) -> ParseResult<ParseInput, ParseInput> {
-        Token::token(input, "*")
+        let (input, token) = Token::token(input, "*")?;
+        if !input.is_empty() {
+            peek(not(char('*')))(input)?;
+        }
+
+        Ok((input, token))
     }
 
     pub fn div(input: ParseInput) -> ParseResult<ParseInput, ParseInput> {
         Token::token(input, "/")
     }
 
+    pub fn rem(input: ParseInput) -> ParseResult<ParseInput, ParseInput> {
+        Token::token(input, "%")
+    }
+
+    pub fn pow(input: ParseInput) -> ParseResult<ParseInput, ParseInput> {
+        Token::token(input, "**")
+    }
+
+    pub fn bit_and(input: ParseInput) -> ParseResult<ParseInput, ParseInput> {
+        Token::token(input, "&")
+    }
+
+    pub fn bit_or(input: ParseInput) -> ParseResult<ParseInput, ParseInput> {
+        Token::token(input, "|")
+    }
+
+    pub fn bit_xor(input: ParseInput) -> ParseResult<ParseInput, ParseInput> {
+        Token::token(input, "^")
+    }
+
     pub fn left_parenthesis(input: ParseInput) -> ParseResult<ParseInput, ParseInput> {
         Token::token(input, "(")
     }
@@ -226,10 +302,23 @@ impl Token {
         Token::token(input, "!=")
     }
 
-    pub fn _left_shift(input: ParseInput) -> ParseResult<ParseInput, ParseInput> {
+    pub fn bang(input: ParseInput) -> ParseResult<ParseInput, ParseInput> {
+        let (input, token) = Token::token(input, "!")?;
+        if !input.is_empty() {
+            peek(not(char('=')))(input)?;
+        }
+
+        Ok((input, token))
+    }
+
+    pub fn left_shift(input: ParseInput) -> ParseResult<ParseInput, ParseInput> {
         Token::token(input, "<<")
     }
 
+    pub fn right_shift(input: ParseInput) -> ParseResult<ParseInput, ParseInput> {
+        Token::token(input, ">>")
+    }
+
     pub fn true_tok(input: ParseInput) -> ParseResult<ParseInput, ParseInput> {
         let (input, t) = Token::specific_token(input, "true")?;
 
@@ -271,15 +360,35 @@ impl Token {
     }
 
     pub fn dot(input: ParseInput) -> ParseResult<ParseInput, ParseInput> {
-        Token::token(input, ".")
+        let (input, token) = Token::token(input, ".")?;
+        if !input.is_empty() {
+            peek(not(char('.')))(input)?;
+        }
+
+        Ok((input, token))
+    }
+
+    pub fn range(input: ParseInput) -> ParseResult<ParseInput, ParseInput> {
+        Token::token(input, "..")
+    }
+
+    pub fn inclusive_range(input: ParseInput) -> ParseResult<ParseInput, ParseInput> {
+        Token::token(input, "..=")
     }
 
     pub fn inner_identifer(input: ParseInput) -> ParseResult<ParseInput, ParseInput> {
-        let (input, id) = take_while1(|c| is_alphanumeric(c as u8) || c == '_')(input)?;
+        let start = input;
+        let (input, id) = take_while1(|c| is_alphanumeric(c as u8) || c == '_')(input).map_err(
+            |_: nom::Err<ParseError<ParseInput>>| {
+                NomError(ParseError::expected(start, "identifier"))
+            },
+        )?;
 
-        if RESERVED_KEYWORDS.contains(&id) {
+        if Token::is_keyword(&id) {
             return Err(NomError(
-                Error::new(ErrKind::Parsing).with_msg(String::from("identifier cannot be keyword")),
+                ParseError::expected(start, "identifier")
+                    .with_msg(format!("`{}` is a reserved keyword", id))
+                    .with_end(input),
             ));
         }
 
@@ -289,10 +398,28 @@ impl Token {
         }
 
         Err(NomError(
-            Error::new(ErrKind::Parsing).with_msg(String::from("invalid identifier")),
+            ParseError::expected(start, "identifier").with_end(input),
         ))
     }
 
+    /// Is a word reserved by the language, and thus not a valid identifier
+    pub fn is_keyword(word: &str) -> bool {
+        RESERVED_KEYWORDS.contains(&word)
+    }
+
+    /// Recognize a reserved keyword, such as `func` or `true`
+    pub fn keyword(input: ParseInput) -> ParseResult<ParseInput, ParseInput> {
+        let start = input;
+        let (input, word) = take_while1(|c| is_alphanumeric(c as u8) || c == '_')(input).map_err(
+            |_: nom::Err<ParseError<ParseInput>>| NomError(ParseError::expected(start, "keyword")),
+        )?;
+
+        match Token::is_keyword(&word) {
+            true => Ok((input, word)),
+            false => Err(NomError(ParseError::expected(start, "keyword"))),
+        }
+    }
+
     pub fn namespace_separator(input: ParseInput) -> ParseResult<ParseInput, ParseInput> {
         Token::token(input, "::")
     }
@@ -315,10 +442,12 @@ impl Token {
         // `<id>::<id>::<id>...<id>::`
         //
         // which is not a valid identifier
-        if Token::namespace_separator(input).is_ok() {
-            return Err(NomError(Error::new(ErrKind::Parsing).with_msg(
-                String::from("cannot finish identifier on namespace separator `::`"),
-            )));
+        if let Ok((end, _)) = Token::namespace_separator(input) {
+            return Err(NomError(
+                ParseError::new(input)
+                    .with_msg("cannot finish identifier on namespace separator `::`")
+                    .with_end(end),
+            ));
         }
 
         Ok((input, identifier))
@@ -333,29 +462,78 @@ impl Token {
     pub fn bool_constant(input: ParseInput) -> ParseResult<ParseInput, bool> {
         let (input, b) = alt((Token::true_tok, Token::false_tok))(input)?;
 
-        // We can unwrap since we recognized valid tokens already
-        Ok((input, b.parse::<bool>().unwrap()))
+        Ok((input, *b.fragment() == "true"))
     }
 
-    pub fn float_constant(input: ParseInput) -> ParseResult<ParseInput, f64> {
-        let (input, negative_sign) = opt(char('-'))(input)?;
-        let (input, whole) = Token::int_constant(input)?;
-        let (input, _) = char('.')(input)?;
-        let (input, decimal) = Token::non_neg_num(input)?;
+    /// Parse the exponent of a floating point number written in scientific notation,
+    /// such as `e9` or `E-3`
+    fn float_exponent(input: ParseInput) -> ParseResult<ParseInput, ParseInput> {
+        recognize(tuple((one_of("eE"), opt(one_of("+-")), Token::non_neg_num)))(input)
+    }
 
-        match format!("{}.{}", whole, decimal).parse::<f64>() {
-            Ok(value) => match negative_sign {
-                Some(_) => Ok((input, -value)),
-                None => Ok((input, value)),
+    /// Parse a floating point constant. Either the whole or the decimal part can be
+    /// omitted, as in `.5` or `1.`, and an exponent can be given in scientific
+    /// notation, as in `1e9` or `2.5e-3`. A trailing decimal point is only part of
+    /// the number if it is not followed by an identifier or another dot, so that
+    /// `1.method()` stays a method call on an integer. A number containing multiple
+    /// decimal points, such as `1.2.3`, is an error.
+    pub fn float_constant(input: ParseInput) -> ParseResult<ParseInput, f64> {
+        let start = input;
+        let (input, _) = opt(char('-'))(input)?;
+        let (input, whole) = opt(Token::non_neg_num)(input)?;
+
+        let (input, decimal) = match char::<_, ParseError<_>>('.')(input) {
+            Ok((after_dot, _)) => match Token::non_neg_num(after_dot) {
+                Ok((input, decimal)) => (input, Some(Some(decimal))),
+                Err(_) => match after_dot.chars().next() {
+                    Some(c) if c == '.' || c == '_' || c.is_alphabetic() => (input, None),
+                    _ => (after_dot, Some(None)),
+                },
             },
-            Err(_) => Err(NomError(Error::new(ErrKind::Parsing).with_msg(format!(
-                "invalid floating point number: {}.{}",
-                whole, decimal
-            )))),
+            Err(_) => (input, None),
+        };
+
+        // `.` on its own is not a number
+        if whole.is_none() && !matches!(decimal, Some(Some(_))) {
+            return Err(NomError(ParseError::expected(start, "number")));
+        }
+
+        let (input, exponent) = match whole.is_some() && decimal == Some(None) {
+            // `1.e5` is a field access on an integer rather than a float
+            true => (input, None),
+            false => opt(Token::float_exponent)(input)?,
+        };
+
+        // Without a decimal point or an exponent, this is an integer
+        if decimal.is_none() && exponent.is_none() {
+            return Err(NomError(ParseError::expected(start, "number")));
+        }
+
+        let len = input.location_offset() - start.location_offset();
+        let literal = start.slice(..len);
+
+        if let Ok((rest, _)) = many1(pair(char::<_, ParseError<_>>('.'), Token::non_neg_num))(input)
+        {
+            let len = rest.location_offset() - start.location_offset();
+            return Err(Token::failure(
+                start,
+                rest,
+                format!("invalid floating point number: {}", start.slice(..len)),
+            ));
+        }
+
+        match literal.parse::<f64>() {
+            Ok(value) => Ok((input, value)),
+            Err(_) => Err(NomError(
+                ParseError::new(start)
+                    .with_msg(format!("invalid floating point number: {}", literal))
+                    .with_end(input),
+            )),
         }
     }
 
     pub fn int_constant(input: ParseInput) -> ParseResult<ParseInput, i64> {
+        let start = input;
         let (input, negative_sign) = opt(char('-'))(input)?;
         let (input, num) = Token::non_neg_num(input)?;
 
@@ -364,21 +542,118 @@ impl Token {
                 Some(_) => Ok((input, -value)),
                 None => Ok((input, value)),
             },
-            Err(_) => Err(NomError(
-                Error::new(ErrKind::Parsing).with_msg(format!("invalid integer: {}", num)),
+            Err(_) => Err(Token::failure(
+                start,
+                input,
+                format!("invalid integer: {}", num),
             )),
         }
     }
 
-    /// Parse a single character constant and return the character inside the quotes
+    /// Create an error spanning from `start` to `end`, which is not recoverable by
+    /// the other parsers
+    fn failure<'i>(
+        start: ParseInput<'i>,
+        end: ParseInput<'i>,
+        msg: String,
+    ) -> nom::Err<ParseError<ParseInput<'i>>> {
+        nom::Err::Failure(ParseError::new(start).with_msg(msg).with_end(end))
+    }
+
+    /// Parse the escape sequence of a character constant, without its backslash. The
+    /// available escapes are `\n`, `\r`, `\t`, `\0`, `\\`, `\'`, `\"` and unicode
+    /// escapes such as `\u{1F600}`
+    fn char_escape(input: ParseInput) -> ParseResult<ParseInput, char> {
+        let start = input;
+        let (input, escaped) = anychar(input)?;
+        let character = match escaped {
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            '0' => '\0',
+            '\\' | '\'' | '"' => escaped,
+            'u' => {
+                let (input, code) = delimited(
+                    char('{'),
+                    take_while_m_n(1, 6, |c: char| c.is_ascii_hexdigit()),
+                    char('}'),
+                )(input)
+                .map_err(|_: nom::Err<ParseError<ParseInput>>| {
+                    Token::failure(
+                        start,
+                        input,
+                        String::from("invalid unicode escape, expected `\\u{<hex>}`"),
+                    )
+                })?;
+
+                // At most six hexadecimal digits are accepted, so the code always fits
+                return match u32::from_str_radix(&code, 16).ok().and_then(char::from_u32) {
+                    Some(character) => Ok((input, character)),
+                    None => Err(Token::failure(
+                        start,
+                        input,
+                        format!("invalid unicode character: \\u{{{}}}", code),
+                    )),
+                };
+            }
+            _ => {
+                return Err(Token::failure(
+                    start,
+                    input,
+                    format!("unknown character escape: \\{}", escaped),
+                ))
+            }
+        };
+
+        Ok((input, character))
+    }
+
+    /// Parse a single character constant and return the character inside the quotes.
+    /// The character can be an escape sequence, such as `'\n'` or `'\u{1F600}'`.
+    /// Empty character constants or constants containing multiple characters are
+    /// errors
     pub fn char_constant(input: ParseInput) -> ParseResult<ParseInput, char> {
-        let (input, _) = Token::single_quote(input)?;
-        let (input, character) = anychar(input)?;
+        let start = input;
         let (input, _) = Token::single_quote(input)?;
 
-        // FIXME: Handle escaping as well
+        let (input, character) = match anychar::<_, ParseError<_>>(input) {
+            Ok((input, '\\')) => Token::char_escape(input)?,
+            Ok((after, '\'')) => {
+                return Err(Token::failure(
+                    start,
+                    after,
+                    String::from("empty character constant"),
+                ))
+            }
+            Ok((input, c)) if c != '\n' => (input, c),
+            _ => {
+                return Err(Token::failure(
+                    start,
+                    input,
+                    String::from("unterminated character constant"),
+                ))
+            }
+        };
 
-        Ok((input, character))
+        match Token::single_quote(input) {
+            Ok((input, _)) => Ok((input, character)),
+            Err(_) => {
+                // Report the whole literal if it is closed on the same line
+                let line = input.fragment().split('\n').next().unwrap_or_default();
+                match line.find('\'') {
+                    Some(idx) => Err(Token::failure(
+                        start,
+                        input.slice(idx + 1..),
+                        String::from("character constants can only contain one character"),
+                    )),
+                    None => Err(Token::failure(
+                        start,
+                        input,
+                        String::from("unterminated character constant"),
+                    )),
+                }
+            }
+        }
     }
 
     /// Parse a string constant and return the characters between the double quotes
@@ -390,12 +665,32 @@ impl Token {
     }
 
     #[inline(always)]
+    /// Consume a multi-line comment. Multi-line comments can be nested, so that
+    /// regions of code containing comments can themselves be commented out
     pub fn consume_multi_comment(input: ParseInput) -> ParseResult<ParseInput, ParseInput> {
-        let (input, _) = Token::comment_multi_start(input)?;
-        let (input, content) = take_until("*/")(input)?;
-        let (input, _) = Token::comment_multi_end(input)?;
-
-        Ok((input, content))
+        let (content, _) = Token::comment_multi_start(input)?;
+        let (mut input, mut depth, mut len) = (content, 1, 0);
+
+        loop {
+            if let Ok((next, _)) = Token::comment_multi_end(input) {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok((next, content.slice(..len)));
+                }
+                input = next;
+                len += 2;
+            } else if let Ok((next, _)) = Token::comment_multi_start(input) {
+                depth += 1;
+                input = next;
+                len += 2;
+            } else {
+                let (next, c) = anychar(input).map_err(|_: nom::Err<ParseError<ParseInput>>| {
+                    NomError(ParseError::new(input).with_msg("unterminated multi-line comment"))
+                })?;
+                input = next;
+                len += c.len_utf8();
+            }
+        }
     }
 
     #[inline(always)]
@@ -427,7 +722,9 @@ impl Token {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::Error;
     use crate::span;
+    use nom_locate::LocatedSpan;
 
     macro_rules! frag_first {
         ($span:expr) => {
@@ -451,14 +748,74 @@ mod tests {
             frag_first!(Token::char_constant(span!("'9'"))),
             Ok(("", '9'))
         );
+        assert_eq!(
+            frag_first!(Token::char_constant(span!("'é'"))),
+            Ok(("", 'é'))
+        );
+    }
+
+    #[test]
+    fn t_char_constant_escapes() {
+        assert_eq!(
+            frag_first!(Token::char_constant(span!(r"'\n'"))),
+            Ok(("", '\n'))
+        );
+        assert_eq!(
+            frag_first!(Token::char_constant(span!(r"'\t'"))),
+            Ok(("", '\t'))
+        );
+        assert_eq!(
+            frag_first!(Token::char_constant(span!(r"'\''"))),
+            Ok(("", '\''))
+        );
+        assert_eq!(
+            frag_first!(Token::char_constant(span!(r"'\\'"))),
+            Ok(("", '\\'))
+        );
+        assert_eq!(
+            frag_first!(Token::char_constant(span!(r"'\0'"))),
+            Ok(("", '\0'))
+        );
+    }
 
-        // FIXME: Add escaping
+    #[test]
+    fn t_char_constant_unicode_escape() {
+        assert_eq!(
+            frag_first!(Token::char_constant(span!(r"'\u{1F600}'"))),
+            Ok(("", '😀'))
+        );
+        assert_eq!(
+            frag_first!(Token::char_constant(span!(r"'\u{41}'"))),
+            Ok(("", 'A'))
+        );
     }
 
     #[test]
     fn t_char_constant_invalid() {
         // Multiple characters
-        assert!(Token::char_constant(span!("'abc'")).is_err());
+        assert!(matches!(
+            Token::char_constant(span!("'abc'")),
+            Err(nom::Err::Failure(_))
+        ));
+        assert!(Token::char_constant(span!("''")).is_err());
+        assert!(Token::char_constant(span!("'a")).is_err());
+        assert!(Token::char_constant(span!(r"'\q'")).is_err());
+        assert!(Token::char_constant(span!(r"'\u{D800}'")).is_err());
+        assert!(Token::char_constant(span!(r"'\u{1234567}'")).is_err());
+        assert!(Token::char_constant(span!(r"'\u41'")).is_err());
+    }
+
+    #[test]
+    fn t_char_constant_multiple_chars_location() {
+        match Token::char_constant(span!("'ab' + 1")) {
+            Err(nom::Err::Failure(e)) => {
+                let e = Error::from(e);
+                let loc = e.loc().unwrap();
+                assert_eq!(loc.start().column(), 1);
+                assert_eq!(loc.end().column(), 5);
+            }
+            _ => unreachable!(),
+        }
     }
 
     #[test]
@@ -521,6 +878,97 @@ mod tests {
         assert!(Token::float_constant(span!("ff2")).is_err());
 
         assert!(Token::float_constant(span!("12")).is_err());
+        assert!(Token::float_constant(span!(".")).is_err());
+        assert!(Token::float_constant(span!("e5")).is_err());
+    }
+
+    #[test]
+    fn t_float_constant_scientific() {
+        assert_eq!(
+            frag_first!(Token::float_constant(span!("1e9"))),
+            Ok(("", 1e9f64))
+        );
+        assert_eq!(
+            frag_first!(Token::float_constant(span!("2.5e-3"))),
+            Ok(("", 2.5e-3f64))
+        );
+        assert_eq!(
+            frag_first!(Token::float_constant(span!("-4E+2"))),
+            Ok(("", -4e2f64))
+        );
+        assert!(Token::float_constant(span!("1ex")).is_err());
+    }
+
+    #[test]
+    fn t_float_constant_leading_trailing_dot() {
+        assert_eq!(
+            frag_first!(Token::float_constant(span!(".5"))),
+            Ok(("", 0.5f64))
+        );
+        assert_eq!(
+            frag_first!(Token::float_constant(span!("-.25e1"))),
+            Ok(("", -2.5f64))
+        );
+        assert_eq!(
+            frag_first!(Token::float_constant(span!("1. + 2"))),
+            Ok((" + 2", 1f64))
+        );
+    }
+
+    #[test]
+    fn t_float_constant_trailing_dot_not_float() {
+        // Method calls and ranges on integers
+        assert!(Token::float_constant(span!("1.call()")).is_err());
+        assert!(Token::float_constant(span!("1.e5")).is_err());
+        assert!(Token::float_constant(span!("1..10")).is_err());
+    }
+
+    #[test]
+    fn t_float_constant_multiple_dots() {
+        match Token::float_constant(span!("1.2.3 + 4")) {
+            Err(nom::Err::Failure(e)) => {
+                let e = Error::from(e);
+                assert_eq!(e.msg(), Some("invalid floating point number: 1.2.3"));
+                let loc = e.loc().unwrap();
+                assert_eq!(loc.start().column(), 1);
+                assert_eq!(loc.end().column(), 6);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn t_float_constant_matches_rust(
+            negative in proptest::bool::ANY,
+            whole in proptest::option::of("[0-9]{1,20}"),
+            decimal in proptest::option::of("[0-9]{0,20}"),
+            exponent in proptest::option::of("[eE][+-]?[0-9]{1,3}"),
+        ) {
+            let literal = format!(
+                "{}{}{}{}",
+                if negative { "-" } else { "" },
+                whole.as_deref().unwrap_or(""),
+                decimal.as_ref().map(|d| format!(".{}", d)).unwrap_or_default(),
+                exponent.as_deref().unwrap_or(""),
+            );
+
+            let has_digits = whole.is_some() || decimal.as_ref().is_some_and(|d| !d.is_empty());
+            let is_float = has_digits
+                && (decimal.is_some() || exponent.is_some())
+                // `1.e5` is a field access on an integer
+                && !(whole.is_some() && decimal.as_deref() == Some("") && exponent.is_some());
+
+            let parsed = Token::float_constant(LocatedSpan::new_extra(literal.as_str(), None));
+            match is_float {
+                true => {
+                    let (input, value) = parsed.unwrap();
+                    proptest::prop_assert_eq!(*input.fragment(), "");
+                    proptest::prop_assert_eq!(value, literal.parse::<f64>().unwrap());
+                }
+                false => proptest::prop_assert!(parsed.is_err()),
+            }
+        }
     }
 
     #[test]
@@ -558,6 +1006,33 @@ mod tests {
         assert!(Token::identifier(span!("func")).is_err());
     }
 
+    #[test]
+    fn t_id_reserved_keyword() {
+        for keyword in RESERVED_KEYWORDS {
+            match Token::identifier(LocatedSpan::new_extra(keyword, None)) {
+                Err(NomError(e)) => assert_eq!(
+                    Error::from(e).msg(),
+                    Some(format!("`{}` is a reserved keyword", keyword).as_str())
+                ),
+                _ => unreachable!(),
+            }
+        }
+
+        assert!(Token::identifier(span!("func_name")).is_ok());
+        assert!(Token::identifier(span!("if_")).is_ok());
+        assert!(Token::identifier(span!("input")).is_ok());
+    }
+
+    #[test]
+    fn t_keyword() {
+        assert_eq!(
+            frag_tuple!(Token::keyword(span!("mut x"))),
+            Ok((" x", "mut"))
+        );
+        assert!(Token::keyword(span!("mutable")).is_err());
+        assert!(Token::keyword(span!("x")).is_err());
+    }
+
     #[test]
     fn t_bool_valid() {
         assert_eq!(
@@ -609,6 +1084,23 @@ mod tests {
     #[test]
     fn t_multi_comment_invalid() {
         assert!(Token::consume_multi_comment(span!("/*")).is_err());
+        assert!(Token::consume_multi_comment(span!("/* /* */")).is_err());
+    }
+
+    #[test]
+    fn t_multi_comment_nested() {
+        assert_eq!(
+            frag_tuple!(Token::consume_multi_comment(span!("/* a /* b */ c */d"))),
+            Ok(("d", " a /* b */ c "))
+        );
+        assert_eq!(
+            frag_tuple!(Token::consume_multi_comment(span!("/*/**/*/"))),
+            Ok(("", "/**/"))
+        );
+        assert_eq!(
+            frag_tuple!(Token::consume_multi_comment(span!("/* é /* à */ */ x"))),
+            Ok((" x", " é /* à */ "))
+        );
     }
 
     #[test]
@@ -619,6 +1111,12 @@ mod tests {
         );
     }
 
+    #[test]
+    fn t_bang_token() {
+        assert_eq!(frag_tuple!(Token::bang(span!("!a"))), Ok(("a", "!")));
+        assert!(Token::bang(span!("!= a")).is_err());
+    }
+
     #[test]
     fn t_dot_token() {
         assert_eq!(frag_tuple!(Token::dot(span!("."))), Ok(("", ".")));
@@ -0,0 +1,190 @@
+//! The profiler records how many times each function of a program gets called, and
+//! how much time is spent executing them. It is enabled with
+//! [`Context::enable_profiler`], and its report is used to find the hot spots of a
+//! program.
+//!
+//! [`Context::enable_profiler`]: crate::context::Context::enable_profiler
+
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::time::{Duration, Instant};
+
+/// Measures taken for a single function
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FunctionProfile {
+    calls: usize,
+    time: Duration,
+}
+
+impl FunctionProfile {
+    /// Amount of times the function was called
+    pub fn calls(&self) -> usize {
+        self.calls
+    }
+
+    /// Cumulative time spent executing the function, including the functions it
+    /// calls. Time spent in recursive calls is only counted once
+    pub fn time(&self) -> Duration {
+        self.time
+    }
+}
+
+#[derive(Default)]
+pub struct Profiler {
+    functions: HashMap<String, FunctionProfile>,
+    /// Functions currently being executed along with the time their execution
+    /// started at, the innermost one being last
+    stack: Vec<(String, Instant)>,
+}
+
+impl Profiler {
+    pub fn new() -> Profiler {
+        Profiler::default()
+    }
+
+    /// Register the start of a call to a function
+    pub fn enter(&mut self, name: &str) {
+        self.functions.entry(name.to_string()).or_default().calls += 1;
+        self.stack.push((name.to_string(), Instant::now()));
+    }
+
+    /// Register the end of the innermost call started with [`Profiler::enter`]
+    pub fn exit(&mut self) {
+        if let Some((name, start)) = self.stack.pop() {
+            // The time spent in a recursive call is already part of the time of
+            // the outermost call to the function
+            if self.stack.iter().all(|(caller, _)| *caller != name) {
+                self.functions.entry(name).or_default().time += start.elapsed();
+            }
+        }
+    }
+
+    /// Get the measures taken for a function, if it was called
+    pub fn function(&self, name: &str) -> Option<&FunctionProfile> {
+        self.functions.get(name)
+    }
+
+    /// Functions called during the execution, the most time consuming ones first
+    pub fn functions(&self) -> Vec<(&str, &FunctionProfile)> {
+        let mut functions: Vec<_> = self
+            .functions
+            .iter()
+            .map(|(name, profile)| (name.as_str(), profile))
+            .collect();
+
+        functions.sort_by(|(lhs_name, lhs), (rhs_name, rhs)| {
+            rhs.time
+                .cmp(&lhs.time)
+                .then(rhs.calls.cmp(&lhs.calls))
+                .then(lhs_name.cmp(rhs_name))
+        });
+
+        functions
+    }
+}
+
+/// Table of the functions called during the execution, sorted by the time spent
+/// executing them
+impl Display for Profiler {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let functions = self.functions();
+        let width = functions
+            .iter()
+            .map(|(name, _)| name.len())
+            .chain(std::iter::once("function".len()))
+            .max()
+            .unwrap_or_default();
+
+        writeln!(
+            f,
+            "{:<width$}  {:>10}  {:>12}  {:>12}",
+            "function", "calls", "total (ms)", "average (ms)",
+        )?;
+
+        for (name, profile) in functions {
+            let total = profile.time.as_secs_f64() * 1000.;
+
+            writeln!(
+                f,
+                "{:<width$}  {:>10}  {:>12.3}  {:>12.3}",
+                name,
+                profile.calls,
+                total,
+                total / profile.calls as f64,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::Context;
+    use crate::parser;
+
+    fn profile(input: &str) -> Profiler {
+        let mut ctx = Context::new();
+        ctx.enable_profiler();
+
+        parser::parse(&mut ctx, input, None).unwrap();
+        ctx.execute().unwrap();
+
+        ctx.take_profiler().unwrap()
+    }
+
+    #[test]
+    fn call_counts() {
+        let profiler = profile(
+            "func id(x: int) -> int { x }
+            func twice(x: int) -> int { id(x) + id(x) }
+            twice(1);
+            twice(2);",
+        );
+
+        assert_eq!(profiler.function("twice").unwrap().calls(), 2);
+        assert_eq!(profiler.function("id").unwrap().calls(), 4);
+        assert!(profiler.function("unknown").is_none());
+    }
+
+    #[test]
+    fn recursive_calls() {
+        let profiler = profile(
+            "func fib(n: int) -> int {
+                if n < 2 { n } else { fib(n - 1) + fib(n - 2) }
+            }
+            fib(10);",
+        );
+
+        assert_eq!(profiler.function("fib").unwrap().calls(), 177);
+    }
+
+    #[test]
+    fn time_includes_callees() {
+        let profiler = profile(
+            "func inner() -> int { mut i = 0; while i < 100 { i = i + 1 }; i }
+            func outer() -> int { inner() }
+            outer();",
+        );
+
+        let inner = profiler.function("inner").unwrap().time();
+        let outer = profiler.function("outer").unwrap().time();
+
+        assert!(outer >= inner);
+    }
+
+    #[test]
+    fn report() {
+        let mut profiler = Profiler::new();
+        profiler.enter("some_function");
+        profiler.exit();
+
+        let report = profiler.to_string();
+        let mut lines = report.lines();
+
+        assert!(lines.next().unwrap().starts_with("function       "));
+        assert!(lines.next().unwrap().starts_with("some_function  "));
+        assert_eq!(lines.next(), None);
+    }
+}
@@ -0,0 +1,747 @@
+//! Programs can be saved to `.jko` files once parsed, in order to execute them later
+//! without parsing their source again. A `.jko` file starts with a header line
+//! identifying the format and the version of the interpreter which produced it,
+//! followed by the syntax tree of the program in the JSON shape described in the
+//! [`crate::ast`] module:
+//!
+//! ```text
+//! jko 1 0.3.0-jinx4
+//! {"source":"file.jk","instructions":[{"kind":"VarAssign",...}]}
+//! ```
+//!
+//! Files produced by another version of the interpreter are rejected, as the shape
+//! of the syntax tree may differ. Included sources are not part of the saved
+//! program: They are read again, relative to the path of the original source, when
+//! the program is loaded and typechecked.
+
+use std::path::PathBuf;
+
+use crate::ast::{AstNode, Json};
+use crate::context::Context;
+use crate::error::{ErrKind, Error};
+use crate::instruction::{
+    BinaryOp, Block, Break, Continue, DecArg, FieldAccess, FunctionCall, FunctionDec, FunctionKind,
+    IfElse, Incl, Instruction, InterfaceDec, InterfaceImpl, JkInst, Lambda, Loop, LoopKind, Match,
+    MatchArm, MethodCall, Operator, Pattern, Range, Return, TryOp, TypeDec, TypeInstantiation,
+    UnaryOp, UnaryOperator, Var, VarAssign, VarOrEmptyType,
+};
+use crate::location::{Location, SpanTuple};
+use crate::parser;
+use crate::typechecker::TypeId;
+use crate::value::{JkBool, JkChar, JkFloat, JkInt, JkString};
+
+/// Extension of the files containing saved programs
+pub const EXTENSION: &str = "jko";
+
+/// First word of the header of saved programs
+const MAGIC: &str = "jko";
+
+/// Version of the format of saved programs. It needs to be bumped whenever the way
+/// programs are saved changes
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Operators accepted by [`Operator::new`], which panics on unknown ones
+const OPERATORS: [&str; 17] = [
+    "+", "-", "*", "/", "%", "**", "&", "|", "^", "<<", ">>", "<", ">", "<=", ">=", "==", "!=",
+];
+
+/// Save the instructions parsed in a context, along with the path of their source
+///
+/// ```
+/// use jinko::{context::Context, program};
+///
+/// let mut ctx = Context::new();
+/// jinko::parse(&mut ctx, "x = 15", None).unwrap();
+///
+/// let saved = program::save(&ctx);
+///
+/// let mut loaded = Context::new();
+/// program::load(&mut loaded, &saved).unwrap();
+/// assert_eq!(loaded.ast_json(), ctx.ast_json());
+/// ```
+pub fn save(ctx: &Context) -> String {
+    // The entry point always has a block
+    let instructions: Vec<AstNode> = ctx
+        .entry_point
+        .block()
+        .unwrap()
+        .instructions()
+        .iter()
+        .map(|inst| inst.ast())
+        .collect();
+    let source = ctx.path().map(|path| path.display().to_string());
+
+    let program = Json::object(vec![
+        ("source", source.into()),
+        ("instructions", instructions.into()),
+    ]);
+
+    format!("{}\n{}\n", header(), program)
+}
+
+/// Load a program saved with [`save`] in a context, as if its source had been
+/// parsed. The path of the context is set to the one of the original source
+pub fn load(ctx: &mut Context, input: &str) -> Result<(), Error> {
+    let (file_header, program) = input.split_once('\n').unwrap_or((input, ""));
+
+    check_header(file_header)?;
+
+    let program = Json::parse(program)?;
+
+    let instructions = match program.get("instructions") {
+        Some(Json::Array(nodes)) => nodes
+            .iter()
+            .map(|node| instruction(Node(node)))
+            .collect::<Result<Vec<_>, Error>>()?,
+        _ => return Err(invalid(String::from("missing instructions"))),
+    };
+    let source = match program.get("source") {
+        Some(Json::Str(path)) => Some(PathBuf::from(path)),
+        _ => None,
+    };
+
+    ctx.set_path(source);
+    // The entry point always has a block
+    ctx.entry_point
+        .block_mut()
+        .unwrap()
+        .add_instructions(instructions);
+
+    Ok(())
+}
+
+fn header() -> String {
+    format!("{} {} {}", MAGIC, FORMAT_VERSION, env!("CARGO_PKG_VERSION"))
+}
+
+fn check_header(file_header: &str) -> Result<(), Error> {
+    let mut words = file_header.split_whitespace();
+
+    if words.next() != Some(MAGIC) {
+        return Err(Error::new(ErrKind::Parsing)
+            .with_msg(String::from("not a saved jinko program"))
+            .with_hint(Error::hint().with_msg(format!(
+                "programs are saved with `jinko build <input>`, in `.{}` files",
+                EXTENSION
+            ))));
+    }
+
+    if file_header != header() {
+        let version = words.nth(1).unwrap_or("unknown");
+
+        return Err(Error::new(ErrKind::Parsing)
+            .with_msg(format!(
+                "program was saved by jinko {}, which is incompatible with jinko {}",
+                version,
+                env!("CARGO_PKG_VERSION")
+            ))
+            .with_hint(
+                Error::hint().with_msg(String::from("build the program again from its source")),
+            ));
+    }
+
+    Ok(())
+}
+
+fn invalid(msg: String) -> Error {
+    Error::new(ErrKind::Parsing).with_msg(format!("invalid saved program: {}", msg))
+}
+
+/// Syntax tree node of a saved program
+#[derive(Clone, Copy)]
+struct Node<'n>(&'n Json);
+
+impl<'n> Node<'n> {
+    fn kind(&self) -> Result<&'n str, Error> {
+        match self.0.get("kind") {
+            Some(Json::Str(kind)) => Ok(kind),
+            _ => Err(invalid(String::from("node without a kind"))),
+        }
+    }
+
+    fn location(&self) -> Result<Option<SpanTuple>, Error> {
+        fn location(loc: Option<&Json>) -> Option<Location> {
+            match (loc?.get("line")?, loc?.get("column")?) {
+                (Json::Int(line), Json::Int(column)) => {
+                    Some(Location::new(*line as usize, *column as usize))
+                }
+                _ => None,
+            }
+        }
+
+        let span = match self.0.get("span") {
+            None | Some(Json::Null) => return Ok(None),
+            Some(span) => span,
+        };
+        let path = match span.get("path") {
+            Some(Json::Str(path)) => Some(PathBuf::from(path)),
+            _ => None,
+        };
+
+        match (location(span.get("start")), location(span.get("end"))) {
+            (Some(start), Some(end)) => Ok(Some(SpanTuple::new(path, start, end))),
+            _ => Err(invalid(format!("invalid span in `{}` node", self.kind()?))),
+        }
+    }
+
+    fn member(&self, group: &str, name: &str) -> Result<&'n Json, Error> {
+        let kind = self.kind()?;
+
+        self.0
+            .get(group)
+            .and_then(|members| members.get(name))
+            .ok_or_else(|| invalid(format!("missing `{}` in `{}` node", name, kind)))
+    }
+
+    fn attr(&self, name: &str) -> Result<&'n Json, Error> {
+        self.member("attributes", name)
+    }
+
+    fn mismatch(&self, name: &str, expected: &str) -> Error {
+        invalid(format!(
+            "`{}` should be {} in `{}` node",
+            name,
+            expected,
+            self.kind().unwrap_or("unknown")
+        ))
+    }
+
+    fn str(&self, name: &str) -> Result<&'n str, Error> {
+        match self.attr(name)? {
+            Json::Str(s) => Ok(s),
+            _ => Err(self.mismatch(name, "a string")),
+        }
+    }
+
+    fn opt_str(&self, name: &str) -> Result<Option<&'n str>, Error> {
+        match self.attr(name)? {
+            Json::Str(s) => Ok(Some(s)),
+            Json::Null => Ok(None),
+            _ => Err(self.mismatch(name, "a string")),
+        }
+    }
+
+    fn bool(&self, name: &str) -> Result<bool, Error> {
+        match self.attr(name)? {
+            Json::Bool(b) => Ok(*b),
+            _ => Err(self.mismatch(name, "a boolean")),
+        }
+    }
+
+    fn ty(&self, name: &str) -> Result<TypeId, Error> {
+        parser::parse_type(self.str(name)?)
+    }
+
+    fn opt_ty(&self, name: &str) -> Result<Option<TypeId>, Error> {
+        self.opt_str(name)?.map(parser::parse_type).transpose()
+    }
+
+    fn types(&self, name: &str) -> Result<Vec<TypeId>, Error> {
+        match self.attr(name)? {
+            Json::Array(types) => types
+                .iter()
+                .map(|ty| match ty {
+                    Json::Str(ty) => parser::parse_type(ty),
+                    _ => Err(self.mismatch(name, "a list of types")),
+                })
+                .collect(),
+            _ => Err(self.mismatch(name, "a list of types")),
+        }
+    }
+
+    fn child(&self, name: &str) -> Result<Node<'n>, Error> {
+        match self.member("children", name)? {
+            child @ Json::Object(_) => Ok(Node(child)),
+            _ => Err(self.mismatch(name, "a node")),
+        }
+    }
+
+    fn opt_child(&self, name: &str) -> Result<Option<Node<'n>>, Error> {
+        match self.member("children", name)? {
+            Json::Null => Ok(None),
+            _ => self.child(name).map(Some),
+        }
+    }
+
+    fn children(&self, name: &str) -> Result<Vec<Node<'n>>, Error> {
+        match self.member("children", name)? {
+            Json::Array(nodes) => Ok(nodes.iter().map(Node).collect()),
+            _ => Err(self.mismatch(name, "a list of nodes")),
+        }
+    }
+}
+
+/// Set the location of a decoded instruction, if the node has one
+macro_rules! located {
+    ($node:expr, $inst:expr) => {{
+        let mut inst = $inst;
+        if let Some(location) = $node.location()? {
+            inst.set_location(location);
+        }
+        inst
+    }};
+}
+
+fn instruction(node: Node) -> Result<Box<dyn Instruction>, Error> {
+    let inst: Box<dyn Instruction> = match node.kind()? {
+        "Constant" => constant(node)?,
+        "Block" => Box::new(block(node)?),
+        "Var" => Box::new(var(node)?),
+        "VarAssign" => Box::new(var_assign(node)?),
+        "VarOrEmptyType" => Box::new(located!(
+            node,
+            VarOrEmptyType::new(node.str("name")?.to_string())
+        )),
+        "BinaryOp" => {
+            let op = node.str("operator")?;
+            if !OPERATORS.contains(&op) {
+                return Err(invalid(format!("unknown operator `{}`", op)));
+            }
+
+            Box::new(located!(
+                node,
+                BinaryOp::new(
+                    instruction(node.child("lhs")?)?,
+                    instruction(node.child("rhs")?)?,
+                    Operator::new(op),
+                )
+            ))
+        }
+        "UnaryOp" => {
+            let op = match node.str("operator")? {
+                op @ ("-" | "!") => UnaryOperator::new(op),
+                op => return Err(invalid(format!("unknown unary operator `{}`", op))),
+            };
+
+            Box::new(located!(
+                node,
+                UnaryOp::new(instruction(node.child("operand")?)?, op)
+            ))
+        }
+        "FunctionCall" => Box::new(located!(
+            node,
+            FunctionCall::new(
+                node.str("name")?.to_string(),
+                node.types("generics")?,
+                instructions(node.children("args")?)?,
+            )
+        )),
+        "MethodCall" => Box::new(method_call(node)?),
+        "FunctionDec" => Box::new(function_dec(node)?),
+        "Lambda" => Box::new(located!(
+            node,
+            Lambda::new(function_dec(node.child("function")?)?)
+        )),
+        "Loop" => Box::new(loop_block(node)?),
+        "Break" => Box::new(located!(
+            node,
+            Break::new(node.opt_child("value")?.map(instruction).transpose()?)
+        )),
+        "Continue" => Box::new(located!(node, Continue::new())),
+        "Return" => Box::new(located!(
+            node,
+            Return::new(node.opt_child("value")?.map(instruction).transpose()?)
+        )),
+        "IfElse" => Box::new(located!(
+            node,
+            IfElse::new(
+                instruction(node.child("condition")?)?,
+                block(node.child("if_body")?)?,
+                node.opt_child("else_body")?.map(block).transpose()?,
+            )
+        )),
+        "Match" => {
+            let arms = node
+                .children("arms")?
+                .into_iter()
+                .map(match_arm)
+                .collect::<Result<Vec<_>, Error>>()?;
+
+            Box::new(located!(
+                node,
+                Match::new(instruction(node.child("value")?)?, arms)
+            ))
+        }
+        "Range" => Box::new(located!(
+            node,
+            Range::new(
+                instruction(node.child("start")?)?,
+                instruction(node.child("end")?)?,
+                node.bool("inclusive")?,
+            )
+        )),
+        "TypeDec" => Box::new(type_dec(node)?),
+        "TypeInstantiation" => {
+            let mut instantiation = TypeInstantiation::new(node.ty("type")?);
+            instantiation.set_generics(node.types("generics")?);
+            for field in node.children("fields")? {
+                instantiation.add_field(var_assign(field)?);
+            }
+
+            Box::new(located!(node, instantiation))
+        }
+        "FieldAccess" => Box::new(located!(
+            node,
+            FieldAccess::new(
+                instruction(node.child("instance")?)?,
+                node.str("field")?.to_string(),
+            )
+        )),
+        "TryOp" => Box::new(located!(
+            node,
+            TryOp::new(instruction(node.child("operand")?)?)
+        )),
+        "InterfaceDec" => Box::new(located!(
+            node,
+            InterfaceDec::new(
+                node.str("name")?.to_string(),
+                function_decs(node.children("functions")?)?,
+            )
+        )),
+        "InterfaceImpl" => Box::new(located!(
+            node,
+            InterfaceImpl::new(
+                node.str("interface")?.to_string(),
+                node.ty("type")?,
+                function_decs(node.children("functions")?)?,
+            )
+        )),
+        "Incl" => Box::new(located!(
+            node,
+            Incl::new(
+                node.str("path")?.to_string(),
+                node.opt_str("alias")?.map(String::from),
+            )
+        )),
+        "JkInst" => {
+            let directive = located!(
+                node,
+                FunctionCall::new(node.str("name")?.to_string(), vec![], vec![])
+            );
+
+            Box::new(JkInst::from_function_call(&directive)?)
+        }
+        kind => return Err(invalid(format!("unknown node kind `{}`", kind))),
+    };
+
+    Ok(inst)
+}
+
+fn instructions(nodes: Vec<Node>) -> Result<Vec<Box<dyn Instruction>>, Error> {
+    nodes.into_iter().map(instruction).collect()
+}
+
+fn constant(node: Node) -> Result<Box<dyn Instruction>, Error> {
+    let value = node.attr("value")?;
+    let constant: Box<dyn Instruction> = match (node.str("type")?, value) {
+        ("bool", Json::Bool(b)) => Box::new(located!(node, JkBool::from(*b))),
+        ("int", Json::Int(i)) => Box::new(located!(node, JkInt::from(*i))),
+        ("float", Json::Float(f)) => Box::new(located!(node, JkFloat::from(*f))),
+        ("float", Json::Int(i)) => Box::new(located!(node, JkFloat::from(*i as f64))),
+        ("string", Json::Str(s)) => Box::new(located!(node, JkString::from(s.as_str()))),
+        ("char", Json::Str(s)) if s.chars().count() == 1 => {
+            Box::new(located!(node, JkChar::from(s.chars().next().unwrap())))
+        }
+        (ty, _) => return Err(node.mismatch("value", &format!("a valid `{}`", ty))),
+    };
+
+    Ok(constant)
+}
+
+fn block(node: Node) -> Result<Block, Error> {
+    let mut block = Block::new();
+    block.set_instructions(instructions(node.children("instructions")?)?);
+    block.set_statement(node.bool("is_statement")?);
+
+    Ok(located!(node, block))
+}
+
+fn var(node: Node) -> Result<Var, Error> {
+    let mut var = Var::new(node.str("name")?.to_string());
+    var.set_mutable(node.bool("mutable")?);
+
+    Ok(located!(node, var))
+}
+
+fn var_assign(node: Node) -> Result<VarAssign, Error> {
+    let assign = VarAssign::new(
+        node.bool("mutable")?,
+        node.str("name")?.to_string(),
+        instruction(node.child("value")?)?,
+    );
+
+    Ok(located!(node, assign))
+}
+
+fn method_call(node: Node) -> Result<MethodCall, Error> {
+    let receiver = instruction(node.child("receiver")?)?;
+    let mut args = instructions(node.children("args")?)?;
+
+    let call = match (node.bool("string_literal")?, args.len()) {
+        (true, 1) => MethodCall::string_literal(receiver, args.remove(0)),
+        (true, _) => return Err(node.mismatch("args", "a single string part")),
+        (false, _) => MethodCall::new(
+            receiver,
+            FunctionCall::new(node.str("name")?.to_string(), node.types("generics")?, args),
+        ),
+    };
+
+    Ok(located!(node, call))
+}
+
+fn type_dec(node: Node) -> Result<TypeDec, Error> {
+    let name = node.str("name")?.to_string();
+    let variants = node
+        .children("variants")?
+        .into_iter()
+        .map(type_dec)
+        .collect::<Result<Vec<TypeDec>, Error>>()?;
+
+    let dec = match variants.is_empty() {
+        true => TypeDec::new(
+            name,
+            node.types("generics")?,
+            dec_args(node.children("fields")?)?,
+        ),
+        false => TypeDec::new_enum(name, variants),
+    };
+
+    Ok(located!(node, dec))
+}
+
+fn dec_args(nodes: Vec<Node>) -> Result<Vec<DecArg>, Error> {
+    nodes
+        .into_iter()
+        .map(|node| {
+            let mut arg = DecArg::new(node.str("name")?.to_string(), node.ty("type")?);
+            if let Some(default) = node.opt_child("default")? {
+                arg.set_default(instruction(default)?);
+            }
+
+            Ok(located!(node, arg))
+        })
+        .collect()
+}
+
+fn function_dec(node: Node) -> Result<FunctionDec, Error> {
+    let kind = match node.str("kind")? {
+        "unknown" => FunctionKind::Unknown,
+        "func" => FunctionKind::Func,
+        "ext" => FunctionKind::Ext,
+        "test" => FunctionKind::Test,
+        "mock" => FunctionKind::Mock,
+        kind => return Err(invalid(format!("unknown function kind `{}`", kind))),
+    };
+
+    let mut dec = FunctionDec::new(
+        node.str("name")?.to_string(),
+        node.opt_ty("return_type")?,
+        node.types("generics")?,
+        dec_args(node.children("args")?)?,
+    );
+    dec.set_kind(kind);
+    if let Some(body) = node.opt_child("block")? {
+        dec.set_block(block(body)?);
+    }
+
+    // `FunctionDec::set_location` moves the end of the span one column back, which
+    // was already done when the program was saved
+    if let Some(loc) = node.location()? {
+        let end = Location::new(loc.end().line(), loc.end().column() + 1);
+        dec.set_location(SpanTuple::new(loc.path().clone(), loc.start().clone(), end));
+    }
+
+    Ok(dec)
+}
+
+fn function_decs(nodes: Vec<Node>) -> Result<Vec<FunctionDec>, Error> {
+    nodes.into_iter().map(function_dec).collect()
+}
+
+fn loop_block(node: Node) -> Result<Loop, Error> {
+    let kind = match node.str("kind")? {
+        "for" => LoopKind::For(
+            Box::new(var(node.child("variable")?)?),
+            instruction(node.child("iterator")?)?,
+        ),
+        "while" => LoopKind::While(instruction(node.child("condition")?)?),
+        "loop" => LoopKind::Loop,
+        kind => return Err(invalid(format!("unknown loop kind `{}`", kind))),
+    };
+
+    Ok(located!(
+        node,
+        Loop::new(kind, block(node.child("block")?)?)
+    ))
+}
+
+fn match_arm(node: Node) -> Result<MatchArm, Error> {
+    let pattern_node = node.child("pattern")?;
+    let pattern = match pattern_node.kind()? {
+        "ConstantPattern" => Pattern::Constant(instruction(pattern_node.child("value")?)?),
+        "TypePattern" => {
+            let bindings = match pattern_node.attr("bindings")? {
+                Json::Array(bindings) => bindings
+                    .iter()
+                    .map(
+                        |binding| match (binding.get("field"), binding.get("binding")) {
+                            (Some(Json::Str(field)), Some(Json::Str(binding))) => {
+                                Ok((field.clone(), binding.clone()))
+                            }
+                            _ => Err(pattern_node.mismatch("bindings", "a list of bindings")),
+                        },
+                    )
+                    .collect::<Result<Vec<_>, Error>>()?,
+                _ => return Err(pattern_node.mismatch("bindings", "a list of bindings")),
+            };
+
+            Pattern::Type(pattern_node.ty("type")?, bindings)
+        }
+        "WildcardPattern" => Pattern::Wildcard,
+        kind => return Err(invalid(format!("unknown pattern kind `{}`", kind))),
+    };
+
+    Ok(located!(
+        node,
+        MatchArm::new(pattern, block(node.child("block")?)?)
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::JkInt;
+    use crate::FromObjectInstance;
+
+    /// Save a program and check that loading it gives back the same instructions
+    fn round_trip(input: &str) -> String {
+        let mut ctx = Context::new();
+        parser::parse(&mut ctx, input, None).unwrap();
+
+        let saved = save(&ctx);
+
+        let mut loaded = Context::new();
+        load(&mut loaded, &saved).unwrap();
+        assert_eq!(loaded.ast_json(), ctx.ast_json());
+
+        saved
+    }
+
+    fn run(input: &str) -> i64 {
+        let saved = round_trip(input);
+
+        let mut ctx = Context::new();
+        ctx.init_stdlib().unwrap();
+        load(&mut ctx, &saved).unwrap();
+        let result = ctx.execute().unwrap().unwrap();
+
+        JkInt::from_instance(&result).rust_value()
+    }
+
+    #[test]
+    fn functions_and_loops() {
+        assert_eq!(
+            run(
+                "func fib(n: int) -> int { if n < 2 { n } else { fib(n - 1) + fib(n - 2) } }
+            mut sum = 0;
+            for i in 0..5 { sum = sum + fib(i) };
+            while sum < 100 { sum = sum * 2 };
+            sum"
+            ),
+            112
+        );
+    }
+
+    #[test]
+    fn types_and_matches() {
+        assert_eq!(
+            run("type Point(x: int, y: int);
+            func norm(p: Point) -> int { p.x * p.x + p.y * p.y }
+            value = match Point(x: 3, y: 4) { Point(x, y: other) -> { x + other }, _ -> { 0 } };
+            norm(Point(x: 1, y: 2)) + value"),
+            12
+        );
+    }
+
+    #[test]
+    fn enums() {
+        assert_eq!(
+            run("type Shape = Circle(r: int) | Square(side: int) | Empty;
+            func area(s: Shape) -> int {
+                match s { Circle(r) -> { 3 * r * r }, Square(side) -> { side * side }, Empty -> { 0 } }
+            }
+            area(Circle(r: 1)) + area(Square(side: 2)) + area(Empty)"),
+            7
+        );
+    }
+
+    #[test]
+    fn generics_lambdas_and_strings() {
+        assert_eq!(
+            run("func id[T](x: T) -> T { x }
+            apply = func(x: int) -> int { x * 3 };
+            name = id[string](\"bc\");
+            s = \"a\\t{name}\";
+            apply(id[int](4)) + s.len()"),
+            16
+        );
+    }
+
+    #[test]
+    fn whole_program() {
+        round_trip(
+            "incl some_module as module
+            type Pair[T](a: T, b: T);
+            interface Show { func show(s: Self) -> string; }
+            impl Show for Pair[int] { func show(p: Pair[int]) -> string { \"pair\" } }
+            ext func puts(s: string) -> int;
+            test works() { assert(!false) }
+            x = Pair[int](a: -1, b: 2);
+            loop { break 4 };
+            c = 'c';
+            f = 1.5e3;
+            r = x?.a;
+            @dump()",
+        );
+    }
+
+    #[test]
+    fn source_path() {
+        let mut ctx = Context::new();
+        ctx.set_path(Some(PathBuf::from("dir/file.jk")));
+
+        let mut loaded = Context::new();
+        load(&mut loaded, &save(&ctx)).unwrap();
+
+        assert_eq!(loaded.path(), Some(&PathBuf::from("dir/file.jk")));
+    }
+
+    #[test]
+    fn invalid_header() {
+        let mut ctx = Context::new();
+
+        assert!(load(&mut ctx, "x = 15").is_err());
+        assert!(load(
+            &mut ctx,
+            "jko 0 0.0.0\n{\"source\":null,\"instructions\":[]}"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn invalid_program() {
+        let mut ctx = Context::new();
+        let load_program =
+            |ctx: &mut Context, program: &str| load(ctx, &format!("{}\n{}", header(), program));
+
+        assert!(load_program(&mut ctx, "{\"source\":null,\"instructions\":[]}").is_ok());
+        assert!(load_program(&mut ctx, "{\"source\":null}").is_err());
+        assert!(load_program(&mut ctx, "{\"source\":null,\"instructions\":[{}]}").is_err());
+        assert!(load_program(
+            &mut ctx,
+            r#"{"source":null,"instructions":[{"kind":"BinaryOp","span":null,
+            "attributes":{"operator":"@"},"children":{}}]}"#
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,296 @@
+//! A project groups multiple jinko source files. Its root directory contains a
+//! `jinko.toml` manifest declaring the entry point of the project, the directories
+//! its sources are included from and the libraries it depends on:
+//!
+//! ```toml
+//! [project]
+//! name = "calculator"
+//! entry = "src/main.jk"
+//! sources = ["src", "lib"]
+//!
+//! [dependencies]
+//! maths = "../maths"
+//! ```
+//!
+//! Paths are relative to the root of the project. When including a source, it is
+//! looked for next to the including file, then in each source directory, in the order
+//! they are declared. Dependencies are included by their name: `incl maths` includes
+//! `../maths/lib.jk`, and `incl "maths/trigo"` includes `../maths/trigo.jk`.
+//!
+//! Only the subset of TOML used by manifests is supported: Tables, strings and
+//! arrays of strings.
+
+use std::path::{Path, PathBuf};
+
+use crate::error::{ErrKind, Error};
+use crate::location::{Location, SpanTuple};
+
+/// Name of the manifest file found at the root of projects
+pub const MANIFEST: &str = "jinko.toml";
+
+/// A jinko project, as declared by its manifest
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Project {
+    root: PathBuf,
+    name: Option<String>,
+    entry: PathBuf,
+    sources: Vec<PathBuf>,
+    dependencies: Vec<(String, PathBuf)>,
+}
+
+/// Value of a key in the manifest
+enum Value {
+    Str(String),
+    Array(Vec<String>),
+}
+
+impl Project {
+    /// Find the manifest of the project containing a directory, by looking for it in
+    /// the directory and all of its parents
+    pub fn find(dir: &Path) -> Option<PathBuf> {
+        dir.ancestors()
+            .map(|ancestor| ancestor.join(MANIFEST))
+            .find(|manifest| manifest.is_file())
+    }
+
+    /// Load the project described by a manifest file
+    pub fn load(manifest: &Path) -> Result<Project, Error> {
+        let input = std::fs::read_to_string(manifest).map_err(|e| {
+            Error::new(ErrKind::IO).with_msg(format!("cannot read {:?}: {}", manifest, e))
+        })?;
+
+        // The manifest is always in a directory, even if it is the current one
+        let root = manifest.parent().unwrap_or_else(|| Path::new(""));
+
+        Project::parse(root, &input, Some(manifest))
+    }
+
+    /// Parse the content of a manifest, for a project located in the `root`
+    /// directory. The path of the manifest is used to locate errors
+    ///
+    /// ```
+    /// use jinko::project::Project;
+    /// use std::path::Path;
+    ///
+    /// let manifest = "[project]\nentry = \"src/main.jk\"\nsources = [\"src\"]\n";
+    /// let project = Project::parse(Path::new("calc"), manifest, None).unwrap();
+    ///
+    /// assert_eq!(project.entry(), Path::new("calc/src/main.jk"));
+    /// assert_eq!(project.sources(), &[Path::new("calc/src").to_owned()]);
+    /// ```
+    pub fn parse(root: &Path, input: &str, manifest: Option<&Path>) -> Result<Project, Error> {
+        let mut project = Project {
+            root: root.to_owned(),
+            ..Default::default()
+        };
+        let mut entry = None;
+        let mut table = None;
+
+        for (idx, line) in input.lines().enumerate() {
+            let loc = SpanTuple::new(
+                manifest,
+                Location::new(idx + 1, 1),
+                Location::whole_line(idx + 1),
+            );
+            let error = |msg: String| {
+                Error::new(ErrKind::Parsing)
+                    .with_msg(msg)
+                    .with_loc(Some(loc.clone()))
+            };
+
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                table = match name.trim() {
+                    name @ ("project" | "dependencies") => Some(name.to_owned()),
+                    name => return Err(error(format!("unknown table in manifest: `{}`", name))),
+                };
+                continue;
+            }
+
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), parse_value(value.trim())),
+                None => return Err(error(format!("expected `key = value`, got `{}`", line))),
+            };
+            let value = value.ok_or_else(|| {
+                error(format!(
+                    "invalid value for `{}`: expected a string or an array of strings",
+                    key
+                ))
+            })?;
+
+            match (table.as_deref(), key, value) {
+                (Some("project"), "name", Value::Str(name)) => project.name = Some(name),
+                (Some("project"), "entry", Value::Str(path)) => entry = Some(root.join(path)),
+                (Some("project"), "sources", Value::Array(paths)) => {
+                    project.sources = paths.iter().map(|path| root.join(path)).collect()
+                }
+                (Some("dependencies"), name, Value::Str(path)) => project
+                    .dependencies
+                    .push((name.to_owned(), root.join(path))),
+                (Some("project"), "name" | "entry", _) => {
+                    return Err(error(format!("`{}` should be a string", key)))
+                }
+                (Some("project"), "sources", _) => {
+                    return Err(error(String::from("`sources` should be an array of paths")))
+                }
+                (Some("dependencies"), name, _) => {
+                    return Err(error(format!(
+                        "the path of dependency `{}` should be a string",
+                        name
+                    )))
+                }
+                (Some(_), key, _) => {
+                    return Err(error(format!("unknown key in manifest: `{}`", key)))
+                }
+                (None, key, _) => {
+                    return Err(
+                        error(format!("key `{}` is not part of a table", key)).with_hint(
+                            Error::hint().with_msg(String::from(
+                                "project settings belong in the `[project]` table",
+                            )),
+                        ),
+                    )
+                }
+            }
+        }
+
+        project.entry = entry.ok_or_else(|| {
+            Error::new(ErrKind::Parsing).with_msg(format!(
+                "no entry point declared in {}: add `entry = \"<file>\"` to the `[project]` table",
+                manifest.map_or(MANIFEST.to_owned(), |path| format!("{:?}", path))
+            ))
+        })?;
+
+        Ok(project)
+    }
+
+    /// Directory containing the manifest of the project
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Name of the project, if one was given
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Source file executed when running the project
+    pub fn entry(&self) -> &Path {
+        &self.entry
+    }
+
+    /// Directories in which included sources are looked for
+    pub fn sources(&self) -> &[PathBuf] {
+        &self.sources
+    }
+
+    /// Get the directory of a dependency from its name
+    pub fn dependency(&self, name: &str) -> Option<&Path> {
+        self.dependencies
+            .iter()
+            .find(|(dep, _)| dep == name)
+            .map(|(_, path)| path.as_path())
+    }
+}
+
+/// Remove the comment at the end of a line, if any. Comments start with a `#` which
+/// is not part of a string
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    for (idx, c) in line.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..idx],
+            _ => {}
+        }
+    }
+
+    line
+}
+
+fn parse_string(input: &str) -> Option<String> {
+    input
+        .strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .filter(|s| !s.contains('"'))
+        .map(String::from)
+}
+
+fn parse_value(input: &str) -> Option<Value> {
+    if let Some(items) = input.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+        return items
+            .split(',')
+            .map(str::trim)
+            // Allow trailing commas
+            .filter(|item| !item.is_empty())
+            .map(parse_string)
+            .collect::<Option<Vec<String>>>()
+            .map(Value::Array);
+    }
+
+    parse_string(input).map(Value::Str)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> Result<Project, Error> {
+        Project::parse(Path::new("root"), input, None)
+    }
+
+    #[test]
+    fn parse_manifest() {
+        let project = parse(
+            r#"
+# A comment
+[project]
+name = "calc" # the name
+entry = "src/main.jk"
+sources = ["src", "lib",]
+
+[dependencies]
+maths = "../maths"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(project.name(), Some("calc"));
+        assert_eq!(project.entry(), Path::new("root/src/main.jk"));
+        assert_eq!(
+            project.sources(),
+            &[PathBuf::from("root/src"), PathBuf::from("root/lib")]
+        );
+        assert_eq!(
+            project.dependency("maths"),
+            Some(Path::new("root/../maths"))
+        );
+        assert_eq!(project.dependency("trigo"), None);
+    }
+
+    #[test]
+    fn parse_manifest_without_entry() {
+        assert!(parse("[project]\nname = \"calc\"").is_err());
+    }
+
+    #[test]
+    fn parse_manifest_invalid() {
+        assert!(parse("entry = \"main.jk\"").is_err());
+        assert!(parse("[project]\nentry = main.jk").is_err());
+        assert!(parse("[project]\nentry = [\"main.jk\"]").is_err());
+        assert!(parse("[project]\nentry = \"main.jk\"\nversion = \"1.0\"").is_err());
+        assert!(parse("[package]\nentry = \"main.jk\"").is_err());
+        assert!(parse("[project]\nentry").is_err());
+    }
+
+    #[test]
+    fn parse_manifest_error_location() {
+        let err = parse("[project]\nentry = \"main.jk\"\n\nsources = \"src\"").unwrap_err();
+
+        assert_eq!(err.loc().unwrap().start().line(), 4);
+    }
+}
@@ -5,14 +5,19 @@
 mod type_id;
 pub use type_id::{TypeId, PRIMITIVE_TYPES};
 
-use crate::context::ScopeMap;
+use crate::analysis::{Definition, SymbolKind};
+use crate::context::{Capabilities, Capability, ScopeMap, SymbolMap};
 use crate::error::{ErrKind, Error, ErrorHandler};
-use crate::instruction::{FunctionDec, Instruction, TypeDec};
+use crate::generics::{self, GenericExpander, GenericMap};
+use crate::instruction::{FunctionDec, FunctionKind, Incl, Instruction, InterfaceDec, TypeDec};
+use crate::location::SpanTuple;
+use crate::project::Project;
+use crate::symbol::Symbol;
 
 use colored::Colorize;
 
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     fmt::{Display, Formatter, Result as FmtResult},
     path::{Path, PathBuf},
 };
@@ -20,21 +25,16 @@ use std::{
 /// The [`CheckedType`] enum contains three possible states about the type. Either the
 /// type has been properly resolved to something, or it corresponds to a Void type. If the
 /// type has not been resolved yet, it can be unknown.
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug, Default)]
 pub enum CheckedType {
     Resolved(TypeId),
     // Should we remove this for Resolved(TypeId::void())?
     Void,
+    #[default]
     Error,
     Later,
 }
 
-impl Default for CheckedType {
-    fn default() -> CheckedType {
-        CheckedType::Error
-    }
-}
-
 impl Display for CheckedType {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         match self {
@@ -47,13 +47,61 @@ impl Display for CheckedType {
     }
 }
 
+impl CheckedType {
+    /// Render the type as it would be written in jinko source code, without colors
+    pub fn print(&self) -> String {
+        match self {
+            CheckedType::Resolved(ty) => ty.print(),
+            CheckedType::Void => String::from("void"),
+            CheckedType::Error | CheckedType::Later => String::from("unknown"),
+        }
+    }
+}
+
 /// Possible generic generated nodes. Since we can only expand generic functions or
 /// generic types, there is no need to store any other instruction type.
+#[derive(Clone)]
 pub enum SpecializedNode {
     Func(Box<FunctionDec>),
     Type(TypeDec),
 }
 
+/// Type and mutability of a variable, as kept by the [`TypeCtx`]
+#[derive(Clone)]
+struct VarInfo {
+    ty: CheckedType,
+    mutable: bool,
+    /// Location of the assignment declaring the variable, if it was declared by one.
+    /// Only these variables are reported when they are not used
+    location: Option<SpanTuple>,
+    used: bool,
+}
+
+impl VarInfo {
+    fn new(ty: CheckedType, mutable: bool) -> VarInfo {
+        VarInfo {
+            ty,
+            mutable,
+            location: None,
+            used: false,
+        }
+    }
+}
+
+/// Variables which can be captured by a closure being typechecked. Closures capture
+/// the variables they reference by value, when they are created
+#[derive(Clone)]
+struct Capture {
+    /// Frame entered for the body of the closure
+    frame: usize,
+    /// Variables visible where the closure is created
+    outer: HashMap<String, VarInfo>,
+    /// Variables which are themselves captured by the enclosing closure
+    inherited: HashSet<String>,
+    /// Variables referenced in the body of the closure, in order of first use
+    used: Vec<String>,
+}
+
 // TODO: Should we factor this into a `Context` trait? All contexts will share some
 // similarities, such as the ability to emit errors or enter and exit scopes
 /// The [`TypeCtx`]'s role is to keep track of declared types based on the scope they
@@ -61,6 +109,7 @@ pub enum SpecializedNode {
 /// in order to resolve to a concrete type.Each declaration (First [`VarAssign`],
 /// [`FunctionDec`]s and [`TypeDec`]s) can also declare a new type and make it available
 /// to all instructions in the avaialble scopes.
+#[derive(Clone)]
 pub struct TypeCtx {
     /// Reference to the context's error handler
     pub(crate) error_handler: ErrorHandler,
@@ -68,16 +117,49 @@ pub struct TypeCtx {
     /// map: Variables, Functions and Types.
     /// For functions, we keep a vector of argument types as well as the return type.
     /// Custom types need to keep a type for themselves, as well as types for all their fields
-    types: ScopeMap<CheckedType, FunctionDec, TypeDec>,
+    types: ScopeMap<VarInfo, FunctionDec, TypeDec>,
     /// When typechecking, monomorphization is performed, meaning that generic functions
     /// and types get expanded into a new [`Instruction`]. We need to store them
     /// as we go and then use them in the calling context
     // FIXME: Turn this into a hashset?
     generated: Vec<SpecializedNode>,
+    /// Types of the values given to `break` instructions, for each loop currently
+    /// being typechecked. The innermost loop is the last one
+    loops: Vec<Vec<CheckedType>>,
+    /// Return types of the functions currently being typechecked. The innermost
+    /// function is the last one
+    functions: Vec<CheckedType>,
+    /// Closures currently being typechecked. The innermost closure is the last one
+    captures: Vec<Capture>,
+    /// Interfaces declared so far
+    interfaces: HashMap<String, InterfaceDec>,
+    /// Pairs of interfaces and of the types implementing them
+    implementations: HashSet<(String, String)>,
+    /// Names of the functions which were called or referenced so far
+    used_functions: HashSet<String>,
+    /// Symbols declared so far, with their location, for tooling purposes
+    definitions: Vec<Definition>,
+    /// Original names of the symbols declared under a prefix by inclusions
+    symbols: SymbolMap,
     // FIXME: Remove both of these fields...
     /// Path from which the typechecking context was instantiated
     path: Option<PathBuf>,
     included: HashSet<PathBuf>,
+    /// Sources currently being included, from the outermost to the innermost one.
+    /// This is used to detect cyclic inclusions
+    including: Vec<PathBuf>,
+    /// Names of the generic types currently being specialized. Specialized types are
+    /// only declared once their fields are resolved, so this prevents types referring
+    /// to themselves from being specialized endlessly
+    specializing: HashSet<String>,
+    /// Project the typechecked sources are part of, whose source directories and
+    /// dependencies are used to resolve inclusions
+    project: Option<Project>,
+    /// Capabilities granted to the typechecked program. Sources other than the
+    /// standard library can only be included with the `io` capability
+    capabilities: Capabilities,
+    /// Directory of the standard library, if it was included
+    stdlib: Option<PathBuf>,
 }
 
 impl TypeCtx {
@@ -88,8 +170,21 @@ impl TypeCtx {
             error_handler: ErrorHandler::default(),
             types: ScopeMap::new(),
             generated: vec![],
+            loops: vec![],
+            functions: vec![],
+            captures: vec![],
+            interfaces: HashMap::new(),
+            implementations: HashSet::new(),
+            used_functions: HashSet::new(),
+            definitions: vec![],
+            symbols: SymbolMap::new(),
             path: None,
             included: HashSet::new(),
+            including: vec![],
+            specializing: HashSet::new(),
+            project: None,
+            capabilities: Capabilities::default(),
+            stdlib: None,
         };
 
         macro_rules! declare_primitive {
@@ -109,6 +204,8 @@ impl TypeCtx {
         declare_primitive!(float);
         declare_primitive!(char);
         declare_primitive!(string);
+        declare_primitive!(vec);
+        declare_primitive!(map);
 
         ctx
     }
@@ -130,6 +227,85 @@ impl TypeCtx {
         self.included.contains(path)
     }
 
+    pub fn set_project(&mut self, project: Option<Project>) {
+        self.project = project
+    }
+
+    /// Get the project the typechecked sources are part of, if any
+    pub fn project(&self) -> Option<&Project> {
+        self.project.as_ref()
+    }
+
+    /// Set the capabilities granted to the typechecked program
+    pub fn set_capabilities(&mut self, capabilities: Capabilities) {
+        self.capabilities = capabilities
+    }
+
+    /// Get the capabilities granted to the typechecked program
+    pub fn capabilities(&self) -> Capabilities {
+        self.capabilities
+    }
+
+    /// Set the directory of the standard library, whose sources can always be included
+    pub fn set_stdlib(&mut self, stdlib: PathBuf) {
+        self.stdlib = Some(stdlib.canonicalize().unwrap_or(stdlib))
+    }
+
+    /// Check that a source can be included: Sources outside of the standard library
+    /// require the `io` capability. Paths are canonicalized before being compared, so
+    /// that paths such as `stdlib/../secret` are not considered part of the standard
+    /// library
+    pub fn check_inclusion(&self, path: &Path) -> Result<(), Error> {
+        let in_stdlib = match (&self.stdlib, path.canonicalize()) {
+            (Some(stdlib), Ok(path)) => path.starts_with(stdlib),
+            _ => false,
+        };
+
+        match in_stdlib || self.capabilities.allows(Capability::Io) {
+            true => Ok(()),
+            false => Err(Error::new(ErrKind::Context)
+                .with_msg(format!("{} is disabled", Capability::Io.description()))
+                .with_hint(Error::hint().with_msg(format!(
+                    "including {:?} requires the `{}` capability",
+                    path,
+                    Capability::Io
+                )))),
+        }
+    }
+
+    /// Start including a source. The source stays on the inclusion stack until
+    /// [`TypeCtx::include_exit`] is called
+    pub fn include_enter(&mut self, path: PathBuf) {
+        self.including.push(path)
+    }
+
+    /// Finish including the innermost source
+    pub fn include_exit(&mut self) {
+        self.including.pop();
+    }
+
+    /// Get the chain of sources currently being included, from the outermost to the
+    /// innermost one
+    pub fn inclusion_chain(&self) -> &[PathBuf] {
+        &self.including
+    }
+
+    /// Declare the inclusions, types, interfaces and function signatures of a source
+    /// before typechecking the rest of its instructions. This allows functions to be
+    /// called before the point where they are declared, for example by mutually
+    /// recursive functions
+    pub fn hoist_declarations(&mut self, instructions: &mut [Box<dyn Instruction>]) {
+        for instr in instructions.iter_mut() {
+            if let Some(dec) = instr.downcast_mut::<FunctionDec>() {
+                if matches!(dec.fn_kind(), FunctionKind::Func | FunctionKind::Ext) {
+                    dec.declare(self);
+                }
+            } else if instr.is::<Incl>() || instr.is::<TypeDec>() || instr.is::<InterfaceDec>() {
+                instr.type_of(self);
+            }
+        }
+    }
+
     /// Enter a new scope. This is the same as lexical scopes
     pub fn scope_enter(&mut self) {
         self.types.scope_enter()
@@ -137,19 +313,215 @@ impl TypeCtx {
 
     /// Exit a previously created scope. This is the same as lexical scopes
     pub fn scope_exit(&mut self) {
+        self.warn_unused_vars();
         self.types.scope_exit()
     }
 
+    /// Emit a warning for each variable of the current scope which was declared by an
+    /// assignment but never used. Variables starting with an underscore are ignored
+    fn warn_unused_vars(&mut self) {
+        let mut unused: Vec<(String, SpanTuple)> = match self.types.scopes().front() {
+            Some(scope) => scope
+                .variables
+                .iter()
+                .filter(|(name, var)| !var.used && !name.starts_with('_'))
+                .filter_map(|(name, var)| Some((name.clone(), var.location.clone()?)))
+                .collect(),
+            None => return,
+        };
+        unused.sort_by_key(|(_, loc)| (loc.start().line(), loc.start().column()));
+
+        for (name, loc) in unused {
+            self.error(
+                Error::new(ErrKind::Warning)
+                    .with_msg(format!("unused variable `{}`", name))
+                    .with_loc(Some(loc))
+                    .with_hint(Error::hint().with_msg(format!(
+                        "if this is intentional, prefix it with an underscore: `_{}`",
+                        name
+                    ))),
+            );
+        }
+    }
+
+    /// Enter a new frame, in which only global variables are visible. This is used
+    /// for function bodies
+    pub fn frame_enter(&mut self) {
+        self.types.frame_enter()
+    }
+
+    /// Exit a previously created frame
+    pub fn frame_exit(&mut self) {
+        self.warn_unused_vars();
+        self.types.frame_exit()
+    }
+
+    /// Start typechecking a closure, which may capture the variables visible from the
+    /// current scope. This must be called before entering the frame of the closure
+    pub fn capture_enter(&mut self) {
+        let (mut outer, mut inherited) = match self.active_capture() {
+            Some(enclosing) => (
+                enclosing.outer.clone(),
+                enclosing.outer.keys().cloned().collect(),
+            ),
+            None => (HashMap::new(), HashSet::new()),
+        };
+
+        // Variables of the enclosing closure's own frame shadow the ones it captures
+        let visible = match self.active_capture() {
+            Some(_) => self.types.frame_variables(),
+            None => self.types.variables(),
+        };
+        visible.into_iter().for_each(|(name, var)| {
+            inherited.remove(name);
+            outer.insert(name.clone(), var.clone());
+        });
+
+        self.captures.push(Capture {
+            frame: self.types.frame_depth() + 1,
+            outer,
+            inherited,
+            used: vec![],
+        });
+    }
+
+    /// Finish typechecking the innermost closure, and return the name of the
+    /// variables it captures
+    pub fn capture_exit(&mut self) -> Vec<String> {
+        // We unwrap since exiting a closure which was never entered is an error in
+        // the interpreter's logic
+        let used = self.captures.pop().unwrap().used;
+
+        // The captured variables are used by the closure, in the scope it is created in
+        for name in used.iter() {
+            if let Some(var) = self.types.get_variable_mut(name) {
+                var.used = true;
+            }
+        }
+
+        used
+    }
+
+    /// Get the innermost closure, if its body is the frame currently typechecked
+    fn active_capture(&self) -> Option<&Capture> {
+        self.captures
+            .last()
+            .filter(|capture| capture.frame == self.types.frame_depth())
+    }
+
+    /// Get a variable captured by the closure currently typechecked. Variables declared
+    /// in the closure itself are not captured
+    fn captured_var(&self, name: &str) -> Option<&VarInfo> {
+        if self.types.get_frame_variable(name).is_some() {
+            return None;
+        }
+
+        self.active_capture()?.outer.get(name)
+    }
+
+    /// Mark a variable as captured by the closure currently typechecked, as well as by
+    /// the enclosing closures it is inherited from
+    fn use_capture(&mut self, name: &str) {
+        for capture in self.captures.iter_mut().rev() {
+            if !capture.used.iter().any(|used| used == name) {
+                capture.used.push(name.to_string());
+            }
+
+            if !capture.inherited.contains(name) {
+                break;
+            }
+        }
+    }
+
+    /// Is a variable captured by the closure currently typechecked
+    pub fn is_var_captured(&self, name: &str) -> bool {
+        self.captured_var(name).is_some()
+    }
+
+    /// Enter the body of a function returning values of type `return_ty`
+    pub fn function_enter(&mut self, return_ty: CheckedType) {
+        self.functions.push(return_ty)
+    }
+
+    /// Exit the body of the innermost function
+    pub fn function_exit(&mut self) {
+        self.functions.pop();
+    }
+
+    /// Get the return type of the innermost function, if the typechecker is currently
+    /// inside a function
+    pub fn function_return_type(&self) -> Option<&CheckedType> {
+        self.functions.last()
+    }
+
+    /// Enter a new loop, in which `break` and `continue` instructions are allowed
+    pub fn loop_enter(&mut self) {
+        self.loops.push(vec![])
+    }
+
+    /// Exit the innermost loop, returning the types of all the values it was broken
+    /// out of with
+    pub fn loop_exit(&mut self) -> Vec<CheckedType> {
+        self.loops.pop().unwrap_or_default()
+    }
+
+    /// Is the typechecker currently inside a loop or not
+    pub fn in_loop(&self) -> bool {
+        !self.loops.is_empty()
+    }
+
+    /// Register the type of a value given to a `break` instruction in the innermost
+    /// loop
+    pub fn declare_break(&mut self, ty: CheckedType) {
+        if let Some(breaks) = self.loops.last_mut() {
+            breaks.push(ty)
+        }
+    }
+
     /// Declare a newly-created variable's type
     pub fn declare_var(&mut self, name: String, ty: CheckedType) -> Result<(), Error> {
         // FIXME: Add hint here too
-        self.types.add_variable(name, ty)
+        self.types.add_variable(name, VarInfo::new(ty, false))
+    }
+
+    /// Declare a newly-created mutable variable's type
+    pub fn declare_mut_var(&mut self, name: String, ty: CheckedType) -> Result<(), Error> {
+        self.types.add_variable(name, VarInfo::new(ty, true))
+    }
+
+    /// Declare a variable created by an assignment. A warning is emitted if it goes
+    /// out of scope without having been used
+    pub fn declare_assigned_var(
+        &mut self,
+        name: String,
+        ty: CheckedType,
+        mutable: bool,
+        location: Option<SpanTuple>,
+    ) -> Result<(), Error> {
+        let detail = match mutable {
+            true => format!("mut {}: {}", name, ty.print()),
+            false => format!("{}: {}", name, ty.print()),
+        };
+        self.define(&name, SymbolKind::Variable, location.as_ref(), detail);
+
+        let var = VarInfo {
+            location,
+            ..VarInfo::new(ty, mutable)
+        };
+
+        self.types.add_variable(name, var)
     }
 
     /// Declare a newly-created function
     pub fn declare_function(&mut self, name: String, function: FunctionDec) -> Result<(), Error> {
         // FIXME: Remove clones here
         let loc = function.location().cloned();
+        self.define(
+            &name,
+            SymbolKind::Function,
+            loc.as_ref(),
+            function.signature(),
+        );
         match self.types.add_function(name.clone(), function) {
             Ok(_) => Ok(()),
             Err(err) => {
@@ -171,10 +543,21 @@ impl TypeCtx {
     ) -> Result<(), Error> {
         // FIXME: Remove clones here
         let loc = function.location().cloned();
+        self.define(
+            &name,
+            SymbolKind::Function,
+            loc.as_ref(),
+            function.signature(),
+        );
         match self.types.add_function(name.clone(), function) {
             Ok(_) => Ok(()),
             Err(err) => {
-                let previous_dec = self.types.get_generic_function(&name).unwrap();
+                // The conflicting declaration is not necessarily a generic one
+                let previous_dec = self
+                    .types
+                    .get_generic_function(&name)
+                    .or_else(|| self.types.get_function(&name))
+                    .unwrap();
                 Err(err.with_loc(loc).with_hint(
                     Error::new(ErrKind::Hint)
                         .with_msg(String::from("previous generic declaration here"))
@@ -185,12 +568,35 @@ impl TypeCtx {
     }
 
     /// Add a new generated node to the context
+    /// Generate and declare the specialization of a generic type, unless it already
+    /// exists or is currently being generated
+    pub fn specialize_type(&mut self, dec: &TypeDec, name: &str, type_map: &GenericMap) {
+        if self.get_custom_type(name).is_some() || !self.specializing.insert(name.to_string()) {
+            return;
+        }
+
+        let specialized_ty = dec.generate(name.to_string(), type_map, self);
+        self.add_specialized_node(SpecializedNode::Type(specialized_ty));
+
+        self.specializing.remove(name);
+    }
+
     pub fn add_specialized_node(&mut self, mut node: SpecializedNode) {
         match &mut node {
             SpecializedNode::Func(f) => f.type_of(self),
             SpecializedNode::Type(t) => t.type_of(self),
         };
 
+        // Specialized nodes are generated from global declarations, so they need to
+        // stay available once the scope in which they were generated is exited. The
+        // node is already declared if it was generated in the global scope
+        let _ = match &node {
+            SpecializedNode::Func(f) => self
+                .types
+                .add_global_function(f.name().to_string(), (**f).clone()),
+            SpecializedNode::Type(t) => self.types.add_global_type(t.name().to_string(), t.clone()),
+        };
+
         self.generated.push(node)
     }
 
@@ -215,26 +621,152 @@ impl TypeCtx {
 
     /// Declare a newly-created custom type
     pub fn declare_custom_type(&mut self, name: String, dec: TypeDec) -> Result<(), Error> {
-        self.types.add_type(name, dec)
-        // FIXME: Add hint here too
+        self.define(&name, SymbolKind::Type, dec.location(), dec.print());
+
+        let loc = dec.location().cloned();
+        let msg = match dec.variant_of() {
+            Some(enum_name) => format!(
+                "variant `{}` of `{}` has the same name as an existing type",
+                name, enum_name
+            ),
+            None => format!("type `{}` is already declared", name),
+        };
+
+        self.types.add_type(name.clone(), dec).map_err(|err| {
+            let previous_dec = self.types.get_type(&name).unwrap();
+            let hint = match previous_dec.variant_of() {
+                Some(enum_name) => format!(
+                    "`{}` previously declared as a variant of `{}`",
+                    name, enum_name
+                ),
+                None => String::from("previous declaration here"),
+            };
+
+            err.with_msg(msg).with_loc(loc).with_hint(
+                Error::new(ErrKind::Hint)
+                    .with_msg(hint)
+                    .with_loc(previous_dec.location().cloned()),
+            )
+        })
     }
 
     /// Declare a newly-created generic custom type
     pub fn declare_generic_custom_type(&mut self, name: String, dec: TypeDec) -> Result<(), Error> {
+        self.define(&name, SymbolKind::Type, dec.location(), dec.print());
         self.types.add_generic_type(name, dec)
         // FIXME: Add hint here too
     }
 
+    /// Record the declaration of a symbol. Symbols without a location, such as the
+    /// ones declared by the interpreter itself, are ignored. Generic declarations are
+    /// typechecked once per specialization, so only their first declaration is kept
+    pub fn define(
+        &mut self,
+        name: &str,
+        kind: SymbolKind,
+        location: Option<&SpanTuple>,
+        detail: String,
+    ) {
+        let location = match location {
+            Some(location) => location,
+            None => return,
+        };
+
+        let defined = self
+            .definitions
+            .iter()
+            .any(|def| def.kind() == kind && def.location() == location);
+
+        if !defined {
+            self.definitions
+                .push(Definition::new(name, kind, location.clone(), detail));
+        }
+    }
+
+    /// Get the symbols declared so far
+    pub fn definitions(&self) -> &[Definition] {
+        &self.definitions
+    }
+
+    /// Get the original names of the symbols declared under a prefix
+    pub fn symbols(&self) -> &SymbolMap {
+        &self.symbols
+    }
+
+    /// Record that `prefixed` was declared for the symbol `name` of an included source
+    pub fn declare_prefixed(&mut self, prefixed: String, name: &str, module: &str) {
+        self.symbols.insert(prefixed, name, module)
+    }
+
     /// Access a previously declared variable's type
     pub fn get_var(&mut self, name: &str) -> Option<&CheckedType> {
-        self.types.get_variable(name)
+        if self.captured_var(name).is_some() {
+            self.use_capture(name);
+            return self.captured_var(name).map(|var| &var.ty);
+        }
+
+        let var = self.types.get_variable_mut(name)?;
+        var.used = true;
+
+        Some(&var.ty)
+    }
+
+    /// Access the type of a previously declared variable which is being assigned to.
+    /// Unlike [`TypeCtx::get_var`], this does not count as a use of the variable: A
+    /// variable which is only ever written to is still reported as unused
+    pub fn get_assigned_var(&mut self, name: &str) -> Option<&CheckedType> {
+        if self.captured_var(name).is_some() {
+            return self.get_var(name);
+        }
+
+        self.types.get_variable(name).map(|var| &var.ty)
+    }
+
+    /// Is a previously declared variable mutable or not
+    pub fn is_var_mutable(&self, name: &str) -> bool {
+        self.captured_var(name)
+            .or_else(|| self.types.get_variable(name))
+            .is_some_and(|var| var.mutable)
+    }
+
+    /// Get the location of the assignment which declared a variable, if any
+    pub fn var_location(&self, name: &str) -> Option<&SpanTuple> {
+        self.captured_var(name)
+            .or_else(|| self.types.get_variable(name))
+            .and_then(|var| var.location.as_ref())
+    }
+
+    /// Is a variable declared at the top level of the program and accessed from within
+    /// a function, as opposed to a variable of the current function
+    pub fn is_var_global(&self, name: &str) -> bool {
+        self.types.frame_depth() > 0
+            && self.captured_var(name).is_none()
+            && self.types.get_frame_variable(name).is_none()
+            && self.types.get_variable(name).is_some()
+    }
+
+    /// Is a variable declared in the current scope, as opposed to an enclosing one
+    pub fn is_var_in_current_scope(&self, name: &str) -> bool {
+        self.types.get_variable_in_current_scope(name).is_some()
     }
 
     /// Access a previously declared function
-    pub fn get_function(&mut self, name: &str) -> Option<&FunctionDec> {
+    pub fn get_function(&self, name: &str) -> Option<&FunctionDec> {
         self.types.get_function(name)
     }
 
+    /// Mark a function as used, because it is called or referenced
+    pub fn use_function(&mut self, name: &str) {
+        if !self.used_functions.contains(name) {
+            self.used_functions.insert(name.to_owned());
+        }
+    }
+
+    /// Has a function been called or referenced so far
+    pub fn is_function_used(&self, name: &str) -> bool {
+        self.used_functions.contains(name)
+    }
+
     /// Access a previously declared generic function
     pub fn get_generic_function(&mut self, name: &str) -> Option<&FunctionDec> {
         self.types.get_generic_function(name)
@@ -245,6 +777,92 @@ impl TypeCtx {
         self.types.get_type(name)
     }
 
+    /// Resolve a type written with concrete generic types, such as `Vec[int]`, to the
+    /// specialized type it refers to, which gets generated if it does not exist yet.
+    /// Types without generics are returned as they are
+    pub fn resolve_concrete_type(&mut self, ty: &TypeId) -> Result<TypeId, Error> {
+        let (id, generics) = match ty {
+            TypeId::Type { id, generics } if !generics.is_empty() => (id.access(), generics),
+            TypeId::Functor {
+                generics,
+                arg_types,
+                return_type,
+            } => {
+                return Ok(TypeId::Functor {
+                    generics: generics.clone(),
+                    arg_types: arg_types
+                        .iter()
+                        .map(|arg| self.resolve_concrete_type(arg))
+                        .collect::<Result<Vec<TypeId>, Error>>()?,
+                    return_type: match return_type {
+                        Some(ret) => Some(Box::new(self.resolve_concrete_type(ret)?)),
+                        None => None,
+                    },
+                })
+            }
+            _ => return Ok(ty.clone()),
+        };
+
+        let generics = generics
+            .iter()
+            .map(|generic| self.resolve_concrete_type(generic))
+            .collect::<Result<Vec<TypeId>, Error>>()?;
+
+        let dec = match self.get_custom_type(id) {
+            Some(dec) => dec.clone(),
+            None => {
+                return Err(Error::new(ErrKind::TypeChecker)
+                    .with_msg(format!("use of undeclared type `{}`", ty)))
+            }
+        };
+
+        let specialized_name = generics::mangle(id, &generics);
+        let type_map = GenericMap::create(dec.generics(), &generics, self)?;
+        self.specialize_type(&dec, &specialized_name, &type_map);
+
+        Ok(TypeId::new(Symbol::from(specialized_name)))
+    }
+
+    /// Declare a newly-created interface
+    pub fn declare_interface(&mut self, interface: InterfaceDec) -> Result<(), Error> {
+        match self.interfaces.contains_key(interface.name()) {
+            true => Err(Error::new(ErrKind::TypeChecker).with_msg(format!(
+                "interface `{}` is declared twice",
+                interface.name()
+            ))),
+            false => {
+                self.interfaces
+                    .insert(interface.name().to_string(), interface);
+                Ok(())
+            }
+        }
+    }
+
+    /// Access a previously declared interface
+    pub fn get_interface(&self, name: &str) -> Option<&InterfaceDec> {
+        self.interfaces.get(name)
+    }
+
+    /// Register a type as implementing an interface
+    pub fn declare_implementation(&mut self, interface: &str, ty: &TypeId) -> Result<(), Error> {
+        match self
+            .implementations
+            .insert((interface.to_string(), ty.id().to_string()))
+        {
+            true => Ok(()),
+            false => Err(Error::new(ErrKind::TypeChecker).with_msg(format!(
+                "interface `{}` is implemented twice for type `{}`",
+                interface, ty
+            ))),
+        }
+    }
+
+    /// Does a type implement an interface
+    pub fn implements(&self, interface: &str, ty: &TypeId) -> bool {
+        self.implementations
+            .contains(&(interface.to_string(), ty.id().to_string()))
+    }
+
     /// Access a previously declared generic custom type
     pub fn get_generic_custom_type(&mut self, name: &str) -> Option<&TypeDec> {
         self.types.get_generic_type(name)
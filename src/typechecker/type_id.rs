@@ -28,12 +28,11 @@ use std::hash::Hash;
 use colored::Colorize;
 
 use crate::error::{ErrKind, Error};
-use crate::generics::{self, GenericExpander, GenericUser};
+use crate::generics::{self, GenericUser};
 use crate::instruction::TypeDec;
 use crate::symbol::Symbol;
-use crate::typechecker::SpecializedNode;
 
-pub const PRIMITIVE_TYPES: [&str; 5] = ["bool", "int", "float", "char", "string"];
+pub const PRIMITIVE_TYPES: [&str; 7] = ["bool", "int", "float", "char", "string", "vec", "map"];
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 // FIXME: They should probably have location info
@@ -154,6 +153,42 @@ impl TypeId {
         }
     }
 
+    /// Print the type identifier as it would be written in jinko source code, such
+    /// as `Pair[int, string]` or `func(int) -> bool`
+    pub fn print(&self) -> String {
+        fn list(types: &[TypeId]) -> String {
+            types
+                .iter()
+                .map(TypeId::print)
+                .collect::<Vec<String>>()
+                .join(", ")
+        }
+
+        let generics = match self {
+            TypeId::Type { generics, .. } | TypeId::Functor { generics, .. } => generics,
+        };
+        let mut base = String::from(self.id());
+
+        if !generics.is_empty() {
+            base = format!("{}[{}]", base, list(generics));
+        }
+
+        if let TypeId::Functor {
+            arg_types,
+            return_type,
+            ..
+        } = self
+        {
+            base = format!("{}({})", base, list(arg_types));
+
+            if let Some(ret) = return_type {
+                base = format!("{} -> {}", base, ret.print());
+            }
+        }
+
+        base
+    }
+
     pub fn void() -> TypeId {
         TypeId::new(Symbol::from(String::from("void")))
     }
@@ -196,13 +231,28 @@ impl GenericUser for TypeId {
             self.set_id(Symbol::from(String::from(new_id.id())));
         }
 
+        // The arguments and return type of functors may use generics as well, such as
+        // `func(T) -> T`
+        if let TypeId::Functor {
+            arg_types,
+            return_type,
+            ..
+        } = self
+        {
+            arg_types
+                .iter_mut()
+                .for_each(|arg| arg.resolve_usages(type_map, ctx));
+            if let Some(ret) = return_type {
+                ret.resolve_usages(type_map, ctx);
+            }
+        }
+
         if let TypeId::Type { id, .. } = self {
             let new_name = generics::mangle(id.access(), &new_types);
 
             // If the type does not exist yet, then we must create it and add it to the specialized
             // nodes
-            let type_dec = ctx.get_custom_type(&new_name);
-            if type_dec.is_none() {
+            if ctx.get_custom_type(&new_name).is_none() {
                 let new_dec = match ctx.get_custom_type(id.access()) {
                     Some(t) => t.clone(),
                     None => {
@@ -214,8 +264,7 @@ impl GenericUser for TypeId {
                         return;
                     }
                 };
-                let new_dec = new_dec.generate(new_name.clone(), type_map, ctx);
-                ctx.add_specialized_node(SpecializedNode::Type(new_dec));
+                ctx.specialize_type(&new_dec, &new_name, type_map);
             }
 
             *id = Symbol::from(new_name);
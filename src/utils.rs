@@ -16,8 +16,9 @@ macro_rules! jinko {
 
             $crate::jk_parse!(&mut ctx, $($tokens)*);
 
+            let res = ctx.execute();
             ctx.emit_errors();
-            assert!(ctx.execute().is_ok());
+            assert!(res.is_ok());
             assert!(!ctx.error_handler.has_errors());
 
             ctx
@@ -34,8 +35,9 @@ macro_rules! jinko_fail {
 
             $crate::jk_parse! (&mut ctx, $($tokens)*);
 
+            let res = ctx.execute();
             ctx.emit_errors();
-            assert!(ctx.execute().is_err());
+            assert!(res.is_err());
             assert!(ctx.error_handler.has_errors());
 
             ctx
@@ -0,0 +1,88 @@
+//! A JkCommandResult contains the outcome of a command executed by the system's shell.
+//! It is the interpreter's backing type for the `CommandResult` type declared in
+//! `stdlib/shell.jk`.
+//!
+//! A command result gets converted to an instance of type `CommandResult`, with three
+//! fields: `exit_code`, `stdout` and `stderr`.
+
+use crate::instance::{FromObjectInstance, ObjectInstance, ToObjectInstance};
+use crate::typechecker::{CheckedType, TypeId};
+use crate::value::{JkInt, JkString};
+
+/// Name of the type backing command results
+pub const COMMAND_RESULT_TYPE: &str = "CommandResult";
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct JkCommandResult {
+    pub(crate) exit_code: i64,
+    pub(crate) stdout: String,
+    pub(crate) stderr: String,
+}
+
+impl JkCommandResult {
+    /// Create a new command result
+    pub fn new(exit_code: i64, stdout: String, stderr: String) -> JkCommandResult {
+        JkCommandResult {
+            exit_code,
+            stdout,
+            stderr,
+        }
+    }
+}
+
+impl ToObjectInstance for JkCommandResult {
+    fn to_instance(&self) -> ObjectInstance {
+        let exit_code = JkInt::from(self.exit_code).to_instance();
+        let stdout = JkString::from(self.stdout.clone()).to_instance();
+        let stderr = JkString::from(self.stderr.clone()).to_instance();
+
+        ObjectInstance::from_fields(
+            CheckedType::Resolved(TypeId::from(COMMAND_RESULT_TYPE)),
+            vec![
+                (String::from("exit_code"), exit_code),
+                (String::from("stdout"), stdout),
+                (String::from("stderr"), stderr),
+            ],
+        )
+    }
+}
+
+impl FromObjectInstance for JkCommandResult {
+    fn from_instance(i: &ObjectInstance) -> Self {
+        // A command result instance always contains these three fields
+        JkCommandResult {
+            exit_code: i.get_field_as("exit_code").unwrap(),
+            stdout: i.get_field_as("stdout").unwrap(),
+            stderr: i.get_field_as("stderr").unwrap(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn t_command_result_to_instance_and_back() {
+        let result = JkCommandResult::new(1, String::from("out"), String::from("err"));
+
+        assert_eq!(
+            JkCommandResult::from_instance(&result.to_instance()),
+            result
+        );
+    }
+
+    #[test]
+    fn t_command_result_instance_fields() {
+        let instance = JkCommandResult::new(0, String::from("out"), String::new()).to_instance();
+
+        assert_eq!(
+            instance.get_field("stdout").unwrap(),
+            JkString::from("out").to_instance()
+        );
+        assert_eq!(
+            instance.display(),
+            "CommandResult(exit_code: 0, stdout: \"out\", stderr: \"\")"
+        );
+    }
+}
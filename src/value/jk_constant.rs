@@ -1,3 +1,4 @@
+use crate::ast::AstNode;
 use crate::context::Context;
 use crate::error::Error;
 use crate::generics::GenericUser;
@@ -5,9 +6,10 @@ use crate::instance::{FromObjectInstance, ObjectInstance, ToObjectInstance};
 use crate::instruction::{InstrKind, Instruction, Operator};
 use crate::location::SpanTuple;
 use crate::typechecker::{CheckedType, TypeCheck, TypeCtx, TypeId};
-use crate::value::{JkString, Value};
+use crate::value::{JkChar, JkString, Primitive, Value};
 
 use std::convert::TryFrom;
+use std::rc::Rc;
 
 #[derive(Clone)]
 /// A JkConstant represents a primitive type in Jinko. It is used in order to
@@ -62,10 +64,30 @@ impl<T: Clone> JkConstant<T> {
 //     |                                        ^ doesn't have a size known at compile-time
 // ```
 
+/// Print numbers as they would be written in jinko source code
+trait PrintConstant {
+    fn print_constant(&self) -> String;
+}
+
+impl PrintConstant for i64 {
+    fn print_constant(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl PrintConstant for f64 {
+    fn print_constant(&self) -> String {
+        // Floating point numbers always keep their decimal point or their exponent, so
+        // that they do not get parsed back as integers. Very big or very small numbers
+        // are printed in scientific notation
+        format!("{:?}", self)
+    }
+}
+
 /// Circumvents the need for a generic implementation (see comment).
 /// Call it with the type contained in the JkConstant and the &str representation
 ///
-/// ```
+/// ```ignore
 /// // Implements a JkConstant<i64> with type displayed as "int"
 /// jk_primitive!(i64, "int");
 /// ```
@@ -114,6 +136,12 @@ macro_rules! jk_primitive {
                 self.0.to_string()
             }
 
+            fn ast(&self) -> AstNode {
+                AstNode::new("Constant", self.location())
+                    .with_attr("type", "bool")
+                    .with_attr("value", self.0)
+            }
+
             fn execute(&self, _ctx: &mut Context) -> Option<ObjectInstance> {
                 // Since we cannot use the generic ToObjectInstance implementation, we also have to
                 // copy paste our four basic implementations for jinko's primitive types...
@@ -179,7 +207,13 @@ macro_rules! jk_primitive {
             }
 
             fn print(&self) -> String {
-                self.0.to_string()
+                format!("'{}'", JkChar::escape(self.0))
+            }
+
+            fn ast(&self) -> AstNode {
+                AstNode::new("Constant", self.location())
+                    .with_attr("type", "char")
+                    .with_attr("value", self.0)
             }
 
             fn execute(&self, ctx: &mut Context) -> Option<ObjectInstance> {
@@ -225,13 +259,7 @@ macro_rules! jk_primitive {
 
         impl FromObjectInstance for JkConstant<$t> {
             fn from_instance(i: &ObjectInstance) -> Self {
-                use std::mem::{size_of, transmute};
-
-                unsafe {
-                    Self::from(transmute::<[u8; size_of::<$t>()], $t>(
-                        TryFrom::try_from(i.data()).unwrap(),
-                    ))
-                }
+                Self::from(<$t>::from_ne_bytes(TryFrom::try_from(i.data()).unwrap()))
             }
         }
 
@@ -241,7 +269,13 @@ macro_rules! jk_primitive {
             }
 
             fn print(&self) -> String {
-                self.0.to_string()
+                self.0.print_constant()
+            }
+
+            fn ast(&self) -> AstNode {
+                AstNode::new("Constant", self.location())
+                    .with_attr("type", $s)
+                    .with_attr("value", self.0)
             }
 
             fn execute(&self, _ctx: &mut Context) -> Option<ObjectInstance> {
@@ -282,56 +316,46 @@ jk_primitive!(f64, "float");
 jk_primitive!(char);
 jk_primitive!(bool);
 
+// Operations on primitive values are realized without boxing them: See `Primitive`
 impl Value for JkConstant<i64> {
     fn do_op(&self, other: &Self, op: Operator) -> Result<ObjectInstance, Error> {
-        match op {
-            Operator::Add => Ok(JkConstant::from(self.0 + other.0).to_instance()),
-            Operator::Sub => Ok(JkConstant::from(self.0 - other.0).to_instance()),
-            Operator::Mul => Ok(JkConstant::from(self.0 * other.0).to_instance()),
-            Operator::Div => Ok(JkConstant::from(self.0 / other.0).to_instance()),
-            Operator::Lt => Ok(JkConstant::from(self.0 < other.0).to_instance()),
-            Operator::Gt => Ok(JkConstant::from(self.0 > other.0).to_instance()),
-            Operator::LtEq => Ok(JkConstant::from(self.0 <= other.0).to_instance()),
-            Operator::GtEq => Ok(JkConstant::from(self.0 >= other.0).to_instance()),
-            Operator::Equals => Ok(JkConstant::from(self.0 == other.0).to_instance()),
-            Operator::NotEquals => Ok(JkConstant::from(self.0 != other.0).to_instance()),
-            _ => self.no_op(other, op),
-        }
+        Primitive::Int(self.0)
+            .apply(Primitive::Int(other.0), op)
+            .map(|value| value.to_instance())
+    }
+}
+
+impl From<JkConstant<i64>> for JkConstant<f64> {
+    fn from(value: JkConstant<i64>) -> Self {
+        JkConstant::from(value.0 as f64)
     }
 }
 
 impl Value for JkConstant<f64> {
     fn do_op(&self, other: &Self, op: Operator) -> Result<ObjectInstance, Error> {
-        match op {
-            Operator::Add => Ok(JkConstant::from(self.0 + other.0).to_instance()),
-            Operator::Sub => Ok(JkConstant::from(self.0 - other.0).to_instance()),
-            Operator::Mul => Ok(JkConstant::from(self.0 * other.0).to_instance()),
-            Operator::Div => Ok(JkConstant::from(self.0 / other.0).to_instance()),
-            Operator::Lt => Ok(JkConstant::from(self.0 < other.0).to_instance()),
-            Operator::Gt => Ok(JkConstant::from(self.0 > other.0).to_instance()),
-            Operator::LtEq => Ok(JkConstant::from(self.0 <= other.0).to_instance()),
-            Operator::GtEq => Ok(JkConstant::from(self.0 >= other.0).to_instance()),
-            // TODO: Do we want to allow float equality comparison?
-            _ => self.no_op(other, op),
-        }
+        Primitive::Float(self.0)
+            .apply(Primitive::Float(other.0), op)
+            .map(|value| value.to_instance())
+    }
+}
+
+impl Value for JkConstant<bool> {
+    fn do_op(&self, other: &Self, op: Operator) -> Result<ObjectInstance, Error> {
+        Primitive::Bool(self.0)
+            .apply(Primitive::Bool(other.0), op)
+            .map(|value| value.to_instance())
     }
 }
 
 impl ToObjectInstance for JkString {
     fn to_instance(&self) -> ObjectInstance {
-        ObjectInstance::from_bytes(
-            CheckedType::Resolved(TypeId::from("string")),
-            self.0.as_bytes().len(),
-            self.0.as_bytes(),
-            None,
-        )
+        JkString::shared_instance(self.0.as_str().into())
     }
 }
 
 impl FromObjectInstance for JkString {
     fn from_instance(i: &ObjectInstance) -> Self {
-        // unchecked is safe because this instance came from a utf8 string in ToObjectInstance
-        unsafe { JkString::from(String::from_utf8_unchecked(i.data().to_vec())) }
+        JkString::from(JkString::as_str(i))
     }
 }
 
@@ -341,7 +365,17 @@ impl Instruction for JkString {
     }
 
     fn print(&self) -> String {
-        format!("\"{}\"", self.0.clone())
+        let mut base = String::from("\"");
+        base.push_str(&JkString::escape(&self.0));
+        base.push('"');
+
+        base
+    }
+
+    fn ast(&self) -> AstNode {
+        AstNode::new("Constant", self.location())
+            .with_attr("type", "string")
+            .with_attr("value", self.0.as_str())
     }
 
     fn execute(&self, _ctx: &mut Context) -> Option<ObjectInstance> {
@@ -367,6 +401,57 @@ impl TypeCheck for JkString {
 
 impl GenericUser for JkString {}
 
+/// String instances share their content between all their copies: It is only copied
+/// when a new string is created from it. Builtins should therefore borrow the content of
+/// string instances with [`JkString::as_str`] rather than converting them to a
+/// `JkString`, and return their argument as-is when they leave it unchanged.
+impl JkString {
+    /// Create a string instance sharing the given content
+    pub fn shared_instance(s: Rc<str>) -> ObjectInstance {
+        ObjectInstance::from_shared(CheckedType::Resolved(TypeId::from("string")), s.into())
+    }
+
+    /// Borrow the content of a string instance, without copying it
+    pub fn as_str(instance: &ObjectInstance) -> &str {
+        // unchecked is safe because the data of string instances always comes from
+        // valid UTF-8 strings
+        unsafe { std::str::from_utf8_unchecked(instance.data()) }
+    }
+
+    /// Escape the characters which have a special meaning inside of a string
+    /// literal, such as quotes or opening format braces
+    pub(crate) fn escape(s: &str) -> String {
+        let mut escaped = String::with_capacity(s.len());
+
+        s.chars().for_each(|c| match c {
+            '"' => escaped.push_str("\\\""),
+            '{' => escaped.push_str("\\{"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            _ => escaped.push(c),
+        });
+
+        escaped
+    }
+}
+
+impl JkChar {
+    /// Escape a character so that it can be written inside of a character literal
+    pub(crate) fn escape(c: char) -> String {
+        match c {
+            '\'' => String::from("\\'"),
+            '\\' => String::from("\\\\"),
+            '\n' => String::from("\\n"),
+            '\r' => String::from("\\r"),
+            '\t' => String::from("\\t"),
+            '\0' => String::from("\\0"),
+            _ if c.is_control() => format!("\\u{{{:x}}}", c as u32),
+            _ => c.to_string(),
+        }
+    }
+}
+
 impl From<&str> for JkConstant<String> {
     fn from(s: &str) -> Self {
         JkConstant(
@@ -401,6 +486,16 @@ mod tests {
         );
     }
 
+    #[test]
+    fn t_string_instance_shares_content() {
+        let instance = JkString::from("jinkö").to_instance();
+        let copy = instance.clone();
+
+        assert_eq!(JkString::as_str(&copy), "jinkö");
+        assert!(Rc::ptr_eq(&instance.shared_data(), &copy.shared_data()));
+        assert_eq!(JkString::from_instance(&copy).0, "jinkö");
+    }
+
     #[test]
     fn tc_bool_type() {
         let mut ctx = Context::new();
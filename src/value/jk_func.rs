@@ -0,0 +1,129 @@
+//! A JkFunc is a function used as a value. Functions referred to by name, such as
+//! `double` in `apply(double, 2)`, are converted to instances which can be stored in
+//! variables, passed as arguments and called indirectly.
+//!
+//! The instance keeps a reference to the declaration of the function, and its type is
+//! the functor type matching the function's signature, such as `func(int) -> int`.
+//! Closures also keep the variables they captured when they were created, which are
+//! made available to their body when they get called.
+
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::rc::Rc;
+
+use crate::instance::{FromObjectInstance, ObjectInstance, ToObjectInstance};
+use crate::instruction::{DecArg, FunctionDec, Var};
+use crate::typechecker::{CheckedType, TypeId};
+
+#[derive(Clone)]
+pub struct JkFunc {
+    dec: Rc<FunctionDec>,
+    captures: Rc<[Var]>,
+}
+
+impl JkFunc {
+    /// Create a new function value from a function declaration
+    pub fn new(dec: Rc<FunctionDec>) -> JkFunc {
+        JkFunc::with_captures(dec, vec![])
+    }
+
+    /// Create a new closure from a function declaration and the variables it captures
+    pub fn with_captures(dec: Rc<FunctionDec>, captures: Vec<Var>) -> JkFunc {
+        JkFunc {
+            dec,
+            captures: captures.into(),
+        }
+    }
+
+    /// Get a reference to the declaration of the function
+    pub fn dec(&self) -> &Rc<FunctionDec> {
+        &self.dec
+    }
+
+    /// Get the variables captured by the function
+    pub fn captures(&self) -> &[Var] {
+        &self.captures
+    }
+
+    /// Build the functor type corresponding to the signature of a function
+    pub fn type_of(dec: &FunctionDec) -> TypeId {
+        TypeId::Functor {
+            generics: vec![],
+            arg_types: dec.args().iter().map(DecArg::get_type).cloned().collect(),
+            return_type: dec.ty().cloned().map(Box::new),
+        }
+    }
+
+    /// Render the signature of the function, such as `func double(int) -> int`
+    pub fn signature(&self) -> String {
+        let ty = JkFunc::type_of(&self.dec).print();
+
+        // The functor type is printed as `func(...)`, so the name of the function
+        // simply needs to be inserted after the keyword
+        format!("func {}{}", self.dec.name(), ty.trim_start_matches("func"))
+    }
+}
+
+impl PartialEq for JkFunc {
+    fn eq(&self, other: &JkFunc) -> bool {
+        let same_captures = (self.captures.is_empty() && other.captures.is_empty())
+            || Rc::ptr_eq(&self.captures, &other.captures);
+
+        Rc::ptr_eq(&self.dec, &other.dec) && same_captures
+    }
+}
+
+impl Debug for JkFunc {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{}", self.signature())
+    }
+}
+
+impl ToObjectInstance for JkFunc {
+    fn to_instance(&self) -> ObjectInstance {
+        ObjectInstance::from_function(
+            CheckedType::Resolved(JkFunc::type_of(&self.dec)),
+            self.clone(),
+        )
+    }
+}
+
+impl FromObjectInstance for JkFunc {
+    fn from_instance(i: &ObjectInstance) -> Self {
+        // FIXME: Return an error instead once instances can be converted fallibly
+        i.function().cloned().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instruction::DecArg;
+
+    fn double() -> Rc<FunctionDec> {
+        Rc::new(FunctionDec::new(
+            String::from("double"),
+            Some(TypeId::from("int")),
+            vec![],
+            vec![DecArg::new(String::from("x"), TypeId::from("int"))],
+        ))
+    }
+
+    #[test]
+    fn t_func_type() {
+        assert_eq!(
+            JkFunc::type_of(&double()),
+            TypeId::functor()
+                .with_arg(TypeId::from("int"))
+                .with_return_type(TypeId::from("int"))
+        );
+    }
+
+    #[test]
+    fn t_func_to_instance_and_back() {
+        let func = JkFunc::new(double());
+        let instance = func.to_instance();
+
+        assert_eq!(JkFunc::from_instance(&instance), func);
+        assert_eq!(instance.display(), "func double(int) -> int");
+    }
+}
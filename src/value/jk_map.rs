@@ -0,0 +1,96 @@
+//! A JkMap associates string keys to instances. It is the interpreter's backing type
+//! for the `Map[T]` type declared in `stdlib/map.jk`, and is only manipulated through
+//! builtins.
+//!
+//! A map gets converted to an instance of type `map`, whose fields are named after the
+//! map's keys. Fields are laid out in the order of their keys, so that two maps
+//! containing the same entries always result in equal instances, no matter the order
+//! in which the entries were inserted.
+
+use std::collections::HashMap;
+
+use crate::instance::{FromObjectInstance, Name, ObjectInstance, ToObjectInstance};
+use crate::typechecker::{CheckedType, TypeId};
+
+/// Name of the primitive type backing maps
+pub const MAP_TYPE: &str = "map";
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct JkMap(pub(crate) HashMap<String, ObjectInstance>);
+
+impl JkMap {
+    /// Create a new, empty map
+    pub fn new() -> JkMap {
+        JkMap::default()
+    }
+
+    /// Get a reference to the entries contained in the map
+    pub fn entries(&self) -> &HashMap<String, ObjectInstance> {
+        &self.0
+    }
+}
+
+impl From<HashMap<String, ObjectInstance>> for JkMap {
+    fn from(entries: HashMap<String, ObjectInstance>) -> JkMap {
+        JkMap(entries)
+    }
+}
+
+impl ToObjectInstance for JkMap {
+    fn to_instance(&self) -> ObjectInstance {
+        let mut fields: Vec<(Name, ObjectInstance)> = self
+            .0
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+
+        fields.sort_by(|(lhs, _), (rhs, _)| lhs.cmp(rhs));
+
+        ObjectInstance::from_fields(CheckedType::Resolved(TypeId::from(MAP_TYPE)), fields)
+    }
+}
+
+impl FromObjectInstance for JkMap {
+    fn from_instance(i: &ObjectInstance) -> Self {
+        JkMap(
+            i.fields()
+                .into_iter()
+                .flatten()
+                .map(|(key, field)| (key.clone(), field.instance().clone()))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::JkInt;
+
+    fn map_from(entries: &[(&str, i64)]) -> JkMap {
+        JkMap(
+            entries
+                .iter()
+                .map(|(key, value)| (key.to_string(), JkInt::from(*value).to_instance()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn t_map_to_instance_and_back() {
+        let map = map_from(&[("one", 1), ("two", 2)]);
+        let instance = map.to_instance();
+
+        assert_eq!(instance.size(), 2 * JkInt::from(0).to_instance().size());
+        assert_eq!(JkMap::from_instance(&instance), map);
+    }
+
+    #[test]
+    fn t_map_instances_equality() {
+        let lhs = map_from(&[("a", 1), ("b", 2), ("c", 3), ("d", 4)]);
+        let rhs = map_from(&[("d", 4), ("c", 3), ("b", 2), ("a", 1)]);
+
+        assert_eq!(lhs.to_instance(), rhs.to_instance());
+        assert_ne!(lhs.to_instance(), map_from(&[("a", 1)]).to_instance());
+    }
+}
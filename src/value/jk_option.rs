@@ -0,0 +1,116 @@
+//! A JkOption is an optional integer. It is the interpreter's backing type for the
+//! `Maybe_int` type declared in `stdlib/maybe.jk`, and allows builtins to signal the
+//! absence of a value without halting the interpreter.
+//!
+//! An option gets converted to an instance of type `Maybe_int`, with two fields:
+//! `inner`, which contains the value or zero, and `is_some`.
+
+use crate::error::Error;
+use crate::instance::{FromObjectInstance, ObjectInstance, ToObjectInstance};
+use crate::typechecker::{CheckedType, TypeId};
+use crate::value::{JkBool, JkInt};
+
+/// Name of the type backing options
+// FIXME: Make this generic once generic types are usable from builtins
+pub const OPTION_TYPE: &str = "Maybe_int";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct JkOption(pub(crate) Option<i64>);
+
+impl JkOption {
+    /// Create an option containing a value
+    pub fn some(value: i64) -> JkOption {
+        JkOption(Some(value))
+    }
+
+    /// Create an empty option
+    pub fn nothing() -> JkOption {
+        JkOption(None)
+    }
+
+    /// Get the value contained in the option, if any
+    pub fn value(&self) -> Option<i64> {
+        self.0
+    }
+
+    /// Get the option contained in an instance. This fails if the instance does not
+    /// have the `inner` and `is_some` fields, such as a `Maybe_int` declared by users
+    /// who do not include the standard library
+    pub fn try_from_instance(i: &ObjectInstance) -> Result<JkOption, Error> {
+        let is_some = i.get_field_as::<bool>("is_some")?;
+        let inner = i.get_field_as::<i64>("inner")?;
+
+        Ok(JkOption(is_some.then_some(inner)))
+    }
+}
+
+impl From<Option<i64>> for JkOption {
+    fn from(value: Option<i64>) -> JkOption {
+        JkOption(value)
+    }
+}
+
+impl ToObjectInstance for JkOption {
+    fn to_instance(&self) -> ObjectInstance {
+        let inner = JkInt::from(self.0.unwrap_or_default()).to_instance();
+        let is_some = JkBool::from(self.0.is_some()).to_instance();
+
+        ObjectInstance::from_fields(
+            CheckedType::Resolved(TypeId::from(OPTION_TYPE)),
+            vec![
+                (String::from("inner"), inner),
+                (String::from("is_some"), is_some),
+            ],
+        )
+    }
+}
+
+impl FromObjectInstance for JkOption {
+    fn from_instance(i: &ObjectInstance) -> Self {
+        // Instances without the fields of an option are considered empty. Use
+        // `JkOption::try_from_instance` in order to report them
+        JkOption::try_from_instance(i).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn t_option_to_instance_and_back() {
+        let some = JkOption::some(15);
+        let nothing = JkOption::nothing();
+
+        assert_eq!(JkOption::from_instance(&some.to_instance()), some);
+        assert_eq!(JkOption::from_instance(&nothing.to_instance()), nothing);
+    }
+
+    #[test]
+    fn t_option_of_zero_is_not_nothing() {
+        let instance = JkOption::some(0).to_instance();
+
+        assert_eq!(JkOption::from_instance(&instance).value(), Some(0));
+    }
+
+    #[test]
+    fn t_option_from_malformed_instance() {
+        let instance = ObjectInstance::from_fields(
+            CheckedType::Resolved(TypeId::from(OPTION_TYPE)),
+            vec![(String::from("x"), JkInt::from(1).to_instance())],
+        );
+
+        assert!(JkOption::try_from_instance(&instance).is_err());
+        assert_eq!(JkOption::from_instance(&instance), JkOption::nothing());
+    }
+
+    #[test]
+    fn t_option_instance_type() {
+        let instance = JkOption::nothing().to_instance();
+
+        assert_eq!(
+            instance.ty(),
+            &CheckedType::Resolved(TypeId::from(OPTION_TYPE))
+        );
+    }
+}
@@ -0,0 +1,102 @@
+//! A JkVec is a growable collection of instances. It is the interpreter's backing
+//! type for the `Vec[T]` type declared in `stdlib/vec.jk`, and is only manipulated
+//! through builtins.
+//!
+//! A vector gets converted to an instance of type `vec`. Its elements are stored as the
+//! instance's fields, named after their index, and its raw data is the concatenation
+//! of the elements' data.
+
+use crate::instance::{FromObjectInstance, Name, ObjectInstance, ToObjectInstance};
+use crate::typechecker::{CheckedType, TypeId};
+
+/// Name of the primitive type backing vectors
+pub const VEC_TYPE: &str = "vec";
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct JkVec(pub(crate) Vec<ObjectInstance>);
+
+impl JkVec {
+    /// Create a new, empty vector
+    pub fn new() -> JkVec {
+        JkVec::default()
+    }
+
+    /// Get a reference to the elements contained in the vector
+    pub fn elements(&self) -> &Vec<ObjectInstance> {
+        &self.0
+    }
+}
+
+impl From<Vec<ObjectInstance>> for JkVec {
+    fn from(elements: Vec<ObjectInstance>) -> JkVec {
+        JkVec(elements)
+    }
+}
+
+impl ToObjectInstance for JkVec {
+    fn to_instance(&self) -> ObjectInstance {
+        let fields: Vec<(Name, ObjectInstance)> = self
+            .0
+            .iter()
+            .enumerate()
+            .map(|(idx, elt)| (idx.to_string(), elt.clone()))
+            .collect();
+
+        ObjectInstance::from_fields(CheckedType::Resolved(TypeId::from(VEC_TYPE)), fields)
+    }
+}
+
+impl FromObjectInstance for JkVec {
+    fn from_instance(i: &ObjectInstance) -> Self {
+        let mut elements: Vec<(usize, ObjectInstance)> = i
+            .fields()
+            .into_iter()
+            .flatten()
+            .map(|(idx, field)| {
+                // The fields of a vector instance are always named after their index
+                (idx.parse().unwrap(), field.instance().clone())
+            })
+            .collect();
+
+        elements.sort_by_key(|(idx, _)| *idx);
+
+        JkVec(elements.into_iter().map(|(_, elt)| elt).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::{JkInt, JkString};
+
+    #[test]
+    fn t_vec_to_instance_and_back() {
+        let vec = JkVec::from(vec![
+            JkInt::from(1).to_instance(),
+            JkInt::from(2).to_instance(),
+            JkInt::from(3).to_instance(),
+        ]);
+        let instance = vec.to_instance();
+
+        assert_eq!(instance.size(), 3 * JkInt::from(0).to_instance().size());
+        assert_eq!(JkVec::from_instance(&instance), vec);
+    }
+
+    #[test]
+    fn t_empty_vec() {
+        let instance = JkVec::new().to_instance();
+
+        assert_eq!(instance.size(), 0);
+        assert!(JkVec::from_instance(&instance).elements().is_empty());
+    }
+
+    #[test]
+    fn t_vec_keeps_order() {
+        let elements: Vec<ObjectInstance> = (0..15)
+            .map(|i| JkString::from(i.to_string()).to_instance())
+            .collect();
+        let instance = JkVec::from(elements.clone()).to_instance();
+
+        assert_eq!(JkVec::from_instance(&instance).elements(), &elements);
+    }
+}
@@ -1,14 +1,26 @@
 //! A `Value` is a number instance in jinko. It refers to arithmetic primtive types, such
 //! as Ints and Floats
 
-use crate::error::Error;
+use crate::error::{ErrKind, Error};
 use crate::instance::ObjectInstance;
 use crate::instruction::Instruction;
 use crate::instruction::Operator;
 
+mod jk_command_result;
 mod jk_constant;
+mod jk_func;
+mod jk_map;
+mod jk_option;
+mod jk_vec;
+mod primitive;
 
+pub use jk_command_result::{JkCommandResult, COMMAND_RESULT_TYPE};
 pub use jk_constant::JkConstant;
+pub use jk_func::JkFunc;
+pub use jk_map::{JkMap, MAP_TYPE};
+pub use jk_option::{JkOption, OPTION_TYPE};
+pub use jk_vec::{JkVec, VEC_TYPE};
+pub use primitive::Primitive;
 
 pub type JkBool = JkConstant<bool>;
 pub type JkInt = JkConstant<i64>;
@@ -20,14 +32,18 @@ pub type JkString = JkConstant<String>;
 pub trait Value: Instruction {
     /// Call this function when an operation is not implemented, rather than implementing
     /// your own. This will format the error nicely.
-    fn no_op(&self, _other: &Self, _op: Operator) -> Result<ObjectInstance, Error> {
-        unreachable!("NOP") // FIXME
+    fn no_op(&self, other: &Self, op: Operator) -> Result<ObjectInstance, Error> {
+        Err(Error::new(ErrKind::Context).with_msg(format!(
+            "invalid operation: {} {} {}",
+            self.print(),
+            op.as_str(),
+            other.print()
+        )))
     }
 
     /// Realize any operation implemented by the type, and return a new instance
-    /// of a valid type. You cannot add multiple types together, except in one case:
-    /// Adding a floating point number and an integer together. Doing that will
-    /// return a new JkFloat.
+    /// of a valid type. Operations on values of different types, such as adding an
+    /// integer to a floating point number, need to be promoted to a common type first.
     fn do_op(&self, other: &Self, op: Operator) -> Result<ObjectInstance, Error> {
         self.no_op(other, op)
     }
@@ -0,0 +1,204 @@
+//! Primitive values which can be operated on without being boxed into an
+//! [`ObjectInstance`]. Binary operations evaluate their operands as primitives whenever
+//! possible, so that only the final result of an arithmetic expression gets converted
+//! to an instance: Evaluating `a * 2 + b * 3` creates a single instance instead of
+//! three.
+
+use crate::error::{ErrKind, Error};
+use crate::instance::{FromObjectInstance, ObjectInstance, ToObjectInstance};
+use crate::instruction::{Instruction, Operator};
+use crate::typechecker::CheckedType;
+use crate::value::{JkBool, JkFloat, JkInt};
+
+/// An unboxed `int`, `float` or `bool`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Primitive {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+}
+
+impl Primitive {
+    /// Get the primitive value contained in an instance, if it is an `int`, a `float`
+    /// or a `bool`
+    pub fn from_instance(instance: &ObjectInstance) -> Option<Primitive> {
+        let ty = match instance.ty() {
+            CheckedType::Resolved(ty) => ty.id(),
+            _ => return None,
+        };
+
+        match ty {
+            "int" => Some(Primitive::Int(JkInt::from_instance(instance).0)),
+            "float" => Some(Primitive::Float(JkFloat::from_instance(instance).0)),
+            "bool" => Some(Primitive::Bool(JkBool::from_instance(instance).0)),
+            _ => None,
+        }
+    }
+
+    /// Get the primitive value of a constant instruction, such as `15` or `true`,
+    /// without executing it
+    pub fn from_constant(instruction: &dyn Instruction) -> Option<Primitive> {
+        if let Some(int) = instruction.downcast_ref::<JkInt>() {
+            Some(Primitive::Int(int.0))
+        } else if let Some(float) = instruction.downcast_ref::<JkFloat>() {
+            Some(Primitive::Float(float.0))
+        } else {
+            instruction
+                .downcast_ref::<JkBool>()
+                .map(|boolean| Primitive::Bool(boolean.0))
+        }
+    }
+
+    /// Apply an operator to two primitive values. Integers are promoted to floats when
+    /// operated on with a float
+    pub fn apply(self, other: Primitive, op: Operator) -> Result<Primitive, Error> {
+        match (self, other) {
+            (Primitive::Int(lhs), Primitive::Int(rhs)) => int_op(lhs, rhs, op),
+            (Primitive::Float(lhs), Primitive::Float(rhs)) => float_op(lhs, rhs, op),
+            (Primitive::Int(lhs), Primitive::Float(rhs)) => float_op(lhs as f64, rhs, op),
+            (Primitive::Float(lhs), Primitive::Int(rhs)) => float_op(lhs, rhs as f64, op),
+            (Primitive::Bool(lhs), Primitive::Bool(rhs)) => match op {
+                Operator::Equals => Ok(Primitive::Bool(lhs == rhs)),
+                Operator::NotEquals => Ok(Primitive::Bool(lhs != rhs)),
+                _ => Err(self.no_op(other, op)),
+            },
+            _ => Err(self.no_op(other, op)),
+        }
+    }
+
+    /// Source representation of the value, used in error messages
+    fn print(self) -> String {
+        match self {
+            Primitive::Int(value) => JkInt::from(value).print(),
+            Primitive::Float(value) => JkFloat::from(value).print(),
+            Primitive::Bool(value) => JkBool::from(value).print(),
+        }
+    }
+
+    fn no_op(self, other: Primitive, op: Operator) -> Error {
+        Error::new(ErrKind::Context).with_msg(format!(
+            "invalid operation: {} {} {}",
+            self.print(),
+            op.as_str(),
+            other.print()
+        ))
+    }
+}
+
+impl ToObjectInstance for Primitive {
+    fn to_instance(&self) -> ObjectInstance {
+        match *self {
+            Primitive::Int(value) => JkInt::from(value).to_instance(),
+            Primitive::Float(value) => JkFloat::from(value).to_instance(),
+            Primitive::Bool(value) => JkBool::from(value).to_instance(),
+        }
+    }
+}
+
+/// Realize an operation on integers, erroring out on overflows, divisions by zero,
+/// negative exponents or shifts of 64 bits or more. Bits shifted out of an integer are
+/// lost and do not count as an overflow, and right shifts keep the sign of the integer
+fn int_op(lhs: i64, rhs: i64, op: Operator) -> Result<Primitive, Error> {
+    let checked: fn(i64, i64) -> Option<i64> = match op {
+        Operator::Add => i64::checked_add,
+        Operator::Sub => i64::checked_sub,
+        Operator::Mul => i64::checked_mul,
+        Operator::Div => i64::checked_div,
+        Operator::Rem => i64::checked_rem,
+        Operator::Pow => |lhs, rhs| u32::try_from(rhs).ok().and_then(|rhs| lhs.checked_pow(rhs)),
+        Operator::Shl => |lhs, rhs| u32::try_from(rhs).ok().and_then(|rhs| lhs.checked_shl(rhs)),
+        Operator::Shr => |lhs, rhs| u32::try_from(rhs).ok().and_then(|rhs| lhs.checked_shr(rhs)),
+        Operator::BitAnd => return Ok(Primitive::Int(lhs & rhs)),
+        Operator::BitOr => return Ok(Primitive::Int(lhs | rhs)),
+        Operator::BitXor => return Ok(Primitive::Int(lhs ^ rhs)),
+        Operator::Lt => return Ok(Primitive::Bool(lhs < rhs)),
+        Operator::Gt => return Ok(Primitive::Bool(lhs > rhs)),
+        Operator::LtEq => return Ok(Primitive::Bool(lhs <= rhs)),
+        Operator::GtEq => return Ok(Primitive::Bool(lhs >= rhs)),
+        Operator::Equals => return Ok(Primitive::Bool(lhs == rhs)),
+        Operator::NotEquals => return Ok(Primitive::Bool(lhs != rhs)),
+        _ => return Err(Primitive::Int(lhs).no_op(Primitive::Int(rhs), op)),
+    };
+
+    checked(lhs, rhs).map(Primitive::Int).ok_or_else(|| {
+        let reason = match (op, rhs) {
+            (Operator::Div | Operator::Rem, 0) => "division by zero",
+            (Operator::Pow, exp) if exp < 0 => "negative exponent",
+            (Operator::Shl | Operator::Shr, _) => "invalid shift amount",
+            _ => "integer overflow",
+        };
+
+        Error::new(ErrKind::Context).with_msg(format!(
+            "{}: {} {} {}",
+            reason,
+            lhs,
+            op.as_str(),
+            rhs
+        ))
+    })
+}
+
+/// Realize an operation on floats. Floating point operations follow IEEE 754: Dividing
+/// by zero produces an infinite value or NaN rather than an error
+fn float_op(lhs: f64, rhs: f64, op: Operator) -> Result<Primitive, Error> {
+    match op {
+        Operator::Add => Ok(Primitive::Float(lhs + rhs)),
+        Operator::Sub => Ok(Primitive::Float(lhs - rhs)),
+        Operator::Mul => Ok(Primitive::Float(lhs * rhs)),
+        Operator::Div => Ok(Primitive::Float(lhs / rhs)),
+        Operator::Rem => Ok(Primitive::Float(lhs % rhs)),
+        Operator::Pow => Ok(Primitive::Float(lhs.powf(rhs))),
+        Operator::Lt => Ok(Primitive::Bool(lhs < rhs)),
+        Operator::Gt => Ok(Primitive::Bool(lhs > rhs)),
+        Operator::LtEq => Ok(Primitive::Bool(lhs <= rhs)),
+        Operator::GtEq => Ok(Primitive::Bool(lhs >= rhs)),
+        Operator::Equals => Ok(Primitive::Bool(lhs == rhs)),
+        Operator::NotEquals => Ok(Primitive::Bool(lhs != rhs)),
+        _ => Err(Primitive::Float(lhs).no_op(Primitive::Float(rhs), op)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apply_promotes_ints() {
+        let res = Primitive::Int(1).apply(Primitive::Float(2.5), Operator::Add);
+
+        assert_eq!(res.unwrap(), Primitive::Float(3.5));
+    }
+
+    #[test]
+    fn apply_comparisons() {
+        let res = Primitive::Int(1).apply(Primitive::Int(2), Operator::Lt);
+
+        assert_eq!(res.unwrap(), Primitive::Bool(true));
+    }
+
+    #[test]
+    fn apply_invalid() {
+        assert!(Primitive::Bool(true)
+            .apply(Primitive::Bool(false), Operator::Add)
+            .is_err());
+        assert!(Primitive::Float(1.0)
+            .apply(Primitive::Float(1.0), Operator::BitAnd)
+            .is_err());
+        assert!(Primitive::Int(i64::MAX)
+            .apply(Primitive::Int(1), Operator::Add)
+            .is_err());
+    }
+
+    #[test]
+    fn round_trip() {
+        let values = [
+            Primitive::Int(-15),
+            Primitive::Float(1.5),
+            Primitive::Bool(true),
+        ];
+
+        for value in values {
+            assert_eq!(Primitive::from_instance(&value.to_instance()), Some(value));
+        }
+    }
+}
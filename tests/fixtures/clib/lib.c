@@ -8,7 +8,9 @@
 // Symbol<fn(i32) -> i32>, or Symbol<i32> for a value, but we cannot change the
 // type dynamically (at least not simply, and not without reflection).
 
+#include <stdbool.h>
 #include <stdio.h>
+#include <stddef.h>
 #include <string.h>
 
 int square(int a) { return a * a; }
@@ -24,3 +26,17 @@ void receive_const_char_ptr(const char *ptr) {
 }
 
 const char *return_const_char_ptr(void) { return "C library returned!"; }
+
+double half(double value) { return value / 2.0; }
+
+char next_char(char c) { return c + 1; }
+
+bool is_positive(long value) { return value > 0; }
+
+static long stored_value = 42;
+
+long *stored_value_ptr(void) { return &stored_value; }
+
+long deref(long *ptr) { return *ptr; }
+
+void *null_ptr(void) { return NULL; }
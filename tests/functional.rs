@@ -0,0 +1,186 @@
+//! Golden tests of the interpreter. Each `.jk` program in `tests/functional` is executed
+//! in deterministic mode, and its output is compared to the expectation files sitting
+//! next to it:
+//!
+//! - `<name>.stdout`: Expected standard output, empty if the file does not exist
+//! - `<name>.stderr`: Expected standard error, empty if the file does not exist
+//! - `<name>.exit_code`: Expected exit code, 0 if the file does not exist
+//!
+//! Running the tests with `JINKO_BLESS=1` writes the current output of the programs to
+//! their expectation files instead of comparing them.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// Directory containing the programs to execute, relative to the root of the crate
+const FUNCTIONAL_DIR: &str = "tests/functional";
+
+/// Environment variable which updates the expectation files when set
+const BLESS_VAR: &str = "JINKO_BLESS";
+
+/// Output of a program, as stored in its expectation files
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Expectation {
+    stdout: String,
+    stderr: String,
+    exit_code: i32,
+}
+
+impl Expectation {
+    fn from_output(output: Output) -> Expectation {
+        Expectation {
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+            // Programs killed by a signal do not have an exit code
+            exit_code: output.status.code().unwrap_or(-1),
+        }
+    }
+
+    /// Read the expectation files of a program. Missing files stand for an empty
+    /// output and a successful exit code
+    fn read(program: &Path) -> io::Result<Expectation> {
+        let read = |extension| match fs::read_to_string(program.with_extension(extension)) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(String::new()),
+            res => res,
+        };
+
+        let exit_code = read("exit_code")?;
+        let exit_code = match exit_code.trim() {
+            "" => 0,
+            code => code.parse().map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid exit code `{}`", code),
+                )
+            })?,
+        };
+
+        Ok(Expectation {
+            stdout: read("stdout")?,
+            stderr: read("stderr")?,
+            exit_code,
+        })
+    }
+
+    /// Write the expectation files of a program. Files which would only contain the
+    /// default expectation are removed instead
+    fn write(&self, program: &Path) -> io::Result<()> {
+        let write = |extension, content: String| {
+            let path = program.with_extension(extension);
+            match content.is_empty() {
+                true if path.exists() => fs::remove_file(path),
+                true => Ok(()),
+                false => fs::write(path, content),
+            }
+        };
+
+        let exit_code = match self.exit_code {
+            0 => String::new(),
+            code => format!("{}\n", code),
+        };
+
+        write("stdout", self.stdout.clone())?;
+        write("stderr", self.stderr.clone())?;
+        write("exit_code", exit_code)
+    }
+
+    /// Describe the differences between the expected output and the actual one
+    fn diff(&self, actual: &Expectation) -> String {
+        let mut diff = String::new();
+
+        if self.exit_code != actual.exit_code {
+            diff.push_str(&format!(
+                "exit code: expected {}, got {}\n",
+                self.exit_code, actual.exit_code
+            ));
+        }
+
+        for (stream, expected, actual) in [
+            ("stdout", &self.stdout, &actual.stdout),
+            ("stderr", &self.stderr, &actual.stderr),
+        ] {
+            if expected != actual {
+                diff.push_str(&format!(
+                    "{}: expected\n{}\n{}: got\n{}\n",
+                    stream, expected, stream, actual
+                ));
+            }
+        }
+
+        diff
+    }
+}
+
+/// Get the programs to execute in a directory and its subdirectories, sorted so that
+/// failures are always reported in the same order
+fn programs(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut found = Vec::new();
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            found.extend(programs(&path)?);
+        } else if path.extension().is_some_and(|ext| ext == "jk") {
+            found.push(path);
+        }
+    }
+
+    found.sort();
+
+    Ok(found)
+}
+
+fn run(program: &Path) -> io::Result<Expectation> {
+    let output = Command::new(env!("CARGO_BIN_EXE_jinko"))
+        .arg("--deterministic")
+        .arg(program)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()?;
+
+    Ok(Expectation::from_output(output))
+}
+
+#[test]
+fn functional() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let bless = env::var_os(BLESS_VAR).is_some();
+
+    let programs = programs(&root.join(FUNCTIONAL_DIR)).unwrap();
+    assert!(!programs.is_empty(), "no program in {}", FUNCTIONAL_DIR);
+
+    let mut failures = Vec::new();
+
+    for program in programs {
+        // Programs are given relative to the root of the crate, so that the paths in
+        // their error messages do not depend on where the crate is located
+        let relative = program.strip_prefix(root).unwrap();
+        let actual = run(relative).unwrap();
+
+        if bless {
+            actual.write(&program).unwrap();
+            continue;
+        }
+
+        let expected = Expectation::read(&program).unwrap();
+        if expected != actual {
+            failures.push(format!(
+                "{}\n{}",
+                relative.display(),
+                expected.diff(&actual)
+            ));
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{} program(s) did not produce their expected output. Run the tests with {}=1 to \
+         update the expectations\n\n{}",
+        failures.len(),
+        BLESS_VAR,
+        failures.join("\n")
+    );
+}